@@ -2,7 +2,7 @@ use criterion::{black_box, Criterion};
 use criterion::criterion_group;
 use criterion::criterion_main;
 
-use plonky::{Field, TweedledeeBase, hash_base_field_to_curve, blake_hash_base_field_to_curve, Tweedledee};
+use plonky::{Field, TweedledeeBase, hash_base_field_to_curve, blake_hash_base_field_to_curve, poseidon_hash_base_field_to_curve, Tweedledee};
 
 fn criterion_benchmark(c: &mut Criterion) {
     let x = TweedledeeBase::rand();
@@ -11,6 +11,10 @@ fn criterion_benchmark(c: &mut Criterion) {
         hash_base_field_to_curve::<Tweedledee>(black_box(x), 128)
     }));
 
+    c.bench_function("Hash using Poseidon", move |b| b.iter(|| {
+        poseidon_hash_base_field_to_curve::<Tweedledee>(black_box(x), 128)
+    }));
+
     c.bench_function("Hash using Blake", move |b| b.iter(|| {
         blake_hash_base_field_to_curve::<Tweedledee>(black_box(x))
     }));
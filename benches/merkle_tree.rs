@@ -0,0 +1,30 @@
+use criterion::{black_box, Criterion};
+use criterion::criterion_group;
+use criterion::criterion_main;
+
+use plonky::{Field, MerkleTree, PoseidonHasher, RescueHasher, TweedledeeBase, merkle_compress_with_hasher};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let leaves = (0..1 << 10).map(|_| TweedledeeBase::rand()).collect::<Vec<_>>();
+
+    let l = leaves.clone();
+    c.bench_function("Merkle tree of 1024 leaves using Rescue", move |b| b.iter(|| {
+        MerkleTree::<TweedledeeBase, RescueHasher>::new_with_hasher(black_box(l.clone()), 128)
+    }));
+
+    let l = leaves.clone();
+    c.bench_function("Merkle tree of 1024 leaves using Poseidon", move |b| b.iter(|| {
+        MerkleTree::<TweedledeeBase, PoseidonHasher>::new_with_hasher(black_box(l.clone()), 128)
+    }));
+
+    // For comparison, the first layer of the Poseidon tree, hashing one pair at a time.
+    let l = leaves;
+    c.bench_function("Unbatched first layer of 1024 leaves using Poseidon", move |b| b.iter(|| {
+        black_box(&l).chunks(2)
+            .map(|pair| merkle_compress_with_hasher::<TweedledeeBase, PoseidonHasher>(pair[0], pair[1], 128))
+            .collect::<Vec<_>>()
+    }));
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+/*
+ * A C interface for proving and verifying with plonky. See ffi/src/lib.rs for details.
+ *
+ * Prover keys, verification keys and witnesses are CBOR-serialized by plonky's Rust API. Proofs
+ * use the format of `Proof::to_bytes`, and field elements are 32-byte little-endian canonical
+ * encodings.
+ *
+ * Failing functions return a null handle, an empty buffer or -1, and record a message which
+ * plonky_last_error returns. Handles and buffers must be released with the matching _free
+ * function.
+ */
+
+#ifndef PLONKY_H
+#define PLONKY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct PlonkyProverKey PlonkyProverKey;
+typedef struct PlonkyVerificationKey PlonkyVerificationKey;
+typedef struct PlonkyProof PlonkyProof;
+
+/* Bytes allocated by plonky. An empty buffer has a null data pointer. */
+typedef struct PlonkyBuffer {
+    uint8_t *data;
+    size_t len;
+} PlonkyBuffer;
+
+/* The message of the last error on this thread, or null. Valid until the next call into plonky. */
+const char *plonky_last_error(void);
+
+void plonky_buffer_free(PlonkyBuffer buffer);
+
+PlonkyProverKey *plonky_prover_key_from_bytes(const uint8_t *data, size_t len);
+PlonkyBuffer plonky_prover_key_to_bytes(const PlonkyProverKey *prover_key);
+void plonky_prover_key_free(PlonkyProverKey *prover_key);
+
+PlonkyVerificationKey *plonky_verification_key_from_bytes(const uint8_t *data, size_t len);
+PlonkyBuffer plonky_verification_key_to_bytes(const PlonkyVerificationKey *vk);
+void plonky_verification_key_free(PlonkyVerificationKey *vk);
+
+PlonkyProof *plonky_proof_from_bytes(const uint8_t *data, size_t len);
+PlonkyBuffer plonky_proof_to_bytes(const PlonkyProof *proof);
+void plonky_proof_free(PlonkyProof *proof);
+
+/* Proves with a CBOR-serialized witness. */
+PlonkyProof *plonky_prove(const PlonkyProverKey *prover_key, const uint8_t *witness,
+                          size_t witness_len);
+
+/*
+ * Verifies a proof against num_public_inputs consecutive 32-byte field elements. Returns 1 if the
+ * proof is valid, 0 if it's invalid, and -1 if the arguments are malformed.
+ */
+int32_t plonky_verify(const PlonkyVerificationKey *vk, const uint8_t *public_inputs,
+                      size_t num_public_inputs, const PlonkyProof *proof);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PLONKY_H */
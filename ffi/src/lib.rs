@@ -0,0 +1,341 @@
+//! A C interface for plonky, so that applications in other languages can embed the prover. See
+//! `include/plonky.h` for the declarations.
+//!
+//! Circuits can't be passed across the C boundary, since their witness generators are Rust code.
+//! Instead, a circuit's prover key, verification key and witnesses are built in Rust and serialized
+//! with CBOR, and this interface loads them, proves and verifies. Proofs use the format of
+//! `Proof::to_bytes`, and field elements are 32-byte little-endian canonical encodings. Circuits
+//! are over Tweedledee, with Tweedledum as the inner curve.
+//!
+//! Keys and proofs are passed as opaque handles, which must be released with the matching `_free`
+//! function. Functions which fail return a null handle, an empty buffer, or a negative status, and
+//! record a message which `plonky_last_error` returns.
+//!
+//! The `python` feature adds Python bindings with the same formats; see the `python` module.
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use anyhow::{anyhow, ensure, Result};
+use plonky::{verify, Curve, Field, Proof, ProverKey, Tweedledee, Tweedledum, VerificationKey, Witness};
+
+#[cfg(feature = "python")]
+mod python;
+
+type F = <Tweedledee as Curve>::ScalarField;
+
+pub struct PlonkyProverKey(ProverKey<Tweedledee>);
+
+pub struct PlonkyVerificationKey(VerificationKey<Tweedledee>);
+
+pub struct PlonkyProof(Proof<Tweedledee>);
+
+/// Bytes allocated by plonky, which must be released with `plonky_buffer_free`. An empty buffer
+/// has a null `data` pointer.
+#[repr(C)]
+pub struct PlonkyBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl PlonkyBuffer {
+    const EMPTY: PlonkyBuffer = PlonkyBuffer {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+
+    fn new(bytes: Vec<u8>) -> Self {
+        if bytes.is_empty() {
+            return Self::EMPTY;
+        }
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        PlonkyBuffer { data, len }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Interior nul bytes can't be represented in a C string, so we drop them.
+    let message = CString::new(message.replace('\0', "")).expect("Nul bytes were removed");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Runs `f`, recording any error or panic and returning `default` in its place. Panics must not
+/// unwind into C.
+fn run<T>(default: T, f: impl FnOnce() -> Result<T>) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            default
+        }
+        Err(_) => {
+            set_last_error("plonky panicked".to_string());
+            default
+        }
+    }
+}
+
+/// Borrows `len` bytes, which may be null if `len` is zero.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    ensure!(!data.is_null(), "Null pointer to {} bytes", len);
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn handle<'a, T>(handle: *const T) -> Result<&'a T> {
+    handle.as_ref().ok_or_else(|| anyhow!("Null handle"))
+}
+
+fn into_handle<T>(value: T) -> *mut T {
+    Box::into_raw(Box::new(value))
+}
+
+unsafe fn free_handle<T>(handle: *mut T) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Returns the message of the last error on this thread, or null if there was none. The string is
+/// valid until the next call into plonky on this thread.
+#[no_mangle]
+pub extern "C" fn plonky_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// # Safety
+/// `buffer` must have been returned by plonky, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_buffer_free(buffer: PlonkyBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len)));
+    }
+}
+
+/// Loads a CBOR-serialized prover key.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_prover_key_from_bytes(
+    data: *const u8,
+    len: usize,
+) -> *mut PlonkyProverKey {
+    run(ptr::null_mut(), || {
+        let prover_key = serde_cbor::from_slice(bytes(data, len)?)?;
+        Ok(into_handle(PlonkyProverKey(prover_key)))
+    })
+}
+
+/// # Safety
+/// `prover_key` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_prover_key_to_bytes(
+    prover_key: *const PlonkyProverKey,
+) -> PlonkyBuffer {
+    run(PlonkyBuffer::EMPTY, || {
+        Ok(PlonkyBuffer::new(serde_cbor::to_vec(&handle(prover_key)?.0)?))
+    })
+}
+
+/// # Safety
+/// `prover_key` must be null or a live handle, which is invalid afterward.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_prover_key_free(prover_key: *mut PlonkyProverKey) {
+    free_handle(prover_key)
+}
+
+/// Loads a CBOR-serialized verification key.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_verification_key_from_bytes(
+    data: *const u8,
+    len: usize,
+) -> *mut PlonkyVerificationKey {
+    run(ptr::null_mut(), || {
+        let vk = serde_cbor::from_slice(bytes(data, len)?)?;
+        Ok(into_handle(PlonkyVerificationKey(vk)))
+    })
+}
+
+/// # Safety
+/// `vk` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_verification_key_to_bytes(
+    vk: *const PlonkyVerificationKey,
+) -> PlonkyBuffer {
+    run(PlonkyBuffer::EMPTY, || {
+        Ok(PlonkyBuffer::new(serde_cbor::to_vec(&handle(vk)?.0)?))
+    })
+}
+
+/// # Safety
+/// `vk` must be null or a live handle, which is invalid afterward.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_verification_key_free(vk: *mut PlonkyVerificationKey) {
+    free_handle(vk)
+}
+
+/// Loads a proof encoded with `Proof::to_bytes`.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_proof_from_bytes(data: *const u8, len: usize) -> *mut PlonkyProof {
+    run(ptr::null_mut(), || {
+        let proof = Proof::from_bytes(bytes(data, len)?)?;
+        Ok(into_handle(PlonkyProof(proof)))
+    })
+}
+
+/// # Safety
+/// `proof` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_proof_to_bytes(proof: *const PlonkyProof) -> PlonkyBuffer {
+    run(PlonkyBuffer::EMPTY, || {
+        Ok(PlonkyBuffer::new(handle(proof)?.0.to_bytes()))
+    })
+}
+
+/// # Safety
+/// `proof` must be null or a live handle, which is invalid afterward.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_proof_free(proof: *mut PlonkyProof) {
+    free_handle(proof)
+}
+
+/// Proves with a CBOR-serialized `Witness`, returning a new proof handle.
+///
+/// # Safety
+/// `prover_key` must be a live handle, and `witness` must point to `witness_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_prove(
+    prover_key: *const PlonkyProverKey,
+    witness: *const u8,
+    witness_len: usize,
+) -> *mut PlonkyProof {
+    run(ptr::null_mut(), || {
+        let prover_key = &handle(prover_key)?.0;
+        let witness: Witness<F> = serde_cbor::from_slice(bytes(witness, witness_len)?)?;
+        let proof = prover_key.generate_proof::<Tweedledum>(&witness, &[])?;
+        Ok(into_handle(PlonkyProof(proof)))
+    })
+}
+
+/// Verifies a proof against `num_public_inputs` public inputs, stored consecutively as 32-byte
+/// field elements. Returns 1 if the proof is valid, 0 if it's invalid, and -1 if the arguments are
+/// malformed. The reason for an invalid proof is recorded as the last error.
+///
+/// # Safety
+/// `vk` and `proof` must be live handles, and `public_inputs` must point to
+/// `32 * num_public_inputs` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonky_verify(
+    vk: *const PlonkyVerificationKey,
+    public_inputs: *const u8,
+    num_public_inputs: usize,
+    proof: *const PlonkyProof,
+) -> i32 {
+    run(-1, || {
+        let vk = &handle(vk)?.0;
+        let proof = &handle(proof)?.0;
+        let public_inputs = bytes(public_inputs, num_public_inputs * F::BYTES)?
+            .chunks(F::BYTES)
+            .map(|chunk| F::from_canonical_u8_vec(chunk.to_vec()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(match verify::<Tweedledee, Tweedledum>(vk, &public_inputs, proof) {
+            Ok(()) => 1,
+            Err(e) => {
+                set_last_error(e.to_string());
+                0
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use plonky::{CircuitBuilder, Field, PartialWitness, Tweedledee};
+
+    use crate::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(plonky_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_prove_and_verify() -> Result<()> {
+        // Proves knowledge of a square root of a public input.
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let square = builder.add_public_input();
+        let root = builder.add_virtual_target();
+        let computed_square = builder.mul(root, root);
+        builder.copy(computed_square, square);
+        let circuit = builder.build();
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(root, F::from_canonical_u64(7));
+        partial_witness.set_target(square, F::from_canonical_u64(49));
+        let witness = circuit.generate_witness(partial_witness)?;
+
+        let pk_bytes = serde_cbor::to_vec(&circuit.prover_key)?;
+        let vk_bytes = serde_cbor::to_vec(&circuit.to_vk())?;
+        let witness_bytes = serde_cbor::to_vec(&witness)?;
+        let public_input = F::from_canonical_u64(49).to_canonical_u8_vec();
+        let wrong_public_input = F::from_canonical_u64(50).to_canonical_u8_vec();
+
+        unsafe {
+            let pk = plonky_prover_key_from_bytes(pk_bytes.as_ptr(), pk_bytes.len());
+            let vk = plonky_verification_key_from_bytes(vk_bytes.as_ptr(), vk_bytes.len());
+            assert!(!pk.is_null() && !vk.is_null());
+            let proof = plonky_prove(pk, witness_bytes.as_ptr(), witness_bytes.len());
+            assert!(!proof.is_null());
+            assert_eq!(plonky_verify(vk, public_input.as_ptr(), 1, proof), 1);
+            assert_eq!(plonky_verify(vk, wrong_public_input.as_ptr(), 1, proof), 0);
+
+            // Round trip the proof through its encoding.
+            let proof_bytes = plonky_proof_to_bytes(proof);
+            let decoded = plonky_proof_from_bytes(proof_bytes.data, proof_bytes.len);
+            assert_eq!(plonky_verify(vk, public_input.as_ptr(), 1, decoded), 1);
+
+            let vk_bytes_again = plonky_verification_key_to_bytes(vk);
+            assert_eq!(slice::from_raw_parts(vk_bytes_again.data, vk_bytes_again.len), &vk_bytes[..]);
+
+            plonky_buffer_free(proof_bytes);
+            plonky_buffer_free(vk_bytes_again);
+            plonky_proof_free(decoded);
+            plonky_proof_free(proof);
+            plonky_verification_key_free(vk);
+            plonky_prover_key_free(pk);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        let garbage = [1u8, 2, 3];
+        unsafe {
+            assert!(plonky_proof_from_bytes(garbage.as_ptr(), garbage.len()).is_null());
+            assert!(!last_error().is_empty());
+            assert!(plonky_verification_key_from_bytes(ptr::null(), 1).is_null());
+            assert_eq!(last_error(), "Null pointer to 1 bytes");
+            assert_eq!(plonky_verify(ptr::null(), ptr::null(), 0, ptr::null()), -1);
+            assert_eq!(last_error(), "Null handle");
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! Python bindings, built with the `python` feature, e.g. using
+//! `maturin build -m ffi/Cargo.toml --features python,pyo3/extension-module`.
+//!
+//! Circuits can be built with a small set of arithmetic operations, or loaded as serialized keys,
+//! as in the C interface. Field elements are Python ints, and keys, witnesses and proofs are
+//! `bytes` in the same formats as in the C interface.
+
+// pyo3's `#[pymethods]` expansion converts each method's `PyErr` into a `PyErr`.
+#![allow(clippy::useless_conversion)]
+
+use anyhow::Result;
+use plonky::{verify, Curve, Field, PartialWitness, Proof, Tweedledee, Tweedledum};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyLong};
+
+type F = <Tweedledee as Curve>::ScalarField;
+
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn to_py_result<T>(result: Result<T>) -> PyResult<T> {
+    result.map_err(to_py_err)
+}
+
+/// Converts a Python int, which must be a canonical field element, to a field element.
+fn field_from_int(value: &Bound<'_, PyLong>) -> PyResult<F> {
+    let bytes: Vec<u8> = value
+        .call_method1("to_bytes", (F::BYTES, "little"))?
+        .extract()?;
+    to_py_result(F::from_canonical_u8_vec(bytes))
+}
+
+fn field_to_int(py: Python<'_>, value: F) -> PyResult<PyObject> {
+    let bytes = PyBytes::new_bound(py, &value.to_canonical_u8_vec());
+    Ok(py
+        .get_type_bound::<PyLong>()
+        .call_method1("from_bytes", (bytes, "little"))?
+        .unbind())
+}
+
+/// A wire, or a value which the circuit routes to some wire.
+#[pyclass(frozen)]
+#[derive(Clone, Copy)]
+pub struct Target(plonky::Target<F>);
+
+#[pyclass(unsendable)]
+pub struct CircuitBuilder(Option<plonky::CircuitBuilder<Tweedledee>>);
+
+impl CircuitBuilder {
+    fn builder(&mut self) -> PyResult<&mut plonky::CircuitBuilder<Tweedledee>> {
+        self.0
+            .as_mut()
+            .ok_or_else(|| PyValueError::new_err("The circuit was already built"))
+    }
+}
+
+#[pymethods]
+impl CircuitBuilder {
+    #[new]
+    #[pyo3(signature = (security_bits = 128))]
+    fn new(security_bits: usize) -> Self {
+        CircuitBuilder(Some(plonky::CircuitBuilder::new(security_bits)))
+    }
+
+    /// Adds a public input. Its value can be set, or computed from values it's copied from.
+    fn add_public_input(&mut self) -> PyResult<Target> {
+        let builder = self.builder()?;
+        // Generators can't depend on public input targets directly, so the public input is copied
+        // from a virtual target.
+        let target = builder.add_virtual_target();
+        let public_input = builder.add_public_input();
+        builder.copy(target, public_input);
+        Ok(Target(target))
+    }
+
+    fn add_virtual_target(&mut self) -> PyResult<Target> {
+        Ok(Target(self.builder()?.add_virtual_target()))
+    }
+
+    fn constant(&mut self, value: &Bound<'_, PyLong>) -> PyResult<Target> {
+        let value = field_from_int(value)?;
+        Ok(Target(self.builder()?.constant_wire(value)))
+    }
+
+    fn add(&mut self, x: Target, y: Target) -> PyResult<Target> {
+        Ok(Target(self.builder()?.add(x.0, y.0)))
+    }
+
+    fn sub(&mut self, x: Target, y: Target) -> PyResult<Target> {
+        Ok(Target(self.builder()?.sub(x.0, y.0)))
+    }
+
+    fn mul(&mut self, x: Target, y: Target) -> PyResult<Target> {
+        Ok(Target(self.builder()?.mul(x.0, y.0)))
+    }
+
+    /// Constrains two targets to be equal.
+    fn copy(&mut self, x: Target, y: Target) -> PyResult<()> {
+        self.builder()?.copy(x.0, y.0);
+        Ok(())
+    }
+
+    fn assert_zero(&mut self, x: Target) -> PyResult<()> {
+        self.builder()?.assert_zero(x.0);
+        Ok(())
+    }
+
+    /// Builds the circuit. The builder can't be used afterward.
+    fn build(&mut self) -> PyResult<Circuit> {
+        self.builder()?;
+        let builder = self.0.take().expect("Checked above");
+        Ok(Circuit(builder.build()))
+    }
+}
+
+#[pyclass(unsendable)]
+pub struct Circuit(plonky::Circuit<Tweedledee>);
+
+#[pymethods]
+impl Circuit {
+    /// Generates a witness, given the values of some targets as `(target, int)` pairs.
+    fn generate_witness(&self, values: Vec<(Target, Bound<'_, PyLong>)>) -> PyResult<Witness> {
+        let mut partial_witness = PartialWitness::new();
+        for (target, value) in values {
+            to_py_result(partial_witness.try_set_target(target.0, field_from_int(&value)?))?;
+        }
+        Ok(Witness(to_py_result(self.0.generate_witness(partial_witness))?))
+    }
+
+    fn public_inputs(&self, py: Python<'_>, witness: &Witness) -> PyResult<Vec<PyObject>> {
+        self.0
+            .get_public_inputs(&witness.0)
+            .into_iter()
+            .map(|x| field_to_int(py, x))
+            .collect()
+    }
+
+    fn prover_key(&self) -> ProverKey {
+        ProverKey(self.0.prover_key.clone())
+    }
+
+    fn verification_key(&self) -> VerificationKey {
+        VerificationKey(self.0.to_vk())
+    }
+
+    fn prove(&self, py: Python<'_>, witness: &Witness) -> PyResult<PyObject> {
+        prove(py, &self.0.prover_key, witness)
+    }
+}
+
+#[pyclass(frozen)]
+pub struct Witness(plonky::Witness<F>);
+
+#[pymethods]
+impl Witness {
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Ok(Witness(to_py_result(serde_cbor::from_slice(bytes).map_err(Into::into))?))
+    }
+
+    fn to_bytes(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_bytes(py, &self.0)
+    }
+}
+
+#[pyclass(frozen)]
+pub struct ProverKey(plonky::ProverKey<Tweedledee>);
+
+#[pymethods]
+impl ProverKey {
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Ok(ProverKey(to_py_result(serde_cbor::from_slice(bytes).map_err(Into::into))?))
+    }
+
+    fn to_bytes(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_bytes(py, &self.0)
+    }
+
+    fn prove(&self, py: Python<'_>, witness: &Witness) -> PyResult<PyObject> {
+        prove(py, &self.0, witness)
+    }
+}
+
+#[pyclass(frozen)]
+pub struct VerificationKey(plonky::VerificationKey<Tweedledee>);
+
+#[pymethods]
+impl VerificationKey {
+    #[staticmethod]
+    fn from_bytes(bytes: &[u8]) -> PyResult<Self> {
+        Ok(VerificationKey(to_py_result(serde_cbor::from_slice(bytes).map_err(Into::into))?))
+    }
+
+    fn to_bytes(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_bytes(py, &self.0)
+    }
+
+    /// Returns whether the proof is valid for the given public inputs. Malformed proofs are invalid.
+    fn verify(&self, public_inputs: Vec<Bound<'_, PyLong>>, proof: &[u8]) -> PyResult<bool> {
+        let public_inputs = public_inputs
+            .iter()
+            .map(field_from_int)
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(match Proof::from_bytes(proof) {
+            Ok(proof) => verify::<Tweedledee, Tweedledum>(&self.0, &public_inputs, &proof).is_ok(),
+            Err(_) => false,
+        })
+    }
+}
+
+fn prove(
+    py: Python<'_>,
+    prover_key: &plonky::ProverKey<Tweedledee>,
+    witness: &Witness,
+) -> PyResult<PyObject> {
+    let proof = py.allow_threads(|| prover_key.generate_proof::<Tweedledum>(&witness.0, &[]));
+    Ok(PyBytes::new_bound(py, &to_py_result(proof)?.to_bytes()).into())
+}
+
+fn to_bytes<T: serde::Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let bytes = to_py_result(serde_cbor::to_vec(value).map_err(Into::into))?;
+    Ok(PyBytes::new_bound(py, &bytes).into())
+}
+
+#[pymodule]
+#[pyo3(name = "plonky")]
+fn plonky_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Target>()?;
+    m.add_class::<CircuitBuilder>()?;
+    m.add_class::<Circuit>()?;
+    m.add_class::<Witness>()?;
+    m.add_class::<ProverKey>()?;
+    m.add_class::<VerificationKey>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+    use pyo3::wrap_pymodule;
+
+    use crate::python::plonky_python;
+
+    #[test]
+    fn test_python_bindings() -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let globals = PyDict::new_bound(py);
+            globals.set_item("plonky", wrap_pymodule!(plonky_python)(py))?;
+            py.run_bound(
+                r#"
+builder = plonky.CircuitBuilder()
+square = builder.add_public_input()
+root = builder.add_virtual_target()
+builder.copy(builder.mul(root, root), square)
+circuit = builder.build()
+
+witness = circuit.generate_witness([(root, 7)])
+assert circuit.public_inputs(witness) == [49]
+proof = circuit.prove(witness)
+vk = plonky.VerificationKey.from_bytes(circuit.verification_key().to_bytes())
+assert vk.verify([49], proof)
+assert not vk.verify([50], proof)
+
+# Prove again with a loaded key and witness.
+prover_key = plonky.ProverKey.from_bytes(circuit.prover_key().to_bytes())
+proof = prover_key.prove(plonky.Witness.from_bytes(witness.to_bytes()))
+assert vk.verify([49], proof)
+
+try:
+    circuit.generate_witness([(root, -1)])
+    assert False
+except OverflowError:
+    pass
+"#,
+                Some(&globals),
+                None,
+            )
+        })
+    }
+}
@@ -0,0 +1,37 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky::{msm_parallel, AffinePoint, Curve, CurveScalar, Field, ProjectivePoint, Tweedledee, Tweedledum};
+use plonky_fuzz::elements_from_bytes;
+
+/// Checks that scalar multiplication is linear, that affine and projective additions agree, and
+/// that MSMs match sums of scalar multiplications.
+fn check<C: Curve>(data: &[u8]) {
+    let scalars = elements_from_bytes::<C::ScalarField>(data);
+    let g = C::GENERATOR_PROJECTIVE;
+    let mul = |s: C::ScalarField| CurveScalar(s) * g;
+
+    for pair in scalars.chunks_exact(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let (a_g, b_g) = (mul(a), mul(b));
+        assert_eq!(mul(a + b), a_g + b_g);
+        assert_eq!((a_g + b_g.to_affine()).to_affine(), (a_g + b_g).to_affine());
+        assert_eq!(mul(a.double()), a_g.double());
+        assert!(a_g.to_affine().is_valid());
+    }
+
+    let points = scalars.iter().map(|&s| mul(s)).collect::<Vec<_>>();
+    let points = AffinePoint::batch_to_projective(&ProjectivePoint::batch_to_affine(&points));
+    let expected = scalars
+        .iter()
+        .zip(&points)
+        .fold(ProjectivePoint::ZERO, |acc, (&s, &p)| acc + CurveScalar(s) * p);
+    if !scalars.is_empty() {
+        assert_eq!(msm_parallel(&scalars, &points, 4), expected);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    check::<Tweedledee>(data);
+    check::<Tweedledum>(data);
+});
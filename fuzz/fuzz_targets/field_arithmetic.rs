@@ -0,0 +1,35 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use num::BigUint;
+use plonky::{field_to_biguint, Bls12377Base, Field, TweedledeeBase, TweedledumBase};
+use plonky_fuzz::elements_from_bytes;
+
+/// Checks field identities, and compares multiplication against `BigUint`.
+fn check<F: Field>(data: &[u8]) {
+    let modulus: BigUint = field_to_biguint(F::NEG_ONE) + 1u32;
+    for xs in elements_from_bytes::<F>(data).chunks_exact(3) {
+        let (a, b, c) = (xs[0], xs[1], xs[2]);
+        assert_eq!(a + b - b, a);
+        assert_eq!(a + -a, F::ZERO);
+        assert_eq!(a * (b + c), a * b + a * c);
+        assert_eq!(a.square(), a * a);
+        assert_eq!(
+            field_to_biguint(a * b),
+            field_to_biguint(a) * field_to_biguint(b) % &modulus
+        );
+        match a.multiplicative_inverse() {
+            Some(a_inv) => assert_eq!(a * a_inv, F::ONE),
+            None => assert!(a.is_zero()),
+        }
+        if let Some(root) = a.square_root() {
+            assert_eq!(root.square(), a);
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    check::<TweedledeeBase>(data);
+    check::<TweedledumBase>(data);
+    check::<Bls12377Base>(data);
+});
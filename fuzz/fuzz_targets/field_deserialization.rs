@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky::{Bls12377Base, Field, FromBytes, ToBytes, TweedledeeBase, TweedledumBase};
+
+/// Decoding arbitrary bytes must fail gracefully, and whatever decodes must re-encode to the same
+/// bytes, since only canonical encodings are accepted.
+fn check<F: Field>(data: &[u8]) {
+    if let Ok(x) = F::read(data) {
+        let mut encoded = Vec::new();
+        x.write(&mut encoded).unwrap();
+        assert_eq!(&encoded[..], &data[..F::BYTES]);
+        assert_eq!(F::from_canonical_u8_vec(encoded).unwrap(), x);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    check::<TweedledeeBase>(data);
+    check::<TweedledumBase>(data);
+    check::<Bls12377Base>(data);
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky::{AffinePoint, Bls12377, Curve, FromBytes, ToBytes, Tweedledee, Tweedledum};
+
+/// Decoding arbitrary bytes must fail gracefully, and whatever decodes must be a point on the
+/// curve, which re-encodes to the same bytes.
+fn check<C: Curve>(data: &[u8]) {
+    if let Ok(p) = AffinePoint::<C>::read(data) {
+        assert!(p.is_valid());
+        let mut encoded = Vec::new();
+        p.write(&mut encoded).unwrap();
+        assert_eq!(&encoded[..], &data[..encoded.len()]);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    check::<Tweedledee>(data);
+    check::<Tweedledum>(data);
+    check::<Bls12377>(data);
+});
@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky::{public_inputs_from_json, FromBytes, Proof, TextEncoding, VerificationKey};
+use plonky_fuzz::{C, F};
+
+fuzz_target!(|data: &[u8]| {
+    // Proofs have a compact binary encoding, which must round-trip exactly.
+    if let Ok(proof) = Proof::<C>::from_bytes(data) {
+        assert_eq!(proof.to_bytes(), data);
+    }
+    // Keys are CBOR, so they have no `from_bytes`; decode them with `FromBytes` directly.
+    let _ = VerificationKey::<C>::read(data);
+
+    if let Ok(text) = std::str::from_utf8(data) {
+        let _ = Proof::<C>::from_json(text);
+        let _ = VerificationKey::<C>::from_json(text);
+        let _ = Proof::<C>::from_hex(text);
+        let _ = VerificationKey::<C>::from_hex(text);
+        let _ = Proof::<C>::from_base64(text);
+        let _ = public_inputs_from_json::<F>(text);
+    }
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use plonky::{verify, Proof};
+use plonky_fuzz::{elements_from_bytes, InnerC, C, F, STATEMENT};
+
+fuzz_target!(|data: &[u8]| {
+    let statement = &*STATEMENT;
+
+    // Flip bits of a valid proof, so that most inputs decode, and reach the verifier's checks.
+    let mut bytes = statement.proof.to_bytes();
+    for (byte, &mask) in bytes.iter_mut().zip(data) {
+        *byte ^= mask;
+    }
+    if let Ok(proof) = Proof::<C>::from_bytes(&bytes) {
+        let result = verify::<C, InnerC>(&statement.vk, &statement.public_inputs, &proof);
+        if proof != statement.proof {
+            assert!(result.is_err(), "A tampered proof was accepted");
+        }
+    }
+
+    // Any public inputs, including too many or too few, must be handled gracefully.
+    let public_inputs = elements_from_bytes::<F>(data);
+    let result = verify::<C, InnerC>(&statement.vk, &public_inputs, &statement.proof);
+    assert_eq!(result.is_ok(), public_inputs == statement.public_inputs);
+});
@@ -0,0 +1,47 @@
+//! Shared inputs of the fuzz targets.
+
+use num::BigUint;
+use once_cell::sync::Lazy;
+use plonky::{biguint_to_field, field_to_biguint, CircuitBuilder, Field, PartialWitness, Proof, Tweedledee, Tweedledum, VerificationKey};
+
+pub type C = Tweedledee;
+pub type InnerC = Tweedledum;
+pub type F = <Tweedledee as plonky::Curve>::ScalarField;
+
+/// A small valid statement, with its verification key, public inputs and proof.
+pub struct Statement {
+    pub vk: VerificationKey<C>,
+    pub public_inputs: Vec<F>,
+    pub proof: Proof<C>,
+}
+
+/// A proof that the public input is a square. Proving is slow, so it's done once per process.
+pub static STATEMENT: Lazy<Statement> = Lazy::new(|| {
+    let mut builder = CircuitBuilder::<C>::new(128);
+    let pi = builder.add_public_input();
+    let x = builder.add_virtual_target();
+    let x_sq = builder.square(x);
+    builder.copy(x_sq, pi);
+    let circuit = builder.build();
+
+    let mut inputs = PartialWitness::new();
+    inputs.set_target(x, F::from_canonical_usize(7));
+    let witness = circuit.generate_witness(inputs).expect("Witness generation should succeed");
+    let proof = circuit
+        .generate_proof::<InnerC>(&witness, &[])
+        .expect("Proving should succeed");
+    Statement {
+        vk: circuit.to_vk(),
+        public_inputs: circuit.get_public_inputs(&witness),
+        proof,
+    }
+});
+
+/// Splits `data` into field elements, reducing each chunk of `F::BYTES` bytes modulo the order of
+/// `F`, so that any input yields valid elements.
+pub fn elements_from_bytes<F: Field>(data: &[u8]) -> Vec<F> {
+    let modulus = field_to_biguint(F::NEG_ONE) + 1u32;
+    data.chunks(F::BYTES)
+        .map(|chunk| biguint_to_field(BigUint::from_bytes_le(chunk) % &modulus))
+        .collect()
+}
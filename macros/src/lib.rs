@@ -0,0 +1,350 @@
+//! The `circuit!` macro and `#[derive(CircuitIo)]`, which plonky re-exports. See their
+//! documentation there.
+
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, parse_quote, BinOp, Data, DeriveInput, Expr, Fields, GenericParam, Ident, Index, Lit, LitStr, Member, Token};
+
+/// Declares targets and constraints in a `CircuitBuilder`. The first item is the builder, as a
+/// `&mut CircuitBuilder`, followed by statements, each ending with a semicolon:
+///
+/// * `public a, b;` adds public inputs;
+/// * `input x, y;` adds virtual targets, which the witness should populate;
+/// * `let z = x * y + 3;` lowers an expression with `CircuitBuilder::eval`;
+/// * `assert z == a;` and `assert x != y;` constrain expressions to be equal, or to differ;
+/// * `assert_binary x;` and `assert_nonzero x;` call the corresponding builder methods.
+///
+/// Integer literals in expressions are field constants. Every target declared by the macro is
+/// bound in the enclosing scope, and named after its variable, so error messages mention it:
+///
+/// ```ignore
+/// let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+/// circuit! {
+///     &mut builder;
+///     public product;
+///     input x, y;
+///     assert x * y == product;
+///     assert_nonzero x - 1;
+/// }
+/// let circuit = builder.build();
+/// ```
+#[proc_macro]
+pub fn circuit(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as Circuit).expand().into()
+}
+
+struct Circuit {
+    builder: Expr,
+    statements: Vec<Statement>,
+}
+
+enum Statement {
+    Public(Vec<Ident>),
+    Input(Vec<Ident>),
+    Let(Ident, Expr),
+    AssertEq(Expr, Expr),
+    AssertNe(Expr, Expr),
+    AssertBinary(Expr),
+    AssertNonzero(Expr),
+}
+
+impl Parse for Circuit {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let builder = input.parse()?;
+        input.parse::<Token![;]>()?;
+        let mut statements = Vec::new();
+        while !input.is_empty() {
+            statements.push(input.parse()?);
+            input.parse::<Token![;]>()?;
+        }
+        Ok(Circuit {
+            builder,
+            statements,
+        })
+    }
+}
+
+impl Parse for Statement {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![let]) {
+            input.parse::<Token![let]>()?;
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            return Ok(Statement::Let(name, input.parse()?));
+        }
+
+        let keyword: Ident = input.parse()?;
+        match keyword.to_string().as_str() {
+            "public" => Ok(Statement::Public(parse_idents(input)?)),
+            "input" => Ok(Statement::Input(parse_idents(input)?)),
+            "assert" => {
+                let expr: Expr = input.parse()?;
+                if let Expr::Binary(binary) = &expr {
+                    let (left, right) = ((*binary.left).clone(), (*binary.right).clone());
+                    match binary.op {
+                        BinOp::Eq(_) => return Ok(Statement::AssertEq(left, right)),
+                        BinOp::Ne(_) => return Ok(Statement::AssertNe(left, right)),
+                        _ => {}
+                    }
+                }
+                Err(syn::Error::new_spanned(
+                    expr,
+                    "Expected a comparison, `lhs == rhs` or `lhs != rhs`",
+                ))
+            }
+            "assert_binary" => Ok(Statement::AssertBinary(input.parse()?)),
+            "assert_nonzero" => Ok(Statement::AssertNonzero(input.parse()?)),
+            _ => Err(syn::Error::new(
+                keyword.span(),
+                "Expected `public`, `input`, `let`, `assert`, `assert_binary` or `assert_nonzero`",
+            )),
+        }
+    }
+}
+
+fn parse_idents(input: ParseStream) -> syn::Result<Vec<Ident>> {
+    let idents = Punctuated::<Ident, Token![,]>::parse_separated_nonempty(input)?;
+    Ok(idents.into_iter().collect())
+}
+
+/// Replaces integer literals which are operands of arithmetic with field constants. Literals
+/// elsewhere, such as arguments of function calls, are left alone.
+fn lower_literals(expr: &mut Expr) {
+    match expr {
+        Expr::Lit(lit) => {
+            if let Lit::Int(int) = &lit.lit {
+                let int = int.clone();
+                *expr = parse_quote!(::plonky::Expr::from_canonical_u64(#int));
+            }
+        }
+        Expr::Binary(binary) => {
+            lower_literals(&mut binary.left);
+            lower_literals(&mut binary.right);
+        }
+        Expr::Unary(unary) => lower_literals(&mut unary.expr),
+        Expr::Paren(paren) => lower_literals(&mut paren.expr),
+        Expr::Group(group) => lower_literals(&mut group.expr),
+        _ => {}
+    }
+}
+
+impl Circuit {
+    fn expand(self) -> TokenStream2 {
+        // These identifiers are hygienic, so they can't clash with the user's variables.
+        let builder = Ident::new("builder", Span::mixed_site());
+        let value = Ident::new("value", Span::mixed_site());
+
+        let mut body = TokenStream2::new();
+        let mut bindings = Vec::new();
+        let name = |ident: &Ident| LitStr::new(&ident.to_string(), ident.span());
+        let lowered = |mut expr: Expr| {
+            lower_literals(&mut expr);
+            expr
+        };
+
+        for statement in self.statements {
+            body.extend(match statement {
+                Statement::Public(idents) => {
+                    let names = idents.iter().map(name).collect::<Vec<_>>();
+                    bindings.extend(idents.iter().cloned());
+                    quote! {
+                        #(
+                            let #idents = #builder.add_public_input();
+                            #builder.set_target_name(#idents, #names);
+                        )*
+                    }
+                }
+                Statement::Input(idents) => {
+                    let names = idents.iter().map(name).collect::<Vec<_>>();
+                    bindings.extend(idents.iter().cloned());
+                    quote! {
+                        #(
+                            let #idents = #builder.add_virtual_target();
+                            #builder.set_target_name(#idents, #names);
+                        )*
+                    }
+                }
+                Statement::Let(ident, expr) => {
+                    let expr = lowered(expr);
+                    let ident_name = name(&ident);
+                    bindings.push(ident.clone());
+                    quote! {
+                        let #ident = #builder.eval(#expr);
+                        #builder.set_target_name(#ident, #ident_name);
+                    }
+                }
+                Statement::AssertEq(left, right) => {
+                    let (left, right) = (lowered(left), lowered(right));
+                    quote! {
+                        let #value = #builder.eval(#left);
+                        let #value = (#value, #builder.eval(#right));
+                        #builder.copy(#value.0, #value.1);
+                    }
+                }
+                Statement::AssertNe(left, right) => {
+                    let (left, right) = (lowered(left), lowered(right));
+                    quote! {
+                        let #value = #builder.eval(::plonky::Expr::from(#left) - (#right));
+                        #builder.assert_nonzero(#value);
+                    }
+                }
+                Statement::AssertBinary(expr) => {
+                    let expr = lowered(expr);
+                    quote! {
+                        let #value = #builder.eval(#expr);
+                        #builder.assert_binary(#value);
+                    }
+                }
+                Statement::AssertNonzero(expr) => {
+                    let expr = lowered(expr);
+                    quote! {
+                        let #value = #builder.eval(#expr);
+                        #builder.assert_nonzero(#value);
+                    }
+                }
+            });
+        }
+
+        // A name declared twice refers to its last declaration, like shadowed variables.
+        let mut seen = HashSet::new();
+        bindings.reverse();
+        bindings.retain(|ident| seen.insert(ident.to_string()));
+        bindings.reverse();
+
+        let target_builder = self.builder;
+        quote! {
+            let (#(#bindings,)*) = {
+                let #builder: &mut ::plonky::CircuitBuilder<_> = #target_builder;
+                #body
+                (#(#bindings,)*)
+            };
+        }
+    }
+}
+
+/// Implements `CircuitIo` for a struct with a single type parameter, the field, by encoding its
+/// fields in order. Also generates a struct of the fields' targets, named after the original
+/// struct with a `Targets` suffix, with the same field names and visibilities.
+#[proc_macro_derive(CircuitIo)]
+pub fn derive_circuit_io(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_circuit_io_impl(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn derive_circuit_io_impl(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => data.fields.clone(),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "CircuitIo can only be derived for structs",
+            ))
+        }
+    };
+    let type_params = input
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let field = match type_params.as_slice() {
+        [field] => field.clone(),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.generics,
+                "CircuitIo can only be derived for structs with a single type parameter, the field",
+            ))
+        }
+    };
+    if let Fields::Unit = fields {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "CircuitIo can't be derived for unit structs",
+        ));
+    }
+
+    input
+        .generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(#field: ::plonky::Field));
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let ident = &input.ident;
+    let vis = &input.vis;
+    let targets_ident = Ident::new(&format!("{}Targets", ident), ident.span());
+    let targets_doc = format!("The targets of a `{}`, see `CircuitIo`.", ident);
+    let members = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| match &f.ident {
+            Some(name) => Member::Named(name.clone()),
+            None => Member::Unnamed(Index::from(i)),
+        })
+        .collect::<Vec<_>>();
+    let io_types = fields
+        .iter()
+        .map(|f| {
+            let ty = &f.ty;
+            quote!(<#ty as ::plonky::CircuitIo<#field>>)
+        })
+        .collect::<Vec<_>>();
+    let field_vis = fields.iter().map(|f| &f.vis).collect::<Vec<_>>();
+
+    let targets_struct = match &fields {
+        Fields::Named(_) => quote! {
+            #[doc = #targets_doc]
+            #[derive(Clone, Debug)]
+            #vis struct #targets_ident #impl_generics #where_clause {
+                #(#field_vis #members: #io_types::Targets,)*
+            }
+        },
+        _ => quote! {
+            #[doc = #targets_doc]
+            #[derive(Clone, Debug)]
+            #vis struct #targets_ident #impl_generics (#(#field_vis #io_types::Targets,)*) #where_clause;
+        },
+    };
+
+    Ok(quote! {
+        #targets_struct
+
+        impl #impl_generics ::plonky::CircuitIo<#field> for #ident #ty_generics #where_clause {
+            type Targets = #targets_ident #ty_generics;
+
+            fn num_elements() -> usize {
+                0 #(+ #io_types::num_elements())*
+            }
+
+            fn read_targets(targets: &mut &[::plonky::Target<#field>]) -> Self::Targets {
+                #targets_ident {
+                    #(#members: #io_types::read_targets(targets),)*
+                }
+            }
+
+            fn write_targets(targets: &Self::Targets, out: &mut Vec<::plonky::Target<#field>>) {
+                #(#io_types::write_targets(&targets.#members, out);)*
+            }
+
+            fn read_elements(elements: &mut &[#field]) -> Self {
+                #ident {
+                    #(#members: #io_types::read_elements(elements),)*
+                }
+            }
+
+            fn write_elements(&self, out: &mut Vec<#field>) {
+                #(#io_types::write_elements(&self.#members, out);)*
+            }
+        }
+    })
+}
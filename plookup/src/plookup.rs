@@ -4,11 +4,12 @@ use crate::openings::open_all_polynomials;
 use crate::proof::PlookupProof;
 use anyhow::Result;
 use plonky::halo::batch_opening_proof;
-use plonky::plonk_challenger::Challenger;
+use plonky::plonk_challenger::{Challenger, Transcript};
 use plonky::plonk_util::reduce_with_powers;
 use plonky::polynomial::Polynomial;
 use plonky::util::log2_strict;
-use plonky::{blake_hash_usize_to_curve, fft_precompute, msm_precompute, AffinePoint, Field, HaloCurve};
+use plonky::{blake_hash_usize_to_curve, cached_fft_precomputation, msm_precompute, AffinePoint, Field, HaloCurve};
+use rand::thread_rng;
 
 pub const SECURITY_BITS: usize = 128;
 
@@ -21,11 +22,13 @@ pub fn prove<C: HaloCurve>(f: &[C::ScalarField], t: &[C::ScalarField]) -> Result
     s.extend_from_slice(&t);
     sort_by(&mut s, &t);
 
+    let mut rng = thread_rng();
+
     // Initiate the Fiat-Shamir challenger.
     let mut challenger = Challenger::new(SECURITY_BITS);
 
     // FFT precomputation on the cyclic subgroup of order `n+1`.
-    let fft_precomputation = fft_precompute(n + 1);
+    let fft_precomputation = cached_fft_precomputation(n + 1);
 
     // Compute the polynomials corresponding to `f`, `t`, `h1` and `h2`.
     let f_padded = padded(&f, n + 1);
@@ -44,10 +47,10 @@ pub fn prove<C: HaloCurve>(f: &[C::ScalarField], t: &[C::ScalarField]) -> Result
     let msm_precomputation_2n2 = msm_precompute(&AffinePoint::batch_to_projective(&gs), 8);
 
     // Commit to all polynomials.
-    let c_f = f_poly.commit(&msm_precomputation, h, true);
-    let c_t = t_poly.commit(&msm_precomputation, h, false);
-    let c_h1 = h1_poly.commit(&msm_precomputation, h, true);
-    let c_h2 = h2_poly.commit(&msm_precomputation, h, true);
+    let c_f = f_poly.commit(&msm_precomputation, h, true, &mut rng);
+    let c_t = t_poly.commit(&msm_precomputation, h, false, &mut rng);
+    let c_h1 = h1_poly.commit(&msm_precomputation, h, true, &mut rng);
+    let c_h2 = h2_poly.commit(&msm_precomputation, h, true, &mut rng);
 
     // Observe the commitments to get verifier challenges.
     // `beta` and `gamma` are used to construct the Plookup grand product.
@@ -65,7 +68,7 @@ pub fn prove<C: HaloCurve>(f: &[C::ScalarField], t: &[C::ScalarField]) -> Result
     // `f` is a subset of `t` iff this polynomial is well-formed and its last value is 1.
     let z_values = grand_polynomial(&f, &t, &s, beta, gamma);
     let z_poly = Polynomial::from_evaluations(&z_values, &fft_precomputation);
-    let c_z = z_poly.commit(&msm_precomputation, h, true);
+    let c_z = z_poly.commit(&msm_precomputation, h, true, &mut rng);
 
     // Observe the commitment to get a verifier challenge.
     // `alpha` is used to batch all vanishing polynomials.
@@ -85,7 +88,7 @@ pub fn prove<C: HaloCurve>(f: &[C::ScalarField], t: &[C::ScalarField]) -> Result
     quotient_poly.trim();
     assert!(quotient_poly.len() <= 2 * n + 1);
     quotient_poly.pad(2 * n + 2);
-    let c_quotient = quotient_poly.commit(&msm_precomputation_2n2, h, true);
+    let c_quotient = quotient_poly.commit(&msm_precomputation_2n2, h, true, &mut rng);
 
     // Observe the commitment to get a verifier challenge.
     // `zeta` is the point at which we'll open all polynomials.
@@ -147,6 +150,7 @@ pub fn prove<C: HaloCurve>(f: &[C::ScalarField], t: &[C::ScalarField]) -> Result
         2 * n + 2,
         SECURITY_BITS,
         &mut challenger,
+        &mut rng,
     )?;
 
     Ok(PlookupProof::from((commitments, openings, halo_proof, n)))
@@ -215,7 +219,7 @@ fn vanishing_polynomial<F: Field>(
 ) -> Polynomial<F> {
     let order = 4 * (n + 1);
     let generator_4 = F::primitive_root_of_unity(log2_strict(order));
-    let fft_precomp4 = fft_precompute(order);
+    let fft_precomp4 = cached_fft_precomputation(order);
     let z_4_values = z_poly.eval_domain(&fft_precomp4);
     let f_4_values = f_poly.eval_domain(&fft_precomp4);
     let t_4_values = t_poly.eval_domain(&fft_precomp4);
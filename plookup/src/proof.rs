@@ -2,7 +2,7 @@ use crate::openings::PlookupOpenings;
 use crate::plookup::SECURITY_BITS;
 use anyhow::{anyhow, Result};
 use plonky::halo::OpeningProof;
-use plonky::plonk_challenger::Challenger;
+use plonky::plonk_challenger::{Challenger, Transcript};
 use plonky::plonk_util::halo_n;
 use plonky::{AffinePoint, Curve, Field, HaloCurve, PolynomialCommitment};
 
@@ -3,7 +3,7 @@ use crate::proof::PlookupProof;
 use crate::verifier::verify;
 use anyhow::Result;
 use itertools::Itertools;
-use plonky::plonk_challenger::Challenger;
+use plonky::plonk_challenger::{Challenger, Transcript};
 use plonky::{Field, HaloCurve};
 
 /// A `Table` is a list of rows of field elements, all with size `N`.
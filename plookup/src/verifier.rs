@@ -4,14 +4,15 @@ use anyhow::{ensure, Result};
 use plonky::halo::verify_ipa;
 use plonky::plonk_util::{halo_g, halo_n, halo_n_mul, powers, reduce_with_powers};
 use plonky::util::log2_strict;
-use plonky::{blake_hash_usize_to_curve, fft_precompute, ifft_with_precomputation_power_of_2, msm_execute_parallel, msm_precompute, AffinePoint, Field, HaloCurve, PolynomialCommitment};
+use plonky::{blake_hash_usize_to_curve, cached_fft_precomputation, ifft_with_precomputation_power_of_2, msm_execute_parallel, msm_precompute, AffinePoint, Field, HaloCurve, PolynomialCommitment};
+use rand::thread_rng;
 
 /// Verifies that a proof is valid for a set `t`.
 /// TODO: The verifier should have some auxiliary knowledge of `c_t`. For now, it is stored in the `proof`.
 pub fn verify<C: HaloCurve>(t: &[C::ScalarField], proof: &PlookupProof<C>) -> Result<()> {
     let n = proof.n;
     let t = padded(t, n + 1);
-    let fft_precomputation = fft_precompute(n + 1);
+    let fft_precomputation = cached_fft_precomputation(n + 1);
     let gs = (0..2 * n + 2)
         .map(blake_hash_usize_to_curve::<C>)
         .collect::<Vec<_>>();
@@ -19,7 +20,7 @@ pub fn verify<C: HaloCurve>(t: &[C::ScalarField], proof: &PlookupProof<C>) -> Re
     let u_curve = blake_hash_usize_to_curve(2 * n + 3);
     let t_coeffs = ifft_with_precomputation_power_of_2(&t, &fft_precomputation);
     let msm_precomputation = msm_precompute(&AffinePoint::batch_to_projective(&gs[..n + 1]), 8);
-    let c_t = PolynomialCommitment::coeffs_to_commitment(&t_coeffs, &msm_precomputation, h, false);
+    let c_t = PolynomialCommitment::coeffs_to_commitment(&t_coeffs, &msm_precomputation, h, false, &mut thread_rng());
     ensure!(c_t.to_affine() == proof.c_t, "Incorrect table commitment");
 
     let challs = proof.get_challenges()?;
@@ -152,5 +153,6 @@ fn verify_all_ipas<C: HaloCurve>(
         pedersen_h,
         schnorr_challenge,
         proof.halo_proof.schnorr_proof,
+        security_bits,
     )
 }
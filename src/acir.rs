@@ -0,0 +1,433 @@
+//! A backend for ACIR, the abstract circuit intermediate representation which Noir compiles to, so
+//! that Noir programs can be proven with this library.
+//!
+//! The types here mirror the subset of ACIR which we support. Witness values are expected to come
+//! from an ACIR solver such as Noir's `nargo execute`, which assigns every witness, including the
+//! outputs of black box functions; this backend only enforces the constraints between them.
+//!
+//! Black box functions are mapped to our own gadgets. SHA-256 is computed bit by bit with
+//! arithmetic gates, and Pedersen hashes use our MSM gadget, with generators hashed to a curve
+//! whose base field is the circuit's field. Pedersen hashes therefore differ from those of other
+//! backends, as do any results which depend on the field, such as arithmetic overflowing it.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, ensure, Result};
+
+use crate::r1cs::arithmetic_gate;
+use crate::{blake_hash_base_field_to_curve, Circuit, CircuitBuilder, Curve, CurveMulOp, Field, HaloCurve, PartialWitness, Target};
+
+/// The index of a witness. Witness 0 is unused, as in ACIR.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Witness(pub u32);
+
+/// The constraint `sum(q_m * a * b) + sum(q_l * w) + q_c = 0`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Expression<F: Field> {
+    pub mul_terms: Vec<(F, Witness, Witness)>,
+    pub linear_combinations: Vec<(F, Witness)>,
+    pub q_c: F,
+}
+
+/// An input to a black box function, which is asserted to fit in `num_bits` bits.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FunctionInput {
+    pub witness: Witness,
+    pub num_bits: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BlackBoxFuncCall {
+    Range {
+        input: FunctionInput,
+    },
+    And {
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        output: Witness,
+    },
+    Xor {
+        lhs: FunctionInput,
+        rhs: FunctionInput,
+        output: Witness,
+    },
+    /// Hashes a message of bytes. The outputs are the 32 bytes of the digest.
+    Sha256 {
+        inputs: Vec<FunctionInput>,
+        outputs: Vec<Witness>,
+    },
+    /// Hashes field elements to a point, whose coordinates are the outputs.
+    Pedersen {
+        inputs: Vec<FunctionInput>,
+        domain_separator: u32,
+        outputs: (Witness, Witness),
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Opcode<F: Field> {
+    Arithmetic(Expression<F>),
+    BlackBoxFuncCall(BlackBoxFuncCall),
+}
+
+/// An ACIR circuit. The public parameters and the return values are public, and all other
+/// witnesses are private.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Acir<F: Field> {
+    /// The largest witness index in use.
+    pub current_witness_index: u32,
+    pub opcodes: Vec<Opcode<F>>,
+    pub public_parameters: Vec<Witness>,
+    pub return_values: Vec<Witness>,
+}
+
+/// A circuit built from ACIR. Its public inputs are the ACIR circuit's public parameters followed
+/// by its return values.
+pub struct AcirCircuit<C: HaloCurve> {
+    pub circuit: Circuit<C>,
+    /// The target of each witness, indexed by witness index.
+    pub witnesses: Vec<Target<C::ScalarField>>,
+}
+
+impl<C: HaloCurve> AcirCircuit<C> {
+    /// Builds a circuit which enforces the given opcodes. Pedersen hashes are computed on `InnerC`.
+    pub fn build<InnerC: Curve<BaseField = C::ScalarField>>(
+        acir: &Acir<C::ScalarField>,
+        security_bits: usize,
+    ) -> Result<Self> {
+        let mut builder = CircuitBuilder::<C>::new(security_bits);
+        let num_witnesses = acir.current_witness_index as usize + 1;
+        let witnesses = builder.add_virtual_targets(num_witnesses);
+
+        // Each public input is copied from its witness, which may be public more than once, e.g.
+        // when a parameter is returned.
+        let public_witnesses = acir
+            .public_parameters
+            .iter()
+            .chain(&acir.return_values)
+            .collect::<Vec<_>>();
+        let public_inputs = builder.add_public_inputs(public_witnesses.len());
+        for (&&witness, &public_input) in public_witnesses.iter().zip(&public_inputs) {
+            let index = witness_index(witness, num_witnesses)?;
+            builder.copy(witnesses[index], public_input);
+        }
+
+        let mut synthesizer = Synthesizer {
+            builder: &mut builder,
+            witnesses: &witnesses,
+        };
+        for opcode in &acir.opcodes {
+            match opcode {
+                Opcode::Arithmetic(expression) => synthesizer.assert_expression(expression)?,
+                Opcode::BlackBoxFuncCall(call) => synthesizer.black_box::<InnerC>(call)?,
+            }
+        }
+
+        Ok(AcirCircuit {
+            circuit: builder.build(),
+            witnesses,
+        })
+    }
+
+    /// Returns the inputs of witness generation, given the solved value of each witness.
+    pub fn partial_witness(
+        &self,
+        values: &BTreeMap<Witness, C::ScalarField>,
+    ) -> Result<PartialWitness<C::ScalarField>> {
+        let mut partial_witness = PartialWitness::new();
+        for (&witness, &value) in values {
+            let index = witness_index(witness, self.witnesses.len())?;
+            partial_witness.try_set_target(self.witnesses[index], value)?;
+        }
+        Ok(partial_witness)
+    }
+}
+
+fn witness_index(witness: Witness, num_witnesses: usize) -> Result<usize> {
+    let index = witness.0 as usize;
+    ensure!(index < num_witnesses, "Witness {} is out of range", index);
+    Ok(index)
+}
+
+/// An operation on a pair of bits, such as `CircuitBuilder::and`.
+type BitOp<C> = fn(
+    &mut CircuitBuilder<C>,
+    Target<<C as Curve>::ScalarField>,
+    Target<<C as Curve>::ScalarField>,
+) -> Target<<C as Curve>::ScalarField>;
+
+struct Synthesizer<'a, C: HaloCurve> {
+    builder: &'a mut CircuitBuilder<C>,
+    witnesses: &'a [Target<C::ScalarField>],
+}
+
+impl<'a, C: HaloCurve> Synthesizer<'a, C> {
+    fn target(&self, witness: Witness) -> Result<Target<C::ScalarField>> {
+        Ok(self.witnesses[witness_index(witness, self.witnesses.len())?])
+    }
+
+    /// Accumulates the terms of the expression with one arithmetic gate each, and asserts that the
+    /// sum is zero.
+    fn assert_expression(&mut self, expression: &Expression<C::ScalarField>) -> Result<()> {
+        let one = self.builder.one_wire();
+        let mut sum = self.builder.constant_wire(expression.q_c);
+        for &(q_m, a, b) in &expression.mul_terms {
+            let (a, b) = (self.target(a)?, self.target(b)?);
+            sum = arithmetic_gate(self.builder, q_m, a, b, C::ScalarField::ONE, sum);
+        }
+        for &(q_l, w) in &expression.linear_combinations {
+            let w = self.target(w)?;
+            sum = arithmetic_gate(self.builder, q_l, w, one, C::ScalarField::ONE, sum);
+        }
+        self.builder.assert_zero(sum);
+        Ok(())
+    }
+
+    fn black_box<InnerC: Curve<BaseField = C::ScalarField>>(
+        &mut self,
+        call: &BlackBoxFuncCall,
+    ) -> Result<()> {
+        match call {
+            BlackBoxFuncCall::Range { input } => {
+                self.input_bits(input)?;
+            }
+            BlackBoxFuncCall::And { lhs, rhs, output } => {
+                self.bitwise(lhs, rhs, *output, CircuitBuilder::and)?;
+            }
+            BlackBoxFuncCall::Xor { lhs, rhs, output } => {
+                self.bitwise(lhs, rhs, *output, CircuitBuilder::xor)?;
+            }
+            BlackBoxFuncCall::Sha256 { inputs, outputs } => {
+                ensure!(outputs.len() == 32, "SHA-256 has 32 output bytes");
+                let mut message = Vec::with_capacity(inputs.len());
+                for input in inputs {
+                    ensure!(input.num_bits <= 8, "SHA-256 inputs must be bytes");
+                    // The SHA-256 gadget itself checks that each input is a byte.
+                    if input.num_bits < 8 {
+                        self.input_bits(input)?;
+                    }
+                    message.push(self.target(input.witness)?);
+                }
+                let digest = self.builder.sha256(&message);
+                for (&byte, &output) in digest.iter().zip(outputs) {
+                    let output = self.target(output)?;
+                    self.builder.copy(byte, output);
+                }
+            }
+            BlackBoxFuncCall::Pedersen {
+                inputs,
+                domain_separator,
+                outputs,
+            } => {
+                // Our MSM gadget requires each scalar's most significant bit to be unset.
+                let mut parts = Vec::with_capacity(inputs.len());
+                for (i, input) in inputs.iter().enumerate() {
+                    ensure!(
+                        (input.num_bits as usize) < C::ScalarField::BITS,
+                        "Pedersen inputs must have fewer than {} bits",
+                        C::ScalarField::BITS
+                    );
+                    self.input_bits(input)?;
+                    let seed = (*domain_separator as u64) << 32 | i as u64;
+                    let generator = blake_hash_base_field_to_curve::<InnerC>(
+                        C::ScalarField::from_canonical_u64(seed),
+                    );
+                    parts.push(CurveMulOp {
+                        scalar: self.target(input.witness)?,
+                        point: self.builder.constant_affine_point(generator),
+                    });
+                }
+                ensure!(
+                    !parts.is_empty(),
+                    "A Pedersen hash needs at least one input"
+                );
+                let hash = self.builder.curve_msm::<InnerC>(&parts);
+                let (x, y) = (self.target(outputs.0)?, self.target(outputs.1)?);
+                self.builder.copy(hash.x, x);
+                self.builder.copy(hash.y, y);
+            }
+        }
+        Ok(())
+    }
+
+    /// Asserts that the input fits in its number of bits, returning its bits if it's smaller than
+    /// the field.
+    fn input_bits(&mut self, input: &FunctionInput) -> Result<Option<Vec<Target<C::ScalarField>>>> {
+        let num_bits = input.num_bits as usize;
+        if num_bits >= C::ScalarField::BITS {
+            // Every field element fits.
+            return Ok(None);
+        }
+        let x = self.target(input.witness)?;
+        Ok(Some(self.builder.split_binary_checked(x, num_bits)))
+    }
+
+    fn bitwise(
+        &mut self,
+        lhs: &FunctionInput,
+        rhs: &FunctionInput,
+        output: Witness,
+        op: BitOp<C>,
+    ) -> Result<()> {
+        ensure!(
+            lhs.num_bits == rhs.num_bits,
+            "Bitwise operands must have the same number of bits"
+        );
+        let (lhs_bits, rhs_bits) = match (self.input_bits(lhs)?, self.input_bits(rhs)?) {
+            (Some(lhs_bits), Some(rhs_bits)) => (lhs_bits, rhs_bits),
+            _ => bail!("Bitwise operands must be smaller than the field"),
+        };
+        let bits = lhs_bits
+            .into_iter()
+            .zip(rhs_bits)
+            .map(|(x, y)| op(self.builder, x, y))
+            .collect::<Vec<_>>();
+        let result = self.builder.le_sum(&bits);
+        let output = self.target(output)?;
+        self.builder.copy(result, output);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use anyhow::Result;
+
+    use crate::acir::{Acir, AcirCircuit, BlackBoxFuncCall, Expression, FunctionInput, Opcode, Witness};
+    use crate::{blake_hash_base_field_to_curve, verify_proof, Curve, CurveScalar, Field, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// `assert(x * y + 3 == z)` with `z` returned, followed by `(x & y) ^ x`, with each of `x` and
+    /// `y` an 8-bit integer.
+    fn program() -> Acir<F> {
+        let input = |i| FunctionInput {
+            witness: Witness(i),
+            num_bits: 8,
+        };
+        Acir {
+            current_witness_index: 5,
+            opcodes: vec![
+                Opcode::Arithmetic(Expression {
+                    mul_terms: vec![(F::ONE, Witness(1), Witness(2))],
+                    linear_combinations: vec![(F::NEG_ONE, Witness(3))],
+                    q_c: F::THREE,
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::And {
+                    lhs: input(1),
+                    rhs: input(2),
+                    output: Witness(4),
+                }),
+                Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Xor {
+                    lhs: input(4),
+                    rhs: input(1),
+                    output: Witness(5),
+                }),
+            ],
+            public_parameters: vec![Witness(1)],
+            return_values: vec![Witness(3), Witness(5)],
+        }
+    }
+
+    fn values(x: u32, y: u32) -> BTreeMap<Witness, F> {
+        [x, y, x * y + 3, x & y, (x & y) ^ x]
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (Witness(i as u32 + 1), F::from_canonical_u32(v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_acir_circuit() -> Result<()> {
+        let acir_circuit = AcirCircuit::<Tweedledee>::build::<Tweedledum>(&program(), 128)?;
+        let circuit = &acir_circuit.circuit;
+        let inputs = acir_circuit.partial_witness(&values(200, 99))?;
+        let witness = circuit.generate_witness(inputs)?;
+        let public_inputs = circuit.get_public_inputs(&witness);
+        let expected = [200, 200 * 99 + 3, (200 & 99) ^ 200];
+        let expected = expected
+            .iter()
+            .map(|&v| F::from_canonical_u32(v))
+            .collect::<Vec<_>>();
+        assert_eq!(public_inputs, expected);
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        verify_proof::<Tweedledee, Tweedledum>(
+            &public_inputs,
+            &proof,
+            &[],
+            &circuit.to_vk(),
+            true,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_acir_circuit_rejects_bad_witness() -> Result<()> {
+        let acir_circuit = AcirCircuit::<Tweedledee>::build::<Tweedledum>(&program(), 128)?;
+        let circuit = &acir_circuit.circuit;
+
+        // An expression which isn't satisfied.
+        let mut bad_values = values(5, 6);
+        bad_values.insert(Witness(3), F::from_canonical_u32(34));
+        let inputs = acir_circuit.partial_witness(&bad_values)?;
+        assert!(circuit.generate_witness(inputs).is_err());
+
+        // A wrong result of a black box function.
+        let mut bad_values = values(5, 6);
+        bad_values.insert(Witness(4), F::from_canonical_u32(5 | 6));
+        let inputs = acir_circuit.partial_witness(&bad_values)?;
+        assert!(circuit.generate_witness(inputs).is_err());
+
+        // An input which is out of range.
+        let inputs = acir_circuit.partial_witness(&values(256, 1))?;
+        assert!(circuit.generate_witness(inputs).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pedersen() -> Result<()> {
+        let input = |i| FunctionInput {
+            witness: Witness(i),
+            num_bits: 64,
+        };
+        let acir = Acir {
+            current_witness_index: 4,
+            opcodes: vec![Opcode::BlackBoxFuncCall(BlackBoxFuncCall::Pedersen {
+                inputs: vec![input(1), input(2)],
+                domain_separator: 7,
+                outputs: (Witness(3), Witness(4)),
+            })],
+            public_parameters: vec![],
+            return_values: vec![Witness(3), Witness(4)],
+        };
+        let acir_circuit = AcirCircuit::<Tweedledum>::build::<Tweedledee>(&acir, 128)?;
+
+        // Compute the expected hash natively.
+        type BF = <Tweedledee as Curve>::BaseField;
+        type SF = <Tweedledee as Curve>::ScalarField;
+        let term = |i: u64, x: u64| {
+            let generator =
+                blake_hash_base_field_to_curve::<Tweedledee>(BF::from_canonical_u64(7 << 32 | i));
+            CurveScalar(SF::from_canonical_u64(x)) * generator.to_projective()
+        };
+        let hash = (term(0, 12) + term(1, 34)).to_affine();
+        let values: BTreeMap<_, _> = vec![
+            (Witness(1), BF::from_canonical_u64(12)),
+            (Witness(2), BF::from_canonical_u64(34)),
+            (Witness(3), hash.x),
+            (Witness(4), hash.y),
+        ]
+        .into_iter()
+        .collect();
+        let inputs = acir_circuit.partial_witness(&values)?;
+        assert!(acir_circuit.circuit.generate_witness(inputs).is_ok());
+
+        let mut wrong_values = values;
+        wrong_values.insert(Witness(4), -hash.y);
+        let inputs = acir_circuit.partial_witness(&wrong_values)?;
+        assert!(acir_circuit.circuit.generate_witness(inputs).is_err());
+        Ok(())
+    }
+}
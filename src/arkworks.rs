@@ -0,0 +1,162 @@
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::util::ceil_div_usize;
+use crate::{AffinePoint, Bls12377, Bls12377Base, Bls12377Scalar, Curve, Field};
+
+/// Converts a field element to the same element of an arkworks field, which must have the same
+/// modulus.
+pub fn field_to_ark<F: Field, A: PrimeField>(x: F) -> A {
+    assert_same_modulus::<F, A>();
+    A::from_le_bytes_mod_order(&x.to_canonical_u8_vec())
+}
+
+/// Converts an element of an arkworks field to the same element of our field, which must have the
+/// same modulus.
+pub fn field_from_ark<A: PrimeField, F: Field>(x: A) -> F {
+    assert_same_modulus::<F, A>();
+    let mut bytes = x.into_bigint().to_bytes_le();
+    // `from_canonical_u8_vec` expects whole u64 limbs.
+    bytes.resize(ceil_div_usize(F::BITS, 64) * 8, 0);
+    F::from_canonical_u8_vec(bytes).expect("The moduli match, so the element should be canonical")
+}
+
+/// Converts a point to the same point of an arkworks short Weierstrass curve, which must have the
+/// same equation.
+pub fn point_to_ark<C: Curve, P: SWCurveConfig>(p: AffinePoint<C>) -> Affine<P>
+where
+    P::BaseField: PrimeField,
+{
+    assert_same_equation::<C, P>();
+    if p.zero {
+        Affine::identity()
+    } else {
+        Affine::new_unchecked(field_to_ark(p.x), field_to_ark(p.y))
+    }
+}
+
+/// Converts a point of an arkworks short Weierstrass curve to the same point of our curve, which
+/// must have the same equation.
+pub fn point_from_ark<P: SWCurveConfig, C: Curve>(p: Affine<P>) -> AffinePoint<C>
+where
+    P::BaseField: PrimeField,
+{
+    assert_same_equation::<C, P>();
+    if p.infinity {
+        AffinePoint::ZERO
+    } else {
+        AffinePoint::nonzero(field_from_ark(p.x), field_from_ark(p.y))
+    }
+}
+
+fn assert_same_modulus<F: Field, A: PrimeField>() {
+    // Comparing `-1` avoids representing the modulus itself, which doesn't fit in our fields.
+    let trimmed = |mut bytes: Vec<u8>| {
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        bytes
+    };
+    assert_eq!(
+        trimmed(F::NEG_ONE.to_canonical_u8_vec()),
+        trimmed((-A::one()).into_bigint().to_bytes_le()),
+        "The fields have different moduli"
+    );
+}
+
+fn assert_same_equation<C: Curve, P: SWCurveConfig>()
+where
+    P::BaseField: PrimeField,
+{
+    assert!(
+        field_to_ark::<_, P::BaseField>(C::A) == P::COEFF_A
+            && field_to_ark::<_, P::BaseField>(C::B) == P::COEFF_B,
+        "The curves have different equations"
+    );
+}
+
+impl From<Bls12377Base> for ark_bls12_377::Fq {
+    fn from(x: Bls12377Base) -> Self {
+        field_to_ark(x)
+    }
+}
+
+impl From<ark_bls12_377::Fq> for Bls12377Base {
+    fn from(x: ark_bls12_377::Fq) -> Self {
+        field_from_ark(x)
+    }
+}
+
+impl From<Bls12377Scalar> for ark_bls12_377::Fr {
+    fn from(x: Bls12377Scalar) -> Self {
+        field_to_ark(x)
+    }
+}
+
+impl From<ark_bls12_377::Fr> for Bls12377Scalar {
+    fn from(x: ark_bls12_377::Fr) -> Self {
+        field_from_ark(x)
+    }
+}
+
+impl From<AffinePoint<Bls12377>> for ark_bls12_377::G1Affine {
+    fn from(p: AffinePoint<Bls12377>) -> Self {
+        point_to_ark(p)
+    }
+}
+
+impl From<ark_bls12_377::G1Affine> for AffinePoint<Bls12377> {
+    fn from(p: ark_bls12_377::G1Affine) -> Self {
+        point_from_ark(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ec::{AffineRepr, CurveGroup};
+
+    use crate::{blake_hash_base_field_to_curve, field_to_ark, AffinePoint, Bls12377, Bls12377Base, Bls12377Scalar, Curve, CurveScalar, Field, TweedledumBase};
+
+    type Fq = ark_bls12_377::Fq;
+    type Fr = ark_bls12_377::Fr;
+    type G1 = ark_bls12_377::G1Affine;
+
+    #[test]
+    fn test_field_conversions() {
+        let x = Bls12377Base::rand();
+        let y = Bls12377Base::rand();
+        assert_eq!(Bls12377Base::from(Fq::from(x)), x);
+        assert_eq!(Fq::from(x * y), Fq::from(x) * Fq::from(y));
+        assert_eq!(Fq::from(Bls12377Base::NEG_ONE), -Fq::from(1u64));
+
+        let s = Bls12377Scalar::rand();
+        let t = Bls12377Scalar::rand();
+        assert_eq!(Bls12377Scalar::from(Fr::from(s)), s);
+        assert_eq!(Fr::from(s + t), Fr::from(s) + Fr::from(t));
+    }
+
+    #[test]
+    #[should_panic(expected = "The fields have different moduli")]
+    fn test_field_modulus_mismatch() {
+        field_to_ark::<_, Fq>(TweedledumBase::ONE);
+    }
+
+    #[test]
+    fn test_point_conversions() {
+        let p = blake_hash_base_field_to_curve::<Bls12377>(Bls12377Base::rand());
+        let q = blake_hash_base_field_to_curve::<Bls12377>(Bls12377Base::rand());
+        let p_ark = G1::from(p);
+        assert!(p_ark.is_on_curve());
+        assert_eq!(AffinePoint::<Bls12377>::from(p_ark), p);
+        assert_eq!(G1::from(AffinePoint::<Bls12377>::ZERO), G1::zero());
+        assert_eq!(AffinePoint::<Bls12377>::from(G1::zero()), AffinePoint::ZERO);
+        assert_eq!(G1::from(Bls12377::GENERATOR_AFFINE), G1::generator());
+
+        assert_eq!(G1::from((p + q).to_affine()), (p_ark + G1::from(q)).into_affine());
+        let s = <Bls12377 as Curve>::ScalarField::rand();
+        assert_eq!(
+            G1::from((CurveScalar(s) * p.to_projective()).to_affine()),
+            (p_ark * Fr::from(s)).into_affine()
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! An implementation of bellman's `ConstraintSystem` on top of `CircuitBuilder`, so that circuits
+//! and gadget libraries written against bellman can be synthesized into our circuits.
+//!
+//! Bellman's field must be the circuit's field. Field elements are converted through their
+//! representations, which are assumed to be little-endian, as they are for BLS12-381 and the Pasta
+//! fields.
+
+use std::marker::PhantomData;
+
+use ::bellman::{Circuit as BellmanCircuit, ConstraintSystem, Index, LinearCombination as BellmanLinearCombination, SynthesisError, Variable};
+use anyhow::{ensure, Result};
+use ff::PrimeField;
+
+use crate::r1cs::enforce_constraint;
+use crate::zkinterface::field_from_le_bytes;
+use crate::{Circuit, CircuitBuilder, Field, HaloCurve, LinearCombination, PartialWitness, Target};
+
+/// A bellman constraint system which adds each constraint to a `CircuitBuilder`, as in
+/// `R1csCircuit`. Input variables become public inputs, in the order in which they're allocated.
+///
+/// Bellman computes values while synthesizing, if it has a witness. Those values are recorded, and
+/// become the inputs of witness generation.
+pub struct BellmanConstraintSystem<'a, C: HaloCurve, S: PrimeField> {
+    builder: &'a mut CircuitBuilder<C>,
+    /// The target of every variable, starting with the constant one.
+    variables: Vec<Target<C::ScalarField>>,
+    /// The index in `variables` of each input variable.
+    inputs: Vec<usize>,
+    /// The index in `variables` of each auxiliary variable.
+    aux: Vec<usize>,
+    partial_witness: PartialWitness<C::ScalarField>,
+    _field: PhantomData<S>,
+}
+
+impl<'a, C: HaloCurve, S: PrimeField> BellmanConstraintSystem<'a, C, S> {
+    pub fn new(builder: &'a mut CircuitBuilder<C>) -> Result<Self> {
+        ensure!(
+            field_from_bellman::<S, C::ScalarField>(-S::ONE).ok() == Some(C::ScalarField::NEG_ONE),
+            "bellman's field is not the circuit's field"
+        );
+        let one = builder.one_wire();
+        Ok(BellmanConstraintSystem {
+            builder,
+            variables: vec![one],
+            inputs: vec![0],
+            aux: Vec::new(),
+            partial_witness: PartialWitness::new(),
+            _field: PhantomData,
+        })
+    }
+
+    /// The target of each input variable, other than the constant one. These are copied to the
+    /// circuit's public inputs.
+    pub fn input_targets(&self) -> Vec<Target<C::ScalarField>> {
+        self.inputs[1..].iter().map(|&i| self.variables[i]).collect()
+    }
+
+    /// The values which bellman assigned, as inputs of witness generation.
+    pub fn into_partial_witness(self) -> PartialWitness<C::ScalarField> {
+        self.partial_witness
+    }
+
+    /// Adds a variable, recording its value if bellman knows it.
+    fn add_variable<F>(&mut self, target: Target<C::ScalarField>, value: F) -> Result<usize, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+    {
+        match value() {
+            Ok(value) => {
+                let value = field_from_bellman(value).map_err(|_| SynthesisError::Unsatisfiable)?;
+                self.partial_witness.set_target(target, value);
+            }
+            // Bellman synthesizes without values when it doesn't have a witness.
+            Err(SynthesisError::AssignmentMissing) => (),
+            Err(e) => return Err(e),
+        }
+        self.variables.push(target);
+        Ok(self.variables.len() - 1)
+    }
+
+    fn import_linear_combination(
+        &self,
+        lc: BellmanLinearCombination<S>,
+    ) -> LinearCombination<C::ScalarField> {
+        lc.as_ref()
+            .iter()
+            .map(|&(variable, coefficient)| {
+                let index = match variable.get_unchecked() {
+                    Index::Input(i) => self.inputs[i],
+                    Index::Aux(i) => self.aux[i],
+                };
+                let coefficient = field_from_bellman(coefficient)
+                    .expect("The fields match, so the coefficient should be canonical");
+                (index, coefficient)
+            })
+            .collect()
+    }
+}
+
+impl<'a, C: HaloCurve, S: PrimeField> ConstraintSystem<S> for BellmanConstraintSystem<'a, C, S> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let target = self.builder.add_virtual_target();
+        let index = self.add_variable(target, f)?;
+        self.aux.push(index);
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _annotation: A, f: F) -> Result<Variable, SynthesisError>
+    where
+        F: FnOnce() -> Result<S, SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Generators can't depend on public input targets directly, so the variable is a virtual
+        // target which is copied to the public input.
+        let target = self.builder.add_virtual_target();
+        let public_input = self.builder.add_public_input();
+        self.builder.copy(target, public_input);
+        let index = self.add_variable(target, f)?;
+        self.inputs.push(index);
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(BellmanLinearCombination<S>) -> BellmanLinearCombination<S>,
+        LB: FnOnce(BellmanLinearCombination<S>) -> BellmanLinearCombination<S>,
+        LC: FnOnce(BellmanLinearCombination<S>) -> BellmanLinearCombination<S>,
+    {
+        let a = self.import_linear_combination(a(BellmanLinearCombination::zero()));
+        let b = self.import_linear_combination(b(BellmanLinearCombination::zero()));
+        let c = self.import_linear_combination(c(BellmanLinearCombination::zero()));
+        enforce_constraint(self.builder, &self.variables, &a, &b, &c);
+    }
+
+    fn push_namespace<NR, N>(&mut self, _name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+/// Synthesizes a bellman circuit, returning our circuit along with the inputs of witness
+/// generation, which are empty unless the bellman circuit has a witness.
+pub fn synthesize_bellman<C: HaloCurve, S: PrimeField, B: BellmanCircuit<S>>(
+    circuit: B,
+    security_bits: usize,
+) -> Result<(Circuit<C>, PartialWitness<C::ScalarField>)> {
+    let mut builder = CircuitBuilder::<C>::new(security_bits);
+    let mut cs = BellmanConstraintSystem::<C, S>::new(&mut builder)?;
+    circuit.synthesize(&mut cs)?;
+    let partial_witness = cs.into_partial_witness();
+    Ok((builder.build(), partial_witness))
+}
+
+/// Converts an element of a bellman field to the same element of our field.
+fn field_from_bellman<S: PrimeField, F: Field>(x: S) -> Result<F> {
+    field_from_le_bytes(x.to_repr().as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use ::bellman::{Circuit as BellmanCircuit, ConstraintSystem, SynthesisError};
+    use anyhow::Result;
+    use ff::{Field as _, PrimeField};
+
+    use crate::bellman_adapter::{synthesize_bellman, BellmanConstraintSystem};
+    use crate::{verify_proof, CircuitBuilder, Curve, Field, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// Tweedledee's scalar field, as a bellman field.
+    #[derive(PrimeField)]
+    #[PrimeFieldModulus = "28948022309329048855892746252171976963322203655955319056773317069363642105857"]
+    #[PrimeFieldGenerator = "5"]
+    #[PrimeFieldReprEndianness = "little"]
+    struct Fp([u64; 4]);
+
+    /// Proves knowledge of `x` such that `x^3 + x + 5 = out`, with `out` public.
+    struct Cubic {
+        x: Option<Fp>,
+    }
+
+    impl BellmanCircuit<Fp> for Cubic {
+        fn synthesize<CS: ConstraintSystem<Fp>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+            let x_value = self.x;
+            let x = cs.alloc(|| "x", || x_value.ok_or(SynthesisError::AssignmentMissing))?;
+            let x_sq_value = x_value.map(|x| x.square());
+            let x_sq = cs.alloc(|| "x^2", || x_sq_value.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "x * x = x^2", |lc| lc + x, |lc| lc + x, |lc| lc + x_sq);
+            let x_cube_value = x_sq_value.zip(x_value).map(|(x_sq, x)| x_sq * x);
+            let x_cube = cs.alloc(|| "x^3", || x_cube_value.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(|| "x^2 * x = x^3", |lc| lc + x_sq, |lc| lc + x, |lc| lc + x_cube);
+            let out_value = x_cube_value.zip(x_value).map(|(x_cube, x)| x_cube + x + Fp::from(5));
+            let out = cs.alloc_input(|| "out", || out_value.ok_or(SynthesisError::AssignmentMissing))?;
+            cs.enforce(
+                || "(x^3 + x + 5) * 1 = out",
+                |lc| lc + x_cube + x + (Fp::from(5), CS::one()),
+                |lc| lc + CS::one(),
+                |lc| lc + out,
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_synthesize_bellman() -> Result<()> {
+        let (circuit, inputs) = synthesize_bellman::<Tweedledee, Fp, _>(Cubic { x: Some(Fp::from(3)) }, 128)?;
+        let witness = circuit.generate_witness(inputs)?;
+        let public_inputs = circuit.get_public_inputs(&witness);
+        assert_eq!(public_inputs, vec![F::from_canonical_u64(35)]);
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+
+        // Without a witness, the same circuit is synthesized.
+        let (circuit_without_witness, _) = synthesize_bellman::<Tweedledee, Fp, _>(Cubic { x: None }, 128)?;
+        assert_eq!(circuit_without_witness.to_vk(), circuit.to_vk());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrong_field() {
+        let mut builder = CircuitBuilder::<Tweedledum>::new(128);
+        assert!(BellmanConstraintSystem::<Tweedledum, Fp>::new(&mut builder).is_err());
+    }
+}
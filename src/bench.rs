@@ -0,0 +1,260 @@
+//! Canonical circuits and timing helpers for benchmarking, so that the effect of changes to field
+//! arithmetic, FFTs or MSMs on proving and verification can be measured with one command:
+//!
+//! ```sh
+//! cargo run --release --bin bench_suite
+//! ```
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::{blake_hash_usize_to_curve, cached_fft_precomputation, fft_with_precomputation_power_of_2, merkle_root_from_proof, msm_execute_parallel, msm_precompute, recursive_verification_circuit, verify_proof, AffinePoint, BufferGate, Circuit, CircuitBuilder, Curve, Field, HaloCurve, MerkleProof, PartialWitness, Tweedledee, Tweedledum, Witness, RESCUE_SPONGE_WIDTH};
+
+/// A circuit, along with a witness which satisfies it.
+pub struct BenchCircuit<C: HaloCurve> {
+    pub name: String,
+    pub circuit: Circuit<C>,
+    pub witness: Witness<C::ScalarField>,
+}
+
+/// A circuit which applies the Rescue permutation `k` times in sequence.
+pub fn rescue_circuit<C: HaloCurve>(k: usize, security_bits: usize) -> Result<BenchCircuit<C>> {
+    let mut builder = CircuitBuilder::<C>::new(security_bits);
+    let inputs = builder.add_virtual_targets(RESCUE_SPONGE_WIDTH);
+    let mut state = inputs.clone();
+    for _ in 0..k {
+        state = builder.rescue_permutation(&state);
+    }
+    let circuit = builder.build();
+
+    let mut partial_witness = PartialWitness::new();
+    let input_values = (0..RESCUE_SPONGE_WIDTH).map(|_| C::ScalarField::rand()).collect::<Vec<_>>();
+    partial_witness.set_targets(&inputs, &input_values);
+    let witness = circuit.generate_witness(partial_witness)?;
+    Ok(BenchCircuit {
+        name: format!("Rescue x{}", k),
+        circuit,
+        witness,
+    })
+}
+
+/// A circuit which verifies a Merkle path of the given depth, in a tree hashed with Rescue.
+pub fn merkle_circuit<C: HaloCurve>(depth: usize, security_bits: usize) -> Result<BenchCircuit<C>> {
+    let leaf = C::ScalarField::rand();
+    let index = (1 << depth) - 1;
+    let proof = MerkleProof {
+        siblings: (0..depth).map(|_| C::ScalarField::rand()).collect(),
+    };
+    let root = merkle_root_from_proof(leaf, index, &proof, security_bits);
+
+    let mut builder = CircuitBuilder::<C>::new(security_bits);
+    let leaf_target = builder.add_virtual_target();
+    let index_bits = builder.add_virtual_targets(depth);
+    let siblings = builder.add_virtual_targets(depth);
+    let root_target = builder.constant_wire(root);
+    builder.verify_merkle_proof(leaf_target, &index_bits, &siblings, root_target);
+    let circuit = builder.build();
+
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(leaf_target, leaf);
+    partial_witness.set_targets(&index_bits, &vec![C::ScalarField::ONE; depth]);
+    partial_witness.set_targets(&siblings, &proof.siblings);
+    let witness = circuit.generate_witness(partial_witness)?;
+    Ok(BenchCircuit {
+        name: format!("Merkle path of depth {}", depth),
+        circuit,
+        witness,
+    })
+}
+
+/// A circuit which verifies a proof of a trivial circuit with `2^inner_degree_pow` gates, along
+/// with a witness containing such a proof.
+pub fn recursive_verifier_circuit(
+    inner_degree_pow: usize,
+    security_bits: usize,
+) -> Result<BenchCircuit<Tweedledee>> {
+    let mut inner_builder = CircuitBuilder::<Tweedledum>::new(security_bits);
+    while inner_builder.num_gates() < (1 << inner_degree_pow) - 3 {
+        inner_builder.add_gate_no_constants(BufferGate::new(inner_builder.num_gates()));
+    }
+    let inner_circuit = inner_builder.build();
+    let inner_witness = inner_circuit.generate_witness(PartialWitness::new())?;
+    let inner_proof = inner_circuit.generate_proof::<Tweedledee>(&inner_witness, &[])?;
+
+    let recursion_circuit =
+        recursive_verification_circuit::<Tweedledee, Tweedledum>(&inner_circuit.to_vk(), 0, None);
+    let mut partial_witness = PartialWitness::new();
+    recursion_circuit.populate_witness(&mut partial_witness, &inner_proof, &[], &[])?;
+    let witness = recursion_circuit.circuit.generate_witness(partial_witness)?;
+    Ok(BenchCircuit {
+        name: format!("Recursive verifier of 2^{} gates", inner_degree_pow),
+        circuit: recursion_circuit.circuit,
+        witness,
+    })
+}
+
+/// The durations of repeated runs of some operation.
+#[derive(Clone, Debug)]
+pub struct Timing {
+    pub name: String,
+    pub median: Duration,
+    pub min: Duration,
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<56} median {:>10.3}ms   min {:>10.3}ms",
+            self.name,
+            self.median.as_secs_f64() * 1000.0,
+            self.min.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+/// Runs `op` once to warm up caches, such as FFT precomputations, then `iterations` more times,
+/// returning the median and minimum durations of the latter.
+pub fn time<R, OP: FnMut() -> R>(name: &str, iterations: usize, mut op: OP) -> Timing {
+    assert!(iterations > 0, "Need at least one iteration");
+    op();
+    let mut durations = (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            op();
+            start.elapsed()
+        })
+        .collect::<Vec<_>>();
+    durations.sort();
+    Timing {
+        name: name.to_string(),
+        median: durations[durations.len() / 2],
+        min: durations[0],
+    }
+}
+
+/// Times proving and verification of the given circuit.
+pub fn time_circuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    bench: &BenchCircuit<C>,
+    iterations: usize,
+) -> Result<Vec<Timing>> {
+    let name = format!("{} ({} gates)", bench.name, bench.circuit.degree());
+    let proof = bench.circuit.generate_proof::<InnerC>(&bench.witness, &[])?;
+    let public_inputs = bench.circuit.get_public_inputs(&bench.witness);
+    let vk = bench.circuit.to_vk();
+    verify_proof::<C, InnerC>(&public_inputs, &proof, &[], &vk, true)?;
+
+    Ok(vec![
+        time(&format!("{}: prove", name), iterations, || {
+            bench.circuit.generate_proof::<InnerC>(&bench.witness, &[])
+        }),
+        time(&format!("{}: verify", name), iterations, || {
+            verify_proof::<C, InnerC>(&public_inputs, &proof, &[], &vk, true)
+        }),
+    ])
+}
+
+/// The sizes of the benchmarks run by `run_suite`.
+#[derive(Clone, Debug)]
+pub struct SuiteConfig {
+    pub iterations: usize,
+    pub security_bits: usize,
+    pub field_ops: usize,
+    pub fft_degree_pow: usize,
+    pub msm_size: usize,
+    pub msm_window: usize,
+    pub rescue_permutations: usize,
+    pub merkle_depth: usize,
+    /// `log_2` of the degree of the proof verified by the recursive verifier. With `None`, the
+    /// recursive verifier, which is by far the slowest benchmark, is skipped.
+    pub recursion_inner_degree_pow: Option<usize>,
+}
+
+impl Default for SuiteConfig {
+    fn default() -> Self {
+        SuiteConfig {
+            iterations: 5,
+            security_bits: 128,
+            field_ops: 1 << 20,
+            fft_degree_pow: 16,
+            msm_size: 1 << 14,
+            msm_window: 11,
+            rescue_permutations: 64,
+            merkle_depth: 32,
+            recursion_inner_degree_pow: Some(10),
+        }
+    }
+}
+
+/// Times field arithmetic, FFTs and MSMs over Tweedledee, then proving and verification of each
+/// canonical circuit, calling `report` with each timing as soon as it's measured.
+pub fn run_suite<RE: FnMut(&Timing)>(config: &SuiteConfig, mut report: RE) -> Result<Vec<Timing>> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut timings = Vec::new();
+    let mut record = |timing: Timing| {
+        report(&timing);
+        timings.push(timing);
+    };
+
+    let xs = (0..config.field_ops).map(|_| F::rand()).collect::<Vec<_>>();
+    record(time(&format!("{} field multiplications", config.field_ops), config.iterations, || {
+        xs.iter().fold(F::ONE, |acc, &x| acc * x)
+    }));
+    record(time(&format!("{} field inversions", config.field_ops / 64), config.iterations, || {
+        xs[..config.field_ops / 64].iter().map(|x| x.multiplicative_inverse()).collect::<Vec<_>>()
+    }));
+
+    let coefficients = (0..1 << config.fft_degree_pow).map(|_| F::rand()).collect::<Vec<_>>();
+    let precomputation = cached_fft_precomputation(coefficients.len());
+    record(time(&format!("FFT of 2^{} points", config.fft_degree_pow), config.iterations, || {
+        fft_with_precomputation_power_of_2(&coefficients, &precomputation)
+    }));
+
+    let generators = (0..config.msm_size).map(blake_hash_usize_to_curve::<Tweedledee>).collect::<Vec<_>>();
+    let msm_precomputation = msm_precompute(&AffinePoint::batch_to_projective(&generators), config.msm_window);
+    let scalars = (0..config.msm_size).map(|_| F::rand()).collect::<Vec<_>>();
+    record(time(&format!("MSM of {} points", config.msm_size), config.iterations, || {
+        msm_execute_parallel(&msm_precomputation, &scalars)
+    }));
+
+    let rescue = rescue_circuit::<Tweedledee>(config.rescue_permutations, config.security_bits)?;
+    time_circuit::<Tweedledee, Tweedledum>(&rescue, config.iterations)?.into_iter().for_each(&mut record);
+    let merkle = merkle_circuit::<Tweedledee>(config.merkle_depth, config.security_bits)?;
+    time_circuit::<Tweedledee, Tweedledum>(&merkle, config.iterations)?.into_iter().for_each(&mut record);
+    if let Some(inner_degree_pow) = config.recursion_inner_degree_pow {
+        let recursion = recursive_verifier_circuit(inner_degree_pow, config.security_bits)?;
+        time_circuit::<Tweedledee, Tweedledum>(&recursion, config.iterations)?.into_iter().for_each(&mut record);
+    }
+
+    Ok(timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::bench::{run_suite, SuiteConfig};
+
+    #[test]
+    fn test_run_suite() -> Result<()> {
+        let config = SuiteConfig {
+            iterations: 1,
+            security_bits: 128,
+            field_ops: 1 << 8,
+            fft_degree_pow: 8,
+            msm_size: 1 << 4,
+            msm_window: 4,
+            rescue_permutations: 2,
+            merkle_depth: 2,
+            recursion_inner_degree_pow: None,
+        };
+        let mut reported = 0;
+        let timings = run_suite(&config, |_| reported += 1)?;
+        assert_eq!(timings.len(), 8);
+        assert_eq!(reported, 8);
+        assert!(timings.iter().all(|t| t.min <= t.median));
+        Ok(())
+    }
+}
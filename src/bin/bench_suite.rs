@@ -0,0 +1,19 @@
+//! Runs the benchmark suite in `plonky::bench`, printing each timing as it's measured. The first
+//! argument, if any, is the number of iterations of each benchmark. Set `SKIP_RECURSION` to skip
+//! the recursive verifier, which takes much longer than the other benchmarks.
+
+use anyhow::{Context, Result};
+
+use plonky::bench::{run_suite, SuiteConfig};
+
+fn main() -> Result<()> {
+    let mut config = SuiteConfig::default();
+    if let Some(iterations) = std::env::args().nth(1) {
+        config.iterations = iterations.parse().context("Invalid number of iterations")?;
+    }
+    if std::env::var_os("SKIP_RECURSION").is_some() {
+        config.recursion_inner_degree_pow = None;
+    }
+    run_suite(&config, |timing| println!("{}", timing))?;
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! A command-line tool for compiling zkInterface circuits, and proving and verifying with them.
+//!
+//! Circuits are JSON objects with the zkInterface `header` and `constraint_system`, and witnesses
+//! are JSON objects with the values of the `instance_variables` (the public inputs) and the
+//! `assigned_variables` (the private variables). Circuits are over Tweedledee's scalar field.
+
+use std::fs;
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde::{Deserialize, Serialize};
+
+use plonky::zkinterface::{CircuitHeader, ConstraintSystem, Variables, Witness, ZkifCircuit};
+use plonky::{public_inputs_from_json, public_inputs_to_json, verify, Circuit, Proof, ProverKey, Tweedledee, Tweedledum, VerificationKey};
+
+#[derive(Serialize, Deserialize)]
+struct CircuitFile {
+    header: CircuitHeader,
+    constraint_system: ConstraintSystem,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WitnessFile {
+    instance_variables: Variables,
+    assigned_variables: Variables,
+}
+
+fn main() {
+    let circuit_arg = Arg::with_name("circuit")
+        .help("The circuit, as zkInterface JSON")
+        .required(true);
+    let security_bits_arg = Arg::with_name("security-bits")
+        .long("security-bits")
+        .takes_value(true)
+        .default_value("128");
+    let matches = App::new("plonky")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Compiles circuits, and generates and verifies proofs")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("compile")
+                .about("Writes the prover key and verification key of a circuit")
+                .arg(circuit_arg.clone())
+                .arg(security_bits_arg.clone())
+                .arg(path_arg("prover-key", "The prover key, as CBOR"))
+                .arg(path_arg("verification-key", "The verification key, as JSON")),
+        )
+        .subcommand(
+            SubCommand::with_name("prove")
+                .about("Generates a proof, and writes it along with the public inputs")
+                .arg(circuit_arg)
+                .arg(
+                    Arg::with_name("witness")
+                        .help("The values of the circuit's variables, as JSON")
+                        .required(true),
+                )
+                .arg(security_bits_arg)
+                .arg(path_arg("proof", "The proof, as JSON"))
+                .arg(path_arg("public-inputs", "The public inputs, as JSON")),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Verifies a proof, exiting with status 1 if it's invalid")
+                .arg(path_arg("verification-key", "The verification key, as JSON"))
+                .arg(path_arg("public-inputs", "The public inputs, as JSON"))
+                .arg(path_arg("proof", "The proof, as JSON")),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("compile", Some(matches)) => compile(matches),
+        ("prove", Some(matches)) => prove(matches),
+        ("verify", Some(matches)) => verify_command(matches),
+        _ => unreachable!("A subcommand is required"),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {:#}", e);
+        process::exit(1);
+    }
+}
+
+/// A required option giving the path of a file.
+fn path_arg<'a>(name: &'a str, help: &'a str) -> Arg<'a, 'a> {
+    Arg::with_name(name).long(name).help(help).takes_value(true).required(true)
+}
+
+fn read(path: &str) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))
+}
+
+fn write(path: &str, contents: &[u8]) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path))
+}
+
+fn load_circuit(matches: &ArgMatches) -> Result<(CircuitFile, ZkifCircuit<Tweedledee>)> {
+    let path = matches.value_of("circuit").expect("Required");
+    let file: CircuitFile = serde_json::from_str(&read(path)?)
+        .with_context(|| format!("Failed to parse the circuit in {}", path))?;
+    let security_bits = matches
+        .value_of("security-bits")
+        .expect("Has a default")
+        .parse()
+        .context("Invalid number of security bits")?;
+    let circuit = ZkifCircuit::import(&file.header, &file.constraint_system, security_bits)?;
+    Ok((file, circuit))
+}
+
+fn compile(matches: &ArgMatches) -> Result<()> {
+    let (_, zkif_circuit) = load_circuit(matches)?;
+    let circuit: &Circuit<Tweedledee> = &zkif_circuit.circuit;
+    let prover_key: &ProverKey<Tweedledee> = &circuit.prover_key;
+    write(matches.value_of("prover-key").expect("Required"), &serde_cbor::to_vec(prover_key)?)?;
+    let vk = circuit.to_vk();
+    write(matches.value_of("verification-key").expect("Required"), vk.to_json().as_bytes())
+}
+
+fn prove(matches: &ArgMatches) -> Result<()> {
+    let (file, zkif_circuit) = load_circuit(matches)?;
+    let path = matches.value_of("witness").expect("Required");
+    let witness_file: WitnessFile = serde_json::from_str(&read(path)?)
+        .with_context(|| format!("Failed to parse the witness in {}", path))?;
+    let header = CircuitHeader {
+        instance_variables: witness_file.instance_variables,
+        ..file.header
+    };
+    let witness = Witness {
+        assigned_variables: witness_file.assigned_variables,
+    };
+
+    let circuit = &zkif_circuit.circuit;
+    let inputs = zkif_circuit.partial_witness(&header, &witness)?;
+    let witness = circuit.generate_witness(inputs)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    write(matches.value_of("proof").expect("Required"), proof.to_json().as_bytes())?;
+    let public_inputs = circuit.get_public_inputs(&witness);
+    write(
+        matches.value_of("public-inputs").expect("Required"),
+        public_inputs_to_json(&public_inputs).as_bytes(),
+    )
+}
+
+fn verify_command(matches: &ArgMatches) -> Result<()> {
+    let vk = VerificationKey::<Tweedledee>::from_json(&read(
+        matches.value_of("verification-key").expect("Required"),
+    )?)?;
+    let public_inputs = public_inputs_from_json(&read(
+        matches.value_of("public-inputs").expect("Required"),
+    )?)?;
+    let proof = Proof::<Tweedledee>::from_json(&read(matches.value_of("proof").expect("Required"))?)?;
+    verify::<Tweedledee, Tweedledum>(&vk, &public_inputs, &proof)
+        .map_err(|e| anyhow::anyhow!("The proof is invalid: {}", e))?;
+    println!("The proof is valid");
+    Ok(())
+}
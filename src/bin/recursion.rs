@@ -1,7 +1,7 @@
 use anyhow::Result;
 use std::time::Instant;
 
-use plonky::{recursive_verification_circuit, verify_proof, BufferGate, Circuit, CircuitBuilder, PartialWitness, Tweedledee, Tweedledum};
+use plonky::{finalize_recursion, recursive_verification_circuit, verify_proof, BufferGate, Circuit, CircuitBuilder, PartialWitness, Tweedledee, Tweedledum};
 
 const INNER_PROOF_DEGREE_POW: usize = 14;
 const INNER_PROOF_DEGREE: usize = 1 << INNER_PROOF_DEGREE_POW;
@@ -16,7 +16,7 @@ fn main() -> Result<()> {
 
     println!("Generating inner witness");
     let start = Instant::now();
-    let inner_witness = inner_circuit.generate_witness(PartialWitness::new());
+    let inner_witness = inner_circuit.generate_witness(PartialWitness::new())?;
     println!("Finished in {}s", start.elapsed().as_secs_f64());
     println!();
 
@@ -24,7 +24,7 @@ fn main() -> Result<()> {
     let start = Instant::now();
     let old_proofs = vec![];
     let inner_proof = inner_circuit
-        .generate_proof::<Tweedledee>(&inner_witness, &old_proofs, true)
+        .generate_proof::<Tweedledee>(&inner_witness, &old_proofs)
         .unwrap();
     println!("Finished in {}s", start.elapsed().as_secs_f64());
     println!();
@@ -38,37 +38,19 @@ fn main() -> Result<()> {
 
     println!("Generating recursion circuit...");
     let start = Instant::now();
-    let recursion_circuit = recursive_verification_circuit::<Tweedledee, Tweedledum>(
-        // INNER_PROOF_DEGREE_POW,
-        inner_proof.halo_l.len(),
-        SECURITY_BITS,
-        0,
-        old_proofs.len(),
-    );
+    let recursion_circuit =
+        recursive_verification_circuit::<Tweedledee, Tweedledum>(&inner_vk, old_proofs.len(), None);
     println!("Finished in {}s", start.elapsed().as_secs_f64());
     println!("Gate count: {}", recursion_circuit.circuit.degree());
     println!();
 
     // Populate inputs.
     let mut recursion_inputs = PartialWitness::new();
-    if let Err(e) = recursion_circuit
-        .proof
-        .populate_witness(&mut recursion_inputs, inner_proof)
-    {
-        panic!("Failed to populate inputs: {:?}", e);
-    }
-
-    // Populate old proofs inputs.
-    old_proofs
-        .iter()
-        .zip(recursion_circuit.old_proofs.iter())
-        .for_each(|(p, pt)| {
-            pt.populate_witness(&mut recursion_inputs, p).unwrap();
-        });
+    recursion_circuit.populate_witness(&mut recursion_inputs, &inner_proof, &[], &old_proofs)?;
 
     println!("Generating recursion witness...");
     let start = Instant::now();
-    let recursion_witness = recursion_circuit.circuit.generate_witness(recursion_inputs);
+    let recursion_witness = recursion_circuit.circuit.generate_witness(recursion_inputs)?;
     println!("Finished in {}s", start.elapsed().as_secs_f64());
     println!();
 
@@ -76,8 +58,8 @@ fn main() -> Result<()> {
     let start = Instant::now();
     let proof = recursion_circuit
         .circuit
-        // .generate_proof::<Tweedledum>(recursion_witness, &[inner_proof.into()], true)
-        .generate_proof::<Tweedledum>(&recursion_witness, &[], true)
+        // .generate_proof::<Tweedledum>(recursion_witness, &[inner_proof.into()])
+        .generate_proof::<Tweedledum>(&recursion_witness, &[])
         .unwrap();
     println!("Finished in {}s", start.elapsed().as_secs_f64());
     println!();
@@ -94,6 +76,12 @@ fn main() -> Result<()> {
     let vk = recursion_circuit.circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&pis, &proof, &[], &vk, true)?;
     println!("Finished in {}s", start.elapsed().as_secs_f64());
+    println!();
+
+    println!("Finalizing recursion...");
+    let start = Instant::now();
+    finalize_recursion::<Tweedledum, Tweedledee>(&pis, recursion_circuit.layout, &inner_vk)?;
+    println!("Finished in {}s", start.elapsed().as_secs_f64());
     Ok(())
 }
 
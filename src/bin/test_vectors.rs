@@ -0,0 +1,21 @@
+//! Writes the JSON test vectors of `plonky::test_vectors` to the directory given as the first
+//! argument, defaulting to `test_vectors`, with one file per set of vectors.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use plonky::test_vectors::all_test_vectors;
+
+fn main() -> Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| "test_vectors".to_string()));
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for (name, vectors) in all_test_vectors()? {
+        let path = dir.join(format!("{}.json", name));
+        fs::write(&path, serde_json::to_string_pretty(&vectors)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
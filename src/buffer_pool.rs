@@ -0,0 +1,97 @@
+//! A process-wide pool of field element buffers, so that the many vectors of size 8n which the
+//! prover allocates for FFTs and LDEs can be reused across proving phases and across proofs, rather
+//! than going back to the allocator each time.
+
+use crate::Field;
+use once_cell::sync::Lazy;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::Mutex;
+
+/// The maximum number of idle buffers of any one field and length which are kept. Buffers which
+/// are recycled beyond this are freed, which bounds the memory held by the pool to a small multiple
+/// of what a single proof uses.
+pub const MAX_POOLED_BUFFERS_PER_SIZE: usize = 64;
+
+/// Buffers of any field, keyed by the field's `TypeId` and the buffers' length.
+type Buffers = HashMap<(TypeId, usize), Box<dyn Any + Send>>;
+
+/// Idle buffers, keyed by field and length. The values are `Vec<Vec<F>>`s for the field with the
+/// given `TypeId`.
+static BUFFER_POOL: Lazy<Mutex<Buffers>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Returns a buffer of `len` field elements, reusing a recycled one if possible. The buffer's
+/// contents are unspecified, so callers must overwrite every element.
+pub fn take_buffer<F: Field>(len: usize) -> Vec<F> {
+    let recycled = BUFFER_POOL
+        .lock()
+        .unwrap()
+        .get_mut(&(TypeId::of::<F>(), len))
+        .and_then(|buffers| pool_of::<F>(buffers).pop());
+    recycled.unwrap_or_else(|| vec![F::ZERO; len])
+}
+
+/// Returns a buffer to the pool, so that a later `take_buffer` of the same length can reuse it.
+pub fn recycle_buffer<F: Field>(buffer: Vec<F>) {
+    if buffer.is_empty() {
+        return;
+    }
+    let mut pool = BUFFER_POOL.lock().unwrap();
+    let buffers = pool
+        .entry((TypeId::of::<F>(), buffer.len()))
+        .or_insert_with(|| Box::new(Vec::<Vec<F>>::new()));
+    let buffers = pool_of::<F>(buffers);
+    if buffers.len() < MAX_POOLED_BUFFERS_PER_SIZE {
+        buffers.push(buffer);
+    }
+}
+
+/// Returns each of the given buffers to the pool.
+pub fn recycle_buffers<F: Field>(buffers: Vec<Vec<F>>) {
+    buffers.into_iter().for_each(recycle_buffer);
+}
+
+/// Frees all idle buffers, e.g. once a prover is done proving for a while.
+pub fn clear_buffer_pool() {
+    BUFFER_POOL.lock().unwrap().clear();
+}
+
+/// The total size, in bytes, of the idle buffers of field `F`.
+pub fn buffer_pool_size_bytes<F: Field>() -> usize {
+    let mut pool = BUFFER_POOL.lock().unwrap();
+    pool.iter_mut()
+        .filter(|((type_id, _), _)| *type_id == TypeId::of::<F>())
+        .map(|((_, len), buffers)| pool_of::<F>(buffers).len() * len * size_of::<F>())
+        .sum()
+}
+
+fn pool_of<F: Field>(buffers: &mut Box<dyn Any + Send>) -> &mut Vec<Vec<F>> {
+    buffers
+        .downcast_mut::<Vec<Vec<F>>>()
+        .expect("Pooled buffers have the wrong type")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{recycle_buffer, take_buffer, Field, TweedledeeBase, TweedledumBase};
+
+    #[test]
+    fn test_buffers_are_reused() {
+        // Other tests share the pool, so use a length which only this test uses.
+        let len = 12345;
+        let mut buffer = take_buffer::<TweedledeeBase>(len);
+        assert_eq!(buffer.len(), len);
+        buffer[0] = TweedledeeBase::ONE;
+        let ptr = buffer.as_ptr();
+        recycle_buffer(buffer);
+
+        // A buffer of another field or length isn't reused.
+        assert_eq!(take_buffer::<TweedledumBase>(len).len(), len);
+        assert_eq!(take_buffer::<TweedledeeBase>(len + 1).len(), len + 1);
+
+        let buffer = take_buffer::<TweedledeeBase>(len);
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(buffer[0], TweedledeeBase::ONE);
+    }
+}
@@ -0,0 +1,397 @@
+//! Import of Circom circuits and witnesses, from the binary `.r1cs` files written by the Circom
+//! compiler and the `.wtns` files written by its witness generators and snarkjs.
+//!
+//! Both formats are a magic string and a version, followed by a list of typed sections. Integers
+//! are little-endian, and field elements are little-endian integers of the length given in the
+//! file's header. Wire 0 is the constant one, followed by the public outputs, the public inputs,
+//! the private inputs, and finally the internal signals.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use anyhow::{anyhow, bail, ensure, Result};
+
+use crate::zkinterface::field_from_le_bytes;
+use crate::{Field, HaloCurve, LinearCombination, PartialWitness, R1cs, R1csCircuit};
+
+const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+const WTNS_MAGIC: &[u8; 4] = b"wtns";
+
+const R1CS_HEADER_SECTION: u32 = 1;
+const R1CS_CONSTRAINTS_SECTION: u32 = 2;
+const R1CS_WIRE_TO_LABEL_SECTION: u32 = 3;
+const WTNS_HEADER_SECTION: u32 = 1;
+const WTNS_VALUES_SECTION: u32 = 2;
+
+/// An R1CS instance read from a `.r1cs` file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CircomR1cs<F: Field> {
+    /// The constraints, in which wire `i` is variable `i`. The public signals are the public
+    /// outputs followed by the public inputs.
+    pub r1cs: R1cs<F>,
+    pub num_public_outputs: usize,
+    pub num_public_inputs: usize,
+    pub num_private_inputs: usize,
+    /// The label of the signal behind each wire, if the file maps wires to labels.
+    pub wire_labels: Option<Vec<u64>>,
+}
+
+impl<F: Field> CircomR1cs<F> {
+    /// Decodes the contents of a `.r1cs` file, which must be over `F`.
+    pub fn read(bytes: &[u8]) -> Result<Self> {
+        let sections = read_sections(bytes, R1CS_MAGIC)?;
+
+        let mut header = Reader(section(&sections, R1CS_HEADER_SECTION)?);
+        let field_size = header.read_u32()? as usize;
+        check_prime::<F>(header.read_bytes(field_size)?)?;
+        let num_wires = header.read_u32()? as usize;
+        let num_public_outputs = header.read_u32()? as usize;
+        let num_public_inputs = header.read_u32()? as usize;
+        let num_private_inputs = header.read_u32()? as usize;
+        let _num_labels = header.read_u64()?;
+        let num_constraints = header.read_u32()? as usize;
+        header.finish()?;
+
+        let num_public_signals = num_public_outputs + num_public_inputs;
+        ensure!(
+            1 + num_public_signals + num_private_inputs <= num_wires,
+            "There are more signals than wires"
+        );
+
+        let mut constraints = Reader(section(&sections, R1CS_CONSTRAINTS_SECTION)?);
+        // The header's counts are untrusted, so they only bound the loops, not allocations; a
+        // malformed file runs out of bytes instead of memory.
+        let (mut a, mut b, mut c) = (Vec::new(), Vec::new(), Vec::new());
+        for _ in 0..num_constraints {
+            a.push(constraints.read_linear_combination(field_size)?);
+            b.push(constraints.read_linear_combination(field_size)?);
+            c.push(constraints.read_linear_combination(field_size)?);
+        }
+        constraints.finish()?;
+
+        let wire_labels = match sections.get(&R1CS_WIRE_TO_LABEL_SECTION) {
+            Some(&bytes) => {
+                let mut labels = Reader(bytes);
+                let wire_labels = (0..num_wires)
+                    .map(|_| labels.read_u64())
+                    .collect::<Result<Vec<_>>>()?;
+                labels.finish()?;
+                Some(wire_labels)
+            }
+            None => None,
+        };
+
+        // Internal signals are private too, as far as the R1CS is concerned.
+        let r1cs = R1cs {
+            num_public_inputs: num_public_signals,
+            num_private_inputs: num_wires - 1 - num_public_signals,
+            a,
+            b,
+            c,
+        };
+        r1cs.validate()?;
+        Ok(CircomR1cs {
+            r1cs,
+            num_public_outputs,
+            num_public_inputs,
+            num_private_inputs,
+            wire_labels,
+        })
+    }
+
+    pub fn num_wires(&self) -> usize {
+        self.r1cs.num_variables()
+    }
+}
+
+/// Decodes the contents of a `.wtns` file, which must be over `F`. The result holds the value of
+/// every wire, starting with the constant one.
+pub fn read_wtns<F: Field>(bytes: &[u8]) -> Result<Vec<F>> {
+    let sections = read_sections(bytes, WTNS_MAGIC)?;
+
+    let mut header = Reader(section(&sections, WTNS_HEADER_SECTION)?);
+    let field_size = header.read_u32()? as usize;
+    check_prime::<F>(header.read_bytes(field_size)?)?;
+    let num_values = header.read_u32()? as usize;
+    header.finish()?;
+
+    let mut values = Reader(section(&sections, WTNS_VALUES_SECTION)?);
+    let wire_values = (0..num_values)
+        .map(|_| values.read_field(field_size))
+        .collect::<Result<Vec<F>>>()?;
+    values.finish()?;
+
+    ensure!(
+        wire_values.first() == Some(&F::ONE),
+        "The value of wire 0 must be one"
+    );
+    Ok(wire_values)
+}
+
+/// A circuit imported from a `.r1cs` file. The public outputs and public inputs become the
+/// circuit's public inputs, in that order.
+pub struct CircomCircuit<C: HaloCurve> {
+    pub r1cs_circuit: R1csCircuit<C>,
+}
+
+impl<C: HaloCurve> CircomCircuit<C> {
+    pub fn import(circom_r1cs: &CircomR1cs<C::ScalarField>, security_bits: usize) -> Result<Self> {
+        Ok(CircomCircuit {
+            r1cs_circuit: R1csCircuit::new(&circom_r1cs.r1cs, security_bits)?,
+        })
+    }
+
+    /// Returns the inputs of witness generation, given the value of every wire, as read from a
+    /// `.wtns` file.
+    pub fn partial_witness(
+        &self,
+        wire_values: &[C::ScalarField],
+    ) -> Result<PartialWitness<C::ScalarField>> {
+        let num_wires = self.r1cs_circuit.variables.len();
+        ensure!(
+            wire_values.len() == num_wires,
+            "{} wire values for {} wires",
+            wire_values.len(),
+            num_wires
+        );
+        ensure!(
+            wire_values[0] == C::ScalarField::ONE,
+            "The value of wire 0 must be one"
+        );
+        let num_public_signals = self.r1cs_circuit.num_public_inputs();
+        self.r1cs_circuit.partial_witness(
+            &wire_values[1..1 + num_public_signals],
+            &wire_values[1 + num_public_signals..],
+        )
+    }
+}
+
+/// Checks the magic string and version, and returns the contents of each section by type.
+fn read_sections<'a>(bytes: &'a [u8], magic: &[u8; 4]) -> Result<HashMap<u32, &'a [u8]>> {
+    let mut reader = Reader(bytes);
+    ensure!(reader.read_bytes(4)? == magic, "Wrong magic string");
+    let version = reader.read_u32()?;
+    ensure!(
+        version == 1 || version == 2,
+        "Unsupported version {}",
+        version
+    );
+    let num_sections = reader.read_u32()?;
+    let mut sections = HashMap::new();
+    for _ in 0..num_sections {
+        let section_type = reader.read_u32()?;
+        let size = reader.read_u64()? as usize;
+        let contents = reader.read_bytes(size)?;
+        ensure!(
+            sections.insert(section_type, contents).is_none(),
+            "Section {} appears twice",
+            section_type
+        );
+    }
+    reader.finish()?;
+    Ok(sections)
+}
+
+fn section<'a>(sections: &HashMap<u32, &'a [u8]>, section_type: u32) -> Result<&'a [u8]> {
+    sections
+        .get(&section_type)
+        .copied()
+        .ok_or_else(|| anyhow!("Section {} is missing", section_type))
+}
+
+/// Checks that the given little-endian prime is the order of `F`.
+fn check_prime<F: Field>(prime: &[u8]) -> Result<()> {
+    // The prime is odd, so subtracting one only affects its lowest byte.
+    let mut field_maximum = prime.to_vec();
+    match field_maximum.first_mut() {
+        Some(lowest_byte) if *lowest_byte & 1 == 1 => *lowest_byte -= 1,
+        _ => bail!("The field's order is not an odd prime"),
+    }
+    ensure!(
+        field_from_le_bytes::<F>(&field_maximum).ok() == Some(F::NEG_ONE),
+        "The file is over a different field"
+    );
+    Ok(())
+}
+
+/// Reads values from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.0.len() >= len, "Unexpected end of input");
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_field<F: Field>(&mut self, field_size: usize) -> Result<F> {
+        field_from_le_bytes(self.read_bytes(field_size)?)
+    }
+
+    fn read_linear_combination<F: Field>(
+        &mut self,
+        field_size: usize,
+    ) -> Result<LinearCombination<F>> {
+        let num_terms = self.read_u32()?;
+        (0..num_terms)
+            .map(|_| Ok((self.read_u32()? as usize, self.read_field(field_size)?)))
+            .collect()
+    }
+
+    /// Checks that all of the input has been read.
+    fn finish(&self) -> Result<()> {
+        ensure!(self.0.is_empty(), "{} trailing bytes", self.0.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::circom::{read_wtns, CircomCircuit, CircomR1cs};
+    use crate::{verify_proof, Curve, Field, LinearCombination, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// The prime, padded to 32 bytes, as Circom writes it.
+    fn prime() -> Vec<u8> {
+        let mut prime = F::NEG_ONE.to_canonical_u8_vec();
+        prime[0] += 1;
+        prime.resize(32, 0);
+        prime
+    }
+
+    fn field(x: F) -> Vec<u8> {
+        let mut bytes = x.to_canonical_u8_vec();
+        bytes.resize(32, 0);
+        bytes
+    }
+
+    fn file(magic: &[u8], sections: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        bytes.extend(&1u32.to_le_bytes());
+        bytes.extend(&(sections.len() as u32).to_le_bytes());
+        for (section_type, contents) in sections {
+            bytes.extend(&section_type.to_le_bytes());
+            bytes.extend(&(contents.len() as u64).to_le_bytes());
+            bytes.extend(contents);
+        }
+        bytes
+    }
+
+    fn linear_combination(lc: LinearCombination<F>) -> Vec<u8> {
+        let mut bytes = (lc.len() as u32).to_le_bytes().to_vec();
+        for (wire, coefficient) in lc {
+            bytes.extend(&(wire as u32).to_le_bytes());
+            bytes.extend(field(coefficient));
+        }
+        bytes
+    }
+
+    /// `x^3 + x + 5 = out`, with wires `(1, out, x, x^2, x^3)` and `x` a private input. The
+    /// constraints section comes first, which Circom allows.
+    fn cubic_r1cs() -> Vec<u8> {
+        cubic_r1cs_with_num_constraints(3)
+    }
+
+    /// Like `cubic_r1cs`, but with the given number of constraints in the header.
+    fn cubic_r1cs_with_num_constraints(num_constraints: u32) -> Vec<u8> {
+        let mut header = 32u32.to_le_bytes().to_vec();
+        header.extend(prime());
+        for &n in &[5u32, 1, 0, 1] {
+            header.extend(&n.to_le_bytes());
+        }
+        header.extend(&5u64.to_le_bytes());
+        header.extend(&num_constraints.to_le_bytes());
+
+        let mut constraints = Vec::new();
+        let rows = vec![
+            (vec![(2, F::ONE)], vec![(2, F::ONE)], vec![(3, F::ONE)]),
+            (vec![(3, F::ONE)], vec![(2, F::ONE)], vec![(4, F::ONE)]),
+            (
+                vec![(4, F::ONE), (2, F::ONE), (0, F::FIVE)],
+                vec![(0, F::ONE)],
+                vec![(1, F::ONE)],
+            ),
+        ];
+        for (a, b, c) in rows {
+            constraints.extend(linear_combination(a));
+            constraints.extend(linear_combination(b));
+            constraints.extend(linear_combination(c));
+        }
+
+        let labels = (0..5u64).flat_map(|i| i.to_le_bytes().to_vec()).collect();
+        file(b"r1cs", vec![(2, constraints), (1, header), (3, labels)])
+    }
+
+    fn cubic_wtns(x: u64) -> Vec<u8> {
+        let x = F::from_canonical_u64(x);
+        let values = [F::ONE, x.cube() + x + F::FIVE, x, x.square(), x.cube()];
+        let mut header = 32u32.to_le_bytes().to_vec();
+        header.extend(prime());
+        header.extend(&(values.len() as u32).to_le_bytes());
+        let values = values.iter().flat_map(|&v| field(v)).collect();
+        file(b"wtns", vec![(1, header), (2, values)])
+    }
+
+    #[test]
+    fn test_import_cubic() -> Result<()> {
+        let circom_r1cs = CircomR1cs::<F>::read(&cubic_r1cs())?;
+        assert_eq!(circom_r1cs.num_wires(), 5);
+        assert_eq!(circom_r1cs.num_public_outputs, 1);
+        assert_eq!(circom_r1cs.wire_labels, Some(vec![0, 1, 2, 3, 4]));
+
+        let wire_values = read_wtns::<F>(&cubic_wtns(3))?;
+        let public_inputs = vec![F::from_canonical_u64(35)];
+        assert!(circom_r1cs
+            .r1cs
+            .is_satisfied(&public_inputs, &wire_values[2..]));
+
+        let circom_circuit = CircomCircuit::<Tweedledee>::import(&circom_r1cs, 128)?;
+        let circuit = &circom_circuit.r1cs_circuit.circuit;
+        let inputs = circom_circuit.partial_witness(&wire_values)?;
+        let witness = circuit.generate_witness(inputs)?;
+        assert_eq!(circuit.get_public_inputs(&witness), public_inputs);
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        verify_proof::<Tweedledee, Tweedledum>(
+            &public_inputs,
+            &proof,
+            &[],
+            &circuit.to_vk(),
+            true,
+        )?;
+
+        // A witness for a different output doesn't satisfy the constraints.
+        let mut wrong_values = wire_values;
+        wrong_values[1] = F::from_canonical_u64(36);
+        let inputs = circom_circuit.partial_witness(&wrong_values)?;
+        assert!(circuit.generate_witness(inputs).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_malformed_files() {
+        let r1cs = cubic_r1cs();
+        assert!(CircomR1cs::<F>::read(&r1cs[..r1cs.len() - 1]).is_err());
+        let mut wrong_magic = r1cs.clone();
+        wrong_magic[0] = b'x';
+        assert!(CircomR1cs::<F>::read(&wrong_magic).is_err());
+        assert!(read_wtns::<F>(&r1cs).is_err());
+        // A huge constraint count runs out of input rather than allocating for it.
+        assert!(CircomR1cs::<F>::read(&cubic_r1cs_with_num_constraints(u32::MAX)).is_err());
+
+        // The same circuit over another field is rejected.
+        assert!(CircomR1cs::<<Tweedledum as Curve>::ScalarField>::read(&r1cs).is_err());
+        assert!(read_wtns::<<Tweedledum as Curve>::ScalarField>(&cubic_wtns(3)).is_err());
+    }
+}
@@ -515,7 +515,7 @@ mod tests {
         let z = builder.bigint_add(&x, &y);
         let circuit = builder.build();
 
-        let witness = circuit.generate_partial_witness(PartialWitness::new());
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
         let actual_z_value = witness.get_bigint_target(&z);
         assert_eq!(actual_z_value, expected_z_value);
     }
@@ -532,7 +532,7 @@ mod tests {
         let z = builder.bigint_mul(&x, &y);
         let circuit = builder.build();
 
-        let witness = circuit.generate_partial_witness(PartialWitness::new());
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
         let actual_z_value = witness.get_bigint_target(&z);
         assert_eq!(actual_z_value, expected_z_value);
     }
@@ -549,7 +549,7 @@ mod tests {
         let (div, rem) = builder.bigint_div_rem(&x, &y);
         let circuit = builder.build();
 
-        let witness = circuit.generate_partial_witness(PartialWitness::new());
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
         let actual_div_value = witness.get_bigint_target(&div);
         let actual_rem_value = witness.get_bigint_target(&rem);
         assert_eq!(actual_div_value, expected_div_value);
@@ -0,0 +1,108 @@
+use crate::plonk_util::reduce_with_powers_recursive;
+use crate::r1cs::arithmetic_gate;
+use crate::{CircuitBuilder, Field, HaloCurve, Target};
+
+impl<C: HaloCurve> CircuitBuilder<C> {
+    /// Splits `x` into `num_bits` little-endian bits, and constrains the decomposition, which
+    /// implies that `x < 2^num_bits`.
+    pub fn split_binary_checked(
+        &mut self,
+        x: Target<C::ScalarField>,
+        num_bits: usize,
+    ) -> Vec<Target<C::ScalarField>> {
+        assert!(
+            num_bits < C::ScalarField::BITS,
+            "A decomposition into {} bits would not be unique",
+            num_bits
+        );
+        let bits = self.split_binary(x, num_bits);
+        for &bit in &bits {
+            self.assert_binary(bit);
+        }
+        let sum = self.le_sum(&bits);
+        self.copy(sum, x);
+        bits
+    }
+
+    /// Computes the integer with the given little-endian bits. This doesn't constrain the bits to
+    /// be binary.
+    pub fn le_sum(&mut self, bits: &[Target<C::ScalarField>]) -> Target<C::ScalarField> {
+        let two = self.two_wire();
+        reduce_with_powers_recursive(self, bits, two)
+    }
+
+    /// Computes `x AND y` for bits `x` and `y`.
+    pub fn and(
+        &mut self,
+        x: Target<C::ScalarField>,
+        y: Target<C::ScalarField>,
+    ) -> Target<C::ScalarField> {
+        self.mul(x, y)
+    }
+
+    /// Computes `x XOR y = x + y - 2xy` for bits `x` and `y`.
+    pub fn xor(
+        &mut self,
+        x: Target<C::ScalarField>,
+        y: Target<C::ScalarField>,
+    ) -> Target<C::ScalarField> {
+        let zero = self.zero_wire();
+        if x == zero {
+            return y;
+        }
+        if y == zero {
+            return x;
+        }
+
+        let sum = self.add(x, y);
+        arithmetic_gate(self, -C::ScalarField::TWO, x, y, C::ScalarField::ONE, sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{CircuitBuilder, Curve, Field, PartialWitness, Tweedledee};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_bitwise_ops() -> Result<()> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let inputs = builder.add_virtual_targets(2);
+        let x_bits = builder.split_binary_checked(inputs[0], 8);
+        let y_bits = builder.split_binary_checked(inputs[1], 8);
+        let and_bits = x_bits
+            .iter()
+            .zip(&y_bits)
+            .map(|(&x, &y)| builder.and(x, y))
+            .collect::<Vec<_>>();
+        let xor_bits = x_bits
+            .iter()
+            .zip(&y_bits)
+            .map(|(&x, &y)| builder.xor(x, y))
+            .collect::<Vec<_>>();
+        let and = builder.le_sum(&and_bits);
+        let xor = builder.le_sum(&xor_bits);
+        let circuit = builder.build();
+
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_targets(
+            &inputs,
+            &[
+                F::from_canonical_u32(0b1100_1010),
+                F::from_canonical_u32(0b1010_0110),
+            ],
+        );
+        let witness = circuit.generate_partial_witness(partial_witness)?;
+        assert_eq!(witness.get_target(and), F::from_canonical_u32(0b1000_0010));
+        assert_eq!(witness.get_target(xor), F::from_canonical_u32(0b0110_1100));
+
+        // A value which doesn't fit in 8 bits is rejected.
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_targets(&inputs, &[F::from_canonical_u32(256), F::ZERO]);
+        assert!(circuit.generate_partial_witness(partial_witness).is_err());
+        Ok(())
+    }
+}
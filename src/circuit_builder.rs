@@ -1,35 +1,146 @@
 #![allow(clippy::type_complexity)]
 
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use anyhow::Result;
 
 use crate::gates::*;
 use crate::plonk_util::{commit_polynomials, polynomials_to_values_padded, sigma_polynomials, values_to_polynomials};
+use crate::preprocessing_cache::{circuit_digest, PreprocessedPolynomials};
+use crate::sponge::VARIABLE_LENGTH_HASH_DOMAIN_TAG;
 use crate::util::{ceil_div_usize, log2_strict, transpose};
-use crate::{blake_hash_usize_to_curve, fft_precompute, generate_rescue_constants, msm_precompute, AffinePoint, AffinePointTarget, BoundedTarget, Circuit, Curve, Field, HaloCurve, PartialWitness, PublicInput, Target, TargetPartitions, VirtualTarget, Wire, WitnessGenerator, NUM_CONSTANTS, NUM_WIRES};
+use crate::{blake_hash_usize_to_curve, cached_fft_precomputation, generate_rescue_constants, msm_precompute, pad_variable_length, AffinePoint, AffinePointTarget, AlgebraicHasher, BoundedTarget, Circuit, ComponentLayout, Curve, Field, HaloCurve, KeyFormat, PartialWitness, PlonkyError, PolynomialCommitment, PreprocessingCache, ProverKey, PublicInput, RescueHasher, SecurityParams, Target, TargetPartitions, VirtualTarget, Wire, WitnessGenerator, NUM_BLINDING_GATES, NUM_CONSTANTS, NUM_ROUTED_WIRES, NUM_WIRES, QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER};
 use num::{BigUint, Zero};
+use rand::thread_rng;
+
+/// The number of gates in the domain on which `try_build` measures constraint degrees. Small, since
+/// it's measured for every gate type of every circuit; the measured degrees don't depend on it.
+const DEGREE_CHECK_N: usize = 16;
 
 pub struct CircuitBuilder<C: HaloCurve> {
     pub(crate) security_bits: usize,
+    /// The length of the challenges which gadgets verifying Halo proofs decompose. See
+    /// `SecurityParams::challenge_len`.
+    pub(crate) challenge_len: usize,
+    num_wires: usize,
+    num_routed_wires: usize,
+    zero_knowledge: bool,
     public_input_index: usize,
     virtual_target_index: usize,
     gate_counts: BTreeMap<&'static str, usize>,
     gate_constants: Vec<Vec<C::ScalarField>>,
     copy_constraints: Vec<(Target<C::ScalarField>, Target<C::ScalarField>)>,
     generators: Vec<Box<dyn WitnessGenerator<C::ScalarField>>>,
-    constant_wires: HashMap<C::ScalarField, Target<C::ScalarField>>,
+    constant_wires: BTreeMap<C::ScalarField, Target<C::ScalarField>>,
+    /// The inverse of `constant_wires`, used to fold operations whose operands are all constants.
+    constant_values: HashMap<Target<C::ScalarField>, C::ScalarField>,
+    pub(crate) component_layouts: BTreeMap<&'static str, ComponentLayout>,
+    /// The gates of each component instance, along with the component's name.
+    pub(crate) component_instances: Vec<(Range<usize>, &'static str)>,
+    /// The contexts which have been pushed but not yet popped, along with the gate at which each
+    /// one started.
+    context_stack: Vec<(String, usize)>,
+    /// The gates added within each popped context, along with the context's path.
+    context_spans: Vec<(Range<usize>, String)>,
+    /// Names of targets, for error messages. See `set_target_name`.
+    target_names: HashMap<Target<C::ScalarField>, String>,
+    preprocessing_cache: Option<PreprocessingCache>,
+    /// Measures the constraint degrees of each gate type which has been added. See
+    /// `constraint_degrees`.
+    constraint_degree_fns: BTreeMap<&'static str, fn(usize) -> Vec<usize>>,
+    /// The last `ArithmeticGate` added by `add_arithmetic_gate`, along with its constants, its
+    /// `multiplicand_1` and the number of lanes in use, if further operations can still be packed
+    /// into it.
+    open_arithmetic_gate: Option<OpenArithmeticGate<C::ScalarField>>,
+}
+
+/// An `ArithmeticGate` which further operations can be packed into. See
+/// `CircuitBuilder::add_arithmetic_gate`.
+#[derive(Copy, Clone)]
+struct OpenArithmeticGate<F: Field> {
+    index: usize,
+    constants: [F; 2],
+    multiplicand_1: Target<F>,
+    num_lanes_used: usize,
+}
+
+/// A snapshot of a `CircuitBuilder`'s state, which the builder can later be rolled back to. See
+/// `CircuitBuilder::checkpoint`.
+#[derive(Clone)]
+pub struct BuilderCheckpoint<F: Field> {
+    num_public_inputs: usize,
+    num_virtual_targets: usize,
+    num_gates: usize,
+    num_copy_constraints: usize,
+    num_generators: usize,
+    gate_counts: BTreeMap<&'static str, usize>,
+    constant_wires: BTreeMap<F, Target<F>>,
+    constant_values: HashMap<Target<F>, F>,
+    component_layouts: BTreeMap<&'static str, ComponentLayout>,
+}
+
+impl<F: Field> BuilderCheckpoint<F> {
+    /// The number of gates the builder had when this checkpoint was taken.
+    pub fn num_gates(&self) -> usize {
+        self.num_gates
+    }
 }
 
 impl<C: HaloCurve> CircuitBuilder<C> {
     pub fn new(security_bits: usize) -> Self {
+        Self::new_with_width(security_bits, NUM_WIRES, NUM_ROUTED_WIRES)
+    }
+
+    /// Creates a builder for a circuit with the given number of wires, of which the first
+    /// `num_routed_wires` can be copy-constrained. Our gates require at least the default widths,
+    /// `NUM_WIRES` and `NUM_ROUTED_WIRES`; any additional wires are available to wider gates.
+    ///
+    /// The permutation argument multiplies one term per routed wire with `Z`, so the number of
+    /// routed wires is bounded by the degree of the quotient polynomial.
+    pub fn new_with_width(security_bits: usize, num_wires: usize, num_routed_wires: usize) -> Self {
+        assert!(
+            num_wires >= NUM_WIRES,
+            "Circuits need at least {} wires",
+            NUM_WIRES
+        );
+        assert!(
+            num_routed_wires >= NUM_ROUTED_WIRES,
+            "Circuits need at least {} routed wires",
+            NUM_ROUTED_WIRES
+        );
+        assert!(
+            num_routed_wires <= num_wires,
+            "Cannot have more routed wires than wires"
+        );
+        assert!(
+            num_routed_wires <= QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER,
+            "Circuits can have at most {} routed wires",
+            QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER
+        );
+
         CircuitBuilder {
             security_bits,
+            challenge_len: security_bits,
+            num_wires,
+            num_routed_wires,
+            zero_knowledge: true,
             public_input_index: 0,
             virtual_target_index: 0,
             gate_counts: BTreeMap::new(),
             gate_constants: Vec::new(),
             copy_constraints: Vec::new(),
             generators: Vec::new(),
-            constant_wires: HashMap::new(),
+            constant_wires: BTreeMap::new(),
+            constant_values: HashMap::new(),
+            component_layouts: BTreeMap::new(),
+            component_instances: Vec::new(),
+            context_stack: Vec::new(),
+            context_spans: Vec::new(),
+            target_names: HashMap::new(),
+            preprocessing_cache: None,
+            constraint_degree_fns: BTreeMap::new(),
+            open_arithmetic_gate: None,
         }
     }
 
@@ -92,6 +203,21 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         self.constant_wire(C::ScalarField::NEG_ONE)
     }
 
+    /// Shorthand for `zero_wire`. Like every constant target, it's created once and then cached.
+    pub fn zero(&mut self) -> Target<C::ScalarField> {
+        self.zero_wire()
+    }
+
+    /// Shorthand for `one_wire`.
+    pub fn one(&mut self) -> Target<C::ScalarField> {
+        self.one_wire()
+    }
+
+    /// Shorthand for `neg_one_wire`.
+    pub fn neg_one(&mut self) -> Target<C::ScalarField> {
+        self.neg_one_wire()
+    }
+
     #[allow(clippy::map_entry)]
     pub fn constant_wire(&mut self, c: C::ScalarField) -> Target<C::ScalarField> {
         if self.constant_wires.contains_key(&c) {
@@ -99,10 +225,16 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         } else {
             let result = self.create_constant_wire(c);
             self.constant_wires.insert(c, result);
+            self.constant_values.insert(result, c);
             result
         }
     }
 
+    /// Returns the value of `target` if it was created by `constant_wire`, or `None` otherwise.
+    pub fn constant_value(&self, target: Target<C::ScalarField>) -> Option<C::ScalarField> {
+        self.constant_values.get(&target).copied()
+    }
+
     pub fn constant_wires(&mut self, constants: &[C::ScalarField]) -> Vec<Target<C::ScalarField>> {
         constants.iter().map(|&c| self.constant_wire(c)).collect()
     }
@@ -192,6 +324,18 @@ impl<C: HaloCurve> CircuitBuilder<C> {
                     }),
                 )
             }
+
+            // The remaining limb wires are unused, but they must be populated (with any base 4
+            // value) for the gate's generator to run.
+            for i in chunk.len()..Base4SumGate::<C>::NUM_LIMBS {
+                self.generate_constant(
+                    Target::Wire(Wire {
+                        gate,
+                        input: Base4SumGate::<C>::wire_limb(i),
+                    }),
+                    C::ScalarField::ZERO,
+                );
+            }
         }
     }
 
@@ -312,6 +456,10 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         x: Target<C::ScalarField>,
         y: Target<C::ScalarField>,
     ) -> Target<C::ScalarField> {
+        if let (Some(x_value), Some(y_value)) = (self.constant_value(x), self.constant_value(y)) {
+            return self.constant_wire(x_value + y_value);
+        }
+
         let zero = self.zero_wire();
         if x == zero {
             return y;
@@ -321,36 +469,7 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         }
 
         let one = self.one_wire();
-        let index = self.num_gates();
-        self.add_gate(
-            ArithmeticGate::new(index),
-            vec![C::ScalarField::ONE, C::ScalarField::ONE],
-        );
-        self.copy(
-            x,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_0,
-            }),
-        );
-        self.copy(
-            one,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_1,
-            }),
-        );
-        self.copy(
-            y,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_ADDEND,
-            }),
-        );
-        Target::Wire(Wire {
-            gate: index,
-            input: ArithmeticGate::<C>::WIRE_OUTPUT,
-        })
+        self.add_arithmetic_gate(C::ScalarField::ONE, C::ScalarField::ONE, x, one, y)
     }
 
     pub fn add_many(&mut self, terms: &[Target<C::ScalarField>]) -> Target<C::ScalarField> {
@@ -370,42 +489,17 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         x: Target<C::ScalarField>,
         y: Target<C::ScalarField>,
     ) -> Target<C::ScalarField> {
+        if let (Some(x_value), Some(y_value)) = (self.constant_value(x), self.constant_value(y)) {
+            return self.constant_wire(x_value - y_value);
+        }
+
         let zero = self.zero_wire();
         if y == zero {
             return x;
         }
 
         let one = self.one_wire();
-        let index = self.num_gates();
-        self.add_gate(
-            ArithmeticGate::new(index),
-            vec![C::ScalarField::ONE, C::ScalarField::NEG_ONE],
-        );
-        self.copy(
-            x,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_0,
-            }),
-        );
-        self.copy(
-            one,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_1,
-            }),
-        );
-        self.copy(
-            y,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_ADDEND,
-            }),
-        );
-        Target::Wire(Wire {
-            gate: index,
-            input: ArithmeticGate::<C>::WIRE_OUTPUT,
-        })
+        self.add_arithmetic_gate(C::ScalarField::ONE, C::ScalarField::NEG_ONE, x, one, y)
     }
 
     pub fn mul(
@@ -413,6 +507,10 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         x: Target<C::ScalarField>,
         y: Target<C::ScalarField>,
     ) -> Target<C::ScalarField> {
+        if let (Some(x_value), Some(y_value)) = (self.constant_value(x), self.constant_value(y)) {
+            return self.constant_wire(x_value * y_value);
+        }
+
         let one = self.one_wire();
         if x == one {
             return y;
@@ -422,36 +520,7 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         }
 
         let zero = self.zero_wire();
-        let index = self.num_gates();
-        self.add_gate(
-            ArithmeticGate::new(index),
-            vec![C::ScalarField::ONE, C::ScalarField::ZERO],
-        );
-        self.copy(
-            x,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_0,
-            }),
-        );
-        self.copy(
-            y,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_1,
-            }),
-        );
-        self.copy(
-            zero,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_ADDEND,
-            }),
-        );
-        Target::Wire(Wire {
-            gate: index,
-            input: ArithmeticGate::<C>::WIRE_OUTPUT,
-        })
+        self.add_arithmetic_gate(C::ScalarField::ONE, C::ScalarField::ZERO, x, y, zero)
     }
 
     pub fn mul_many(&mut self, terms: &[Target<C::ScalarField>]) -> Target<C::ScalarField> {
@@ -654,36 +723,13 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         y: Target<C::ScalarField>,
         z: Target<C::ScalarField>,
     ) -> Target<C::ScalarField> {
-        let index = self.num_gates();
-        self.add_gate(
-            ArithmeticGate::new(index),
-            vec![C::ScalarField::ONE, C::ScalarField::ONE],
-        );
-        self.copy(
-            x,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_0,
-            }),
-        );
-        self.copy(
-            y,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_1,
-            }),
-        );
-        self.copy(
-            z,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_ADDEND,
-            }),
-        );
-        Target::Wire(Wire {
-            gate: index,
-            input: ArithmeticGate::<C>::WIRE_OUTPUT,
-        })
+        if let (Some(x_value), Some(y_value), Some(z_value)) =
+            (self.constant_value(x), self.constant_value(y), self.constant_value(z))
+        {
+            return self.constant_wire(x_value * y_value + z_value);
+        }
+
+        self.add_arithmetic_gate(C::ScalarField::ONE, C::ScalarField::ONE, x, y, z)
     }
 
     pub(crate) fn bounded_mul_add(
@@ -704,42 +750,118 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         y: Target<C::ScalarField>,
         z: Target<C::ScalarField>,
     ) -> Target<C::ScalarField> {
-        let index = self.num_gates();
-        self.add_gate(
-            ArithmeticGate::new(index),
-            vec![C::ScalarField::ONE, C::ScalarField::NEG_ONE],
-        );
-        self.copy(
-            x,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_0,
-            }),
-        );
-        self.copy(
-            y,
-            Target::Wire(Wire {
-                gate: index,
-                input: ArithmeticGate::<C>::WIRE_MULTIPLICAND_1,
-            }),
-        );
-        self.copy(
-            z,
+        if let (Some(x_value), Some(y_value), Some(z_value)) =
+            (self.constant_value(x), self.constant_value(y), self.constant_value(z))
+        {
+            return self.constant_wire(x_value * y_value - z_value);
+        }
+
+        self.add_arithmetic_gate(C::ScalarField::ONE, C::ScalarField::NEG_ONE, x, y, z)
+    }
+
+    /// Computes `x + c`. The constant is folded into the gate's constants, so unlike `add`, this
+    /// doesn't create a target for `c`; the multiplicands are the cached one target.
+    pub fn add_const(
+        &mut self,
+        x: Target<C::ScalarField>,
+        c: C::ScalarField,
+    ) -> Target<C::ScalarField> {
+        if let Some(x_value) = self.constant_value(x) {
+            return self.constant_wire(x_value + c);
+        }
+        if c == C::ScalarField::ZERO {
+            return x;
+        }
+
+        let one = self.one_wire();
+        self.add_arithmetic_gate(c, C::ScalarField::ONE, one, one, x)
+    }
+
+    /// Computes `c * x`. The constant is folded into the gate's constants, so unlike `mul`, this
+    /// doesn't create a target for `c`.
+    pub fn mul_const(
+        &mut self,
+        x: Target<C::ScalarField>,
+        c: C::ScalarField,
+    ) -> Target<C::ScalarField> {
+        if let Some(x_value) = self.constant_value(x) {
+            return self.constant_wire(x_value * c);
+        }
+        if c == C::ScalarField::ZERO {
+            return self.zero_wire();
+        }
+        if c == C::ScalarField::ONE {
+            return x;
+        }
+
+        let zero = self.zero_wire();
+        self.add_arithmetic_gate(C::ScalarField::ZERO, c, zero, zero, x)
+    }
+
+    /// Adds an operation computing `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`,
+    /// and returns its output.
+    ///
+    /// In circuits wide enough for an `ArithmeticGate` to have several routed lanes, the operation
+    /// is packed into a free lane of the previous gate when that gate is an `ArithmeticGate` with the
+    /// same constants and `multiplicand_1`, as in chains of additions, which all multiply by one.
+    /// Otherwise it gets a new `ArithmeticGate`.
+    fn add_arithmetic_gate(
+        &mut self,
+        const_0: C::ScalarField,
+        const_1: C::ScalarField,
+        multiplicand_0: Target<C::ScalarField>,
+        multiplicand_1: Target<C::ScalarField>,
+        addend: Target<C::ScalarField>,
+    ) -> Target<C::ScalarField> {
+        let constants = [const_0, const_1];
+        let num_lanes = ArithmeticGate::<C>::num_routed_lanes(self.num_wires, self.num_routed_wires);
+        let (index, lane) = match self.open_arithmetic_gate {
+            Some(open)
+                if open.index + 1 == self.num_gates()
+                    && open.constants == constants
+                    && open.multiplicand_1 == multiplicand_1
+                    && open.num_lanes_used < num_lanes =>
+            {
+                self.add_generator(ArithmeticGate::<C>::new_lane(open.index, open.num_lanes_used));
+                (open.index, open.num_lanes_used)
+            }
+            _ => {
+                let index = self.num_gates();
+                self.add_gate(ArithmeticGate::new(index), constants.to_vec());
+                (index, 0)
+            }
+        };
+        self.open_arithmetic_gate = Some(OpenArithmeticGate {
+            index,
+            constants,
+            multiplicand_1,
+            num_lanes_used: lane + 1,
+        });
+
+        let wire = |input| {
             Target::Wire(Wire {
                 gate: index,
-                input: ArithmeticGate::<C>::WIRE_ADDEND,
-            }),
-        );
-        Target::Wire(Wire {
-            gate: index,
-            input: ArithmeticGate::<C>::WIRE_OUTPUT,
-        })
+                input: ArithmeticGate::<C>::lane_wire(lane, input),
+            })
+        };
+        self.copy(multiplicand_0, wire(ArithmeticGate::<C>::WIRE_MULTIPLICAND_0));
+        if lane == 0 {
+            // Further lanes share the first lane's `multiplicand_1`.
+            self.copy(multiplicand_1, wire(ArithmeticGate::<C>::WIRE_MULTIPLICAND_1));
+        }
+        self.copy(addend, wire(ArithmeticGate::<C>::WIRE_ADDEND));
+        wire(ArithmeticGate::<C>::WIRE_OUTPUT)
+    }
+
+    /// Stops `add_arithmetic_gate` from packing operations into the gates added so far, so that the
+    /// gates added next hold only operations added after this call.
+    pub(crate) fn close_arithmetic_gate(&mut self) {
+        self.open_arithmetic_gate = None;
     }
 
     /// Computes `-x`.
     pub fn neg(&mut self, x: Target<C::ScalarField>) -> Target<C::ScalarField> {
-        let neg_one = self.neg_one_wire();
-        self.mul(x, neg_one)
+        self.mul_const(x, C::ScalarField::NEG_ONE)
     }
 
     /// Splits `x` into its binary representation. Note that this method merely adds a generator to
@@ -821,8 +943,10 @@ impl<C: HaloCurve> CircuitBuilder<C> {
     ///
     /// Note: This is most efficient when `num_dibits` is a multiple of `Base4SumGate::NUM_LIMBS`.
     pub(crate) fn assert_dibit_length(&mut self, x: Target<C::ScalarField>, num_dibits: usize) {
-        // Get the purported base 4 decomposition of x.
-        let dibits = self.split_base_4(x, num_dibits);
+        // Get the purported base 4 decomposition of x. Since each accumulation step multiplies the
+        // sum by 4, we accumulate the dibits starting from the most significant one.
+        let mut dibits = self.split_base_4(x, num_dibits);
+        dibits.reverse();
 
         // Accumulate each full chunk of NUM_LIMBS dibits using a Base4SumGate.
         let mut sum = self.zero_wire();
@@ -899,32 +1023,64 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         (outputs[0], outputs[1], outputs[2])
     }
 
+    /// Hashes a variable number of field elements, consistent with `hash_fields`.
+    pub fn hash_fields(&mut self, inputs: &[Target<C::ScalarField>]) -> Target<C::ScalarField> {
+        let zero = self.zero_wire();
+        let one = self.one_wire();
+        let padded = pad_variable_length(inputs, one, zero, RESCUE_SPONGE_RATE);
+
+        let mut state = vec![zero; RESCUE_SPONGE_WIDTH];
+        state[RESCUE_SPONGE_RATE] = self.constant_wire(C::ScalarField::from_canonical_u64(
+            VARIABLE_LENGTH_HASH_DOMAIN_TAG,
+        ));
+        self.sponge_from_state::<RescueHasher>(state, &padded, 1)[0]
+    }
+
     pub fn rescue_sponge(
         &mut self,
         inputs: &[Target<C::ScalarField>],
         num_outputs: usize,
+    ) -> Vec<Target<C::ScalarField>> {
+        self.sponge_with_hasher::<RescueHasher>(inputs, num_outputs)
+    }
+
+    /// Like `rescue_sponge`, but using the given hasher. This is consistent with a native `Sponge`
+    /// created by `Sponge::new_with_hasher`.
+    pub fn sponge_with_hasher<H: AlgebraicHasher<C::ScalarField>>(
+        &mut self,
+        inputs: &[Target<C::ScalarField>],
+        num_outputs: usize,
     ) -> Vec<Target<C::ScalarField>> {
         let zero = self.zero_wire();
-        let mut state = vec![zero; RESCUE_SPONGE_WIDTH];
+        let state = vec![zero; H::WIDTH];
+        self.sponge_from_state::<H>(state, inputs, num_outputs)
+    }
 
+    /// Like `sponge_with_hasher`, but starting from the given sponge state.
+    fn sponge_from_state<H: AlgebraicHasher<C::ScalarField>>(
+        &mut self,
+        mut state: Vec<Target<C::ScalarField>>,
+        inputs: &[Target<C::ScalarField>],
+        num_outputs: usize,
+    ) -> Vec<Target<C::ScalarField>> {
         // Absorb all input chunks.
-        for input_chunk in inputs.chunks(RESCUE_SPONGE_WIDTH - 1) {
+        for input_chunk in inputs.chunks(H::RATE) {
             for i in 0..input_chunk.len() {
                 state[i] = self.add(state[i], input_chunk[i]);
             }
-            state = self.rescue_permutation(&state);
+            state = H::permute_recursively(self, &state);
         }
 
         // Squeeze until we have the desired number of outputs.
         let mut outputs = Vec::new();
         loop {
-            for i in 0..(RESCUE_SPONGE_WIDTH - 1) {
+            for i in 0..H::RATE {
                 outputs.push(state[i]);
                 if outputs.len() == num_outputs {
                     return outputs;
                 }
             }
-            state = self.rescue_permutation(&state);
+            state = H::permute_recursively(self, &state);
         }
     }
 
@@ -994,6 +1150,9 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         self.gate_constants.push(all_constants);
         self.add_generator(gate);
         *self.gate_counts.entry(G::NAME).or_insert(0) += 1;
+        self.constraint_degree_fns
+            .entry(G::NAME)
+            .or_insert(constraint_degrees::<C, G>);
     }
 
     pub fn add_generator<G: WitnessGenerator<C::ScalarField>>(&mut self, generator: G) {
@@ -1004,6 +1163,181 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         self.gate_constants.len()
     }
 
+    /// The degree the circuit would have if it were built now, i.e. its number of gates after
+    /// `build` adds any blinding gates and the public input gates, rounded up to a power of two.
+    pub fn degree(&self) -> usize {
+        let num_blinding_gates = if self.zero_knowledge {
+            NUM_BLINDING_GATES
+        } else {
+            0
+        };
+        let num_pi_gates = 2 * ceil_div_usize(self.public_input_index, NUM_WIRES);
+        (self.num_gates() + num_blinding_gates + num_pi_gates).next_power_of_two()
+    }
+
+    pub fn num_wires(&self) -> usize {
+        self.num_wires
+    }
+
+    pub fn num_routed_wires(&self) -> usize {
+        self.num_routed_wires
+    }
+
+    /// Sets whether proofs of the circuit should be zero-knowledge, which is the default. If not,
+    /// `build` omits the blinding gates and the prover doesn't blind its commitments, which saves
+    /// a few percent of proving time. This is only appropriate when the witness is public anyway.
+    pub fn set_zero_knowledge(&mut self, zero_knowledge: bool) {
+        self.zero_knowledge = zero_knowledge;
+    }
+
+    /// Sets the security parameters of the circuit, which otherwise has challenges as long as the
+    /// security level passed to `new`. Gadgets use the parameters as they're added, so this must be
+    /// called before adding any gates.
+    pub fn set_security_params(&mut self, params: SecurityParams) {
+        params.check::<C::ScalarField>().expect("Invalid security parameters");
+        assert_eq!(
+            self.num_gates(),
+            0,
+            "Security parameters must be set before adding gates"
+        );
+        self.security_bits = params.bits;
+        self.challenge_len = params.challenge_len;
+    }
+
+    pub fn security_params(&self) -> SecurityParams {
+        SecurityParams {
+            bits: self.security_bits,
+            challenge_len: self.challenge_len,
+        }
+    }
+
+    /// Sets a cache of preprocessed polynomials and commitments for `build` to use. If the cache
+    /// has an entry for a circuit with the same gate constants and copy constraints, `build` uses it
+    /// rather than preprocessing the circuit; otherwise it preprocesses the circuit, then adds an
+    /// entry for it.
+    pub fn set_preprocessing_cache(&mut self, cache: PreprocessingCache) {
+        self.preprocessing_cache = Some(cache);
+    }
+
+    /// The number of advice wires, i.e. wires which are constrained by gates but which are not part
+    /// of the permutation argument. These are the last wires of each gate.
+    pub fn num_advice_wires(&self) -> usize {
+        self.num_wires - self.num_routed_wires
+    }
+
+    /// Records the current state of the builder, so that anything added afterwards can be discarded
+    /// with `rollback`. This lets gadgets build a circuit fragment speculatively, e.g. to compare the
+    /// gate counts of two encodings before keeping one of them.
+    pub fn checkpoint(&self) -> BuilderCheckpoint<C::ScalarField> {
+        BuilderCheckpoint {
+            num_public_inputs: self.public_input_index,
+            num_virtual_targets: self.virtual_target_index,
+            num_gates: self.num_gates(),
+            num_copy_constraints: self.copy_constraints.len(),
+            num_generators: self.generators.len(),
+            gate_counts: self.gate_counts.clone(),
+            constant_wires: self.constant_wires.clone(),
+            constant_values: self.constant_values.clone(),
+            component_layouts: self.component_layouts.clone(),
+        }
+    }
+
+    /// Discards all public inputs, virtual targets, gates, copy constraints and generators added
+    /// since the given checkpoint was taken. Any target created since then must no longer be used.
+    ///
+    /// A checkpoint can be rolled back to several times, but only while the builder has not been
+    /// rolled back to an earlier state.
+    pub fn rollback(&mut self, checkpoint: &BuilderCheckpoint<C::ScalarField>) {
+        assert!(
+            self.num_gates() >= checkpoint.num_gates
+                && self.generators.len() >= checkpoint.num_generators
+                && self.copy_constraints.len() >= checkpoint.num_copy_constraints,
+            "Cannot roll back to a checkpoint taken after the builder's current state"
+        );
+
+        self.public_input_index = checkpoint.num_public_inputs;
+        self.virtual_target_index = checkpoint.num_virtual_targets;
+        self.gate_constants.truncate(checkpoint.num_gates);
+        self.copy_constraints.truncate(checkpoint.num_copy_constraints);
+        self.generators.truncate(checkpoint.num_generators);
+        self.gate_counts = checkpoint.gate_counts.clone();
+        self.constant_wires = checkpoint.constant_wires.clone();
+        self.constant_values = checkpoint.constant_values.clone();
+        self.component_layouts = checkpoint.component_layouts.clone();
+        self.close_arithmetic_gate();
+        self.component_instances
+            .retain(|(gates, _)| gates.end <= checkpoint.num_gates);
+        self.context_spans
+            .retain(|(gates, _)| gates.end <= checkpoint.num_gates);
+        self.target_names.retain(|target, _| match target {
+            Target::PublicInput(pi) => pi.index < checkpoint.num_public_inputs,
+            Target::VirtualTarget(v) => v.index < checkpoint.num_virtual_targets,
+            Target::Wire(w) => w.gate < checkpoint.num_gates,
+            _ => false,
+        });
+    }
+
+    /// Starts a named context, such as `"sha256 round 3"`, which lasts until the matching
+    /// `pop_context`. Contexts can be nested. Error messages about a gate added within a context,
+    /// and constraint failure reports, mention the context's path, e.g. `"sha256 > round 3"`, and
+    /// `build` logs the number of gates in each context.
+    pub fn push_context(&mut self, name: &str) {
+        self.close_arithmetic_gate();
+        let start = self.num_gates();
+        self.context_stack.push((name.to_string(), start));
+    }
+
+    /// Ends the innermost context started by `push_context`.
+    pub fn pop_context(&mut self) {
+        let path = self.context_path().expect("No context to pop");
+        let (_, start) = self.context_stack.pop().unwrap();
+        let end = self.num_gates();
+        self.close_arithmetic_gate();
+        self.context_spans.push((start..end, path));
+    }
+
+    /// Calls `f` within a context with the given name.
+    pub fn with_context<T>(&mut self, name: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.push_context(name);
+        let result = f(self);
+        self.pop_context();
+        result
+    }
+
+    /// The names of the current contexts, from outermost to innermost, joined with `" > "`.
+    fn context_path(&self) -> Option<String> {
+        if self.context_stack.is_empty() {
+            return None;
+        }
+        let names = self.context_stack.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+        Some(names.join(" > "))
+    }
+
+    /// Attaches a name to `target`, which error messages about it will mention. The name is
+    /// prefixed with the current context's path, if any.
+    pub fn set_target_name(&mut self, target: Target<C::ScalarField>, name: &str) {
+        let name = match self.context_path() {
+            Some(path) => format!("{} > {}", path, name),
+            None => name.to_string(),
+        };
+        self.target_names.insert(target, name);
+    }
+
+    /// The name attached to `target` with `set_target_name`, if any.
+    pub fn target_name(&self, target: Target<C::ScalarField>) -> Option<&str> {
+        self.target_names.get(&target).map(String::as_str)
+    }
+
+    /// The number of gates added within each context path so far. The gates of a nested context
+    /// are also counted in the enclosing ones.
+    pub fn context_gate_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for (gates, path) in &self.context_spans {
+            *counts.entry(path.clone()).or_insert(0) += gates.len();
+        }
+        counts
+    }
+
     /// Add a copy constraint between two routing targets.
     pub fn copy(&mut self, target_1: Target<C::ScalarField>, target_2: Target<C::ScalarField>) {
         self.copy_constraints.push((target_1, target_2));
@@ -1021,15 +1355,32 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         self.copy(conditional_target_1, conditional_target_2);
     }
 
-    /// Adds a gate with random wire values. By adding `k` of these gates, we can ensure that
-    /// nothing is learned by opening the wire polynomials at `k` points outside of H.
-    fn add_blinding_gate(&mut self) {
-        let gate = self.num_gates();
-        self.add_gate_no_constants(BufferGate::new(gate));
-        for input in 0..NUM_WIRES {
-            self.add_generator(RandomGenerator {
-                target: Target::Wire(Wire { gate, input }),
-            });
+    /// Adds `NUM_BLINDING_GATES` gates with random wire values. Since we open each polynomial at
+    /// three points outside of H, three random values per wire ensure that nothing is learned
+    /// from the openings.
+    ///
+    /// The random wires alone would leave `Z` unblinded, as a wire which isn't copied contributes
+    /// the same factor to both sides of the permutation argument. So we also copy wire `i` of the
+    /// `i`th blinding gate to the same wire of the next gate. Each such copy multiplies `Z` by a
+    /// random factor after the first gate, and by its inverse after the second, so `Z` takes a
+    /// random value at three consecutive gates.
+    fn add_blinding_gates(&mut self) {
+        let first_gate = self.num_gates();
+        for i in 0..NUM_BLINDING_GATES {
+            self.add_gate_no_constants(BufferGate::new(first_gate + i));
+        }
+
+        for i in 0..NUM_BLINDING_GATES {
+            let gate = first_gate + i;
+            for input in 0..self.num_wires {
+                let target = Target::Wire(Wire { gate, input });
+                if i > 0 && input == i - 1 {
+                    // This wire is a copy of a random wire of the previous gate.
+                    self.copy(Target::Wire(Wire { gate: gate - 1, input }), target);
+                } else {
+                    self.add_generator(RandomGenerator { target });
+                }
+            }
         }
 
         struct RandomGenerator<F: Field> {
@@ -1075,11 +1426,26 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         });
     }
 
-    pub fn build(mut self) -> Circuit<C> {
-        // Since we will open each polynomial at three points outside of H, we need three random
-        // values to ensure nothing is learned from the out-of-H openings.
-        for _i in 0..3 {
-            self.add_blinding_gate();
+    /// Builds the circuit. This is deterministic: running the same builder code twice results in
+    /// identical gate orderings, constants and routing, and thus identical prover and verifier keys.
+    ///
+    /// Panics if a gate's constraints have a higher degree than the prover supports; see
+    /// `try_build`.
+    pub fn build(self) -> Circuit<C> {
+        self.try_build().expect("Failed to build the circuit")
+    }
+
+    /// Like `build`, but returns an error if one of the added gate types has a constraint of
+    /// higher degree than `MAX_CONSTRAINT_DEGREE`. Its quotient polynomial wouldn't fit in the
+    /// prover's chunks, so proofs of the circuit would never verify.
+    pub fn try_build(mut self) -> Result<Circuit<C>> {
+        if let Some(path) = self.context_path() {
+            panic!("Context {} was never popped", path);
+        }
+        self.check_constraint_degrees()?;
+
+        if self.zero_knowledge {
+            self.add_blinding_gates();
         }
 
         // Append the public inputs at the end of the circuit and update the copy constraints.
@@ -1092,6 +1458,15 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         for (gate, count) in &self.gate_counts {
             info!("{}: {}", gate, count);
         }
+        for (component, layout) in &self.component_layouts {
+            info!(
+                "{}: {} instances of {} gates",
+                component, layout.num_instances, layout.num_gates
+            );
+        }
+        for (path, count) in self.context_gate_counts() {
+            info!("{}: {} gates", path, count);
+        }
 
         // Pad to a power of two.
         info!("Total gates before padding: {}", self.num_gates());
@@ -1104,19 +1479,27 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         let degree = self.num_gates();
         let degree_pow = log2_strict(degree);
         let routing_target_partitions = self.get_routing_partitions();
-        let wire_partitions = routing_target_partitions.to_wire_partitions();
-        let sigma = wire_partitions.to_sigma();
+        let wire_partitions = routing_target_partitions.to_wire_partitions(self.num_routed_wires);
+        let sigma = wire_partitions.to_sigma(self.num_wires, self.num_routed_wires);
 
         let CircuitBuilder {
             security_bits,
+            challenge_len,
+            num_wires,
+            num_routed_wires,
+            zero_knowledge,
             public_input_index: num_public_inputs,
             gate_constants,
             generators,
+            component_instances,
+            context_spans,
+            target_names,
+            preprocessing_cache,
             ..
         } = self;
 
-        let fft_precomputation_n = fft_precompute(degree);
-        let fft_precomputation_8n = fft_precompute(degree * 8);
+        let fft_precomputation_n = cached_fft_precomputation(degree);
+        let fft_precomputation_8n = cached_fft_precomputation(degree * 8);
 
         let subgroup_generator_n = C::ScalarField::primitive_root_of_unity(degree_pow);
         let subgroup_generator_8n = C::ScalarField::primitive_root_of_unity(degree_pow + 3);
@@ -1132,57 +1515,108 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         let pedersen_g_msm_precomputation =
             msm_precompute(&AffinePoint::batch_to_projective(&pedersen_g), w);
 
-        // While gate_constants is indexed by gate index first, this is indexed by wire index first.
-        let wire_constants = transpose::<C::ScalarField>(&gate_constants);
-
-        let constant_polynomials = values_to_polynomials(&wire_constants, &fft_precomputation_n);
-        let constants_8n =
-            polynomials_to_values_padded(&constant_polynomials, &fft_precomputation_8n);
-        let c_constants = commit_polynomials(
-            constant_polynomials.as_slice(),
-            &pedersen_g_msm_precomputation,
-            pedersen_h,
-            false, // Circuit blinding is not necessary here.
-        );
+        let digest = preprocessing_cache
+            .as_ref()
+            .map(|_| circuit_digest::<C>(num_wires, num_routed_wires, &gate_constants, &sigma));
+        let preprocess = || {
+            // While gate_constants is indexed by gate index first, this is indexed by wire index first.
+            let wire_constants = transpose::<C::ScalarField>(&gate_constants);
+
+            let constant_polynomials = values_to_polynomials(&wire_constants, &fft_precomputation_n);
+            let c_constants = commit_polynomials(
+                constant_polynomials.as_slice(),
+                &pedersen_g_msm_precomputation,
+                pedersen_h,
+                false, // Circuit blinding is not necessary here.
+                &mut thread_rng(),
+            );
 
-        // Convert sigma's values to scalar field elements and split it into degree-n chunks.
-        let sigma_chunks = sigma_polynomials(sigma, degree, subgroup_generator_n);
-
-        // Compute S_sigma, then a commitment to it.
-        let s_sigma_polynomials = values_to_polynomials(&sigma_chunks, &fft_precomputation_n);
-        let s_sigma_values_8n =
-            polynomials_to_values_padded(&s_sigma_polynomials, &fft_precomputation_8n);
-        let c_s_sigmas = commit_polynomials(
-            s_sigma_polynomials.as_slice(),
-            &pedersen_g_msm_precomputation,
-            pedersen_h,
-            false, // Circuit blinding is not necessary here.
-        );
+            // Convert sigma's values to scalar field elements and split it into degree-n chunks.
+            let sigma_chunks = sigma_polynomials(sigma, degree, subgroup_generator_n);
+
+            // Compute S_sigma, then a commitment to it.
+            let s_sigma_polynomials = values_to_polynomials(&sigma_chunks, &fft_precomputation_n);
+            let c_s_sigmas = commit_polynomials(
+                s_sigma_polynomials.as_slice(),
+                &pedersen_g_msm_precomputation,
+                pedersen_h,
+                false, // Circuit blinding is not necessary here.
+                &mut thread_rng(),
+            );
 
-        Circuit {
-            security_bits,
-            num_public_inputs,
-            num_gates_without_pis,
-            gate_constants,
-            routing_target_partitions,
-            generators,
-            subgroup_generator_n,
-            subgroup_generator_8n,
-            subgroup_n,
-            subgroup_8n,
-            pedersen_g,
-            pedersen_h,
-            u,
+            PreprocessedPolynomials {
+                format: KeyFormat::new(),
+                constant_polynomials,
+                c_constants: PolynomialCommitment::commitments_to_affine_vec(&c_constants),
+                s_sigma_polynomials,
+                c_s_sigmas: PolynomialCommitment::commitments_to_affine_vec(&c_s_sigmas),
+            }
+        };
+        let PreprocessedPolynomials {
             constant_polynomials,
-            constants_8n,
             c_constants,
             s_sigma_polynomials,
-            s_sigma_values_8n,
             c_s_sigmas,
-            pedersen_g_msm_precomputation,
-            fft_precomputation_n,
-            fft_precomputation_8n,
+            ..
+        } = match (&preprocessing_cache, &digest) {
+            (Some(cache), Some(digest)) => cache.get_or_compute(digest, preprocess),
+            _ => preprocess(),
+        };
+        let constants_8n = polynomials_to_values_padded(&constant_polynomials);
+        let s_sigma_values_8n = polynomials_to_values_padded(&s_sigma_polynomials);
+
+        Ok(Circuit {
+            prover_key: ProverKey {
+                format: KeyFormat::new(),
+                security_bits,
+                challenge_len,
+                num_wires,
+                num_routed_wires,
+                zero_knowledge,
+                low_memory: false,
+                num_public_inputs,
+                num_gates_without_pis,
+                gate_constants,
+                subgroup_generator_n,
+                subgroup_generator_8n,
+                subgroup_n,
+                subgroup_8n,
+                pedersen_g,
+                pedersen_h,
+                u,
+                constant_polynomials,
+                constants_8n,
+                c_constants,
+                s_sigma_polynomials,
+                s_sigma_values_8n,
+                c_s_sigmas,
+                pedersen_g_msm_precomputation,
+                fft_precomputation_n,
+                fft_precomputation_8n,
+            },
+            routing_target_partitions,
+            generators,
+            component_instances,
+            context_spans,
+            target_names,
+        })
+    }
+
+    fn check_constraint_degrees(&self) -> Result<()> {
+        for (&gate, degrees_fn) in &self.constraint_degree_fns {
+            for (constraint, &degree) in degrees_fn(DEGREE_CHECK_N).iter().enumerate() {
+                if degree > MAX_CONSTRAINT_DEGREE {
+                    return Err(PlonkyError::ConstraintDegreeTooHigh {
+                        gate,
+                        constraint,
+                        degree,
+                        max_degree: MAX_CONSTRAINT_DEGREE,
+                    }
+                    .into());
+                }
+            }
         }
+        Ok(())
     }
 
     fn get_routing_partitions(&self) -> TargetPartitions<C::ScalarField> {
@@ -1193,7 +1627,7 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         }
 
         for gate in 0..self.num_gates() {
-            for input in 0..NUM_WIRES {
+            for input in 0..self.num_wires {
                 partitions.add_partition(Target::Wire(Wire { gate, input }));
             }
         }
@@ -1204,3 +1638,323 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         partitions
     }
 }
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+
+    use crate::plonk_util::permutation_polynomial;
+    use crate::{hash_fields, ArithmeticGate, BufferGate, Circuit, CircuitBuilder, Curve, Field, Gate, HaloCurve, PartialWitness, PlonkyError, PublicInput, Target, Tweedledee, Wire, WitnessGenerator, NUM_BLINDING_GATES};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    fn build_sample_circuit() -> Circuit<Tweedledee> {
+        build_sample_circuit_with_zk(true)
+    }
+
+    fn build_sample_circuit_with_zk(zero_knowledge: bool) -> Circuit<Tweedledee> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        builder.set_zero_knowledge(zero_knowledge);
+        let pis = builder.add_public_inputs(3);
+        let consts = (0..20)
+            .map(|i| builder.constant_wire(F::from_canonical_usize(i * 7919)))
+            .collect::<Vec<_>>();
+        let sum = builder.add_many(&consts);
+        let product = builder.mul_many(&pis);
+        let is_zero = builder.is_zero(product);
+        let result = builder.select(is_zero, sum, product);
+        let hash = builder.rescue_hash_n_to_1(&[result, pis[0]]);
+        builder.assert_nonzero(hash);
+        builder.build()
+    }
+
+    #[test]
+    fn test_degree() {
+        for &zero_knowledge in &[false, true] {
+            // Enough gates that the blinding and public input gates cross a power of two.
+            let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+            builder.set_zero_knowledge(zero_knowledge);
+            let pis = builder.add_public_inputs(10);
+            let product = builder.mul_many(&pis);
+            builder.assert_nonzero(product);
+            while builder.num_gates() < 60 {
+                builder.add_gate_no_constants(BufferGate::new(builder.num_gates()));
+            }
+            let degree = builder.degree();
+            assert_eq!(degree, builder.build().degree());
+        }
+    }
+
+    #[test]
+    fn test_deterministic_build() {
+        let circuit_1 = build_sample_circuit();
+        let circuit_2 = build_sample_circuit();
+        assert!(circuit_1.prover_key == circuit_2.prover_key);
+        assert_eq!(circuit_1.to_vk(), circuit_2.to_vk());
+        assert_eq!(circuit_1.to_vk().fingerprint(), circuit_2.to_vk().fingerprint());
+    }
+
+    #[test]
+    fn test_blinding_gates() {
+        let circuit = build_sample_circuit_with_zk(true);
+        let circuit_no_zk = build_sample_circuit_with_zk(false);
+        assert_eq!(
+            circuit.num_gates_without_pis,
+            circuit_no_zk.num_gates_without_pis + NUM_BLINDING_GATES
+        );
+
+        let sample_inputs = || {
+            let mut inputs = PartialWitness::new();
+            for i in 0..3 {
+                inputs.set_target(
+                    Target::PublicInput(PublicInput::new(i)),
+                    F::from_canonical_usize(i + 1),
+                );
+            }
+            inputs
+        };
+        let witness_1 = circuit.generate_witness(sample_inputs()).unwrap();
+        let witness_2 = circuit.generate_witness(sample_inputs()).unwrap();
+        assert_ne!(witness_1, witness_2);
+
+        // Z should only differ at the three gates following the first blinding gate.
+        let (beta, gamma) = (F::rand(), F::rand());
+        let z = |witness| {
+            permutation_polynomial(
+                circuit.degree(),
+                &circuit.subgroup_n,
+                witness,
+                &circuit.s_sigma_values_8n,
+                beta,
+                gamma,
+            )
+        };
+        let (z_1, z_2) = (z(&witness_1), z(&witness_2));
+        let first_blinding_gate = circuit.num_gates_without_pis - NUM_BLINDING_GATES;
+        for i in 0..circuit.degree() {
+            if i > first_blinding_gate && i < first_blinding_gate + NUM_BLINDING_GATES {
+                assert_ne!(z_1[i], z_2[i]);
+            } else {
+                assert_eq!(z_1[i], z_2[i]);
+            }
+        }
+
+        // Without blinding gates, witness generation is deterministic.
+        assert_eq!(
+            circuit_no_zk.generate_witness(sample_inputs()).unwrap(),
+            circuit_no_zk.generate_witness(sample_inputs()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stalled_witness_generation() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let x_inv = builder.inv(x);
+        builder.assert_one(x_inv);
+        let circuit = builder.build();
+
+        // x was never set, so the inverse generator should be blocked on it.
+        let err = circuit.generate_witness(PartialWitness::new()).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PlonkyError>(),
+            Some(PlonkyError::WitnessGenerationStalled(_))
+        ));
+        let err = err.to_string();
+        assert!(err.contains("InverseGenerator"), "{}", err);
+        assert!(err.contains(&format!("{:?}", x)), "{}", err);
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::ONE);
+        assert!(circuit.generate_witness(inputs).is_ok());
+    }
+
+    #[test]
+    fn test_conflicting_copy() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        builder.copy(x, y);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::ONE);
+        inputs.set_target(y, F::TWO);
+        let err = circuit.generate_witness(inputs).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PlonkyError>(),
+            Some(PlonkyError::ConflictingValues(_))
+        ));
+        let err = err.to_string();
+        assert!(err.contains("Copy constraint violated"), "{}", err);
+    }
+
+    /// A gate whose only constraint, `w_0^3`, has degree 9 with the buffer gate's 6-bit prefix.
+    struct CubeGate;
+
+    impl<C: HaloCurve> Gate<C> for CubeGate {
+        const NAME: &'static str = "CubeGate";
+
+        const PREFIX: &'static [bool] = BufferGate::<C>::PREFIX;
+
+        fn evaluate_unfiltered(
+            _local_constant_values: &[C::ScalarField],
+            local_wire_values: &[C::ScalarField],
+            _right_wire_values: &[C::ScalarField],
+            _below_wire_values: &[C::ScalarField],
+        ) -> Vec<C::ScalarField> {
+            vec![local_wire_values[0].cube()]
+        }
+
+        fn evaluate_unfiltered_recursively(
+            builder: &mut CircuitBuilder<C>,
+            _local_constant_values: &[Target<C::ScalarField>],
+            local_wire_values: &[Target<C::ScalarField>],
+            _right_wire_values: &[Target<C::ScalarField>],
+            _below_wire_values: &[Target<C::ScalarField>],
+        ) -> Vec<Target<C::ScalarField>> {
+            let x = local_wire_values[0];
+            let x_squared = builder.square(x);
+            vec![builder.mul(x_squared, x)]
+        }
+    }
+
+    impl<F: Field> WitnessGenerator<F> for CubeGate {
+        fn dependencies(&self) -> Vec<Target<F>> {
+            Vec::new()
+        }
+
+        fn generate(&self, _constants: &[Vec<F>], _witness: &PartialWitness<F>) -> PartialWitness<F> {
+            PartialWitness::new()
+        }
+    }
+
+    #[test]
+    fn test_constraint_degree_too_high() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        builder.add_gate_no_constants(CubeGate);
+        let err = match builder.try_build() {
+            Ok(_) => panic!("A degree 9 constraint should be rejected"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.downcast_ref::<PlonkyError>(),
+            Some(&PlonkyError::ConstraintDegreeTooHigh {
+                gate: "CubeGate",
+                constraint: 0,
+                degree: 9,
+                max_degree: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_rollback() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let x_sq = builder.square(x);
+
+        // Speculatively build x^4 in two ways, keeping the cheaper one.
+        let checkpoint = builder.checkpoint();
+        builder.mul_many(&[x, x, x, x]);
+        let mul_many_gates = builder.num_gates() - checkpoint.num_gates();
+        builder.rollback(&checkpoint);
+        assert_eq!(builder.num_gates(), checkpoint.num_gates());
+
+        let x_4_alt = builder.square(x_sq);
+        let square_gates = builder.num_gates() - checkpoint.num_gates();
+        assert!(square_gates < mul_many_gates);
+
+        let circuit = builder.build();
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::TWO);
+        let witness = circuit.generate_partial_witness(inputs).unwrap();
+        assert_eq!(witness.get_target(x_4_alt), F::from_canonical_usize(16));
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let arithmetic_gates = |builder: &CircuitBuilder<Tweedledee>| {
+            builder.gate_counts.get(ArithmeticGate::<Tweedledee>::NAME).copied().unwrap_or(0)
+        };
+
+        let two = builder.two_wire();
+        let three = builder.constant_wire_u32(3);
+        let five = builder.add(two, three);
+        let six = builder.mul(two, three);
+        let neg_one = builder.sub(five, six);
+        let eleven = builder.mul_add(two, three, five);
+        assert_eq!(arithmetic_gates(&builder), 0);
+        assert_eq!(builder.constant_value(neg_one), Some(F::NEG_ONE));
+        assert_eq!(builder.constant_value(eleven), Some(F::from_canonical_u32(11)));
+        assert_eq!(neg_one, builder.neg_one_wire());
+
+        // Operations involving a non-constant operand still emit a gate.
+        let x = builder.add_virtual_target();
+        let sum = builder.add(x, two);
+        assert_eq!(builder.constant_value(sum), None);
+        assert_eq!(arithmetic_gates(&builder), 1);
+    }
+
+    #[test]
+    fn test_add_const_mul_const() -> Result<()> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let one = builder.one();
+        let zero = builder.zero();
+        let num_constant_wires = builder.constant_wires.len();
+
+        let x_plus_three = builder.add_const(x, F::from_canonical_u32(3));
+        let x_times_five = builder.mul_const(x, F::from_canonical_u32(5));
+        let neg_x = builder.neg(x);
+        assert_eq!(builder.constant_wires.len(), num_constant_wires, "A constant was allocated a target");
+        assert_eq!(builder.add_const(x, F::ZERO), x);
+        assert_eq!(builder.mul_const(x, F::ONE), x);
+        assert_eq!(builder.mul_const(x, F::ZERO), zero);
+        assert_eq!(builder.add_const(one, F::ONE), builder.two_wire());
+        assert_eq!(builder.neg_one(), builder.neg_one_wire());
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::from_canonical_u32(7));
+        let witness = circuit.generate_partial_witness(inputs)?;
+        assert_eq!(witness.get_target(x_plus_three), F::from_canonical_u32(10));
+        assert_eq!(witness.get_target(x_times_five), F::from_canonical_u32(35));
+        assert_eq!(witness.get_target(neg_x), -F::from_canonical_u32(7));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be copy-constrained")]
+    fn test_copy_advice_wires() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new_with_width(128, 10, 6);
+        assert_eq!(builder.num_advice_wires(), 4);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let sum = builder.add(x, y);
+        let gate = match sum {
+            Target::Wire(wire) => wire.gate,
+            _ => unreachable!(),
+        };
+        // Copying an advice wire to a virtual target is fine, but copying it to another wire isn't.
+        let advice = Target::Wire(Wire { gate, input: 9 });
+        let z = builder.add_virtual_target();
+        builder.copy(z, advice);
+        builder.copy(sum, advice);
+        builder.build();
+    }
+
+    #[test]
+    fn test_hash_fields_consistency() {
+        for num_inputs in 0..5 {
+            let inputs = (0..num_inputs).map(|_| F::rand()).collect::<Vec<_>>();
+            let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+            let targets = builder.constant_wires(&inputs);
+            let hash = builder.hash_fields(&targets);
+            let circuit = builder.build();
+            let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
+            assert_eq!(witness.get_target(hash), hash_fields(&inputs, 128));
+        }
+    }
+}
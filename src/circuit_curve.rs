@@ -1,6 +1,6 @@
 #![allow(clippy::type_complexity)]
 use crate::plonk_util::halo_n;
-use crate::{blake_hash_base_field_to_curve, AffinePoint, Base4SumGate, BufferGate, CircuitBuilder, Curve, CurveAddGate, CurveDblGate, CurveEndoGate, Field, HaloCurve, PartialWitness, Target, Wire, WitnessGenerator};
+use crate::{blake_hash_base_field_to_curve, AffinePoint, Base4SumGate, BufferGate, CircuitBuilder, Curve, CurveAddGate, CurveDblGate, CurveEndoGate, Field, HaloCurve, PartialWitness, Target, Wire, WitnessGenerator, GRID_WIDTH};
 use std::marker::PhantomData;
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -33,6 +33,11 @@ pub struct CurveMsmEndoResult<C: Curve, InnerC: Curve<BaseField = C::ScalarField
     /// While `msm` computes a sum of `[s] P` terms, `msm_endo` computes a sum of `[n(s)] P` terms
     /// for some injective `n`. Here we return each `n(s)`, i.e., the scalar by which the point was
     /// actually multiplied.
+    ///
+    /// Note that `n(s)` really lives in `InnerC::ScalarField`, whereas these are accumulated in the
+    /// "wrong" field `C::ScalarField`, using its own `zeta`. They are thus only meaningful for
+    /// comparing the scalars of different multiplications, e.g. that `[n(s)] P` and
+    /// `[1 / n(s)] Q` used the same `s`.
     pub actual_scalars: Vec<Target<C::ScalarField>>,
 }
 
@@ -58,6 +63,20 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         self.copy(affine_target_1.y, affine_target_2.y);
     }
 
+    /// Selects `p1` or `p2` based on `b`, which is assumed to be binary.
+    /// In particular, this returns `if b { p1 } else { p2 }`.
+    pub fn curve_select<InnerC: Curve<BaseField = C::ScalarField>>(
+        &mut self,
+        b: Target<C::ScalarField>,
+        p1: AffinePointTarget<InnerC>,
+        p2: AffinePointTarget<InnerC>,
+    ) -> AffinePointTarget<InnerC> {
+        AffinePointTarget {
+            x: self.select(b, p1.x, p2.x),
+            y: self.select(b, p1.y, p2.y),
+        }
+    }
+
     /// Assert that a given coordinate pair is on the curve `C`.
     pub fn curve_assert_valid<InnerC: Curve<BaseField = C::ScalarField>>(
         &mut self,
@@ -221,7 +240,7 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         struct ResultGenerator<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
             mul: CurveMulOp<C, InnerC>,
             result: AffinePointTarget<InnerC>,
-            security_bits: usize,
+            challenge_len: usize,
             _phantom: PhantomData<C>,
         }
 
@@ -245,7 +264,7 @@ impl<C: HaloCurve> CircuitBuilder<C> {
                     .get_target(self.mul.scalar.convert())
                     .try_convert::<InnerC::ScalarField>()
                     .expect("Improbable");
-                let scalar_bits = &scalar.to_canonical_bool_vec()[..self.security_bits];
+                let scalar_bits = &scalar.to_canonical_bool_vec()[..self.challenge_len];
                 let n_scalar = halo_n::<InnerC>(scalar_bits);
                 let n_scalar_inv = n_scalar
                     .multiplicative_inverse()
@@ -264,7 +283,7 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         self.add_generator(ResultGenerator::<C, InnerC> {
             mul,
             result,
-            security_bits: self.security_bits,
+            challenge_len: self.challenge_len,
             _phantom: PhantomData,
         });
 
@@ -300,8 +319,9 @@ impl<C: HaloCurve> CircuitBuilder<C> {
         // random nonzero point and subtract it later. This avoids exceptional cases with high
         // probability. A malicious prover may be able to craft an input which leads to an
         // exceptional case, but this isn't a problem as our curve gates will be unsatisfiable in
-        // exceptional cases.
-        let mut filler = blake_hash_base_field_to_curve::<InnerC>(InnerC::BaseField::ZERO);
+        // exceptional cases. Note that the Pedersen generators are hashed from small seeds, so we
+        // use a different seed, as commitments such as [1] G_0 are common.
+        let mut filler = blake_hash_base_field_to_curve::<InnerC>(InnerC::BaseField::NEG_ONE);
         let mut acc = self.constant_affine_point(filler);
         let mut scalar_accs = vec![self.zero_wire(); parts.len()];
 
@@ -410,20 +430,30 @@ impl<C: HaloCurve> CircuitBuilder<C> {
     }
 
     /// Like `curve_msm`, but uses the endomorphism described in the Halo paper.
+    ///
+    /// Each iteration of the loop is laid out in its own row of `GRID_WIDTH` gates, since
+    /// `CurveEndoGate` stores its updated scalar accumulators in the gate below. This means that at
+    /// most `GRID_WIDTH - 1` terms are supported.
     pub fn curve_msm_endo<InnerC: HaloCurve<BaseField = C::ScalarField>>(
         &mut self,
         parts: &[CurveMulOp<C, InnerC>],
     ) -> CurveMsmEndoResult<C, InnerC> {
+        assert!(
+            parts.len() < GRID_WIDTH,
+            "An MSM can have at most {} terms",
+            GRID_WIDTH - 1
+        );
         let zero = self.zero_wire();
 
         // We assume each most significant bit is unset; see the note in curve_msm's method doc.
         let f_bits = C::ScalarField::BITS - 1;
-        let scalar_bits = self.security_bits;
+        let scalar_bits = self.challenge_len;
         let scalar_dibits = (f_bits - scalar_bits) / 2;
 
-        // To keep things simple for now, we only handle the case of |F| ~= 2^254 and lambda = 128.
+        // To keep things simple for now, we only handle the case of |F| ~= 2^254 and challenges of
+        // 128 bits.
         assert_eq!(f_bits, 254);
-        assert_eq!(scalar_bits, 128);
+        assert_eq!(scalar_bits, 128, "Only 128-bit challenges are supported in circuits");
         assert_eq!(scalar_dibits, 63);
 
         // We split each scalar into 128 bits and 63 dibits. The bits are used in the MSM, while the
@@ -439,36 +469,73 @@ impl<C: HaloCurve> CircuitBuilder<C> {
             })
             .unzip();
 
-        // Normally we would start with zero, but to avoid exceptional cases, we start with some
-        // random nonzero point and subtract it later. This avoids exceptional cases with high
-        // probability. A malicious prover may be able to craft an input which leads to an
-        // exceptional case, but this isn't a problem as our curve gates will be unsatisfiable in
-        // exceptional cases.
-        let mut filler = blake_hash_base_field_to_curve::<InnerC>(InnerC::BaseField::ZERO);
-        let mut acc = self.constant_affine_point(filler);
-
         // For each scalar, we maintain two accumulators. The unsigned one is for computing a
         // weighted sum of bits and dibits in the usual manner, so that we can later check that this
         // sum equals the original scalar. The signed one is for computing n(s) for each scalar s.
         // This is the "actual" scalar by which the associated point was multiplied, accounting for
         // the endomorphism.
+        // Since each accumulation step multiplies the unsigned sum by 4, we start by accumulating
+        // the dibits, which are the most significant limbs, starting from the most significant one.
         let mut scalar_acc_unsigned = Vec::new();
-        let mut scalar_acc_signed = Vec::new();
+        for dibits in &all_dibits {
+            let dibits_rev: Vec<_> = dibits.iter().rev().copied().collect();
+            let mut acc = zero;
+            for dibits_chunk in dibits_rev.chunks(Base4SumGate::<C>::NUM_LIMBS) {
+                assert_eq!(dibits_chunk.len(), Base4SumGate::<C>::NUM_LIMBS);
+
+                let gate = self.num_gates();
+                self.add_gate_no_constants(Base4SumGate::new(gate));
+                self.copy(
+                    acc,
+                    Target::Wire(Wire {
+                        gate,
+                        input: Base4SumGate::<C>::WIRE_ACC_OLD,
+                    }),
+                );
+                acc = Target::Wire(Wire {
+                    gate,
+                    input: Base4SumGate::<C>::WIRE_ACC_NEW,
+                });
+
+                for (i, &dibit) in dibits_chunk.iter().enumerate() {
+                    self.copy(
+                        dibit,
+                        Target::Wire(Wire {
+                            gate,
+                            input: Base4SumGate::<C>::wire_limb(i),
+                        }),
+                    );
+                }
+            }
+            scalar_acc_unsigned.push(acc);
+        }
+
+        // Normally we would start with zero, but to avoid exceptional cases, we start with some
+        // random nonzero point and subtract it later. This avoids exceptional cases with high
+        // probability. A malicious prover may be able to craft an input which leads to an
+        // exceptional case, but this isn't a problem as our curve gates will be unsatisfiable in
+        // exceptional cases. Note that the Pedersen generators are hashed from small seeds, so we
+        // use a different seed, as commitments such as [1] G_0 are common.
+        let mut filler = blake_hash_base_field_to_curve::<InnerC>(InnerC::BaseField::NEG_ONE);
+        let mut acc = self.constant_affine_point(filler);
 
-        // As in the Halo paper, we process two scalar bits at a time.
+        // As in the Halo paper, we process two scalar bits at a time, starting with the most
+        // significant pair.
         for i in (0..scalar_bits).step_by(2).rev() {
+            let row_start = self.num_gates();
+
             // Route the point accumulator to the first gate's inputs.
             self.copy(
                 acc.x,
                 Target::Wire(Wire {
-                    gate: self.num_gates(),
+                    gate: row_start,
                     input: CurveEndoGate::<C, InnerC>::WIRE_GROUP_ACC_X,
                 }),
             );
             self.copy(
                 acc.y,
                 Target::Wire(Wire {
-                    gate: self.num_gates(),
+                    gate: row_start,
                     input: CurveEndoGate::<C, InnerC>::WIRE_GROUP_ACC_Y,
                 }),
             );
@@ -509,10 +576,12 @@ impl<C: HaloCurve> CircuitBuilder<C> {
                     }),
                 );
 
-                // If this is the first pair of scalar bits being processed, route 0 to the scalar accumulators.
+                // If this is the first pair of scalar bits being processed, route the sum of
+                // dibits and 0 to the scalar accumulators. Afterwards, each CurveEndoGate receives
+                // them from the gate above.
                 if i == scalar_bits - 2 {
                     self.copy(
-                        zero,
+                        scalar_acc_unsigned[j],
                         Target::Wire(Wire {
                             gate,
                             input: CurveEndoGate::<C, InnerC>::WIRE_SCALAR_ACC_UNSIGNED,
@@ -526,23 +595,6 @@ impl<C: HaloCurve> CircuitBuilder<C> {
                         }),
                     );
                 }
-
-                // If this is the last pair of scalar bits being processed, save the final
-                // accumulator states.
-                // Since CurveEndoGate will store these in the "next" gate, but this is the last
-                // CurveEndoGate for this scalar, we need to add an extra BufferGate to receive them.
-                if i == 0 {
-                    let gate = self.num_gates();
-                    self.add_gate_no_constants(BufferGate::new(gate));
-                    scalar_acc_unsigned.push(Target::Wire(Wire {
-                        gate,
-                        input: CurveEndoGate::<C, InnerC>::WIRE_SCALAR_ACC_UNSIGNED,
-                    }));
-                    scalar_acc_signed.push(Target::Wire(Wire {
-                        gate,
-                        input: CurveEndoGate::<C, InnerC>::WIRE_SCALAR_ACC_SIGNED,
-                    }));
-                }
             }
 
             // Double the accumulator.
@@ -579,43 +631,34 @@ impl<C: HaloCurve> CircuitBuilder<C> {
                 // Also double the filler, so we can subtract out a rescaled version later.
                 filler = filler.double();
             }
+
+            // Pad the rest of the row, so that the next row's CurveEndoGates are below this row's.
+            while self.num_gates() < row_start + GRID_WIDTH {
+                let gate = self.num_gates();
+                self.add_gate_no_constants(BufferGate::new(gate));
+            }
+        }
+
+        // The last row of CurveEndoGates stores the final scalar accumulators in the gates below,
+        // so we need a row of BufferGates to receive them.
+        let mut scalar_acc_signed = Vec::new();
+        for acc_unsigned in scalar_acc_unsigned.iter_mut() {
+            let gate = self.num_gates();
+            self.add_gate_no_constants(BufferGate::new(gate));
+            *acc_unsigned = Target::Wire(Wire {
+                gate,
+                input: CurveEndoGate::<C, InnerC>::WIRE_SCALAR_ACC_UNSIGNED,
+            });
+            scalar_acc_signed.push(Target::Wire(Wire {
+                gate,
+                input: CurveEndoGate::<C, InnerC>::WIRE_SCALAR_ACC_SIGNED,
+            }));
         }
 
         // Subtract (a rescaled version of) the arbitrary nonzero value that we started with.
         let filler_target = self.constant_affine_point(filler);
         acc = self.curve_sub::<InnerC>(acc, filler_target);
 
-        // By now we've accumulated all the bits of each scalar, but we also need to accumulate the dibits.
-        for j in 0..parts.len() {
-            for dibits_chunk in all_dibits[j].chunks(Base4SumGate::<C>::NUM_LIMBS) {
-                assert_eq!(dibits_chunk.len(), Base4SumGate::<C>::NUM_LIMBS);
-
-                let gate = self.num_gates();
-                self.add_gate_no_constants(Base4SumGate::new(gate));
-                self.copy(
-                    scalar_acc_unsigned[j],
-                    Target::Wire(Wire {
-                        gate,
-                        input: Base4SumGate::<C>::WIRE_ACC_OLD,
-                    }),
-                );
-                scalar_acc_unsigned[j] = Target::Wire(Wire {
-                    gate,
-                    input: Base4SumGate::<C>::WIRE_ACC_NEW,
-                });
-
-                for (i, &dibit) in dibits_chunk.iter().enumerate() {
-                    self.copy(
-                        dibit,
-                        Target::Wire(Wire {
-                            gate,
-                            input: Base4SumGate::<C>::wire_limb(i),
-                        }),
-                    );
-                }
-            }
-        }
-
         // Finally, assert that each unsigned accumulator matches the original scalar.
         for (j, part) in parts.iter().enumerate() {
             self.copy(scalar_acc_unsigned[j], part.scalar.convert());
@@ -635,8 +678,6 @@ mod tests {
     use crate::{verify_proof, CircuitBuilder, Curve, CurveMulOp, Field, PartialWitness, Tweedledee, Tweedledum};
 
     #[test]
-    // TODO: This fails because curve_mul_endo has a flaw.
-    #[ignore]
     fn test_curve_mul_inv_endo() -> Result<()> {
         type C = Tweedledee;
         type InnerC = Tweedledum;
@@ -661,8 +702,8 @@ mod tests {
         builder.copy_curve(p1, p3);
 
         let circuit = builder.build();
-        let witness = circuit.generate_witness(PartialWitness::new());
-        let proof = circuit.generate_proof::<InnerC>(&witness, &[], false)?;
+        let witness = circuit.generate_witness(PartialWitness::new())?;
+        let proof = circuit.generate_proof::<InnerC>(&witness, &[])?;
         let vk = circuit.to_vk();
         verify_proof::<C, InnerC>(&[], &proof, &[], &vk, true)?;
 
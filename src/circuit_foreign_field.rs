@@ -90,7 +90,7 @@ mod tests {
         let z = builder.foreign_field_add::<FF>(&x, &y);
         let circuit = builder.build();
 
-        let witness = circuit.generate_partial_witness(PartialWitness::new());
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
         let actual_z_value: FF = witness.get_foreign_field_target(&z);
         assert_eq!(actual_z_value, expected_z_value);
     }
@@ -110,7 +110,7 @@ mod tests {
         let z = builder.foreign_field_mul::<FF>(&x, &y);
         let circuit = builder.build();
 
-        let witness = circuit.generate_partial_witness(PartialWitness::new());
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
         let actual_z_value: FF = witness.get_foreign_field_target(&z);
         assert_eq!(actual_z_value, expected_z_value);
     }
@@ -0,0 +1,183 @@
+use std::convert::TryInto;
+use std::fmt::Debug;
+
+use anyhow::{ensure, Result};
+
+use crate::{CircuitBuilder, Field, HaloCurve, PartialWitness, Target};
+
+/// A value which a circuit takes as inputs or exposes as public inputs, encoded as a fixed number
+/// of field elements. Structs can derive this with `#[derive(CircuitIo)]`, which encodes their
+/// fields in order, and generates a struct of the fields' targets named after the original one:
+///
+/// ```ignore
+/// #[derive(CircuitIo)]
+/// struct Transfer<F: Field> {
+///     amount: F,
+///     balances: [F; 2],
+/// }
+///
+/// let targets: TransferTargets<F> = builder.add_public_input_io::<Transfer<F>>();
+/// builder.assert_nonzero(targets.amount);
+/// // ...
+/// witness.set_io(&targets, &transfer);
+/// let transfer = Transfer::from_elements(&public_inputs)?;
+/// ```
+///
+/// The derive requires the struct to have a single type parameter, the field, and every field to
+/// implement `CircuitIo` itself. Field elements, arrays and other derived structs do.
+pub trait CircuitIo<F: Field>: Sized {
+    /// The targets of a value in a circuit.
+    type Targets: Clone + Debug;
+
+    /// The number of field elements in the encoding.
+    fn num_elements() -> usize;
+
+    /// Takes the targets of a value from the front of `targets`.
+    fn read_targets(targets: &mut &[Target<F>]) -> Self::Targets;
+
+    /// Appends the targets of a value to `out`.
+    fn write_targets(targets: &Self::Targets, out: &mut Vec<Target<F>>);
+
+    /// Decodes a value from the front of `elements`.
+    fn read_elements(elements: &mut &[F]) -> Self;
+
+    /// Appends the encoding of this value to `out`.
+    fn write_elements(&self, out: &mut Vec<F>);
+
+    fn targets_to_vec(targets: &Self::Targets) -> Vec<Target<F>> {
+        let mut out = Vec::with_capacity(Self::num_elements());
+        Self::write_targets(targets, &mut out);
+        out
+    }
+
+    fn to_elements(&self) -> Vec<F> {
+        let mut out = Vec::with_capacity(Self::num_elements());
+        self.write_elements(&mut out);
+        out
+    }
+
+    /// Decodes a value from exactly `num_elements` field elements, such as the public inputs of a
+    /// circuit whose public inputs were added with `add_public_input_io`.
+    fn from_elements(elements: &[F]) -> Result<Self> {
+        ensure!(
+            elements.len() == Self::num_elements(),
+            "Expected {} elements, got {}",
+            Self::num_elements(),
+            elements.len()
+        );
+        Ok(Self::read_elements(&mut &elements[..]))
+    }
+}
+
+/// Takes the first element of `slice`, panicking if it's empty.
+fn take_first<T: Copy>(slice: &mut &[T]) -> T {
+    let (&first, rest) = slice.split_first().expect("Too few elements to decode");
+    *slice = rest;
+    first
+}
+
+impl<F: Field> CircuitIo<F> for F {
+    type Targets = Target<F>;
+
+    fn num_elements() -> usize {
+        1
+    }
+
+    fn read_targets(targets: &mut &[Target<F>]) -> Target<F> {
+        take_first(targets)
+    }
+
+    fn write_targets(targets: &Target<F>, out: &mut Vec<Target<F>>) {
+        out.push(*targets);
+    }
+
+    fn read_elements(elements: &mut &[F]) -> F {
+        take_first(elements)
+    }
+
+    fn write_elements(&self, out: &mut Vec<F>) {
+        out.push(*self);
+    }
+}
+
+impl<F: Field, T: CircuitIo<F>, const N: usize> CircuitIo<F> for [T; N] {
+    type Targets = Vec<T::Targets>;
+
+    fn num_elements() -> usize {
+        N * T::num_elements()
+    }
+
+    fn read_targets(targets: &mut &[Target<F>]) -> Vec<T::Targets> {
+        (0..N).map(|_| T::read_targets(targets)).collect()
+    }
+
+    fn write_targets(targets: &Vec<T::Targets>, out: &mut Vec<Target<F>>) {
+        for t in targets {
+            T::write_targets(t, out);
+        }
+    }
+
+    fn read_elements(elements: &mut &[F]) -> Self {
+        let values = (0..N).map(|_| T::read_elements(elements)).collect::<Vec<_>>();
+        values.try_into().unwrap_or_else(|_| unreachable!())
+    }
+
+    fn write_elements(&self, out: &mut Vec<F>) {
+        for value in self.iter() {
+            value.write_elements(out);
+        }
+    }
+}
+
+impl<C: HaloCurve> CircuitBuilder<C> {
+    /// Adds virtual targets for a value of type `T`.
+    pub fn add_virtual_io<T: CircuitIo<C::ScalarField>>(&mut self) -> T::Targets {
+        let targets = self.add_virtual_targets(T::num_elements());
+        T::read_targets(&mut &targets[..])
+    }
+
+    /// Adds public inputs for a value of type `T`. Its fields are the next public inputs, in
+    /// order, so if these are the only public inputs, `T::from_elements` decodes them.
+    pub fn add_public_input_io<T: CircuitIo<C::ScalarField>>(&mut self) -> T::Targets {
+        let targets = self.add_public_inputs(T::num_elements());
+        T::read_targets(&mut &targets[..])
+    }
+}
+
+impl<F: Field> PartialWitness<F> {
+    /// Sets the targets of a value of type `T`.
+    pub fn set_io<T: CircuitIo<F>>(&mut self, targets: &T::Targets, value: &T) {
+        self.set_targets(&T::targets_to_vec(targets), &value.to_elements());
+    }
+
+    /// Reads a value of type `T` from its targets, which must all be set.
+    pub fn get_io<T: CircuitIo<F>>(&self, targets: &T::Targets) -> T {
+        let elements = self.get_targets(&T::targets_to_vec(targets));
+        T::read_elements(&mut &elements[..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CircuitBuilder, CircuitIo, Curve, Field, PartialWitness, Target, Tweedledee};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_array_io() {
+        let value = [[F::ONE, F::TWO], [F::ZERO, F::NEG_ONE]];
+        assert_eq!(<[[F; 2]; 2]>::num_elements(), 4);
+        assert_eq!(value.to_elements(), vec![F::ONE, F::TWO, F::ZERO, F::NEG_ONE]);
+        assert_eq!(<[[F; 2]; 2]>::from_elements(&value.to_elements()).unwrap(), value);
+        assert!(<[[F; 2]; 2]>::from_elements(&[F::ONE]).is_err());
+
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let targets = builder.add_public_input_io::<[[F; 2]; 2]>();
+        assert_eq!(targets.len(), 2);
+        assert!(matches!(targets[1][0], Target::PublicInput(pi) if pi.index == 2));
+
+        let mut witness = PartialWitness::new();
+        witness.set_io(&targets, &value);
+        assert_eq!(witness.get_io::<[[F; 2]; 2]>(&targets), value);
+    }
+}
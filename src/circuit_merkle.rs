@@ -0,0 +1,63 @@
+use crate::{AlgebraicHasher, CircuitBuilder, HaloCurve, RescueHasher, Target};
+
+impl<C: HaloCurve> CircuitBuilder<C> {
+    /// Computes the root implied by a leaf, the bits of its index and the siblings on its path,
+    /// consistent with the native `merkle_root_from_proof`. `index_bits` are little-endian, and
+    /// are constrained to be binary.
+    pub fn merkle_root_from_proof(
+        &mut self,
+        leaf: Target<C::ScalarField>,
+        index_bits: &[Target<C::ScalarField>],
+        siblings: &[Target<C::ScalarField>],
+    ) -> Target<C::ScalarField> {
+        self.merkle_root_from_proof_with_hasher::<RescueHasher>(leaf, index_bits, siblings)
+    }
+
+    /// Like `merkle_root_from_proof`, but for trees built with the given hasher.
+    pub fn merkle_root_from_proof_with_hasher<H: AlgebraicHasher<C::ScalarField>>(
+        &mut self,
+        leaf: Target<C::ScalarField>,
+        index_bits: &[Target<C::ScalarField>],
+        siblings: &[Target<C::ScalarField>],
+    ) -> Target<C::ScalarField> {
+        assert_eq!(
+            index_bits.len(),
+            siblings.len(),
+            "Need one index bit per sibling"
+        );
+
+        let mut current = leaf;
+        for (&bit, &sibling) in index_bits.iter().zip(siblings) {
+            self.assert_binary(bit);
+            // If the bit is set, the current node is a right child.
+            let left = self.select(bit, sibling, current);
+            let right = self.select(bit, current, sibling);
+            current = self.sponge_with_hasher::<H>(&[left, right], 1)[0];
+        }
+        current
+    }
+
+    /// Asserts that `leaf` is present at the given index of the Merkle tree with the given root,
+    /// consistent with the native `verify_merkle_proof`.
+    pub fn verify_merkle_proof(
+        &mut self,
+        leaf: Target<C::ScalarField>,
+        index_bits: &[Target<C::ScalarField>],
+        siblings: &[Target<C::ScalarField>],
+        root: Target<C::ScalarField>,
+    ) {
+        self.verify_merkle_proof_with_hasher::<RescueHasher>(leaf, index_bits, siblings, root);
+    }
+
+    /// Like `verify_merkle_proof`, but for trees built with the given hasher.
+    pub fn verify_merkle_proof_with_hasher<H: AlgebraicHasher<C::ScalarField>>(
+        &mut self,
+        leaf: Target<C::ScalarField>,
+        index_bits: &[Target<C::ScalarField>],
+        siblings: &[Target<C::ScalarField>],
+        root: Target<C::ScalarField>,
+    ) {
+        let computed_root = self.merkle_root_from_proof_with_hasher::<H>(leaf, index_bits, siblings);
+        self.copy(computed_root, root);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::r1cs::arithmetic_gate;
+use crate::{CircuitBuilder, Field, HaloCurve, Target};
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// A 32-bit word, as its little-endian bits along with its value.
+#[derive(Clone)]
+struct WordTarget<F: Field> {
+    bits: Vec<Target<F>>,
+    value: Target<F>,
+}
+
+impl<C: HaloCurve> CircuitBuilder<C> {
+    /// Computes the SHA-256 digest of a message of bytes. Each byte is constrained to be less than
+    /// 256, and the 32 bytes of the digest are returned in order.
+    pub fn sha256(&mut self, message: &[Target<C::ScalarField>]) -> Vec<Target<C::ScalarField>> {
+        let mut bytes = message
+            .iter()
+            .map(|&byte| self.split_binary_checked(byte, 8))
+            .collect::<Vec<_>>();
+
+        // Pad with a one bit, zeros, and the message length in bits as a big-endian u64.
+        let bit_len = message.len() as u64 * 8;
+        let mut padding = vec![0x80u8];
+        padding.resize((119 - message.len() % 64) % 64 + 1, 0);
+        padding.extend_from_slice(&bit_len.to_be_bytes());
+        for byte in padding {
+            let bits = self.constant_bits(byte as u32, 8);
+            bytes.push(bits);
+        }
+        debug_assert_eq!(bytes.len() % 64, 0);
+
+        let mut state = INITIAL_STATE
+            .iter()
+            .map(|&word| self.constant_word(word))
+            .collect::<Vec<_>>();
+        for block in bytes.chunks(64) {
+            // Words are big-endian, so the last byte holds the lowest bits.
+            let words = block
+                .chunks(4)
+                .map(|word_bytes| {
+                    let bits = word_bytes
+                        .iter()
+                        .rev()
+                        .flatten()
+                        .copied()
+                        .collect::<Vec<_>>();
+                    self.word_from_bits(bits)
+                })
+                .collect::<Vec<_>>();
+            state = self.sha256_compress(&state, words);
+        }
+
+        // Output each word as big-endian bytes.
+        let mut digest = Vec::with_capacity(32);
+        for word in &state {
+            for byte_bits in word.bits.chunks(8).rev() {
+                digest.push(self.le_sum(byte_bits));
+            }
+        }
+        digest
+    }
+
+    fn sha256_compress(
+        &mut self,
+        state: &[WordTarget<C::ScalarField>],
+        mut schedule: Vec<WordTarget<C::ScalarField>>,
+    ) -> Vec<WordTarget<C::ScalarField>> {
+        for t in 16..64 {
+            let s0 = self.xor_rotations(&schedule[t - 15].bits, &[7, 18], Some(3));
+            let s1 = self.xor_rotations(&schedule[t - 2].bits, &[17, 19], Some(10));
+            let sum = self.add_many(&[s1, schedule[t - 7].value, s0, schedule[t - 16].value]);
+            let w = self.reduce_word(sum, 2);
+            schedule.push(w);
+        }
+
+        let mut working = state.to_vec();
+        for t in 0..64 {
+            let (a, b, c, d) = (&working[0], &working[1], &working[2], &working[3]);
+            let (e, f, g, h) = (&working[4], &working[5], &working[6], &working[7]);
+
+            let big_s1 = self.xor_rotations(&e.bits, &[6, 11, 25], None);
+            let ch = self.ch(e, f, g);
+            let k = self.constant_wire_u32(ROUND_CONSTANTS[t]);
+            let temp_1 = self.add_many(&[h.value, big_s1, ch, k, schedule[t].value]);
+            let big_s0 = self.xor_rotations(&a.bits, &[2, 13, 22], None);
+            let maj = self.maj(a, b, c);
+
+            // temp_1 is left unreduced, as it only feeds into the two sums below. Each is a sum of at
+            // most seven words, so it fits in 35 bits.
+            let new_a_value = self.add_many(&[temp_1, big_s0, maj]);
+            let new_a = self.reduce_word(new_a_value, 3);
+            let new_e_value = self.add(d.value, temp_1);
+            let new_e = self.reduce_word(new_e_value, 3);
+
+            working = vec![
+                new_a,
+                a.clone(),
+                b.clone(),
+                c.clone(),
+                new_e,
+                e.clone(),
+                f.clone(),
+                g.clone(),
+            ];
+        }
+
+        state
+            .iter()
+            .zip(&working)
+            .map(|(x, y)| {
+                let sum = self.add(x.value, y.value);
+                self.reduce_word(sum, 1)
+            })
+            .collect()
+    }
+
+    /// Computes the value of `(x >>> r_1) ^ (x >>> r_2) ^ ...`, further XORed with `x >> shift` if
+    /// present. The bits of the result are never needed, so each is accumulated into the value as
+    /// soon as it's computed.
+    fn xor_rotations(
+        &mut self,
+        x: &[Target<C::ScalarField>],
+        rotations: &[usize],
+        shift: Option<usize>,
+    ) -> Target<C::ScalarField> {
+        let mut acc = self.zero_wire();
+        for i in 0..32 {
+            let mut operands = rotations.iter().map(|&r| x[(i + r) % 32]).collect::<Vec<_>>();
+            match shift {
+                Some(shift) if i + shift < 32 => operands.push(x[i + shift]),
+                _ => (),
+            }
+            acc = self.accumulate_xor(acc, i, &operands);
+        }
+        acc
+    }
+
+    /// Computes `acc + 2^i (x_0 ^ x_1 ^ x_2)` for two or three bits. For bits, `x ^ y = (x - y)^2`,
+    /// which lets the final squaring share a gate with the accumulation.
+    fn accumulate_xor(
+        &mut self,
+        acc: Target<C::ScalarField>,
+        i: usize,
+        operands: &[Target<C::ScalarField>],
+    ) -> Target<C::ScalarField> {
+        let diff = match *operands {
+            [x, y] => self.sub(x, y),
+            [x, y, z] => {
+                let x_minus_y = self.sub(x, y);
+                self.mul_sub(x_minus_y, x_minus_y, z)
+            }
+            _ => panic!("Expected two or three operands"),
+        };
+        let weight = C::ScalarField::from_canonical_u64(1 << i);
+        arithmetic_gate(self, weight, diff, diff, C::ScalarField::ONE, acc)
+    }
+
+    /// Computes the value of `(e & f) ^ (!e & g)`, i.e. of `e * (f - g) + g` bitwise.
+    fn ch(
+        &mut self,
+        e: &WordTarget<C::ScalarField>,
+        f: &WordTarget<C::ScalarField>,
+        g: &WordTarget<C::ScalarField>,
+    ) -> Target<C::ScalarField> {
+        let mut acc = g.value;
+        for i in 0..32 {
+            let diff = self.sub(f.bits[i], g.bits[i]);
+            let weight = C::ScalarField::from_canonical_u64(1 << i);
+            acc = arithmetic_gate(self, weight, e.bits[i], diff, C::ScalarField::ONE, acc);
+        }
+        acc
+    }
+
+    /// Computes the value of the bitwise majority of `a`, `b` and `c`. For bits, the majority is
+    /// `(a + b + c - (a ^ b ^ c)) / 2`, and this holds for whole words too.
+    fn maj(
+        &mut self,
+        a: &WordTarget<C::ScalarField>,
+        b: &WordTarget<C::ScalarField>,
+        c: &WordTarget<C::ScalarField>,
+    ) -> Target<C::ScalarField> {
+        let mut parity = self.zero_wire();
+        for i in 0..32 {
+            parity = self.accumulate_xor(parity, i, &[a.bits[i], b.bits[i], c.bits[i]]);
+        }
+        let sum = self.add_many(&[a.value, b.value, c.value]);
+        let one = self.one_wire();
+        let half = C::ScalarField::TWO.multiplicative_inverse().expect("2 is invertible");
+        arithmetic_gate(self, half, sum, one, -half, parity)
+    }
+
+    /// Reduces a sum of words, which must fit in `32 + carry_bits` bits, modulo `2^32`.
+    fn reduce_word(
+        &mut self,
+        sum: Target<C::ScalarField>,
+        carry_bits: usize,
+    ) -> WordTarget<C::ScalarField> {
+        let mut bits = self.split_binary(sum, 32 + carry_bits);
+        for &bit in &bits {
+            self.assert_binary(bit);
+        }
+        let carry = bits.split_off(32);
+        let value = self.le_sum(&bits);
+        let carry = self.le_sum(&carry);
+        let two_32 = self.constant_wire(C::ScalarField::from_canonical_u64(1 << 32));
+        let recombined = self.mul_add(carry, two_32, value);
+        self.copy(recombined, sum);
+        WordTarget { bits, value }
+    }
+
+    fn word_from_bits(&mut self, bits: Vec<Target<C::ScalarField>>) -> WordTarget<C::ScalarField> {
+        let value = self.le_sum(&bits);
+        WordTarget { bits, value }
+    }
+
+    fn constant_word(&mut self, word: u32) -> WordTarget<C::ScalarField> {
+        WordTarget {
+            bits: self.constant_bits(word, 32),
+            value: self.constant_wire_u32(word),
+        }
+    }
+
+    fn constant_bits(&mut self, x: u32, num_bits: usize) -> Vec<Target<C::ScalarField>> {
+        (0..num_bits)
+            .map(|i| self.constant_wire_u32((x >> i) & 1))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{CircuitBuilder, Curve, Field, PartialWitness, Tweedledee};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    fn sha256_circuit(message: &[u8]) -> Result<Vec<u8>> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let message_targets = builder.add_virtual_targets(message.len());
+        let digest_targets = builder.sha256(&message_targets);
+        let circuit = builder.build();
+
+        let mut partial_witness = PartialWitness::new();
+        let message_values = message
+            .iter()
+            .map(|&b| F::from_canonical_u32(b as u32))
+            .collect::<Vec<_>>();
+        partial_witness.set_targets(&message_targets, &message_values);
+        let witness = circuit.generate_partial_witness(partial_witness)?;
+        Ok(witness
+            .get_targets(&digest_targets)
+            .into_iter()
+            .map(|byte| byte.to_canonical_u8_vec()[0])
+            .collect())
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    #[ignore] // Too slow to run regularly.
+    fn test_sha256() -> Result<()> {
+        assert_eq!(
+            sha256_circuit(b"abc")?,
+            from_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        Ok(())
+    }
+}
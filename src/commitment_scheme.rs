@@ -0,0 +1,297 @@
+use anyhow::{ensure, Result};
+use rand::Rng;
+
+use crate::halo::{batch_opening_proof, verify_ipa, OpeningProof};
+use crate::plonk_challenger::Transcript;
+use crate::plonk_util::{halo_g, halo_n, halo_n_mul, halo_s, pedersen_hash, powers, reduce_with_powers};
+use crate::util::log2_strict;
+use crate::{blake_hash_usize_to_curve, msm_execute_parallel, msm_precompute, AffinePoint, Curve, Field, HaloCurve, MsmPrecomputation, Polynomial, PolynomialCommitment};
+
+/// A polynomial commitment scheme which supports opening many polynomials at many points with a
+/// single proof, as Plonk requires.
+///
+/// Both `batch_open` and `batch_verify` observe the commitments, the opening points and the
+/// opened values before generating their own challenges, so the transcript passed to them should
+/// already reflect everything that led to the choice of the opening points.
+pub trait CommitmentScheme<C: Curve> {
+    type Commitment: Clone;
+    type OpeningProof: Clone;
+
+    /// The maximum number of coefficients of a committed polynomial.
+    fn max_degree(&self) -> usize;
+
+    fn commit<R: Rng>(&self, coeffs: &[C::ScalarField], rng: &mut R) -> Self::Commitment;
+
+    /// Proves the values of each polynomial in `polynomials`, which were committed to in
+    /// `commitments`, at each point in `points`.
+    fn batch_open<T: Transcript<C::BaseField>, R: Rng>(
+        &self,
+        polynomials: &[&[C::ScalarField]],
+        commitments: &[Self::Commitment],
+        points: &[C::ScalarField],
+        transcript: &mut T,
+        rng: &mut R,
+    ) -> Result<Self::OpeningProof>;
+
+    /// Verifies a proof generated by `batch_open`. `values[j][i]` is the purported value of the
+    /// `i`th polynomial at the `j`th point.
+    fn batch_verify<T: Transcript<C::BaseField>>(
+        &self,
+        commitments: &[Self::Commitment],
+        points: &[C::ScalarField],
+        values: &[Vec<C::ScalarField>],
+        proof: &Self::OpeningProof,
+        transcript: &mut T,
+    ) -> Result<()>;
+}
+
+/// Evaluates each polynomial at each point, in the layout expected by `batch_verify`.
+pub(crate) fn evaluate_at_points<F: Field>(polynomials: &[&[F]], points: &[F]) -> Vec<Vec<F>> {
+    points
+        .iter()
+        .map(|&x| {
+            polynomials
+                .iter()
+                .map(|coeffs| Polynomial::from_coeffs(coeffs).eval(x))
+                .collect()
+        })
+        .collect()
+}
+
+/// Observes the opening points and the opened values of a batch opening.
+pub(crate) fn observe_openings<C: Curve, T: Transcript<C::BaseField>>(
+    transcript: &mut T,
+    points: &[C::ScalarField],
+    values: &[Vec<C::ScalarField>],
+) -> Result<()> {
+    transcript.observe_elements(&C::try_convert_s2b_slice(points)?);
+    for point_values in values {
+        transcript.observe_elements(&C::try_convert_s2b_slice(point_values)?);
+    }
+    Ok(())
+}
+
+/// The Halo inner product argument over Pedersen commitments, i.e. the scheme our Plonk prover
+/// uses. It needs no trusted setup, and its verifier can be run recursively, apart from a linear
+/// time check of the final `G` point which can be deferred to a later proof.
+pub struct IpaCommitmentScheme<C: HaloCurve> {
+    pub pedersen_g: Vec<AffinePoint<C>>,
+    pub pedersen_h: AffinePoint<C>,
+    pub u_curve: AffinePoint<C>,
+    pub pedersen_g_msm_precomputation: MsmPrecomputation<C>,
+    pub security_bits: usize,
+    /// Whether commitments are blinded, which makes them hiding.
+    pub blinding: bool,
+}
+
+impl<C: HaloCurve> IpaCommitmentScheme<C> {
+    /// Creates an IPA scheme for polynomials with up to `degree` coefficients, using the same
+    /// generators as a circuit of that degree.
+    pub fn new(degree: usize, security_bits: usize) -> Self {
+        let pedersen_g: Vec<_> = (0..degree).map(blake_hash_usize_to_curve::<C>).collect();
+        let w = 8; // TODO: Should really be set dynamically based on MSM size.
+        let pedersen_g_msm_precomputation =
+            msm_precompute(&AffinePoint::batch_to_projective(&pedersen_g), w);
+        IpaCommitmentScheme {
+            pedersen_g,
+            pedersen_h: blake_hash_usize_to_curve(degree),
+            u_curve: blake_hash_usize_to_curve(degree + 1),
+            pedersen_g_msm_precomputation,
+            security_bits,
+            blinding: true,
+        }
+    }
+
+    fn scalar_challenge<T: Transcript<C::BaseField>>(transcript: &mut T) -> Result<C::ScalarField> {
+        transcript.get_challenge().try_convert::<C::ScalarField>()
+    }
+}
+
+impl<C: HaloCurve> CommitmentScheme<C> for IpaCommitmentScheme<C> {
+    type Commitment = PolynomialCommitment<C>;
+    type OpeningProof = OpeningProof<C>;
+
+    fn max_degree(&self) -> usize {
+        self.pedersen_g.len()
+    }
+
+    fn commit<R: Rng>(&self, coeffs: &[C::ScalarField], rng: &mut R) -> Self::Commitment {
+        assert!(coeffs.len() <= self.max_degree(), "Polynomial is too large to commit to");
+        let mut padded = coeffs.to_vec();
+        padded.resize(self.max_degree(), C::ScalarField::ZERO);
+        PolynomialCommitment::coeffs_to_commitment(
+            &padded,
+            &self.pedersen_g_msm_precomputation,
+            self.pedersen_h,
+            self.blinding,
+            rng,
+        )
+    }
+
+    fn batch_open<T: Transcript<C::BaseField>, R: Rng>(
+        &self,
+        polynomials: &[&[C::ScalarField]],
+        commitments: &[Self::Commitment],
+        points: &[C::ScalarField],
+        transcript: &mut T,
+        rng: &mut R,
+    ) -> Result<Self::OpeningProof> {
+        ensure!(
+            polynomials.len() == commitments.len(),
+            "Expected one commitment per polynomial"
+        );
+        transcript.observe_affine_points(&PolynomialCommitment::commitments_to_affine_vec(
+            commitments,
+        ));
+        observe_openings::<C, T>(transcript, points, &evaluate_at_points(polynomials, points))?;
+        let v = Self::scalar_challenge(transcript)?;
+        let u = Self::scalar_challenge(transcript)?;
+        let u_scaling = Self::scalar_challenge(transcript)?;
+
+        batch_opening_proof(
+            polynomials,
+            commitments,
+            points,
+            &self.pedersen_g,
+            self.pedersen_h.to_projective(),
+            self.u_curve,
+            u,
+            v,
+            u_scaling,
+            self.max_degree(),
+            self.security_bits,
+            transcript,
+            rng,
+        )
+    }
+
+    fn batch_verify<T: Transcript<C::BaseField>>(
+        &self,
+        commitments: &[Self::Commitment],
+        points: &[C::ScalarField],
+        values: &[Vec<C::ScalarField>],
+        proof: &Self::OpeningProof,
+        transcript: &mut T,
+    ) -> Result<()> {
+        ensure!(values.len() == points.len(), "Expected values for each point");
+        ensure!(
+            values.iter().all(|vs| vs.len() == commitments.len()),
+            "Expected one value per commitment at each point"
+        );
+        let degree_pow = log2_strict(self.max_degree());
+        ensure!(
+            proof.halo_l.len() == degree_pow && proof.halo_r.len() == degree_pow,
+            "Wrong number of IPA rounds"
+        );
+
+        let commitments = PolynomialCommitment::commitments_to_affine_vec(commitments);
+        transcript.observe_affine_points(&commitments);
+        observe_openings::<C, T>(transcript, points, values)?;
+        let v = Self::scalar_challenge(transcript)?;
+        let u = Self::scalar_challenge(transcript)?;
+        let u_scaling = Self::scalar_challenge(transcript)?;
+
+        let mut halo_us = Vec::new();
+        for (&l, &r) in proof.halo_l.iter().zip(&proof.halo_r) {
+            transcript.observe_affine_points(&[l, r]);
+            let r_sf = Self::scalar_challenge(transcript)?;
+            let r_bits = &r_sf.to_canonical_bool_vec()[..self.security_bits];
+            let u_j = halo_n::<C>(r_bits)
+                .square_root()
+                .ok_or_else(|| anyhow::anyhow!("Prover should have ensured that n(r) is square"))?;
+            halo_us.push(u_j);
+        }
+        transcript.observe_affine_point(proof.schnorr_proof.r);
+        let schnorr_challenge = Self::scalar_challenge(transcript)?;
+
+        // Reduce the commitments and values in the same way `batch_opening_proof` reduces the
+        // polynomials.
+        let actual_scalars = powers(u, commitments.len())
+            .iter()
+            .map(|u_pow| halo_n::<C>(&u_pow.to_canonical_bool_vec()[..self.security_bits]))
+            .collect::<Vec<_>>();
+        let precomputation = msm_precompute(&AffinePoint::batch_to_projective(&commitments), 8);
+        let c_reduction = msm_execute_parallel(&precomputation, &actual_scalars);
+        let value_reductions = values
+            .iter()
+            .map(|vs| C::ScalarField::inner_product(vs, &actual_scalars))
+            .collect::<Vec<_>>();
+        let reduced_value = reduce_with_powers(&value_reductions, v);
+
+        let u_prime = halo_n_mul(&u_scaling.to_canonical_bool_vec()[..self.security_bits], self.u_curve)
+            .to_projective();
+        let halo_bs = points
+            .iter()
+            .map(|&p| halo_g(p, &halo_us))
+            .collect::<Vec<_>>();
+        let halo_b = reduce_with_powers(&halo_bs, v);
+
+        ensure!(
+            verify_ipa::<C>(
+                &proof.halo_l,
+                &proof.halo_r,
+                proof.halo_g,
+                c_reduction,
+                reduced_value,
+                halo_b,
+                &halo_us,
+                u_prime,
+                self.pedersen_h,
+                schnorr_challenge,
+                proof.schnorr_proof,
+                self.security_bits,
+            ),
+            "Invalid inner product argument"
+        );
+        ensure!(
+            proof.halo_g
+                == pedersen_hash(&halo_s(&halo_us), &self.pedersen_g_msm_precomputation).to_affine(),
+            "Invalid G point"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use rand::thread_rng;
+
+    use crate::plonk_challenger::Challenger;
+    use crate::commitment_scheme::evaluate_at_points;
+    use crate::{CommitmentScheme, Curve, Field, IpaCommitmentScheme, Tweedledum};
+
+    #[test]
+    fn test_ipa_batch_opening() -> Result<()> {
+        type C = Tweedledum;
+        type SF = <C as Curve>::ScalarField;
+        let degree = 16;
+        let scheme = IpaCommitmentScheme::<C>::new(degree, 128);
+        let polynomials = (0..3)
+            .map(|_| (0..degree).map(|_| SF::rand()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let polynomials = polynomials.iter().map(|p| &p[..]).collect::<Vec<_>>();
+        let commitments = polynomials
+            .iter()
+            .map(|p| scheme.commit(p, &mut thread_rng()))
+            .collect::<Vec<_>>();
+        let points = [SF::rand(), SF::rand()];
+
+        let proof = scheme.batch_open(
+            &polynomials,
+            &commitments,
+            &points,
+            &mut Challenger::new(128),
+            &mut thread_rng(),
+        )?;
+
+        let mut values = evaluate_at_points(&polynomials, &points);
+        scheme.batch_verify(&commitments, &points, &values, &proof, &mut Challenger::new(128))?;
+
+        values[1][2] = values[1][2] + SF::ONE;
+        assert!(scheme
+            .batch_verify(&commitments, &points, &values, &proof, &mut Challenger::new(128))
+            .is_err());
+        Ok(())
+    }
+}
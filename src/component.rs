@@ -0,0 +1,158 @@
+use crate::{CircuitBuilder, HaloCurve, Target};
+
+/// A reusable gadget, defined once and instantiated any number of times in a `CircuitBuilder`.
+///
+/// Every instance of a component is expected to produce the same layout, i.e. the same number of
+/// gates and the same number of outputs. Constants returned by `constants` are materialized once
+/// per builder and shared between all instances, so that each instance's gates are identical. Any
+/// other constant wire created by `build` would only be added by the first instance, so all
+/// constants a component needs should be listed in `constants`.
+pub trait Component<C: HaloCurve> {
+    /// A name for this component, used to group its instances.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// The number of input targets each instance takes.
+    fn num_inputs(&self) -> usize;
+
+    /// The constants used by this component.
+    fn constants(&self) -> Vec<C::ScalarField> {
+        Vec::new()
+    }
+
+    /// Adds the gates and generators of one instance to the builder, returning its outputs.
+    /// `constants` contains a target for each element of `self.constants()`, in the same order.
+    fn build(
+        &self,
+        builder: &mut CircuitBuilder<C>,
+        constants: &[Target<C::ScalarField>],
+        inputs: &[Target<C::ScalarField>],
+    ) -> Vec<Target<C::ScalarField>>;
+}
+
+/// The layout shared by all instances of a component.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct ComponentLayout {
+    /// The number of gates used by each instance.
+    pub num_gates: usize,
+    /// The number of output targets of each instance.
+    pub num_outputs: usize,
+    /// The number of instances added so far.
+    pub num_instances: usize,
+}
+
+impl<C: HaloCurve> CircuitBuilder<C> {
+    /// Adds an instance of the given component, with the given inputs, and returns its outputs.
+    pub fn add_component<Comp: Component<C>>(
+        &mut self,
+        component: &Comp,
+        inputs: &[Target<C::ScalarField>],
+    ) -> Vec<Target<C::ScalarField>> {
+        assert_eq!(
+            inputs.len(),
+            component.num_inputs(),
+            "Component {} expects {} inputs",
+            component.name(),
+            component.num_inputs()
+        );
+
+        // Constant wires are deduplicated, so only the first instance adds `ConstantGate`s. Doing
+        // this before recording the start of the instance keeps instance layouts identical. Many
+        // builder methods use the zero and one wires internally, so we create those here as well.
+        self.zero_wire();
+        self.one_wire();
+        let constants = self.constant_wires(&component.constants());
+
+        // Operations packed across an instance's boundary would change its number of gates.
+        self.close_arithmetic_gate();
+        let start_gate = self.num_gates();
+        let outputs = component.build(self, &constants, inputs);
+        let num_gates = self.num_gates() - start_gate;
+        self.close_arithmetic_gate();
+
+        let layout = self
+            .component_layouts
+            .entry(component.name())
+            .or_insert(ComponentLayout {
+                num_gates,
+                num_outputs: outputs.len(),
+                num_instances: 0,
+            });
+        assert!(
+            layout.num_gates == num_gates && layout.num_outputs == outputs.len(),
+            "Instances of component {} have different layouts",
+            component.name()
+        );
+        layout.num_instances += 1;
+        self.component_instances
+            .push((start_gate..start_gate + num_gates, component.name()));
+
+        outputs
+    }
+
+    /// Returns the layout of the given component, if any instance of it has been added.
+    pub fn component_layout(&self, name: &str) -> Option<ComponentLayout> {
+        self.component_layouts.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CircuitBuilder, Component, Curve, Field, HaloCurve, PartialWitness, Target, Tweedledee};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// Computes `x^5 + 3`.
+    struct Quintic;
+
+    impl<C: HaloCurve> Component<C> for Quintic {
+        fn num_inputs(&self) -> usize {
+            1
+        }
+
+        fn constants(&self) -> Vec<C::ScalarField> {
+            vec![C::ScalarField::from_canonical_usize(3)]
+        }
+
+        fn build(
+            &self,
+            builder: &mut CircuitBuilder<C>,
+            constants: &[Target<C::ScalarField>],
+            inputs: &[Target<C::ScalarField>],
+        ) -> Vec<Target<C::ScalarField>> {
+            let x = inputs[0];
+            let x_sq = builder.square(x);
+            let x_4 = builder.square(x_sq);
+            let x_5 = builder.mul(x_4, x);
+            vec![builder.add(x_5, constants[0])]
+        }
+    }
+
+    #[test]
+    fn test_component_instances() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let mut outputs = vec![x];
+        for _ in 0..4 {
+            let output = builder.add_component(&Quintic, &[*outputs.last().unwrap()])[0];
+            outputs.push(output);
+        }
+
+        let name = Component::<Tweedledee>::name(&Quintic);
+        let layout = builder.component_layout(name).unwrap();
+        assert_eq!(layout.num_instances, 4);
+        assert_eq!(layout.num_outputs, 1);
+
+        let circuit = builder.build();
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::ONE);
+        let witness = circuit.generate_partial_witness(inputs).unwrap();
+
+        let mut expected = F::ONE;
+        for &output in &outputs[1..] {
+            expected = expected.exp_usize(5) + F::from_canonical_usize(3);
+            assert_eq!(witness.get_target(output), expected);
+        }
+    }
+}
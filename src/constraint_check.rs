@@ -0,0 +1,372 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use anyhow::Result;
+
+use crate::gates::{evaluate_all_constraints, gate_name};
+use crate::{Circuit, Field, HaloCurve, PartialWitness, PlonkyError, Target, Wire, Witness, GRID_WIDTH};
+
+/// The maximum number of missing wires `check_witness_complete` lists.
+const MAX_REPORTED_WIRES: usize = 10;
+
+/// A constraint which a witness doesn't satisfy. See `check_circuit`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConstraintFailure<F: Field> {
+    /// The `constraint`-th constraint of the given gate evaluates to `value` rather than zero.
+    Gate {
+        gate: usize,
+        gate_type: Option<&'static str>,
+        constraint: usize,
+        value: F,
+    },
+    /// `wire` is copy-constrained to `copy_of`, but their values differ.
+    Copy {
+        wire: Wire,
+        value: F,
+        copy_of: Wire,
+        expected: F,
+    },
+}
+
+impl<F: Field> fmt::Display for ConstraintFailure<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintFailure::Gate {
+                gate,
+                gate_type,
+                constraint,
+                value,
+            } => write!(
+                f,
+                "Constraint {} of gate {} ({}) evaluates to {:?}",
+                constraint,
+                gate,
+                gate_type.unwrap_or("unknown gate type"),
+                value
+            ),
+            ConstraintFailure::Copy {
+                wire,
+                value,
+                copy_of,
+                expected,
+            } => write!(
+                f,
+                "{:?} has value {:?} but it's a copy of {:?}, which has value {:?}",
+                wire, value, copy_of, expected
+            ),
+        }
+    }
+}
+
+/// Checks that `witness` satisfies every gate constraint and copy constraint of `circuit`, and
+/// reports all failures. Constraints are evaluated directly on the witness, without FFTs or
+/// commitments, so this is much faster than proving, and tells which gates are at fault. Meant for
+/// debugging gadgets; the checked witness must have been generated for this circuit.
+pub fn check_circuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    circuit: &Circuit<C>,
+    witness: &Witness<C::ScalarField>,
+) -> Result<(), Vec<ConstraintFailure<C::ScalarField>>> {
+    let degree = circuit.degree();
+    let row = |gate: usize| {
+        (0..circuit.num_wires)
+            .map(|input| witness.get_indices(gate, input))
+            .collect::<Vec<_>>()
+    };
+
+    let mut failures = Vec::new();
+    for gate in 0..degree {
+        // Like in the prover, the gate "to the right" is the next one and the gate "below" is
+        // `GRID_WIDTH` gates later, wrapping around.
+        let local_constant_values = &circuit.gate_constants[gate];
+        let constraints = evaluate_all_constraints::<C, InnerC>(
+            local_constant_values,
+            &row(gate),
+            &row((gate + 1) % degree),
+            &row((gate + GRID_WIDTH) % degree),
+        );
+        for (constraint, &value) in constraints.iter().enumerate() {
+            if !value.is_zero() {
+                failures.push(ConstraintFailure::Gate {
+                    gate,
+                    gate_type: gate_name::<C, InnerC>(local_constant_values),
+                    constraint,
+                    value,
+                });
+            }
+        }
+    }
+
+    let wire_partitions = circuit
+        .routing_target_partitions
+        .to_wire_partitions(circuit.num_routed_wires);
+    for partition in wire_partitions.partitions() {
+        if let Some((&copy_of, rest)) = partition.split_first() {
+            let expected = witness.get(copy_of);
+            for &wire in rest {
+                let value = witness.get(wire);
+                if value != expected {
+                    failures.push(ConstraintFailure::Copy {
+                        wire,
+                        value,
+                        copy_of,
+                        expected,
+                    });
+                }
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+/// Describes each failure reported by `check_circuit`, one per line, labelling gates with their
+/// type, component and context, and wires with the names of the targets they're copies of.
+pub fn describe_constraint_failures<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    circuit: &Circuit<C>,
+    failures: &[ConstraintFailure<C::ScalarField>],
+) -> String {
+    let wire_label = |wire: Wire| {
+        format!(
+            "{} ({})",
+            circuit.target_label(Target::Wire(wire)),
+            circuit.gate_label::<InnerC>(wire.gate)
+        )
+    };
+    failures
+        .iter()
+        .map(|failure| match *failure {
+            ConstraintFailure::Gate {
+                gate,
+                constraint,
+                value,
+                ..
+            } => format!(
+                "Constraint {} of {} evaluates to {:?}",
+                constraint,
+                circuit.gate_label::<InnerC>(gate),
+                value
+            ),
+            ConstraintFailure::Copy {
+                wire,
+                value,
+                copy_of,
+                expected,
+            } => format!(
+                "{} has value {:?} but it's a copy of {}, which has value {:?}",
+                wire_label(wire),
+                value,
+                wire_label(copy_of),
+                expected
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks that `partial_witness` has a value for every wire which a gate constraint or a copy
+/// constraint of `circuit` refers to. `Witness::from_partial` sets missing wires to zero, so a
+/// gadget which forgets to generate a value otherwise only shows up as unsatisfied constraints, or
+/// as a panic in a generator which reads the value.
+pub fn check_witness_complete<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    circuit: &Circuit<C>,
+    partial_witness: &PartialWitness<C::ScalarField>,
+) -> Result<()> {
+    let degree = circuit.degree();
+    let mut referenced = HashSet::new();
+
+    let wire_partitions = circuit
+        .routing_target_partitions
+        .to_wire_partitions(circuit.num_routed_wires);
+    for partition in wire_partitions.partitions() {
+        if partition.len() > 1 {
+            referenced.extend(partition.iter().copied());
+        }
+    }
+
+    // Gates with the same constants refer to the same wires, so each set of constants is only
+    // probed once.
+    let mut wires_by_constants = HashMap::new();
+    for (gate, constants) in circuit.gate_constants.iter().enumerate() {
+        let wires = wires_by_constants
+            .entry(constants)
+            .or_insert_with(|| constraint_wires::<C, InnerC>(constants, circuit.num_wires));
+        referenced.extend(wires.iter().map(|&(offset, input)| Wire {
+            gate: (gate + offset) % degree,
+            input,
+        }));
+    }
+
+    let mut missing = referenced
+        .into_iter()
+        .filter(|&wire| !partial_witness.contains_wire(wire))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    missing.sort_by_key(|wire| (wire.gate, wire.input));
+
+    let mut msg = format!("{} wires were never populated:", missing.len());
+    for wire in missing.iter().take(MAX_REPORTED_WIRES) {
+        msg += &format!(
+            "\n{} ({})",
+            circuit.target_label(Target::Wire(*wire)),
+            circuit.gate_label::<InnerC>(wire.gate)
+        );
+    }
+    if missing.len() > MAX_REPORTED_WIRES {
+        msg += &format!("\n...and {} more", missing.len() - MAX_REPORTED_WIRES);
+    }
+    Err(PlonkyError::IncompleteWitness(msg).into())
+}
+
+/// Returns the wires which the constraints of a gate with the given constants depend on, as pairs
+/// of a gate offset (zero, one for the gate to the right, or `GRID_WIDTH` for the gate below) and
+/// a wire index. A wire counts if changing its value alone changes a constraint, which we test
+/// with random values.
+fn constraint_wires<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    constants: &[C::ScalarField],
+    num_wires: usize,
+) -> Vec<(usize, usize)> {
+    let random_row = || (0..num_wires).map(|_| C::ScalarField::rand()).collect::<Vec<_>>();
+    let mut rows = [random_row(), random_row(), random_row()];
+    let evaluate = |rows: &[Vec<C::ScalarField>; 3]| {
+        evaluate_all_constraints::<C, InnerC>(constants, &rows[0], &rows[1], &rows[2])
+    };
+    let unperturbed = evaluate(&rows);
+
+    let mut wires = Vec::new();
+    for (row, &offset) in [0, 1, GRID_WIDTH].iter().enumerate() {
+        for input in 0..num_wires {
+            let old_value = rows[row][input];
+            rows[row][input] = C::ScalarField::rand();
+            if evaluate(&rows) != unperturbed {
+                wires.push((offset, input));
+            }
+            rows[row][input] = old_value;
+        }
+    }
+    wires
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::transpose;
+    use crate::{check_circuit, describe_constraint_failures, ArithmeticGate, BufferGate, CircuitBuilder, ConstraintFailure, Curve, Field, Gate, PartialWitness, PlonkyError, Target, Tweedledee, Tweedledum, Wire, Witness};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_check_circuit() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let x_sq = builder.mul(x, x);
+        let x_cu = builder.mul(x_sq, x);
+        builder.assert_nonzero(x_cu);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::TWO);
+        let witness = circuit.generate_witness(inputs).unwrap();
+        assert_eq!(check_circuit::<Tweedledee, Tweedledum>(&circuit, &witness), Ok(()));
+
+        // Tampering with the output of the first multiplication breaks both its gate constraint,
+        // and the copy of it into the second multiplication.
+        let x_sq_wire = match x_sq {
+            Target::Wire(wire) => wire,
+            _ => panic!("Expected a wire"),
+        };
+        let mut rows = transpose(&witness.transpose());
+        rows[x_sq_wire.gate][x_sq_wire.input] = F::from_canonical_usize(5);
+        let tampered = Witness::new(rows);
+        let failures = check_circuit::<Tweedledee, Tweedledum>(&circuit, &tampered).unwrap_err();
+        assert!(failures.iter().any(|failure| matches!(
+            failure,
+            ConstraintFailure::Gate { gate, gate_type: Some(gate_type), .. }
+                if *gate == x_sq_wire.gate && *gate_type == ArithmeticGate::<Tweedledee>::NAME
+        )));
+        assert!(failures.iter().any(|failure| matches!(
+            failure,
+            ConstraintFailure::Copy { wire, copy_of, .. } if *wire == x_sq_wire || *copy_of == x_sq_wire
+        )));
+    }
+
+    #[test]
+    fn test_check_witness_complete() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let x_sq = builder.square(x);
+        builder.assert_nonzero(x_sq);
+
+        // Nothing generates y, but it's copied into two wires.
+        let y = builder.add_virtual_target();
+        let index = builder.num_gates();
+        builder.add_gate_no_constants(BufferGate::new(index));
+        builder.copy(y, Target::Wire(Wire { gate: index, input: 0 }));
+        builder.copy(y, Target::Wire(Wire { gate: index, input: 1 }));
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::TWO);
+        assert!(circuit.generate_witness(inputs.clone()).is_ok());
+        let err = circuit
+            .generate_witness_checked::<Tweedledum>(inputs.clone())
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PlonkyError>(),
+            Some(PlonkyError::IncompleteWitness(_))
+        ));
+        let err = err.to_string();
+        assert!(err.starts_with("2 wires were never populated"), "{}", err);
+        assert!(err.contains(&format!("gate {}: BufferGate", index)), "{}", err);
+
+        inputs.set_target(y, F::ONE);
+        assert!(circuit.generate_witness_checked::<Tweedledum>(inputs).is_ok());
+    }
+
+    #[test]
+    fn test_context_and_target_names() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        builder.set_target_name(x, "x");
+        // Create the constant wires which `mul` uses up front, so that the contexts only contain
+        // arithmetic gates.
+        builder.zero_wire();
+        builder.one_wire();
+        let x_cu = builder.with_context("outer", |builder| {
+            builder.push_context("square");
+            let x_sq = builder.square(x);
+            builder.set_target_name(x_sq, "x_sq");
+            builder.pop_context();
+            builder.mul(x_sq, x)
+        });
+        assert_eq!(builder.target_name(x_cu), None);
+        let counts = builder.context_gate_counts();
+        assert_eq!(counts["outer > square"], 1);
+        assert_eq!(counts["outer"], 2);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::TWO);
+        let witness = circuit.generate_witness(inputs).unwrap();
+        let x_sq_wire = match circuit.target_names.iter().find(|(_, name)| *name == "outer > square > x_sq") {
+            Some((Target::Wire(wire), _)) => *wire,
+            _ => panic!("Expected a named wire"),
+        };
+        assert_eq!(circuit.context_of(x_sq_wire.gate), Some("outer > square"));
+
+        let mut rows = transpose(&witness.transpose());
+        rows[x_sq_wire.gate][x_sq_wire.input] = F::from_canonical_usize(5);
+        let failures = check_circuit::<Tweedledee, Tweedledum>(&circuit, &Witness::new(rows)).unwrap_err();
+        let report = describe_constraint_failures::<Tweedledee, Tweedledum>(&circuit, &failures);
+        assert!(report.contains("ArithmeticGate (outer > square)"), "{}", report);
+        assert!(report.contains("(outer > square > x_sq)"), "{}", report);
+
+        // Nothing generates x, so witness generation stalls on a generator which needs it.
+        let err = circuit.generate_witness(PartialWitness::new()).unwrap_err().to_string();
+        assert!(err.contains("(x)"), "{}", err);
+    }
+}
@@ -0,0 +1,290 @@
+//! Differential tests which compare our field arithmetic, curve arithmetic, FFTs and MSMs against
+//! independent implementations on random inputs: a simple `BigUint` implementation of each for all
+//! fields and curves, and arkworks for BLS12-377. They're slow, so they only run with the
+//! `cross-check` feature.
+
+use num::{BigUint, One, Zero};
+
+use crate::{biguint_to_field, fft, field_to_biguint, msm_parallel, AffinePoint, Curve, CurveScalar, Field};
+
+/// The number of random inputs each operation is checked on.
+const NUM_SAMPLES: usize = 100;
+
+/// Reference arithmetic modulo the order of `F`, with `BigUint`s.
+struct RefField {
+    modulus: BigUint,
+}
+
+impl RefField {
+    fn of<F: Field>() -> Self {
+        RefField {
+            modulus: field_to_biguint(F::NEG_ONE) + 1u32,
+        }
+    }
+
+    fn add(&self, x: &BigUint, y: &BigUint) -> BigUint {
+        (x + y) % &self.modulus
+    }
+
+    fn sub(&self, x: &BigUint, y: &BigUint) -> BigUint {
+        (x + &self.modulus - y) % &self.modulus
+    }
+
+    fn mul(&self, x: &BigUint, y: &BigUint) -> BigUint {
+        (x * y) % &self.modulus
+    }
+
+    /// Inverts `x` with Fermat's little theorem, which is independent of our extended Euclidean
+    /// algorithm.
+    fn inv(&self, x: &BigUint) -> BigUint {
+        x.modpow(&(&self.modulus - 2u32), &self.modulus)
+    }
+}
+
+/// A point in affine coordinates, `None` being the point at infinity.
+type RefPoint = Option<(BigUint, BigUint)>;
+
+/// Reference affine arithmetic on the curve `C`, with textbook formulas.
+struct RefCurve {
+    field: RefField,
+    a: BigUint,
+}
+
+impl RefCurve {
+    fn of<C: Curve>() -> Self {
+        RefCurve {
+            field: RefField::of::<C::BaseField>(),
+            a: field_to_biguint(C::A),
+        }
+    }
+
+    fn add(&self, p: &RefPoint, q: &RefPoint) -> RefPoint {
+        let f = &self.field;
+        let ((x1, y1), (x2, y2)) = match (p, q) {
+            (None, _) => return q.clone(),
+            (_, None) => return p.clone(),
+            (Some(p), Some(q)) => (p, q),
+        };
+        let lambda = if x1 == x2 {
+            if y1 != y2 || y1.is_zero() {
+                return None;
+            }
+            let numerator = f.add(&f.mul(&3u32.into(), &f.mul(x1, x1)), &self.a);
+            f.mul(&numerator, &f.inv(&f.add(y1, y1)))
+        } else {
+            f.mul(&f.sub(y2, y1), &f.inv(&f.sub(x2, x1)))
+        };
+        let x3 = f.sub(&f.sub(&f.mul(&lambda, &lambda), x1), x2);
+        let y3 = f.sub(&f.mul(&lambda, &f.sub(x1, &x3)), y1);
+        Some((x3, y3))
+    }
+
+    /// Double-and-add, from the most significant bit.
+    fn mul(&self, scalar: &BigUint, p: &RefPoint) -> RefPoint {
+        let mut result = None;
+        for &bit in scalar.to_radix_le(2).iter().rev() {
+            result = self.add(&result, &result);
+            if bit == 1 {
+                result = self.add(&result, p);
+            }
+        }
+        result
+    }
+}
+
+fn point_to_ref<C: Curve>(p: AffinePoint<C>) -> RefPoint {
+    if p.zero {
+        None
+    } else {
+        Some((field_to_biguint(p.x), field_to_biguint(p.y)))
+    }
+}
+
+fn point_from_ref<C: Curve>(p: RefPoint) -> AffinePoint<C> {
+    match p {
+        None => AffinePoint::ZERO,
+        Some((x, y)) => AffinePoint::nonzero(biguint_to_field(x), biguint_to_field(y)),
+    }
+}
+
+fn rand_point<C: Curve>() -> AffinePoint<C> {
+    (CurveScalar(C::ScalarField::rand()) * C::GENERATOR_PROJECTIVE).to_affine()
+}
+
+fn check_field_ops<F: Field>() {
+    let r = RefField::of::<F>();
+    // Include the extremes, where carries and reductions are most likely to go wrong.
+    let mut xs = vec![F::ZERO, F::ONE, F::TWO, F::NEG_ONE, F::NEG_ONE - F::ONE];
+    xs.extend((0..NUM_SAMPLES).map(|_| F::rand()));
+
+    for (&x, &y) in xs.iter().zip(xs.iter().rev()) {
+        let (x_ref, y_ref) = (field_to_biguint(x), field_to_biguint(y));
+        assert_eq!(field_to_biguint(x + y), r.add(&x_ref, &y_ref), "{:?} + {:?}", x, y);
+        assert_eq!(field_to_biguint(x - y), r.sub(&x_ref, &y_ref), "{:?} - {:?}", x, y);
+        assert_eq!(field_to_biguint(x * y), r.mul(&x_ref, &y_ref), "{:?} * {:?}", x, y);
+        assert_eq!(field_to_biguint(-x), r.sub(&BigUint::zero(), &x_ref), "-{:?}", x);
+        assert_eq!(field_to_biguint(x.square()), r.mul(&x_ref, &x_ref), "{:?}^2", x);
+        assert_eq!(
+            field_to_biguint(x.exp(y)),
+            x_ref.modpow(&y_ref, &r.modulus),
+            "{:?}^{:?}",
+            x,
+            y
+        );
+        if x.is_nonzero() {
+            assert_eq!(
+                x.multiplicative_inverse().map(field_to_biguint),
+                Some(r.inv(&x_ref)),
+                "1 / {:?}",
+                x
+            );
+        }
+    }
+}
+
+fn check_curve_ops<C: Curve>() {
+    let r = RefCurve::of::<C>();
+    for _ in 0..NUM_SAMPLES / 10 {
+        let p = rand_point::<C>();
+        let q = rand_point::<C>();
+        let (p_ref, q_ref) = (point_to_ref(p), point_to_ref(q));
+
+        let sum = (p.to_projective() + q.to_projective()).to_affine();
+        assert_eq!(sum, point_from_ref(r.add(&p_ref, &q_ref)));
+        let mixed_sum = (p.to_projective() + q).to_affine();
+        assert_eq!(mixed_sum, sum);
+        assert_eq!(p.double(), point_from_ref(r.add(&p_ref, &p_ref)));
+        assert_eq!((p.to_projective() + (-p).to_projective()).to_affine(), AffinePoint::ZERO);
+
+        let s = C::ScalarField::rand();
+        let product = (CurveScalar(s) * p.to_projective()).to_affine();
+        assert_eq!(product, point_from_ref(r.mul(&field_to_biguint(s), &p_ref)));
+    }
+}
+
+fn check_fft<F: Field>() {
+    let r = RefField::of::<F>();
+    for &log_n in &[0, 1, 4, 6] {
+        let n = 1u64 << log_n;
+        let coefficients = (0..n).map(|_| F::rand()).collect::<Vec<_>>();
+        let values = fft(&coefficients);
+
+        // Evaluate at the powers of the generator, checking that it has order exactly n.
+        let generator = field_to_biguint(F::primitive_root_of_unity(log_n));
+        assert!(generator.modpow(&BigUint::from(n), &r.modulus).is_one());
+        if n > 1 {
+            assert!(!generator.modpow(&BigUint::from(n / 2), &r.modulus).is_one());
+        }
+        let mut point = BigUint::one();
+        for value in values {
+            let expected = coefficients.iter().rev().fold(BigUint::zero(), |acc, &c| {
+                r.add(&r.mul(&acc, &point), &field_to_biguint(c))
+            });
+            assert_eq!(field_to_biguint(value), expected);
+            point = r.mul(&point, &generator);
+        }
+    }
+}
+
+fn check_msm<C: Curve>() {
+    let r = RefCurve::of::<C>();
+    for &n in &[1, 2, 7] {
+        let scalars = (0..n).map(|_| C::ScalarField::rand()).collect::<Vec<_>>();
+        let points = (0..n).map(|_| rand_point::<C>()).collect::<Vec<_>>();
+        for &w in &[1, 5, 8] {
+            let result = msm_parallel(&scalars, &AffinePoint::batch_to_projective(&points), w);
+            let expected = scalars.iter().zip(&points).fold(None, |acc, (&s, &p)| {
+                r.add(&acc, &r.mul(&field_to_biguint(s), &point_to_ref(p)))
+            });
+            assert_eq!(result.to_affine(), point_from_ref(expected), "n = {}, w = {}", n, w);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cross_check::{check_curve_ops, check_fft, check_field_ops, check_msm};
+    use crate::{Bls12377, Bls12377Base, Bls12377Scalar, Bn254, Bn254Base, Bn254Scalar, Pallas, PallasBase, Tweedledee, TweedledeeBase, Tweedledum, TweedledumBase, Vesta, VestaBase};
+
+    #[test]
+    fn test_field_ops() {
+        check_field_ops::<TweedledeeBase>();
+        check_field_ops::<TweedledumBase>();
+        check_field_ops::<PallasBase>();
+        check_field_ops::<VestaBase>();
+        check_field_ops::<Bn254Base>();
+        check_field_ops::<Bn254Scalar>();
+        check_field_ops::<Bls12377Base>();
+        check_field_ops::<Bls12377Scalar>();
+    }
+
+    #[test]
+    fn test_curve_ops() {
+        check_curve_ops::<Tweedledee>();
+        check_curve_ops::<Tweedledum>();
+        check_curve_ops::<Pallas>();
+        check_curve_ops::<Vesta>();
+        check_curve_ops::<Bn254>();
+        check_curve_ops::<Bls12377>();
+    }
+
+    #[test]
+    fn test_fft() {
+        check_fft::<TweedledeeBase>();
+        check_fft::<TweedledumBase>();
+        check_fft::<PallasBase>();
+        check_fft::<Bls12377Scalar>();
+    }
+
+    #[test]
+    fn test_msm() {
+        check_msm::<Tweedledee>();
+        check_msm::<Pallas>();
+        check_msm::<Bn254>();
+        check_msm::<Bls12377>();
+    }
+
+    /// Compares BLS12-377 arithmetic against arkworks.
+    mod arkworks {
+        use ark_ec::{CurveGroup, VariableBaseMSM};
+        use ark_ff::Field as ArkField;
+
+        use crate::cross_check::{rand_point, NUM_SAMPLES};
+        use crate::{msm_parallel, AffinePoint, Bls12377, Bls12377Base, Bls12377Scalar, Field};
+
+        #[test]
+        fn test_field_ops() {
+            for _ in 0..NUM_SAMPLES {
+                let (x, y) = (Bls12377Base::rand(), Bls12377Base::rand());
+                let (x_ark, y_ark) = (ark_bls12_377::Fq::from(x), ark_bls12_377::Fq::from(y));
+                assert_eq!(ark_bls12_377::Fq::from(x + y), x_ark + y_ark);
+                assert_eq!(ark_bls12_377::Fq::from(x - y), x_ark - y_ark);
+                assert_eq!(ark_bls12_377::Fq::from(x * y), x_ark * y_ark);
+                assert_eq!(
+                    x.multiplicative_inverse().map(ark_bls12_377::Fq::from),
+                    x_ark.inverse()
+                );
+
+                let (s, t) = (Bls12377Scalar::rand(), Bls12377Scalar::rand());
+                let (s_ark, t_ark) = (ark_bls12_377::Fr::from(s), ark_bls12_377::Fr::from(t));
+                assert_eq!(ark_bls12_377::Fr::from(s * t), s_ark * t_ark);
+                assert_eq!(ark_bls12_377::Fr::from(s - t), s_ark - t_ark);
+            }
+        }
+
+        #[test]
+        fn test_msm() {
+            let n = 20;
+            let scalars = (0..n).map(|_| Bls12377Scalar::rand()).collect::<Vec<_>>();
+            let points = (0..n).map(|_| rand_point::<Bls12377>()).collect::<Vec<_>>();
+            let result = msm_parallel(&scalars, &AffinePoint::batch_to_projective(&points), 8);
+
+            let ark_scalars = scalars.iter().map(|&s| s.into()).collect::<Vec<ark_bls12_377::Fr>>();
+            let ark_points = points.iter().map(|&p| p.into()).collect::<Vec<ark_bls12_377::G1Affine>>();
+            let expected = ark_bls12_377::G1Projective::msm(&ark_points, &ark_scalars)
+                .expect("The lengths match")
+                .into_affine();
+            assert_eq!(result.to_affine(), AffinePoint::<Bls12377>::from(expected));
+        }
+    }
+}
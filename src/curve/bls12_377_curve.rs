@@ -11,8 +11,12 @@ impl Curve for Bls12377 {
     type BaseField = Bls12377Base;
     type ScalarField = Bls12377Scalar;
 
+    const NAME: &'static str = "BLS12-377";
+
     const A: Bls12377Base = Bls12377Base::ZERO;
     const B: Bls12377Base = Bls12377Base::ONE;
+    /// 0x170b5d44300000000000000000000000
+    const COFACTOR: &'static [u64] = &[0, 0x170b5d4430000000];
     const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
         x: BLS12_377_GENERATOR_X,
         y: BLS12_377_GENERATOR_Y,
@@ -61,6 +65,13 @@ mod tests {
         assert_eq!(Bls12377::convert(lhs) * Bls12377::GENERATOR_PROJECTIVE, mul_naive(lhs, Bls12377::GENERATOR_PROJECTIVE));
     }
 
+    #[test]
+    fn test_hash_to_curve_clears_cofactor() {
+        for i in 0..10 {
+            assert!(blake_hash_usize_to_curve::<Bls12377>(i).is_in_subgroup());
+        }
+    }
+
     /// A simple, somewhat inefficient implementation of multiplication which is used as a reference
     /// for correctness.
     fn mul_naive(lhs: Bls12377Scalar, rhs: ProjectivePoint<Bls12377>) -> ProjectivePoint<Bls12377> {
@@ -0,0 +1,82 @@
+use crate::{AffinePoint, Bn254Base, Bn254Scalar, Curve};
+use crate::field::Field;
+
+// BN254, also known as alt_bn128, as used by Ethereum's precompiles. See EIP-196:
+// https://eips.ethereum.org/EIPS/eip-196
+
+#[derive(Debug, Copy, Clone)]
+pub struct Bn254;
+
+impl Curve for Bn254 {
+    type BaseField = Bn254Base;
+    type ScalarField = Bn254Scalar;
+
+    const NAME: &'static str = "BN254";
+
+    const A: Bn254Base = Bn254Base::ZERO;
+    const B: Bn254Base = Bn254Base::THREE;
+    const PRIME_ORDER: bool = true;
+    const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
+        x: Bn254Base::ONE,
+        y: Bn254Base::TWO,
+        zero: false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{blake_hash_usize_to_curve, Bn254, Bn254Scalar, Curve, Field, ProjectivePoint};
+
+    #[test]
+    fn test_generator_on_curve() {
+        assert!(Bn254::GENERATOR_AFFINE.is_valid());
+    }
+
+    #[test]
+    fn test_double_affine() {
+        for i in 0..100 {
+            let p = blake_hash_usize_to_curve::<Bn254>(i);
+            assert_eq!(
+                p.double(),
+                p.to_projective().double().to_affine());
+        }
+    }
+
+    #[test]
+    fn test_generator_order() {
+        let order_minus_one = Bn254::convert(Bn254Scalar::NEG_ONE);
+        let g = Bn254::GENERATOR_PROJECTIVE;
+        assert_eq!(order_minus_one * g + g, ProjectivePoint::ZERO);
+    }
+
+    #[test]
+    fn test_naive_multiplication() {
+        let g = Bn254::GENERATOR_PROJECTIVE;
+        let ten = Bn254Scalar::from_canonical_u64(10);
+        let product = mul_naive(ten, g);
+        let sum = g + g + g + g + g + g + g + g + g + g;
+        assert_eq!(product, sum);
+    }
+
+    #[test]
+    fn test_g1_multiplication() {
+        let lhs = Bn254Scalar::from_canonical([11111111, 22222222, 33333333, 44444444]);
+        assert_eq!(Bn254::convert(lhs) * Bn254::GENERATOR_PROJECTIVE, mul_naive(lhs, Bn254::GENERATOR_PROJECTIVE));
+    }
+
+    /// A simple, somewhat inefficient implementation of multiplication which is used as a reference
+    /// for correctness.
+    fn mul_naive(lhs: Bn254Scalar, rhs: ProjectivePoint<Bn254>) -> ProjectivePoint<Bn254> {
+        let mut g = rhs;
+        let mut sum = ProjectivePoint::ZERO;
+        for limb in lhs.to_canonical().iter() {
+            for j in 0..64 {
+                if (limb >> j & 1u64) != 0u64 {
+                    sum = sum + g;
+                }
+                g = g.double();
+            }
+        }
+        sum
+    }
+}
@@ -16,9 +16,20 @@ pub trait Curve: 'static + Sync + Sized + Copy + Debug {
     type BaseField: Field;
     type ScalarField: Field;
 
+    /// A name for the curve, which identifies it in serialized proofs and keys.
+    const NAME: &'static str;
+
     const A: Self::BaseField;
     const B: Self::BaseField;
 
+    /// Whether the group of points has prime order, i.e. every point on the curve is in the
+    /// subgroup of order `|ScalarField|`. If not, decoded points are checked to be in it.
+    const PRIME_ORDER: bool = false;
+
+    /// The cofactor of the subgroup of order `|ScalarField|`, as little-endian `u64` limbs. Curves
+    /// without `PRIME_ORDER` must set it, so that hashes to the curve land in the subgroup.
+    const COFACTOR: &'static [u64] = &[1];
+
     const GENERATOR_AFFINE: AffinePoint<Self>;
 
     const GENERATOR_PROJECTIVE: ProjectivePoint<Self> = ProjectivePoint {
@@ -95,6 +106,35 @@ impl<C: Curve> AffinePoint<C> {
         zero || y.square() == x.cube() + C::A * x + C::B
     }
 
+    /// Returns whether the point, assumed to be on the curve, is in the subgroup of order
+    /// `|C::ScalarField|`. That's any point if `C::PRIME_ORDER`.
+    pub fn is_in_subgroup(&self) -> bool {
+        // With r = |C::ScalarField|, r P = 0 if and only if (r - 1) P = -P.
+        self.zero
+            || C::PRIME_ORDER
+            || (CurveScalar(C::ScalarField::NEG_ONE) * self.to_projective()).to_affine() == -*self
+    }
+
+    /// Maps a point on the curve into the subgroup of order `|C::ScalarField|`, by multiplying it
+    /// by `C::COFACTOR`.
+    pub fn clear_cofactor(&self) -> Self {
+        if C::PRIME_ORDER {
+            return *self;
+        }
+        // The cofactor may exceed the scalar field, so this is a plain double-and-add.
+        let p = self.to_projective();
+        let mut product = ProjectivePoint::ZERO;
+        for &limb in C::COFACTOR.iter().rev() {
+            for i in (0..64).rev() {
+                product = product.double();
+                if (limb >> i) & 1 == 1 {
+                    product = product + p;
+                }
+            }
+        }
+        product.to_affine()
+    }
+
     pub fn to_projective(&self) -> ProjectivePoint<C> {
         let Self { x, y, zero } = *self;
         ProjectivePoint {
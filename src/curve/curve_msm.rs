@@ -1,5 +1,4 @@
-use std::time::Instant;
-
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use crate::{affine_multisummation_best, AffinePoint, Curve, Field, ProjectivePoint};
@@ -24,15 +23,25 @@ pub struct MsmPrecomputation<C: Curve> {
     w: usize,
 }
 
+impl<C: Curve> MsmPrecomputation<C> {
+    /// The window size.
+    pub fn window(&self) -> usize {
+        self.w
+    }
+}
+
 pub fn msm_precompute<C: Curve>(
     generators: &[ProjectivePoint<C>],
     w: usize,
 ) -> MsmPrecomputation<C> {
+    let precompute = |&g| precompute_single_generator(g, w);
+
+    #[cfg(feature = "parallel")]
+    let powers_per_generator = generators.into_par_iter().map(precompute).collect();
+    #[cfg(not(feature = "parallel"))]
+    let powers_per_generator = generators.iter().map(precompute).collect();
     MsmPrecomputation {
-        powers_per_generator: generators
-            .into_par_iter()
-            .map(|&g| precompute_single_generator(g, w))
-            .collect(),
+        powers_per_generator,
         w,
     }
 }
@@ -127,32 +136,30 @@ pub fn msm_execute_parallel<C: Curve>(
 
     // For each digit, we add up the powers associated with all occurrences that digit.
     let digits: Vec<usize> = (0..base).collect();
-    let _start = Instant::now();
-    let digit_acc: Vec<ProjectivePoint<C>> = digits
-        .par_chunks(DIGITS_PER_CHUNK)
-        .flat_map(|chunk| {
-            let summations: Vec<Vec<AffinePoint<C>>> = chunk
-                .iter()
-                .map(|&digit| {
-                    digit_occurrences[digit]
-                        .iter()
-                        .map(|&(i, j)| precomputation.powers_per_generator[i][j])
-                        .collect()
-                })
-                .collect();
-            affine_multisummation_best(summations)
-        })
-        .collect();
-    // println!("Computing the per-digit summations (in parallel) took {}s", start.elapsed().as_secs_f64());
-
-    let _start = Instant::now();
+    let sum_chunk = |chunk: &[usize]| {
+        let summations: Vec<Vec<AffinePoint<C>>> = chunk
+            .iter()
+            .map(|&digit| {
+                digit_occurrences[digit]
+                    .iter()
+                    .map(|&(i, j)| precomputation.powers_per_generator[i][j])
+                    .collect()
+            })
+            .collect();
+        affine_multisummation_best(summations)
+    };
+
+    #[cfg(feature = "parallel")]
+    let digit_acc: Vec<ProjectivePoint<C>> = digits.par_chunks(DIGITS_PER_CHUNK).flat_map(sum_chunk).collect();
+    #[cfg(not(feature = "parallel"))]
+    let digit_acc: Vec<ProjectivePoint<C>> = digits.chunks(DIGITS_PER_CHUNK).flat_map(sum_chunk).collect();
+
     let mut y = ProjectivePoint::ZERO;
     let mut u = ProjectivePoint::ZERO;
     for digit in (1..base).rev() {
         u = u + digit_acc[digit];
         y = y + u;
     }
-    // println!("Final summation (sequential) {}s", start.elapsed().as_secs_f64());
     y
 }
 
@@ -1,4 +1,5 @@
 pub use bls12_377_curve::*;
+pub use bn254_curve::*;
 pub use curve::*;
 pub use curve_adds::*;
 pub use curve_msm::*;
@@ -10,6 +11,7 @@ pub use pallas_curve::*;
 pub use vesta_curve::*;
 
 mod bls12_377_curve;
+mod bn254_curve;
 #[allow(clippy::module_inception)]
 mod curve;
 mod curve_adds;
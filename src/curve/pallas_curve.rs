@@ -8,8 +8,11 @@ impl Curve for Pallas {
     type BaseField = PallasBase;
     type ScalarField = VestaBase;
 
+    const NAME: &'static str = "Pallas";
+
     const A: PallasBase = PallasBase::ZERO;
     const B: PallasBase = PallasBase::FIVE;
+    const PRIME_ORDER: bool = true;
 
     const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
         x: PallasBase::NEG_ONE,
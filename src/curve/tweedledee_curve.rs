@@ -8,8 +8,11 @@ impl Curve for Tweedledee {
     type BaseField = TweedledeeBase;
     type ScalarField = TweedledumBase;
 
+    const NAME: &'static str = "Tweedledee";
+
     const A: TweedledeeBase = TweedledeeBase::ZERO;
     const B: TweedledeeBase = TweedledeeBase::FIVE;
+    const PRIME_ORDER: bool = true;
 
     const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
         x: TweedledeeBase::NEG_ONE,
@@ -8,6 +8,8 @@ impl Curve for Tweedledum {
     type BaseField = TweedledumBase;
     type ScalarField = TweedledeeBase;
 
+    const NAME: &'static str = "Tweedledum";
+
     const A: TweedledumBase = TweedledumBase::ZERO;
     // B = 7
     const B: TweedledumBase = TweedledumBase {
@@ -18,6 +20,7 @@ impl Curve for Tweedledum {
             4611686018427387903,
         ]
     };
+    const PRIME_ORDER: bool = true;
 
     const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
         x: TweedledumBase::ONE,
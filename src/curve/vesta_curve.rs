@@ -8,8 +8,11 @@ impl Curve for Vesta {
     type BaseField = VestaBase;
     type ScalarField = PallasBase;
 
+    const NAME: &'static str = "Vesta";
+
     const A: VestaBase = VestaBase::ZERO;
     const B: VestaBase = VestaBase::FIVE;
+    const PRIME_ORDER: bool = true;
 
     const GENERATOR_AFFINE: AffinePoint<Self> = AffinePoint {
         x: VestaBase::NEG_ONE,
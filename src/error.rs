@@ -0,0 +1,70 @@
+use std::fmt;
+
+use crate::VerificationError;
+
+/// The reason witness generation or proving failed. Fallible functions return `anyhow::Result`,
+/// so that context can be attached along the way; callers who need to tell failures apart can
+/// recover the underlying error with `anyhow::Error::downcast_ref::<PlonkyError>`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PlonkyError {
+    /// A target was assigned two different values, whether by the inputs, a generator or a copy
+    /// constraint.
+    ConflictingValues(String),
+    /// Some generators could never be run, since their dependencies were never populated.
+    WitnessGenerationStalled(String),
+    /// Wires which a constraint or a copy constraint refers to were never populated.
+    IncompleteWitness(String),
+    /// The witness doesn't satisfy the constraints of the given gate.
+    UnsatisfiedConstraints { gate: usize },
+    /// A constraint of the given gate type has a higher degree than the prover supports.
+    ConstraintDegreeTooHigh {
+        gate: &'static str,
+        constraint: usize,
+        degree: usize,
+        max_degree: usize,
+    },
+    /// A value which the transcript must observe doesn't fit in both of the curve's fields.
+    FieldConversion(String),
+    /// A proof was rejected.
+    Verification(VerificationError),
+}
+
+impl fmt::Display for PlonkyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlonkyError::ConflictingValues(msg) => write!(f, "{}", msg),
+            PlonkyError::WitnessGenerationStalled(msg) => write!(f, "{}", msg),
+            PlonkyError::IncompleteWitness(msg) => write!(f, "{}", msg),
+            PlonkyError::UnsatisfiedConstraints { gate } => {
+                write!(f, "{}-th gate constraints are not satisfied", gate)
+            }
+            PlonkyError::ConstraintDegreeTooHigh {
+                gate,
+                constraint,
+                degree,
+                max_degree,
+            } => write!(
+                f,
+                "Constraint {} of {} has degree {}, but at most {} is supported",
+                constraint, gate, degree, max_degree
+            ),
+            PlonkyError::FieldConversion(msg) => write!(f, "Field conversion failed: {}", msg),
+            PlonkyError::Verification(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PlonkyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlonkyError::Verification(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<VerificationError> for PlonkyError {
+    fn from(e: VerificationError) -> Self {
+        PlonkyError::Verification(e)
+    }
+}
@@ -0,0 +1,182 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::{CircuitBuilder, Field, HaloCurve, Target};
+
+/// An arithmetic expression over targets and constants, which `CircuitBuilder::eval` lowers to
+/// gates. Arithmetic operators on targets build expressions, so that
+///
+/// ```ignore
+/// let y = builder.eval((a * b + c) * d);
+/// ```
+///
+/// is equivalent to chaining `builder.mul_add(a, b, c)` and `builder.mul(_, d)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expr<F: Field> {
+    Target(Target<F>),
+    Constant(F),
+    Add(Box<Expr<F>>, Box<Expr<F>>),
+    Sub(Box<Expr<F>>, Box<Expr<F>>),
+    Mul(Box<Expr<F>>, Box<Expr<F>>),
+    Neg(Box<Expr<F>>),
+}
+
+impl<F: Field> From<Target<F>> for Expr<F> {
+    fn from(target: Target<F>) -> Self {
+        Expr::Target(target)
+    }
+}
+
+impl<F: Field> From<F> for Expr<F> {
+    fn from(c: F) -> Self {
+        Expr::Constant(c)
+    }
+}
+
+impl<F: Field> Expr<F> {
+    pub fn constant(c: F) -> Self {
+        Expr::Constant(c)
+    }
+
+    /// A constant expression, as used for integer literals by the `circuit!` macro.
+    pub fn from_canonical_u64(n: u64) -> Self {
+        Expr::Constant(F::from_canonical_u64(n))
+    }
+}
+
+/// Implements a binary operator for expressions, targets and constants, where at least one operand
+/// is an expression or a target.
+macro_rules! impl_expr_op {
+    ($trait:ident, $method:ident, $variant:ident) => {
+        impl_expr_op!($trait, $method, $variant, Expr<F>, Expr<F>);
+        impl_expr_op!($trait, $method, $variant, Expr<F>, Target<F>);
+        impl_expr_op!($trait, $method, $variant, Target<F>, Expr<F>);
+        impl_expr_op!($trait, $method, $variant, Target<F>, Target<F>);
+        impl_expr_op!($trait, $method, $variant, Expr<F>, F);
+        impl_expr_op!($trait, $method, $variant, Target<F>, F);
+    };
+    ($trait:ident, $method:ident, $variant:ident, $lhs:ty, $rhs:ty) => {
+        impl<F: Field> $trait<$rhs> for $lhs {
+            type Output = Expr<F>;
+
+            fn $method(self, rhs: $rhs) -> Expr<F> {
+                Expr::$variant(Box::new(self.into()), Box::new(rhs.into()))
+            }
+        }
+    };
+}
+
+impl_expr_op!(Add, add, Add);
+impl_expr_op!(Sub, sub, Sub);
+impl_expr_op!(Mul, mul, Mul);
+
+impl<F: Field> Neg for Expr<F> {
+    type Output = Expr<F>;
+
+    fn neg(self) -> Expr<F> {
+        Expr::Neg(Box::new(self))
+    }
+}
+
+impl<F: Field> Neg for Target<F> {
+    type Output = Expr<F>;
+
+    fn neg(self) -> Expr<F> {
+        Expr::Neg(Box::new(self.into()))
+    }
+}
+
+impl<C: HaloCurve> CircuitBuilder<C> {
+    /// Lowers an expression to gates, returning a target with its value. Products with a constant
+    /// factor and sums with a constant term use `mul_const` and `add_const`, and a product plus or
+    /// minus another term uses a single `mul_add` or `mul_sub` gate.
+    pub fn eval<E: Into<Expr<C::ScalarField>>>(&mut self, expr: E) -> Target<C::ScalarField> {
+        match expr.into() {
+            Expr::Target(target) => target,
+            Expr::Constant(c) => self.constant_wire(c),
+            Expr::Add(x, y) => match (*x, *y) {
+                (Expr::Constant(c), e) | (e, Expr::Constant(c)) => {
+                    let e = self.eval(e);
+                    self.add_const(e, c)
+                }
+                (Expr::Mul(a, b), e) | (e, Expr::Mul(a, b)) => {
+                    let (a, b, e) = (self.eval(*a), self.eval(*b), self.eval(e));
+                    self.mul_add(a, b, e)
+                }
+                (x, y) => {
+                    let (x, y) = (self.eval(x), self.eval(y));
+                    self.add(x, y)
+                }
+            },
+            Expr::Sub(x, y) => match (*x, *y) {
+                (e, Expr::Constant(c)) => {
+                    let e = self.eval(e);
+                    self.add_const(e, -c)
+                }
+                (Expr::Mul(a, b), e) => {
+                    let (a, b, e) = (self.eval(*a), self.eval(*b), self.eval(e));
+                    self.mul_sub(a, b, e)
+                }
+                (x, y) => {
+                    let (x, y) = (self.eval(x), self.eval(y));
+                    self.sub(x, y)
+                }
+            },
+            Expr::Mul(x, y) => match (*x, *y) {
+                (Expr::Constant(c), e) | (e, Expr::Constant(c)) => {
+                    let e = self.eval(e);
+                    self.mul_const(e, c)
+                }
+                (x, y) => {
+                    let (x, y) = (self.eval(x), self.eval(y));
+                    self.mul(x, y)
+                }
+            },
+            Expr::Neg(x) => {
+                let x = self.eval(*x);
+                self.neg(x)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{CircuitBuilder, Curve, Expr, Field, PartialWitness, Tweedledee};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_eval() -> Result<()> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let [a, b, c, d] = [
+            builder.add_virtual_target(),
+            builder.add_virtual_target(),
+            builder.add_virtual_target(),
+            builder.add_virtual_target(),
+        ];
+
+        // `mul` wires in the cached zero and one constants, so create them upfront to count only
+        // the arithmetic gates.
+        builder.zero_wire();
+        builder.one_wire();
+        let num_gates = builder.num_gates();
+        let y = builder.eval((a * b + c) * d);
+        assert_eq!(builder.num_gates(), num_gates + 2, "(a * b + c) * d should use two gates");
+        let z = builder.eval(-(a - b * F::TWO) + F::ONE);
+        let w = builder.eval(c - a * b);
+        let constant = builder.eval(Expr::constant(F::TWO) * F::TWO + F::ONE);
+        assert_eq!(builder.constant_value(constant), Some(F::from_canonical_u32(5)));
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        let values = [2u32, 3, 5, 7].iter().map(|&x| F::from_canonical_u32(x)).collect::<Vec<_>>();
+        inputs.set_targets(&[a, b, c, d], &values);
+        let witness = circuit.generate_partial_witness(inputs)?;
+        assert_eq!(witness.get_target(y), F::from_canonical_u32(77));
+        assert_eq!(witness.get_target(z), F::from_canonical_u32(5));
+        assert_eq!(witness.get_target(w), -F::ONE);
+        Ok(())
+    }
+}
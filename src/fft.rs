@@ -1,8 +1,88 @@
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 use crate::util::{log2_ceil, log2_strict};
-use crate::Field;
-use serde::{Serialize, Deserialize};
+use crate::{take_buffer, Field, Polynomial};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::any::{Any, TypeId};
+#[cfg(feature = "parallel")]
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Precomputations of any field, keyed by the field's `TypeId` and the domain size.
+type PrecomputationCache = HashMap<(TypeId, usize), Arc<dyn Any + Send + Sync>>;
+
+/// FFT precomputations for each field and domain size which has been used with
+/// `cached_fft_precomputation`. The values are `OnceCell<Arc<FftPrecomputation<F>>>`s for the field
+/// with the given `TypeId`, so that a precomputation can be computed without holding the lock.
+static CACHED_FFT_PRECOMPUTATIONS: Lazy<Mutex<PrecomputationCache>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// The FFT accelerators which have been registered with `register_fft_accelerator`. The values are
+/// `Arc<dyn FftAccelerator<F>>`s for the field with the given `TypeId`.
+static FFT_ACCELERATORS: Lazy<RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// An alternative FFT implementation, such as one which runs on a GPU. Once registered for a field,
+/// it is used for all FFTs over that field of at least `min_size` points, which includes the LDEs
+/// done by the prover.
+///
+/// This is only the dispatch point: plonky doesn't ship a GPU backend, or a `gpu` feature, so
+/// accelerators are implemented and registered by downstream crates.
+///
+/// Moving data to and from a device has a fixed cost, so `min_size` should be set to the size
+/// above which the accelerator was measured to beat the CPU implementation on the host it runs on.
+pub trait FftAccelerator<F: Field>: Send + Sync {
+    /// The smallest FFT, in number of points, which should be dispatched to this accelerator.
+    fn min_size(&self) -> usize;
+
+    /// Evaluates the polynomial with the given coefficients over the subgroup of order
+    /// `coefficients.len()`, which is a power of two. Returns `None` if the accelerator can't
+    /// perform this FFT, e.g. because the device is out of memory, in which case we fall back to
+    /// the CPU.
+    fn fft(&self, coefficients: &[F], precomputation: &FftPrecomputation<F>) -> Option<Vec<F>>;
+}
+
+/// Registers an accelerator to be used for large FFTs over `F`, replacing any accelerator which
+/// was previously registered for `F`.
+pub fn register_fft_accelerator<F: Field>(accelerator: Arc<dyn FftAccelerator<F>>) {
+    FFT_ACCELERATORS
+        .write()
+        .unwrap()
+        .insert(TypeId::of::<F>(), Arc::new(accelerator));
+}
+
+/// Unregisters the accelerator for `F`, if any, so that all FFTs over `F` run on the CPU.
+pub fn unregister_fft_accelerator<F: Field>() {
+    FFT_ACCELERATORS.write().unwrap().remove(&TypeId::of::<F>());
+}
+
+fn fft_accelerator<F: Field>() -> Option<Arc<dyn FftAccelerator<F>>> {
+    let accelerators = FFT_ACCELERATORS.read().unwrap();
+    accelerators.get(&TypeId::of::<F>()).map(|a| {
+        a.downcast_ref::<Arc<dyn FftAccelerator<F>>>()
+            .expect("Registered FFT accelerator has the wrong type")
+            .clone()
+    })
+}
+
+#[cfg(feature = "parallel")]
+thread_local! {
+    /// Whether FFTs on this thread run their layers serially. See `FftStrategy::Serial`.
+    static SERIAL_FFTS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets whether FFTs on the current thread run their layers serially.
+#[cfg(feature = "parallel")]
+pub(crate) fn set_serial_ffts(serial: bool) {
+    SERIAL_FFTS.with(|s| s.set(serial));
+}
+
+#[cfg(feature = "parallel")]
+pub(crate) fn serial_ffts() -> bool {
+    SERIAL_FFTS.with(|s| s.get())
+}
 
 /// Permutes `arr` such that each index is mapped to its reverse in binary.
 fn reverse_index_bits<T: Copy>(arr: Vec<T>) -> Vec<T> {
@@ -16,6 +96,22 @@ fn reverse_index_bits<T: Copy>(arr: Vec<T>) -> Vec<T> {
     result
 }
 
+/// Like `reverse_index_bits`, but permutes `arr` in place.
+fn reverse_index_bits_in_place<T>(arr: &mut [T]) {
+    let n = arr.len();
+    let n_power = log2_strict(n);
+    for i in 0..n {
+        let j = reverse_bits(i, n_power);
+        if i < j {
+            arr.swap(i, j);
+        }
+    }
+}
+
+/// The number of butterflies computed by each task within an FFT layer. Splitting a layer any finer
+/// than this costs more in scheduling overhead than it gains.
+const BUTTERFLIES_PER_TASK: usize = 1 << 10;
+
 fn reverse_bits(n: usize, num_bits: usize) -> usize {
     let mut result = 0;
     for i in 0..num_bits {
@@ -40,7 +136,7 @@ impl<F: Field> FftPrecomputation<F> {
 }
 
 pub fn fft<F: Field>(coefficients: &[F]) -> Vec<F> {
-    let precomputation = fft_precompute(coefficients.len());
+    let precomputation = cached_fft_precomputation(coefficients.len());
     fft_with_precomputation(coefficients, &precomputation)
 }
 
@@ -58,6 +154,72 @@ pub fn fft_precompute<F: Field>(degree: usize) -> FftPrecomputation<F> {
     FftPrecomputation { subgroups_rev }
 }
 
+/// Returns an FFT precomputation for domains of (at least) the given size. Precomputations are
+/// cached, so the roots of unity for a given field and size are only computed once per process,
+/// and are shared by all polynomials, prover keys and proofs which use them.
+pub fn cached_fft_precomputation<F: Field>(size: usize) -> Arc<FftPrecomputation<F>> {
+    let size = 1 << log2_ceil(size);
+    let key = (TypeId::of::<F>(), size);
+
+    let cell = CACHED_FFT_PRECOMPUTATIONS
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert_with(|| Arc::new(OnceCell::<Arc<FftPrecomputation<F>>>::new()))
+        .clone();
+    let cell = cell
+        .downcast_ref::<OnceCell<Arc<FftPrecomputation<F>>>>()
+        .expect("Cached FFT precomputation has the wrong type");
+    cell.get_or_init(|| Arc::new(fft_precompute::<F>(size))).clone()
+}
+
+/// Serializes a cached FFT precomputation as its domain size, rather than as the full tables.
+pub(crate) fn serialize_cached_fft_precomputation<F: Field, S: Serializer>(
+    precomputation: &Arc<FftPrecomputation<F>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    precomputation.size().serialize(serializer)
+}
+
+/// Deserializes a precomputation serialized with `serialize_cached_fft_precomputation`, sharing
+/// the cached tables for that domain size.
+pub(crate) fn deserialize_cached_fft_precomputation<'de, F: Field, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Arc<FftPrecomputation<F>>, D::Error> {
+    let size = usize::deserialize(deserializer)?;
+    if !size.is_power_of_two() {
+        return Err(serde::de::Error::custom(format!(
+            "FFT domain size {} is not a power of two",
+            size
+        )));
+    }
+    Ok(cached_fft_precomputation(size))
+}
+
+/// Evaluates `poly` on the coset `shift H`, where `H` is the subgroup of order
+/// `2^rate_bits` times `poly.len()` (rounded up to a power of two). With a `shift` of one, this is
+/// an ordinary low-degree extension onto `H`.
+pub fn lde_onto_coset<F: Field>(poly: &Polynomial<F>, rate_bits: usize, shift: F) -> Vec<F> {
+    let lde_size = (1 << log2_ceil(poly.len())) << rate_bits;
+
+    // Scaling the i-th coefficient by shift^i gives a polynomial p' with p'(x) = p(shift x).
+    let mut coefficients = take_buffer(lde_size);
+    if shift == F::ONE {
+        coefficients[..poly.len()].copy_from_slice(&poly[..]);
+    } else {
+        let mut shift_power = F::ONE;
+        for (i, &c) in poly.iter().enumerate() {
+            coefficients[i] = c * shift_power;
+            shift_power = shift_power * shift;
+        }
+    }
+    coefficients[poly.len()..].iter_mut().for_each(|c| *c = F::ZERO);
+
+    let precomputation = cached_fft_precomputation(lde_size);
+    fft_in_place(&mut coefficients, &precomputation);
+    coefficients
+}
+
 pub fn fft_with_precomputation<F: Field>(
     coefficients: &[F],
     precomputation: &FftPrecomputation<F>,
@@ -65,101 +227,136 @@ pub fn fft_with_precomputation<F: Field>(
     let degree = coefficients.len();
     let degree_padded = 1 << log2_ceil(degree);
 
-    if degree == degree_padded {
-        fft_with_precomputation_power_of_2(coefficients, precomputation)
-    } else {
-        let mut coefficients_padded = Vec::with_capacity(degree_padded);
-        for &c in coefficients {
-            coefficients_padded.push(c);
-        }
-        for _i in degree..degree_padded {
-            coefficients_padded.push(F::ZERO);
-        }
-        fft_with_precomputation_power_of_2(&coefficients_padded, precomputation)
-    }
+    let mut values = take_buffer(degree_padded);
+    values[..degree].copy_from_slice(coefficients);
+    values[degree..].iter_mut().for_each(|c| *c = F::ZERO);
+    fft_in_place(&mut values, precomputation);
+    values
 }
 
 pub fn ifft_with_precomputation_power_of_2<F: Field>(
     points: &[F],
     precomputation: &FftPrecomputation<F>,
 ) -> Vec<F> {
-    let n = points.len();
+    let mut result = take_buffer(points.len());
+    result.copy_from_slice(points);
+    ifft_in_place(&mut result, precomputation);
+    result
+}
+
+/// Like `ifft_with_precomputation_power_of_2`, but overwrites the given evaluations with the
+/// coefficients of the polynomial which they interpolate.
+pub fn ifft_in_place<F: Field>(values: &mut [F], precomputation: &FftPrecomputation<F>) {
+    let n = values.len();
     let n_inv = F::from_canonical_usize(n).multiplicative_inverse().unwrap();
-    let mut result = fft_with_precomputation_power_of_2(points, precomputation);
+    fft_in_place(values, precomputation);
 
     // We reverse all values except the first, and divide each by n.
-    result[0] = result[0] * n_inv;
-    result[n / 2] = result[n / 2] * n_inv;
-    for i in 1..(n / 2) {
-        let j = n - i;
-        let result_i = result[j] * n_inv;
-        let result_j = result[i] * n_inv;
-        result[i] = result_i;
-        result[j] = result_j;
-    }
-    result
+    values[1..].reverse();
+    values.iter_mut().for_each(|v| *v = *v * n_inv);
 }
 
 pub fn fft_with_precomputation_power_of_2<F: Field>(
     coefficients: &[F],
     precomputation: &FftPrecomputation<F>,
 ) -> Vec<F> {
+    let mut result = take_buffer(coefficients.len());
+    result.copy_from_slice(coefficients);
+    fft_in_place(&mut result, precomputation);
+    result
+}
+
+/// Overwrites the given coefficients, whose number must be a power of two, with the polynomial's
+/// evaluations over the subgroup of that order. Unlike `fft_with_precomputation_power_of_2`, this
+/// doesn't allocate, unless the FFT is dispatched to an accelerator.
+pub fn fft_in_place<F: Field>(values: &mut [F], precomputation: &FftPrecomputation<F>) {
+    if let Some(accelerator) = fft_accelerator::<F>() {
+        if values.len() >= accelerator.min_size() {
+            if let Some(evaluations) = accelerator.fft(values, precomputation) {
+                values.copy_from_slice(&evaluations);
+                return;
+            }
+        }
+    }
+    fft_in_place_cpu(values, precomputation)
+}
+
+fn fft_in_place_cpu<F: Field>(values: &mut [F], precomputation: &FftPrecomputation<F>) {
     debug_assert_eq!(
-        coefficients.len(),
+        values.len(),
         precomputation.subgroups_rev.last().unwrap().len(),
         "Number of coefficients does not match size of subgroup in precomputation"
     );
 
-    let degree = coefficients.len();
-    let half_degree = coefficients.len() >> 1;
-    let degree_pow = log2_strict(degree);
-
-    // In the base layer, we're just evaluating "degree 0 polynomials", i.e. the coefficients
-    // themselves.
-    let coefficients_vec = coefficients.to_vec();
-    let mut evaluations = reverse_index_bits(coefficients_vec);
-
-    for i in 1..=degree_pow {
-        // In layer i, we're evaluating a series of polynomials, each at 2^i points. In practice
-        // we evaluate a pair of points together, so we have 2^(i - 1) pairs.
-        let points_per_poly = 1 << i;
-        let pairs_per_poly = 1 << (i - 1);
-
-        let pair_indices: Vec<usize> = (0..half_degree).collect();
-        let new_evaluations = pair_indices
-            .par_chunks(2000)
-            .flat_map(|chunk| {
-                let mut new_evaluations_chunk = Vec::new();
-                for pair_index in chunk {
-                    let poly_index = pair_index / pairs_per_poly;
-                    let pair_index_within_poly = pair_index % pairs_per_poly;
-
-                    let child_index_0 = poly_index * points_per_poly + pair_index_within_poly;
-                    let child_index_1 = child_index_0 + pairs_per_poly;
-
-                    let even = evaluations[child_index_0];
-                    let odd = evaluations[child_index_1];
-
-                    let point_0 = precomputation.subgroups_rev[i][pair_index_within_poly * 2];
-                    let product = point_0 * odd;
-                    new_evaluations_chunk.push(even + product);
-                    new_evaluations_chunk.push(even - product);
-                }
-                new_evaluations_chunk
-            })
-            .collect();
-        evaluations = new_evaluations;
+    // This is an in-place Cooley-Tukey FFT, which takes coefficients in their natural order and
+    // leaves the evaluations in bit-reversed order. Layer i splits the values into 2^i blocks,
+    // and combines the two halves of each block; block j's twiddle factor is the (2j)-th element
+    // of the bit-reversed subgroup of order 2^(i + 1).
+    let degree_pow = log2_strict(values.len());
+    for i in 0..degree_pow {
+        let half = values.len() >> (i + 1);
+        fft_layer(values, half, &precomputation.subgroups_rev[i + 1]);
     }
 
     // Reorder so that evaluations' indices correspond to (g_0, g_1, g_2, ...)
-    reverse_index_bits(evaluations)
+    reverse_index_bits_in_place(values);
+}
+
+/// Applies the butterflies which combine the halves `lo` and `hi` of a block.
+fn butterflies<F: Field>(lo: &mut [F], hi: &mut [F], twiddle: F) {
+    // Scale a small chunk of `hi` at a time, so that it's still cached when combined with `lo`.
+    for (lo, hi) in lo.chunks_mut(64).zip(hi.chunks_mut(64)) {
+        twiddle.scale_slice_in_place(hi);
+        for (x, y) in lo.iter_mut().zip(hi.iter_mut()) {
+            let u = *x;
+            let v = *y;
+            *x = u + v;
+            *y = u - v;
+        }
+    }
+}
+
+/// Computes an FFT layer in place, for blocks of size `2 half`. With the `parallel` feature, the
+/// layer's butterflies are split into tasks which run on the rayon thread pool, unless this thread
+/// belongs to a pool which uses `FftStrategy::Serial`.
+fn fft_layer<F: Field>(values: &mut [F], half: usize, subgroup_rev: &[F]) {
+    // A task handles whole blocks, or part of a single block if blocks are large.
+    let task_len = (2 * half).max(2 * BUTTERFLIES_PER_TASK).min(values.len());
+    let blocks_task = |(task_index, task_values): (usize, &mut [F])| {
+        let first_block = task_index * task_len / (2 * half);
+        for (j, block) in task_values.chunks_mut(2 * half).enumerate() {
+            let (lo, hi) = block.split_at_mut(half);
+            butterflies(lo, hi, subgroup_rev[2 * (first_block + j)]);
+        }
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        if !serial_ffts() {
+            if half >= BUTTERFLIES_PER_TASK {
+                for (j, block) in values.chunks_mut(2 * half).enumerate() {
+                    let twiddle = subgroup_rev[2 * j];
+                    let (lo, hi) = block.split_at_mut(half);
+                    lo.par_chunks_mut(BUTTERFLIES_PER_TASK)
+                        .zip(hi.par_chunks_mut(BUTTERFLIES_PER_TASK))
+                        .for_each(|(lo, hi)| butterflies(lo, hi, twiddle));
+                }
+            } else {
+                values.par_chunks_mut(task_len).enumerate().for_each(blocks_task);
+            }
+            return;
+        }
+    }
+    values.chunks_mut(task_len).enumerate().for_each(blocks_task);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::fft::{log2_strict, reverse_bits, reverse_index_bits};
+    use crate::fft::{fft_in_place_cpu, log2_strict, reverse_bits, reverse_index_bits};
     use crate::util::log2_ceil;
-    use crate::{fft_precompute, fft_with_precomputation, ifft_with_precomputation_power_of_2, Bls12377Scalar, Field};
+    use crate::{cached_fft_precomputation, fft_in_place, fft_precompute, fft_with_precomputation, ifft_in_place, ifft_with_precomputation_power_of_2, lde_onto_coset, register_fft_accelerator, unregister_fft_accelerator, Bls12377Base, Bls12377Scalar, FftAccelerator, FftPrecomputation, FftStrategy, Field, Polynomial, ProverConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn fft_and_ifft() {
@@ -184,6 +381,122 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fft_many_tasks() {
+        // Large enough that each layer is split into several tasks.
+        let degree = 1 << 12;
+        let coefficients = (0..degree)
+            .map(|i| Bls12377Scalar::from_canonical_usize(i * 7919 % 1000))
+            .collect::<Vec<_>>();
+
+        let precomputation = fft_precompute(degree);
+        let points = fft_with_precomputation(&coefficients, &precomputation);
+        assert_eq!(points, evaluate_naive(&coefficients));
+        assert_eq!(
+            ifft_with_precomputation_power_of_2(&points, &precomputation),
+            coefficients
+        );
+    }
+
+    #[test]
+    fn test_lde_onto_coset() {
+        type F = Bls12377Scalar;
+        let poly = Polynomial::from(
+            (0..10)
+                .map(|i| F::from_canonical_usize(i * i + 1))
+                .collect::<Vec<_>>(),
+        );
+        let rate_bits = 2;
+        let shift = F::MULTIPLICATIVE_SUBGROUP_GENERATOR;
+
+        // The domain is the order-64 subgroup, since 10 coefficients are padded to 16.
+        let lde = lde_onto_coset(&poly, rate_bits, shift);
+        let generator = F::primitive_root_of_unity(6);
+        let subgroup = F::cyclic_subgroup_known_order(generator, 64);
+        assert_eq!(lde.len(), 64);
+        for (&x, &y) in subgroup.iter().zip(lde.iter()) {
+            assert_eq!(poly.eval(shift * x), y);
+        }
+
+        let lde_no_shift = lde_onto_coset(&poly, rate_bits, F::ONE);
+        for (&x, &y) in subgroup.iter().zip(lde_no_shift.iter()) {
+            assert_eq!(poly.eval(x), y);
+        }
+    }
+
+    #[test]
+    fn test_large_fft_in_place() {
+        // Large enough that each of the first layers' blocks is split into several tasks.
+        type F = Bls12377Scalar;
+        let n = 1 << 13;
+        let poly = Polynomial::from((0..n).map(|_| F::rand()).collect::<Vec<_>>());
+        let precomputation = cached_fft_precomputation::<F>(n);
+        let subgroup = F::cyclic_subgroup_known_order(F::primitive_root_of_unity(13), n);
+
+        let mut values = poly.coeffs().to_vec();
+        fft_in_place(&mut values, &precomputation);
+        for &i in &[0, 1, 1000, n - 1] {
+            assert_eq!(values[i], poly.eval(subgroup[i]));
+        }
+
+        let serial = ProverConfig {
+            fft_strategy: FftStrategy::Serial,
+            ..ProverConfig::default()
+        };
+        let serial_values = serial.install(|| fft_with_precomputation(poly.coeffs(), &precomputation)).unwrap();
+        assert_eq!(serial_values, values);
+
+        ifft_in_place(&mut values, &precomputation);
+        assert_eq!(values, poly.coeffs());
+    }
+
+    #[test]
+    fn test_cached_fft_precomputation() {
+        let a = cached_fft_precomputation::<Bls12377Scalar>(100);
+        let b = cached_fft_precomputation::<Bls12377Scalar>(128);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(a.size(), 128);
+        assert_eq!(*a, fft_precompute(128));
+    }
+
+    /// Runs FFTs on the CPU, but counts how many were dispatched to it.
+    struct CountingAccelerator {
+        calls: AtomicUsize,
+    }
+
+    impl<F: Field> FftAccelerator<F> for CountingAccelerator {
+        fn min_size(&self) -> usize {
+            64
+        }
+
+        fn fft(&self, coefficients: &[F], precomputation: &FftPrecomputation<F>) -> Option<Vec<F>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut values = coefficients.to_vec();
+            fft_in_place_cpu(&mut values, precomputation);
+            Some(values)
+        }
+    }
+
+    #[test]
+    fn test_fft_accelerator_dispatch() {
+        // Other tests don't use this field, so they won't touch the accelerator.
+        type F = Bls12377Base;
+        let accelerator = Arc::new(CountingAccelerator { calls: AtomicUsize::new(0) });
+        register_fft_accelerator::<F>(accelerator.clone());
+
+        let small = (0..32).map(F::from_canonical_usize).collect::<Vec<_>>();
+        assert_eq!(fft_with_precomputation(&small, &fft_precompute(32)), evaluate_naive(&small));
+        assert_eq!(accelerator.calls.load(Ordering::SeqCst), 0);
+
+        let large = (0..128).map(F::from_canonical_usize).collect::<Vec<_>>();
+        assert_eq!(fft_with_precomputation(&large, &fft_precompute(128)), evaluate_naive(&large));
+        assert_eq!(accelerator.calls.load(Ordering::SeqCst), 1);
+
+        unregister_fft_accelerator::<F>();
+        fft_with_precomputation(&large, &fft_precompute(128));
+        assert_eq!(accelerator.calls.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_reverse_bits() {
         assert_eq!(reverse_bits(0b00110101, 8), 0b10101100);
@@ -0,0 +1,273 @@
+use rand::Rng;
+use std::cmp::Ordering;
+use std::cmp::Ordering::Less;
+use std::convert::TryInto;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{cmp, field_to_biguint,
+            rand_range, rand_range_from_rng,
+            MontyRepr, Field};
+
+/// An element of the BN254 group's base field.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct Bn254Base {
+    /// Montgomery representation, encoded with little-endian u64 limbs.
+    pub limbs: [u64; 4],
+}
+
+impl MontyRepr for Bn254Base {
+    /// The order of the field: 0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47
+    const ORDER: [u64; 4] = [
+        0x3c208c16d87cfd47,
+        0x97816a916871ca8d,
+        0xb85045b68181585d,
+        0x30644e72e131a029,
+    ];
+
+    /// Twice the order of the field
+    #[allow(dead_code)]
+    const ORDER_X2: [u64; 4] = [
+        0x7841182db0f9fa8e,
+        0x2f02d522d0e3951a,
+        0x70a08b6d0302b0bb,
+        0x60c89ce5c2634053,
+    ];
+
+    /// R in the context of the Montgomery reduction, i.e. 2^256 % |F|.
+    const R: [u64; 4] = [
+        0xd35d438dc58f0d9d,
+        0x0a78eb28f5c70b3d,
+        0x666ea36f7879462c,
+        0x0e0a77c19a07df2f,
+    ];
+
+    /// R^2 in the context of the Montgomery reduction, i.e. 2^(256*2) % |F|.
+    const R2: [u64; 4] = [
+        0xf32cfc5b538afa89,
+        0xb5e71911d44501fb,
+        0x47ab1eff0a417ff6,
+        0x06d89f71cab8351f,
+    ];
+
+    /// R^3 in the context of the Montgomery reduction, i.e. 2^(256*3) % |F|.
+    const R3: [u64; 4] = [
+        0xb1cd6dafda1530df,
+        0x62f210e6a7283db6,
+        0xef7f0b0c0ada0afb,
+        0x20fd6e902d592544,
+    ];
+
+    /// In the context of Montgomery multiplication, µ = -|F|^-1 mod 2^64.
+    const MU: u64 = 0x87d20782e4866389;
+}
+
+impl Bn254Base {
+    pub fn from_canonical(c: [u64; 4]) -> Self {
+        Self { limbs: Self::from_monty(c) }
+    }
+
+    pub fn to_canonical(&self) -> [u64; 4] {
+        Self::to_monty(self.limbs)
+    }
+}
+
+impl Add<Bn254Base> for Bn254Base {
+    type Output = Self;
+
+    fn add(self, rhs: Bn254Base) -> Self::Output {
+        Self { limbs: Self::monty_add(self.limbs, rhs.limbs) }
+    }
+}
+
+impl Sub<Bn254Base> for Bn254Base {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self { limbs: Self::monty_sub(self.limbs, rhs.limbs) }
+    }
+}
+
+impl Mul<Bn254Base> for Bn254Base {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self { limbs: Self::monty_multiply(self.limbs, rhs.limbs) }
+    }
+}
+
+impl Div<Bn254Base> for Bn254Base {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.multiplicative_inverse().expect("No inverse")
+    }
+}
+
+impl Neg for Bn254Base {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { limbs: Self::monty_neg(self.limbs) }
+    }
+}
+
+impl Field for Bn254Base {
+    const BITS: usize = 254;
+    const BYTES: usize = 32;
+    const ZERO: Self = Self { limbs: <Self as MontyRepr>::ZERO };
+    const ONE: Self = Self { limbs: <Self as MontyRepr>::ONE };
+    const TWO: Self = Self {
+        limbs: [
+            0xa6ba871b8b1e1b3a,
+            0x14f1d651eb8e167b,
+            0xccdd46def0f28c58,
+            0x1c14ef83340fbe5e,
+        ],
+    };
+    const THREE: Self = Self {
+        limbs: [
+            0x7a17caa950ad28d7,
+            0x1f6ac17ae15521b9,
+            0x334bea4e696bd284,
+            0x2a1f6744ce179d8e,
+        ],
+    };
+    const FOUR: Self = Self {
+        limbs: [
+            0x115482203dbf392d,
+            0x926242126eaa626a,
+            0xe16a48076063c052,
+            0x07c5909386eddc93,
+        ],
+    };
+    const FIVE: Self = Self {
+        limbs: [
+            0xe4b1c5ae034e46ca,
+            0x9cdb2d3b64716da7,
+            0x47d8eb76d8dd067e,
+            0x15d0085520f5bbc3,
+        ],
+    };
+    const NEG_ONE: Self = Self {
+        limbs: [
+            0x68c3488912edefaa,
+            0x8d087f6872aabf4f,
+            0x51e1a24709081231,
+            0x2259d6b14729c0fa,
+        ]
+    };
+
+    const MULTIPLICATIVE_SUBGROUP_GENERATOR: Self = Self::THREE;
+
+    const ALPHA: Self = Self::FIVE;
+
+    const TWO_ADICITY: usize = 1;
+
+    /// T = (ORDER - 1) / 2^TWO_ADICITY  in Monty form
+    const T: Self = Self {
+        limbs: [
+            0xb461a4448976f7d5,
+            0xc6843fb439555fa7,
+            0x28f0d12384840918,
+            0x112ceb58a394e07d,
+        ],
+    };
+
+    fn to_canonical_u64_vec(&self) -> Vec<u64> {
+        self.to_canonical().to_vec()
+    }
+
+    fn from_canonical_u64_vec(v: Vec<u64>) -> Self {
+        Self::from_canonical(v[..].try_into().unwrap())
+    }
+
+    fn from_canonical_u64(n: u64) -> Self {
+        Self::from_canonical([n, 0, 0, 0])
+    }
+
+    fn is_valid_canonical_u64(v: &[u64]) -> bool {
+        v.len() == 4 && cmp(v[..].try_into().unwrap(), Self::ORDER) == Less
+    }
+
+    fn multiplicative_inverse_assuming_nonzero(&self) -> Self {
+        Self {
+            limbs: Self::monty_inverse(self.limbs)
+        }
+    }
+
+    fn rand() -> Self {
+        Self {
+            limbs: rand_range(Self::ORDER),
+        }
+    }
+
+    fn rand_from_rng<R: Rng>(rng: &mut R) -> Self {
+        Self {
+            limbs: rand_range_from_rng(Self::ORDER, rng),
+        }
+    }
+
+    #[inline(always)]
+    fn square(&self) -> Self {
+        Self {
+            limbs: Self::monty_square(self.limbs),
+        }
+    }
+}
+
+impl Ord for Bn254Base {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_helper(other)
+    }
+}
+
+impl PartialOrd for Bn254Base {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Bn254Base {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", field_to_biguint(*self))
+    }
+}
+
+impl Debug for Bn254Base {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Bn254Base {}", field_to_biguint(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_arithmetic;
+    use crate::Field;
+    use crate::Bn254Base;
+    use crate::MontyRepr; // This is just to access ORDER_X2.
+
+    #[test]
+    fn primitive_root_order() {
+        for n_power in 0..=1 {
+            let root = Bn254Base::primitive_root_of_unity(n_power);
+            let order = Bn254Base::generator_order(root);
+            assert_eq!(order, 1 << n_power, "2^{}'th primitive root", n_power);
+        }
+    }
+
+    #[test]
+    fn valid_canonical_vec() {
+        let small = <Bn254Base as Field>::ONE.to_canonical_u64_vec();
+        assert!(Bn254Base::is_valid_canonical_u64(&small));
+
+        let big = Bn254Base::ORDER_X2.to_vec();
+        assert!(!Bn254Base::is_valid_canonical_u64(&big));
+
+        let limbs = vec![1, 2, 3, 4, 5];
+        assert!(!Bn254Base::is_valid_canonical_u64(&limbs));
+    }
+
+    test_arithmetic!(crate::Bn254Base);
+}
@@ -0,0 +1,273 @@
+use rand::Rng;
+use std::cmp::Ordering;
+use std::cmp::Ordering::Less;
+use std::convert::TryInto;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{cmp, field_to_biguint,
+            rand_range, rand_range_from_rng,
+            MontyRepr, Field};
+
+/// An element of the BN254 group's scalar field.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct Bn254Scalar {
+    /// Montgomery representation, encoded with little-endian u64 limbs.
+    pub limbs: [u64; 4],
+}
+
+impl MontyRepr for Bn254Scalar {
+    /// The order of the field: 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001
+    const ORDER: [u64; 4] = [
+        0x43e1f593f0000001,
+        0x2833e84879b97091,
+        0xb85045b68181585d,
+        0x30644e72e131a029,
+    ];
+
+    /// Twice the order of the field
+    #[allow(dead_code)]
+    const ORDER_X2: [u64; 4] = [
+        0x87c3eb27e0000002,
+        0x5067d090f372e122,
+        0x70a08b6d0302b0ba,
+        0x60c89ce5c2634053,
+    ];
+
+    /// R in the context of the Montgomery reduction, i.e. 2^256 % |F|.
+    const R: [u64; 4] = [
+        0xac96341c4ffffffb,
+        0x36fc76959f60cd29,
+        0x666ea36f7879462e,
+        0x0e0a77c19a07df2f,
+    ];
+
+    /// R^2 in the context of the Montgomery reduction, i.e. 2^(256*2) % |F|.
+    const R2: [u64; 4] = [
+        0x1bb8e645ae216da7,
+        0x53fe3ab1e35c59e3,
+        0x8c49833d53bb8085,
+        0x0216d0b17f4e44a5,
+    ];
+
+    /// R^3 in the context of the Montgomery reduction, i.e. 2^(256*3) % |F|.
+    const R3: [u64; 4] = [
+        0x5e94d8e1b4bf0040,
+        0x2a489cbe1cfbb6b8,
+        0x893cc664a19fcfed,
+        0x0cf8594b7fcc657c,
+    ];
+
+    /// In the context of Montgomery multiplication, µ = -|F|^-1 mod 2^64.
+    const MU: u64 = 0xc2e1f593efffffff;
+}
+
+impl Bn254Scalar {
+    pub fn from_canonical(c: [u64; 4]) -> Self {
+        Self { limbs: Self::from_monty(c) }
+    }
+
+    pub fn to_canonical(&self) -> [u64; 4] {
+        Self::to_monty(self.limbs)
+    }
+}
+
+impl Add<Bn254Scalar> for Bn254Scalar {
+    type Output = Self;
+
+    fn add(self, rhs: Bn254Scalar) -> Self::Output {
+        Self { limbs: Self::monty_add(self.limbs, rhs.limbs) }
+    }
+}
+
+impl Sub<Bn254Scalar> for Bn254Scalar {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self { limbs: Self::monty_sub(self.limbs, rhs.limbs) }
+    }
+}
+
+impl Mul<Bn254Scalar> for Bn254Scalar {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self { limbs: Self::monty_multiply(self.limbs, rhs.limbs) }
+    }
+}
+
+impl Div<Bn254Scalar> for Bn254Scalar {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.multiplicative_inverse().expect("No inverse")
+    }
+}
+
+impl Neg for Bn254Scalar {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self { limbs: Self::monty_neg(self.limbs) }
+    }
+}
+
+impl Field for Bn254Scalar {
+    const BITS: usize = 254;
+    const BYTES: usize = 32;
+    const ZERO: Self = Self { limbs: <Self as MontyRepr>::ZERO };
+    const ONE: Self = Self { limbs: <Self as MontyRepr>::ONE };
+    const TWO: Self = Self {
+        limbs: [
+            0x592c68389ffffff6,
+            0x6df8ed2b3ec19a53,
+            0xccdd46def0f28c5c,
+            0x1c14ef83340fbe5e,
+        ],
+    };
+    const THREE: Self = Self {
+        limbs: [
+            0x05c29c54effffff1,
+            0xa4f563c0de22677d,
+            0x334bea4e696bd28a,
+            0x2a1f6744ce179d8e,
+        ],
+    };
+    const FOUR: Self = Self {
+        limbs: [
+            0x6e76dadd4fffffeb,
+            0xb3bdf20e03c9c415,
+            0xe16a48076063c05b,
+            0x07c5909386eddc93,
+        ],
+    };
+    const FIVE: Self = Self {
+        limbs: [
+            0x1b0d0ef99fffffe6,
+            0xeaba68a3a32a913f,
+            0x47d8eb76d8dd0689,
+            0x15d0085520f5bbc3,
+        ],
+    };
+    const NEG_ONE: Self = Self {
+        limbs: [
+            0x974bc177a0000006,
+            0xf13771b2da58a367,
+            0x51e1a2470908122e,
+            0x2259d6b14729c0fa,
+        ]
+    };
+
+    const MULTIPLICATIVE_SUBGROUP_GENERATOR: Self = Self::FIVE;
+
+    const ALPHA: Self = Self::FIVE;
+
+    const TWO_ADICITY: usize = 28;
+
+    /// T = (ORDER - 1) / 2^TWO_ADICITY  in Monty form
+    const T: Self = Self {
+        limbs: [
+            0x43e1f593f0000001,
+            0x2833e84879b97091,
+            0xb85045b68181585d,
+            0x30644e62e131a029,
+        ],
+    };
+
+    fn to_canonical_u64_vec(&self) -> Vec<u64> {
+        self.to_canonical().to_vec()
+    }
+
+    fn from_canonical_u64_vec(v: Vec<u64>) -> Self {
+        Self::from_canonical(v[..].try_into().unwrap())
+    }
+
+    fn from_canonical_u64(n: u64) -> Self {
+        Self::from_canonical([n, 0, 0, 0])
+    }
+
+    fn is_valid_canonical_u64(v: &[u64]) -> bool {
+        v.len() == 4 && cmp(v[..].try_into().unwrap(), Self::ORDER) == Less
+    }
+
+    fn multiplicative_inverse_assuming_nonzero(&self) -> Self {
+        Self {
+            limbs: Self::monty_inverse(self.limbs)
+        }
+    }
+
+    fn rand() -> Self {
+        Self {
+            limbs: rand_range(Self::ORDER),
+        }
+    }
+
+    fn rand_from_rng<R: Rng>(rng: &mut R) -> Self {
+        Self {
+            limbs: rand_range_from_rng(Self::ORDER, rng),
+        }
+    }
+
+    #[inline(always)]
+    fn square(&self) -> Self {
+        Self {
+            limbs: Self::monty_square(self.limbs),
+        }
+    }
+}
+
+impl Ord for Bn254Scalar {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_helper(other)
+    }
+}
+
+impl PartialOrd for Bn254Scalar {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Bn254Scalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", field_to_biguint(*self))
+    }
+}
+
+impl Debug for Bn254Scalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Bn254Scalar {}", field_to_biguint(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_arithmetic;
+    use crate::Field;
+    use crate::Bn254Scalar;
+    use crate::MontyRepr; // This is just to access ORDER_X2.
+
+    #[test]
+    fn primitive_root_order() {
+        for n_power in 0..10 {
+            let root = Bn254Scalar::primitive_root_of_unity(n_power);
+            let order = Bn254Scalar::generator_order(root);
+            assert_eq!(order, 1 << n_power, "2^{}'th primitive root", n_power);
+        }
+    }
+
+    #[test]
+    fn valid_canonical_vec() {
+        let small = <Bn254Scalar as Field>::ONE.to_canonical_u64_vec();
+        assert!(Bn254Scalar::is_valid_canonical_u64(&small));
+
+        let big = Bn254Scalar::ORDER_X2.to_vec();
+        assert!(!Bn254Scalar::is_valid_canonical_u64(&big));
+
+        let limbs = vec![1, 2, 3, 4, 5];
+        assert!(!Bn254Scalar::is_valid_canonical_u64(&limbs));
+    }
+
+    test_arithmetic!(crate::Bn254Scalar);
+}
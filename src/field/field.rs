@@ -194,7 +194,26 @@ pub trait Field:
     }
 
     fn scale_slice(&self, slice: &[Self]) -> Vec<Self> {
-        slice.iter().map(|&x| *self * x).collect()
+        let mut scaled = slice.to_vec();
+        self.scale_slice_in_place(&mut scaled);
+        scaled
+    }
+
+    /// Multiplies each element of `slice` by `self`. Fields may override this with a vectorized
+    /// implementation.
+    fn scale_slice_in_place(&self, slice: &mut [Self]) {
+        for x in slice.iter_mut() {
+            *x = *x * *self;
+        }
+    }
+
+    /// Multiplies each element of `values` by the corresponding element of `factors`. Fields may
+    /// override this with a vectorized implementation.
+    fn batch_multiply_in_place(values: &mut [Self], factors: &[Self]) {
+        assert_eq!(values.len(), factors.len(), "Slices have different lengths");
+        for (x, &y) in values.iter_mut().zip(factors) {
+            *x = *x * y;
+        }
     }
 
     fn scale_proj_point_slice<C>(&self, slice: &[ProjectivePoint<C>]) -> Vec<ProjectivePoint<C>>
@@ -475,7 +494,20 @@ pub trait Field:
 
     /// Return this field element re-encoded as an element of `F` if it fits, or `Err` if not.
     fn try_convert<F: Field>(&self) -> Result<F> {
-        F::from_canonical_u8_vec(self.to_canonical_u8_vec())
+        // The two fields may use different numbers of limbs, so we zero-pad or truncate ours,
+        // failing if any truncated limb is nonzero.
+        let mut limbs = self.to_canonical_u64_vec();
+        let num_limbs = F::ZERO.to_canonical_u64_vec().len();
+        if limbs.iter().skip(num_limbs).any(|&limb| limb != 0) {
+            return Err(Error::msg("Out of range"));
+        }
+        limbs.resize(num_limbs, 0);
+
+        if F::is_valid_canonical_u64(&limbs) {
+            Ok(F::from_canonical_u64_vec(limbs))
+        } else {
+            Err(Error::msg("Out of range"))
+        }
     }
 
     fn try_convert_all<F: Field>(values: &[Self]) -> Result<Vec<F>> {
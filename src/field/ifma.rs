@@ -0,0 +1,269 @@
+//! Montgomery multiplication of eight field elements at once, using AVX-512 IFMA instructions,
+//! which multiply 52-bit limbs. IFMA is only available on some x86_64 CPUs (Ice Lake and later,
+//! and Zen 4), so callers check for it at runtime, and otherwise fall back to `MontyRepr`. It backs
+//! both scaling a slice by one factor and multiplying two slices elementwise.
+//!
+//! Elements are converted to five 52-bit limbs, with limb `k` of each of the eight elements held
+//! in one vector, and multiplied with a radix-2^52 Montgomery multiplication, which divides by
+//! 2^260. Since our Montgomery representation uses R = 2^256, one operand is first multiplied by
+//! 2^4. It's left unreduced, which is fine since our moduli are below 2^255, so it still fits in
+//! five limbs, and the Montgomery product is still below 2p.
+
+use crate::MontyRepr;
+
+/// Multiplies each element of `values` by `factor`, all in Montgomery form, like
+/// `MontyRepr::monty_multiply`. Returns false, leaving `values` unchanged, if IFMA is unsupported.
+/// Only moduli below 2^255 are supported.
+pub(crate) fn monty_scale_slice_ifma<M: MontyRepr>(values: &mut [[u64; 4]], factor: [u64; 4]) -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        debug_assert!(M::ORDER[3] >> 63 == 0, "Modulus is too large for IFMA");
+        if x86_64::is_available() {
+            // Safe since we just checked that the CPU supports IFMA.
+            unsafe { x86_64::monty_scale_slice::<M>(values, factor) };
+            return true;
+        }
+    }
+    let _ = (values, factor);
+    false
+}
+
+/// Multiplies each element of `values` by the corresponding element of `factors`, all in
+/// Montgomery form. Returns false, leaving `values` unchanged, if IFMA is unsupported. Only moduli
+/// below 2^255 are supported.
+pub(crate) fn monty_mul_slices_ifma<M: MontyRepr>(values: &mut [[u64; 4]], factors: &[[u64; 4]]) -> bool {
+    assert_eq!(values.len(), factors.len(), "Slices have different lengths");
+    #[cfg(target_arch = "x86_64")]
+    {
+        debug_assert!(M::ORDER[3] >> 63 == 0, "Modulus is too large for IFMA");
+        if x86_64::is_available() {
+            // Safe since we just checked that the CPU supports IFMA.
+            unsafe { x86_64::monty_mul_slices::<M>(values, factors) };
+            return true;
+        }
+    }
+    let _ = (values, factors);
+    false
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64 {
+    use std::arch::x86_64::*;
+    use std::cmp::Ordering::Less;
+
+    use crate::{cmp, sub, MontyRepr};
+
+    const MASK_52: u64 = (1 << 52) - 1;
+
+    pub(super) fn is_available() -> bool {
+        is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512ifma")
+    }
+
+    /// Splits `x << shift` into 52-bit limbs, where `shift` is at most 4.
+    fn to_radix_52(x: [u64; 4], shift: u32) -> [u64; 5] {
+        let y = if shift == 0 {
+            [x[0], x[1], x[2], x[3], 0]
+        } else {
+            [
+                x[0] << shift,
+                (x[1] << shift) | (x[0] >> (64 - shift)),
+                (x[2] << shift) | (x[1] >> (64 - shift)),
+                (x[3] << shift) | (x[2] >> (64 - shift)),
+                x[3] >> (64 - shift),
+            ]
+        };
+        [
+            y[0] & MASK_52,
+            ((y[0] >> 52) | (y[1] << 12)) & MASK_52,
+            ((y[1] >> 40) | (y[2] << 24)) & MASK_52,
+            ((y[2] >> 28) | (y[3] << 36)) & MASK_52,
+            (y[3] >> 16) | (y[4] << 48),
+        ]
+    }
+
+    /// Joins normalized 52-bit limbs of a value below 2^256 into 64-bit limbs.
+    fn from_radix_52(t: [u64; 5]) -> [u64; 4] {
+        [
+            t[0] | (t[1] << 52),
+            (t[1] >> 12) | (t[2] << 40),
+            (t[2] >> 24) | (t[3] << 28),
+            (t[3] >> 36) | (t[4] << 16),
+        ]
+    }
+
+    /// Transposes eight elements' 52-bit limbs into five vectors, each holding one limb of every
+    /// element.
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load_transposed(limbs: &[[u64; 5]; 8]) -> [__m512i; 5] {
+        let mut transposed = [[0u64; 8]; 5];
+        for (i, element) in limbs.iter().enumerate() {
+            for k in 0..5 {
+                transposed[k][i] = element[k];
+            }
+        }
+        let mut vectors = [_mm512_setzero_si512(); 5];
+        for (v, t) in vectors.iter_mut().zip(transposed.iter()) {
+            *v = _mm512_loadu_epi64(t.as_ptr() as *const i64);
+        }
+        vectors
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn broadcast(limbs: [u64; 5]) -> [__m512i; 5] {
+        let mut vectors = [_mm512_setzero_si512(); 5];
+        for (v, &l) in vectors.iter_mut().zip(limbs.iter()) {
+            *v = _mm512_set1_epi64(l as i64);
+        }
+        vectors
+    }
+
+    /// Computes `a b / 2^260` mod p for each lane, returning normalized limbs of a value below
+    /// `2p`, provided that `a b < 2^259 p`.
+    #[target_feature(enable = "avx512f,avx512ifma")]
+    unsafe fn monty_multiply_x8<M: MontyRepr>(a: &[__m512i; 5], b: &[__m512i; 5]) -> [__m512i; 5] {
+        let p = broadcast(to_radix_52(M::ORDER, 0));
+        let mu = _mm512_set1_epi64((M::MU & MASK_52) as i64);
+        let zero = _mm512_setzero_si512();
+
+        let mut t = [zero; 6];
+        for a_i in a.iter() {
+            // t += a_i b
+            for j in 0..5 {
+                t[j] = _mm512_madd52lo_epu64(t[j], *a_i, b[j]);
+                t[j + 1] = _mm512_madd52hi_epu64(t[j + 1], *a_i, b[j]);
+            }
+
+            // t += q p, where q is chosen so that the lowest 52 bits of t become zero.
+            let q = _mm512_madd52lo_epu64(zero, t[0], mu);
+            for j in 0..5 {
+                t[j] = _mm512_madd52lo_epu64(t[j], q, p[j]);
+                t[j + 1] = _mm512_madd52hi_epu64(t[j + 1], q, p[j]);
+            }
+
+            // t /= 2^52
+            let carry = _mm512_srli_epi64::<52>(t[0]);
+            t = [_mm512_add_epi64(t[1], carry), t[2], t[3], t[4], t[5], zero];
+        }
+
+        // Propagate carries, so that each limb is below 2^52.
+        let mask = _mm512_set1_epi64(MASK_52 as i64);
+        for j in 0..4 {
+            t[j + 1] = _mm512_add_epi64(t[j + 1], _mm512_srli_epi64::<52>(t[j]));
+            t[j] = _mm512_and_si512(t[j], mask);
+        }
+        [t[0], t[1], t[2], t[3], t[4]]
+    }
+
+    /// Loads eight elements, shifted left by 4 bits, into vectors of their 52-bit limbs.
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load_shifted(chunk: &[[u64; 4]]) -> [__m512i; 5] {
+        let mut limbs = [[0u64; 5]; 8];
+        for (l, x) in limbs.iter_mut().zip(chunk.iter()) {
+            *l = to_radix_52(*x, 4);
+        }
+        load_transposed(&limbs)
+    }
+
+    /// Stores the eight products returned by `monty_multiply_x8` in `chunk`, fully reduced.
+    #[target_feature(enable = "avx512f")]
+    unsafe fn store_reduced<M: MontyRepr>(product: &[__m512i; 5], chunk: &mut [[u64; 4]]) {
+        let mut transposed = [[0u64; 8]; 5];
+        for k in 0..5 {
+            _mm512_storeu_epi64(transposed[k].as_mut_ptr() as *mut i64, product[k]);
+        }
+        for (i, x) in chunk.iter_mut().enumerate() {
+            let t = [transposed[0][i], transposed[1][i], transposed[2][i], transposed[3][i], transposed[4][i]];
+            let mut result = from_radix_52(t);
+            if cmp(result, M::ORDER) != Less {
+                result = sub(result, M::ORDER);
+            }
+            *x = result;
+        }
+    }
+
+    #[target_feature(enable = "avx512f,avx512ifma")]
+    pub(super) unsafe fn monty_scale_slice<M: MontyRepr>(values: &mut [[u64; 4]], factor: [u64; 4]) {
+        let factor_x8 = broadcast(to_radix_52(factor, 0));
+        let mut chunks = values.chunks_exact_mut(8);
+        for chunk in &mut chunks {
+            let product = monty_multiply_x8::<M>(&load_shifted(chunk), &factor_x8);
+            store_reduced::<M>(&product, chunk);
+        }
+        for x in chunks.into_remainder() {
+            *x = M::monty_multiply(*x, factor);
+        }
+    }
+
+    #[target_feature(enable = "avx512f,avx512ifma")]
+    pub(super) unsafe fn monty_mul_slices<M: MontyRepr>(values: &mut [[u64; 4]], factors: &[[u64; 4]]) {
+        let mut chunks = values.chunks_exact_mut(8);
+        let mut factor_chunks = factors.chunks_exact(8);
+        for (chunk, factor_chunk) in (&mut chunks).zip(&mut factor_chunks) {
+            let mut factor_limbs = [[0u64; 5]; 8];
+            for (l, y) in factor_limbs.iter_mut().zip(factor_chunk.iter()) {
+                *l = to_radix_52(*y, 0);
+            }
+            let product = monty_multiply_x8::<M>(&load_shifted(chunk), &load_transposed(&factor_limbs));
+            store_reduced::<M>(&product, chunk);
+        }
+        for (x, &y) in chunks.into_remainder().iter_mut().zip(factor_chunks.remainder()) {
+            *x = M::monty_multiply(*x, y);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::field::ifma::x86_64::{
+            from_radix_52, is_available, monty_mul_slices, monty_scale_slice, to_radix_52,
+        };
+        use crate::{Field, MontyRepr, TweedledeeBase, TweedledumBase};
+
+        #[test]
+        fn test_radix_52_round_trip() {
+            let x = TweedledeeBase::rand().limbs;
+            assert_eq!(from_radix_52(to_radix_52(x, 0)), x);
+        }
+
+        fn check_scale_slice<F: Field + MontyRepr>(limbs: fn(F) -> [u64; 4]) {
+            if !is_available() {
+                return;
+            }
+            // Include the extremes, and a remainder which isn't a multiple of 8.
+            let mut xs = vec![<F as Field>::ZERO, <F as Field>::ONE, F::NEG_ONE];
+            xs.extend((0..20).map(|_| F::rand()));
+            for &factor in &[F::NEG_ONE, F::rand()] {
+                let mut values = xs.iter().map(|&x| limbs(x)).collect::<Vec<_>>();
+                unsafe { monty_scale_slice::<F>(&mut values, limbs(factor)) };
+                let expected = xs.iter().map(|&x| limbs(x * factor)).collect::<Vec<_>>();
+                assert_eq!(values, expected);
+            }
+        }
+
+        #[test]
+        fn test_scale_slice() {
+            check_scale_slice::<TweedledeeBase>(|x| x.limbs);
+            check_scale_slice::<TweedledumBase>(|x| x.limbs);
+        }
+
+        fn check_mul_slices<F: Field + MontyRepr>(limbs: fn(F) -> [u64; 4]) {
+            if !is_available() {
+                return;
+            }
+            // Include the extremes, and a remainder which isn't a multiple of 8.
+            let mut xs = vec![<F as Field>::ZERO, <F as Field>::ONE, F::NEG_ONE, F::NEG_ONE];
+            xs.extend((0..19).map(|_| F::rand()));
+            let ys = xs.iter().rev().map(|&x| x + F::rand()).collect::<Vec<_>>();
+            let mut values = xs.iter().map(|&x| limbs(x)).collect::<Vec<_>>();
+            let factors = ys.iter().map(|&y| limbs(y)).collect::<Vec<_>>();
+            unsafe { monty_mul_slices::<F>(&mut values, &factors) };
+            let expected = xs.iter().zip(&ys).map(|(&x, &y)| limbs(x * y)).collect::<Vec<_>>();
+            assert_eq!(values, expected);
+        }
+
+        #[test]
+        fn test_mul_slices() {
+            check_mul_slices::<TweedledeeBase>(|x| x.limbs);
+            check_mul_slices::<TweedledumBase>(|x| x.limbs);
+        }
+    }
+}
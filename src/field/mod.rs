@@ -1,6 +1,10 @@
 pub use bls12_377_base::*;
 pub use bls12_377_scalar::*;
+pub use bn254_base::*;
+pub use bn254_scalar::*;
 pub use field::*;
+pub(crate) use ifma::*;
+pub(crate) use neon::*;
 pub use tweedledee_base::*;
 pub use tweedledum_base::*;
 pub use monty::*;
@@ -9,8 +13,12 @@ pub use vesta_base::*;
 
 mod bls12_377_base;
 mod bls12_377_scalar;
+mod bn254_base;
+mod bn254_scalar;
 #[allow(clippy::module_inception)]
 mod field;
+mod ifma;
+mod neon;
 mod tweedledee_base;
 mod tweedledum_base;
 mod monty;
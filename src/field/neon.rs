@@ -0,0 +1,224 @@
+//! Montgomery multiplication of two field elements at once, using NEON instructions, which
+//! multiply 32-bit limbs into 64-bit lanes. NEON is part of the aarch64 baseline, so unlike IFMA
+//! no runtime check is needed; on other architectures callers fall back to `MontyRepr`.
+//!
+//! Elements are split into eight 32-bit limbs, with limb `k` of each of the two elements held in
+//! one vector, and multiplied with a radix-2^32 Montgomery multiplication. Eight limbs of 32 bits
+//! divide by exactly 2^256, which matches our Montgomery representation, so unlike the IFMA
+//! backend no operand needs to be shifted first. Like IFMA, it backs both scaling a slice by one
+//! factor and multiplying two slices elementwise.
+
+use crate::MontyRepr;
+
+/// Multiplies each element of `values` by `factor`, all in Montgomery form, like
+/// `MontyRepr::monty_multiply`. Returns false, leaving `values` unchanged, if NEON is unsupported.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+pub(crate) fn monty_scale_slice_neon<M: MontyRepr>(values: &mut [[u64; 4]], factor: [u64; 4]) -> bool {
+    // Safe since NEON is enabled at compile time.
+    unsafe { aarch64::monty_scale_slice::<M>(values, factor) };
+    true
+}
+
+// `M` only matters to the NEON version, but callers name it on every architecture.
+#[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+#[allow(clippy::extra_unused_type_parameters)]
+pub(crate) fn monty_scale_slice_neon<M: MontyRepr>(_values: &mut [[u64; 4]], _factor: [u64; 4]) -> bool {
+    false
+}
+
+/// Multiplies each element of `values` by the corresponding element of `factors`, all in
+/// Montgomery form. Returns false, leaving `values` unchanged, if NEON is unsupported.
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+pub(crate) fn monty_mul_slices_neon<M: MontyRepr>(values: &mut [[u64; 4]], factors: &[[u64; 4]]) -> bool {
+    assert_eq!(values.len(), factors.len(), "Slices have different lengths");
+    // Safe since NEON is enabled at compile time.
+    unsafe { aarch64::monty_mul_slices::<M>(values, factors) };
+    true
+}
+
+#[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+#[allow(clippy::extra_unused_type_parameters)]
+pub(crate) fn monty_mul_slices_neon<M: MontyRepr>(values: &mut [[u64; 4]], factors: &[[u64; 4]]) -> bool {
+    assert_eq!(values.len(), factors.len(), "Slices have different lengths");
+    false
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod aarch64 {
+    use std::arch::aarch64::*;
+    use std::cmp::Ordering::Less;
+
+    use crate::{cmp, sub, MontyRepr};
+
+    const MASK_32: u64 = (1 << 32) - 1;
+
+    /// Splits `x` into little-endian 32-bit limbs.
+    fn to_radix_32(x: [u64; 4]) -> [u32; 8] {
+        let mut limbs = [0u32; 8];
+        for (i, &l) in x.iter().enumerate() {
+            limbs[2 * i] = l as u32;
+            limbs[2 * i + 1] = (l >> 32) as u32;
+        }
+        limbs
+    }
+
+    /// Joins little-endian 32-bit limbs into 64-bit limbs.
+    fn from_radix_32(t: [u32; 8]) -> [u64; 4] {
+        let mut limbs = [0u64; 4];
+        for (i, l) in limbs.iter_mut().enumerate() {
+            *l = t[2 * i] as u64 | ((t[2 * i + 1] as u64) << 32);
+        }
+        limbs
+    }
+
+    /// Computes `a b / 2^256` mod p for both lanes, returning the limbs of a value below `2p`.
+    /// Each vector of `a` and `b` holds one limb of both elements.
+    #[target_feature(enable = "neon")]
+    unsafe fn monty_multiply_x2<M: MontyRepr>(a: &[uint32x2_t; 8], b: &[uint32x2_t; 8]) -> [[u32; 8]; 2] {
+        let p = to_radix_32(M::ORDER);
+        let mu = M::MU as u32;
+        let mask = vdupq_n_u64(MASK_32);
+
+        // Each lane of `t` holds a 32-bit limb, leaving room for a 32x32 bit product plus two
+        // 32-bit summands without overflowing.
+        let mut t = [vdupq_n_u64(0); 10];
+        for a_i in a.iter() {
+            // t += a_i b
+            let mut carry = vdupq_n_u64(0);
+            for j in 0..8 {
+                let s = vmlal_u32(vaddq_u64(t[j], carry), *a_i, b[j]);
+                t[j] = vandq_u64(s, mask);
+                carry = vshrq_n_u64::<32>(s);
+            }
+            let s = vaddq_u64(t[8], carry);
+            t[8] = vandq_u64(s, mask);
+            t[9] = vshrq_n_u64::<32>(s);
+
+            // t += q p, where q is chosen so that the lowest limb of t becomes zero, then t /= 2^32.
+            let q = vmul_n_u32(vmovn_u64(t[0]), mu);
+            let mut carry = vshrq_n_u64::<32>(vmlal_n_u32(t[0], q, p[0]));
+            for j in 1..8 {
+                let s = vmlal_n_u32(vaddq_u64(t[j], carry), q, p[j]);
+                t[j - 1] = vandq_u64(s, mask);
+                carry = vshrq_n_u64::<32>(s);
+            }
+            let s = vaddq_u64(t[8], carry);
+            t[7] = vandq_u64(s, mask);
+            t[8] = vaddq_u64(t[9], vshrq_n_u64::<32>(s));
+        }
+
+        let mut result = [[0u32; 8]; 2];
+        for (j, t_j) in t.iter().take(8).enumerate() {
+            result[0][j] = vgetq_lane_u64::<0>(*t_j) as u32;
+            result[1][j] = vgetq_lane_u64::<1>(*t_j) as u32;
+        }
+        result
+    }
+
+    /// Loads the limbs of two elements, with limb `k` of both elements in vector `k`.
+    #[target_feature(enable = "neon")]
+    unsafe fn load_transposed(chunk: &[[u64; 4]]) -> [uint32x2_t; 8] {
+        let x0 = to_radix_32(chunk[0]);
+        let x1 = to_radix_32(chunk[1]);
+        let mut a = [vdup_n_u32(0); 8];
+        for (k, a_k) in a.iter_mut().enumerate() {
+            *a_k = vld1_u32([x0[k], x1[k]].as_ptr());
+        }
+        a
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn broadcast(x: [u64; 4]) -> [uint32x2_t; 8] {
+        let limbs = to_radix_32(x);
+        let mut b = [vdup_n_u32(0); 8];
+        for (b_k, &l) in b.iter_mut().zip(limbs.iter()) {
+            *b_k = vdup_n_u32(l);
+        }
+        b
+    }
+
+    /// Stores the two products returned by `monty_multiply_x2` in `chunk`, fully reduced.
+    fn store_reduced<M: MontyRepr>(product: &[[u32; 8]; 2], chunk: &mut [[u64; 4]]) {
+        for (x, p) in chunk.iter_mut().zip(product.iter()) {
+            let mut result = from_radix_32(*p);
+            if cmp(result, M::ORDER) != Less {
+                result = sub(result, M::ORDER);
+            }
+            *x = result;
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn monty_scale_slice<M: MontyRepr>(values: &mut [[u64; 4]], factor: [u64; 4]) {
+        let factor_x2 = broadcast(factor);
+        let mut chunks = values.chunks_exact_mut(2);
+        for chunk in &mut chunks {
+            let product = monty_multiply_x2::<M>(&load_transposed(chunk), &factor_x2);
+            store_reduced::<M>(&product, chunk);
+        }
+        for x in chunks.into_remainder() {
+            *x = M::monty_multiply(*x, factor);
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn monty_mul_slices<M: MontyRepr>(values: &mut [[u64; 4]], factors: &[[u64; 4]]) {
+        let mut chunks = values.chunks_exact_mut(2);
+        let mut factor_chunks = factors.chunks_exact(2);
+        for (chunk, factor_chunk) in (&mut chunks).zip(&mut factor_chunks) {
+            let product = monty_multiply_x2::<M>(&load_transposed(chunk), &load_transposed(factor_chunk));
+            store_reduced::<M>(&product, chunk);
+        }
+        for (x, &y) in chunks.into_remainder().iter_mut().zip(factor_chunks.remainder()) {
+            *x = M::monty_multiply(*x, y);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::field::neon::aarch64::{from_radix_32, monty_mul_slices, monty_scale_slice, to_radix_32};
+        use crate::{Field, MontyRepr, TweedledeeBase, TweedledumBase};
+
+        #[test]
+        fn test_radix_32_round_trip() {
+            let x = TweedledeeBase::rand().limbs;
+            assert_eq!(from_radix_32(to_radix_32(x)), x);
+        }
+
+        fn check_scale_slice<F: Field + MontyRepr>(limbs: fn(F) -> [u64; 4]) {
+            // Include the extremes, and a remainder which isn't a multiple of 2.
+            let mut xs = vec![<F as Field>::ZERO, <F as Field>::ONE, F::NEG_ONE];
+            xs.extend((0..20).map(|_| F::rand()));
+            for &factor in &[F::NEG_ONE, F::rand()] {
+                let mut values = xs.iter().map(|&x| limbs(x)).collect::<Vec<_>>();
+                unsafe { monty_scale_slice::<F>(&mut values, limbs(factor)) };
+                let expected = xs.iter().map(|&x| limbs(x * factor)).collect::<Vec<_>>();
+                assert_eq!(values, expected);
+            }
+        }
+
+        #[test]
+        fn test_scale_slice() {
+            check_scale_slice::<TweedledeeBase>(|x| x.limbs);
+            check_scale_slice::<TweedledumBase>(|x| x.limbs);
+        }
+
+        fn check_mul_slices<F: Field + MontyRepr>(limbs: fn(F) -> [u64; 4]) {
+            // Include the extremes, and a remainder which isn't a multiple of 2.
+            let mut xs = vec![<F as Field>::ZERO, <F as Field>::ONE, F::NEG_ONE, F::NEG_ONE];
+            xs.extend((0..19).map(|_| F::rand()));
+            let ys = xs.iter().rev().map(|&x| x + F::rand()).collect::<Vec<_>>();
+            let mut values = xs.iter().map(|&x| limbs(x)).collect::<Vec<_>>();
+            let factors = ys.iter().map(|&y| limbs(y)).collect::<Vec<_>>();
+            unsafe { monty_mul_slices::<F>(&mut values, &factors) };
+            let expected = xs.iter().zip(&ys).map(|(&x, &y)| limbs(x * y)).collect::<Vec<_>>();
+            assert_eq!(values, expected);
+        }
+
+        #[test]
+        fn test_mul_slices() {
+            check_mul_slices::<TweedledeeBase>(|x| x.limbs);
+            check_mul_slices::<TweedledumBase>(|x| x.limbs);
+        }
+    }
+}
@@ -8,10 +8,12 @@ use std::fmt::{Debug, Display, Formatter};
 
 use crate::{cmp, field_to_biguint,
             rand_range, rand_range_from_rng,
-            MontyRepr, Field};
+            monty_mul_slices_ifma, monty_mul_slices_neon, monty_scale_slice_ifma,
+            monty_scale_slice_neon, MontyRepr, Field};
 
 /// An element of the Tweedledee group's base field.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Default)]
+#[repr(transparent)]
 pub struct TweedledeeBase {
     /// Montgomery representation, encoded with little-endian u64 limbs.
     pub limbs: [u64; 4],
@@ -210,6 +212,32 @@ impl Field for TweedledeeBase {
             limbs: Self::monty_square(self.limbs),
         }
     }
+
+    fn scale_slice_in_place(&self, slice: &mut [Self]) {
+        // This is safe since `Self` is a transparent wrapper around its limbs.
+        let limbs = unsafe { &mut *(slice as *mut [Self] as *mut [[u64; 4]]) };
+        if !monty_scale_slice_ifma::<Self>(limbs, self.limbs)
+            && !monty_scale_slice_neon::<Self>(limbs, self.limbs)
+        {
+            for x in slice.iter_mut() {
+                *x = *x * *self;
+            }
+        }
+    }
+
+    fn batch_multiply_in_place(values: &mut [Self], factors: &[Self]) {
+        assert_eq!(values.len(), factors.len(), "Slices have different lengths");
+        // These are safe since `Self` is a transparent wrapper around its limbs.
+        let limbs = unsafe { &mut *(values as *mut [Self] as *mut [[u64; 4]]) };
+        let factor_limbs = unsafe { &*(factors as *const [Self] as *const [[u64; 4]]) };
+        if !monty_mul_slices_ifma::<Self>(limbs, factor_limbs)
+            && !monty_mul_slices_neon::<Self>(limbs, factor_limbs)
+        {
+            for (x, &y) in values.iter_mut().zip(factors) {
+                *x = *x * y;
+            }
+        }
+    }
 }
 
 impl Ord for TweedledeeBase {
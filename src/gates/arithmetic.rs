@@ -1,22 +1,35 @@
 use std::marker::PhantomData;
 
 use crate::gates::Gate;
-use crate::{CircuitBuilder, HaloCurve, PartialWitness, Target, Wire, WitnessGenerator};
+use crate::{CircuitBuilder, HaloCurve, PartialWitness, Target, Wire, WitnessGenerator, NUM_WIRES};
 
 /// A gate which can be configured to perform various arithmetic. In particular, it computes
 ///
 /// ```text
 /// output := const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend
 /// ```
+///
+/// In circuits wider than `NUM_WIRES`, a row holds several such operations, or lanes, which share
+/// the row's constants and `multiplicand_1`. Lane 0 uses wires 0 through 3, and each further lane
+/// three more wires for its `multiplicand_0`, `addend` and `output`. See `num_lanes`.
 pub struct ArithmeticGate<C: HaloCurve> {
     pub index: usize,
+    /// The lane whose output this gate's generator populates.
+    pub lane: usize,
     _phantom: PhantomData<C>,
 }
 
 impl<C: HaloCurve> ArithmeticGate<C> {
     pub fn new(index: usize) -> Self {
+        Self::new_lane(index, 0)
+    }
+
+    /// A generator for lane `lane` of the given gate, which `CircuitBuilder` adds when it packs an
+    /// operation into an existing row.
+    pub fn new_lane(index: usize, lane: usize) -> Self {
         ArithmeticGate {
             index,
+            lane,
             _phantom: PhantomData,
         }
     }
@@ -25,6 +38,36 @@ impl<C: HaloCurve> ArithmeticGate<C> {
     pub const WIRE_MULTIPLICAND_1: usize = 1;
     pub const WIRE_ADDEND: usize = 2;
     pub const WIRE_OUTPUT: usize = 3;
+
+    /// The number of wires used by each lane after the first.
+    pub const WIRES_PER_EXTRA_LANE: usize = 3;
+
+    /// The number of lanes in a row of the given width. Circuits of the default width have a single
+    /// lane, so that their constraints are unchanged; each three wires beyond that add a lane.
+    pub fn num_lanes(num_wires: usize) -> usize {
+        1 + num_wires.saturating_sub(NUM_WIRES) / Self::WIRES_PER_EXTRA_LANE
+    }
+
+    /// The number of lanes whose wires are all among the first `num_routed_wires`, so that
+    /// operations in them can be wired to the rest of the circuit.
+    pub fn num_routed_lanes(num_wires: usize, num_routed_wires: usize) -> usize {
+        let routed = 1 + (num_routed_wires - 4) / Self::WIRES_PER_EXTRA_LANE;
+        Self::num_lanes(num_wires).min(routed)
+    }
+
+    /// The index of the given wire of the given lane, e.g. `WIRE_OUTPUT`.
+    pub fn lane_wire(lane: usize, input: usize) -> usize {
+        if lane == 0 || input == Self::WIRE_MULTIPLICAND_1 {
+            return input;
+        }
+        let offset = match input {
+            Self::WIRE_MULTIPLICAND_0 => 0,
+            Self::WIRE_ADDEND => 1,
+            Self::WIRE_OUTPUT => 2,
+            _ => panic!("Invalid input: {}", input),
+        };
+        4 + (lane - 1) * Self::WIRES_PER_EXTRA_LANE + offset
+    }
 }
 
 impl<C: HaloCurve> Gate<C> for ArithmeticGate<C> {
@@ -40,12 +83,17 @@ impl<C: HaloCurve> Gate<C> for ArithmeticGate<C> {
     ) -> Vec<C::ScalarField> {
         let const_0 = local_constant_values[Self::PREFIX.len()];
         let const_1 = local_constant_values[Self::PREFIX.len() + 1];
-        let multiplicand_0 = local_wire_values[Self::WIRE_MULTIPLICAND_0];
-        let multiplicand_1 = local_wire_values[Self::WIRE_MULTIPLICAND_1];
-        let addend = local_wire_values[Self::WIRE_ADDEND];
-        let output = local_wire_values[Self::WIRE_OUTPUT];
-        let computed_output = const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend;
-        vec![computed_output - output]
+        (0..Self::num_lanes(local_wire_values.len()))
+            .map(|lane| {
+                let wire = |input| local_wire_values[Self::lane_wire(lane, input)];
+                let multiplicand_0 = wire(Self::WIRE_MULTIPLICAND_0);
+                let multiplicand_1 = wire(Self::WIRE_MULTIPLICAND_1);
+                let addend = wire(Self::WIRE_ADDEND);
+                let output = wire(Self::WIRE_OUTPUT);
+                let computed_output = const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend;
+                computed_output - output
+            })
+            .collect()
     }
 
     fn evaluate_unfiltered_recursively(
@@ -57,33 +105,38 @@ impl<C: HaloCurve> Gate<C> for ArithmeticGate<C> {
     ) -> Vec<Target<C::ScalarField>> {
         let const_0 = local_constant_values[Self::PREFIX.len()];
         let const_1 = local_constant_values[Self::PREFIX.len() + 1];
-        let multiplicand_0 = local_wire_values[Self::WIRE_MULTIPLICAND_0];
-        let multiplicand_1 = local_wire_values[Self::WIRE_MULTIPLICAND_1];
-        let addend = local_wire_values[Self::WIRE_ADDEND];
-        let output = local_wire_values[Self::WIRE_OUTPUT];
-
-        let product_term = builder.mul_many(&[const_0, multiplicand_0, multiplicand_1]);
-        let addend_term = builder.mul(const_1, addend);
-        let computed_output = builder.add_many(&[product_term, addend_term]);
-        vec![builder.sub(computed_output, output)]
+        (0..Self::num_lanes(local_wire_values.len()))
+            .map(|lane| {
+                let wire = |input| local_wire_values[Self::lane_wire(lane, input)];
+                let multiplicand_0 = wire(Self::WIRE_MULTIPLICAND_0);
+                let multiplicand_1 = wire(Self::WIRE_MULTIPLICAND_1);
+                let addend = wire(Self::WIRE_ADDEND);
+                let output = wire(Self::WIRE_OUTPUT);
+
+                let product_term = builder.mul_many(&[const_0, multiplicand_0, multiplicand_1]);
+                let addend_term = builder.mul(const_1, addend);
+                let computed_output = builder.add_many(&[product_term, addend_term]);
+                builder.sub(computed_output, output)
+            })
+            .collect()
+    }
+}
+
+impl<C: HaloCurve> ArithmeticGate<C> {
+    fn wire(&self, input: usize) -> Wire {
+        Wire {
+            gate: self.index,
+            input: Self::lane_wire(self.lane, input),
+        }
     }
 }
 
 impl<C: HaloCurve> WitnessGenerator<C::ScalarField> for ArithmeticGate<C> {
     fn dependencies(&self) -> Vec<Target<C::ScalarField>> {
         vec![
-            Target::Wire(Wire {
-                gate: self.index,
-                input: Self::WIRE_MULTIPLICAND_0,
-            }),
-            Target::Wire(Wire {
-                gate: self.index,
-                input: Self::WIRE_MULTIPLICAND_1,
-            }),
-            Target::Wire(Wire {
-                gate: self.index,
-                input: Self::WIRE_ADDEND,
-            }),
+            Target::Wire(self.wire(Self::WIRE_MULTIPLICAND_0)),
+            Target::Wire(self.wire(Self::WIRE_MULTIPLICAND_1)),
+            Target::Wire(self.wire(Self::WIRE_ADDEND)),
         ]
     }
 
@@ -92,34 +145,17 @@ impl<C: HaloCurve> WitnessGenerator<C::ScalarField> for ArithmeticGate<C> {
         constants: &[Vec<C::ScalarField>],
         witness: &PartialWitness<C::ScalarField>,
     ) -> PartialWitness<C::ScalarField> {
-        let multiplicand_0_target = Wire {
-            gate: self.index,
-            input: Self::WIRE_MULTIPLICAND_0,
-        };
-        let multiplicand_1_target = Wire {
-            gate: self.index,
-            input: Self::WIRE_MULTIPLICAND_1,
-        };
-        let addend_target = Wire {
-            gate: self.index,
-            input: Self::WIRE_ADDEND,
-        };
-        let output_target = Wire {
-            gate: self.index,
-            input: Self::WIRE_OUTPUT,
-        };
-
         let const_0 = constants[self.index][Self::PREFIX.len()];
         let const_1 = constants[self.index][Self::PREFIX.len() + 1];
 
-        let multiplicand_0 = witness.get_wire(multiplicand_0_target);
-        let multiplicand_1 = witness.get_wire(multiplicand_1_target);
-        let addend = witness.get_wire(addend_target);
+        let multiplicand_0 = witness.get_wire(self.wire(Self::WIRE_MULTIPLICAND_0));
+        let multiplicand_1 = witness.get_wire(self.wire(Self::WIRE_MULTIPLICAND_1));
+        let addend = witness.get_wire(self.wire(Self::WIRE_ADDEND));
 
         let output = const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend;
 
         let mut result = PartialWitness::new();
-        result.set_wire(output_target, output);
+        result.set_wire(self.wire(Self::WIRE_OUTPUT), output);
         result
     }
 }
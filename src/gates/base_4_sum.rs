@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use crate::gates::Gate;
-use crate::{CircuitBuilder, Curve, Field, HaloCurve, PartialWitness, Target, WitnessGenerator, NUM_ROUTED_WIRES, NUM_WIRES};
+use crate::{CircuitBuilder, Curve, Field, HaloCurve, PartialWitness, Target, Wire, WitnessGenerator, NUM_ROUTED_WIRES, NUM_WIRES};
 
 /// A gate for accumulating base-4 limbs.
 pub struct Base4SumGate<C: Curve> {
@@ -98,17 +98,47 @@ impl<C: HaloCurve> Gate<C> for Base4SumGate<C> {
 
 impl<C: HaloCurve> WitnessGenerator<C::ScalarField> for Base4SumGate<C> {
     fn dependencies(&self) -> Vec<Target<C::ScalarField>> {
-        Vec::new()
+        let mut deps = vec![Target::Wire(Wire {
+            gate: self.index,
+            input: Self::WIRE_ACC_OLD,
+        })];
+        for i in 0..Self::NUM_LIMBS {
+            deps.push(Target::Wire(Wire {
+                gate: self.index,
+                input: Self::wire_limb(i),
+            }));
+        }
+        deps
     }
 
     fn generate(
         &self,
         _constants: &[Vec<C::ScalarField>],
-        _witness: &PartialWitness<C::ScalarField>,
+        witness: &PartialWitness<C::ScalarField>,
     ) -> PartialWitness<C::ScalarField> {
-        // For base 4 decompositions, we don't do any witness generation on a per-gate level.
-        // Instead, we have a single generator which generates values for an entire decomposition.
-        PartialWitness::new()
+        // The limbs themselves are populated by a single generator for an entire decomposition;
+        // here we just accumulate them.
+        let mut acc_new = witness.get_wire(Wire {
+            gate: self.index,
+            input: Self::WIRE_ACC_OLD,
+        });
+        for i in 0..Self::NUM_LIMBS {
+            let limb = witness.get_wire(Wire {
+                gate: self.index,
+                input: Self::wire_limb(i),
+            });
+            acc_new = acc_new.quadruple() + limb;
+        }
+
+        let mut result = PartialWitness::new();
+        result.set_wire(
+            Wire {
+                gate: self.index,
+                input: Self::WIRE_ACC_NEW,
+            },
+            acc_new,
+        );
+        result
     }
 }
 
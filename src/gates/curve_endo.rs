@@ -5,6 +5,9 @@ use crate::{AffinePoint, CircuitBuilder, Field, HaloCurve, PartialWitness, Targe
 
 /// A gate which performs an iteration of an simultaneous doubling MSM loop, employing the
 /// endomorphism described in the Halo paper. `C` is the curve of the inner proof.
+///
+/// The updated group accumulator is stored in the next gate, while the updated scalar accumulators
+/// are stored in the gate below, i.e. `GRID_WIDTH` gates later.
 pub struct CurveEndoGate<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
     pub index: usize,
     _phantom_oc: PhantomData<C>,
@@ -132,8 +135,8 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> Gate<C>
         let computed_scalar_acc_unsigned_new =
             builder.mul_add(scalar_acc_unsigned_old, four, unsigned_limb);
 
-        // This is based on Algorithm 2 in the Halo paper.
-        // TODO: Wrong zeta used here?
+        // This is based on Algorithm 2 in the Halo paper. Note that n(s) lives in the scalar field
+        // of InnerC, so this uses the base field's zeta, as in the unrecursive evaluation.
         let signed_limb_multiplier = builder.mul_add(zeta_minus_one, scalar_bit_1, one);
         let signed_limb_sign = builder.mul_sub(scalar_bit_0, two, one);
         let signed_limb = builder.mul(signed_limb_sign, signed_limb_multiplier);
@@ -143,8 +146,8 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> Gate<C>
         vec![
             builder.sub(computed_x3, x3),
             builder.sub(computed_y3, y3),
-            builder.sub(computed_scalar_acc_unsigned_new, scalar_acc_unsigned_new),
-            builder.sub(computed_scalar_acc_signed_new, scalar_acc_signed_new),
+            builder.sub(scalar_acc_unsigned_new, computed_scalar_acc_unsigned_new),
+            builder.sub(scalar_acc_signed_new, computed_scalar_acc_signed_new),
             assert_binary_recursively(builder, scalar_bit_0),
             assert_binary_recursively(builder, scalar_bit_1),
             assert_inverses_recursively(builder, inverse, x1_minus_x2),
@@ -216,19 +219,19 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> WitnessGenerat
 
         let scalar_acc_unsigned_old_target = Wire {
             gate: self.index,
-            input: Self::WIRE_GROUP_ACC_Y,
+            input: Self::WIRE_SCALAR_ACC_UNSIGNED,
         };
         let scalar_acc_unsigned_new_target = Wire {
             gate: self.index + GRID_WIDTH,
-            input: Self::WIRE_GROUP_ACC_Y,
+            input: Self::WIRE_SCALAR_ACC_UNSIGNED,
         };
         let scalar_acc_signed_old_target = Wire {
             gate: self.index,
-            input: Self::WIRE_GROUP_ACC_Y,
+            input: Self::WIRE_SCALAR_ACC_SIGNED,
         };
         let scalar_acc_signed_new_target = Wire {
             gate: self.index + GRID_WIDTH,
-            input: Self::WIRE_GROUP_ACC_Y,
+            input: Self::WIRE_SCALAR_ACC_SIGNED,
         };
 
         let addend_x_target = Wire {
@@ -267,16 +270,18 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> WitnessGenerat
         let p_x = witness.get_wire(addend_x_target);
         let p_y = witness.get_wire(addend_y_target);
 
+        // As in the constraints, bit 1 determines whether the endomorphism is applied, and bit 0
+        // determines the sign.
         let mut s_i_x = p_x;
-        if scalar_bit_0 == InnerC::BaseField::ONE {
+        if scalar_bit_1 == InnerC::BaseField::ONE {
             s_i_x = s_i_x * InnerC::ZETA;
         }
         let mut s_i_y = p_y;
-        if scalar_bit_1 == InnerC::BaseField::ZERO {
+        if scalar_bit_0 == InnerC::BaseField::ZERO {
             s_i_y = -s_i_y;
         }
         let s_i = AffinePoint::nonzero(s_i_x, s_i_y);
-        let group_acc_new = group_acc_old + s_i;
+        let group_acc_new = (group_acc_old + s_i).to_affine();
 
         let scalar_acc_unsigned_new =
             scalar_acc_unsigned_old.quadruple() + scalar_bit_0 + scalar_bit_1.double();
@@ -294,8 +299,7 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> WitnessGenerat
 
         // Here's where our abstraction leaks a bit. Although we already have the sum, we need to
         // redo part of the computation in order to populate the purported inverse wire.
-        let dx = group_acc_old_x - p_x;
-        let _dy = group_acc_old_y - p_y;
+        let dx = group_acc_old_x - s_i_x;
         let inverse = dx.multiplicative_inverse().expect("x_1 = x_2");
 
         let mut result = PartialWitness::new();
@@ -2,11 +2,11 @@
 //!
 //! ```text
 //! 101001 PublicInputGate
-//! 101000 CurveAddGate
+//! 10101* CurveAddGate
 //! 10111* CurveDblGate
 //! 11**** CurveEndoGate
 //! 1000** Base4SumGate
-//! 101010 BufferGate
+//! 101000 BufferGate
 //! 10110* ConstantGate
 //! 1001** ArithmeticGate
 //! 00**** RescueStepAGate
@@ -15,6 +15,11 @@
 //!
 //! The `*`s above represent constants which are not used in the gate prefix, and are thus available
 //! for gate configuration.
+//!
+//! Note that the tree is full, so a new gate type requires either shortening another gate's
+//! configuration or increasing `NUM_CONSTANTS`. Rather than adding a packed gate type, wider
+//! circuits give `ArithmeticGate` more lanes, which share its constants, and `CircuitBuilder` packs
+//! consecutive compatible operations into them. See `ArithmeticGate::num_lanes`.
 
 pub use arithmetic::*;
 pub use base_4_sum::*;
@@ -27,7 +32,9 @@ pub use public_input::*;
 pub use rescue_a::*;
 pub use rescue_b::*;
 
-use crate::{CircuitBuilder, Field, HaloCurve, Target, WitnessGenerator};
+use crate::plonk_util::{polynomial_degree_plus_1, values_to_polynomials};
+use crate::util::transpose;
+use crate::{cached_fft_precomputation, fft_with_precomputation_power_of_2, CircuitBuilder, Field, HaloCurve, Target, WitnessGenerator, GRID_WIDTH, NUM_CONSTANTS, NUM_WIRES, QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER};
 
 mod arithmetic;
 mod base_4_sum;
@@ -124,6 +131,112 @@ pub fn evaluate_all_constraints<C: HaloCurve, InnerC: HaloCurve<BaseField = C::S
     unified_constraint_set
 }
 
+/// Returns the name of the gate type whose prefix matches the given constants, or `None` if no
+/// prefix matches, which a circuit built by `CircuitBuilder` never contains.
+pub(crate) fn gate_name<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    local_constant_values: &[C::ScalarField],
+) -> Option<&'static str> {
+    let filters = [
+        (
+            CurveAddGate::<C, InnerC>::NAME,
+            CurveAddGate::<C, InnerC>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            CurveDblGate::<C, InnerC>::NAME,
+            CurveDblGate::<C, InnerC>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            CurveEndoGate::<C, InnerC>::NAME,
+            CurveEndoGate::<C, InnerC>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            Base4SumGate::<C>::NAME,
+            Base4SumGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            PublicInputGate::<C>::NAME,
+            PublicInputGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            BufferGate::<C>::NAME,
+            BufferGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            ConstantGate::<C>::NAME,
+            ConstantGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            ArithmeticGate::<C>::NAME,
+            ArithmeticGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            RescueStepAGate::<C>::NAME,
+            RescueStepAGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+        (
+            RescueStepBGate::<C>::NAME,
+            RescueStepBGate::<C>::evaluate_prefix_filter(local_constant_values),
+        ),
+    ];
+    // Prefix constants are bits, so only the filter of the matching gate type is one.
+    filters
+        .iter()
+        .find(|(_, filter)| *filter == C::ScalarField::ONE)
+        .map(|&(name, _)| name)
+}
+
+/// The highest constraint degree the prover supports. A constraint of degree `d` in the wire and
+/// constant polynomials has degree `d n`, so after dividing by the vanishing polynomial, the
+/// quotient must fit in `QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER` chunks of degree `n`.
+pub(crate) const MAX_CONSTRAINT_DEGREE: usize = QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER + 1;
+
+/// Measures the degree of each of `G`'s constraints, including the gate prefix filter, by
+/// substituting random polynomials of degree less than `n` for the constants and wires, and
+/// interpolating the constraint values over a domain of size `16n`. Constraints of degree 16 or
+/// higher can't be told apart from lower degrees, but those are well beyond what we support.
+pub(crate) fn constraint_degrees<C: HaloCurve, G: Gate<C>>(n: usize) -> Vec<usize> {
+    let fft_precomputation_n = cached_fft_precomputation::<C::ScalarField>(n);
+    let fft_precomputation_16n = cached_fft_precomputation::<C::ScalarField>(16 * n);
+
+    // Generate random constant and wire polynomials, and low-degree extend them to 16n values.
+    let random_values_16n = |num_polys: usize| {
+        let values_n = (0..num_polys)
+            .map(|_| (0..n).map(|_| C::ScalarField::rand()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let values_16n = values_to_polynomials(&values_n, &fft_precomputation_n)
+            .into_iter()
+            .map(|mut poly| {
+                poly.pad(16 * n);
+                fft_with_precomputation_power_of_2(&poly[..], &fft_precomputation_16n)
+            })
+            .collect::<Vec<_>>();
+        transpose(&values_16n)
+    };
+    let constant_values_16n_t = random_values_16n(NUM_CONSTANTS);
+    let wire_values_16n_t = random_values_16n(NUM_WIRES);
+
+    // Evaluate constraints at each of our 16n points. The next gate is 16 points later.
+    let constraint_values_16n = (0..16 * n)
+        .map(|i| {
+            G::evaluate_filtered(
+                &constant_values_16n_t[i],
+                &wire_values_16n_t[i],
+                &wire_values_16n_t[(i + 16) % (16 * n)],
+                &wire_values_16n_t[(i + 16 * GRID_WIDTH) % (16 * n)],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // A constraint of degree d in polynomials of degree n - 1 has degree d (n - 1).
+    transpose(&constraint_values_16n)
+        .iter()
+        .map(|c| {
+            let degree_plus_1 = polynomial_degree_plus_1(c, &fft_precomputation_16n);
+            (degree_plus_1.saturating_sub(1) + n - 2) / (n - 1)
+        })
+        .collect()
+}
+
 pub fn evaluate_all_constraints_recursively<
     C: HaloCurve,
     InnerC: HaloCurve<BaseField = C::ScalarField>,
@@ -340,104 +453,62 @@ macro_rules! test_gate_low_degree {
         #[ignore] // Too slow to run regularly.
         #[allow(non_snake_case)]
         fn $method() {
-            type C = $curve;
-            type SF = <C as $crate::curve::Curve>::ScalarField;
-
-            let n = 256;
-            let fft_precomputation_n = $crate::fft::fft_precompute::<SF>(n);
-            let fft_precomputation_16n = $crate::fft::fft_precompute::<SF>(16 * n);
-
-            // Generate random constant and wire polynomials.
-            let mut constant_values_n: Vec<Vec<SF>> =
-                vec![Vec::new(); $crate::plonk::NUM_CONSTANTS];
-            let mut wire_values_n: Vec<Vec<SF>> = vec![Vec::new(); $crate::plonk::NUM_WIRES];
-            for _ in 0..n {
-                for points in constant_values_n.iter_mut() {
-                    points.push(<SF as $crate::field::Field>::rand())
-                }
-                for points in wire_values_n.iter_mut() {
-                    points.push(<SF as $crate::field::Field>::rand())
-                }
-            }
-
-            // Low-degree extend them to 16n values.
-            let mut constant_polynomials_16n = $crate::plonk_util::values_to_polynomials(
-                &constant_values_n,
-                &fft_precomputation_n,
-            );
-            let mut wire_polynomials_16n =
-                $crate::plonk_util::values_to_polynomials(&wire_values_n, &fft_precomputation_n);
-            for poly in constant_polynomials_16n
-                .iter_mut()
-                .chain(wire_polynomials_16n.iter_mut())
-            {
-                poly.pad(16 * n);
-            }
-            let constant_values_16n: Vec<Vec<SF>> = constant_polynomials_16n
-                .iter()
-                .map(|poly| {
-                    $crate::fft::fft_with_precomputation_power_of_2(
-                        &poly[..],
-                        &fft_precomputation_16n,
-                    )
-                })
-                .collect();
-            let wire_values_16n: Vec<Vec<SF>> = wire_polynomials_16n
-                .iter()
-                .map(|poly| {
-                    $crate::fft::fft_with_precomputation_power_of_2(
-                        &poly[..],
-                        &fft_precomputation_16n,
-                    )
-                })
-                .collect();
-
-            // Make sure each extended polynomial is still degree <n.
-            for values_16n in constant_values_16n.iter().chain(wire_values_16n.iter()) {
-                assert!(
-                    $crate::plonk_util::polynomial_degree_plus_1(
-                        values_16n,
-                        &fft_precomputation_16n
-                    ) <= n
-                );
-            }
-
-            let constant_values_16n_t = $crate::util::transpose(&constant_values_16n);
-            let wire_values_16n_t = $crate::util::transpose(&wire_values_16n);
-
-            // Evaluate constraints at each of our 16n points.
-            let mut constraint_values_16n: Vec<Vec<SF>> = Vec::new();
-            for i in 0..16 * n {
-                let constraints: Vec<SF> = <$gate as $crate::gates::Gate<C>>::evaluate_filtered(
-                    &constant_values_16n_t[i],
-                    &wire_values_16n_t[i],
-                    &wire_values_16n_t[(i + 16) % (16 * n)],
-                    &wire_values_16n_t[(i + 16 * $crate::plonk::GRID_WIDTH) % (16 * n)],
-                );
-                for (j, &c) in constraints.iter().enumerate() {
-                    if constraint_values_16n.len() <= j {
-                        constraint_values_16n.push(Vec::new());
-                    }
-                    constraint_values_16n[j].push(c);
-                }
-            }
-
-            // Check that the degree of each constraint is within the limit.
-            let constraint_degrees_plus_1 = constraint_values_16n
-                .iter()
-                .map(|c| $crate::plonk_util::polynomial_degree_plus_1(c, &fft_precomputation_16n))
-                .collect::<Vec<_>>();
-            let max_degree_excl = (crate::plonk::QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER + 1) * n;
-            for (i, &deg_plus_1) in constraint_degrees_plus_1.iter().enumerate() {
+            let degrees = $crate::gates::constraint_degrees::<$curve, $gate>(256);
+            for (i, &degree) in degrees.iter().enumerate() {
                 assert!(
-                    deg_plus_1 <= max_degree_excl,
-                    "Constraint at index {} has degree+1 {}; should be at most {}n = {}",
+                    degree <= $crate::gates::MAX_CONSTRAINT_DEGREE,
+                    "Constraint at index {} has degree {}; should be at most {}",
                     i,
-                    deg_plus_1,
-                    crate::plonk::QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER + 1,
-                    max_degree_excl
+                    degree,
+                    $crate::gates::MAX_CONSTRAINT_DEGREE
                 );
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::gates::{evaluate_all_constraints, evaluate_all_constraints_recursively};
+    use crate::{CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum, NUM_CONSTANTS, NUM_WIRES};
+
+    #[test]
+    fn test_recursive_constraint_evaluation() -> Result<()> {
+        type F = <Tweedledee as Curve>::ScalarField;
+        let constants = (0..NUM_CONSTANTS).map(|_| F::rand()).collect::<Vec<_>>();
+        let local_wires = (0..NUM_WIRES).map(|_| F::rand()).collect::<Vec<_>>();
+        let right_wires = (0..NUM_WIRES).map(|_| F::rand()).collect::<Vec<_>>();
+        let below_wires = (0..NUM_WIRES).map(|_| F::rand()).collect::<Vec<_>>();
+        let expected = evaluate_all_constraints::<Tweedledee, Tweedledum>(
+            &constants,
+            &local_wires,
+            &right_wires,
+            &below_wires,
+        );
+
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let constants_t = builder.add_virtual_targets(NUM_CONSTANTS);
+        let local_wires_t = builder.add_virtual_targets(NUM_WIRES);
+        let right_wires_t = builder.add_virtual_targets(NUM_WIRES);
+        let below_wires_t = builder.add_virtual_targets(NUM_WIRES);
+        let constraints = evaluate_all_constraints_recursively::<Tweedledee, Tweedledum>(
+            &mut builder,
+            &constants_t,
+            &local_wires_t,
+            &right_wires_t,
+            &below_wires_t,
+        );
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_targets(&constants_t, &constants);
+        inputs.set_targets(&local_wires_t, &local_wires);
+        inputs.set_targets(&right_wires_t, &right_wires);
+        inputs.set_targets(&below_wires_t, &below_wires);
+        let witness = circuit.generate_partial_witness(inputs)?;
+        assert_eq!(witness.get_targets(&constraints), expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::permutation_batch::permute_in_lanes;
+use crate::{Field, Sponge};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The number of rounds of GMiMC. GMiMC has received less cryptanalysis than Rescue or Poseidon,
+/// and some instances have been attacked algebraically, so we use a generous number of rounds for
+/// our small widths. It should still be considered experimental.
+pub(crate) const GMIMC_ROUNDS: usize = 121;
+
+pub fn gmimc_sponge<F: Field>(inputs: Vec<F>, num_outputs: usize, security_bits: usize) -> Vec<F> {
+    let mut sponge = Sponge::new_gmimc(security_bits);
+    sponge.absorb_all(&inputs);
+    sponge.squeeze_n(num_outputs)
+}
+
+/// The GMiMC-erf permutation. In each round, a single S-box is applied to the first element plus a
+/// round constant, its output is added to every other element, and the state is rotated by one.
+/// Since this needs only one S-box per round, it is cheap both natively and in-circuit.
+pub fn gmimc_permutation<F: Field>(state: &[F], security_bits: usize) -> Vec<F> {
+    assert!(
+        security_bits <= 128,
+        "Our GMiMC parameters target at most 128 bits of security"
+    );
+    let mut state = state.to_vec();
+    for c in generate_gmimc_constants::<F>() {
+        let f = (state[0] + c).exp(F::ALPHA);
+        for x in state.iter_mut().skip(1) {
+            *x = *x + f;
+        }
+        state.rotate_left(1);
+    }
+    state
+}
+
+/// Applies `gmimc_permutation` to each of the given states, which must have the same width. The
+/// states are permuted in lockstep, so this is faster than permuting them one at a time.
+pub fn gmimc_permutation_batch<F: Field>(states: &[Vec<F>], security_bits: usize) -> Vec<Vec<F>> {
+    assert!(
+        security_bits <= 128,
+        "Our GMiMC parameters target at most 128 bits of security"
+    );
+    let constants = generate_gmimc_constants::<F>();
+
+    permute_in_lanes(states, |lanes| {
+        for &c in &constants {
+            lanes.apply_gmimc_round(c, |x| x.exp(F::ALPHA));
+        }
+    })
+}
+
+pub(crate) fn generate_gmimc_constants<F: Field>() -> Vec<F> {
+    // We use a different seed than Rescue and Poseidon, so that the permutations don't share
+    // constants.
+    let mut rng = ChaCha8Rng::seed_from_u64(4242);
+    (0..GMIMC_ROUNDS)
+        .map(|_| F::rand_from_rng(&mut rng))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{gmimc_permutation, gmimc_sponge, poseidon_sponge, Field, TweedledumBase};
+
+    type F = TweedledumBase;
+
+    #[test]
+    fn test_gmimc_permutation_is_not_trivial() {
+        let state = vec![F::ZERO; 4];
+        let permuted = gmimc_permutation(&state, 128);
+        assert_ne!(permuted, state);
+        assert_ne!(gmimc_permutation(&permuted, 128), permuted);
+    }
+
+    #[test]
+    fn test_gmimc_sponge() {
+        let inputs = (0..5).map(F::from_canonical_usize).collect::<Vec<_>>();
+        let outputs = gmimc_sponge(inputs.clone(), 2, 128);
+        assert_eq!(outputs, gmimc_sponge(inputs.clone(), 2, 128));
+        assert_ne!(outputs, poseidon_sponge(inputs, 2, 128));
+    }
+}
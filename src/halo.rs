@@ -1,10 +1,14 @@
-use crate::plonk_challenger::Challenger;
+use crate::plonk_challenger::Transcript;
 use crate::plonk_util::{halo_n, halo_n_mul, powers, reduce_with_powers};
 use crate::util::log2_strict;
-use crate::{msm_execute_parallel, msm_parallel, msm_precompute, AffinePoint, Curve, Field, HaloCurve, PolynomialCommitment, ProjectivePoint, SchnorrProof};
+use crate::{msm_parallel, AffinePoint, Curve, Field, HaloCurve, PolynomialCommitment, ProjectivePoint, SchnorrProof};
 use anyhow::Result;
+use rand::Rng;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct OpeningProof<C: HaloCurve> {
     pub halo_l: Vec<AffinePoint<C>>,
     pub halo_r: Vec<AffinePoint<C>>,
@@ -13,7 +17,7 @@ pub struct OpeningProof<C: HaloCurve> {
 }
 
 #[allow(clippy::too_many_arguments)]
-pub fn batch_opening_proof<C: HaloCurve>(
+pub fn batch_opening_proof<C: HaloCurve, T: Transcript<C::BaseField>, R: Rng>(
     polynomials_coeffs: &[&[C::ScalarField]],
     commitments: &[PolynomialCommitment<C>],
     opening_points: &[C::ScalarField],
@@ -24,15 +28,16 @@ pub fn batch_opening_proof<C: HaloCurve>(
     v: C::ScalarField,
     u_scaling: C::ScalarField,
     degree: usize,
-    security_bits: usize,
-    challenger: &mut Challenger<C::BaseField>,
+    challenge_len: usize,
+    challenger: &mut T,
+    rng: &mut R,
 ) -> Result<OpeningProof<C>> {
     // Normally we would reduce these lists using powers of u, but for the sake of efficiency
     // (particularly in the recursive verifier) we instead use n(u^i) for each u^i, where n is
     // the injective function related to the Halo endomorphism. Here we compute n(u^i).
     let actual_scalars: Vec<C::ScalarField> = powers(u, polynomials_coeffs.len())
         .iter()
-        .map(|u_power| halo_n::<C>(&u_power.to_canonical_bool_vec()[..security_bits]))
+        .map(|u_power| halo_n::<C>(&u_power.to_canonical_bool_vec()[..challenge_len]))
         .collect();
 
     // Reduce the coefficient list to a single set of polynomial coefficients.
@@ -44,7 +49,7 @@ pub fn batch_opening_proof<C: HaloCurve>(
     }
 
     let u_prime =
-        halo_n_mul(&u_scaling.to_canonical_bool_vec()[..security_bits], u_curve).to_projective();
+        halo_n_mul(&u_scaling.to_canonical_bool_vec()[..challenge_len], u_curve).to_projective();
 
     // Make a list of all polynomials' commitment randomness and coefficients, to be reduced later.
     // This must match the order of OpeningSet::to_vec.
@@ -80,8 +85,8 @@ pub fn batch_opening_proof<C: HaloCurve>(
         // We may need to re-generate L_i/R_i a few times with different blinding factors until
         // we get a challenge r such that n(r) is square.
         let u_j = loop {
-            let l_j_blinding_factor = C::ScalarField::rand();
-            let r_j_blinding_factor = C::ScalarField::rand();
+            let l_j_blinding_factor = C::ScalarField::rand_from_rng(rng);
+            let r_j_blinding_factor = C::ScalarField::rand_from_rng(rng);
 
             // L_i = <a_lo, G_hi> + [l_j] H + [<a_lo, b_hi>] U.
             let halo_l_j = msm_parallel(a_lo, g_hi, window_size)
@@ -96,7 +101,7 @@ pub fn batch_opening_proof<C: HaloCurve>(
             challenger_fork.observe_proj_points(&[halo_l_j, halo_r_j]);
             let r_bf = challenger_fork.get_challenge();
             let r_sf = r_bf.try_convert::<C::ScalarField>()?;
-            let r_bits = &r_sf.to_canonical_bool_vec()[..security_bits];
+            let r_bits = &r_sf.to_canonical_bool_vec()[..challenge_len];
             let u_j_squared = halo_n::<C>(r_bits);
 
             if let Some(u_j) = u_j_squared.square_root() {
@@ -116,11 +121,15 @@ pub fn batch_opening_proof<C: HaloCurve>(
 
         halo_a = C::ScalarField::add_slices(&u_j_inv.scale_slice(a_hi), &u_j.scale_slice(a_lo));
         halo_b = C::ScalarField::add_slices(&u_j_inv.scale_slice(b_lo), &u_j.scale_slice(b_hi));
-        halo_g = g_lo
-            .into_par_iter()
-            .zip(g_hi)
-            .map(|(&g_lo_i, &g_hi_i)| msm_parallel(&[u_j_inv, u_j], &[g_lo_i, g_hi_i], 4))
-            .collect();
+        let fold = |(&g_lo_i, &g_hi_i)| msm_parallel(&[u_j_inv, u_j], &[g_lo_i, g_hi_i], 4);
+        #[cfg(feature = "parallel")]
+        {
+            halo_g = g_lo.into_par_iter().zip(g_hi).map(fold).collect();
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            halo_g = g_lo.iter().zip(g_hi).map(fold).collect();
+        }
     }
 
     debug_assert_eq!(halo_g.len(), 1);
@@ -129,7 +138,15 @@ pub fn batch_opening_proof<C: HaloCurve>(
     debug_assert_eq!(halo_a.len(), 1);
     debug_assert_eq!(halo_b.len(), 1);
     let schnorr_proof = schnorr_protocol(
-        halo_a[0], halo_b[0], halo_g, randomness, u_prime, pedersen_h, challenger,
+        halo_a[0],
+        halo_b[0],
+        halo_g,
+        randomness,
+        u_prime,
+        pedersen_h,
+        challenge_len,
+        challenger,
+        rng,
     );
 
     Ok(OpeningProof {
@@ -154,24 +171,30 @@ fn build_halo_b<C: Curve>(
         .collect()
 }
 
-fn schnorr_protocol<C: HaloCurve>(
+#[allow(clippy::too_many_arguments)]
+fn schnorr_protocol<C: HaloCurve, T: Transcript<C::BaseField>, R: Rng>(
     halo_a: C::ScalarField,
     halo_b: C::ScalarField,
     halo_g: AffinePoint<C>,
     randomness: C::ScalarField,
     u_curve: ProjectivePoint<C>,
     pedersen_h: ProjectivePoint<C>,
-    challenger: &mut Challenger<C::BaseField>,
+    challenge_len: usize,
+    challenger: &mut T,
+    rng: &mut R,
 ) -> SchnorrProof<C> {
-    let (d, s) = (C::ScalarField::rand(), C::ScalarField::rand());
+    let (d, s) = (C::ScalarField::rand_from_rng(rng), C::ScalarField::rand_from_rng(rng));
     let r_curve = C::convert(d) * (halo_g.to_projective() + C::convert(halo_b) * u_curve)
         + C::convert(s) * pedersen_h;
 
     challenger.observe_proj_point(r_curve);
     let chall_bf = challenger.get_challenge();
-    let chall = chall_bf
-        .try_convert::<C::ScalarField>()
-        .expect("Improbable");
+    let chall = schnorr_scalar::<C>(
+        chall_bf
+            .try_convert::<C::ScalarField>()
+            .expect("Improbable"),
+        challenge_len,
+    );
     let z1 = halo_a * chall + d;
     let z2 = randomness * chall + s;
     SchnorrProof {
@@ -181,6 +204,16 @@ fn schnorr_protocol<C: HaloCurve>(
     }
 }
 
+/// The scalar `e` by which the Schnorr protocol scales `Q`, given the Schnorr challenge `c`. As
+/// with `u'`, we use `e = n(c)`, with `c` truncated to `challenge_len` bits, so that the recursive
+/// verifier can compute `[e] Q` with the endomorphism.
+fn schnorr_scalar<C: HaloCurve>(
+    schnorr_challenge: C::ScalarField,
+    challenge_len: usize,
+) -> C::ScalarField {
+    halo_n::<C>(&schnorr_challenge.to_canonical_bool_vec()[..challenge_len])
+}
+
 /// Verify the final IPA.
 #[allow(clippy::too_many_arguments)]
 pub fn verify_ipa<C: HaloCurve>(
@@ -195,29 +228,72 @@ pub fn verify_ipa<C: HaloCurve>(
     pedersen_h: AffinePoint<C>,
     schnorr_challenge: C::ScalarField,
     schnorr_proof: SchnorrProof<C>,
+    challenge_len: usize,
 ) -> bool {
-    // Now we begin IPA verification by computing P' and u' as in Protocol 1 of Bulletproofs.
-    // In Protocol 1 we compute u' = [x] u, but we leverage to endomorphism, instead computing
-    // u' = [n(x)] u.
-
-    // Compute [c] [n(x)] u = [c] u'.
-    let u_n_x_c = C::convert(value) * u_prime;
-    let p_prime = commitment + u_n_x_c;
-
-    // Compute Q as defined in the Halo paper.
-    let mut points = halo_l.to_vec();
-    points.extend(halo_r.iter());
-    let mut scalars = halo_us.iter().map(|u| u.square()).collect::<Vec<_>>();
-    scalars.extend(
-        halo_us
-            .iter()
-            .map(|chal| chal.multiplicative_inverse_assuming_nonzero().square()),
+    let (scalars, points) = ipa_msm_terms(
+        halo_l,
+        halo_r,
+        halo_g,
+        commitment,
+        value,
+        halo_b,
+        halo_us,
+        u_prime,
+        pedersen_h,
+        schnorr_challenge,
+        schnorr_proof,
+        challenge_len,
     );
-    let precomputation = msm_precompute(&AffinePoint::batch_to_projective(&points), 8);
-    let q = msm_execute_parallel(&precomputation, &scalars) + p_prime;
+    msm_parallel(&scalars, &points, 8).to_affine().zero
+}
+
+/// Returns scalars and points whose MSM is zero if and only if `verify_ipa` would accept. This
+/// lets verifiers check many IPAs at once, by taking a random combination of their terms.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn ipa_msm_terms<C: HaloCurve>(
+    halo_l: &[AffinePoint<C>],
+    halo_r: &[AffinePoint<C>],
+    halo_g: AffinePoint<C>,
+    commitment: ProjectivePoint<C>,
+    value: C::ScalarField,
+    halo_b: C::ScalarField,
+    halo_us: &[C::ScalarField],
+    u_prime: ProjectivePoint<C>,
+    pedersen_h: AffinePoint<C>,
+    schnorr_challenge: C::ScalarField,
+    schnorr_proof: SchnorrProof<C>,
+    challenge_len: usize,
+) -> (Vec<C::ScalarField>, Vec<ProjectivePoint<C>>) {
+    // We compute P' and u' as in Protocol 1 of Bulletproofs, then Q as defined in the Halo paper:
+    //     P' = P + [c] u',
+    //     Q = sum_j ([u_j^2] L_j + [u_j^-2] R_j) + P'.
+    // In Protocol 1 we compute u' = [x] u, but we leverage to endomorphism, instead computing
+    // u' = [n(x)] u. The ZK opening protocol then checks that
+    //     [e] Q + R = [z1] (G + [b] u') + [z2] H,
+    // where e = n(c) for the Schnorr challenge c. We move everything to the left side.
+    let schnorr_challenge = schnorr_scalar::<C>(schnorr_challenge, challenge_len);
+    let mut scalars = vec![
+        schnorr_challenge,
+        schnorr_challenge * value - schnorr_proof.z1 * halo_b,
+        C::ScalarField::ONE,
+        -schnorr_proof.z1,
+        -schnorr_proof.z2,
+    ];
+    let mut points = vec![
+        commitment,
+        u_prime,
+        schnorr_proof.r.to_projective(),
+        halo_g.to_projective(),
+        pedersen_h.to_projective(),
+    ];
+
+    let us_inv = C::ScalarField::batch_multiplicative_inverse(halo_us);
+    for ((l, r), (&u, &u_inv)) in halo_l.iter().zip(halo_r).zip(halo_us.iter().zip(&us_inv)) {
+        scalars.push(schnorr_challenge * u.square());
+        points.push(l.to_projective());
+        scalars.push(schnorr_challenge * u_inv.square());
+        points.push(r.to_projective());
+    }
 
-    // Performing ZK opening protocol.
-    C::convert(schnorr_challenge) * q + schnorr_proof.r
-        == C::convert(schnorr_proof.z1) * (halo_g.to_projective() + C::convert(halo_b) * u_prime)
-            + C::convert(schnorr_proof.z2) * pedersen_h.to_projective()
+    (scalars, points)
 }
@@ -0,0 +1,231 @@
+use anyhow::{ensure, Result};
+
+use crate::poseidon::{generate_poseidon_constants, POSEIDON_FULL_ROUNDS, POSEIDON_PARTIAL_ROUNDS};
+use crate::rescue::generate_rescue_constants;
+use crate::{mds_matrix, Field};
+
+/// The smallest permutation width for which we derive parameters.
+pub const MIN_PERMUTATION_WIDTH: usize = 2;
+/// The largest permutation width for which we derive parameters.
+pub const MAX_PERMUTATION_WIDTH: usize = 12;
+
+/// The parameters of a Rescue permutation of a given width. These are the parameters used by
+/// `rescue_permutation`, and in-circuit by the Rescue gates.
+#[derive(Clone, Debug)]
+pub struct RescueParams<F: Field> {
+    pub width: usize,
+    pub mds: Vec<Vec<F>>,
+    /// The constants added after step A and step B of each round.
+    pub round_constants: Vec<(Vec<F>, Vec<F>)>,
+}
+
+/// The parameters of a Poseidon permutation of a given width. These are the parameters used by
+/// `poseidon_permutation`.
+#[derive(Clone, Debug)]
+pub struct PoseidonParams<F: Field> {
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
+    pub mds: Vec<Vec<F>>,
+    /// The constants added at the start of each round.
+    pub round_constants: Vec<Vec<F>>,
+}
+
+/// Derives the Rescue parameters for the given width, after checking that our number of rounds
+/// meets the bound from the Rescue paper, plus its recommended security margin.
+pub fn rescue_params<F: Field>(width: usize, security_bits: usize) -> Result<RescueParams<F>> {
+    check_width(width)?;
+    let round_constants = generate_rescue_constants::<F>(width, security_bits);
+    let alpha = F::ALPHA.to_canonical_u64_vec()[0];
+    let min_rounds = rescue_min_rounds(width, security_bits, alpha, F::BITS);
+    ensure!(
+        round_constants.len() >= min_rounds,
+        "Rescue with width {} needs {} rounds, but we use {}",
+        width,
+        min_rounds,
+        round_constants.len()
+    );
+    Ok(RescueParams {
+        width,
+        mds: mds_matrix(width).to_rows(),
+        round_constants,
+    })
+}
+
+/// Derives the Poseidon parameters for the given width, after checking that our round numbers
+/// meet the bounds from the Poseidon paper, plus its recommended security margin.
+pub fn poseidon_params<F: Field>(width: usize, security_bits: usize) -> Result<PoseidonParams<F>> {
+    check_width(width)?;
+    ensure!(
+        security_bits <= 128,
+        "Our Poseidon parameters target at most 128 bits of security"
+    );
+    let alpha = F::ALPHA.to_canonical_u64_vec()[0];
+    let (min_full_rounds, min_partial_rounds) =
+        poseidon_min_rounds(width, security_bits, alpha, F::BITS);
+    ensure!(
+        POSEIDON_FULL_ROUNDS >= min_full_rounds && POSEIDON_PARTIAL_ROUNDS >= min_partial_rounds,
+        "Poseidon with width {} needs {} full and {} partial rounds, but we use {} and {}",
+        width,
+        min_full_rounds,
+        min_partial_rounds,
+        POSEIDON_FULL_ROUNDS,
+        POSEIDON_PARTIAL_ROUNDS
+    );
+    Ok(PoseidonParams {
+        width,
+        full_rounds: POSEIDON_FULL_ROUNDS,
+        partial_rounds: POSEIDON_PARTIAL_ROUNDS,
+        mds: mds_matrix(width).to_rows(),
+        round_constants: generate_poseidon_constants(width),
+    })
+}
+
+/// The minimum number of Rescue rounds, including the security margin recommended by the Rescue
+/// paper: twice the number of rounds needed to resist differential attacks, and at least 10.
+fn rescue_min_rounds(width: usize, security_bits: usize, alpha: u64, field_bits: usize) -> usize {
+    let bits_per_round = (width + 1) as f64 * (field_bits as f64 - ((alpha - 1) as f64).log2());
+    let statistical_rounds = (2.0 * security_bits as f64 / bits_per_round).ceil() as usize;
+    (2 * statistical_rounds).max(10)
+}
+
+/// The minimum numbers of full and partial Poseidon rounds, including the security margin
+/// recommended by the Poseidon paper: two extra full rounds, and 7.5% more partial rounds.
+fn poseidon_min_rounds(
+    width: usize,
+    security_bits: usize,
+    alpha: u64,
+    field_bits: usize,
+) -> (usize, usize) {
+    let m = security_bits as f64;
+    let n = field_bits as f64;
+    let t = width as f64;
+    let log_alpha = |x: f64| x.log2() / (alpha as f64).log2();
+
+    // Statistical attacks need at least 6 full rounds.
+    let full_rounds = 6;
+    // Interpolation and Groebner basis attacks bound the total number of rounds.
+    let total_rounds_bound = [
+        log_alpha(2.0) * m.min(n) + log_alpha(t),
+        log_alpha(2.0) * (m / 3.0).min(n / 2.0),
+        t - 1.0 + log_alpha(2.0) * (m / (t + 1.0)).min(n / 2.0),
+    ]
+    .iter()
+    .cloned()
+    .fold(0.0, f64::max);
+    // The bounds are strict inequalities.
+    let partial_rounds = (total_rounds_bound.floor() as usize + 1).saturating_sub(full_rounds);
+
+    (
+        full_rounds + 2,
+        (partial_rounds as f64 * 1.075).ceil() as usize,
+    )
+}
+
+fn check_width(width: usize) -> Result<()> {
+    ensure!(
+        (MIN_PERMUTATION_WIDTH..=MAX_PERMUTATION_WIDTH).contains(&width),
+        "Width {} is outside the supported range of {} to {}",
+        width,
+        MIN_PERMUTATION_WIDTH,
+        MAX_PERMUTATION_WIDTH
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash_params::{poseidon_min_rounds, rescue_min_rounds};
+    use crate::{poseidon_params, poseidon_permutation, rescue_params, rescue_permutation, Field, Sponge, TweedledumBase, MAX_PERMUTATION_WIDTH, MIN_PERMUTATION_WIDTH};
+
+    type F = TweedledumBase;
+
+    #[test]
+    fn test_supported_widths() {
+        for width in MIN_PERMUTATION_WIDTH..=MAX_PERMUTATION_WIDTH {
+            let rescue = rescue_params::<F>(width, 128).unwrap();
+            assert_eq!(rescue.mds.len(), width);
+            assert!(rescue
+                .round_constants
+                .iter()
+                .all(|(a, b)| a.len() == width && b.len() == width));
+
+            let poseidon = poseidon_params::<F>(width, 128).unwrap();
+            assert_eq!(
+                poseidon.round_constants.len(),
+                poseidon.full_rounds + poseidon.partial_rounds
+            );
+        }
+
+        assert!(rescue_params::<F>(1, 128).is_err());
+        assert!(poseidon_params::<F>(MAX_PERMUTATION_WIDTH + 1, 128).is_err());
+        assert!(poseidon_params::<F>(4, 256).is_err());
+    }
+
+    #[test]
+    fn test_poseidon_min_rounds() {
+        // With alpha = 5 and a 255-bit field, the interpolation bound dominates.
+        let (full_rounds, partial_rounds) = poseidon_min_rounds(3, 128, 5, 255);
+        assert_eq!(full_rounds, 8);
+        assert_eq!(partial_rounds, 54);
+    }
+
+    #[test]
+    fn test_rescue_min_rounds() {
+        assert_eq!(rescue_min_rounds(4, 128, 5, 255), 10);
+        // A tiny field needs many more rounds.
+        assert_eq!(rescue_min_rounds(2, 128, 5, 10), 2 * 11);
+    }
+
+    /// Tests that the derived parameters are the ones our permutations use.
+    #[test]
+    fn test_params_match_permutations() {
+        let width = 8;
+        let state = (0..width).map(|_| F::rand()).collect::<Vec<_>>();
+
+        let params = poseidon_params::<F>(width, 128).unwrap();
+        let mut expected = state.clone();
+        for (r, round_constants) in params.round_constants.iter().enumerate() {
+            for (x, &c) in expected.iter_mut().zip(round_constants) {
+                *x = *x + c;
+            }
+            let half_full_rounds = params.full_rounds / 2;
+            for (i, x) in expected.iter_mut().enumerate() {
+                if i == 0 || r < half_full_rounds || r >= half_full_rounds + params.partial_rounds {
+                    *x = x.exp(F::ALPHA);
+                }
+            }
+            expected = mat_vec(&params.mds, &expected);
+        }
+        assert_eq!(poseidon_permutation(&state, 128), expected);
+
+        let params = rescue_params::<F>(width, 128).unwrap();
+        let mut expected = state.clone();
+        for (a_constants, b_constants) in &params.round_constants {
+            expected = expected.iter().map(|x| x.kth_root(F::ALPHA)).collect();
+            expected = add(&mat_vec(&params.mds, &expected), a_constants);
+            expected = expected.iter().map(|x| x.exp(F::ALPHA)).collect();
+            expected = add(&mat_vec(&params.mds, &expected), b_constants);
+        }
+        assert_eq!(rescue_permutation(&state, 128), expected);
+    }
+
+    #[test]
+    fn test_wide_sponge() {
+        let width = MAX_PERMUTATION_WIDTH;
+        let mut sponge = Sponge::new(width - 2, 2, 128, poseidon_permutation);
+        sponge.absorb_all(&[F::ONE; 25]);
+        assert_eq!(sponge.squeeze_n(15).len(), 15);
+    }
+
+    fn mat_vec(matrix: &[Vec<F>], v: &[F]) -> Vec<F> {
+        matrix
+            .iter()
+            .map(|row| row.iter().zip(v).fold(F::ZERO, |acc, (&m, &x)| acc + m * x))
+            .collect()
+    }
+
+    fn add(a: &[F], b: &[F]) -> Vec<F> {
+        a.iter().zip(b).map(|(&x, &y)| x + y).collect()
+    }
+}
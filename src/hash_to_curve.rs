@@ -1,5 +1,6 @@
-use crate::{rescue_sponge, AffinePoint, Curve, Field};
-    
+use crate::sponge::{HASH_BYTES_TO_CURVE_DOMAIN_TAG, HASH_TO_CURVE_DOMAIN_TAG};
+use crate::{bytes_to_fields, AffinePoint, AlgebraicHasher, Curve, Field, PoseidonHasher, RescueHasher, Sponge};
+
 pub fn hash_u32_to_curve<C: Curve>(seed: u32, security_bits: usize) -> AffinePoint<C> {
     let seed_f = C::BaseField::from_canonical_u32(seed);
     hash_base_field_to_curve(seed_f, security_bits)
@@ -10,6 +11,11 @@ pub fn hash_usize_to_curve<C: Curve>(seed: usize, security_bits: usize) -> Affin
     hash_base_field_to_curve(seed_f, security_bits)
 }
 
+pub fn poseidon_hash_usize_to_curve<C: Curve>(seed: usize, security_bits: usize) -> AffinePoint<C> {
+    let seed_f = C::BaseField::from_canonical_usize(seed);
+    poseidon_hash_base_field_to_curve(seed_f, security_bits)
+}
+
 pub fn blake_field<F: Field>(iter: u8, seed: F) -> (F, bool) {
     let mut hasher = blake3::Hasher::new();
     // Number of bytes required to make a field element.
@@ -68,24 +74,64 @@ pub fn blake_hash_base_field_to_curve<C: Curve>(seed: C::BaseField) -> AffinePoi
             if y_neg {
                 y = -y;
             }
-            return AffinePoint::nonzero(x, y);
+            return AffinePoint::nonzero(x, y).clear_cofactor();
         }
 
         i += 1;
     }
 }
 
-// TODO: This is rather slow! Should use ChaCha20 or something instead of Rescue.
+// TODO: This is rather slow! Consider `poseidon_hash_base_field_to_curve` instead.
 pub fn hash_base_field_to_curve<C: Curve>(
     seed: C::BaseField,
     security_bits: usize,
 ) -> AffinePoint<C> {
+    hash_base_field_to_curve_with_hasher::<C, RescueHasher>(seed, security_bits)
+}
+
+/// Like `hash_base_field_to_curve`, but using a Poseidon sponge, which is much faster to evaluate.
+pub fn poseidon_hash_base_field_to_curve<C: Curve>(
+    seed: C::BaseField,
+    security_bits: usize,
+) -> AffinePoint<C> {
+    hash_base_field_to_curve_with_hasher::<C, PoseidonHasher>(seed, security_bits)
+}
+
+/// Like `hash_base_field_to_curve`, but using a sponge over the given hasher.
+pub fn hash_base_field_to_curve_with_hasher<C: Curve, H: AlgebraicHasher<C::BaseField>>(
+    seed: C::BaseField,
+    security_bits: usize,
+) -> AffinePoint<C> {
+    let mut sponge =
+        Sponge::new_with_hasher::<H>(security_bits).with_domain_tag(HASH_TO_CURVE_DOMAIN_TAG);
+    sponge.absorb(seed);
+    hash_absorbed_to_curve(sponge)
+}
+
+/// Hashes a domain separation tag and a message of arbitrary length to a curve point. Both are
+/// encoded with `bytes_to_fields`, which binds their lengths, and absorbed into a Rescue sponge
+/// before searching for a point as in `hash_base_field_to_curve`.
+pub fn hash_bytes_to_curve<C: Curve>(
+    domain_tag: &[u8],
+    msg: &[u8],
+    security_bits: usize,
+) -> AffinePoint<C> {
+    let mut sponge =
+        Sponge::new_rescue(security_bits).with_domain_tag(HASH_BYTES_TO_CURVE_DOMAIN_TAG);
+    sponge.absorb_all(&bytes_to_fields(domain_tag));
+    sponge.absorb_all(&bytes_to_fields(msg));
+    hash_absorbed_to_curve(sponge)
+}
+
+/// Derives a curve point from a sponge which has absorbed the data being hashed.
+fn hash_absorbed_to_curve<C: Curve>(sponge: Sponge<C::BaseField>) -> AffinePoint<C> {
     // Based on the MapToGroup method of BLS.
     let mut i = 0;
     loop {
-        // Let (x, y_neg) = H(seed, i).
-        let inputs = vec![seed, C::BaseField::from_canonical_u32(i)];
-        let outputs = rescue_sponge(inputs, 2, security_bits);
+        // Let (x, y_neg) = H(data, i).
+        let mut sponge = sponge.clone();
+        sponge.absorb(C::BaseField::from_canonical_u32(i));
+        let outputs = sponge.squeeze_n(2);
         let x = outputs[0];
         let y_neg = outputs[1].to_canonical_bool_vec()[0];
 
@@ -96,7 +142,7 @@ pub fn hash_base_field_to_curve<C: Curve>(
             if y_neg {
                 y = -y;
             }
-            return AffinePoint::nonzero(x, y);
+            return AffinePoint::nonzero(x, y).clear_cofactor();
         }
 
         i += 1;
@@ -129,6 +175,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_poseidon_hash_u32_to_point() {
+        for i in 0..5 {
+            let x = poseidon_hash_usize_to_curve::<Tweedledum>(i, 128);
+            assert!(x.is_valid());
+            assert_eq!(x, poseidon_hash_usize_to_curve::<Tweedledum>(i, 128));
+            assert_ne!(x, hash_usize_to_curve::<Tweedledum>(i, 128));
+        }
+    }
+
+    #[test]
+    fn test_hash_bytes_to_curve() {
+        let p = hash_bytes_to_curve::<Tweedledum>(b"tag", b"message", 128);
+        assert!(p.is_valid());
+        assert_eq!(p, hash_bytes_to_curve::<Tweedledum>(b"tag", b"message", 128));
+
+        // Changing the tag, the message, or where one ends and the other begins changes the point.
+        assert_ne!(p, hash_bytes_to_curve::<Tweedledum>(b"other tag", b"message", 128));
+        assert_ne!(p, hash_bytes_to_curve::<Tweedledum>(b"tag", b"message\0", 128));
+        assert_ne!(p, hash_bytes_to_curve::<Tweedledum>(b"tagm", b"essage", 128));
+
+        let long_msg = vec![7u8; 1000];
+        assert!(hash_bytes_to_curve::<Tweedledum>(b"tag", &long_msg, 128).is_valid());
+    }
+
     #[test]
     fn test_hash_blake_deterministic() {
         let n = 10000;
@@ -0,0 +1,218 @@
+use crate::gmimc::generate_gmimc_constants;
+use crate::poseidon::{generate_poseidon_constants, is_full_poseidon_round};
+use crate::{gmimc_permutation, gmimc_permutation_batch, mds_matrix, poseidon_permutation, poseidon_permutation_batch, rescue_permutation, rescue_permutation_batch, CircuitBuilder, Field, HaloCurve, Target, RESCUE_SPONGE_RATE, RESCUE_SPONGE_WIDTH};
+
+/// An algebraic permutation, along with the sponge parameters we use it with, which can be
+/// evaluated both natively and in-circuit. The sponges, transcripts, Merkle trees and hashes to
+/// curves which are generic over the hasher thus compute the same values natively and in-circuit.
+pub trait AlgebraicHasher<F: Field> {
+    /// A name for the hasher, which identifies the transcript hash of serialized proofs.
+    const NAME: &'static str;
+
+    /// The number of elements in the permutation's state.
+    const WIDTH: usize;
+
+    /// The number of state elements which absorb inputs and from which outputs are squeezed. The
+    /// remaining elements form the capacity.
+    const RATE: usize;
+
+    fn permute(state: &[F], security_bits: usize) -> Vec<F>;
+
+    /// Applies the permutation to each of the given states. Hashers can override this to permute
+    /// many states more efficiently than one at a time.
+    fn permute_many(states: &[Vec<F>], security_bits: usize) -> Vec<Vec<F>> {
+        states
+            .iter()
+            .map(|state| Self::permute(state, security_bits))
+            .collect()
+    }
+
+    /// Applies the permutation in-circuit, consistent with `permute` when the builder's security
+    /// level is the same.
+    fn permute_recursively<C: HaloCurve<ScalarField = F>>(
+        builder: &mut CircuitBuilder<C>,
+        state: &[Target<F>],
+    ) -> Vec<Target<F>>;
+}
+
+/// Rescue, which has dedicated gates, making it the cheapest hasher in-circuit.
+#[derive(Copy, Clone, Debug)]
+pub struct RescueHasher;
+
+impl<F: Field> AlgebraicHasher<F> for RescueHasher {
+    const NAME: &'static str = "Rescue";
+    const WIDTH: usize = RESCUE_SPONGE_WIDTH;
+    const RATE: usize = RESCUE_SPONGE_RATE;
+
+    fn permute(state: &[F], security_bits: usize) -> Vec<F> {
+        rescue_permutation(state, security_bits)
+    }
+
+    fn permute_many(states: &[Vec<F>], security_bits: usize) -> Vec<Vec<F>> {
+        rescue_permutation_batch(states, security_bits)
+    }
+
+    fn permute_recursively<C: HaloCurve<ScalarField = F>>(
+        builder: &mut CircuitBuilder<C>,
+        state: &[Target<F>],
+    ) -> Vec<Target<F>> {
+        builder.rescue_permutation(state)
+    }
+}
+
+/// Poseidon, which is much cheaper than Rescue natively. In-circuit, it is evaluated with
+/// arithmetic gates.
+#[derive(Copy, Clone, Debug)]
+pub struct PoseidonHasher;
+
+impl<F: Field> AlgebraicHasher<F> for PoseidonHasher {
+    const NAME: &'static str = "Poseidon";
+    const WIDTH: usize = 4;
+    const RATE: usize = 3;
+
+    fn permute(state: &[F], security_bits: usize) -> Vec<F> {
+        poseidon_permutation(state, security_bits)
+    }
+
+    fn permute_many(states: &[Vec<F>], security_bits: usize) -> Vec<Vec<F>> {
+        poseidon_permutation_batch(states, security_bits)
+    }
+
+    fn permute_recursively<C: HaloCurve<ScalarField = F>>(
+        builder: &mut CircuitBuilder<C>,
+        state: &[Target<F>],
+    ) -> Vec<Target<F>> {
+        assert!(
+            builder.security_bits <= 128,
+            "Our Poseidon parameters target at most 128 bits of security"
+        );
+        let mut state = state.to_vec();
+        let constants = generate_poseidon_constants::<F>(state.len());
+        for (r, round_constants) in constants.into_iter().enumerate() {
+            for (x, c) in state.iter_mut().zip(round_constants) {
+                let c = builder.constant_wire(c);
+                *x = builder.add(*x, c);
+            }
+
+            if is_full_poseidon_round(r) {
+                for x in state.iter_mut() {
+                    *x = builder.exp_constant(*x, F::ALPHA);
+                }
+            } else {
+                state[0] = builder.exp_constant(state[0], F::ALPHA);
+            }
+
+            state = apply_mds_recursively(builder, &state);
+        }
+        state
+    }
+}
+
+/// GMiMC, which needs only one S-box per round. It is experimental; see `GMIMC_ROUNDS`.
+#[derive(Copy, Clone, Debug)]
+pub struct GmimcHasher;
+
+impl<F: Field> AlgebraicHasher<F> for GmimcHasher {
+    const NAME: &'static str = "GMiMC";
+    const WIDTH: usize = 4;
+    const RATE: usize = 3;
+
+    fn permute(state: &[F], security_bits: usize) -> Vec<F> {
+        gmimc_permutation(state, security_bits)
+    }
+
+    fn permute_many(states: &[Vec<F>], security_bits: usize) -> Vec<Vec<F>> {
+        gmimc_permutation_batch(states, security_bits)
+    }
+
+    fn permute_recursively<C: HaloCurve<ScalarField = F>>(
+        builder: &mut CircuitBuilder<C>,
+        state: &[Target<F>],
+    ) -> Vec<Target<F>> {
+        assert!(
+            builder.security_bits <= 128,
+            "Our GMiMC parameters target at most 128 bits of security"
+        );
+        let mut state = state.to_vec();
+        for c in generate_gmimc_constants::<F>() {
+            let c = builder.constant_wire(c);
+            let sbox_input = builder.add(state[0], c);
+            let f = builder.exp_constant(sbox_input, F::ALPHA);
+            for x in state.iter_mut().skip(1) {
+                *x = builder.add(*x, f);
+            }
+            state.rotate_left(1);
+        }
+        state
+    }
+}
+
+/// An in-circuit version of `apply_mds`.
+fn apply_mds_recursively<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    state: &[Target<C::ScalarField>],
+) -> Vec<Target<C::ScalarField>> {
+    let n = state.len();
+    let mds = mds_matrix::<C::ScalarField>(n);
+    (0..n)
+        .map(|r| {
+            let mut sum = builder.zero_wire();
+            for (c, &x) in state.iter().enumerate() {
+                let m = builder.constant_wire(mds.get(r, c));
+                sum = builder.mul_add(m, x, sum);
+            }
+            sum
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AlgebraicHasher, CircuitBuilder, Curve, Field, GmimcHasher, PartialWitness, PoseidonHasher, RescueHasher, Tweedledee, PERMUTATION_LANES};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    fn check_consistency<H: AlgebraicHasher<F>>() {
+        let state = (0..H::WIDTH).map(|_| F::rand()).collect::<Vec<_>>();
+        let expected = H::permute(&state, 128);
+
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let state_targets = builder.constant_wires(&state);
+        let outputs = H::permute_recursively(&mut builder, &state_targets);
+        let circuit = builder.build();
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
+
+        assert_eq!(witness.get_targets(&outputs), expected);
+    }
+
+    /// Checks `permute_many` against `permute`, with enough states to leave some lanes unused.
+    fn check_permute_many<H: AlgebraicHasher<F>>() {
+        let states = (0..PERMUTATION_LANES + 3)
+            .map(|_| (0..H::WIDTH).map(|_| F::rand()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let expected = states
+            .iter()
+            .map(|state| H::permute(state, 128))
+            .collect::<Vec<_>>();
+        assert_eq!(H::permute_many(&states, 128), expected);
+        assert!(H::permute_many(&[], 128).is_empty());
+    }
+
+    #[test]
+    fn test_rescue_consistency() {
+        check_consistency::<RescueHasher>();
+        check_permute_many::<RescueHasher>();
+    }
+
+    #[test]
+    fn test_poseidon_consistency() {
+        check_consistency::<PoseidonHasher>();
+        check_permute_many::<PoseidonHasher>();
+    }
+
+    #[test]
+    fn test_gmimc_consistency() {
+        check_consistency::<GmimcHasher>();
+        check_permute_many::<GmimcHasher>();
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt::Debug;
+
+use anyhow::{ensure, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::commitment_scheme::{evaluate_at_points, observe_openings};
+use crate::plonk_challenger::Transcript;
+use crate::plonk_util::{pedersen_hash, powers};
+use crate::{msm_precompute, AffinePoint, CommitmentScheme, Curve, Field, MsmPrecomputation, ProjectivePoint};
+
+/// A pairing-friendly curve, with `G1` as its first source group.
+///
+/// We don't implement any pairings ourselves yet, so users of `KzgCommitmentScheme` need to
+/// provide G2 arithmetic and a pairing check, e.g. by wrapping an external pairing library.
+pub trait PairingEngine {
+    type G1: Curve;
+    /// A point in the second source group, in affine form.
+    type G2: Copy + Debug + Eq;
+
+    fn g2_generator() -> Self::G2;
+
+    fn g2_mul(point: Self::G2, scalar: <Self::G1 as Curve>::ScalarField) -> Self::G2;
+
+    /// Returns whether the product of `e(a_i, b_i)` over all pairs is the identity of the target
+    /// group.
+    fn pairing_product_is_one(pairs: &[(AffinePoint<Self::G1>, Self::G2)]) -> bool;
+}
+
+/// A structured reference string for KZG commitments, i.e. `[tau^i] G1` for each `i` below the
+/// maximum degree, along with `G2` and `[tau] G2`.
+pub struct KzgSrs<E: PairingEngine> {
+    pub powers_of_tau_g1: Vec<AffinePoint<E::G1>>,
+    pub g2: E::G2,
+    pub tau_g2: E::G2,
+}
+
+impl<E: PairingEngine> KzgSrs<E> {
+    /// Samples a fresh SRS. The secret `tau` is discarded afterward, but anyone observing this
+    /// call could forge proofs, so this is only suitable for tests. Real deployments should load
+    /// the output of a trusted setup ceremony instead.
+    pub fn insecure_setup<R: Rng>(max_degree: usize, rng: &mut R) -> Self {
+        let tau = <E::G1 as Curve>::ScalarField::rand_from_rng(rng);
+        let powers_of_tau_g1 = powers(tau, max_degree)
+            .into_iter()
+            .map(|t| <E::G1 as Curve>::convert(t) * <E::G1 as Curve>::GENERATOR_PROJECTIVE)
+            .collect::<Vec<_>>();
+        let g2 = E::g2_generator();
+        KzgSrs {
+            powers_of_tau_g1: ProjectivePoint::batch_to_affine(&powers_of_tau_g1),
+            g2,
+            tau_g2: E::g2_mul(g2, tau),
+        }
+    }
+}
+
+/// KZG polynomial commitments, which have constant-size opening proofs and a constant-time
+/// verifier, at the cost of a trusted setup and pairings. Since our curves are not pairing-friendly
+/// and pairings are expensive in-circuit, this is meant for final proofs verified natively or
+/// on-chain, while recursion should keep using `IpaCommitmentScheme`.
+///
+/// Commitments are not hiding.
+pub struct KzgCommitmentScheme<E: PairingEngine> {
+    pub srs: KzgSrs<E>,
+    msm_precomputation: MsmPrecomputation<E::G1>,
+}
+
+/// A batch KZG opening proof, containing one witness commitment per opening point.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KzgOpeningProof<C: Curve> {
+    pub witnesses: Vec<AffinePoint<C>>,
+}
+
+impl<E: PairingEngine> KzgCommitmentScheme<E> {
+    pub fn new(srs: KzgSrs<E>) -> Self {
+        let w = 8; // TODO: Should really be set dynamically based on MSM size.
+        let msm_precomputation =
+            msm_precompute(&AffinePoint::batch_to_projective(&srs.powers_of_tau_g1), w);
+        KzgCommitmentScheme {
+            srs,
+            msm_precomputation,
+        }
+    }
+
+    fn commit_unchecked(&self, coeffs: &[<E::G1 as Curve>::ScalarField]) -> AffinePoint<E::G1> {
+        let mut padded = coeffs.to_vec();
+        padded.resize(self.max_degree(), <E::G1 as Curve>::ScalarField::ZERO);
+        pedersen_hash(&padded, &self.msm_precomputation).to_affine()
+    }
+
+    /// Generates a challenge of 128 bits, which is enough for the random linear combinations we
+    /// use, and always fits in the scalar field even when it's smaller than the base field.
+    fn scalar_challenge<T: Transcript<<E::G1 as Curve>::BaseField>>(
+        transcript: &mut T,
+    ) -> <E::G1 as Curve>::ScalarField {
+        type SF<E> = <<E as PairingEngine>::G1 as Curve>::ScalarField;
+        let limbs = transcript.get_challenge().to_canonical_u64_vec();
+        let two_to_64 = SF::<E>::from_canonical_u64(1 << 32).square();
+        SF::<E>::from_canonical_u64(limbs[1]) * two_to_64 + SF::<E>::from_canonical_u64(limbs[0])
+    }
+}
+
+impl<E: PairingEngine> CommitmentScheme<E::G1> for KzgCommitmentScheme<E> {
+    type Commitment = AffinePoint<E::G1>;
+    type OpeningProof = KzgOpeningProof<E::G1>;
+
+    fn max_degree(&self) -> usize {
+        self.srs.powers_of_tau_g1.len()
+    }
+
+    fn commit<R: Rng>(
+        &self,
+        coeffs: &[<E::G1 as Curve>::ScalarField],
+        _rng: &mut R,
+    ) -> Self::Commitment {
+        assert!(coeffs.len() <= self.max_degree(), "Polynomial is too large to commit to");
+        self.commit_unchecked(coeffs)
+    }
+
+    fn batch_open<T: Transcript<<E::G1 as Curve>::BaseField>, R: Rng>(
+        &self,
+        polynomials: &[&[<E::G1 as Curve>::ScalarField]],
+        commitments: &[Self::Commitment],
+        points: &[<E::G1 as Curve>::ScalarField],
+        transcript: &mut T,
+        _rng: &mut R,
+    ) -> Result<Self::OpeningProof> {
+        ensure!(
+            polynomials.len() == commitments.len(),
+            "Expected one commitment per polynomial"
+        );
+        observe_commitments(transcript, commitments);
+        let values = evaluate_at_points(polynomials, points);
+        observe_openings::<E::G1, T>(transcript, points, &values)?;
+        let gamma = Self::scalar_challenge(transcript);
+
+        // For each point z, the witness is a commitment to sum_i gamma^i (f_i(X) - f_i(z)) / (X - z).
+        let gamma_powers = powers(gamma, polynomials.len());
+        let combined = combine_polynomials(polynomials, &gamma_powers);
+        let witnesses = points
+            .iter()
+            .map(|&z| self.commit_unchecked(&divide_by_linear(&combined, z)))
+            .collect();
+        Ok(KzgOpeningProof { witnesses })
+    }
+
+    fn batch_verify<T: Transcript<<E::G1 as Curve>::BaseField>>(
+        &self,
+        commitments: &[Self::Commitment],
+        points: &[<E::G1 as Curve>::ScalarField],
+        values: &[Vec<<E::G1 as Curve>::ScalarField>],
+        proof: &Self::OpeningProof,
+        transcript: &mut T,
+    ) -> Result<()> {
+        ensure!(values.len() == points.len(), "Expected values for each point");
+        ensure!(
+            proof.witnesses.len() == points.len(),
+            "Expected one witness per point"
+        );
+        ensure!(
+            values.iter().all(|vs| vs.len() == commitments.len()),
+            "Expected one value per commitment at each point"
+        );
+        observe_commitments(transcript, commitments);
+        observe_openings::<E::G1, T>(transcript, points, values)?;
+        let gamma = Self::scalar_challenge(transcript);
+        observe_commitments(transcript, &proof.witnesses);
+        let r = Self::scalar_challenge(transcript);
+
+        // For each point z with witness W and combined value y, we need
+        // e(C - [y] G1 + [z] W, G2) = e(W, [tau] G2), where C is the combined commitment. We check
+        // a random combination of these equations, weighted by powers of r.
+        let gamma_powers = powers(gamma, commitments.len());
+        let combined_commitment = commitments
+            .iter()
+            .zip(&gamma_powers)
+            .map(|(&c, &g)| <E::G1 as Curve>::convert(g) * c.to_projective())
+            .fold(ProjectivePoint::ZERO, |acc, p| acc + p);
+        let mut lhs = ProjectivePoint::<E::G1>::ZERO;
+        let mut rhs = ProjectivePoint::<E::G1>::ZERO;
+        for (((&z, vs), w), r_power) in points
+            .iter()
+            .zip(values)
+            .zip(&proof.witnesses)
+            .zip(powers(r, points.len()))
+        {
+            let y = <E::G1 as Curve>::ScalarField::inner_product(vs, &gamma_powers);
+            let w = w.to_projective();
+            let term = combined_commitment
+                + <E::G1 as Curve>::convert(-y) * <E::G1 as Curve>::GENERATOR_PROJECTIVE
+                + <E::G1 as Curve>::convert(z) * w;
+            lhs = lhs + <E::G1 as Curve>::convert(r_power) * term;
+            rhs = rhs + <E::G1 as Curve>::convert(r_power) * w;
+        }
+
+        let pairs = [
+            (lhs.to_affine(), self.srs.g2),
+            ((-rhs).to_affine(), self.srs.tau_g2),
+        ];
+        ensure!(E::pairing_product_is_one(&pairs), "Invalid KZG opening proof");
+        Ok(())
+    }
+}
+
+fn observe_commitments<C: Curve, T: Transcript<C::BaseField>>(
+    transcript: &mut T,
+    commitments: &[AffinePoint<C>],
+) {
+    // Commitments to zero polynomials are the zero point, encoded as (0, 0), which is not on the
+    // curve.
+    for c in commitments {
+        transcript.observe_elements(&[c.x, c.y]);
+    }
+}
+
+fn combine_polynomials<F: Field>(polynomials: &[&[F]], scalars: &[F]) -> Vec<F> {
+    let len = polynomials.iter().map(|p| p.len()).max().unwrap_or(0);
+    let mut combined = vec![F::ZERO; len];
+    for (p, &s) in polynomials.iter().zip(scalars) {
+        for (c, &x) in combined.iter_mut().zip(p.iter()) {
+            *c = *c + s * x;
+        }
+    }
+    combined
+}
+
+/// Computes `(f(X) - f(z)) / (X - z)` using synthetic division.
+fn divide_by_linear<F: Field>(coeffs: &[F], z: F) -> Vec<F> {
+    let mut quotient = vec![F::ZERO; coeffs.len().saturating_sub(1)];
+    let mut acc = F::ZERO;
+    for i in (1..coeffs.len()).rev() {
+        acc = acc * z + coeffs[i];
+        quotient[i - 1] = acc;
+    }
+    quotient
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Result;
+    use rand::thread_rng;
+
+    use crate::commitment_scheme::evaluate_at_points;
+    use crate::kzg::divide_by_linear;
+    use crate::plonk_challenger::Challenger;
+    use crate::{AffinePoint, Bls12377, Bls12377Scalar, CommitmentScheme, Curve, Field, KzgCommitmentScheme, KzgSrs, PairingEngine, Polynomial, ProjectivePoint};
+
+    /// A stand-in for a real pairing, for testing the KZG algebra. A G2 point `[x] G2` is
+    /// represented by `x`, and `e(A, [x] G2)` by `[x] A`, which is bilinear but offers no security
+    /// since "G2" points reveal their discrete logs.
+    struct MockEngine;
+
+    impl PairingEngine for MockEngine {
+        type G1 = Bls12377;
+        type G2 = Bls12377Scalar;
+
+        fn g2_generator() -> Self::G2 {
+            Bls12377Scalar::ONE
+        }
+
+        fn g2_mul(point: Self::G2, scalar: Bls12377Scalar) -> Self::G2 {
+            point * scalar
+        }
+
+        fn pairing_product_is_one(pairs: &[(AffinePoint<Bls12377>, Self::G2)]) -> bool {
+            pairs
+                .iter()
+                .map(|&(a, x)| Bls12377::convert(x) * a.to_projective())
+                .fold(ProjectivePoint::ZERO, |acc, p| acc + p)
+                .to_affine()
+                .zero
+        }
+    }
+
+    #[test]
+    fn test_divide_by_linear() {
+        type F = Bls12377Scalar;
+        let coeffs = (0..9).map(|_| F::rand()).collect::<Vec<_>>();
+        let z = F::rand();
+        let x = F::rand();
+        let f = Polynomial::from_coeffs(&coeffs);
+        let q = Polynomial::from(divide_by_linear(&coeffs, z));
+        assert_eq!(q.eval(x) * (x - z), f.eval(x) - f.eval(z));
+    }
+
+    #[test]
+    fn test_kzg_batch_opening() -> Result<()> {
+        type F = Bls12377Scalar;
+        let degree = 8;
+        let scheme =
+            KzgCommitmentScheme::new(KzgSrs::<MockEngine>::insecure_setup(degree, &mut thread_rng()));
+        let polynomials = (0..3)
+            .map(|_| (0..degree).map(|_| F::rand()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let polynomials = polynomials.iter().map(|p| &p[..]).collect::<Vec<_>>();
+        let commitments = polynomials
+            .iter()
+            .map(|p| scheme.commit(p, &mut thread_rng()))
+            .collect::<Vec<_>>();
+        let points = [F::rand(), F::rand(), F::rand()];
+
+        let proof = scheme.batch_open(
+            &polynomials,
+            &commitments,
+            &points,
+            &mut Challenger::new(128),
+            &mut thread_rng(),
+        )?;
+
+        let mut values = evaluate_at_points(&polynomials, &points);
+        scheme.batch_verify(&commitments, &points, &values, &proof, &mut Challenger::new(128))?;
+
+        values[2][0] = values[2][0] + F::ONE;
+        assert!(scheme
+            .batch_verify(&commitments, &points, &values, &proof, &mut Challenger::new(128))
+            .is_err());
+        Ok(())
+    }
+}
@@ -13,61 +13,136 @@
 #![allow(incomplete_features)]
 
 
+#[cfg(feature = "arkworks")]
+pub use arkworks::*;
+#[cfg(feature = "bellman-adapter")]
+pub use bellman_adapter::*;
 pub use bigint::*;
+pub use buffer_pool::*;
 pub use circuit_bigint::*;
 pub use circuit_builder::*;
 pub use circuit_curve::*;
 pub use circuit_foreign_field::*;
+pub use circuit_io::*;
 pub use circuit_ordering::*;
+pub use commitment_scheme::*;
+pub use component::*;
+pub use constraint_check::*;
 pub use conversions::*;
 pub use curve::*;
+pub use error::*;
+pub use expr::*;
 pub use fft::*;
 pub use field::*;
 pub use gates::*;
+pub use gmimc::*;
+pub use hash_params::*;
 pub use hash_to_curve::*;
+pub use hasher::*;
+pub use kzg::*;
 pub use mds::*;
+pub use merkle_tree::*;
 pub use partition::*;
+pub use permutation_batch::*;
 pub use plonk::*;
+pub use plonk_aggregation::*;
+pub use plonk_pcd::*;
 pub use plonk_proof::*;
 pub use plonk_recursion::*;
 pub use poly_commit::*;
 pub use polynomial::*;
+pub use poseidon::*;
+pub use preprocessing_cache::*;
+pub use prover_config::*;
+pub use prover_observer::*;
 pub use pseudorandom::*;
+pub use r1cs::*;
+pub use randomness::*;
 pub use rescue::*;
+pub use resource_estimate::*;
+pub use security::*;
 pub use serialization::*;
+pub use sponge::*;
 pub use target::*;
 pub use verifier::*;
 pub use witness::*;
+#[cfg(feature = "macros")]
+pub use plonky_macros::{circuit, CircuitIo};
 
+#[cfg(feature = "arkworks")]
+mod arkworks;
+pub mod acir;
+#[cfg(feature = "bellman-adapter")]
+mod bellman_adapter;
+pub mod bench;
 mod bigint;
+mod buffer_pool;
+pub mod circom;
 mod circuit_bigint;
+mod circuit_bits;
 mod circuit_builder;
 mod circuit_curve;
 mod circuit_foreign_field;
+mod circuit_io;
+mod circuit_merkle;
 mod circuit_ordering;
+mod circuit_sha256;
+mod commitment_scheme;
+mod component;
+mod constraint_check;
 mod conversions;
+#[cfg(all(test, feature = "cross-check"))]
+mod cross_check;
 mod curve;
+mod error;
+mod expr;
 mod fft;
 mod field;
 mod gates;
+mod gmimc;
 pub mod halo;
+mod hash_params;
 mod hash_to_curve;
+mod hasher;
+mod kzg;
 mod mds;
+mod merkle_tree;
 mod partition;
+mod permutation_batch;
 mod plonk;
+mod plonk_aggregation;
 pub mod plonk_challenger;
+mod plonk_pcd;
 mod plonk_proof;
 mod plonk_recursion;
 pub mod plonk_util;
 pub mod poly_commit;
 pub mod polynomial;
+mod poseidon;
+mod preprocessing_cache;
+mod prover_config;
+mod prover_observer;
 mod pseudorandom;
+mod r1cs;
+mod randomness;
 mod rescue;
+mod resource_estimate;
+mod security;
 mod serialization;
+pub mod serialization_testing;
+#[cfg(test)]
+mod soundness;
+#[cfg(test)]
+mod zero_knowledge;
+#[cfg(feature = "service")]
+pub mod service;
+mod sponge;
 mod target;
+pub mod test_vectors;
 pub mod util;
 mod verifier;
 mod witness;
+pub mod zkinterface;
 
 #[macro_use]
 extern crate log;
@@ -31,6 +31,14 @@ impl<F: Field> MdsMatrix<F> {
     pub fn get(&self, r: usize, c: usize) -> F {
         F::from_canonical_u64_vec(self.unparameterized.rows[r][c].clone())
     }
+
+    /// Returns all entries of the matrix, as a list of rows.
+    pub fn to_rows(&self) -> Vec<Vec<F>> {
+        let n = self.unparameterized.rows.len();
+        (0..n)
+            .map(|r| (0..n).map(|c| self.get(r, c)).collect())
+            .collect()
+    }
 }
 
 /// A representation of an MDS matrix which does not involve Field types.
@@ -0,0 +1,432 @@
+use std::marker::PhantomData;
+
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{AlgebraicHasher, Field, RescueHasher, Sponge};
+
+/// A binary Merkle tree over field elements. Each internal node is the hash of its two children
+/// with a sponge over `H`, so roots and proofs match `CircuitBuilder::verify_merkle_proof` (or
+/// `verify_merkle_proof_with_hasher` for hashers other than Rescue).
+#[derive(Clone, Debug)]
+pub struct MerkleTree<F: Field, H: AlgebraicHasher<F> = RescueHasher> {
+    /// The layers of the tree, from the leaves up to the root.
+    layers: Vec<Vec<F>>,
+    security_bits: usize,
+    _hasher: PhantomData<H>,
+}
+
+/// A proof that a leaf is present in a `MerkleTree`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MerkleProof<F: Field> {
+    /// The siblings of the nodes on the path from the leaf to the root, from the bottom up.
+    pub siblings: Vec<F>,
+}
+
+impl<F: Field> MerkleTree<F> {
+    /// Builds a tree from its leaves, whose number must be a power of two.
+    pub fn new(leaves: Vec<F>, security_bits: usize) -> Self {
+        Self::new_with_hasher(leaves, security_bits)
+    }
+}
+
+impl<F: Field, H: AlgebraicHasher<F>> MerkleTree<F, H> {
+    pub fn new_with_hasher(leaves: Vec<F>, security_bits: usize) -> Self {
+        assert!(
+            leaves.len().is_power_of_two(),
+            "The number of leaves must be a power of two"
+        );
+
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next_layer =
+                merkle_compress_many_with_hasher::<F, H>(layers.last().unwrap(), security_bits);
+            layers.push(next_layer);
+        }
+
+        MerkleTree {
+            layers,
+            security_bits,
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn root(&self) -> F {
+        self.layers.last().unwrap()[0]
+    }
+
+    pub fn leaves(&self) -> &[F] {
+        &self.layers[0]
+    }
+
+    /// The number of layers above the leaves, which is also the length of each proof.
+    pub fn height(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn security_bits(&self) -> usize {
+        self.security_bits
+    }
+
+    /// Returns a proof that the leaf at the given index is present in the tree.
+    pub fn open(&self, index: usize) -> MerkleProof<F> {
+        assert!(index < self.leaves().len(), "Leaf index out of range");
+        let siblings = self.layers[..self.height()]
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| layer[(index >> i) ^ 1])
+            .collect();
+        MerkleProof { siblings }
+    }
+}
+
+/// An append-only Merkle tree of fixed depth, such as those used to accumulate note commitments.
+/// Leaves which have not been appended yet are zero, so at any point the root is that of a
+/// `MerkleTree` whose leaves are the appended leaves padded with zeros, and proofs can be checked
+/// with `verify_merkle_proof` or `CircuitBuilder::verify_merkle_proof`.
+///
+/// The roots of empty subtrees are cached, so appending a leaf only hashes the nodes on its path.
+#[derive(Clone, Debug)]
+pub struct IncrementalMerkleTree<F: Field, H: AlgebraicHasher<F> = RescueHasher> {
+    /// The nodes which have been computed so far, from the leaves up to the root. Each layer is
+    /// filled from the left; the last node of each layer forms the frontier of the tree.
+    layers: Vec<Vec<F>>,
+    /// The root of an empty subtree of each height.
+    empty_roots: Vec<F>,
+    security_bits: usize,
+    _hasher: PhantomData<H>,
+}
+
+impl<F: Field> IncrementalMerkleTree<F> {
+    pub fn new(depth: usize, security_bits: usize) -> Self {
+        Self::new_with_hasher(depth, security_bits)
+    }
+}
+
+impl<F: Field, H: AlgebraicHasher<F>> IncrementalMerkleTree<F, H> {
+    pub fn new_with_hasher(depth: usize, security_bits: usize) -> Self {
+        assert!(
+            depth < usize::BITS as usize,
+            "Depth {} is too large",
+            depth
+        );
+        let mut empty_roots = vec![F::ZERO];
+        for i in 0..depth {
+            empty_roots.push(merkle_compress_with_hasher::<F, H>(
+                empty_roots[i],
+                empty_roots[i],
+                security_bits,
+            ));
+        }
+
+        IncrementalMerkleTree {
+            layers: vec![Vec::new(); depth + 1],
+            empty_roots,
+            security_bits,
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    /// The number of leaves appended so far.
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == 1 << self.depth()
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.depth(), 0)
+    }
+
+    /// Appends a leaf, returning its index.
+    pub fn append(&mut self, leaf: F) -> usize {
+        assert!(!self.is_full(), "The tree is full");
+        let index = self.len();
+        self.layers[0].push(leaf);
+
+        // Update the nodes on the path from the new leaf to the root.
+        for level in 0..self.depth() {
+            let parent_index = (index >> level) / 2;
+            let parent = merkle_compress_with_hasher::<F, H>(
+                self.node(level, parent_index * 2),
+                self.node(level, parent_index * 2 + 1),
+                self.security_bits,
+            );
+            let parent_layer = &mut self.layers[level + 1];
+            if parent_index < parent_layer.len() {
+                parent_layer[parent_index] = parent;
+            } else {
+                parent_layer.push(parent);
+            }
+        }
+
+        index
+    }
+
+    /// Returns a proof that the leaf at the given index, which must have been appended, is
+    /// present in the tree. The proof is only valid for the current root.
+    pub fn open(&self, index: usize) -> MerkleProof<F> {
+        assert!(index < self.len(), "Leaf index out of range");
+        let siblings = (0..self.depth())
+            .map(|level| self.node(level, (index >> level) ^ 1))
+            .collect();
+        MerkleProof { siblings }
+    }
+
+    /// Returns the node at the given height and index, which is the root of an empty subtree if
+    /// it has not been computed yet.
+    fn node(&self, level: usize, index: usize) -> F {
+        self.layers[level]
+            .get(index)
+            .copied()
+            .unwrap_or(self.empty_roots[level])
+    }
+}
+
+/// Hashes two sibling nodes to their parent.
+pub fn merkle_compress<F: Field>(left: F, right: F, security_bits: usize) -> F {
+    merkle_compress_with_hasher::<F, RescueHasher>(left, right, security_bits)
+}
+
+pub fn merkle_compress_with_hasher<F: Field, H: AlgebraicHasher<F>>(
+    left: F,
+    right: F,
+    security_bits: usize,
+) -> F {
+    let mut sponge = Sponge::new_with_hasher::<H>(security_bits);
+    sponge.absorb_all(&[left, right]);
+    sponge.squeeze()
+}
+
+/// Hashes each pair of adjacent nodes, i.e. a whole layer of a tree, to their parents. This gives
+/// the same results as `merkle_compress_with_hasher`, but permutes the sponge states in a batch
+/// with `AlgebraicHasher::permute_many`, which is much faster for large layers.
+pub fn merkle_compress_many_with_hasher<F: Field, H: AlgebraicHasher<F>>(
+    nodes: &[F],
+    security_bits: usize,
+) -> Vec<F> {
+    assert_eq!(nodes.len() % 2, 0, "Nodes must come in pairs");
+    if H::RATE < 2 {
+        // A pair doesn't fit in a single block, so we fall back to a sponge per pair.
+        return nodes
+            .chunks(2)
+            .map(|pair| merkle_compress_with_hasher::<F, H>(pair[0], pair[1], security_bits))
+            .collect();
+    }
+
+    // Absorbing a pair into a fresh sponge leaves it in this state, which is then permuted once
+    // before the first output is squeezed.
+    let states = nodes
+        .chunks(2)
+        .map(|pair| {
+            let mut state = vec![F::ZERO; H::WIDTH];
+            state[..2].copy_from_slice(pair);
+            state
+        })
+        .collect::<Vec<_>>();
+    H::permute_many(&states, security_bits)
+        .into_iter()
+        .map(|state| state[0])
+        .collect()
+}
+
+/// Computes the root implied by a leaf, its index and a proof. Bit `i` of the index indicates
+/// whether the node at height `i` on the path is a right child.
+pub fn merkle_root_from_proof<F: Field>(
+    leaf: F,
+    index: usize,
+    proof: &MerkleProof<F>,
+    security_bits: usize,
+) -> F {
+    merkle_root_from_proof_with_hasher::<F, RescueHasher>(leaf, index, proof, security_bits)
+}
+
+pub fn merkle_root_from_proof_with_hasher<F: Field, H: AlgebraicHasher<F>>(
+    leaf: F,
+    index: usize,
+    proof: &MerkleProof<F>,
+    security_bits: usize,
+) -> F {
+    let mut current = leaf;
+    for (i, &sibling) in proof.siblings.iter().enumerate() {
+        current = if (index >> i) & 1 == 1 {
+            merkle_compress_with_hasher::<F, H>(sibling, current, security_bits)
+        } else {
+            merkle_compress_with_hasher::<F, H>(current, sibling, security_bits)
+        };
+    }
+    current
+}
+
+/// Verifies that `leaf` is present at the given index of the tree with the given root. The proof
+/// length determines the height of the tree, so callers should check it if the height matters.
+pub fn verify_merkle_proof<F: Field>(
+    leaf: F,
+    index: usize,
+    proof: &MerkleProof<F>,
+    root: F,
+    security_bits: usize,
+) -> Result<()> {
+    verify_merkle_proof_with_hasher::<F, RescueHasher>(leaf, index, proof, root, security_bits)
+}
+
+pub fn verify_merkle_proof_with_hasher<F: Field, H: AlgebraicHasher<F>>(
+    leaf: F,
+    index: usize,
+    proof: &MerkleProof<F>,
+    root: F,
+    security_bits: usize,
+) -> Result<()> {
+    ensure!(
+        index.checked_shr(proof.siblings.len() as u32).unwrap_or(0) == 0,
+        "Leaf index {} is out of range for a tree of height {}",
+        index,
+        proof.siblings.len()
+    );
+    ensure!(
+        merkle_root_from_proof_with_hasher::<F, H>(leaf, index, proof, security_bits) == root,
+        "Invalid Merkle proof"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        merkle_compress, merkle_compress_many_with_hasher, merkle_compress_with_hasher,
+        verify_merkle_proof, verify_merkle_proof_with_hasher, AlgebraicHasher, CircuitBuilder,
+        Curve, Field, GmimcHasher, IncrementalMerkleTree, MerkleTree, PartialWitness,
+        PoseidonHasher, RescueHasher, Tweedledee, PERMUTATION_LANES,
+    };
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    fn random_tree(num_leaves: usize) -> MerkleTree<F> {
+        MerkleTree::new((0..num_leaves).map(|_| F::rand()).collect(), 128)
+    }
+
+    #[test]
+    fn test_merkle_tree() {
+        let tree = random_tree(8);
+        assert_eq!(tree.height(), 3);
+        for (i, &leaf) in tree.leaves().iter().enumerate() {
+            let proof = tree.open(i);
+            assert_eq!(proof.siblings.len(), 3);
+            verify_merkle_proof(leaf, i, &proof, tree.root(), 128).unwrap();
+
+            // The proof shouldn't verify for a different leaf, or at a different index.
+            assert!(verify_merkle_proof(leaf + F::ONE, i, &proof, tree.root(), 128).is_err());
+            assert!(verify_merkle_proof(leaf, i ^ 1, &proof, tree.root(), 128).is_err());
+            assert!(verify_merkle_proof(leaf, i + 8, &proof, tree.root(), 128).is_err());
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_small() {
+        let tree = random_tree(1);
+        assert_eq!(tree.root(), tree.leaves()[0]);
+        assert!(tree.open(0).siblings.is_empty());
+
+        let tree = random_tree(2);
+        let leaves = tree.leaves();
+        assert_eq!(tree.root(), merkle_compress(leaves[0], leaves[1], 128));
+    }
+
+    #[test]
+    #[should_panic(expected = "The number of leaves must be a power of two")]
+    fn test_merkle_tree_bad_size() {
+        random_tree(6);
+    }
+
+    #[test]
+    fn test_merkle_compress_many() {
+        check_merkle_compress_many::<RescueHasher>();
+        check_merkle_compress_many::<PoseidonHasher>();
+        check_merkle_compress_many::<GmimcHasher>();
+    }
+
+    fn check_merkle_compress_many<H: AlgebraicHasher<F>>() {
+        let nodes = (0..2 * PERMUTATION_LANES + 2)
+            .map(|_| F::rand())
+            .collect::<Vec<_>>();
+        let expected = nodes
+            .chunks(2)
+            .map(|pair| merkle_compress_with_hasher::<F, H>(pair[0], pair[1], 128))
+            .collect::<Vec<_>>();
+        assert_eq!(merkle_compress_many_with_hasher::<F, H>(&nodes, 128), expected);
+    }
+
+    #[test]
+    fn test_merkle_proof_consistency() {
+        check_merkle_proof_consistency::<RescueHasher>();
+        check_merkle_proof_consistency::<PoseidonHasher>();
+    }
+
+    fn check_merkle_proof_consistency<H: AlgebraicHasher<F>>() {
+        let leaves = (0..16).map(|_| F::rand()).collect();
+        let tree = MerkleTree::<F, H>::new_with_hasher(leaves, 128);
+        let index = 11;
+        let proof = tree.open(index);
+        verify_merkle_proof_with_hasher::<F, H>(tree.leaves()[index], index, &proof, tree.root(), 128)
+            .unwrap();
+
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let leaf = builder.constant_wire(tree.leaves()[index]);
+        let index_bits = (0..tree.height())
+            .map(|i| builder.constant_wire(F::from_canonical_usize((index >> i) & 1)))
+            .collect::<Vec<_>>();
+        let siblings = builder.constant_wires(&proof.siblings);
+        let root = builder.merkle_root_from_proof_with_hasher::<H>(leaf, &index_bits, &siblings);
+        let circuit = builder.build();
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
+
+        assert_eq!(witness.get_target(root), tree.root());
+    }
+
+    #[test]
+    fn test_incremental_merkle_tree() {
+        let depth = 3;
+        let leaves = (0..1 << depth).map(|_| F::rand()).collect::<Vec<_>>();
+        let mut tree = IncrementalMerkleTree::new(depth, 128);
+        assert_eq!(
+            tree.root(),
+            MerkleTree::new(vec![F::ZERO; 1 << depth], 128).root()
+        );
+
+        for (i, &leaf) in leaves.iter().enumerate() {
+            assert_eq!(tree.append(leaf), i);
+
+            // The root should match that of a full tree with the remaining leaves set to zero.
+            let mut padded_leaves = leaves[..=i].to_vec();
+            padded_leaves.resize(1 << depth, F::ZERO);
+            let full_tree = MerkleTree::new(padded_leaves, 128);
+            assert_eq!(tree.root(), full_tree.root());
+
+            for j in 0..=i {
+                assert_eq!(tree.open(j), full_tree.open(j));
+                verify_merkle_proof(leaves[j], j, &tree.open(j), tree.root(), 128).unwrap();
+            }
+        }
+        assert!(tree.is_full());
+    }
+
+    #[test]
+    #[should_panic(expected = "The tree is full")]
+    fn test_incremental_merkle_tree_overflow() {
+        let mut tree = IncrementalMerkleTree::new(1, 128);
+        for _ in 0..3 {
+            tree.append(F::ONE);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Field, Target, Wire, NUM_ROUTED_WIRES, NUM_WIRES};
+use crate::{Field, Target, Wire};
 use rand_chacha::rand_core::SeedableRng;
 use rand_chacha::ChaCha8Rng;
 use std::collections::HashMap;
@@ -26,6 +26,13 @@ impl<F: Field> TargetPartitions<F> {
         &self.partitions[self.indices[&target]]
     }
 
+    /// Like `get_partition`, but returns `None` for targets which were never added.
+    pub fn try_get_partition(&self, target: Target<F>) -> Option<&[Target<F>]> {
+        self.indices
+            .get(&target)
+            .map(|&index| self.partitions[index].as_slice())
+    }
+
     /// Add a new partition with a single member.
     pub fn add_partition(&mut self, target: Target<F>) {
         let index = self.partitions.len();
@@ -51,7 +58,7 @@ impl<F: Field> TargetPartitions<F> {
         }
     }
 
-    pub fn to_wire_partitions(&self) -> WirePartitions {
+    pub fn to_wire_partitions(&self, num_routed_wires: usize) -> WirePartitions {
         // Here we just drop all CircuitInputs, leaving all GateInputs.
         let mut partitions = Vec::new();
         let mut indices = HashMap::new();
@@ -76,7 +83,7 @@ impl<F: Field> TargetPartitions<F> {
             partitions,
             indices,
         };
-        result.assert_valid();
+        result.assert_valid(num_routed_wires);
         result
     }
 }
@@ -87,14 +94,21 @@ pub struct WirePartitions {
 }
 
 impl WirePartitions {
-    fn assert_valid(&self) {
+    /// Returns each partition, i.e. each set of wires which must have equal values.
+    pub(crate) fn partitions(&self) -> &[Vec<Wire>] {
+        &self.partitions
+    }
+
+    fn assert_valid(&self, num_routed_wires: usize) {
         for partition in &self.partitions {
             for wire in partition {
-                if wire.input >= NUM_ROUTED_WIRES {
+                if wire.input >= num_routed_wires {
                     assert_eq!(
                         partition.len(),
                         1,
-                        "Non-routed wires should not be in a partition containing other wires"
+                        "Advice wire {:?} cannot be copy-constrained to other wires, but its partition is {:?}",
+                        wire,
+                        partition
                     );
                 }
             }
@@ -119,13 +133,13 @@ impl WirePartitions {
 
     /// Generates sigma in the context of Plonk, which is a map from `[kn]` to `[kn]`, where `k` is
     /// the number of routed wires and `n` is the number of gates.
-    pub fn to_sigma(&self) -> Vec<usize> {
-        debug_assert_eq!(self.indices.len() % NUM_WIRES, 0);
+    pub fn to_sigma(&self, num_wires: usize, num_routed_wires: usize) -> Vec<usize> {
+        debug_assert_eq!(self.indices.len() % num_wires, 0);
         let num_all_wires = self.indices.len();
-        let num_gates = num_all_wires / NUM_WIRES;
+        let num_gates = num_all_wires / num_wires;
 
         let mut sigma = Vec::new();
-        for input in 0..NUM_ROUTED_WIRES {
+        for input in 0..num_routed_wires {
             for gate in 0..num_gates {
                 let wire = Wire { gate, input };
                 let neighbor = self.get_neighbor(wire);
@@ -138,7 +152,7 @@ impl WirePartitions {
 
 /// Returns `k_i`, the multiplier used in `S_ID_i` in the context of Plonk's permutation argument.
 pub(crate) fn get_subgroup_shift<F: Field>(i: usize) -> F {
-    // The optimized variant of Plonk's permutation argument calls for NUM_ROUTED_WIRES shifts,
+    // The optimized variant of Plonk's permutation argument calls for one shift per routed wire,
     // k_1, ..., k_n, which result in distinct cosets. The paper suggests a method which is
     // fairly straightforward when only three shifts are needed, but seems a bit complex and
     // expensive if more are needed.
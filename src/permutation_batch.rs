@@ -0,0 +1,119 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::Field;
+
+/// The number of states which `permute_in_lanes` processes in lockstep.
+pub const PERMUTATION_LANES: usize = 8;
+
+/// A group of up to `PERMUTATION_LANES` states of the same width, stored element-major. Each step
+/// of a permutation is applied to the same element of every state in turn, so the work on
+/// different states is independent, and the compiler can interleave or vectorize it.
+pub(crate) struct StateLanes<F: Field> {
+    /// `elements[i][j]` is element `i` of state `j`.
+    elements: Vec<[F; PERMUTATION_LANES]>,
+}
+
+impl<F: Field> StateLanes<F> {
+    pub(crate) fn width(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Adds `constants[i]` to element `i` of every state.
+    pub(crate) fn add_constants(&mut self, constants: &[F]) {
+        for (lanes, &c) in self.elements.iter_mut().zip(constants) {
+            for x in lanes.iter_mut() {
+                *x = *x + c;
+            }
+        }
+    }
+
+    /// Applies an S-box to every element of every state.
+    pub(crate) fn apply_sbox<S: Fn(F) -> F>(&mut self, sbox: S) {
+        for i in 0..self.width() {
+            self.apply_sbox_to_element(i, &sbox);
+        }
+    }
+
+    /// Applies an S-box to element `i` of every state.
+    pub(crate) fn apply_sbox_to_element<S: Fn(F) -> F>(&mut self, i: usize, sbox: S) {
+        for x in self.elements[i].iter_mut() {
+            *x = sbox(*x);
+        }
+    }
+
+    /// Multiplies every state by the given matrix, given as a list of rows.
+    pub(crate) fn apply_mds(&mut self, mds: &[Vec<F>]) {
+        self.elements = mds
+            .iter()
+            .map(|row| {
+                let mut result = [F::ZERO; PERMUTATION_LANES];
+                for (&m, lanes) in row.iter().zip(&self.elements) {
+                    for (r, &x) in result.iter_mut().zip(lanes) {
+                        *r = *r + m * x;
+                    }
+                }
+                result
+            })
+            .collect();
+    }
+
+    /// Applies one GMiMC-erf round to every state: `f = sbox(x_0 + c)` is added to every other
+    /// element, and the state is rotated by one.
+    pub(crate) fn apply_gmimc_round<S: Fn(F) -> F>(&mut self, c: F, sbox: S) {
+        let mut f = self.elements[0];
+        for x in f.iter_mut() {
+            *x = sbox(*x + c);
+        }
+        for lanes in self.elements.iter_mut().skip(1) {
+            for (x, &f) in lanes.iter_mut().zip(&f) {
+                *x = *x + f;
+            }
+        }
+        self.elements.rotate_left(1);
+    }
+}
+
+/// Applies a permutation, given as a function which permutes a group of `StateLanes`, to each of
+/// the given states, which must have the same width. States are permuted in groups of
+/// `PERMUTATION_LANES`, and with the `parallel` feature, groups are spread across threads.
+pub(crate) fn permute_in_lanes<F: Field, P: Fn(&mut StateLanes<F>) + Sync>(
+    states: &[Vec<F>],
+    permute: P,
+) -> Vec<Vec<F>> {
+    let width = states.first().map_or(0, Vec::len);
+    assert!(
+        states.iter().all(|state| state.len() == width),
+        "All states must have the same width"
+    );
+
+    let permute_group = |group: &[Vec<F>]| {
+        let mut lanes = StateLanes {
+            elements: vec![[F::ZERO; PERMUTATION_LANES]; width],
+        };
+        for (j, state) in group.iter().enumerate() {
+            for (i, &x) in state.iter().enumerate() {
+                lanes.elements[i][j] = x;
+            }
+        }
+
+        permute(&mut lanes);
+
+        // Any unused lanes just hold permuted zero states, which we discard.
+        (0..group.len())
+            .map(|j| lanes.elements.iter().map(|x| x[j]).collect())
+            .collect::<Vec<Vec<F>>>()
+    };
+
+    #[cfg(feature = "parallel")]
+    let groups = states
+        .par_chunks(PERMUTATION_LANES)
+        .map(permute_group)
+        .collect::<Vec<_>>();
+    #[cfg(not(feature = "parallel"))]
+    let groups = states
+        .chunks(PERMUTATION_LANES)
+        .map(permute_group)
+        .collect::<Vec<_>>();
+    groups.into_iter().flatten().collect()
+}
@@ -1,40 +1,86 @@
 use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Debug;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
 use std::time::Instant;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use rand::Rng;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::halo::batch_opening_proof;
 use crate::partition::{get_subgroup_shift, TargetPartitions};
-use crate::plonk_challenger::Challenger;
-use crate::plonk_proof::{OldProof, Proof};
-use crate::plonk_util::{commit_polynomials, eval_l_1, eval_polys, eval_zero_poly, pad_to_8n, permutation_polynomial, polynomials_to_values_padded, powers, reduce_with_powers, scale_polynomials, values_to_polynomials};
+use crate::plonk_challenger::{Challenger, Transcript};
+use crate::plonk_proof::{observe_statement, OldProof, Proof, ProofParameters};
+use crate::plonk_util::{commit_polynomials, eval_l_1, eval_polys, eval_zero_poly, permutation_polynomial, permutation_polynomial_with_stride, polynomials_to_values_padded, powers, reduce_with_powers, scale_polynomials, values_to_polynomials};
 use crate::poly_commit::PolynomialCommitment;
-use crate::polynomial::Polynomial;
+use crate::polynomial::{Polynomial, PolynomialValues};
 use crate::target::Target;
 use crate::util::{ceil_div_usize, log2_strict};
 use crate::witness::{PartialWitness, Witness, WitnessGenerator};
-use crate::{evaluate_all_constraints, fft_with_precomputation_power_of_2, AffinePoint, FftPrecomputation, Field, HaloCurve, MsmPrecomputation, OpeningSet, VerificationKey};
+use crate::fft::{deserialize_cached_fft_precomputation, serialize_cached_fft_precomputation};
+use crate::gates::gate_name;
+use crate::prover_observer::PhaseTimer;
+use crate::{blinding_rng, check_witness_complete, evaluate_all_constraints, msm_precompute, NoopObserver, ProverConfig, ProverObserver, ProverPhase, recycle_buffer, take_buffer, seeded_rng, AffinePoint, FftPrecomputation, Field, HaloCurve, KeyFormat, MsmPrecomputation, OpeningSet, PlonkyError, SecurityParams, VerificationKey};
 
+/// The default number of wires, which is also the minimum supported by our gates. See
+/// `CircuitBuilder::new_with_width` for building wider circuits.
 pub(crate) const NUM_WIRES: usize = 9;
+/// The default number of routed wires, i.e. wires which can participate in copy constraints.
 pub(crate) const NUM_ROUTED_WIRES: usize = 6;
 pub(crate) const NUM_ADVICE_WIRES: usize = NUM_WIRES - NUM_ROUTED_WIRES;
 pub(crate) const NUM_CONSTANTS: usize = 6;
 pub(crate) const GRID_WIDTH: usize = 65;
+/// The number of gates with random wire values in zero-knowledge circuits. See
+/// `CircuitBuilder::add_blinding_gates`.
+pub(crate) const NUM_BLINDING_GATES: usize = 4;
 // This is currently dominated by Base4SumGate. It has degree-4n constraints, and its prefix is 4
 // bits long, so its filtered constraints are degree-8n. Dividing by Z_H makes t degree-7n.
 pub(crate) const QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER: usize = 7;
+/// The number of rows of a coset whose vanishing polynomial values are computed in a single task.
+const VANISHING_EVAL_CHUNK_SIZE: usize = 256;
 
 /// Contains all data needed to generate and/or verify proofs.
 pub struct Circuit<C: HaloCurve> {
+    /// The preprocessed data used by the prover.
+    pub prover_key: ProverKey<C>,
+    pub routing_target_partitions: TargetPartitions<C::ScalarField>,
+    pub generators: Vec<Box<dyn WitnessGenerator<C::ScalarField>>>,
+    /// The gates of each component instance, along with the component's name, for labelling gates
+    /// in error messages.
+    pub component_instances: Vec<(Range<usize>, &'static str)>,
+    /// The gates added within each context, along with the context's path. See
+    /// `CircuitBuilder::push_context`.
+    pub context_spans: Vec<(Range<usize>, String)>,
+    /// Names of targets, for error messages. See `CircuitBuilder::set_target_name`.
+    pub target_names: HashMap<Target<C::ScalarField>, String>,
+}
+
+/// Contains the preprocessed circuit data needed to generate proofs, given a witness. Unlike
+/// `Circuit`, this holds no witness generators, so it can be serialized and loaded by a prover
+/// without rebuilding the circuit. See `VerificationKey` for the much smaller verifier data.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProverKey<C: HaloCurve> {
+    pub format: KeyFormat<C>,
     pub security_bits: usize,
+    /// The length of Fiat-Shamir challenges in the IPA. See `SecurityParams::challenge_len`.
+    pub challenge_len: usize,
+    /// The number of wires, i.e. the width of the circuit.
+    pub num_wires: usize,
+    /// The number of routed wires. These are the first `num_routed_wires` wires of each gate.
+    pub num_routed_wires: usize,
+    /// Whether the circuit has blinding gates, and proofs blind their commitments. See
+    /// `CircuitBuilder::set_zero_knowledge`.
+    pub zero_knowledge: bool,
+    /// Whether the prover avoids degree-8n LDEs. See `ProverKey::set_low_memory`.
+    pub low_memory: bool,
     pub num_public_inputs: usize,
     pub num_gates_without_pis: usize,
     pub gate_constants: Vec<Vec<C::ScalarField>>,
-    pub routing_target_partitions: TargetPartitions<C::ScalarField>,
-    pub generators: Vec<Box<dyn WitnessGenerator<C::ScalarField>>>,
     /// A generator of `subgroup_n`.
     pub subgroup_generator_n: C::ScalarField,
     /// A generator of `subgroup_8n`.
@@ -51,25 +97,40 @@ pub struct Circuit<C: HaloCurve> {
     pub u: AffinePoint<C>,
     /// Each constant polynomial, in coefficient form.
     pub constant_polynomials: Vec<Polynomial<C::ScalarField>>,
-    /// Each constant polynomial, in point-value form, low-degree extended to be degree 8n.
+    /// Each constant polynomial, in point-value form, low-degree extended to be degree 8n. This is
+    /// empty in low-memory mode.
     pub constants_8n: Vec<Vec<C::ScalarField>>,
     /// A commitment to each constant polynomial.
-    pub c_constants: Vec<PolynomialCommitment<C>>,
+    pub c_constants: Vec<AffinePoint<C>>,
     /// Each permutation polynomial, in coefficient form.
     pub s_sigma_polynomials: Vec<Polynomial<C::ScalarField>>,
-    /// Each permutation polynomial, low-degree extended to be degree 8n.
+    /// Each permutation polynomial, low-degree extended to be degree 8n. This is empty in
+    /// low-memory mode.
     pub s_sigma_values_8n: Vec<Vec<C::ScalarField>>,
     /// A commitment to each permutation polynomial.
-    pub c_s_sigmas: Vec<PolynomialCommitment<C>>,
+    pub c_s_sigmas: Vec<AffinePoint<C>>,
     /// A precomputation used for MSMs involving `generators`.
     pub pedersen_g_msm_precomputation: MsmPrecomputation<C>,
-    /// A precomputation used for FFTs of degree n, where n is the number of gates.
-    pub fft_precomputation_n: FftPrecomputation<C::ScalarField>,
+    /// A precomputation used for FFTs of degree n, where n is the number of gates. It's shared with
+    /// `cached_fft_precomputation`, and serialized as just its size.
+    #[serde(serialize_with = "serialize_cached_fft_precomputation")]
+    #[serde(deserialize_with = "deserialize_cached_fft_precomputation")]
+    pub fft_precomputation_n: Arc<FftPrecomputation<C::ScalarField>>,
     /// A precomputation used for FFTs of degree 8n, where n is the number of gates.
-    pub fft_precomputation_8n: FftPrecomputation<C::ScalarField>,
+    #[serde(serialize_with = "serialize_cached_fft_precomputation")]
+    #[serde(deserialize_with = "deserialize_cached_fft_precomputation")]
+    pub fft_precomputation_8n: Arc<FftPrecomputation<C::ScalarField>>,
 }
 
-impl<C: HaloCurve> Circuit<C> {
+impl<C: HaloCurve> Deref for Circuit<C> {
+    type Target = ProverKey<C>;
+
+    fn deref(&self) -> &ProverKey<C> {
+        &self.prover_key
+    }
+}
+
+impl<C: HaloCurve> ProverKey<C> {
     pub fn degree(&self) -> usize {
         self.gate_constants.len()
     }
@@ -78,6 +139,46 @@ impl<C: HaloCurve> Circuit<C> {
         log2_strict(self.degree())
     }
 
+    /// The number of advice wires, which are not part of the permutation argument.
+    pub fn num_advice_wires(&self) -> usize {
+        self.num_wires - self.num_routed_wires
+    }
+
+    pub fn security_params(&self) -> SecurityParams {
+        SecurityParams {
+            bits: self.security_bits,
+            challenge_len: self.challenge_len,
+        }
+    }
+
+    /// Enables or disables the low-memory prover mode, which produces identical proofs while using
+    /// less memory, at the cost of some extra FFTs.
+    ///
+    /// The prover always evaluates the vanishing polynomial one degree-n coset at a time, but
+    /// normally the key holds degree-8n LDEs of the constant and permutation polynomials. In
+    /// low-memory mode, the key drops those LDEs, and the prover recomputes each coset's
+    /// evaluations of them as it goes.
+    pub fn set_low_memory(&mut self, low_memory: bool) {
+        self.low_memory = low_memory;
+        if low_memory {
+            self.constants_8n = Vec::new();
+            self.s_sigma_values_8n = Vec::new();
+        } else if self.constants_8n.is_empty() {
+            self.constants_8n = polynomials_to_values_padded(&self.constant_polynomials);
+            self.s_sigma_values_8n = polynomials_to_values_padded(&self.s_sigma_polynomials);
+        }
+    }
+
+    /// Sets the window size of the MSMs which commit to polynomials, recomputing the key's MSM
+    /// precomputation. Larger windows make commitments faster, up to a point, at the cost of a
+    /// precomputation which is exponentially larger in the window size.
+    pub fn set_msm_window(&mut self, w: usize) {
+        if w != self.pedersen_g_msm_precomputation.window() {
+            self.pedersen_g_msm_precomputation =
+                msm_precompute(&AffinePoint::batch_to_projective(&self.pedersen_g), w);
+        }
+    }
+
     // TODO: For now we assume that there's exactly one embedded curve, InnerC.
     // Ideally it should be possible to use any number of embedded curves (including zero),
     // and we should add a set of curve gates for each embedded curve.
@@ -85,23 +186,201 @@ impl<C: HaloCurve> Circuit<C> {
         &self,
         witness: &Witness<C::ScalarField>,
         old_proofs: &[OldProof<C>],
-        blinding_commitments: bool,
     ) -> Result<Proof<C>> {
-        let mut challenger = Challenger::new(self.security_bits);
+        let challenger = Challenger::new(self.security_bits);
+        self.generate_proof_with_rng::<InnerC, _, _>(witness, old_proofs, challenger, &mut blinding_rng(), &ProverConfig::default(), &NoopObserver)
+    }
+
+    /// Like `generate_proof`, but uses `challenger` as the Fiat-Shamir transcript instead of the
+    /// default Rescue-based `Challenger`. The verifier must use a transcript of the same kind; see
+    /// `verify_with_transcript`.
+    pub fn generate_proof_with_transcript<
+        InnerC: HaloCurve<BaseField = C::ScalarField>,
+        T: Transcript<C::BaseField>,
+    >(
+        &self,
+        witness: &Witness<C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        challenger: T,
+    ) -> Result<Proof<C>> {
+        self.generate_proof_with_rng::<InnerC, _, _>(witness, old_proofs, challenger, &mut blinding_rng(), &ProverConfig::default(), &NoopObserver)
+    }
+
+    /// Like `generate_proof`, but derives all of the prover's randomness, i.e. commitment and IPA
+    /// blinding factors, from `seed`. Proving the same witness with the same seed thus results in
+    /// identical proofs, which is useful for tests and for replaying proving bugs. Note that the
+    /// values of blinding gates are part of the witness, so a replay needs the same `Witness`, not
+    /// just the same inputs.
+    ///
+    /// Proofs are only zero-knowledge if the seed is secret and never reused.
+    pub fn generate_proof_deterministic<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        witness: &Witness<C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        seed: u64,
+    ) -> Result<Proof<C>> {
+        let challenger = Challenger::new(self.security_bits);
+        let mut rng = seeded_rng(seed);
+        self.generate_proof_with_rng::<InnerC, _, _>(witness, old_proofs, challenger, &mut rng, &ProverConfig::default(), &NoopObserver)
+    }
+
+    /// Like `generate_proof`, but with settings tuned for the machine the prover runs on. All of the
+    /// prover's parallel work runs in the thread pool for `config`; see `ProverConfig::install`.
+    pub fn generate_proof_with_config<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        witness: &Witness<C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        config: &ProverConfig,
+    ) -> Result<Proof<C>> {
+        config.install(|| {
+            let challenger = Challenger::new(self.security_bits);
+            self.generate_proof_with_rng::<InnerC, _, _>(witness, old_proofs, challenger, &mut blinding_rng(), config, &NoopObserver)
+        })?
+    }
+
+    /// Like `generate_proof_with_config`, but notifies `observer` as proving moves through each
+    /// `ProverPhase`, e.g. to report progress or to profile each phase.
+    pub fn generate_proof_with_observer<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        witness: &Witness<C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        config: &ProverConfig,
+        observer: &dyn ProverObserver,
+    ) -> Result<Proof<C>> {
+        config.install(|| {
+            let challenger = Challenger::new(self.security_bits);
+            self.generate_proof_with_rng::<InnerC, _, _>(witness, old_proofs, challenger, &mut blinding_rng(), config, observer)
+        })?
+    }
+
+    /// Generates a proof for each witness, without old proofs. This is faster than proving each
+    /// witness in turn, since proofs are generated concurrently, so that one proof's parallel
+    /// phases can use cores left idle by another's serial phases. The FFT and MSM precomputations
+    /// and the preprocessed polynomials are shared by all proofs.
+    ///
+    /// At most one proof per thread is in progress at a time, which bounds memory usage.
+    pub fn prove_batch<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        witnesses: &[Witness<C::ScalarField>],
+    ) -> Result<Vec<Proof<C>>> {
+        self.prove_batch_with_config::<InnerC>(witnesses, &ProverConfig::default())
+    }
+
+    /// Like `prove_batch`, but with settings tuned for the machine the prover runs on. See
+    /// `generate_proof_with_config`.
+    pub fn prove_batch_with_config<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        witnesses: &[Witness<C::ScalarField>],
+        config: &ProverConfig,
+    ) -> Result<Vec<Proof<C>>> {
+        config.install(|| {
+            let custom_msm_precomputation = self.custom_msm_precomputation(config);
+            let pedersen_g_msm_precomputation =
+                custom_msm_precomputation.as_ref().unwrap_or(&self.pedersen_g_msm_precomputation);
+            let prove = |witness: &Witness<C::ScalarField>| {
+                let challenger = Challenger::new(self.security_bits);
+                self.generate_proof_with_msm_precomputation::<InnerC, _, _>(
+                    witness,
+                    &[],
+                    challenger,
+                    &mut blinding_rng(),
+                    pedersen_g_msm_precomputation,
+                    &NoopObserver,
+                )
+            };
+
+            #[cfg(feature = "parallel")]
+            let batch_size = rayon::current_num_threads();
+            #[cfg(not(feature = "parallel"))]
+            let batch_size = 1;
+            let mut proofs = Vec::with_capacity(witnesses.len());
+            for batch in witnesses.chunks(batch_size) {
+                #[cfg(feature = "parallel")]
+                let batch_proofs = batch.par_iter().map(prove).collect::<Result<Vec<_>>>()?;
+                #[cfg(not(feature = "parallel"))]
+                let batch_proofs = batch.iter().map(prove).collect::<Result<Vec<_>>>()?;
+                proofs.extend(batch_proofs);
+            }
+            Ok(proofs)
+        })?
+    }
+
+    /// A precomputation with the configured MSM window, if it differs from the key's.
+    fn custom_msm_precomputation(&self, config: &ProverConfig) -> Option<MsmPrecomputation<C>> {
+        config
+            .msm_window
+            .filter(|&w| w != self.pedersen_g_msm_precomputation.window())
+            .map(|w| msm_precompute(&AffinePoint::batch_to_projective(&self.pedersen_g), w))
+    }
+
+    fn generate_proof_with_rng<
+        InnerC: HaloCurve<BaseField = C::ScalarField>,
+        T: Transcript<C::BaseField>,
+        R: Rng,
+    >(
+        &self,
+        witness: &Witness<C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        challenger: T,
+        rng: &mut R,
+        config: &ProverConfig,
+        observer: &dyn ProverObserver,
+    ) -> Result<Proof<C>> {
+        let custom_msm_precomputation = self.custom_msm_precomputation(config);
+        let pedersen_g_msm_precomputation =
+            custom_msm_precomputation.as_ref().unwrap_or(&self.pedersen_g_msm_precomputation);
+        self.generate_proof_with_msm_precomputation::<InnerC, _, _>(
+            witness,
+            old_proofs,
+            challenger,
+            rng,
+            pedersen_g_msm_precomputation,
+            observer,
+        )
+    }
+
+    fn generate_proof_with_msm_precomputation<
+        InnerC: HaloCurve<BaseField = C::ScalarField>,
+        T: Transcript<C::BaseField>,
+        R: Rng,
+    >(
+        &self,
+        witness: &Witness<C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        mut challenger: T,
+        rng: &mut R,
+        pedersen_g_msm_precomputation: &MsmPrecomputation<C>,
+        observer: &dyn ProverObserver,
+    ) -> Result<Proof<C>> {
+        // Observe the statement, i.e. the circuit and the public inputs, before any prover message.
+        observe_statement(
+            &mut challenger,
+            &self.c_constants,
+            &self.c_s_sigmas,
+            &[
+                self.degree(),
+                self.num_wires,
+                self.num_routed_wires,
+                self.num_public_inputs,
+                self.num_gates_without_pis,
+            ],
+            self.public_inputs_iter(witness),
+        )
+        .context("Public inputs should fit in both fields")?;
 
+        let phase = PhaseTimer::start(observer, ProverPhase::WireCommitments);
         // Convert the witness both to coefficient form and a degree-8n LDE.
         let wire_values_by_wire_index = &witness.transpose();
         let wire_polynomials =
             values_to_polynomials(&wire_values_by_wire_index, &self.fft_precomputation_n);
-        let wire_values_8n =
-            polynomials_to_values_padded(&wire_polynomials, &self.fft_precomputation_8n);
 
         // Commit to the wire polynomials.
         let c_wires = commit_polynomials(
             &wire_polynomials,
-            &self.pedersen_g_msm_precomputation,
+            pedersen_g_msm_precomputation,
             self.pedersen_h,
-            blinding_commitments,
+            self.zero_knowledge,
+            rng,
         );
 
         let num_public_input_gates = ceil_div_usize(self.num_public_inputs, NUM_WIRES);
@@ -116,6 +395,7 @@ impl<C: HaloCurve> Circuit<C> {
             });
             values_to_polynomials(&wire_values_by_wire_no_pis, &self.fft_precomputation_n)
         };
+        phase.finish();
 
         // Generate a random beta and gamma from the transcript.
         challenger
@@ -124,45 +404,67 @@ impl<C: HaloCurve> Circuit<C> {
         let beta_sf = beta_bf.try_convert::<C::ScalarField>()?;
         let gamma_sf = gamma_bf.try_convert::<C::ScalarField>()?;
 
-        let plonk_z_points_n = permutation_polynomial(
-            self.degree(),
-            &self.subgroup_n,
-            &witness,
-            &self.s_sigma_values_8n,
-            beta_sf,
-            gamma_sf,
-        );
+        let phase = PhaseTimer::start(observer, ProverPhase::PermutationProduct);
+        let plonk_z_points_n = if self.low_memory {
+            let s_sigma_values_n = self
+                .s_sigma_polynomials
+                .iter()
+                .map(|p| p.eval_domain(&self.fft_precomputation_n))
+                .collect::<Vec<_>>();
+            permutation_polynomial_with_stride(
+                self.degree(),
+                &self.subgroup_n,
+                witness,
+                &s_sigma_values_n,
+                1,
+                beta_sf,
+                gamma_sf,
+            )
+        } else {
+            permutation_polynomial(
+                self.degree(),
+                &self.subgroup_n,
+                witness,
+                &self.s_sigma_values_8n,
+                beta_sf,
+                gamma_sf,
+            )
+        };
         // Commit to Z.
         let plonk_z_polynomial =
             Polynomial::from_evaluations(&plonk_z_points_n, &self.fft_precomputation_n);
         let c_plonk_z = plonk_z_polynomial.commit(
-            &self.pedersen_g_msm_precomputation,
+            pedersen_g_msm_precomputation,
             self.pedersen_h,
-            blinding_commitments,
+            self.zero_knowledge,
+            rng,
         );
+        phase.finish();
 
         // Generate a random alpha from the transcript.
         challenger.observe_affine_point(c_plonk_z.to_affine());
         let alpha_bf = challenger.get_challenge();
         let alpha_sf = alpha_bf.try_convert::<C::ScalarField>()?;
 
+        let phase = PhaseTimer::start(observer, ProverPhase::Quotient);
         // Generate the vanishing polynomial.
         let vanishing_poly = self.vanishing_poly::<InnerC>(
-            &wire_values_8n,
+            &wire_polynomials,
             alpha_sf,
             beta_sf,
             gamma_sf,
-            &plonk_z_polynomial[..],
+            &plonk_z_polynomial,
         );
 
-        if cfg!(debug_assertions) {
-            // Check that the vanishing polynomial indeed vanishes.
-            self.subgroup_n.iter().enumerate().for_each(|(i, &x)| {
-                assert!(
-                    vanishing_poly.eval(x).is_zero(),
-                    "{}-th gate constraints are not satisfied",
-                    i
-                );
+        // Check that the vanishing polynomial indeed vanishes, i.e. that the witness satisfies the
+        // constraints. Otherwise it isn't divisible by Z_H, and there's no valid quotient.
+        if let Some(gate) = self.first_nonvanishing_gate(&vanishing_poly) {
+            // The key has no context spans, so this names the gate type only. See
+            // `describe_constraint_failures` for a report with contexts and target names.
+            return Err(PlonkyError::UnsatisfiedConstraints { gate }).with_context(|| {
+                let gate_type = gate_name::<C, InnerC>(&self.gate_constants[gate])
+                    .unwrap_or("unknown gate type");
+                format!("Constraints of gate {}: {} are not satisfied", gate, gate_type)
             });
         }
 
@@ -182,18 +484,15 @@ impl<C: HaloCurve> Circuit<C> {
         plonk_t_poly.pad(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER * self.degree());
 
         // Split t into degree-n chunks.
-        let plonk_t_poly_chunks = plonk_t_poly
-            .coeffs()
-            .chunks(self.degree())
-            .map(|chunk| Polynomial::from(chunk.to_vec()))
-            .collect::<Vec<_>>();
+        let plonk_t_poly_chunks = plonk_t_poly.chunks(self.degree());
 
         // Commit to the quotient polynomial.
         let c_plonk_t = commit_polynomials(
             &plonk_t_poly_chunks,
-            &self.pedersen_g_msm_precomputation,
+            pedersen_g_msm_precomputation,
             self.pedersen_h,
-            blinding_commitments,
+            self.zero_knowledge,
+            rng,
         );
 
         // Combine the coefficients in `wires_coeffs_no_pis` using a linear combination weighted by `alpha`.
@@ -202,26 +501,16 @@ impl<C: HaloCurve> Circuit<C> {
         // `vanishing_pis_poly` vanishes at the public input gates. It is thus divisible by the vanishing
         // polynomial at the public input gates. The quotient is computed here.
         let pis_quotient_poly = {
-            // The vanishing polynomial of a set `S` is `prod_{s \in S} (X-s)`.
-            // TODO: Faster implementation.
-            let pis_quotient_denominator = (0..num_public_input_gates).fold(
-                Polynomial::from(vec![C::ScalarField::ONE]),
-                |acc, i| {
-                    let mut ans =
-                        acc.mul(&vec![-self.subgroup_n[self.num_gates_without_pis + 2 * i], C::ScalarField::ONE].into());
-                    ans.trim();
-                    ans
-                },
-            );
-            let mut ans = vanishing_pis_poly
-                .polynomial_division(&pis_quotient_denominator)
-                .0;
+            let pi_gate_points = (0..num_public_input_gates)
+                .map(|i| self.subgroup_n[self.num_gates_without_pis + 2 * i])
+                .collect::<Vec<_>>();
+            let mut ans = vanishing_pis_poly.divide_by_vanishing(&pi_gate_points);
             if cfg!(debug_assertions) {
                 // Check that division was performed correctly by evaluating at a random point.
                 let x = C::ScalarField::rand();
                 assert_eq!(
                     ans.eval(x),
-                    vanishing_pis_poly.eval(x) / pis_quotient_denominator.eval(x)
+                    vanishing_pis_poly.eval(x) / Polynomial::from_roots(&pi_gate_points).eval(x)
                 );
             }
             ans.pad(self.degree());
@@ -229,34 +518,29 @@ impl<C: HaloCurve> Circuit<C> {
         };
         // Commit to the public inputs quotient polynomial.
         let c_pis_quotient = pis_quotient_poly.commit(
-            &self.pedersen_g_msm_precomputation,
+            pedersen_g_msm_precomputation,
             self.pedersen_h,
-            blinding_commitments,
+            self.zero_knowledge,
+            rng,
         );
-
-        let public_inputs = (0..self.num_public_inputs)
-            .map(|i| wire_values_by_wire_index[i % NUM_WIRES][self.num_gates_without_pis + 2 * (i / NUM_WIRES)])
-            .collect::<Vec<_>>();
+        phase.finish();
 
         // Observe the `t` polynomial commitment.
         challenger
             .observe_affine_points(&PolynomialCommitment::commitments_to_affine_vec(&c_plonk_t));
-        // If the proof doesn't output public inputs, observe the `pis_quotient` polynomial commitment and the public inputs.
+        // Observe the `pis_quotient` polynomial commitment.
         challenger.observe_affine_point(c_pis_quotient.to_affine());
-        // Observe the public inputs
-        challenger.observe_elements(
-            &C::ScalarField::try_convert_all(&public_inputs)
-                .expect("Public inputs should fit in both fields"),
-        );
         // Observe the old proofs' `G` points.
         old_proofs
             .iter()
             .for_each(|old_proof| challenger.observe_affine_point(old_proof.halo_g));
         // Generate a random `zeta` from the transcript.
         let zeta_bf = challenger.get_challenge();
-        let zeta_sf =
-            C::try_convert_b2s(zeta_bf).expect("should fit in both fields with high probability");
+        let zeta_sf = C::try_convert_b2s(zeta_bf).map_err(|_| {
+            PlonkyError::FieldConversion("zeta doesn't fit in the scalar field".to_string())
+        })?;
 
+        let phase = PhaseTimer::start(observer, ProverPhase::Opening);
         // Open all polynomials at zeta, zeta * g, and zeta * g^65.
         let o_local = self.open_all_polynomials(
             &wire_polynomials,
@@ -291,14 +575,16 @@ impl<C: HaloCurve> Circuit<C> {
             .map(|os| os.to_vec())
             .collect::<Vec<_>>()
             .concat();
-        let all_opened_values_bf: Vec<_> = all_opened_values_sf
+        let all_opened_values_bf = all_opened_values_sf
             .into_iter()
             .map(|f| {
-                // TODO: Fix this, this can regularly fail if a public input is not in range for example.
-                C::try_convert_s2b(f)
-                    .expect("For now, we assume that all opened values fit in both fields")
+                C::try_convert_s2b(f).map_err(|_| {
+                    PlonkyError::FieldConversion(
+                        "An opened value doesn't fit in the base field".to_string(),
+                    )
+                })
             })
-            .collect();
+            .collect::<std::result::Result<Vec<_>, _>>()?;
 
         // Generate random v, u, and x from the transcript.
         challenger.observe_elements(&all_opened_values_bf);
@@ -323,8 +609,8 @@ impl<C: HaloCurve> Circuit<C> {
         .concat();
 
         let commitments = [
-            self.c_constants.clone(),
-            self.c_s_sigmas.clone(),
+            self.c_constants.iter().map(|&c| c.into()).collect(),
+            self.c_s_sigmas.iter().map(|&c| c.into()).collect(),
             c_wires.clone(),
             vec![c_plonk_z],
             c_plonk_t.clone(),
@@ -353,11 +639,18 @@ impl<C: HaloCurve> Circuit<C> {
             v_sf,
             u_scaling_sf,
             self.degree(),
-            self.security_bits,
+            self.challenge_len,
             &mut challenger,
+            rng,
         )?;
+        phase.finish();
 
         Ok(Proof {
+            parameters: ProofParameters::new::<C>(
+                challenger.hash_name(),
+                self.num_wires,
+                self.security_params(),
+            ),
             c_wires: c_wires.iter().map(|c| c.to_affine()).collect(),
             c_plonk_z: c_plonk_z.to_affine(),
             c_plonk_t: c_plonk_t.iter().map(|c| c.to_affine()).collect(),
@@ -372,87 +665,177 @@ impl<C: HaloCurve> Circuit<C> {
         })
     }
 
+    /// The index of the first gate at which `vanishing_poly` doesn't vanish, if any. The
+    /// polynomial is reduced modulo `X^n - 1` first, which preserves its values on the subgroup, so
+    /// that they can be computed with a single size-n FFT.
+    fn first_nonvanishing_gate(&self, vanishing_poly: &Polynomial<C::ScalarField>) -> Option<usize> {
+        let n = self.degree();
+        let mut reduced = vec![C::ScalarField::ZERO; n];
+        for (i, &c) in vanishing_poly.iter().enumerate() {
+            reduced[i % n] = reduced[i % n] + c;
+        }
+        Polynomial::from(reduced)
+            .eval_domain(&self.fft_precomputation_n)
+            .iter()
+            .position(|v| v.is_nonzero())
+    }
+
+    /// Computes the vanishing polynomial by evaluating it on the degree-8n subgroup, then
+    /// interpolating. It's evaluated on each coset `w^c H` of the degree-n subgroup `H` in turn,
+    /// where `w` generates the degree-8n subgroup. Since `H` is closed under multiplication by `g`,
+    /// a coset contains the "right" and "below" neighbors of each of its points, so only degree-n
+    /// evaluations of the wire polynomials and Z, for a single coset, are held at once.
+    ///
+    /// The constant and permutation polynomials' values are read from the key's degree-8n LDEs, or
+    /// in low-memory mode, where the key has none, are also computed one coset at a time.
     fn vanishing_poly<InnerC: HaloCurve<BaseField = C::ScalarField>>(
         &self,
-        wire_values_8n: &[Vec<C::ScalarField>],
+        wire_polynomials: &[Polynomial<C::ScalarField>],
         alpha_sf: C::ScalarField,
         beta_sf: C::ScalarField,
         gamma_sf: C::ScalarField,
-        plonk_z_coeffs: &[C::ScalarField],
+        plonk_z_polynomial: &Polynomial<C::ScalarField>,
     ) -> Polynomial<C::ScalarField> {
         let degree = self.degree();
-        let k_is = (0..NUM_ROUTED_WIRES)
+        let k_is = (0..self.num_routed_wires)
             .map(get_subgroup_shift::<C::ScalarField>)
             .collect::<Vec<_>>();
-        // Low degree extend Z.
-        let plonk_z_points_8n = fft_with_precomputation_power_of_2(
-            &pad_to_8n(&plonk_z_coeffs),
-            &self.fft_precomputation_8n,
-        );
-
-        // We will evaluate the vanishing polynomial at 8n points, then interpolate.
-        let vanishing_points = self
-            .subgroup_8n
-            .par_iter()
-            .enumerate()
-            .map(|(i, &x)| {
-                // Load the constant polynomials' values at x.
-                let mut local_constant_values = Vec::new();
-                for j in 0..NUM_CONSTANTS {
-                    local_constant_values.push(self.constants_8n[j][i]);
-                }
+        // The LDEs of different polynomials are independent, so they're computed concurrently.
+        let coset_values = |polys: &[Polynomial<C::ScalarField>], shift: C::ScalarField| {
+            let coset_lde = |p: &Polynomial<C::ScalarField>| p.padded(degree).coset_lde(0, shift);
+            #[cfg(feature = "parallel")]
+            let values = polys.par_iter().map(coset_lde).collect::<Vec<_>>();
+            #[cfg(not(feature = "parallel"))]
+            let values = polys.iter().map(coset_lde).collect::<Vec<_>>();
+            values
+        };
+        let values_at = |polys: &[PolynomialValues<C::ScalarField>], j: usize| {
+            polys.iter().map(|p| p[j]).collect::<Vec<_>>()
+        };
 
-                // Load the wire polynomials' values at x, g x (the "right" position), and g^WIDTH x
-                // (the "below" position). Note that a shift of 1 in the degree-n subgroup corresponds
-                // to a shift of 8 in the degree-8n subgroup.
-                let i_right = (i + 8) % (8 * degree);
-                let i_below = (i + 8 * GRID_WIDTH) % (8 * degree);
-                let mut local_wire_values = Vec::new();
-                let mut right_wire_values = Vec::new();
-                let mut below_wire_values = Vec::new();
-                for j in 0..NUM_WIRES {
-                    local_wire_values.push(wire_values_8n[j][i]);
-                    right_wire_values.push(wire_values_8n[j][i_right]);
-                    below_wire_values.push(wire_values_8n[j][i_below]);
-                }
+        // The point w^c g^j of the degree-8n subgroup has index c + 8 j.
+        let mut vanishing_points = take_buffer(8 * degree);
+        for c in 0..8 {
+            let shift = self.subgroup_8n[c];
+            let wire_values = coset_values(wire_polynomials, shift);
+            let plonk_z_values = plonk_z_polynomial.padded(degree).coset_lde(0, shift);
+            let (constant_values, s_sigma_values) = if self.low_memory {
+                (coset_values(&self.constant_polynomials, shift), coset_values(&self.s_sigma_polynomials, shift))
+            } else {
+                (Vec::new(), Vec::new())
+            };
 
-                let constraint_terms = evaluate_all_constraints::<C, InnerC>(
+            let eval_at = |j: usize| {
+                let i = c + 8 * j;
+                let j_right = (j + 1) % degree;
+                let j_below = (j + GRID_WIDTH) % degree;
+                let (local_constant_values, local_s_sigma_values) = if self.low_memory {
+                    (values_at(&constant_values, j), values_at(&s_sigma_values, j))
+                } else {
+                    (
+                        self.constants_8n.iter().map(|p| p[i]).collect(),
+                        self.s_sigma_values_8n.iter().map(|p| p[i]).collect(),
+                    )
+                };
+                self.eval_vanishing_poly::<InnerC>(
+                    self.subgroup_8n[i],
                     &local_constant_values,
-                    &local_wire_values,
-                    &right_wire_values,
-                    &below_wire_values,
-                );
+                    &values_at(&wire_values, j),
+                    &values_at(&wire_values, j_right),
+                    &values_at(&wire_values, j_below),
+                    &local_s_sigma_values,
+                    plonk_z_values[j],
+                    plonk_z_values[j_right],
+                    &k_is,
+                    alpha_sf,
+                    beta_sf,
+                    gamma_sf,
+                )
+            };
 
-                // Evaluate the L_1(x) (Z(x) - 1) vanishing term.
-                let z_x = plonk_z_points_8n[i];
-                let z_gz = plonk_z_points_8n[i_right];
-                let vanishing_z_1_term = eval_l_1(degree, x) * (z_x - C::ScalarField::ONE);
-
-                // Evaluate the Z(x) f'(x) - g'(x) Z(g x) term.
-                let mut f_prime = C::ScalarField::ONE;
-                let mut g_prime = C::ScalarField::ONE;
-                for j in 0..NUM_ROUTED_WIRES {
-                    let wire_value = wire_values_8n[j][i];
-                    let k_i = k_is[j];
-                    let s_id = k_i * x;
-                    let s_sigma = self.s_sigma_values_8n[j][i];
-                    f_prime = f_prime * (wire_value + beta_sf * s_id + gamma_sf);
-                    g_prime = g_prime * (wire_value + beta_sf * s_sigma + gamma_sf);
+            // Each point's constraints only depend on that point's row and its neighbors, so rows
+            // are evaluated concurrently, in chunks large enough to amortize scheduling overhead.
+            let mut coset_vanishing_points = take_buffer(degree);
+            let eval_chunk = |(chunk_index, chunk): (usize, &mut [C::ScalarField])| {
+                for (k, v) in chunk.iter_mut().enumerate() {
+                    *v = eval_at(chunk_index * VANISHING_EVAL_CHUNK_SIZE + k);
                 }
-                let vanishing_v_shift_term = f_prime * z_x - g_prime * z_gz;
+            };
+            #[cfg(feature = "parallel")]
+            coset_vanishing_points
+                .par_chunks_mut(VANISHING_EVAL_CHUNK_SIZE)
+                .enumerate()
+                .for_each(eval_chunk);
+            #[cfg(not(feature = "parallel"))]
+            coset_vanishing_points
+                .chunks_mut(VANISHING_EVAL_CHUNK_SIZE)
+                .enumerate()
+                .for_each(eval_chunk);
+            for (j, &v) in coset_vanishing_points.iter().enumerate() {
+                vanishing_points[c + 8 * j] = v;
+            }
 
-                let vanishing_terms = [
-                    vec![vanishing_z_1_term],
-                    vec![vanishing_v_shift_term],
-                    constraint_terms,
-                ]
-                .concat();
+            // Discard this coset's evaluations before moving on to the next.
+            recycle_buffer(coset_vanishing_points);
+            constant_values
+                .into_iter()
+                .chain(s_sigma_values)
+                .chain(wire_values)
+                .chain(std::iter::once(plonk_z_values))
+                .for_each(|values| recycle_buffer(values.into_values()));
+        }
 
-                reduce_with_powers(&vanishing_terms, alpha_sf)
-            })
-            .collect::<Vec<_>>();
+        PolynomialValues::from(vanishing_points).into_polynomial()
+    }
+
+    /// Evaluates the vanishing polynomial at `x`, given the values of all relevant polynomials
+    /// there.
+    #[allow(clippy::too_many_arguments)]
+    fn eval_vanishing_poly<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        x: C::ScalarField,
+        local_constant_values: &[C::ScalarField],
+        local_wire_values: &[C::ScalarField],
+        right_wire_values: &[C::ScalarField],
+        below_wire_values: &[C::ScalarField],
+        s_sigma_values: &[C::ScalarField],
+        z_x: C::ScalarField,
+        z_gz: C::ScalarField,
+        k_is: &[C::ScalarField],
+        alpha_sf: C::ScalarField,
+        beta_sf: C::ScalarField,
+        gamma_sf: C::ScalarField,
+    ) -> C::ScalarField {
+        let constraint_terms = evaluate_all_constraints::<C, InnerC>(
+            local_constant_values,
+            local_wire_values,
+            right_wire_values,
+            below_wire_values,
+        );
 
-        Polynomial::from_evaluations(&vanishing_points, &self.fft_precomputation_8n)
+        // Evaluate the L_1(x) (Z(x) - 1) vanishing term.
+        let vanishing_z_1_term = eval_l_1(self.degree(), x) * (z_x - C::ScalarField::ONE);
+
+        // Evaluate the Z(x) f'(x) - g'(x) Z(g x) term.
+        let mut f_prime = C::ScalarField::ONE;
+        let mut g_prime = C::ScalarField::ONE;
+        for j in 0..self.num_routed_wires {
+            let wire_value = local_wire_values[j];
+            let s_id = k_is[j] * x;
+            let s_sigma = s_sigma_values[j];
+            f_prime = f_prime * (wire_value + beta_sf * s_id + gamma_sf);
+            g_prime = g_prime * (wire_value + beta_sf * s_sigma + gamma_sf);
+        }
+        let vanishing_v_shift_term = f_prime * z_x - g_prime * z_gz;
+
+        let vanishing_terms = [
+            vec![vanishing_z_1_term],
+            vec![vanishing_v_shift_term],
+            constraint_terms,
+        ]
+        .concat();
+
+        reduce_with_powers(&vanishing_terms, alpha_sf)
     }
 
     /// Open each polynomial at the given point, `zeta`.
@@ -481,13 +864,51 @@ impl<C: HaloCurve> Circuit<C> {
         }
     }
 
+    pub fn to_vk(&self) -> VerificationKey<C> {
+        VerificationKey {
+            format: KeyFormat::new(),
+            c_constants: self.c_constants.clone(),
+            c_s_sigmas: self.c_s_sigmas.clone(),
+            degree: self.degree(),
+            num_wires: self.num_wires,
+            num_routed_wires: self.num_routed_wires,
+            num_public_inputs: self.num_public_inputs,
+            num_gates_without_pis: self.num_gates_without_pis,
+            security_bits: self.security_bits,
+            challenge_len: self.challenge_len,
+            pedersen_g_msm_precomputation: Some(self.pedersen_g_msm_precomputation.clone()),
+            fft_precomputation: Some((*self.fft_precomputation_n).clone()),
+        }
+    }
+
+    pub fn get_public_inputs(&self, witness: &Witness<C::ScalarField>) -> Vec<C::ScalarField> {
+        self.public_inputs_iter(witness).collect()
+    }
+
+    /// Like `get_public_inputs`, but yields the public inputs one at a time, rather than copying
+    /// them all into a `Vec`.
+    pub fn public_inputs_iter<'a>(
+        &'a self,
+        witness: &'a Witness<C::ScalarField>,
+    ) -> impl Iterator<Item = C::ScalarField> + Clone + 'a {
+        (0..self.num_public_inputs)
+            .map(move |i| witness.get_indices(self.num_gates_without_pis + 2 * (i / NUM_WIRES), i % NUM_WIRES))
+    }
+}
+
+impl<C: HaloCurve> Circuit<C> {
     /// Generates a `PartialWitness`, which maps `Target`s to their values. Although
     /// `PartialWitness` is designed as a sparse representation, the result here should have an
     /// entry for every target in the circuit.
+    ///
+    /// Returns an error if some generators could never be run, since their dependencies were never
+    /// populated.
     pub fn generate_partial_witness(
         &self,
         inputs: PartialWitness<C::ScalarField>,
-    ) -> PartialWitness<C::ScalarField> {
+    ) -> Result<PartialWitness<C::ScalarField>> {
+        // `Instant` is unsupported on wasm32-unknown-unknown, so we don't time witness generation there.
+        #[cfg(not(target_arch = "wasm32"))]
         let start = Instant::now();
 
         // Index generator indices by their dependencies.
@@ -508,12 +929,12 @@ impl<C: HaloCurve> Circuit<C> {
         // Replace public inputs targets by their corresponding wires in the circuit.
         witness.replace_public_inputs(self.num_gates_without_pis);
 
-        let mut copy_result = self.generate_copies(&witness, &witness.all_populated_targets());
+        let mut copy_result = self.generate_copies(&witness, &witness.all_populated_targets())?;
         copy_result.copy_buffer_to_pi_gate(self.num_gates_without_pis);
-        witness.extend(copy_result);
+        witness.try_extend(copy_result)?;
 
         // Build a list of "pending" generators which are ready to run.
-        let mut pending_generator_indices = HashSet::new();
+        let mut pending_generator_indices = BTreeSet::new();
         for (i, generator) in self.generators.iter().enumerate() {
             let generator: &dyn WitnessGenerator<C::ScalarField> = generator.borrow();
             if witness.contains_all_targets(&generator.dependencies()) {
@@ -522,29 +943,46 @@ impl<C: HaloCurve> Circuit<C> {
         }
 
         // We will also keep track of which generators have already run.
-        let mut completed_generator_indices = HashSet::new();
+        let mut completed_generator_indices = BTreeSet::new();
 
         // Now we repeat the following:
-        // - Run all pending generators, keeping track of any targets that were just populated.
+        // - Run all pending generators in parallel, keeping track of any targets that were just
+        //   populated. Since pending generators have all their dependencies satisfied, they are
+        //   independent of one another, so each can read the witness as of the start of the round.
         // - For any newly-set targets, execute any relevant copy constraints, again tracking any
         //   newly-populated targets.
         // - Generate a new set of pending generators based on the newly-populated targets.
         while !pending_generator_indices.is_empty() {
             let mut populated_targets: Vec<Target<C::ScalarField>> = Vec::new();
 
-            for &generator_idx in &pending_generator_indices {
+            let run_generator = |&generator_idx: &usize| {
                 let generator: &dyn WitnessGenerator<C::ScalarField> =
                     self.generators[generator_idx].borrow();
-                let result = generator.generate(&self.gate_constants, &witness);
+                (
+                    generator_idx,
+                    generator.generate(&self.gate_constants, &witness),
+                )
+            };
+            #[cfg(feature = "parallel")]
+            let results = pending_generator_indices.par_iter().map(run_generator).collect::<Vec<_>>();
+            #[cfg(not(feature = "parallel"))]
+            let results = pending_generator_indices.iter().map(run_generator).collect::<Vec<_>>();
+            for (generator_idx, result) in results {
                 populated_targets.extend(result.all_populated_targets());
-                witness.extend(result);
-                completed_generator_indices.insert(generator_idx);
+                witness.try_extend(result).with_context(|| {
+                    format!(
+                        "Generator {} ({}) produced a conflicting value",
+                        generator_idx,
+                        self.generators[generator_idx].name()
+                    )
+                })?;
             }
+            completed_generator_indices.extend(pending_generator_indices.iter().copied());
 
-            let mut copy_result = self.generate_copies(&witness, &populated_targets);
+            let mut copy_result = self.generate_copies(&witness, &populated_targets)?;
             copy_result.copy_buffer_to_pi_gate(self.num_gates_without_pis);
             populated_targets.extend(copy_result.all_populated_targets());
-            witness.extend(copy_result);
+            witness.try_extend(copy_result)?;
 
             // Refresh the set of pending generators.
             pending_generator_indices.clear();
@@ -569,25 +1007,144 @@ impl<C: HaloCurve> Circuit<C> {
             }
         }
 
-        // TODO: Fix this.
-        // debug_assert_eq!(
-        //     completed_generator_indices.len(),
-        //     self.generators.len(),
-        //     "Only {} of {} generators could be run",
-        //     completed_generator_indices.len(),
-        //     self.generators.len()
-        // );
+        // If some generators never became ready, generation stalled, either because an input was
+        // never set or because of a dependency cycle.
+        if completed_generator_indices.len() < self.generators.len() {
+            self.stalled_generators_error(&witness, &completed_generator_indices)?;
+        }
 
+        #[cfg(not(target_arch = "wasm32"))]
         info!("Witness generation took {}s", start.elapsed().as_secs_f32());
-        witness
+        Ok(witness)
     }
 
     pub fn generate_witness(
         &self,
         inputs: PartialWitness<C::ScalarField>,
-    ) -> Witness<C::ScalarField> {
-        let partial_witness = self.generate_partial_witness(inputs);
-        Witness::from_partial(&partial_witness, self.degree())
+    ) -> Result<Witness<C::ScalarField>> {
+        let partial_witness = self.generate_partial_witness(inputs)?;
+        Ok(Witness::from_partial(
+            &partial_witness,
+            self.degree(),
+            self.num_wires,
+        ))
+    }
+
+    /// Like `generate_witness`, but first checks that every wire which a constraint or a copy
+    /// constraint refers to was populated, and otherwise lists the missing wires. See
+    /// `check_witness_complete`.
+    pub fn generate_witness_checked<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        inputs: PartialWitness<C::ScalarField>,
+    ) -> Result<Witness<C::ScalarField>> {
+        let partial_witness = self.generate_partial_witness(inputs)?;
+        check_witness_complete::<C, InnerC>(self, &partial_witness)?;
+        Ok(Witness::from_partial(
+            &partial_witness,
+            self.degree(),
+            self.num_wires,
+        ))
+    }
+
+    /// Describes the given gate for error messages: its type and, if it belongs to a component
+    /// instance, the innermost such component.
+    pub(crate) fn gate_label<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        &self,
+        gate: usize,
+    ) -> String {
+        let gate_type = gate_name::<C, InnerC>(&self.gate_constants[gate]).unwrap_or("unknown gate type");
+        let component = self
+            .component_instances
+            .iter()
+            .filter(|(gates, _)| gates.contains(&gate))
+            .min_by_key(|(gates, _)| gates.len())
+            .map(|&(_, name)| name);
+        let mut label = match component {
+            Some(name) => format!("gate {}: {} in {}", gate, gate_type, name),
+            None => format!("gate {}: {}", gate, gate_type),
+        };
+        if let Some(context) = self.context_of(gate) {
+            label += &format!(" ({})", context);
+        }
+        label
+    }
+
+    /// The path of the innermost context the given gate was added within, if any.
+    pub fn context_of(&self, gate: usize) -> Option<&str> {
+        self.context_spans
+            .iter()
+            .filter(|(gates, _)| gates.contains(&gate))
+            .min_by_key(|(gates, _)| gates.len())
+            .map(|(_, path)| path.as_str())
+    }
+
+    /// Describes a target for error messages, including its name if it, or a target it's
+    /// copy-constrained to, has one.
+    pub fn target_label(&self, target: Target<C::ScalarField>) -> String {
+        let name = self.target_names.get(&target).or_else(|| {
+            self.routing_target_partitions
+                .try_get_partition(target)?
+                .iter()
+                .find_map(|sibling| self.target_names.get(sibling))
+        });
+        match name {
+            Some(name) => format!("{:?} ({})", target, name),
+            None => format!("{:?}", target),
+        }
+    }
+
+    /// Like `generate_witness`, but notifies `observer` of the `WitnessGeneration` phase.
+    pub fn generate_witness_with_observer(
+        &self,
+        inputs: PartialWitness<C::ScalarField>,
+        observer: &dyn ProverObserver,
+    ) -> Result<Witness<C::ScalarField>> {
+        let phase = PhaseTimer::start(observer, ProverPhase::WitnessGeneration);
+        let witness = self.generate_witness(inputs)?;
+        phase.finish();
+        Ok(witness)
+    }
+
+    /// Returns an error describing each generator which could not be run, along with the
+    /// dependencies it was missing.
+    fn stalled_generators_error(
+        &self,
+        witness: &PartialWitness<C::ScalarField>,
+        completed_generator_indices: &BTreeSet<usize>,
+    ) -> Result<()> {
+        // Listing every blocked generator of a large circuit would be unreadable.
+        const MAX_REPORTED_GENERATORS: usize = 10;
+
+        let blocked_generator_indices = (0..self.generators.len())
+            .filter(|i| !completed_generator_indices.contains(i))
+            .collect::<Vec<_>>();
+        let mut msg = format!(
+            "Witness generation stalled: {} of {} generators could not be run.",
+            blocked_generator_indices.len(),
+            self.generators.len()
+        );
+        for &i in blocked_generator_indices.iter().take(MAX_REPORTED_GENERATORS) {
+            let generator: &dyn WitnessGenerator<C::ScalarField> = self.generators[i].borrow();
+            let missing_targets = generator
+                .dependencies()
+                .into_iter()
+                .filter(|&t| !witness.contains_target(t))
+                .map(|t| self.target_label(t))
+                .collect::<Vec<_>>();
+            msg += &format!(
+                "\nGenerator {} ({}) is missing {}",
+                i,
+                generator.name(),
+                missing_targets.join(", ")
+            );
+        }
+        if blocked_generator_indices.len() > MAX_REPORTED_GENERATORS {
+            msg += &format!(
+                "\n...and {} more.",
+                blocked_generator_indices.len() - MAX_REPORTED_GENERATORS
+            );
+        }
+        Err(PlonkyError::WitnessGenerationStalled(msg).into())
     }
 
     /// For the given set of targets, find any copy constraints involving those targets and populate
@@ -596,7 +1153,7 @@ impl<C: HaloCurve> Circuit<C> {
         &self,
         witness: &PartialWitness<C::ScalarField>,
         targets: &[Target<C::ScalarField>],
-    ) -> PartialWitness<C::ScalarField> {
+    ) -> Result<PartialWitness<C::ScalarField>> {
         let mut result = PartialWitness::new();
 
         for &target in targets {
@@ -604,42 +1161,24 @@ impl<C: HaloCurve> Circuit<C> {
             let partition = self.routing_target_partitions.get_partition(target);
 
             for &sibling in partition {
-                if witness.contains_target(sibling) {
+                match witness.try_get_target(sibling) {
                     // This sibling's value was already set; make sure it has the same value.
-                    debug_assert_eq!(witness.get_target(sibling), value);
-                } else {
-                    result.set_target(sibling, value);
+                    Some(sibling_value) if sibling_value != value => {
+                        return Err(PlonkyError::ConflictingValues(format!(
+                            "Copy constraint violated: {} has value {:?} but its copy {} has value {:?}",
+                            self.target_label(target),
+                            value,
+                            self.target_label(sibling),
+                            sibling_value
+                        ))
+                        .into())
+                    }
+                    Some(_) => {}
+                    None => result.try_set_target(sibling, value)?,
                 }
             }
         }
-        result
-    }
-
-    pub fn to_vk(&self) -> VerificationKey<C> {
-        VerificationKey {
-            c_constants: self
-                .c_constants
-                .iter()
-                .map(|c| c.to_affine())
-                .collect::<Vec<_>>(),
-            c_s_sigmas: self
-                .c_s_sigmas
-                .iter()
-                .map(|c| c.to_affine())
-                .collect::<Vec<_>>(),
-            degree: self.degree(),
-            num_public_inputs: self.num_public_inputs,
-            num_gates_without_pis: self.num_gates_without_pis,
-            security_bits: self.security_bits,
-            pedersen_g_msm_precomputation: Some(self.pedersen_g_msm_precomputation.clone()),
-            fft_precomputation: Some(self.fft_precomputation_n.clone()),
-        }
-    }
-
-    pub fn get_public_inputs(&self, witness: &Witness<C::ScalarField>) -> Vec<C::ScalarField> {
-        (0..self.num_public_inputs)
-            .map(|i| witness.get_indices(self.num_gates_without_pis + 2 * (i / NUM_WIRES), i % NUM_WIRES))
-            .collect()
+        Ok(result)
     }
 }
 
@@ -648,3 +1187,9 @@ impl<C: HaloCurve> Debug for Circuit<C> {
         write!(f, "Circuit of size {}.", self.degree())
     }
 }
+
+impl<C: HaloCurve> Debug for ProverKey<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Prover key of size {}.", self.degree())
+    }
+}
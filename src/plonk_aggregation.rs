@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use anyhow::{ensure, Result};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::util::pad_to_multiple_usize;
+use crate::{pcd_circuit, verify_pcd, Curve, HaloCurve, PartialWitness, PcdCircuit, PcdLayout, PcdProof, Proof, VerificationKey};
+
+/// The number of proofs verified by each node of an aggregation tree built by `aggregate`.
+pub const AGGREGATION_ARITY: usize = 2;
+
+/// Builds a node of an aggregation tree, i.e. a node circuit of proof-carrying data which verifies
+/// `arity` proofs of the circuit with the given verification key, with an empty message. If that
+/// circuit is itself an aggregation node, `inner_layout` should be its layout.
+///
+/// The verifications share the node's constants, such as the commitments of `inner_vk`, and the
+/// node's proof folds the accumulators of all of them, so the level above verifies a single proof
+/// in their place.
+pub fn aggregation_circuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    inner_vk: &VerificationKey<InnerC>,
+    inner_layout: Option<PcdLayout>,
+    arity: usize,
+) -> PcdCircuit<C, InnerC> {
+    // The aggregated proofs' public inputs are left for the verifier to know by other means.
+    pcd_circuit(inner_vk, inner_layout, arity, |_builder, messages| {
+        for message in messages {
+            for i in 0..message.len() {
+                message.leave_unbound(i);
+            }
+        }
+        Vec::new()
+    })
+}
+
+/// The root of an aggregation tree, with the data needed to complete the verification of every
+/// proof in the tree.
+pub struct Aggregation<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
+    pub pcd_proof: PcdProof<C>,
+    pub vk: VerificationKey<C>,
+    pub layout: PcdLayout,
+    /// The verification key of the proofs verified by the root.
+    pub inner_vk: VerificationKey<InnerC>,
+}
+
+impl<C, InnerC> Aggregation<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    /// Verifies the root proof, then natively checks the assumptions it deferred about the proofs
+    /// it verified, as in `verify_pcd`.
+    pub fn verify(&self) -> Result<()> {
+        verify_pcd(&self.pcd_proof, &self.vk, self.layout, &self.inner_vk)
+    }
+}
+
+/// The result of `aggregate`. Since the curves of a cycle alternate between the levels of the
+/// tree, the root proof is over `C` if the tree has an odd number of levels, and over `InnerC`
+/// otherwise.
+pub enum AggregateProof<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    Odd(Aggregation<C, InnerC>),
+    Even(Aggregation<InnerC, C>),
+}
+
+impl<C, InnerC> AggregateProof<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    pub fn verify(&self) -> Result<()> {
+        match self {
+            AggregateProof::Odd(aggregation) => aggregation.verify(),
+            AggregateProof::Even(aggregation) => aggregation.verify(),
+        }
+    }
+}
+
+/// Compresses proofs of the circuit with the given verification key into a single proof, by
+/// building a tree of aggregation nodes, each of which verifies `AGGREGATION_ARITY` proofs of the
+/// level below. The nodes of each level are proved in parallel. If a level has a number of proofs
+/// which isn't a multiple of the arity, its last proof is repeated.
+///
+/// As with `recursive_verification_circuit`, the public inputs of the verified proofs are bound by
+/// the Fiat-Shamir challenges but not exported, so a verifier of the aggregate proof should know
+/// them by other means. The aggregated proofs must not have old proofs.
+pub fn aggregate<C, InnerC>(
+    vk: &VerificationKey<InnerC>,
+    proofs: &[Proof<InnerC>],
+    public_inputs: &[Vec<InnerC::ScalarField>],
+) -> Result<AggregateProof<C, InnerC>>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    aggregate_with_arity(vk, proofs, public_inputs, AGGREGATION_ARITY)
+}
+
+/// Like `aggregate`, but each node verifies a batch of `arity` proofs. A larger arity gives a
+/// shallower tree, and thus fewer recursive proofs overall, at the cost of larger node circuits.
+/// A level with fewer proofs than `arity` is verified by a single node of a smaller arity. In
+/// particular, if `arity` is at least the number of proofs, they are verified in a single batch
+/// by the root.
+pub fn aggregate_with_arity<C, InnerC>(
+    vk: &VerificationKey<InnerC>,
+    proofs: &[Proof<InnerC>],
+    public_inputs: &[Vec<InnerC::ScalarField>],
+    arity: usize,
+) -> Result<AggregateProof<C, InnerC>>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    RecursionContext::new().aggregate(vk, proofs, public_inputs, arity)
+}
+
+/// Identifies an aggregation node circuit by the fingerprint of the verification key of the
+/// proofs it verifies, their layout and its arity, which determine the circuit.
+type NodeKey = ([u8; 32], Option<PcdLayout>, usize);
+
+/// Caches the node circuits of aggregation trees, so that aggregating many batches of proofs of
+/// the same circuit builds each level's circuit once, rather than once per batch. Since the levels
+/// of a tree alternate between the curves of a cycle, it holds node circuits over both.
+pub struct RecursionContext<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    circuits: HashMap<NodeKey, PcdCircuit<C, InnerC>>,
+    inner_circuits: HashMap<NodeKey, PcdCircuit<InnerC, C>>,
+}
+
+impl<C, InnerC> RecursionContext<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    pub fn new() -> Self {
+        RecursionContext {
+            circuits: HashMap::new(),
+            inner_circuits: HashMap::new(),
+        }
+    }
+
+    /// The number of node circuits built so far.
+    pub fn num_circuits(&self) -> usize {
+        self.circuits.len() + self.inner_circuits.len()
+    }
+
+    /// Like `aggregate_with_arity`, but reuses any node circuits this context has already built.
+    pub fn aggregate(
+        &mut self,
+        vk: &VerificationKey<InnerC>,
+        proofs: &[Proof<InnerC>],
+        public_inputs: &[Vec<InnerC::ScalarField>],
+        arity: usize,
+    ) -> Result<AggregateProof<C, InnerC>> {
+        ensure!(arity >= 2, "The arity of an aggregation tree should be at least two");
+        ensure!(proofs.len() >= 2, "Expected at least two proofs to aggregate");
+        ensure!(
+            public_inputs.len() == proofs.len(),
+            "Expected public inputs for each proof"
+        );
+        ensure!(
+            proofs.iter().all(|p| p.o_local.o_old_proofs.is_empty()),
+            "Aggregated proofs must not have old proofs"
+        );
+
+        let mut level = AggregationLevel {
+            vk: vk.clone(),
+            layout: None,
+            proofs: proofs
+                .iter()
+                .zip(public_inputs)
+                .map(|(proof, public_inputs)| PcdProof {
+                    proof: proof.clone(),
+                    public_inputs: public_inputs.clone(),
+                    old_proofs: Vec::new(),
+                })
+                .collect(),
+        };
+        loop {
+            let odd = aggregate_level(&level, arity, &mut self.circuits)?;
+            if odd.proofs.len() == 1 {
+                return Ok(AggregateProof::Odd(odd.into_root(level.vk)));
+            }
+            let even = aggregate_level(&odd, arity, &mut self.inner_circuits)?;
+            if even.proofs.len() == 1 {
+                return Ok(AggregateProof::Even(even.into_root(odd.vk)));
+            }
+            level = even;
+        }
+    }
+}
+
+impl<C, InnerC> Default for RecursionContext<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The proofs at one level of an aggregation tree, which are all proofs of the same circuit.
+struct AggregationLevel<C: HaloCurve> {
+    vk: VerificationKey<C>,
+    /// The layout of the circuit, if it is an aggregation node.
+    layout: Option<PcdLayout>,
+    proofs: Vec<PcdProof<C>>,
+}
+
+impl<C: HaloCurve> AggregationLevel<C> {
+    /// Converts a level with a single proof into the root of the tree.
+    fn into_root<InnerC: HaloCurve<BaseField = C::ScalarField>>(
+        mut self,
+        inner_vk: VerificationKey<InnerC>,
+    ) -> Aggregation<C, InnerC> {
+        Aggregation {
+            pcd_proof: self.proofs.remove(0),
+            vk: self.vk,
+            layout: self.layout.expect("The root is an aggregation node"),
+            inner_vk,
+        }
+    }
+}
+
+/// Aggregates the proofs of a level, in parallel, giving the level above it. If the level has
+/// fewer proofs than `arity`, a single node verifies all of them. The node circuit is taken from
+/// `circuits` if it was built before, and added to it otherwise.
+fn aggregate_level<C, InnerC>(
+    level: &AggregationLevel<InnerC>,
+    arity: usize,
+    circuits: &mut HashMap<NodeKey, PcdCircuit<C, InnerC>>,
+) -> Result<AggregationLevel<C>>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    let num_proofs = level.proofs.len();
+    let arity = arity.min(num_proofs);
+    let circuit = circuits
+        .entry((level.vk.fingerprint(), level.layout, arity))
+        .or_insert_with(|| aggregation_circuit::<C, InnerC>(&level.vk, level.layout, arity));
+    let indices: Vec<usize> = (0..pad_to_multiple_usize(num_proofs, arity))
+        .map(|i| i.min(num_proofs - 1))
+        .collect();
+
+    let prove = |children: &[usize]| {
+        let children: Vec<&PcdProof<InnerC>> = children.iter().map(|&i| &level.proofs[i]).collect();
+        circuit.prove(PartialWitness::new(), &children)
+    };
+
+    #[cfg(feature = "parallel")]
+    let proofs = indices.par_chunks(arity).map(prove).collect::<Result<Vec<_>>>()?;
+    #[cfg(not(feature = "parallel"))]
+    let proofs = indices.chunks(arity).map(prove).collect::<Result<Vec<_>>>()?;
+
+    Ok(AggregationLevel {
+        vk: circuit.circuit.to_vk(),
+        layout: Some(circuit.layout),
+        proofs,
+    })
+}
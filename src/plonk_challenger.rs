@@ -1,84 +1,66 @@
-use crate::{rescue_permutation, AffinePoint, AffinePointTarget, CircuitBuilder, Curve, Field, HaloCurve, ProjectivePoint, Target, RESCUE_SPONGE_RATE, RESCUE_SPONGE_WIDTH};
+use std::marker::PhantomData;
 
-/// Observes prover messages, and generates challenges by hashing the transcript.
-#[derive(Clone)]
-pub struct Challenger<F: Field> {
-    sponge_state: Vec<F>,
-    input_buffer: Vec<F>,
-    output_buffer: Vec<F>,
-    security_bits: usize,
-}
+use num::BigUint;
 
-/// Observes prover messages, and generates verifier challenges based on the transcript.
+use crate::{biguint_to_field, field_to_biguint, seeded_rng_from_bytes, AffinePoint, AlgebraicHasher, AffinePointTarget, CircuitBuilder, Curve, Field, HaloCurve, ProjectivePoint, PoseidonHasher, RescueHasher, Sponge, Target};
+
+/// A Fiat-Shamir transcript, which observes prover messages and generates verifier challenges
+/// based on everything observed so far.
 ///
-/// The implementation is roughly based on a duplex sponge with a Rescue permutation. Note that in
-/// each round, our sponge can absorb an arbitrary number of prover messages and generate an
-/// arbitrary number of verifier challenges. This might appear to diverge from the duplex sponge
-/// design, but it can be viewed as a duplex sponge whose inputs are sometimes zero (when we perform
-/// multiple squeezes) and whose outputs are sometimes ignored (when we perform multiple
-/// absorptions). Thus the security properties of a duplex sponge still apply to our design.
-impl<F: Field> Challenger<F> {
-    pub fn new(security_bits: usize) -> Challenger<F> {
-        Challenger {
-            sponge_state: vec![F::ZERO; RESCUE_SPONGE_WIDTH],
-            input_buffer: Vec::new(),
-            output_buffer: Vec::new(),
-            security_bits,
-        }
-    }
+/// The prover and verifier must use the same implementation. A recursive verifier must also
+/// mirror it in-circuit; `RecursiveChallenger` mirrors the Rescue-based `Challenger`.
+pub trait Transcript<F: Field>: Clone {
+    /// The name of the hash underlying the transcript. Proofs record it, so that a verifier using a
+    /// different transcript can reject them with a descriptive error.
+    fn hash_name(&self) -> &'static str;
 
-    pub fn observe_element(&mut self, element: F) {
-        // Any buffered outputs are now invalid, since they wouldn't reflect this input.
-        self.output_buffer.clear();
+    fn observe_element(&mut self, element: F);
 
-        self.input_buffer.push(element);
+    fn get_challenge(&mut self) -> F;
+
+    /// Observes a domain separation label, so that transcripts of different protocols, or of
+    /// different stages of a protocol, cannot be confused with one another.
+    fn observe_label(&mut self, label: &[u8]) {
+        self.observe_elements(&label_to_elements(label));
     }
 
-    pub fn observe_elements(&mut self, elements: &[F]) {
+    fn observe_elements(&mut self, elements: &[F]) {
         for &element in elements {
             self.observe_element(element);
         }
     }
 
-    pub fn observe_affine_point<C: Curve<BaseField = F>>(&mut self, point: AffinePoint<C>) {
-        debug_assert!(!point.zero);
-        self.observe_element(point.x);
-        self.observe_element(point.y);
+    /// Observes a point's coordinates. Without blinding, commitments to zero polynomials are the
+    /// identity, which is observed as `(0, 0)`; that isn't on any of our curves, so it can't be
+    /// confused with another point.
+    fn observe_affine_point<C: Curve<BaseField = F>>(&mut self, point: AffinePoint<C>) {
+        if point.zero {
+            self.observe_elements(&[F::ZERO, F::ZERO]);
+        } else {
+            self.observe_element(point.x);
+            self.observe_element(point.y);
+        }
     }
 
-    pub fn observe_affine_points<C: Curve<BaseField = F>>(&mut self, points: &[AffinePoint<C>]) {
+    fn observe_affine_points<C: Curve<BaseField = F>>(&mut self, points: &[AffinePoint<C>]) {
         for &point in points {
             self.observe_affine_point(point);
         }
     }
 
-    pub fn observe_proj_point<C: Curve<BaseField = F>>(&mut self, point: ProjectivePoint<C>) {
+    fn observe_proj_point<C: Curve<BaseField = F>>(&mut self, point: ProjectivePoint<C>) {
         self.observe_affine_point(point.to_affine());
     }
 
-    pub fn observe_proj_points<C: Curve<BaseField = F>>(&mut self, points: &[ProjectivePoint<C>]) {
+    fn observe_proj_points<C: Curve<BaseField = F>>(&mut self, points: &[ProjectivePoint<C>]) {
         self.observe_affine_points(&ProjectivePoint::batch_to_affine(points));
     }
 
-    pub fn get_challenge(&mut self) -> F {
-        self.absorb_buffered_inputs();
-
-        if self.output_buffer.is_empty() {
-            // Evaluate the permutation to produce `r` new outputs.
-            self.sponge_state = rescue_permutation(&self.sponge_state, self.security_bits);
-            self.output_buffer = self.sponge_state[0..RESCUE_SPONGE_RATE].to_vec();
-        }
-
-        self.output_buffer
-            .pop()
-            .expect("Output buffer should be non-empty")
-    }
-
-    pub fn get_2_challenges(&mut self) -> (F, F) {
+    fn get_2_challenges(&mut self) -> (F, F) {
         (self.get_challenge(), self.get_challenge())
     }
 
-    pub fn get_3_challenges(&mut self) -> (F, F, F) {
+    fn get_3_challenges(&mut self) -> (F, F, F) {
         (
             self.get_challenge(),
             self.get_challenge(),
@@ -86,44 +68,220 @@ impl<F: Field> Challenger<F> {
         )
     }
 
-    pub fn get_n_challenges(&mut self, n: usize) -> Vec<F> {
+    fn get_n_challenges(&mut self, n: usize) -> Vec<F> {
         (0..n).map(|_| self.get_challenge()).collect()
     }
+}
 
-    /// Absorb any buffered inputs. After calling this, the input buffer will be empty.
-    fn absorb_buffered_inputs(&mut self) {
-        for input_chunk in self.input_buffer.chunks(RESCUE_SPONGE_RATE) {
-            // Add the inputs to our sponge state.
-            for (i, &input) in input_chunk.iter().enumerate() {
-                self.sponge_state[i] = self.sponge_state[i] + input;
-            }
+/// Encodes a label as field elements: its length, followed by its bytes in little-endian chunks
+/// of 8.
+pub(crate) fn label_to_elements<F: Field>(label: &[u8]) -> Vec<F> {
+    let mut elements = vec![F::from_canonical_usize(label.len())];
+    for chunk in label.chunks(8) {
+        let mut bytes = [0u8; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        elements.push(F::from_canonical_u64(u64::from_le_bytes(bytes)));
+    }
+    elements
+}
 
-            // Apply the permutation.
-            self.sponge_state = rescue_permutation(&self.sponge_state, self.security_bits);
+/// Observes prover messages, and generates challenges by hashing the transcript.
+#[derive(Clone)]
+pub struct Challenger<F: Field> {
+    sponge: Sponge<F>,
+    hash_name: &'static str,
+}
+
+/// Observes prover messages, and generates verifier challenges based on the transcript.
+///
+/// Prover messages are absorbed into a `Sponge`, and challenges are squeezed from it. In each
+/// round, the sponge can absorb an arbitrary number of prover messages and generate an arbitrary
+/// number of verifier challenges; see `Sponge` for why this is still a duplex sponge.
+impl<F: Field> Challenger<F> {
+    pub fn new(security_bits: usize) -> Challenger<F> {
+        Self::new_with_hasher::<RescueHasher>(security_bits)
+    }
+
+    /// Creates a challenger whose sponge uses a Poseidon permutation instead of Rescue. It is
+    /// cheaper to evaluate natively, but more expensive to mirror in-circuit.
+    pub fn new_poseidon(security_bits: usize) -> Challenger<F> {
+        Self::new_with_hasher::<PoseidonHasher>(security_bits)
+    }
+
+    /// Creates a challenger whose sponge uses the given hasher. It is mirrored in-circuit by a
+    /// `RecursiveChallenger` with the same hasher.
+    pub fn new_with_hasher<H: AlgebraicHasher<F>>(security_bits: usize) -> Challenger<F> {
+        Challenger {
+            sponge: Sponge::new_with_hasher::<H>(security_bits),
+            hash_name: H::NAME,
+        }
+    }
+}
+
+impl<F: Field> Transcript<F> for Challenger<F> {
+    fn hash_name(&self) -> &'static str {
+        self.hash_name
+    }
+
+    fn observe_element(&mut self, element: F) {
+        self.sponge.absorb(element);
+    }
+
+    fn get_challenge(&mut self) -> F {
+        self.sponge.squeeze()
+    }
+}
+
+// Tags which precede each kind of message hashed by `Blake2bChallenger`, so that messages of
+// different kinds can never be confused.
+const BLAKE2B_ELEMENT_TAG: u8 = 0;
+const BLAKE2B_LABEL_TAG: u8 = 1;
+const BLAKE2B_CHALLENGE_TAG: u8 = 2;
+
+/// A transcript which hashes all prover messages with Blake2b. Each challenge is derived from the
+/// hash of the entire transcript so far, including the previous challenges. This is much faster
+/// natively than an algebraic sponge, but very expensive to verify in-circuit, so it is only
+/// suitable for proofs which won't be verified recursively.
+#[derive(Clone)]
+pub struct Blake2bChallenger<F: Field> {
+    state: blake2b_simd::State,
+    _field: PhantomData<F>,
+}
+
+impl<F: Field> Blake2bChallenger<F> {
+    pub fn new() -> Self {
+        Blake2bChallenger {
+            state: blake2b_simd::Params::new()
+                .hash_length(32)
+                .personal(b"plonky-fs")
+                .to_state(),
+            _field: PhantomData,
         }
+    }
+}
 
-        self.output_buffer = self.sponge_state[0..RESCUE_SPONGE_RATE].to_vec();
+impl<F: Field> Default for Blake2bChallenger<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        self.input_buffer.clear();
+impl<F: Field> Transcript<F> for Blake2bChallenger<F> {
+    fn hash_name(&self) -> &'static str {
+        "Blake2b"
+    }
+
+    fn observe_element(&mut self, element: F) {
+        self.state.update(&[BLAKE2B_ELEMENT_TAG]);
+        self.state.update(&element.to_canonical_u8_vec());
+    }
+
+    fn get_challenge(&mut self) -> F {
+        self.state.update(&[BLAKE2B_CHALLENGE_TAG]);
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(self.state.clone().finalize().as_bytes());
+        // Hashes don't map uniformly to field elements, so we use the hash to seed a CSPRNG, which
+        // then samples a uniform element.
+        F::rand_from_rng(&mut seeded_rng_from_bytes(seed))
+    }
+
+    fn observe_label(&mut self, label: &[u8]) {
+        self.state.update(&[BLAKE2B_LABEL_TAG]);
+        self.state.update(&(label.len() as u64).to_le_bytes());
+        self.state.update(label);
     }
 }
 
-/// A recursive version of `Challenger`.
-pub(crate) struct RecursiveChallenger<F: Field> {
+// Prefixes which halo2's Blake2b transcript hashes before each kind of message.
+const HALO2_BLAKE2B_PREFIX_CHALLENGE: u8 = 0;
+const HALO2_BLAKE2B_PREFIX_POINT: u8 = 1;
+const HALO2_BLAKE2B_PREFIX_SCALAR: u8 = 2;
+
+/// A transcript with the same encoding as halo2's `Blake2bWrite` and `Blake2bRead`, so that
+/// challenges can be cross-checked against halo2 given the same sequence of messages.
+///
+/// Points are hashed as their canonical little-endian coordinates, and other elements as scalars.
+/// Each challenge is the 64-byte hash of the transcript so far, reduced modulo the field order,
+/// as with halo2's `Challenge255`. halo2's transcript has no notion of labels, so labels are not
+/// observed at all.
+#[derive(Clone)]
+pub struct Halo2Blake2bChallenger<F: Field> {
+    state: blake2b_simd::State,
+    _field: PhantomData<F>,
+}
+
+impl<F: Field> Halo2Blake2bChallenger<F> {
+    pub fn new() -> Self {
+        Halo2Blake2bChallenger {
+            state: blake2b_simd::Params::new()
+                .hash_length(64)
+                .personal(b"Halo2-Transcript")
+                .to_state(),
+            _field: PhantomData,
+        }
+    }
+}
+
+impl<F: Field> Default for Halo2Blake2bChallenger<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Field> Transcript<F> for Halo2Blake2bChallenger<F> {
+    fn hash_name(&self) -> &'static str {
+        "Blake2b-halo2"
+    }
+
+    fn observe_element(&mut self, element: F) {
+        self.state.update(&[HALO2_BLAKE2B_PREFIX_SCALAR]);
+        self.state.update(&element.to_canonical_u8_vec());
+    }
+
+    fn get_challenge(&mut self) -> F {
+        self.state.update(&[HALO2_BLAKE2B_PREFIX_CHALLENGE]);
+        let hash = BigUint::from_bytes_le(self.state.clone().finalize().as_bytes());
+        let order = field_to_biguint(F::NEG_ONE) + 1u32;
+        biguint_to_field(hash % order)
+    }
+
+    fn observe_label(&mut self, _label: &[u8]) {}
+
+    fn observe_affine_point<C: Curve<BaseField = F>>(&mut self, point: AffinePoint<C>) {
+        // halo2 refuses to write the point at infinity to a transcript.
+        debug_assert!(!point.zero);
+        self.state.update(&[HALO2_BLAKE2B_PREFIX_POINT]);
+        self.state.update(&point.x.to_canonical_u8_vec());
+        self.state.update(&point.y.to_canonical_u8_vec());
+    }
+}
+
+/// A recursive version of `Challenger`, using the same hasher.
+pub(crate) struct RecursiveChallenger<F: Field, H: AlgebraicHasher<F> = RescueHasher> {
     sponge_state: Vec<Target<F>>,
     input_buffer: Vec<Target<F>>,
     output_buffer: Vec<Target<F>>,
+    _hasher: PhantomData<H>,
 }
 
 impl<F: Field> RecursiveChallenger<F> {
     pub(crate) fn new<C: HaloCurve<ScalarField = F>>(
         builder: &mut CircuitBuilder<C>,
     ) -> RecursiveChallenger<F> {
+        Self::new_with_hasher(builder)
+    }
+}
+
+impl<F: Field, H: AlgebraicHasher<F>> RecursiveChallenger<F, H> {
+    pub(crate) fn new_with_hasher<C: HaloCurve<ScalarField = F>>(
+        builder: &mut CircuitBuilder<C>,
+    ) -> RecursiveChallenger<F, H> {
         let zero = builder.zero_wire();
         RecursiveChallenger {
-            sponge_state: vec![zero; RESCUE_SPONGE_WIDTH],
+            sponge_state: vec![zero; H::WIDTH],
             input_buffer: Vec::new(),
             output_buffer: Vec::new(),
+            _hasher: PhantomData,
         }
     }
 
@@ -134,6 +292,16 @@ impl<F: Field> RecursiveChallenger<F> {
         self.input_buffer.push(target);
     }
 
+    /// Observes a domain separation label, consistent with `Transcript::observe_label`.
+    pub(crate) fn observe_label<C: HaloCurve<ScalarField = F>>(
+        &mut self,
+        builder: &mut CircuitBuilder<C>,
+        label: &[u8],
+    ) {
+        let elements = label_to_elements::<F>(label);
+        self.observe_elements(&builder.constant_wires(&elements));
+    }
+
     pub(crate) fn observe_elements(&mut self, targets: &[Target<F>]) {
         for &target in targets {
             self.observe_element(target);
@@ -165,8 +333,8 @@ impl<F: Field> RecursiveChallenger<F> {
 
         if self.output_buffer.is_empty() {
             // Evaluate the permutation to produce `r` new outputs.
-            self.sponge_state = builder.rescue_permutation(&self.sponge_state);
-            self.output_buffer = self.sponge_state[0..RESCUE_SPONGE_RATE].to_vec();
+            self.sponge_state = H::permute_recursively(builder, &self.sponge_state);
+            self.buffer_outputs();
         }
 
         self.output_buffer
@@ -211,30 +379,52 @@ impl<F: Field> RecursiveChallenger<F> {
         &mut self,
         builder: &mut CircuitBuilder<C>,
     ) {
-        for input_chunk in self.input_buffer.chunks(RESCUE_SPONGE_RATE) {
+        if self.input_buffer.is_empty() {
+            return;
+        }
+
+        for input_chunk in self.input_buffer.chunks(H::RATE) {
             // Add the inputs to our sponge state.
             for (i, &input) in input_chunk.iter().enumerate() {
                 self.sponge_state[i] = builder.add(self.sponge_state[i], input);
             }
 
             // Apply the permutation.
-            self.sponge_state = builder.rescue_permutation(&self.sponge_state);
+            self.sponge_state = H::permute_recursively(builder, &self.sponge_state);
         }
 
-        self.output_buffer = self.sponge_state[0..RESCUE_SPONGE_RATE].to_vec();
+        self.buffer_outputs();
 
         self.input_buffer.clear();
     }
+
+    /// Buffers the rate portion of the sponge state as outputs. They are stored in reverse, so
+    /// that popping them yields them in the order of `Sponge::squeeze`.
+    fn buffer_outputs(&mut self) {
+        self.output_buffer = self.sponge_state[0..H::RATE]
+            .iter()
+            .rev()
+            .copied()
+            .collect();
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::plonk_challenger::{Challenger, RecursiveChallenger};
-    use crate::{CircuitBuilder, Curve, Field, PartialWitness, Target, Tweedledum};
+    use num::BigUint;
+
+    use crate::plonk_challenger::{Blake2bChallenger, Challenger, Halo2Blake2bChallenger, RecursiveChallenger, Transcript};
+    use crate::{biguint_to_field, AlgebraicHasher, CircuitBuilder, Curve, Field, GmimcHasher, Pallas, PallasBase, PartialWitness, PoseidonHasher, RescueHasher, Target, Tweedledum};
 
     /// Tests for consistency between `Challenger` and `RecursiveChallenger`.
     #[test]
     fn test_consistency() {
+        check_consistency::<RescueHasher>();
+        check_consistency::<PoseidonHasher>();
+        check_consistency::<GmimcHasher>();
+    }
+
+    fn check_consistency<H: AlgebraicHasher<<Tweedledum as Curve>::ScalarField>>() {
         type C = Tweedledum;
         type SF = <C as Curve>::ScalarField;
 
@@ -249,7 +439,7 @@ mod tests {
             .map(|&n| (0..n).map(|_| SF::rand()).collect::<Vec<_>>())
             .collect();
 
-        let mut challenger = Challenger::new(128);
+        let mut challenger = Challenger::new_with_hasher::<H>(128);
         let mut outputs_per_round: Vec<Vec<SF>> = Vec::new();
         for (r, inputs) in inputs_per_round.iter().enumerate() {
             challenger.observe_elements(inputs);
@@ -257,7 +447,7 @@ mod tests {
         }
 
         let mut builder = CircuitBuilder::<C>::new(128);
-        let mut recursive_challenger = RecursiveChallenger::new(&mut builder);
+        let mut recursive_challenger = RecursiveChallenger::<_, H>::new_with_hasher(&mut builder);
         let mut recursive_outputs_per_round: Vec<Vec<Target<<C as Curve>::ScalarField>>> =
             Vec::new();
         for (r, inputs) in inputs_per_round.iter().enumerate() {
@@ -267,7 +457,7 @@ mod tests {
             );
         }
         let circuit = builder.build();
-        let witness = circuit.generate_partial_witness(PartialWitness::new());
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
         let recursive_output_values_per_round: Vec<Vec<SF>> = recursive_outputs_per_round
             .iter()
             .map(|outputs| witness.get_targets(outputs))
@@ -275,4 +465,76 @@ mod tests {
 
         assert_eq!(outputs_per_round, recursive_output_values_per_round);
     }
+
+    /// Tests that a label observed in-circuit produces the same challenge as out of circuit.
+    #[test]
+    fn test_label_consistency() {
+        type C = Tweedledum;
+        type SF = <C as Curve>::ScalarField;
+        let label = b"a label longer than one chunk";
+
+        let mut challenger = Challenger::<SF>::new(128);
+        challenger.observe_label(label);
+        let challenge = challenger.get_challenge();
+
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let mut recursive_challenger = RecursiveChallenger::new(&mut builder);
+        recursive_challenger.observe_label(&mut builder, label);
+        let recursive_challenge = recursive_challenger.get_challenge(&mut builder);
+        let circuit = builder.build();
+        let witness = circuit.generate_partial_witness(PartialWitness::new()).unwrap();
+
+        assert_eq!(challenge, witness.get_target(recursive_challenge));
+    }
+
+    fn check_transcript<SF: Field, T: Transcript<SF>>(new_transcript: impl Fn() -> T) {
+        let inputs = (0..5).map(|_| SF::rand()).collect::<Vec<_>>();
+        let challenges = |label: &[u8], inputs: &[SF]| {
+            let mut transcript = new_transcript();
+            transcript.observe_label(label);
+            transcript.observe_elements(inputs);
+            transcript.get_n_challenges(3)
+        };
+
+        let outputs = challenges(b"label", &inputs);
+        assert_eq!(outputs, challenges(b"label", &inputs));
+        assert_ne!(outputs[0], outputs[1]);
+        assert_ne!(outputs, challenges(b"other label", &inputs));
+        assert_ne!(outputs, challenges(b"label", &inputs[1..]));
+    }
+
+    #[test]
+    fn test_rescue_transcript() {
+        check_transcript(|| Challenger::<<Tweedledum as Curve>::ScalarField>::new(128));
+    }
+
+    #[test]
+    fn test_poseidon_transcript() {
+        check_transcript(|| Challenger::<<Tweedledum as Curve>::ScalarField>::new_poseidon(128));
+    }
+
+    #[test]
+    fn test_blake2b_transcript() {
+        check_transcript(Blake2bChallenger::<<Tweedledum as Curve>::ScalarField>::new);
+    }
+
+    /// Checks challenges against ones computed independently with halo2's transcript encoding.
+    #[test]
+    fn test_halo2_blake2b_transcript() {
+        let mut transcript = Halo2Blake2bChallenger::<PallasBase>::new();
+        transcript.observe_affine_point(Pallas::GENERATOR_AFFINE);
+        // Labels aren't part of halo2's transcript.
+        transcript.observe_label(b"label");
+        transcript.observe_element(PallasBase::FIVE);
+
+        let expected = [
+            "20933594321120670762078069023821342087570706865874861212026367840426008915565",
+            "14756749676736804589943722654404993983499036817485112978886326629808142746472",
+        ];
+        let expected = expected
+            .iter()
+            .map(|x| biguint_to_field(BigUint::parse_bytes(x.as_bytes(), 10).unwrap()))
+            .collect::<Vec<PallasBase>>();
+        assert_eq!(transcript.get_n_challenges(2), expected);
+    }
 }
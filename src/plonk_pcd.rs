@@ -0,0 +1,328 @@
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::plonk_recursion::verify_proof_recursively;
+use crate::{carried_accumulators, finalize_recursion, verify_proof, Circuit, CircuitBuilder, Curve, HaloCurve, OldProof, PartialWitness, Proof, RecursionLayout, Target, VerificationKey, VerifiedProofTarget};
+
+/// The shape of a node's public inputs in proof-carrying data: the node's message, followed by
+/// the values exported for each of its predecessors.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PcdLayout {
+    /// The length of the node's message.
+    pub message_len: usize,
+    /// The number of predecessors of the node.
+    pub arity: usize,
+    /// The layout of the public inputs relating to each predecessor.
+    pub layout: RecursionLayout,
+}
+
+impl PcdLayout {
+    pub fn num_public_inputs(&self) -> usize {
+        self.message_len + self.arity * self.layout.num_public_inputs()
+    }
+
+    /// The public inputs relating to each predecessor, given the node's public inputs.
+    fn predecessor_parts<'a, F>(&self, public_inputs: &'a [F]) -> Vec<&'a [F]> {
+        public_inputs[self.message_len..]
+            .chunks(self.layout.num_public_inputs())
+            .collect()
+    }
+}
+
+/// Declarations of how a node circuit binds each value of a predecessor's message. A node's proof
+/// says nothing about its predecessors' messages unless the circuit constrains or exports them, so
+/// `pcd_circuit` panics if any value was left undeclared.
+pub struct PublicInputBindings<C: HaloCurve> {
+    message: Vec<Target<C::ScalarField>>,
+    declared: Vec<bool>,
+    exposed: Vec<Target<C::ScalarField>>,
+}
+
+impl<C: HaloCurve> PublicInputBindings<C> {
+    pub(crate) fn new(message: &[Target<C::ScalarField>]) -> Self {
+        PublicInputBindings {
+            message: message.to_vec(),
+            declared: vec![false; message.len()],
+            exposed: Vec::new(),
+        }
+    }
+
+    /// The length of the predecessor's message.
+    pub fn len(&self) -> usize {
+        self.message.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.message.is_empty()
+    }
+
+    /// Returns the `i`th value, which the caller takes responsibility for constraining, e.g. by
+    /// computing the node's own message from it.
+    pub fn take(&mut self, i: usize) -> Target<C::ScalarField> {
+        self.declared[i] = true;
+        self.message[i]
+    }
+
+    /// Constrains the `i`th value to equal `target`, a target of the node circuit.
+    pub fn bind(&mut self, builder: &mut CircuitBuilder<C>, i: usize, target: Target<C::ScalarField>) {
+        let value = self.take(i);
+        builder.copy(value, target);
+    }
+
+    /// Constrains the `i`th value to equal a constant.
+    pub fn bind_constant(&mut self, builder: &mut CircuitBuilder<C>, i: usize, c: C::ScalarField) {
+        let target = builder.constant_wire(c);
+        self.bind(builder, i, target);
+    }
+
+    /// Re-exports the `i`th value in the node's message.
+    pub fn expose(&mut self, i: usize) {
+        let value = self.take(i);
+        self.exposed.push(value);
+    }
+
+    /// Exports a single hash of the values at `indices` in the node's message, which should be
+    /// compared against `rescue_hash_n_to_1` of the expected values.
+    pub fn expose_hash(&mut self, builder: &mut CircuitBuilder<C>, indices: &[usize]) {
+        let values: Vec<_> = indices.iter().map(|&i| self.take(i)).collect();
+        let hash = builder.rescue_hash_n_to_1(&values);
+        self.exposed.push(hash);
+    }
+
+    /// Declares that the `i`th value needn't be bound, e.g. because the node's verifier knows it
+    /// by other means.
+    pub fn leave_unbound(&mut self, i: usize) {
+        self.declared[i] = true;
+    }
+
+    /// Returns the exported values, after checking that every value was declared.
+    fn finish(self) -> Vec<Target<C::ScalarField>> {
+        let undeclared: Vec<usize> = (0..self.len()).filter(|&i| !self.declared[i]).collect();
+        assert!(
+            undeclared.is_empty(),
+            "The bindings of the predecessor's message values {:?} were not declared",
+            undeclared
+        );
+        self.exposed
+    }
+}
+
+/// A proof of a node in proof-carrying data, with the data its successors need to verify it.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PcdProof<C: HaloCurve> {
+    pub proof: Proof<C>,
+    /// The node's public inputs, which begin with its message.
+    pub public_inputs: Vec<C::ScalarField>,
+    /// The accumulators carried by the node, which its proof folded.
+    pub old_proofs: Vec<OldProof<C>>,
+}
+
+/// A node circuit of proof-carrying data. It verifies a proof of each of its predecessors, which
+/// are proofs of the same circuit, and computes its own message from their messages.
+pub struct PcdCircuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
+    pub circuit: Circuit<C>,
+    pub layout: PcdLayout,
+    pub predecessors: Vec<VerifiedProofTarget<C, InnerC>>,
+}
+
+impl<C, InnerC> PcdCircuit<C, InnerC>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    /// Proves the node, given its predecessors' proofs and any other inputs of its predicate.
+    pub fn prove(
+        &self,
+        mut inputs: PartialWitness<C::ScalarField>,
+        predecessors: &[&PcdProof<InnerC>],
+    ) -> Result<PcdProof<C>> {
+        ensure!(
+            predecessors.len() == self.predecessors.len(),
+            "Expected {} predecessors, got {}",
+            self.predecessors.len(),
+            predecessors.len()
+        );
+        for (target, predecessor) in self.predecessors.iter().zip(predecessors) {
+            target.populate_witness(
+                &mut inputs,
+                &predecessor.proof,
+                &predecessor.public_inputs,
+                &predecessor.old_proofs,
+            )?;
+        }
+        let witness = self.circuit.generate_witness(inputs)?;
+        let public_inputs = self.circuit.get_public_inputs(&witness);
+        let mut old_proofs = Vec::new();
+        for part in self.layout.predecessor_parts(&public_inputs) {
+            old_proofs.extend(carried_accumulators::<InnerC, C>(part, self.layout.layout)?);
+        }
+        let proof = self
+            .circuit
+            .generate_proof::<InnerC>(&witness, &old_proofs)?;
+        Ok(PcdProof {
+            proof,
+            public_inputs,
+            old_proofs,
+        })
+    }
+}
+
+/// Builds a node circuit of proof-carrying data, which verifies `arity` proofs of the circuit with
+/// the given verification key.
+///
+/// If that circuit is itself a node circuit, `inner_layout` should be its layout. Otherwise, the
+/// predecessors are sources of the graph, and all of their public inputs are their messages.
+///
+/// `predicate` receives bindings for the messages of the predecessors, converted to this circuit's
+/// field, and returns the node's message. It may add any other inputs and constraints to the
+/// circuit. It must declare how each value of the predecessors' messages is bound, and the values
+/// it exposes are appended to the node's message, in order of the predecessors.
+pub fn pcd_circuit<C, InnerC, P>(
+    inner_vk: &VerificationKey<InnerC>,
+    inner_layout: Option<PcdLayout>,
+    arity: usize,
+    predicate: P,
+) -> PcdCircuit<C, InnerC>
+where
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    P: FnOnce(&mut CircuitBuilder<C>, &mut [PublicInputBindings<C>]) -> Vec<Target<C::ScalarField>>,
+{
+    assert!(arity > 0, "A node should have at least one predecessor");
+    let (inner_message_len, inner_layouts) = match inner_layout {
+        Some(inner_layout) => (
+            inner_layout.message_len,
+            vec![inner_layout.layout; inner_layout.arity],
+        ),
+        None => (inner_vk.num_public_inputs, Vec::new()),
+    };
+    let num_old_proofs = inner_layouts
+        .iter()
+        .map(|l| l.num_carried_accumulators)
+        .sum();
+
+    let mut builder = CircuitBuilder::<C>::new(inner_vk.security_bits);
+    let mut layouts = Vec::new();
+    let mut outputs = Vec::new();
+    let mut predecessors = Vec::new();
+    for _ in 0..arity {
+        let (layout, predecessor_outputs, predecessor) = verify_proof_recursively(
+            &mut builder,
+            inner_vk,
+            inner_message_len,
+            num_old_proofs,
+            &inner_layouts,
+            false,
+        );
+        layouts.push(layout);
+        outputs.extend(predecessor_outputs.to_vec());
+        predecessors.push(predecessor);
+    }
+
+    let mut bindings: Vec<PublicInputBindings<C>> = predecessors
+        .iter()
+        .map(|p| PublicInputBindings::new(&p.inner_public_inputs[..inner_message_len]))
+        .collect();
+    let mut message = predicate(&mut builder, &mut bindings);
+    for predecessor_bindings in bindings {
+        message.extend(predecessor_bindings.finish());
+    }
+
+    let public_inputs = builder.add_public_inputs(message.len() + outputs.len());
+    for (&pi, &output) in public_inputs.iter().zip(message.iter().chain(&outputs)) {
+        builder.copy(pi, output);
+    }
+
+    let circuit = builder.build();
+    let layout = layouts[0];
+    // The prover pads the `G` polynomials of old proofs, but can't truncate them.
+    assert!(
+        layout.num_carried_accumulators == 0 || 1 << layout.carried_degree_pow <= circuit.degree(),
+        "The carried accumulators are larger than the node circuit"
+    );
+    PcdCircuit {
+        circuit,
+        layout: PcdLayout {
+            message_len: message.len(),
+            arity,
+            layout,
+        },
+        predecessors,
+    }
+}
+
+/// Verifies the proof of a sink of proof-carrying data, then natively checks the assumptions it
+/// deferred about the proofs of its predecessors, as in `finalize_recursion`. `layout` should be
+/// the layout of the sink's circuit, and `inner_vk` the verification key of its predecessors.
+pub fn verify_pcd<C, InnerC>(
+    pcd_proof: &PcdProof<C>,
+    vk: &VerificationKey<C>,
+    layout: PcdLayout,
+    inner_vk: &VerificationKey<InnerC>,
+) -> Result<()>
+where
+    C: HaloCurve<BaseField = <InnerC as Curve>::ScalarField>,
+    InnerC: HaloCurve<BaseField = <C as Curve>::ScalarField>,
+{
+    verify_proof::<C, InnerC>(
+        &pcd_proof.public_inputs,
+        &pcd_proof.proof,
+        &pcd_proof.old_proofs,
+        vk,
+        true,
+    )?;
+    ensure!(
+        pcd_proof.public_inputs.len() == layout.num_public_inputs(),
+        "The public inputs don't match the PCD layout"
+    );
+    for part in layout.predecessor_parts(&pcd_proof.public_inputs) {
+        finalize_recursion::<InnerC, C>(part, layout.layout, inner_vk)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{rescue_hash_n_to_1, CircuitBuilder, Curve, Field, PartialWitness, PublicInputBindings, Tweedledee};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_public_input_bindings() -> Result<()> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let message = builder.add_virtual_targets(3);
+        let mut bindings = PublicInputBindings::new(&message);
+        bindings.bind_constant(&mut builder, 0, F::from_canonical_usize(5));
+        bindings.expose(1);
+        bindings.expose_hash(&mut builder, &[1, 2]);
+        let exposed = bindings.finish();
+        let public_inputs = builder.add_public_inputs(exposed.len());
+        for (&pi, &value) in public_inputs.iter().zip(&exposed) {
+            builder.copy(pi, value);
+        }
+        let circuit = builder.build();
+
+        let values = [5, 6, 7].iter().map(|&n| F::from_canonical_usize(n)).collect::<Vec<_>>();
+        let mut inputs = PartialWitness::new();
+        inputs.set_targets(&message, &values);
+        let witness = circuit.generate_witness(inputs)?;
+        assert_eq!(
+            circuit.get_public_inputs(&witness),
+            vec![values[1], rescue_hash_n_to_1(values[1..].to_vec(), 128)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "were not declared")]
+    fn test_undeclared_bindings() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let message = builder.add_virtual_targets(3);
+        let mut bindings = PublicInputBindings::<Tweedledee>::new(&message);
+        bindings.expose(0);
+        bindings.leave_unbound(2);
+        bindings.finish();
+    }
+}
@@ -1,9 +1,41 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::plonk_challenger::Challenger;
+use crate::plonk_challenger::Transcript;
 use crate::plonk_util::{halo_g, halo_n, halo_s};
-use crate::{AffinePoint, AffinePointTarget, Curve, Field, HaloCurve, PartialWitness, Target, SECURITY_BITS};
+use crate::{AffinePoint, AffinePointTarget, Curve, Field, HaloCurve, PartialWitness, SecurityParams, Target, VerificationKey, NUM_CONSTANTS, NUM_ROUTED_WIRES, NUM_WIRES, QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER};
+
+/// The domain separation label which starts every Plonk transcript.
+pub(crate) const PLONK_TRANSCRIPT_LABEL: &[u8] = b"plonky plonk proof";
+
+/// Observes the statement being proven, i.e. the circuit's commitments and sizes, followed by the
+/// public inputs. This must happen before any challenge is generated, so that every challenge
+/// depends on the full statement, and a prover can't adapt the statement to the challenges.
+///
+/// `sizes` should contain the degree, the number of wires, the number of routed wires, the number
+/// of public inputs and the number of gates without public inputs, in that order.
+pub(crate) fn observe_statement<C: HaloCurve, T: Transcript<C::BaseField>>(
+    transcript: &mut T,
+    c_constants: &[AffinePoint<C>],
+    c_s_sigmas: &[AffinePoint<C>],
+    sizes: &[usize],
+    public_inputs: impl IntoIterator<Item = C::ScalarField>,
+) -> Result<()> {
+    transcript.observe_label(PLONK_TRANSCRIPT_LABEL);
+    for &n in sizes {
+        transcript.observe_element(C::BaseField::from_canonical_usize(n));
+    }
+    // Some circuit commitments may be zero, e.g. those of unused constant polynomials, so we
+    // observe raw coordinates. The zero point is encoded as (0, 0), which is not on the curve.
+    for c in c_constants.iter().chain(c_s_sigmas) {
+        transcript.observe_elements(&[c.x, c.y]);
+    }
+    // Public inputs are observed one at a time, so that they needn't all be held in memory.
+    for pi in public_inputs {
+        transcript.observe_element(pi.try_convert()?);
+    }
+    Ok(())
+}
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 pub struct SchnorrProof<C: HaloCurve> {
@@ -18,8 +50,59 @@ pub struct SchnorrProofTarget<C: Curve> {
     pub z2: Target<C::ScalarField>,
 }
 
+/// The parameters a proof was generated with. Proofs carry them so that a verifier expecting
+/// different parameters can reject a proof with a descriptive error, rather than just finding it
+/// invalid.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProofParameters {
+    /// The curve's `NAME`.
+    pub curve: String,
+    /// The `hash_name` of the prover's transcript.
+    pub transcript_hash: String,
+    pub num_wires: usize,
+    pub security_bits: usize,
+    pub challenge_len: usize,
+}
+
+impl ProofParameters {
+    pub fn new<C: Curve>(transcript_hash: &str, num_wires: usize, security: SecurityParams) -> Self {
+        ProofParameters {
+            curve: C::NAME.to_string(),
+            transcript_hash: transcript_hash.to_string(),
+            num_wires,
+            security_bits: security.bits,
+            challenge_len: security.challenge_len,
+        }
+    }
+
+    /// Checks that these parameters match the expected ones, describing the first mismatch.
+    pub fn check_compatible(&self, expected: &ProofParameters) -> Result<()> {
+        let mismatch = |what: &str, expected: &dyn std::fmt::Display, actual: &dyn std::fmt::Display| {
+            Err(anyhow!("Expected a proof with {} {}, got {}", what, expected, actual))
+        };
+        if self.curve != expected.curve {
+            return mismatch("curve", &expected.curve, &self.curve);
+        }
+        if self.transcript_hash != expected.transcript_hash {
+            return mismatch("transcript hash", &expected.transcript_hash, &self.transcript_hash);
+        }
+        if self.num_wires != expected.num_wires {
+            return mismatch("wire count", &expected.num_wires, &self.num_wires);
+        }
+        if self.security_bits != expected.security_bits {
+            return mismatch("security level", &expected.security_bits, &self.security_bits);
+        }
+        if self.challenge_len != expected.challenge_len {
+            return mismatch("challenge length", &expected.challenge_len, &self.challenge_len);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Proof<C: HaloCurve> {
+    pub parameters: ProofParameters,
+
     /// A commitment to each wire polynomial.
     pub c_wires: Vec<AffinePoint<C>>,
     /// A commitment to Z, in the context of the permutation argument.
@@ -55,14 +138,41 @@ impl<C: HaloCurve> Proof<C> {
         ]
     }
 
-    // Computes all challenges used in the proof verification.
-    pub fn get_challenges(
+    // Computes all challenges used in the proof verification, observing the transcript with
+    // `challenger`, which should be fresh and of the same kind the prover used.
+    pub fn get_challenges<T: Transcript<C::BaseField>>(
         &self,
+        vk: &VerificationKey<C>,
         public_inputs: &[C::ScalarField],
         old_proofs: &[OldProof<C>],
+        challenger: T,
+    ) -> Result<ProofChallenge<C>> {
+        self.get_challenges_streaming(vk, public_inputs.iter().copied(), old_proofs, challenger)
+    }
+
+    /// Like `get_challenges`, but observes the public inputs as they're yielded by an iterator.
+    pub fn get_challenges_streaming<T: Transcript<C::BaseField>>(
+        &self,
+        vk: &VerificationKey<C>,
+        public_inputs: impl IntoIterator<Item = C::ScalarField>,
+        old_proofs: &[OldProof<C>],
+        mut challenger: T,
     ) -> Result<ProofChallenge<C>> {
-        let mut challenger = Challenger::new(SECURITY_BITS);
         let error_msg = "Conversion from base to scalar field failed.";
+        observe_statement(
+            &mut challenger,
+            &vk.c_constants,
+            &vk.c_s_sigmas,
+            &[
+                vk.degree,
+                vk.num_wires,
+                vk.num_routed_wires,
+                vk.num_public_inputs,
+                vk.num_gates_without_pis,
+            ],
+            public_inputs,
+        )
+        .map_err(|_| anyhow!("Public inputs should fit in both fields"))?;
         challenger.observe_affine_points(&self.c_wires);
         let (beta_bf, gamma_bf) = challenger.get_2_challenges();
         let beta = C::try_convert_b2s(beta_bf).map_err(|_| anyhow!(error_msg))?;
@@ -72,10 +182,6 @@ impl<C: HaloCurve> Proof<C> {
         let alpha = C::try_convert_b2s(alpha_bf).map_err(|_| anyhow!(error_msg))?;
         challenger.observe_affine_points(&self.c_plonk_t);
         challenger.observe_affine_point(self.c_pis_quotient);
-        challenger.observe_elements(
-            &C::ScalarField::try_convert_all(public_inputs)
-                .expect("Public inputs should fit in both fields"),
-        );
         old_proofs
             .iter()
             .for_each(|old_proof| challenger.observe_affine_point(old_proof.halo_g));
@@ -92,16 +198,18 @@ impl<C: HaloCurve> Proof<C> {
         let u_scaling = C::try_convert_b2s(u_scaling_bf).map_err(|_| anyhow!(error_msg))?;
 
         // Compute IPA challenges.
+        let mut ipa_challenges = Vec::new();
         let mut halo_us = Vec::new();
         for i in 0..self.halo_l.len() {
             challenger.observe_affine_points(&[self.halo_l[i], self.halo_r[i]]);
             let r_bf = challenger.get_challenge();
             let r_sf = r_bf.try_convert::<C::ScalarField>()?;
-            let r_bits = &r_sf.to_canonical_bool_vec()[..SECURITY_BITS];
+            let r_bits = &r_sf.to_canonical_bool_vec()[..vk.challenge_len];
             let u_j_squared = halo_n::<C>(r_bits);
             let u_j = u_j_squared.square_root().ok_or_else(|| {
                 anyhow!("Invalid transcript. Prover should have ensured that n(r) is square")
             })?;
+            ipa_challenges.push(r_sf);
             halo_us.push(u_j);
         }
 
@@ -119,6 +227,7 @@ impl<C: HaloCurve> Proof<C> {
             v,
             u,
             u_scaling,
+            ipa_challenges,
             halo_us,
             schnorr_challenge,
         })
@@ -134,11 +243,13 @@ pub struct ProofChallenge<C: Curve> {
     pub v: C::ScalarField,
     pub u: C::ScalarField,
     pub u_scaling: C::ScalarField,
+    /// The raw IPA challenges `r_j`, from which `halo_us` are derived as `u_j = sqrt(n(r_j))`.
+    pub ipa_challenges: Vec<C::ScalarField>,
     pub halo_us: Vec<C::ScalarField>,
     pub schnorr_challenge: C::ScalarField,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 /// Object returned by the verifier, containing the necessary data to verify `halo_g` at a later time.
 /// In particular, `halo_g = commit(g(X, halo_us))` where `g` is the polynomial defined in section 3.2 of the paper.
 pub struct OldProof<C: HaloCurve> {
@@ -190,9 +301,9 @@ pub struct ProofTarget<C: Curve, InnerC: Curve<BaseField = C::ScalarField>> {
     pub c_plonk_z: AffinePointTarget<InnerC>,
     /// A commitment to the quotient polynomial.
     pub c_plonk_t: Vec<AffinePointTarget<InnerC>>,
+    /// A commitment to the public input quotient polynomial.
+    pub c_pis_quotient: AffinePointTarget<InnerC>,
 
-    /// The opening of each polynomial at each `PublicInputGate` index.
-    pub o_public_inputs: Option<Vec<OpeningSetTarget<C>>>,
     /// The opening of each polynomial at `zeta`.
     pub o_local: OpeningSetTarget<C>,
     /// The opening of each polynomial at `g * zeta`.
@@ -218,17 +329,23 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> ProofTarget<C,
     }
 
     pub fn all_opening_sets(&self) -> Vec<OpeningSetTarget<C>> {
+        vec![
+            self.o_local.clone(),
+            self.o_right.clone(),
+            self.o_below.clone(),
+        ]
+    }
+
+    /// All curve points in the proof, which the verifier must check to be on the curve.
+    pub fn all_points(&self) -> Vec<AffinePointTarget<InnerC>> {
         [
-            if let Some(pis) = &self.o_public_inputs {
-                pis.as_slice()
-            } else {
-                &[]
-            },
-            &[
-                self.o_local.clone(),
-                self.o_right.clone(),
-                self.o_below.clone(),
-            ],
+            self.c_wires.as_slice(),
+            &[self.c_plonk_z],
+            self.c_plonk_t.as_slice(),
+            &[self.c_pis_quotient],
+            self.halo_l_i.as_slice(),
+            self.halo_r_i.as_slice(),
+            &[self.halo_g, self.schnorr_proof.r],
         ]
         .concat()
     }
@@ -245,11 +362,12 @@ impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> ProofTarget<C,
     pub fn populate_witness(
         &self,
         witness: &mut PartialWitness<C::ScalarField>,
-        values: Proof<InnerC>,
+        values: &Proof<InnerC>,
     ) -> Result<()> {
         witness.set_point_targets(&self.c_wires, &values.c_wires);
         witness.set_point_target(self.c_plonk_z, values.c_plonk_z);
         witness.set_point_targets(&self.c_plonk_t, &values.c_plonk_t);
+        witness.set_point_target(self.c_pis_quotient, values.c_pis_quotient);
 
         self.o_local.populate_witness(witness, &values.o_local)?;
         self.o_right.populate_witness(witness, &values.o_right)?;
@@ -309,6 +427,38 @@ impl<F: Field> OpeningSet<F> {
         ]
         .concat()
     }
+
+    /// The inverse of `to_vec`.
+    pub fn from_vec(values: &[F], num_old_proofs: usize) -> Self {
+        assert_eq!(
+            values.len(),
+            num_openings(num_old_proofs),
+            "Wrong number of opening values"
+        );
+        let mut values = values.iter().copied();
+        let mut take = |n: usize| (&mut values).take(n).collect::<Vec<_>>();
+        OpeningSet {
+            o_constants: take(NUM_CONSTANTS),
+            o_plonk_sigmas: take(NUM_ROUTED_WIRES),
+            o_wires: take(NUM_WIRES),
+            o_plonk_z: take(1)[0],
+            o_plonk_t: take(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
+            o_old_proofs: take(num_old_proofs),
+            o_pi_quotient: take(1)[0],
+        }
+    }
+}
+
+/// The number of polynomials opened at each point, i.e. the length of an `OpeningSet`, for a
+/// circuit of the default width.
+pub(crate) fn num_openings(num_old_proofs: usize) -> usize {
+    NUM_CONSTANTS
+        + NUM_ROUTED_WIRES
+        + NUM_WIRES
+        + 1
+        + QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER
+        + num_old_proofs
+        + 1
 }
 
 /// The opening of each Plonk polynomial at a particular point.
@@ -326,6 +476,8 @@ pub struct OpeningSetTarget<C: Curve> {
     pub o_plonk_t: Vec<Target<C::ScalarField>>,
     /// The purported opening of some old proofs `halo_g` polynomials.
     pub o_old_proofs: Vec<Target<C::ScalarField>>,
+    /// The purported opening of the public input quotient polynomial.
+    pub o_pi_quotient: Target<C::ScalarField>,
 }
 
 impl<C: Curve> OpeningSetTarget<C> {
@@ -337,10 +489,31 @@ impl<C: Curve> OpeningSetTarget<C> {
             &[self.o_plonk_z],
             self.o_plonk_t.as_slice(),
             self.o_old_proofs.as_slice(),
+            &[self.o_pi_quotient],
         ]
         .concat()
     }
 
+    /// The inverse of `to_vec`.
+    pub fn from_vec(targets: &[Target<C::ScalarField>], num_old_proofs: usize) -> Self {
+        assert_eq!(
+            targets.len(),
+            num_openings(num_old_proofs),
+            "Wrong number of opening targets"
+        );
+        let mut targets = targets.iter().copied();
+        let mut take = |n: usize| (&mut targets).take(n).collect::<Vec<_>>();
+        OpeningSetTarget {
+            o_constants: take(NUM_CONSTANTS),
+            o_plonk_sigmas: take(NUM_ROUTED_WIRES),
+            o_wires: take(NUM_WIRES),
+            o_plonk_z: take(1)[0],
+            o_plonk_t: take(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
+            o_old_proofs: take(num_old_proofs),
+            o_pi_quotient: take(1)[0],
+        }
+    }
+
     pub fn populate_witness<F: Field>(
         &self,
         witness: &mut PartialWitness<C::ScalarField>,
@@ -371,6 +544,10 @@ impl<C: Curve> OpeningSetTarget<C> {
             &Target::convert_slice(&self.o_old_proofs),
             &Field::try_convert_all(&values.o_old_proofs)?,
         );
+        witness.set_target(
+            self.o_pi_quotient.convert(),
+            Field::try_convert(&values.o_pi_quotient)?,
+        );
         Ok(())
     }
 }
@@ -1,284 +1,1383 @@
+use std::marker::PhantomData;
+
+use anyhow::{anyhow, ensure, Result};
+
 use crate::gates::evaluate_all_constraints_recursively;
-use crate::plonk_challenger::RecursiveChallenger;
-use crate::plonk_proof::OldProofTarget;
-use crate::plonk_util::{powers_recursive, reduce_with_powers_recursive};
-use crate::util::ceil_div_usize;
-use crate::{get_subgroup_shift, hash_usize_to_curve, AffinePointTarget, Circuit, CircuitBuilder, CurveMulEndoResult, CurveMulOp, Field, HaloCurve, OpeningSetTarget, ProofTarget, SchnorrProofTarget, Target, GRID_WIDTH, NUM_CONSTANTS, NUM_ROUTED_WIRES, NUM_WIRES, QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER};
+use crate::plonk_challenger::{Challenger, RecursiveChallenger, Transcript};
+use crate::plonk_proof::{num_openings, OldProofTarget, OpeningSet, PLONK_TRANSCRIPT_LABEL};
+use crate::plonk_util::{halo_g, halo_n, halo_n_recursive, powers, powers_recursive, reduce_with_powers, reduce_with_powers_recursive};
+use crate::util::log2_strict;
+use crate::verifier::{quotient_opening_is_valid, verify_accumulators, VerificationError};
+use crate::{blake_hash_base_field_to_curve, blake_hash_usize_to_curve, get_subgroup_shift, AffinePoint, AffinePointTarget, Circuit, CircuitBuilder, Curve, CurveMulEndoResult, CurveMulOp, Field, HaloCurve, OldProof, OpeningSetTarget, PartialWitness, ProjectivePoint, Proof, ProofChallenge, ProofParameters, ProofTarget, SchnorrProof, SchnorrProofTarget, Target, VerificationKey, WitnessGenerator, GRID_WIDTH, NUM_CONSTANTS, NUM_ROUTED_WIRES, NUM_WIRES, QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER};
 
 /// Wraps a `Circuit` for recursive verification with inputs for the proof data.
 /// The circuit is over the field `C::ScalarField` and verifies a proof performed over the curve `InnerC`.
 pub struct RecursiveCircuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
     pub circuit: Circuit<C>,
-    pub public_inputs: RecursionPublicInputs<C::ScalarField>,
+    /// The shape of the verified proof, which determines the layout of `public_inputs`.
+    pub layout: RecursionLayout,
+    pub public_inputs: RecursionPublicInputs<C>,
+    pub verified: VerifiedProofTarget<C, InnerC>,
+}
+
+impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> RecursiveCircuit<C, InnerC> {
+    /// Populates the inputs of the circuit with a proof to verify, its public inputs, and the old
+    /// proofs whose `G` polynomials it opened.
+    pub fn populate_witness(
+        &self,
+        witness: &mut PartialWitness<C::ScalarField>,
+        proof: &Proof<InnerC>,
+        public_inputs: &[InnerC::ScalarField],
+        old_proofs: &[OldProof<InnerC>],
+    ) -> Result<()> {
+        self.verified
+            .populate_witness(witness, proof, public_inputs, old_proofs)
+    }
+
+    /// Populates the verification key of the verified proof, if it is an input of the circuit, as
+    /// in `recursive_verification_circuit_with_vk_input`. Otherwise, this does nothing.
+    pub fn populate_inner_vk(
+        &self,
+        witness: &mut PartialWitness<C::ScalarField>,
+        inner_vk: &VerificationKey<InnerC>,
+    ) -> Result<()> {
+        match &self.verified.inner_vk {
+            Some(target) => target.populate_witness(witness, inner_vk),
+            None => Ok(()),
+        }
+    }
+
+    /// Populates the inputs of the circuit for a base case, such as the first step of a chain,
+    /// where there is no proof to verify. See `VerifiedProofTarget::populate_dummy_witness`.
+    pub fn populate_dummy_witness(
+        &self,
+        witness: &mut PartialWitness<C::ScalarField>,
+        inner_vk: &VerificationKey<InnerC>,
+    ) -> Result<()> {
+        self.verified.populate_dummy_witness(witness, inner_vk)
+    }
+}
+
+/// The inputs of a recursive circuit relating to one verified proof.
+pub struct VerifiedProofTarget<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
     pub proof: ProofTarget<C, InnerC>,
+    /// The public inputs of the verified proof, converted to `InnerC::BaseField`.
+    pub inner_public_inputs: Vec<Target<C::ScalarField>>,
     pub old_proofs: Vec<OldProofTarget<InnerC>>,
+    /// The verification key of the verified proof, if it is an input rather than a constant.
+    pub inner_vk: Option<VerificationKeyTarget<InnerC>>,
+    /// A flag which, when set, disables every check of the verified proof, so that the circuit
+    /// can be proved for a base case where there is no proof to verify.
+    pub dummy: Target<C::ScalarField>,
+}
+
+impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> VerifiedProofTarget<C, InnerC> {
+    /// Populates the targets with a proof to verify, its public inputs, and the old proofs whose
+    /// `G` polynomials it opened.
+    pub fn populate_witness(
+        &self,
+        witness: &mut PartialWitness<C::ScalarField>,
+        proof: &Proof<InnerC>,
+        public_inputs: &[InnerC::ScalarField],
+        old_proofs: &[OldProof<InnerC>],
+    ) -> Result<()> {
+        self.populate_targets(witness, proof, public_inputs, old_proofs, false)
+    }
+
+    /// Populates the targets for a base case, where there is no proof to verify. This sets the
+    /// dummy flag, and fills the other targets with placeholder values for which witness
+    /// generation succeeds, although the checks they would be subject to are disabled.
+    pub fn populate_dummy_witness(
+        &self,
+        witness: &mut PartialWitness<C::ScalarField>,
+        inner_vk: &VerificationKey<InnerC>,
+    ) -> Result<()> {
+        // Nonzero public inputs keep the disabled checks of `verify_assumptions` well defined,
+        // since they invert some of them.
+        let public_inputs = vec![InnerC::ScalarField::TWO; self.inner_public_inputs.len()];
+        let old_proofs: Vec<OldProof<InnerC>> = self
+            .old_proofs
+            .iter()
+            .map(|p| dummy_accumulator(p.halo_us.len()))
+            .collect();
+        let proof = dummy_proof(inner_vk, &public_inputs, &old_proofs)?;
+        if let Some(target) = &self.inner_vk {
+            target.populate_witness(witness, inner_vk)?;
+        }
+        self.populate_targets(witness, &proof, &public_inputs, &old_proofs, true)
+    }
+
+    fn populate_targets(
+        &self,
+        witness: &mut PartialWitness<C::ScalarField>,
+        proof: &Proof<InnerC>,
+        public_inputs: &[InnerC::ScalarField],
+        old_proofs: &[OldProof<InnerC>],
+        dummy: bool,
+    ) -> Result<()> {
+        ensure!(
+            public_inputs.len() == self.inner_public_inputs.len(),
+            "Expected {} public inputs, got {}",
+            self.inner_public_inputs.len(),
+            public_inputs.len()
+        );
+        ensure!(
+            old_proofs.len() == self.old_proofs.len(),
+            "Expected {} old proofs, got {}",
+            self.old_proofs.len(),
+            old_proofs.len()
+        );
+        witness.set_targets(
+            &self.inner_public_inputs,
+            &InnerC::ScalarField::try_convert_all(public_inputs)?,
+        );
+        self.proof.populate_witness(witness, proof)?;
+        for (target, old_proof) in self.old_proofs.iter().zip(old_proofs) {
+            target.populate_witness(witness, old_proof)?;
+        }
+        let dummy = if dummy {
+            C::ScalarField::ONE
+        } else {
+            C::ScalarField::ZERO
+        };
+        witness.set_target(self.dummy, dummy);
+        Ok(())
+    }
+}
+
+/// The parts of a verification key which a recursive circuit can take as inputs, so that it can
+/// verify proofs of any circuit with the same shape, i.e. the same degree and number of public
+/// inputs. As in the transcript, a zero commitment is encoded as `(0, 0)`.
+pub struct VerificationKeyTarget<C: Curve> {
+    pub c_constants: Vec<AffinePointTarget<C>>,
+    pub c_s_sigmas: Vec<AffinePointTarget<C>>,
+    pub num_gates_without_pis: Target<C::BaseField>,
+}
+
+/// The number of values in `VerificationKeyTarget::to_vec`.
+const NUM_VK_VALUES: usize = 2 * (NUM_CONSTANTS + NUM_ROUTED_WIRES) + 1;
+
+impl<C: HaloCurve> VerificationKeyTarget<C> {
+    fn commitments(&self) -> Vec<AffinePointTarget<C>> {
+        [self.c_constants.clone(), self.c_s_sigmas.clone()].concat()
+    }
+
+    /// The coordinates of each commitment, followed by the number of gates. See
+    /// `verification_key_values` for the native equivalent.
+    pub fn to_vec(&self) -> Vec<Target<C::BaseField>> {
+        let mut targets: Vec<Target<C::BaseField>> = self
+            .commitments()
+            .iter()
+            .flat_map(|c| c.to_vec())
+            .collect();
+        targets.push(self.num_gates_without_pis);
+        targets
+    }
+
+    pub fn populate_witness(
+        &self,
+        witness: &mut PartialWitness<C::BaseField>,
+        vk: &VerificationKey<C>,
+    ) -> Result<()> {
+        ensure!(
+            vk.c_constants.len() == self.c_constants.len()
+                && vk.c_s_sigmas.len() == self.c_s_sigmas.len(),
+            "The verification key has the wrong number of commitments"
+        );
+        witness.set_targets(&self.to_vec(), &verification_key_values(vk));
+        Ok(())
+    }
+}
+
+/// The values of a verification key which a recursive circuit can take as inputs, in the order of
+/// `VerificationKeyTarget::to_vec`.
+pub fn verification_key_values<C: HaloCurve>(vk: &VerificationKey<C>) -> Vec<C::BaseField> {
+    let mut values: Vec<C::BaseField> = vk
+        .c_constants
+        .iter()
+        .chain(&vk.c_s_sigmas)
+        .flat_map(|c| vec![c.x, c.y])
+        .collect();
+    values.push(C::BaseField::from_canonical_usize(vk.num_gates_without_pis));
+    values
+}
+
+/// The shape of a proof verified by a recursive circuit, which determines the layout of the
+/// circuit's public inputs.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RecursionLayout {
+    /// `log_2` of the degree of the verified proof.
+    pub degree_pow: usize,
+    /// The number of old proofs whose `G` polynomials the verified proof opened.
+    pub num_old_proofs: usize,
+    /// `log_2` of the degree of the old proofs, which may be smaller than that of the verified
+    /// proof. Unused if there are no old proofs.
+    pub old_proof_degree_pow: usize,
+    /// If the inner circuit is itself recursive, the number of accumulators it exported, which
+    /// this circuit carries for its own proof to fold.
+    pub num_carried_accumulators: usize,
+    /// `log_2` of the degree of the carried accumulators. Unused if there are none.
+    pub carried_degree_pow: usize,
+    /// Whether the verification key of the verified proof is an input of the circuit, which it
+    /// exports after the carried accumulators.
+    pub vk_is_input: bool,
+}
+
+impl RecursionLayout {
+    /// The layout of a recursive circuit verifying a proof with the given verification key. If
+    /// the inner circuit is itself recursive, `inner_layouts` should contain the layout of each
+    /// part of its public inputs, and the verified proof should fold the accumulators carried by
+    /// each part, in order.
+    fn new<C: HaloCurve>(
+        inner_vk: &VerificationKey<C>,
+        num_old_proofs: usize,
+        inner_layouts: &[RecursionLayout],
+        vk_is_input: bool,
+    ) -> Self {
+        let degree_pow = log2_strict(inner_vk.degree);
+        match inner_layouts.first() {
+            Some(&inner_layout) => {
+                assert!(
+                    inner_layouts.iter().all(|&l| l == inner_layout),
+                    "The inner circuit's recursion layouts should match"
+                );
+                assert_eq!(
+                    num_old_proofs,
+                    inner_layouts.len() * inner_layout.num_carried_accumulators,
+                    "The verified proof should fold the accumulators carried by the inner circuit"
+                );
+                RecursionLayout {
+                    degree_pow,
+                    num_old_proofs,
+                    old_proof_degree_pow: inner_layout.carried_degree_pow,
+                    num_carried_accumulators: inner_layouts.len(),
+                    carried_degree_pow: inner_layout.degree_pow,
+                    vk_is_input,
+                }
+            }
+            None => RecursionLayout {
+                degree_pow,
+                num_old_proofs,
+                old_proof_degree_pow: degree_pow,
+                num_carried_accumulators: 0,
+                carried_degree_pow: 0,
+                vk_is_input,
+            },
+        }
+    }
+
+    pub fn num_public_inputs(&self) -> usize {
+        // The dummy flag, the six challenges, three opening sets, the powers of u, the reduced
+        // opening, b, the IPA challenges, the accumulator, the old proofs, the carried
+        // accumulators and the verification key.
+        self.vk_start() + self.num_vk_values()
+    }
+
+    /// The index of the exported accumulator, i.e. the `G` point of the verified proof followed by
+    /// its `u_j`s, in the public inputs.
+    fn accumulator_start(&self) -> usize {
+        let num_openings = num_openings(self.num_old_proofs);
+        1 + 6 + 4 * num_openings + 2 + self.degree_pow
+    }
+
+    /// The index of the carried accumulators in the public inputs.
+    fn carried_accumulators_start(&self) -> usize {
+        self.accumulator_start()
+            + 2
+            + self.degree_pow
+            + self.num_old_proofs * (2 + self.old_proof_degree_pow)
+    }
+
+    /// The index of the verification key in the public inputs, if it is an input.
+    fn vk_start(&self) -> usize {
+        self.carried_accumulators_start()
+            + self.num_carried_accumulators * (2 + self.carried_degree_pow)
+    }
+
+    fn num_vk_values(&self) -> usize {
+        if self.vk_is_input {
+            NUM_VK_VALUES
+        } else {
+            0
+        }
+    }
 }
 
 /// Public inputs of the recursive circuit. This contains data for the inner proof which is needed
 /// to complete verification of it.
-pub struct RecursionPublicInputs<F: Field> {
-    beta: Target<F>,
-    gamma: Target<F>,
-    alpha: Target<F>,
-    zeta: Target<F>,
-    o_constants: Vec<Target<F>>,
-    o_plonk_sigmas: Vec<Target<F>>,
-    o_local_wires: Vec<Target<F>>,
-    o_right_wires: Vec<Target<F>>,
-    o_below_wires: Vec<Target<F>>,
-    o_plonk_z_local: Target<F>,
-    o_plonk_z_right: Target<F>,
-    o_plonk_t: Vec<Target<F>>,
-    halo_us: Vec<Target<F>>,
-    old_proofs: Vec<Target<F>>,
+///
+/// To avoid non-native arithmetic, the recursive circuit witnesses any values which live in the
+/// inner proof's scalar field, such as the reduced opening, and the next circuit in the cycle,
+/// whose native field is that scalar field, checks them in `verify_assumptions`.
+pub struct RecursionPublicInputs<C: HaloCurve> {
+    /// Whether the verified proof is a dummy, in which case the circuit enforced none of its
+    /// checks, and the values below relating to it are meaningless.
+    pub dummy: Target<C::ScalarField>,
+    pub beta: Target<C::ScalarField>,
+    pub gamma: Target<C::ScalarField>,
+    pub alpha: Target<C::ScalarField>,
+    pub zeta: Target<C::ScalarField>,
+    pub v: Target<C::ScalarField>,
+    pub u: Target<C::ScalarField>,
+    pub o_local: OpeningSetTarget<C>,
+    pub o_right: OpeningSetTarget<C>,
+    pub o_below: OpeningSetTarget<C>,
+    /// The powers of `u` used to combine the polynomial commitments.
+    pub u_powers: Vec<Target<C::ScalarField>>,
+    /// The combined opening, which should equal `sum_j v^j sum_i n(u^i) o_j[i]`.
+    pub reduced_opening: Target<C::ScalarField>,
+    /// `b` in the Halo IPA, which should equal `sum_j v^j g(x_j, halo_us)`.
+    pub halo_b: Target<C::ScalarField>,
+    /// The raw IPA challenges `r_j`.
+    pub ipa_challenges: Vec<Target<C::ScalarField>>,
+    /// The coordinates of the verified proof's `G` point. Together with `halo_us`, this is the
+    /// proof's accumulator: the check `G = <s, G>` is deferred to a later proof on the same curve,
+    /// which folds it as an old proof, or to the native verifier at the end of the chain.
+    pub halo_g_x: Target<C::ScalarField>,
+    pub halo_g_y: Target<C::ScalarField>,
+    /// The IPA challenges `u_j`, which should satisfy `u_j^2 = n(r_j)`.
+    pub halo_us: Vec<Target<C::ScalarField>>,
+    /// For each old proof, the coordinates of its `G` point followed by its `u_j`s.
+    pub old_proofs: Vec<Target<C::ScalarField>>,
+    /// If the inner circuit is recursive, the accumulators it exported, in the same format. This
+    /// circuit's own proof should fold them as old proofs, which the next circuit checks.
+    pub carried_accumulators: Vec<Target<C::ScalarField>>,
+    /// If the verification key of the verified proof was an input, its values, as in
+    /// `VerificationKeyTarget::to_vec`. They aren't checked by the next circuit, so whoever relies
+    /// on the verified proof should check that they match an expected key.
+    pub vk: Vec<Target<C::ScalarField>>,
 }
 
-/// The number of `PublicInputGate`s needed to route the given number of public inputs.
-fn num_public_input_gates(num_public_inputs: usize) -> usize {
-    ceil_div_usize(num_public_inputs, NUM_WIRES)
+impl<C: HaloCurve> RecursionPublicInputs<C> {
+    /// Interprets a list of targets, such as a recursive circuit's public inputs, according to
+    /// the given layout.
+    pub fn from_targets(targets: &[Target<C::ScalarField>], layout: RecursionLayout) -> Self {
+        assert_eq!(
+            targets.len(),
+            layout.num_public_inputs(),
+            "Wrong number of targets for the recursion layout"
+        );
+        let num_openings = num_openings(layout.num_old_proofs);
+        let mut targets = targets.iter().copied();
+        let mut take = |n: usize| (&mut targets).take(n).collect::<Vec<_>>();
+        RecursionPublicInputs {
+            dummy: take(1)[0],
+            beta: take(1)[0],
+            gamma: take(1)[0],
+            alpha: take(1)[0],
+            zeta: take(1)[0],
+            v: take(1)[0],
+            u: take(1)[0],
+            o_local: OpeningSetTarget::from_vec(&take(num_openings), layout.num_old_proofs),
+            o_right: OpeningSetTarget::from_vec(&take(num_openings), layout.num_old_proofs),
+            o_below: OpeningSetTarget::from_vec(&take(num_openings), layout.num_old_proofs),
+            u_powers: take(num_openings),
+            reduced_opening: take(1)[0],
+            halo_b: take(1)[0],
+            ipa_challenges: take(layout.degree_pow),
+            halo_g_x: take(1)[0],
+            halo_g_y: take(1)[0],
+            halo_us: take(layout.degree_pow),
+            old_proofs: take(layout.num_old_proofs * (2 + layout.old_proof_degree_pow)),
+            carried_accumulators: take(
+                layout.num_carried_accumulators * (2 + layout.carried_degree_pow),
+            ),
+            vk: take(layout.num_vk_values()),
+        }
+    }
+
+    /// The inverse of `from_targets`.
+    pub fn to_vec(&self) -> Vec<Target<C::ScalarField>> {
+        [
+            vec![self.dummy, self.beta, self.gamma, self.alpha, self.zeta, self.v, self.u],
+            self.o_local.to_vec(),
+            self.o_right.to_vec(),
+            self.o_below.to_vec(),
+            self.u_powers.clone(),
+            vec![self.reduced_opening, self.halo_b],
+            self.ipa_challenges.clone(),
+            vec![self.halo_g_x, self.halo_g_y],
+            self.halo_us.clone(),
+            self.old_proofs.clone(),
+            self.carried_accumulators.clone(),
+            self.vk.clone(),
+        ]
+        .concat()
+    }
+
+    /// The exported accumulator, i.e. the coordinates of the `G` point followed by the `u_j`s.
+    fn accumulator(&self) -> Vec<Target<C::ScalarField>> {
+        [vec![self.halo_g_x, self.halo_g_y], self.halo_us.clone()].concat()
+    }
+
+    /// The `u_j`s of the `i`th old proof.
+    fn old_proof_us(&self, i: usize, degree_pow: usize) -> &[Target<C::ScalarField>] {
+        let start = i * (2 + degree_pow) + 2;
+        &self.old_proofs[start..start + degree_pow]
+    }
 }
 
+/// Computes the public inputs of a recursive circuit verifying `proof`, i.e. the values it exports
+/// for the next circuit in the cycle to check. These are given in `C::BaseField`, the native field
+/// of the recursive circuit.
+///
+/// `public_inputs` are the public inputs of `proof`. If its circuit is itself recursive,
+/// `inner_layout` should be that circuit's layout, as in `recursive_verification_circuit`, and
+/// `InnerC` is the curve of the proofs which that circuit verified. If the recursive circuit takes
+/// the verification key of `proof` as an input, `vk` should be that key.
+#[allow(clippy::too_many_arguments)]
+pub fn recursion_public_inputs<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    proof: &Proof<C>,
+    public_inputs: &[C::ScalarField],
+    inner_layout: Option<RecursionLayout>,
+    old_proofs: &[OldProof<C>],
+    challs: &ProofChallenge<C>,
+    challenge_len: usize,
+    vk: Option<&VerificationKey<C>>,
+) -> Result<Vec<C::BaseField>> {
+    let opening_sets: Vec<Vec<C::ScalarField>> = proof
+        .all_opening_sets()
+        .iter()
+        .map(|set| set.to_vec())
+        .collect();
+    let deferred = compute_deferred_scalars::<C>(
+        challs.zeta,
+        challs.v,
+        challs.u,
+        &opening_sets,
+        &challs.ipa_challenges,
+        challenge_len,
+    )?;
+    let scalars = [
+        vec![
+            C::ScalarField::ZERO,
+            challs.beta,
+            challs.gamma,
+            challs.alpha,
+            challs.zeta,
+            challs.v,
+            challs.u,
+        ],
+        opening_sets.concat(),
+        deferred.u_powers,
+        vec![deferred.reduced_opening, deferred.halo_b],
+        challs.ipa_challenges.clone(),
+    ]
+    .concat();
+    let mut values = C::ScalarField::try_convert_all(&scalars)?;
+    values.extend(&[proof.halo_g.x, proof.halo_g.y]);
+    values.extend(C::ScalarField::try_convert_all::<C::BaseField>(&deferred.halo_us)?);
+    for old_proof in old_proofs {
+        values.extend(&[old_proof.halo_g.x, old_proof.halo_g.y]);
+        values.extend(C::ScalarField::try_convert_all::<C::BaseField>(&old_proof.halo_us)?);
+    }
+    if let Some(inner_layout) = inner_layout {
+        ensure!(
+            public_inputs.len() == inner_layout.num_public_inputs(),
+            "The public inputs don't match the inner recursion layout"
+        );
+        // If the inner circuit's proof was a dummy, the accumulator it exported is replaced.
+        let accumulator = if public_inputs[0] == C::ScalarField::ONE {
+            accumulator_to_values(&dummy_accumulator::<InnerC>(inner_layout.degree_pow))?
+        } else {
+            let start = inner_layout.accumulator_start();
+            public_inputs[start..start + 2 + inner_layout.degree_pow].to_vec()
+        };
+        values.extend(C::ScalarField::try_convert_all::<C::BaseField>(&accumulator)?);
+    }
+    if let Some(vk) = vk {
+        values.extend(verification_key_values(vk));
+    }
+    Ok(values)
+}
+
+/// Returns the accumulator exported by a recursive circuit, i.e. the `G` point and `u_j`s of the
+/// proof of `C` it verified, given the circuit's public inputs.
+pub fn recursion_accumulator<C: HaloCurve>(
+    public_inputs: &[C::BaseField],
+    layout: RecursionLayout,
+) -> Result<OldProof<C>> {
+    ensure!(
+        public_inputs.len() == layout.num_public_inputs(),
+        "The public inputs don't match the recursion layout"
+    );
+    let start = layout.accumulator_start();
+    accumulator_from_values(&public_inputs[start..start + 2 + layout.degree_pow])
+}
+
+/// Returns the accumulators carried by a recursive circuit verifying a proof of `C`. The proof of
+/// the recursive circuit, which is a proof of `InnerC`, should fold them as old proofs.
+pub fn carried_accumulators<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    public_inputs: &[C::BaseField],
+    layout: RecursionLayout,
+) -> Result<Vec<OldProof<InnerC>>> {
+    ensure!(
+        public_inputs.len() == layout.num_public_inputs(),
+        "The public inputs don't match the recursion layout"
+    );
+    // TODO: We temporarily assume that each value fits in both fields.
+    let values = C::BaseField::try_convert_all::<C::ScalarField>(
+        &public_inputs[layout.carried_accumulators_start()..layout.vk_start()],
+    )?;
+    values
+        .chunks(2 + layout.carried_degree_pow)
+        .map(accumulator_from_values)
+        .collect()
+}
+
+/// Parses an accumulator from the coordinates of its `G` point followed by its `u_j`s.
+fn accumulator_from_values<C: HaloCurve>(values: &[C::BaseField]) -> Result<OldProof<C>> {
+    let halo_g = AffinePoint::nonzero(values[0], values[1]);
+    ensure!(halo_g.is_valid(), "The accumulator's G point is not on the curve");
+    Ok(OldProof {
+        halo_g,
+        halo_us: C::BaseField::try_convert_all(&values[2..])?,
+    })
+}
+
+/// The inverse of `accumulator_from_values`.
+fn accumulator_to_values<C: HaloCurve>(accumulator: &OldProof<C>) -> Result<Vec<C::BaseField>> {
+    Ok([
+        vec![accumulator.halo_g.x, accumulator.halo_g.y],
+        C::ScalarField::try_convert_all(&accumulator.halo_us)?,
+    ]
+    .concat())
+}
+
+/// An accumulator which is valid by construction. With each `u_j = 1`, every coefficient of `s`
+/// is one, so `G` is the sum of the first `2^degree_pow` Pedersen generators. It stands in for
+/// the accumulators of dummy proofs, which needn't be valid.
+fn dummy_accumulator<C: HaloCurve>(degree_pow: usize) -> OldProof<C> {
+    let halo_g = (0..1 << degree_pow)
+        .fold(ProjectivePoint::<C>::ZERO, |sum, i| {
+            sum + blake_hash_usize_to_curve::<C>(i)
+        })
+        .to_affine();
+    OldProof {
+        halo_g,
+        halo_us: vec![C::ScalarField::ONE; degree_pow],
+    }
+}
+
+/// Builds a placeholder for a proof of the circuit with the given verification key, for a
+/// recursive circuit whose dummy flag is set. It proves nothing, but as with an honest proof,
+/// each `n(r_j)` is square, which the recursive circuit's witness generation relies on.
+fn dummy_proof<C: HaloCurve>(
+    vk: &VerificationKey<C>,
+    public_inputs: &[C::ScalarField],
+    old_proofs: &[OldProof<C>],
+) -> Result<Proof<C>> {
+    let random_points = |n: usize| -> Vec<AffinePoint<C>> {
+        (0..n)
+            .map(|_| (C::convert(C::ScalarField::rand()) * C::GENERATOR_PROJECTIVE).to_affine())
+            .collect()
+    };
+    let random_opening_set = || {
+        let values: Vec<C::ScalarField> = (0..num_openings(old_proofs.len()))
+            .map(|_| rand_convertible::<C::ScalarField, C::BaseField>())
+            .collect();
+        OpeningSet::from_vec(&values, old_proofs.len())
+    };
+    let hash_name = Challenger::<C::BaseField>::new(vk.security_bits).hash_name();
+    let mut proof = Proof {
+        parameters: ProofParameters::new::<C>(hash_name, NUM_WIRES, vk.security_params()),
+        c_wires: random_points(NUM_WIRES),
+        c_plonk_z: random_points(1)[0],
+        c_plonk_t: random_points(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
+        c_pis_quotient: random_points(1)[0],
+        o_local: random_opening_set(),
+        o_right: random_opening_set(),
+        o_below: random_opening_set(),
+        halo_l: Vec::new(),
+        halo_r: Vec::new(),
+        halo_g: random_points(1)[0],
+        schnorr_proof: SchnorrProof {
+            r: random_points(1)[0],
+            z1: rand_convertible::<C::ScalarField, C::BaseField>(),
+            z2: rand_convertible::<C::ScalarField, C::BaseField>(),
+        },
+    };
+
+    // As the prover does, resample each pair (L_j, R_j) until n(r_j) is square. The challenges
+    // of a truncated proof are computed successfully iff this holds for each pair so far.
+    for _ in 0..log2_strict(vk.degree) {
+        let found = (0..64).any(|_| {
+            let (l, r) = (random_points(1)[0], random_points(1)[0]);
+            proof.halo_l.push(l);
+            proof.halo_r.push(r);
+            let challenger = Challenger::new(vk.security_bits);
+            if proof
+                .get_challenges(vk, public_inputs, old_proofs, challenger)
+                .is_ok()
+            {
+                return true;
+            }
+            proof.halo_l.pop();
+            proof.halo_r.pop();
+            false
+        });
+        ensure!(found, "Failed to sample the IPA of a dummy proof");
+    }
+    Ok(proof)
+}
+
+/// Samples a random element of `F` which also fits in `G`.
+fn rand_convertible<F: Field, G: Field>() -> F {
+    loop {
+        let x = F::rand();
+        if x.try_convert::<G>().is_ok() {
+            return x;
+        }
+    }
+}
+
+/// At the end of a recursive chain, completes the verification of the proof verified by the last
+/// recursive circuit, given that circuit's public inputs. This natively performs the checks which
+/// the next circuit would perform in `verify_assumptions`, and the deferred check of the
+/// accumulator's `G` point, with `verify_accumulators`.
+///
+/// The last proof itself should be fully verified with `verify_proof`, with its carried
+/// accumulator, if any, as an old proof.
+///
+/// If the verified proof's verification key was an input of the last recursive circuit, this also
+/// checks that it was `vk`.
+///
+/// If the last recursive circuit's dummy flag is set, there is nothing to check. Applications
+/// which only allow dummy proofs in base cases should constrain the flag accordingly.
+pub fn finalize_recursion<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    public_inputs: &[C::BaseField],
+    layout: RecursionLayout,
+    vk: &VerificationKey<C>,
+) -> Result<()> {
+    ensure!(
+        1 << layout.degree_pow == vk.degree,
+        "The recursion layout doesn't match the verification key's degree"
+    );
+    vk.security_params().check::<C::ScalarField>()?;
+    ensure!(
+        public_inputs.len() == layout.num_public_inputs(),
+        "The public inputs don't match the recursion layout"
+    );
+    // TODO: We temporarily assume that each value fits in both fields.
+    let values = C::BaseField::try_convert_all::<C::ScalarField>(public_inputs)?;
+    let num_openings = num_openings(layout.num_old_proofs);
+    let mut values = values.iter().copied();
+    let mut take = |n: usize| (&mut values).take(n).collect::<Vec<_>>();
+    if take(1)[0] == C::ScalarField::ONE {
+        // There is no verified proof to complete the verification of.
+        return Ok(());
+    }
+    ensure!(
+        !layout.vk_is_input || public_inputs[layout.vk_start()..] == verification_key_values(vk)[..],
+        "The verification key input doesn't match the given key"
+    );
+    let challenges = take(6);
+    let (beta, gamma, alpha, zeta, v, u) = (
+        challenges[0],
+        challenges[1],
+        challenges[2],
+        challenges[3],
+        challenges[4],
+        challenges[5],
+    );
+    let o_local = OpeningSet::from_vec(&take(num_openings), layout.num_old_proofs);
+    let o_right = OpeningSet::from_vec(&take(num_openings), layout.num_old_proofs);
+    let o_below = OpeningSet::from_vec(&take(num_openings), layout.num_old_proofs);
+    let u_powers = take(num_openings);
+    let reduced_opening = take(1)[0];
+    let halo_b = take(1)[0];
+    let ipa_challenges = take(layout.degree_pow);
+    let _halo_g = take(2);
+    let halo_us = take(layout.degree_pow);
+
+    ensure!(
+        quotient_opening_is_valid::<C, InnerC>(
+            vk.num_routed_wires,
+            vk.degree,
+            beta,
+            gamma,
+            alpha,
+            zeta,
+            &o_local,
+            &o_right,
+            &o_below,
+        ),
+        VerificationError::InvalidQuotientOpening
+    );
+
+    let opening_sets = [o_local.to_vec(), o_right.to_vec(), o_below.to_vec()];
+    let deferred = compute_deferred_scalars::<C>(
+        zeta,
+        v,
+        u,
+        &opening_sets,
+        &ipa_challenges,
+        vk.challenge_len,
+    )?;
+    ensure!(u_powers == deferred.u_powers, "Incorrect powers of u");
+    ensure!(
+        reduced_opening == deferred.reduced_opening,
+        "Incorrect reduced opening"
+    );
+    for (&r, &u_j) in ipa_challenges.iter().zip(&halo_us) {
+        ensure!(
+            u_j.square() == halo_n::<C>(&r.to_canonical_bool_vec()[..vk.challenge_len]),
+            "Incorrect IPA challenge square root"
+        );
+    }
+    let subgroup_generator_n = C::ScalarField::primitive_root_of_unity(layout.degree_pow);
+    let points = [
+        zeta,
+        zeta * subgroup_generator_n,
+        zeta * subgroup_generator_n.exp_usize(GRID_WIDTH),
+    ];
+    let halo_bs: Vec<C::ScalarField> = points.iter().map(|&x| halo_g(x, &halo_us)).collect();
+    ensure!(halo_b == reduce_with_powers(&halo_bs, v), "Incorrect b");
+
+    for i in 0..layout.num_old_proofs {
+        let us = take(2 + layout.old_proof_degree_pow).split_off(2);
+        ensure!(
+            halo_g(zeta, &us) == o_local.o_old_proofs[i],
+            VerificationError::InvalidOldProofOpening(format!(
+                "{}-th old proof opening is incorrect",
+                i
+            ))
+        );
+    }
+
+    let accumulator = recursion_accumulator::<C>(public_inputs, layout)?;
+    verify_accumulators(&[accumulator], vk)?;
+    Ok(())
+}
+
+/// Builds a circuit which verifies a proof of the circuit with the given verification key. The
+/// inner circuit is assumed to have the default width, i.e. `NUM_WIRES` wires of which
+/// `NUM_ROUTED_WIRES` are routed.
+///
+/// If the inner circuit is itself a recursive circuit, `inner_layout` should be its layout. Its
+/// public inputs are then the deferred assumptions of the proof it verified, which this circuit
+/// checks. The accumulator it exported is carried as a public input of this circuit, and its
+/// proof should fold that accumulator as its only old proof; see `carried_accumulators`. The
+/// verified proof's own old proofs are tied to the accumulators carried by the inner circuit.
+///
+/// The circuit has a dummy flag as its first public input. When it is set, none of the checks of
+/// the verified proof are enforced, so the same circuit can be proved for a base case, such as
+/// the first step of a chain, with `populate_dummy_witness`. The next circuit in the cycle then
+/// skips the assumptions this circuit exported, and carries an accumulator which is valid by
+/// construction in place of the dummy proof's. Nothing else constrains the flag, so applications
+/// which only allow dummy proofs in base cases should do so themselves.
+///
+/// The public inputs of a verified proof which isn't recursive are not exported or otherwise
+/// bound. To bind them, use a `pcd_circuit` with a single predecessor, which requires its
+/// predicate to declare their `PublicInputBindings`.
 pub fn recursive_verification_circuit<
     C: HaloCurve,
     InnerC: HaloCurve<BaseField = C::ScalarField>,
 >(
-    degree_pow: usize,
-    security_bits: usize,
-    num_public_inputs: usize,
+    inner_vk: &VerificationKey<InnerC>,
     num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
 ) -> RecursiveCircuit<C, InnerC> {
-    let mut builder = CircuitBuilder::<C>::new(security_bits);
-    let public_inputs = RecursionPublicInputs {
-        beta: builder.add_public_input(),
-        gamma: builder.add_public_input(),
-        alpha: builder.add_public_input(),
-        zeta: builder.add_public_input(),
-        o_constants: builder.add_public_inputs(NUM_CONSTANTS),
-        o_plonk_sigmas: builder.add_public_inputs(NUM_ROUTED_WIRES),
-        o_local_wires: builder.add_public_inputs(NUM_WIRES),
-        o_right_wires: builder.add_public_inputs(NUM_WIRES),
-        o_below_wires: builder.add_public_inputs(NUM_WIRES),
-        o_plonk_z_local: builder.add_public_input(),
-        o_plonk_z_right: builder.add_public_input(),
-        o_plonk_t: builder.add_public_inputs(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
-        halo_us: builder.add_public_inputs(degree_pow),
-        old_proofs: builder.add_public_inputs((2 + degree_pow) * num_old_proofs),
+    build_recursive_circuit(inner_vk, num_old_proofs, inner_layout, false)
+}
+
+/// Like `recursive_verification_circuit`, but the inner circuit's verification key is an input
+/// rather than a constant, so the circuit can verify proofs of any circuit with the same degree
+/// and number of public inputs as `inner_vk`. The key should be populated with
+/// `RecursiveCircuit::populate_inner_vk`. It is exported after the other public inputs, and
+/// `finalize_recursion` checks it against the expected key.
+pub fn recursive_verification_circuit_with_vk_input<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    inner_vk: &VerificationKey<InnerC>,
+    num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
+) -> RecursiveCircuit<C, InnerC> {
+    build_recursive_circuit(inner_vk, num_old_proofs, inner_layout, true)
+}
+
+/// The size of a recursive circuit, as computed by `recursive_circuit_size`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RecursiveCircuitSize {
+    /// The number of gates used to verify the inner proof, excluding the blinding, public input
+    /// and padding gates which `build` adds.
+    pub num_gates: usize,
+    /// `log_2` of the degree of the built circuit.
+    pub degree_pow: usize,
+    pub num_public_inputs: usize,
+}
+
+/// Computes the size of the circuit `recursive_verification_circuit` would build for the same
+/// arguments. This only runs the circuit's builder code, skipping the much more expensive
+/// preprocessing of `build`, so it's a cheap way to choose the sizes of circuits in a chain, such
+/// as by padding an inner circuit until the circuit verifying it has the same degree.
+pub fn recursive_circuit_size<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    inner_vk: &VerificationKey<InnerC>,
+    num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
+) -> RecursiveCircuitSize {
+    size_of_recursive_circuit::<C, InnerC>(inner_vk, num_old_proofs, inner_layout, false)
+}
+
+/// Like `recursive_circuit_size`, for `recursive_verification_circuit_with_vk_input`.
+pub fn recursive_circuit_size_with_vk_input<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    inner_vk: &VerificationKey<InnerC>,
+    num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
+) -> RecursiveCircuitSize {
+    size_of_recursive_circuit::<C, InnerC>(inner_vk, num_old_proofs, inner_layout, true)
+}
+
+fn size_of_recursive_circuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    inner_vk: &VerificationKey<InnerC>,
+    num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
+    vk_is_input: bool,
+) -> RecursiveCircuitSize {
+    let (builder, layout, _, _) = recursive_circuit_builder::<C, InnerC>(
+        inner_vk,
+        num_old_proofs,
+        inner_layout,
+        vk_is_input,
+    );
+    RecursiveCircuitSize {
+        num_gates: builder.num_gates(),
+        degree_pow: log2_strict(builder.degree()),
+        num_public_inputs: layout.num_public_inputs(),
+    }
+}
+
+fn build_recursive_circuit<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    inner_vk: &VerificationKey<InnerC>,
+    num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
+    vk_is_input: bool,
+) -> RecursiveCircuit<C, InnerC> {
+    let (builder, layout, public_inputs, verified) =
+        recursive_circuit_builder(inner_vk, num_old_proofs, inner_layout, vk_is_input);
+    let circuit = builder.build();
+    RecursiveCircuit {
+        circuit,
+        layout,
+        public_inputs,
+        verified,
+    }
+}
+
+/// Runs the builder code of a recursive circuit, returning the builder before `build`.
+fn recursive_circuit_builder<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    inner_vk: &VerificationKey<InnerC>,
+    num_old_proofs: usize,
+    inner_layout: Option<RecursionLayout>,
+    vk_is_input: bool,
+) -> (
+    CircuitBuilder<C>,
+    RecursionLayout,
+    RecursionPublicInputs<C>,
+    VerifiedProofTarget<C, InnerC>,
+) {
+    let inner_layouts: Vec<RecursionLayout> = inner_layout.into_iter().collect();
+    let mut builder = CircuitBuilder::<C>::new(inner_vk.security_bits);
+    builder.set_security_params(inner_vk.security_params());
+    let inner_message_len = if inner_layouts.is_empty() {
+        inner_vk.num_public_inputs
+    } else {
+        0
     };
+    let (layout, outputs, verified) = verify_proof_recursively(
+        &mut builder,
+        inner_vk,
+        inner_message_len,
+        num_old_proofs,
+        &inner_layouts,
+        vk_is_input,
+    );
+    let public_inputs = RecursionPublicInputs::from_targets(
+        &builder.add_public_inputs(layout.num_public_inputs()),
+        layout,
+    );
+    for (pi, output) in public_inputs.to_vec().into_iter().zip(outputs.to_vec()) {
+        builder.copy(pi, output);
+    }
+    (builder, layout, public_inputs, verified)
+}
 
-    let num_public_input_gates = num_public_input_gates(num_public_inputs);
+/// Adds targets for a proof of the circuit with the given verification key, and partially
+/// verifies it. Returns the layout of the values which need to be exported for the next circuit
+/// to check, as in `recursive_verification_circuit`, along with those values.
+///
+/// The inner circuit's public inputs should consist of `inner_message_len` arbitrary values,
+/// followed by a part for each of `inner_layouts` if the inner circuit is itself recursive. If
+/// `vk_is_input`, `inner_vk` only determines the shape of the verified proof.
+pub(crate) fn verify_proof_recursively<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    builder: &mut CircuitBuilder<C>,
+    inner_vk: &VerificationKey<InnerC>,
+    inner_message_len: usize,
+    num_old_proofs: usize,
+    inner_layouts: &[RecursionLayout],
+    vk_is_input: bool,
+) -> (
+    RecursionLayout,
+    RecursionPublicInputs<C>,
+    VerifiedProofTarget<C, InnerC>,
+) {
+    assert_eq!(inner_vk.num_wires, NUM_WIRES, "Only the default width is supported");
+    assert_eq!(
+        inner_vk.num_routed_wires, NUM_ROUTED_WIRES,
+        "Only the default width is supported"
+    );
+    let challenge_len = inner_vk.challenge_len;
+    let layout = RecursionLayout::new(inner_vk, num_old_proofs, inner_layouts, vk_is_input);
+    let degree_pow = layout.degree_pow;
 
-    let proof = ProofTarget::<C, InnerC> {
-        c_wires: builder.add_virtual_point_targets(NUM_WIRES),
-        c_plonk_z: builder.add_virtual_point_target(),
-        c_plonk_t: builder.add_virtual_point_targets(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
-        o_public_inputs: Some(make_opening_sets(
-            &mut builder,
-            num_public_input_gates,
-            num_old_proofs,
-        )),
-        o_local: make_opening_set(&mut builder, num_old_proofs),
-        o_right: make_opening_set(&mut builder, num_old_proofs),
-        o_below: make_opening_set(&mut builder, num_old_proofs),
-        halo_l_i: builder.add_virtual_point_targets(degree_pow),
-        halo_r_i: builder.add_virtual_point_targets(degree_pow),
-        halo_g: builder.add_virtual_point_target(),
-        schnorr_proof: make_schnorr_proof(&mut builder),
+    // The checks of the verified proof are only enforced if it isn't a dummy.
+    let dummy = builder.add_virtual_target();
+    builder.assert_binary(dummy);
+    let enabled = builder.not(dummy);
+
+    let inner_vk_target = if vk_is_input {
+        Some(make_verification_key(builder))
+    } else {
+        None
     };
+    let inner_public_inputs = builder.add_virtual_targets(inner_vk.num_public_inputs);
+    let proof = make_proof(builder, degree_pow, num_old_proofs);
+    let old_proofs = make_old_proofs(builder, num_old_proofs, layout.old_proof_degree_pow);
 
-    let old_proofs = make_old_proofs(&mut builder, num_old_proofs, degree_pow);
+    assert_eq!(
+        inner_vk.num_public_inputs,
+        inner_message_len + inner_layouts.iter().map(|l| l.num_public_inputs()).sum::<usize>(),
+        "The inner circuit's public inputs don't match its recursion layouts"
+    );
+    let mut carried_accumulators = Vec::new();
+    if !inner_layouts.is_empty() {
+        // The verified proof must have folded the accumulators carried by the inner circuit, in
+        // order, as its old proofs.
+        let mut folded = old_proofs
+            .iter()
+            .map(|p| [p.halo_g.to_vec(), Target::convert_slice(&p.halo_us)].concat());
+        let mut inner_targets = &inner_public_inputs[inner_message_len..];
+        for &inner_layout in inner_layouts {
+            let (targets, rest) = inner_targets.split_at(inner_layout.num_public_inputs());
+            inner_targets = rest;
+            let inner_assumptions = RecursionPublicInputs::<C>::from_targets(targets, inner_layout);
+            // The assumptions exported about a dummy proof are meaningless.
+            let inner_enabled = builder.not(inner_assumptions.dummy);
+            let assumptions_enabled = builder.mul(enabled, inner_enabled);
+            verify_assumptions::<C, InnerC>(
+                builder,
+                inner_layout,
+                &inner_assumptions,
+                assumptions_enabled,
+            );
 
-    // Flatten the list of public input openings.
-    let o_public_inputs: Option<Vec<Target<C::ScalarField>>> =
-        proof.o_public_inputs.as_ref().map(|pis| {
-            pis.iter()
-                .cloned()
-                .flat_map(|opening_set| opening_set.o_wires)
-                .collect()
-        });
+            for carried in inner_assumptions
+                .carried_accumulators
+                .chunks(2 + inner_layout.carried_degree_pow)
+            {
+                let folded = folded.next().expect("Checked by the layout");
+                for (&folded, &carried) in folded.iter().zip(carried) {
+                    builder.conditional_copy(enabled, folded, carried);
+                }
+            }
 
-    verify_assumptions::<C, InnerC>(
-        &mut builder,
-        degree_pow,
-        &public_inputs,
-        // TODO: Fix this.
-        &o_public_inputs.unwrap(),
+            // Our proof can't fold the accumulator of a dummy proof, so we carry one which is
+            // valid by construction instead.
+            let dummy_values = builder.constant_wires(
+                &C::BaseField::try_convert_all(
+                    &accumulator_to_values(&dummy_accumulator::<C>(inner_layout.degree_pow))
+                        .expect("Improbable"),
+                )
+                .expect("Improbable"),
+            );
+            for (&value, &dummy_value) in inner_assumptions
+                .accumulator()
+                .iter()
+                .zip(&dummy_values)
+            {
+                let carried = builder.select(assumptions_enabled, value, dummy_value);
+                carried_accumulators.push(carried);
+            }
+        }
+    }
+
+    // Verify that each point supplied by the prover is on the curve.
+    for point in proof.all_points() {
+        builder.curve_assert_valid(point);
+    }
+    for old_proof in &old_proofs {
+        builder.curve_assert_valid(old_proof.halo_g);
+    }
+
+    let challenges = compute_challenges(
+        builder,
+        inner_vk,
+        inner_vk_target.as_ref(),
+        &inner_public_inputs,
+        &proof,
+        &old_proofs,
     );
 
-    // TODO: Verify that each prover polynomial commitment is on the curve.
-    // Can call curve_assert_valid.
+    // Witness the values which live in the inner proof's scalar field.
+    let num_openings = num_openings(num_old_proofs);
+    let u_powers = builder.add_virtual_targets(num_openings);
+    let reduced_opening = builder.add_virtual_target();
+    let halo_b = builder.add_virtual_target();
+    let halo_us = builder.add_virtual_targets(degree_pow);
+    builder.add_generator(DeferredScalarsGenerator::<C, InnerC> {
+        zeta: challenges.zeta,
+        v: challenges.v,
+        u: challenges.u,
+        openings: proof
+            .all_opening_sets()
+            .iter()
+            .map(|set| set.to_vec())
+            .collect(),
+        ipa_challenges: challenges.ipa_challenges.clone(),
+        u_powers: u_powers.clone(),
+        reduced_opening,
+        halo_b,
+        halo_us: halo_us.clone(),
+        challenge_len,
+        _phantom: PhantomData,
+    });
+
+    verify_all_ipas::<C, InnerC>(
+        builder,
+        inner_vk,
+        inner_vk_target.as_ref(),
+        &proof,
+        &old_proofs,
+        &challenges,
+        &u_powers,
+        reduced_opening,
+        halo_b,
+        enabled,
+    );
+
+    // "Outputs" data relating to assumptions which still need to be verified by the next proof.
+    let outputs = RecursionPublicInputs::<C> {
+        dummy,
+        beta: challenges.beta,
+        gamma: challenges.gamma,
+        alpha: challenges.alpha,
+        zeta: challenges.zeta,
+        v: challenges.v,
+        u: challenges.u,
+        o_local: proof.o_local.clone(),
+        o_right: proof.o_right.clone(),
+        o_below: proof.o_below.clone(),
+        u_powers,
+        reduced_opening,
+        halo_b,
+        ipa_challenges: challenges.ipa_challenges,
+        halo_g_x: proof.halo_g.x,
+        halo_g_y: proof.halo_g.y,
+        halo_us,
+        old_proofs: old_proofs
+            .iter()
+            .flat_map(|p| [p.halo_g.to_vec(), Target::convert_slice(&p.halo_us)].concat())
+            .collect(),
+        carried_accumulators,
+        vk: inner_vk_target
+            .as_ref()
+            .map(|vk| vk.to_vec())
+            .unwrap_or_default(),
+    };
+    let verified = VerifiedProofTarget {
+        proof,
+        inner_public_inputs,
+        old_proofs,
+        inner_vk: inner_vk_target,
+        dummy,
+    };
+    (layout, outputs, verified)
+}
+
+/// The Fiat-Shamir challenges of a proof, as computed in a recursive circuit.
+struct ChallengeTargets<F: Field> {
+    beta: Target<F>,
+    gamma: Target<F>,
+    alpha: Target<F>,
+    zeta: Target<F>,
+    v: Target<F>,
+    u: Target<F>,
+    u_scaling: Target<F>,
+    ipa_challenges: Vec<Target<F>>,
+    schnorr_challenge: Target<F>,
+}
+
+/// Computes the challenges of a proof, observing the same transcript as `Proof::get_challenges`.
+/// If `inner_vk_target` is given, the parts of the key which it contains are observed from it.
+fn compute_challenges<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    builder: &mut CircuitBuilder<C>,
+    inner_vk: &VerificationKey<InnerC>,
+    inner_vk_target: Option<&VerificationKeyTarget<InnerC>>,
+    inner_public_inputs: &[Target<C::ScalarField>],
+    proof: &ProofTarget<C, InnerC>,
+    old_proofs: &[OldProofTarget<InnerC>],
+) -> ChallengeTargets<C::ScalarField> {
+    let mut challenger = RecursiveChallenger::<C::ScalarField>::new(builder);
+
+    // Observe the statement, as `observe_statement` does.
+    challenger.observe_label(builder, PLONK_TRANSCRIPT_LABEL);
+    let sizes: Vec<C::ScalarField> = [
+        inner_vk.degree,
+        inner_vk.num_wires,
+        inner_vk.num_routed_wires,
+        inner_vk.num_public_inputs,
+    ]
+    .iter()
+    .map(|&n| C::ScalarField::from_canonical_usize(n))
+    .collect();
+    challenger.observe_elements(&builder.constant_wires(&sizes));
+    match inner_vk_target {
+        Some(vk) => {
+            challenger.observe_element(vk.num_gates_without_pis);
+            let circuit_coordinates: Vec<Target<C::ScalarField>> =
+                vk.commitments().iter().flat_map(|c| c.to_vec()).collect();
+            challenger.observe_elements(&circuit_coordinates);
+        }
+        None => {
+            let num_gates = C::ScalarField::from_canonical_usize(inner_vk.num_gates_without_pis);
+            challenger.observe_element(builder.constant_wire(num_gates));
+            let circuit_coordinates: Vec<C::ScalarField> = inner_vk
+                .c_constants
+                .iter()
+                .chain(&inner_vk.c_s_sigmas)
+                .flat_map(|c| vec![c.x, c.y])
+                .collect();
+            challenger.observe_elements(&builder.constant_wires(&circuit_coordinates));
+        }
+    }
+    challenger.observe_elements(inner_public_inputs);
 
-    // Compute random challenges.
-    let mut challenger = RecursiveChallenger::<C::ScalarField>::new(&mut builder);
     challenger.observe_affine_points(&proof.c_wires);
-    let (beta, gamma) = challenger.get_2_challenges(&mut builder);
+    let (beta, gamma) = challenger.get_2_challenges(builder);
     challenger.observe_affine_point(proof.c_plonk_z);
-    let alpha = challenger.get_challenge(&mut builder);
+    let alpha = challenger.get_challenge(builder);
     challenger.observe_affine_points(&proof.c_plonk_t);
-    let zeta = challenger.get_challenge(&mut builder);
+    challenger.observe_affine_point(proof.c_pis_quotient);
+    for old_proof in old_proofs {
+        challenger.observe_affine_point(old_proof.halo_g);
+    }
+    let zeta = challenger.get_challenge(builder);
     challenger.observe_elements(&proof.all_opening_targets());
 
-    verify_old_proof_evaluation::<C, InnerC>(&mut builder, &old_proofs, &proof.o_local, zeta);
-
     // v: Challenge to combine different opening points.
     // u: Challenge to combine different polynomials.
     // u_scaling: Challenge for scaling u used in the IPA verification.
-    let (v, u, u_scaling) = challenger.get_3_challenges(&mut builder);
+    let (v, u, u_scaling) = challenger.get_3_challenges(builder);
 
     // Compute IPA challenges.
-    let mut raw_ipa_challenges = Vec::new();
-    for i in 0..degree_pow {
-        challenger.observe_affine_points(&[proof.halo_l_i[i], proof.halo_r_i[i]]);
-        let r = challenger.get_challenge(&mut builder);
-        raw_ipa_challenges.push(r);
+    let mut ipa_challenges = Vec::new();
+    for (&l, &r) in proof.halo_l_i.iter().zip(&proof.halo_r_i) {
+        challenger.observe_affine_points(&[l, r]);
+        ipa_challenges.push(challenger.get_challenge(builder));
     }
 
     // Compute challenge for Schnorr protocol.
-    let schnorr_challenge = challenger.get_challenge(&mut builder);
+    challenger.observe_affine_point(proof.schnorr_proof.r);
+    let schnorr_challenge = challenger.get_challenge(builder);
 
-    let halo_us = verify_all_ipas::<C, InnerC>(
-        &mut builder,
-        &proof,
+    ChallengeTargets {
+        beta,
+        gamma,
+        alpha,
         zeta,
-        u,
         v,
+        u,
         u_scaling,
-        raw_ipa_challenges,
+        ipa_challenges,
         schnorr_challenge,
-        num_public_input_gates,
-        security_bits,
-    );
-
-    // "Outputs" data relating to assumption which still need to be verified by the next proof.
-    builder.copy(public_inputs.beta, beta);
-    builder.copy(public_inputs.gamma, gamma);
-    builder.copy(public_inputs.alpha, alpha);
-    builder.copy(public_inputs.zeta, zeta);
-    for i in 0..NUM_CONSTANTS {
-        builder.copy(
-            public_inputs.o_constants[i],
-            proof.o_local.o_constants[i],
-        );
     }
-    for i in 0..NUM_ROUTED_WIRES {
-        builder.copy(
-            public_inputs.o_plonk_sigmas[i],
-            proof.o_local.o_plonk_sigmas[i],
-        );
-    }
-    for i in 0..NUM_WIRES {
-        builder.copy(
-            public_inputs.o_local_wires[i],
-            proof.o_local.o_wires[i],
-        );
-        builder.copy(
-            public_inputs.o_right_wires[i],
-            proof.o_right.o_wires[i],
-        );
-        builder.copy(
-            public_inputs.o_below_wires[i],
-            proof.o_below.o_wires[i],
-        );
-    }
-    builder.copy(
-        public_inputs.o_plonk_z_local,
-        proof.o_local.o_plonk_z,
-    );
-    builder.copy(
-        public_inputs.o_plonk_z_right,
-        proof.o_right.o_plonk_z,
-    );
-    for i in 0..degree_pow {
-        builder.copy(public_inputs.halo_us[i], halo_us[i]);
-    }
-    let shift = 2 + degree_pow;
-    for i in 0..num_old_proofs {
-        builder.copy(
-            old_proofs[i].halo_g.x,
-            public_inputs.old_proofs[shift * i],
-        );
-        builder.copy(
-            old_proofs[i].halo_g.y,
-            public_inputs.old_proofs[shift * i + 1],
-        );
-        for j in 0..degree_pow {
-            builder.copy(
-                old_proofs[i].halo_us[j].convert(),
-                public_inputs.old_proofs[shift * i + j + 2],
-            );
-        }
+}
+
+/// The values which a recursive circuit witnesses in the inner proof's scalar field.
+struct DeferredScalars<F: Field> {
+    u_powers: Vec<F>,
+    reduced_opening: F,
+    halo_b: F,
+    halo_us: Vec<F>,
+}
+
+/// Natively computes the values which a recursive circuit witnesses in `C::ScalarField`, in the
+/// same way as the native verifier.
+fn compute_deferred_scalars<C: HaloCurve>(
+    zeta: C::ScalarField,
+    v: C::ScalarField,
+    u: C::ScalarField,
+    opening_sets: &[Vec<C::ScalarField>],
+    ipa_challenges: &[C::ScalarField],
+    challenge_len: usize,
+) -> Result<DeferredScalars<C::ScalarField>> {
+    let u_powers = powers(u, opening_sets[0].len());
+    let actual_scalars: Vec<C::ScalarField> = u_powers
+        .iter()
+        .map(|u_pow| halo_n::<C>(&u_pow.to_canonical_bool_vec()[..challenge_len]))
+        .collect();
+    let opening_set_reductions: Vec<C::ScalarField> = opening_sets
+        .iter()
+        .map(|set| C::ScalarField::inner_product(set, &actual_scalars))
+        .collect();
+    let reduced_opening = reduce_with_powers(&opening_set_reductions, v);
+
+    let halo_us = ipa_challenges
+        .iter()
+        .map(|r| {
+            halo_n::<C>(&r.to_canonical_bool_vec()[..challenge_len])
+                .square_root()
+                .ok_or_else(|| anyhow!("Prover should have ensured that n(r) is square"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let subgroup_generator_n = C::ScalarField::primitive_root_of_unity(ipa_challenges.len());
+    let points = [
+        zeta,
+        zeta * subgroup_generator_n,
+        zeta * subgroup_generator_n.exp_usize(GRID_WIDTH),
+    ];
+    let halo_bs: Vec<C::ScalarField> = points.iter().map(|&x| halo_g(x, &halo_us)).collect();
+    let halo_b = reduce_with_powers(&halo_bs, v);
+
+    Ok(DeferredScalars {
+        u_powers,
+        reduced_opening,
+        halo_b,
+        halo_us,
+    })
+}
+
+/// Witnesses the values in `DeferredScalars`, given the challenges and openings they depend on.
+struct DeferredScalarsGenerator<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
+    zeta: Target<C::ScalarField>,
+    v: Target<C::ScalarField>,
+    u: Target<C::ScalarField>,
+    openings: Vec<Vec<Target<C::ScalarField>>>,
+    ipa_challenges: Vec<Target<C::ScalarField>>,
+    u_powers: Vec<Target<C::ScalarField>>,
+    reduced_opening: Target<C::ScalarField>,
+    halo_b: Target<C::ScalarField>,
+    halo_us: Vec<Target<C::ScalarField>>,
+    challenge_len: usize,
+    _phantom: PhantomData<InnerC>,
+}
+
+impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> WitnessGenerator<C::ScalarField>
+    for DeferredScalarsGenerator<C, InnerC>
+{
+    fn dependencies(&self) -> Vec<Target<C::ScalarField>> {
+        [
+            vec![self.zeta, self.v, self.u],
+            self.openings.concat(),
+            self.ipa_challenges.clone(),
+        ]
+        .concat()
     }
 
-    let circuit = builder.build();
-    RecursiveCircuit {
-        circuit,
-        public_inputs,
-        proof,
-        old_proofs,
+    fn generate(
+        &self,
+        _constants: &[Vec<C::ScalarField>],
+        witness: &PartialWitness<C::ScalarField>,
+    ) -> PartialWitness<C::ScalarField> {
+        // TODO: We temporarily assume that each of these values fits in both fields.
+        let get_scalars = |targets: &[Target<C::ScalarField>]| -> Vec<InnerC::ScalarField> {
+            C::ScalarField::try_convert_all(&witness.get_targets(targets))
+                .expect("Improbable")
+        };
+        let to_native = |values: &[InnerC::ScalarField]| -> Vec<C::ScalarField> {
+            InnerC::ScalarField::try_convert_all(values).expect("Improbable")
+        };
+
+        let challenges = get_scalars(&[self.zeta, self.v, self.u]);
+        let opening_sets: Vec<Vec<InnerC::ScalarField>> =
+            self.openings.iter().map(|set| get_scalars(set)).collect();
+        let deferred = compute_deferred_scalars::<InnerC>(
+            challenges[0],
+            challenges[1],
+            challenges[2],
+            &opening_sets,
+            &get_scalars(&self.ipa_challenges),
+            self.challenge_len,
+        )
+        .expect("Invalid IPA challenges");
+
+        let mut result = PartialWitness::new();
+        result.set_targets(&self.u_powers, &to_native(&deferred.u_powers));
+        result.set_targets(
+            &[self.reduced_opening, self.halo_b],
+            &to_native(&[deferred.reduced_opening, deferred.halo_b]),
+        );
+        result.set_targets(&self.halo_us, &to_native(&deferred.halo_us));
+        result
     }
 }
 
-/// Verify all IPAs in the given proof, and return IPA challenges.
+/// Verify all IPAs in the given proof, i.e. the group operations of the Halo opening protocol.
+/// The scalars used, which live in `InnerC::ScalarField`, are checked by the next proof. The final
+/// check is only enforced if `enabled` is one.
 #[allow(clippy::too_many_arguments)]
 fn verify_all_ipas<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     builder: &mut CircuitBuilder<C>,
+    inner_vk: &VerificationKey<InnerC>,
+    inner_vk_target: Option<&VerificationKeyTarget<InnerC>>,
     proof: &ProofTarget<C, InnerC>,
-    zeta: Target<C::ScalarField>,
-    u: Target<C::ScalarField>,
-    v: Target<C::ScalarField>,
-    u_scaling: Target<C::ScalarField>,
-    raw_ipa_challenges: Vec<Target<C::ScalarField>>,
-    schnorr_challenge: Target<C::ScalarField>,
-    num_public_input_gates: usize,
-    security_bits: usize,
-) -> Vec<Target<C::ScalarField>> {
+    old_proofs: &[OldProofTarget<InnerC>],
+    challenges: &ChallengeTargets<C::ScalarField>,
+    u_powers: &[Target<C::ScalarField>],
+    reduced_opening: Target<C::ScalarField>,
+    halo_b: Target<C::ScalarField>,
+    enabled: Target<C::ScalarField>,
+) {
     // Reduce all polynomial commitments to a single one, i.e. a random combination of them.
-    // TODO: Configure the actual constants and permutations of whatever circuit we wish to verify.
-    // For now, we use a dummy point for each of those polynomial commitments.
-    let dummy_point = builder.constant_affine_point(InnerC::GENERATOR_AFFINE);
-    let c_constants = vec![dummy_point; NUM_CONSTANTS];
-    let c_s_sigmas = vec![dummy_point; NUM_ROUTED_WIRES];
-    let c_all: Vec<AffinePointTarget<InnerC>> = [
-        c_constants,
-        c_s_sigmas,
+    let proof_commitments: Vec<AffinePointTarget<InnerC>> = [
         proof.c_wires.clone(),
         vec![proof.c_plonk_z],
         proof.c_plonk_t.clone(),
+        old_proofs.iter().map(|p| p.halo_g).collect(),
+        vec![proof.c_pis_quotient],
     ]
     .concat();
-    let mut c_reduction_muls = Vec::new();
-    let powers_of_u = powers_recursive(builder, u, c_all.len());
-    for (&c, &power) in c_all.iter().zip(powers_of_u.iter()) {
-        let mul = CurveMulOp {
-            scalar: power,
-            point: c,
-        };
-        c_reduction_muls.push(mul);
-    }
-    let c_reduction_msm_result = builder.curve_msm_endo::<InnerC>(&c_reduction_muls);
-    let actual_scalars = c_reduction_msm_result.actual_scalars;
-    let c_reduction = c_reduction_msm_result.msm_result;
-
-    // For each opening set, we do a similar reduction, using the actual scalars above.
-    let opening_set_reductions: Vec<Target<C::ScalarField>> = proof
-        .all_opening_sets()
-        .iter()
-        .map(|opening_set| {
-            reduce_with_coefficients(builder, &opening_set.to_vec(), &actual_scalars)
-        })
-        .collect();
-
-    // Then, we reduce the above opening set reductions to a single value.
-    let reduced_opening = reduce_with_powers_recursive(builder, &opening_set_reductions, v);
+    let c_reduction = match inner_vk_target {
+        Some(vk) => reduce_commitments_with_vk_input(builder, vk, proof_commitments, u_powers),
+        None => {
+            // Some circuit commitments may be zero, in which case we skip them.
+            let circuit_commitments: Vec<Option<AffinePointTarget<InnerC>>> = inner_vk
+                .c_constants
+                .iter()
+                .chain(&inner_vk.c_s_sigmas)
+                .map(|&c| {
+                    if c.zero {
+                        None
+                    } else {
+                        Some(builder.constant_affine_point(c))
+                    }
+                })
+                .collect();
+            let c_all = circuit_commitments
+                .into_iter()
+                .chain(proof_commitments.into_iter().map(Some));
+            let c_reduction_muls: Vec<CurveMulOp<C, InnerC>> = c_all
+                .zip(u_powers)
+                .filter_map(|(c, &scalar)| c.map(|point| CurveMulOp { scalar, point }))
+                .collect();
+            builder.curve_msm_endo::<InnerC>(&c_reduction_muls).msm_result
+        }
+    };
 
     // u is set to H(degree + 1).
-    let u = builder.constant_affine_point(hash_usize_to_curve::<InnerC>(
-        (1 << raw_ipa_challenges.len()) + 1,
-        security_bits,
-    ));
+    let u = builder.constant_affine_point(blake_hash_usize_to_curve::<InnerC>(inner_vk.degree + 1));
     // u' is u scaled by n(u_scaling), giving a random generator.
     let u_prime = builder
         .curve_mul_endo::<InnerC>(CurveMulOp {
-            scalar: u_scaling,
+            scalar: challenges.u_scaling,
             point: u,
         })
         .mul_result;
@@ -290,50 +1389,90 @@ fn verify_all_ipas<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     });
     let p_prime = builder.curve_add::<InnerC>(c_reduction, v_u_prime);
 
-    let (halo_q, halo_us) =
-        compute_halo_q::<C, InnerC>(builder, proof, &raw_ipa_challenges, p_prime);
-
-    let generator_n_value = C::ScalarField::primitive_root_of_unity(raw_ipa_challenges.len());
-    let generator_n = builder.constant_wire(generator_n_value);
-    let mut points: Vec<Target<C::ScalarField>> = (0..2 * num_public_input_gates)
-        .scan(C::ScalarField::ONE, |acc, _| {
-            let tmp = *acc;
-            *acc = *acc * generator_n_value;
-            Some(tmp)
-        })
-        .step_by(2)
-        .map(|f| builder.constant_wire(f))
-        .collect();
-    points.extend(&[zeta, builder.mul(zeta, generator_n), {
-        let g65 = builder.exp_constant_usize(generator_n, GRID_WIDTH);
-        builder.mul(zeta, g65)
-    }]);
-    let halo_bs = points
-        .into_iter()
-        .map(|p| halo_g_recursive(builder, p, &halo_us))
-        .collect::<Vec<_>>();
-    let halo_b = reduce_with_powers_recursive(builder, &halo_bs, v);
+    let halo_q = compute_halo_q::<C, InnerC>(builder, proof, &challenges.ipa_challenges, p_prime);
 
     verify_schnorr::<C, InnerC>(
         builder,
+        inner_vk,
         proof,
         u_prime,
         halo_q,
         halo_b,
-        schnorr_challenge,
-        security_bits,
+        challenges.schnorr_challenge,
+        enabled,
     );
+}
 
-    halo_us
+/// Reduces the circuit commitments of `vk`, followed by `proof_commitments`, to a random
+/// combination of them.
+///
+/// Since the circuit commitments are inputs, any of them may be zero, i.e. `(0, 0)`, which the
+/// curve gadgets can't handle. So we shift each circuit commitment by a fixed offset, and subtract
+/// the offsets' combination afterward.
+fn reduce_commitments_with_vk_input<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    builder: &mut CircuitBuilder<C>,
+    vk: &VerificationKeyTarget<InnerC>,
+    proof_commitments: Vec<AffinePointTarget<InnerC>>,
+    u_powers: &[Target<C::ScalarField>],
+) -> AffinePointTarget<InnerC> {
+    let mut shifted_muls = Vec::new();
+    let mut offset_muls = Vec::new();
+    for (i, (c, &scalar)) in vk.commitments().into_iter().zip(u_powers).enumerate() {
+        // We avoid -1, which the MSM gadget uses for its own filler point.
+        let offset = blake_hash_base_field_to_curve::<InnerC>(
+            InnerC::BaseField::NEG_ONE - InnerC::BaseField::from_canonical_usize(i + 1),
+        );
+        let offset_target = builder.constant_affine_point(offset);
+        let point = shift_commitment(builder, c, offset);
+        shifted_muls.push(CurveMulOp { scalar, point });
+        offset_muls.push(CurveMulOp {
+            scalar,
+            point: offset_target,
+        });
+    }
+    let num_circuit_commitments = shifted_muls.len();
+    shifted_muls.extend(
+        proof_commitments
+            .into_iter()
+            .zip(&u_powers[num_circuit_commitments..])
+            .map(|(point, &scalar)| CurveMulOp { scalar, point }),
+    );
+
+    let shifted_reduction = builder.curve_msm_endo::<InnerC>(&shifted_muls).msm_result;
+    let offset_reduction = builder.curve_msm_endo::<InnerC>(&offset_muls).msm_result;
+    builder.curve_sub::<InnerC>(shifted_reduction, offset_reduction)
+}
+
+/// Computes `c + offset`, where `c` is either a valid curve point or zero, i.e. `(0, 0)`.
+fn shift_commitment<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    builder: &mut CircuitBuilder<C>,
+    c: AffinePointTarget<InnerC>,
+    offset: AffinePoint<InnerC>,
+) -> AffinePointTarget<InnerC> {
+    // Our curves have no points with y = 0, so that identifies zero, provided x = 0 as well.
+    let is_zero = builder.is_zero(c.y);
+    let zero_x = builder.mul(is_zero, c.x);
+    builder.assert_zero(zero_x);
+
+    // If c is zero, we add the offset to a placeholder point instead, and discard the sum.
+    let offset_target = builder.constant_affine_point(offset);
+    let placeholder = builder.constant_affine_point(offset.double());
+    let addend = builder.curve_select(is_zero, placeholder, c);
+    builder.curve_assert_valid(addend);
+    let sum = builder.curve_add::<InnerC>(addend, offset_target);
+    builder.curve_select(is_zero, offset_target, sum)
 }
 
-/// Computes `Q` in the context of the Halo paper. Returns `(Q, halo_us)`.
+/// Computes `Q` in the context of the Halo paper.
 fn compute_halo_q<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     builder: &mut CircuitBuilder<C>,
     proof: &ProofTarget<C, InnerC>,
     raw_ipa_challenges: &[Target<C::ScalarField>],
     p_prime: AffinePointTarget<InnerC>,
-) -> (AffinePointTarget<InnerC>, Vec<Target<C::ScalarField>>) {
+) -> AffinePointTarget<InnerC> {
     let mut sum = p_prime;
 
     // The summation of the L_i terms has the structure of an MSM.
@@ -361,38 +1500,34 @@ fn compute_halo_q<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
         builder.copy(actual_scalar, l_msm_result.actual_scalars[i]);
     }
 
-    let halo_us = l_msm_result
-        .actual_scalars
-        .iter()
-        .map(|&n_r| builder.deterministic_square_root(n_r))
-        .collect();
-
-    (sum, halo_us)
+    sum
 }
 
-/// Verify the final Schnorr protocol used in Halo.
+/// Verify the final Schnorr protocol used in Halo, if `enabled` is one.
+#[allow(clippy::too_many_arguments)]
 fn verify_schnorr<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     builder: &mut CircuitBuilder<C>,
+    inner_vk: &VerificationKey<InnerC>,
     proof: &ProofTarget<C, InnerC>,
     u_prime: AffinePointTarget<InnerC>,
     halo_q: AffinePointTarget<InnerC>,
     halo_b: Target<C::ScalarField>,
     schnorr_challenge: Target<C::ScalarField>,
-    security_bits: usize,
+    enabled: Target<C::ScalarField>,
 ) {
     // The H point used for blinding polynomial commitments. Set to H(degree).
-    let pedersen_h = builder.constant_affine_point(hash_usize_to_curve::<InnerC>(
-        1 << proof.halo_l_i.len(),
-        security_bits,
-    ));
+    let pedersen_h =
+        builder.constant_affine_point(blake_hash_usize_to_curve::<InnerC>(inner_vk.degree));
 
     // Perform ZK opening protocol.
-    // LHS is [schnorr_challenge] * q + schnorr_proof.r.
+    // LHS is [n(schnorr_challenge)] * q + schnorr_proof.r.
     // RHS is [schnorr_proof.z1] * (halo_g + [halo_b] * u') + [schnorr_proof.z2] * pedersen_h.
-    let lhs = builder.curve_mul::<InnerC>(CurveMulOp {
-        scalar: schnorr_challenge,
-        point: halo_q,
-    });
+    let lhs = builder
+        .curve_mul_endo::<InnerC>(CurveMulOp {
+            scalar: schnorr_challenge,
+            point: halo_q,
+        })
+        .mul_result;
     let lhs = builder.curve_add::<InnerC>(lhs, proof.schnorr_proof.r);
     let rhs = builder.curve_mul::<InnerC>(CurveMulOp {
         scalar: halo_b,
@@ -409,30 +1544,48 @@ fn verify_schnorr<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     });
     let rhs = builder.curve_add::<InnerC>(rhs, tmp);
     // LHS should be equal to RHS.
-    builder.copy_curve(lhs, rhs);
+    builder.conditional_copy(enabled, lhs.x, rhs.x);
+    builder.conditional_copy(enabled, lhs.y, rhs.y);
 }
 
-fn make_opening_set<C: HaloCurve>(
+fn make_verification_key<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     builder: &mut CircuitBuilder<C>,
+) -> VerificationKeyTarget<InnerC> {
+    VerificationKeyTarget {
+        c_constants: builder.add_virtual_point_targets(NUM_CONSTANTS),
+        c_s_sigmas: builder.add_virtual_point_targets(NUM_ROUTED_WIRES),
+        num_gates_without_pis: builder.add_virtual_target(),
+    }
+}
+
+fn make_proof<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    builder: &mut CircuitBuilder<C>,
+    degree_pow: usize,
     num_old_proofs: usize,
-) -> OpeningSetTarget<C> {
-    OpeningSetTarget {
-        o_constants: builder.add_virtual_targets(NUM_CONSTANTS),
-        o_plonk_sigmas: builder.add_virtual_targets(NUM_ROUTED_WIRES),
-        o_wires: builder.add_virtual_targets(NUM_WIRES),
-        o_plonk_z: builder.add_virtual_target(),
-        o_plonk_t: builder.add_virtual_targets(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
-        o_old_proofs: builder.add_virtual_targets(num_old_proofs),
+) -> ProofTarget<C, InnerC> {
+    ProofTarget {
+        c_wires: builder.add_virtual_point_targets(NUM_WIRES),
+        c_plonk_z: builder.add_virtual_point_target(),
+        c_plonk_t: builder.add_virtual_point_targets(QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER),
+        c_pis_quotient: builder.add_virtual_point_target(),
+        o_local: make_opening_set(builder, num_old_proofs),
+        o_right: make_opening_set(builder, num_old_proofs),
+        o_below: make_opening_set(builder, num_old_proofs),
+        halo_l_i: builder.add_virtual_point_targets(degree_pow),
+        halo_r_i: builder.add_virtual_point_targets(degree_pow),
+        halo_g: builder.add_virtual_point_target(),
+        schnorr_proof: make_schnorr_proof(builder),
     }
 }
-fn make_opening_sets<C: HaloCurve>(
+
+fn make_opening_set<C: HaloCurve>(
     builder: &mut CircuitBuilder<C>,
-    n: usize,
     num_old_proofs: usize,
-) -> Vec<OpeningSetTarget<C>> {
-    (0..n)
-        .map(|_i| make_opening_set(builder, num_old_proofs))
-        .collect()
+) -> OpeningSetTarget<C> {
+    OpeningSetTarget::from_vec(
+        &builder.add_virtual_targets(num_openings(num_old_proofs)),
+        num_old_proofs,
+    )
 }
 
 fn make_schnorr_proof<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
@@ -461,56 +1614,36 @@ fn make_old_proofs<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
 /// In our recursion scheme, to avoid non-native field arithmetic, each proof in a recursive chain
 /// only partially verifies its inner proof. It outputs various challenges and openings, and the
 /// following proof is expected to verify constraints upon that data. This function performs those
-/// final verification steps.
+/// final verification steps. They are only enforced if `enabled` is one.
 fn verify_assumptions<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
     builder: &mut CircuitBuilder<C>,
-    degree_pow: usize,
-    public_inputs: &RecursionPublicInputs<C::ScalarField>,
-    o_public_inputs: &[Target<C::ScalarField>],
+    layout: RecursionLayout,
+    public_inputs: &RecursionPublicInputs<C>,
+    enabled: Target<C::ScalarField>,
 ) {
+    let RecursionPublicInputs {
+        beta,
+        gamma,
+        alpha,
+        zeta,
+        v,
+        u,
+        ref o_local,
+        ref o_right,
+        ref o_below,
+        ..
+    } = *public_inputs;
+    let degree_pow = layout.degree_pow;
     let degree = 1 << degree_pow;
     let degree_f = C::ScalarField::from_canonical_usize(degree);
     let degree_wire = builder.constant_wire(degree_f);
 
     let one = builder.one_wire();
 
-    // Convert inner proof data from `PublicInput`s to `Target`s.
-    let o_constants: Vec<Target<C::ScalarField>> = public_inputs
-        .o_constants
-        .iter()
-        .map(|pi| o_public_inputs[pi.index()])
-        .collect();
-    let o_sigmas: Vec<Target<C::ScalarField>> = public_inputs
-        .o_plonk_sigmas
-        .iter()
-        .map(|pi| o_public_inputs[pi.index()])
-        .collect();
-    let o_local_wires: Vec<Target<C::ScalarField>> = public_inputs
-        .o_local_wires
-        .iter()
-        .map(|pi| o_public_inputs[pi.index()])
-        .collect();
-    let o_right_wires: Vec<Target<C::ScalarField>> = public_inputs
-        .o_right_wires
-        .iter()
-        .map(|pi| o_public_inputs[pi.index()])
-        .collect();
-    let o_below_wires: Vec<Target<C::ScalarField>> = public_inputs
-        .o_below_wires
-        .iter()
-        .map(|pi| o_public_inputs[pi.index()])
-        .collect();
-    let beta = o_public_inputs[public_inputs.beta.index()];
-    let gamma = o_public_inputs[public_inputs.gamma.index()];
-    let alpha = o_public_inputs[public_inputs.alpha.index()];
-    let zeta = o_public_inputs[public_inputs.zeta.index()];
-    let o_z_local = o_public_inputs[public_inputs.o_plonk_z_local.index()];
-    let o_z_right = o_public_inputs[public_inputs.o_plonk_z_right.index()];
-
     // Evaluate zeta^degree.
     let mut zeta_power_d = zeta;
     for _i in 0..degree_pow {
-        zeta_power_d = builder.double(zeta_power_d);
+        zeta_power_d = builder.square(zeta_power_d);
     }
 
     // Evaluate Z_H(zeta) = zeta^degree - 1.
@@ -528,26 +1661,26 @@ fn verify_assumptions<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField
         let k_i = builder.constant_wire(get_subgroup_shift::<C::ScalarField>(i));
         let s_id = builder.mul(k_i, zeta);
         let beta_s_id = builder.mul(beta, s_id);
-        let beta_s_sigma = builder.mul(beta, o_sigmas[i]);
-        let f_prime_part = builder.add_many(&[o_local_wires[i], beta_s_id, gamma]);
-        let g_prime_part = builder.add_many(&[o_local_wires[i], beta_s_sigma, gamma]);
+        let beta_s_sigma = builder.mul(beta, o_local.o_plonk_sigmas[i]);
+        let f_prime_part = builder.add_many(&[o_local.o_wires[i], beta_s_id, gamma]);
+        let g_prime_part = builder.add_many(&[o_local.o_wires[i], beta_s_sigma, gamma]);
         f_prime = builder.mul(f_prime, f_prime_part);
         g_prime = builder.mul(g_prime, g_prime_part);
     }
-    let z_f_prime = builder.mul(o_z_local, f_prime);
-    let z_shifted_g_prime = builder.mul(o_z_right, g_prime);
+    let z_f_prime = builder.mul(o_local.o_plonk_z, f_prime);
+    let z_shifted_g_prime = builder.mul(o_right.o_plonk_z, g_prime);
     let vanishing_v_shift_term = builder.sub(z_f_prime, z_shifted_g_prime);
 
     // Evaluate the function which is supposed to vanish on H. It is a sum of several terms which
     // should vanish, each weighted by a different power of alpha.
-    let o_z_minus_1 = builder.sub(o_z_local, one);
+    let o_z_minus_1 = builder.sub(o_local.o_plonk_z, one);
     let vanishing_z_1_term = builder.mul(o_z_minus_1, lagrange_1_eval);
     let constraint_terms = evaluate_all_constraints_recursively::<C, InnerC>(
         builder,
-        &o_constants,
-        &o_local_wires,
-        &o_right_wires,
-        &o_below_wires,
+        &o_local.o_constants,
+        &o_local.o_wires,
+        &o_right.o_wires,
+        &o_below.o_wires,
     );
     let vanishing_terms = [
         vec![vanishing_z_1_term],
@@ -559,13 +1692,64 @@ fn verify_assumptions<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField
 
     // Evaluate the quotient polynomial, and assert that it matches the prover's opening.
     let quotient_eval = builder.div(vanishing_eval, zero_eval);
-    let t_components: Vec<Target<C::ScalarField>> = public_inputs
-        .o_plonk_t
+    let o_plonk_t_eval = eval_composite_poly(builder, &o_local.o_plonk_t, zeta_power_d);
+    builder.conditional_copy(enabled, quotient_eval, o_plonk_t_eval);
+
+    // TODO: Verify the opening of the public input quotient polynomial. This requires the inner
+    // proof's public inputs, which we would need to export in turn.
+
+    // Check the powers of u, and the combined opening which was computed from them.
+    let u_powers = powers_recursive(builder, u, public_inputs.u_powers.len());
+    for (&computed, &purported) in u_powers.iter().zip(&public_inputs.u_powers) {
+        builder.conditional_copy(enabled, computed, purported);
+    }
+    let actual_scalars: Vec<Target<C::ScalarField>> = u_powers
+        .iter()
+        .map(|&u_power| halo_n_recursive(builder, u_power))
+        .collect();
+    let opening_set_reductions: Vec<Target<C::ScalarField>> = [o_local, o_right, o_below]
+        .iter()
+        .map(|opening_set| {
+            reduce_with_coefficients(builder, &opening_set.to_vec(), &actual_scalars)
+        })
+        .collect();
+    let reduced_opening = reduce_with_powers_recursive(builder, &opening_set_reductions, v);
+    builder.conditional_copy(enabled, reduced_opening, public_inputs.reduced_opening);
+
+    // Check that u_j^2 = n(r_j) for each IPA challenge.
+    for (&r, &u_j) in public_inputs
+        .ipa_challenges
+        .iter()
+        .zip(&public_inputs.halo_us)
+    {
+        let n_r = halo_n_recursive(builder, r);
+        let u_j_squared = builder.square(u_j);
+        builder.conditional_copy(enabled, u_j_squared, n_r);
+    }
+
+    // Check b, i.e. the combined evaluation of g at each opening point.
+    let generator_n = builder.constant_wire(C::ScalarField::primitive_root_of_unity(degree_pow));
+    let generator_n_65 = builder.exp_constant_usize(generator_n, GRID_WIDTH);
+    let points = [
+        zeta,
+        builder.mul(zeta, generator_n),
+        builder.mul(zeta, generator_n_65),
+    ];
+    let halo_bs: Vec<Target<C::ScalarField>> = points
         .iter()
-        .map(|pi| o_public_inputs[pi.index()])
+        .map(|&p| halo_g_recursive(builder, p, &public_inputs.halo_us))
         .collect();
-    let o_plonk_t_eval = eval_composite_poly(builder, &t_components, zeta_power_d);
-    builder.copy(quotient_eval, o_plonk_t_eval);
+    let halo_b = reduce_with_powers_recursive(builder, &halo_bs, v);
+    builder.conditional_copy(enabled, halo_b, public_inputs.halo_b);
+
+    // Check the openings of the old proofs' g polynomials.
+    for i in 0..layout.num_old_proofs {
+        let us = public_inputs
+            .old_proof_us(i, layout.old_proof_degree_pow)
+            .to_vec();
+        let computed = halo_g_recursive(builder, zeta, &us);
+        builder.conditional_copy(enabled, computed, o_local.o_old_proofs[i]);
+    }
 }
 
 /// Computes a sum of terms weighted by the given coefficients.
@@ -603,19 +1787,194 @@ fn halo_g_recursive<C: HaloCurve>(
         let u_i_inv = builder.inv(u_i);
         let term = builder.mul_add(u_i, x_power, u_i_inv);
         product = builder.mul(product, term);
-        x_power = builder.double(x_power);
+        x_power = builder.square(x_power);
     }
     product
 }
 
-fn verify_old_proof_evaluation<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
-    builder: &mut CircuitBuilder<C>,
-    old_proofs: &[OldProofTarget<InnerC>],
-    o_local: &OpeningSetTarget<C>,
-    zeta: Target<C::ScalarField>,
-) {
-    for (i, p) in old_proofs.iter().enumerate() {
-        let computed = halo_g_recursive(builder, zeta, &Target::convert_slice(&p.halo_us));
-        builder.copy(computed, o_local.o_old_proofs[i]);
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::plonk_challenger::Challenger;
+    use crate::plonk_recursion::{compute_challenges, dummy_accumulator, finalize_recursion, make_proof, make_verification_key, recursion_public_inputs, verify_assumptions, RecursionLayout, RecursionPublicInputs};
+    use crate::util::log2_strict;
+    use crate::{verify_accumulators, CircuitBuilder, Curve, Field, PartialWitness, Proof, Tweedledee, Tweedledum, VerificationKey};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// Proves knowledge of a cube root of a public input.
+    fn cube_root_proof() -> Result<(VerificationKey<Tweedledee>, Proof<Tweedledee>, Vec<F>)> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let pi = builder.add_public_input();
+        let t = builder.add_virtual_target();
+        let t_cubed = builder.mul_many(&[t, t, t]);
+        builder.copy(t_cubed, pi);
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(t, F::from_canonical_usize(3));
+        let circuit = builder.build();
+        let witness = circuit.generate_witness(partial_witness)?;
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        Ok((circuit.to_vk(), proof, vec![F::from_canonical_usize(27)]))
+    }
+
+    #[test]
+    fn test_recursive_challenges() -> Result<()> {
+        check_recursive_challenges(false)
+    }
+
+    #[test]
+    fn test_recursive_challenges_with_vk_input() -> Result<()> {
+        check_recursive_challenges(true)
+    }
+
+    fn check_recursive_challenges(vk_is_input: bool) -> Result<()> {
+        type BF = <Tweedledee as Curve>::BaseField;
+        let (vk, proof, public_inputs) = cube_root_proof()?;
+
+        let mut builder = CircuitBuilder::<Tweedledum>::new(128);
+        let vk_target = if vk_is_input {
+            Some(make_verification_key(&mut builder))
+        } else {
+            None
+        };
+        let inner_public_inputs = builder.add_virtual_targets(public_inputs.len());
+        let proof_target = make_proof(&mut builder, log2_strict(vk.degree), 0);
+        let challenges = compute_challenges(
+            &mut builder,
+            &vk,
+            vk_target.as_ref(),
+            &inner_public_inputs,
+            &proof_target,
+            &[],
+        );
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        if let Some(vk_target) = &vk_target {
+            vk_target.populate_witness(&mut inputs, &vk)?;
+        }
+        inputs.set_targets(&inner_public_inputs, &F::try_convert_all(&public_inputs)?);
+        proof_target.populate_witness(&mut inputs, &proof)?;
+        let witness = circuit.generate_partial_witness(inputs)?;
+
+        let expected = proof.get_challenges(&vk, &public_inputs, &[], Challenger::new(128))?;
+        let actual = |targets: &[_]| -> Result<Vec<F>> {
+            BF::try_convert_all(&witness.get_targets(targets))
+        };
+        assert_eq!(
+            actual(&[
+                challenges.beta,
+                challenges.gamma,
+                challenges.alpha,
+                challenges.zeta,
+                challenges.v,
+                challenges.u,
+                challenges.u_scaling,
+                challenges.schnorr_challenge,
+            ])?,
+            vec![
+                expected.beta,
+                expected.gamma,
+                expected.alpha,
+                expected.zeta,
+                expected.v,
+                expected.u,
+                expected.u_scaling,
+                expected.schnorr_challenge,
+            ]
+        );
+        assert_eq!(actual(&challenges.ipa_challenges)?, expected.ipa_challenges);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_assumptions() -> Result<()> {
+        let (vk, proof, public_inputs) = cube_root_proof()?;
+        let challs = proof.get_challenges(&vk, &public_inputs, &[], Challenger::new(128))?;
+        let values: Vec<F> =
+            Field::try_convert_all(&recursion_public_inputs::<Tweedledee, Tweedledum>(
+                &proof,
+                &public_inputs,
+                None,
+                &[],
+                &challs,
+                128,
+                None,
+            )?)?;
+
+        let layout = RecursionLayout::new(&vk, 0, &[], false);
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let targets = builder.add_virtual_targets(layout.num_public_inputs());
+        let enabled = builder.add_virtual_target();
+        let assumptions = RecursionPublicInputs::from_targets(&targets, layout);
+        verify_assumptions::<Tweedledee, Tweedledum>(&mut builder, layout, &assumptions, enabled);
+        let circuit = builder.build();
+
+        let check_if = |values: &[F], enabled_value: F| {
+            let mut inputs = PartialWitness::new();
+            inputs.set_targets(&targets, values);
+            inputs.set_target(enabled, enabled_value);
+            circuit.generate_partial_witness(inputs)
+        };
+        let check = |values: &[F]| check_if(values, F::ONE);
+        check(&values)?;
+
+        // Tampering with any deferred value should make the checks unsatisfiable.
+        for &target in &[
+            assumptions.halo_b,
+            assumptions.reduced_opening,
+            assumptions.u_powers[3],
+            assumptions.halo_us[0],
+            assumptions.o_local.o_plonk_t[1],
+        ] {
+            let i = targets.iter().position(|&t| t == target).unwrap();
+            let mut tampered = values.clone();
+            tampered[i] = tampered[i] + F::ONE;
+            assert!(check(&tampered).is_err());
+            // Unless the checks are disabled.
+            check_if(&tampered, F::ZERO)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_recursion() -> Result<()> {
+        type BF = <Tweedledee as Curve>::BaseField;
+        let (vk, proof, public_inputs) = cube_root_proof()?;
+        let challs = proof.get_challenges(&vk, &public_inputs, &[], Challenger::new(128))?;
+        let values = recursion_public_inputs::<Tweedledee, Tweedledum>(
+            &proof,
+            &public_inputs,
+            None,
+            &[],
+            &challs,
+            128,
+            None,
+        )?;
+        let layout = RecursionLayout::new(&vk, 0, &[], false);
+        finalize_recursion::<Tweedledee, Tweedledum>(&values, layout, &vk)?;
+
+        // Tampering with a deferred scalar should be caught.
+        let halo_b_index = layout.accumulator_start() - layout.degree_pow - 1;
+        let mut tampered = values.clone();
+        tampered[halo_b_index] = tampered[halo_b_index] + BF::ONE;
+        assert!(finalize_recursion::<Tweedledee, Tweedledum>(&tampered, layout, &vk).is_err());
+
+        // So should replacing the accumulator's G point with another point on the curve.
+        let other_g = proof.halo_g.double();
+        let mut tampered = values.clone();
+        tampered[layout.accumulator_start()] = other_g.x;
+        tampered[layout.accumulator_start() + 1] = other_g.y;
+        assert!(finalize_recursion::<Tweedledee, Tweedledum>(&tampered, layout, &vk).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dummy_accumulator() -> Result<()> {
+        let (vk, _proof, _public_inputs) = cube_root_proof()?;
+        let accumulator = dummy_accumulator::<Tweedledee>(log2_strict(vk.degree));
+        verify_accumulators(&[accumulator], &vk)?;
+        Ok(())
     }
 }
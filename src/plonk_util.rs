@@ -1,6 +1,8 @@
 use crate::partition::get_subgroup_shift;
 use crate::witness::Witness;
-use crate::{ifft_with_precomputation_power_of_2, msm_execute_parallel, AffinePoint, CircuitBuilder, Curve, FftPrecomputation, Field, HaloCurve, MsmPrecomputation, Polynomial, PolynomialCommitment, ProjectivePoint, Target, NUM_ROUTED_WIRES};
+use crate::{ifft_with_precomputation_power_of_2, lde_onto_coset, msm_execute_parallel, AffinePoint, CircuitBuilder, Curve, FftPrecomputation, Field, HaloCurve, MsmPrecomputation, Polynomial, PolynomialCommitment, ProjectivePoint, Target};
+use rand::Rng;
+#[cfg(feature = "parallel")]
 use rayon::prelude::*;
 
 /// Evaluate the polynomial which vanishes on any multiplicative subgroup of a given order `n`.
@@ -49,6 +51,7 @@ pub(crate) fn reduce_with_powers_recursive<C: HaloCurve>(
 /// endomorphism.
 pub fn halo_n<C: HaloCurve>(s_bits: &[bool]) -> C::ScalarField {
     // This is based on Algorithm 2 of the Halo paper, except that we start with (a, b) = (0, 0).
+    // As in the paper, the most significant pair of bits is processed first.
 
     debug_assert_eq!(s_bits.len() % 2, 0, "Number of scalar bits must be even");
 
@@ -56,7 +59,7 @@ pub fn halo_n<C: HaloCurve>(s_bits: &[bool]) -> C::ScalarField {
     let mut a = zero;
     let mut b = zero;
 
-    for s_bits_chunk in s_bits.chunks(2) {
+    for s_bits_chunk in s_bits.chunks(2).rev() {
         let bit_lo = s_bits_chunk[0];
         let bit_hi = s_bits_chunk[1];
 
@@ -74,6 +77,51 @@ pub fn halo_n<C: HaloCurve>(s_bits: &[bool]) -> C::ScalarField {
     a * C::ZETA_SCALAR + b
 }
 
+/// Computes `n(x)` in a circuit, where, as in `halo_n`, `n` is applied to the low
+/// `security_bits` bits of `x`.
+///
+/// Note: This assumes the most significant bit of `x` is unset, as `curve_msm_endo` does.
+pub(crate) fn halo_n_recursive<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    x: Target<C::ScalarField>,
+) -> Target<C::ScalarField> {
+    let security_bits = builder.security_bits;
+    let bits = builder.split_binary(x, security_bits);
+    for &bit in &bits {
+        builder.assert_binary(bit);
+    }
+
+    // Verify the decomposition, by checking that the remaining high bits of x fit in the rest of
+    // the field.
+    let one = builder.one_wire();
+    let two = builder.two_wire();
+    let low = reduce_with_powers_recursive(builder, &bits, two);
+    let high_shifted = builder.sub(x, low);
+    let shift_inv = builder.constant_wire(
+        C::ScalarField::TWO
+            .exp_usize(security_bits)
+            .multiplicative_inverse()
+            .expect("2 is invertible"),
+    );
+    let high = builder.mul(high_shifted, shift_inv);
+    builder.assert_dibit_length(high, (C::ScalarField::BITS - 1 - security_bits) / 2);
+
+    let mut a = builder.zero_wire();
+    let mut b = a;
+    for bits_chunk in bits.chunks(2).rev() {
+        let (bit_lo, bit_hi) = (bits_chunk[0], bits_chunk[1]);
+        // sign is 1 if bit_lo is set, or -1 otherwise. It goes to a if bit_hi is set, or b otherwise.
+        let sign = builder.mul_sub(two, bit_lo, one);
+        let c = builder.mul(bit_hi, sign);
+        let d = builder.sub(sign, c);
+        a = builder.mul_add(a, two, c);
+        b = builder.mul_add(b, two, d);
+    }
+
+    let zeta = builder.constant_wire(C::ZETA_SCALAR);
+    builder.mul_add(a, zeta, b)
+}
+
 /// Compute `[n(s)].P` for a given `s`, where `n` is the injective function related to the Halo
 /// endomorphism.
 pub fn halo_n_mul<C: HaloCurve>(s_bits: &[bool], p: AffinePoint<C>) -> AffinePoint<C> {
@@ -88,7 +136,7 @@ pub fn halo_n_mul<C: HaloCurve>(s_bits: &[bool], p: AffinePoint<C>) -> AffinePoi
 
     let mut acc = ProjectivePoint::<C>::ZERO;
 
-    for s_bits_chunk in s_bits.chunks(2) {
+    for s_bits_chunk in s_bits.chunks(2).rev() {
         let bit_lo = s_bits_chunk[0];
         let bit_hi = s_bits_chunk[1];
 
@@ -154,39 +202,28 @@ pub(crate) fn eval_polys<F: Field>(polys: &[Polynomial<F>], powers: &[F]) -> Vec
     polys.iter().map(|p| p.eval_from_power(powers)).collect()
 }
 
-/// Zero-pad a list of `n` polynomial coefficients to a length of `8n`, which is the degree at
-/// which we do most polynomial arithmetic.
-pub(crate) fn pad_to_8n<F: Field>(coeffs: &[F]) -> Vec<F> {
-    let n = coeffs.len();
-
-    let mut result = coeffs.to_vec();
-    while result.len() < 8 * n {
-        result.push(F::ZERO);
-    }
-    result
-}
-
 pub(crate) fn values_to_polynomials<F: Field>(
     values_vec: &[Vec<F>],
     fft_precomputation: &FftPrecomputation<F>,
 ) -> Vec<Polynomial<F>> {
-    values_vec
-        .par_iter()
-        .map(|values| Polynomial::from_evaluations(&values, fft_precomputation))
-        .collect()
+    let interpolate = |values: &Vec<F>| Polynomial::from_evaluations(values, fft_precomputation);
+
+    #[cfg(feature = "parallel")]
+    let polynomials = values_vec.par_iter().map(interpolate).collect();
+    #[cfg(not(feature = "parallel"))]
+    let polynomials = values_vec.iter().map(interpolate).collect();
+    polynomials
 }
 
-pub(crate) fn polynomials_to_values_padded<F: Field>(
-    polys_vec: &[Polynomial<F>],
-    fft_precomputation: &FftPrecomputation<F>,
-) -> Vec<Vec<F>> {
-    polys_vec
-        .par_iter()
-        .map(|poly| {
-            let padded_poly = poly.padded(poly.len() * 8);
-            padded_poly.eval_domain(fft_precomputation)
-        })
-        .collect()
+/// Computes the degree-8n low-degree extension of each of the given degree-n polynomials.
+pub(crate) fn polynomials_to_values_padded<F: Field>(polys_vec: &[Polynomial<F>]) -> Vec<Vec<F>> {
+    let low_degree_extend = |poly: &Polynomial<F>| lde_onto_coset(poly, 3, F::ONE);
+
+    #[cfg(feature = "parallel")]
+    let values = polys_vec.par_iter().map(low_degree_extend).collect();
+    #[cfg(not(feature = "parallel"))]
+    let values = polys_vec.iter().map(low_degree_extend).collect();
+    values
 }
 
 /// Like `pedersen_commit`, but with no blinding factor.
@@ -212,11 +249,12 @@ fn pedersen_commit<C: Curve>(
     msm_execute_parallel(pedersen_g_msm_precomputation, xs) + blinding_term
 }
 
-pub fn commit_polynomials<C: Curve>(
+pub fn commit_polynomials<C: Curve, R: Rng>(
     polynomials: &[Polynomial<C::ScalarField>],
     msm_precomputation: &MsmPrecomputation<C>,
     blinding_point: AffinePoint<C>,
     blinding: bool,
+    rng: &mut R,
 ) -> Vec<PolynomialCommitment<C>> {
     PolynomialCommitment::coeffs_vec_to_commitments(
         polynomials
@@ -227,6 +265,7 @@ pub fn commit_polynomials<C: Curve>(
         msm_precomputation,
         blinding_point,
         blinding,
+        rng,
     )
 }
 
@@ -238,20 +277,37 @@ pub fn permutation_polynomial<F: Field>(
     sigma_values: &[Vec<F>],
     beta: F,
     gamma: F,
+) -> Vec<F> {
+    permutation_polynomial_with_stride(degree, subgroup, witness, sigma_values, 8, beta, gamma)
+}
+
+/// Like `permutation_polynomial`, but the sigma value at the `i`th gate is read from index
+/// `stride * i`, so that `sigma_values` can be evaluations over the degree-n subgroup itself
+/// (with a stride of 1) rather than over the degree-8n one.
+pub(crate) fn permutation_polynomial_with_stride<F: Field>(
+    degree: usize,
+    subgroup: &[F],
+    witness: &Witness<F>,
+    sigma_values: &[Vec<F>],
+    stride: usize,
+    beta: F,
+    gamma: F,
 ) -> Vec<F> {
     let mut plonk_z_points = vec![F::ONE];
-    let k_is = (0..NUM_ROUTED_WIRES)
+    // There is one sigma polynomial per routed wire.
+    let num_routed_wires = sigma_values.len();
+    let k_is = (0..num_routed_wires)
         .map(get_subgroup_shift::<F>)
         .collect::<Vec<_>>();
     for i in 1..degree {
         let x = subgroup[i - 1];
         let mut numerator = F::ONE;
         let mut denominator = F::ONE;
-        for j in 0..NUM_ROUTED_WIRES {
+        for j in 0..num_routed_wires {
             let wire_value = witness.get_indices(i - 1, j);
             let k_i = k_is[j];
             let s_id = k_i * x;
-            let s_sigma = sigma_values[j][8 * (i - 1)];
+            let s_sigma = sigma_values[j][stride * (i - 1)];
             numerator = numerator * (wire_value + beta * s_id + gamma);
             denominator = denominator * (wire_value + beta * s_sigma + gamma);
         }
@@ -266,15 +322,17 @@ pub fn sigma_polynomials<F: Field>(
     degree: usize,
     subgroup_generator: F,
 ) -> Vec<Vec<F>> {
+    let sigma_value = |&x: &usize| {
+        get_subgroup_shift::<F>(x / degree) * subgroup_generator.exp_usize(x % degree)
+    };
     sigma
         .chunks(degree)
         .map(|chunk| {
-            chunk
-                .par_iter()
-                .map(|&x| {
-                    get_subgroup_shift::<F>(x / degree) * subgroup_generator.exp_usize(x % degree)
-                })
-                .collect::<Vec<_>>()
+            #[cfg(feature = "parallel")]
+            let values = chunk.par_iter().map(sigma_value).collect::<Vec<_>>();
+            #[cfg(not(feature = "parallel"))]
+            let values = chunk.iter().map(sigma_value).collect::<Vec<_>>();
+            values
         })
         .collect()
 }
@@ -371,7 +429,7 @@ mod test {
         let mut partial_witness = PartialWitness::new();
         partial_witness.set_target(t, <Tweedledee as Curve>::ScalarField::TWO);
         let circuit = builder.build();
-        let witness = circuit.generate_witness(partial_witness);
+        let witness = circuit.generate_witness(partial_witness).unwrap();
         let beta = <Tweedledee as Curve>::ScalarField::rand();
         let gamma = <Tweedledee as Curve>::ScalarField::rand();
         let plonk_z_points_n = permutation_polynomial(
@@ -383,7 +441,7 @@ mod test {
             gamma,
         );
         // Verify that the permutation polynomial is well-formed.
-        let k_is = (0..NUM_ROUTED_WIRES)
+        let k_is = (0..circuit.num_routed_wires)
             .map(get_subgroup_shift::<<Tweedledee as Curve>::ScalarField>)
             .collect::<Vec<_>>();
         let wire_values = &witness.transpose();
@@ -394,7 +452,7 @@ mod test {
             );
             let mut f_prime = <Tweedledee as Curve>::ScalarField::ONE;
             let mut g_prime = <Tweedledee as Curve>::ScalarField::ONE;
-            for j in 0..NUM_ROUTED_WIRES {
+            for j in 0..circuit.num_routed_wires {
                 let wire_value = wire_values[j][i];
                 let k_i = k_is[j];
                 let s_id = k_i * x;
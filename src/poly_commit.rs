@@ -1,6 +1,7 @@
 use crate::plonk_util::pedersen_hash;
 use crate::MsmPrecomputation;
 use crate::{AffinePoint, Curve, Field, ProjectivePoint};
+use rand::Rng;
 
 #[derive(Debug, Copy, Clone)]
 /// Represents a curve point either in affine or projective coordinates.
@@ -28,15 +29,16 @@ impl<C: Curve> From<AffinePoint<C>> for PolynomialCommitment<C> {
 
 impl<C: Curve> PolynomialCommitment<C> {
     /// Creates a polynomial commitment from a vector of coefficients.
-    /// If `blinding` is true, a random blinding factor is used. Otherwise, it is set to zero.
-    pub fn coeffs_to_commitment(
+    /// If `blinding` is true, a blinding factor is sampled from `rng`. Otherwise, it is set to zero.
+    pub fn coeffs_to_commitment<R: Rng>(
         coeffs: &[C::ScalarField],
         msm_precomputation: &MsmPrecomputation<C>,
         blinding_point: AffinePoint<C>,
         blinding: bool,
+        rng: &mut R,
     ) -> Self {
         let blinding_factor = if blinding {
-            C::ScalarField::rand()
+            C::ScalarField::rand_from_rng(rng)
         } else {
             C::ScalarField::ZERO
         };
@@ -49,16 +51,17 @@ impl<C: Curve> PolynomialCommitment<C> {
     }
 
     /// Creates a list of polynomial commitments from a list of polynomials in coefficients vector form.
-    pub fn coeffs_vec_to_commitments(
+    pub fn coeffs_vec_to_commitments<R: Rng>(
         coefficients_vec: &[&[C::ScalarField]],
         msm_precomputation: &MsmPrecomputation<C>,
         blinding_point: AffinePoint<C>,
         blinding: bool,
+        rng: &mut R,
     ) -> Vec<Self> {
         let mut comms: Vec<_> = coefficients_vec
             .iter()
             .map(|coeffs| {
-                Self::coeffs_to_commitment(coeffs, &msm_precomputation, blinding_point, blinding)
+                Self::coeffs_to_commitment(coeffs, msm_precomputation, blinding_point, blinding, rng)
             })
             .collect();
         Self::batch_to_affine(&mut comms);
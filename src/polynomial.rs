@@ -1,11 +1,16 @@
 #![allow(clippy::many_single_char_names)]
-use crate::{fft_precompute, fft_with_precomputation, ifft_with_precomputation_power_of_2, util::log2_ceil, AffinePoint, Curve, FftPrecomputation, Field, MsmPrecomputation, PolynomialCommitment};
+use crate::plonk_util::powers;
+use crate::util::{log2_ceil, log2_strict};
+use crate::{cached_fft_precomputation, fft_in_place, fft_with_precomputation, ifft_in_place, lde_onto_coset, ifft_with_precomputation_power_of_2, take_buffer, AffinePoint, Curve, FftPrecomputation, Field, MsmPrecomputation, PolynomialCommitment};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::ops::{Index, IndexMut, RangeBounds};
 use std::slice::{Iter, IterMut, SliceIndex};
 
 /// Polynomial struct holding a polynomial in coefficient form.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Polynomial<F: Field>(Vec<F>);
 
 impl<F: Field> PartialEq for Polynomial<F> {
@@ -131,12 +136,42 @@ impl<F: Field> Polynomial<F> {
         F::inner_product(&self[..], x_pow)
     }
 
+    /// Evaluates the polynomial at each of the given points.
+    pub fn eval_many(&self, points: &[F]) -> Vec<F> {
+        points.iter().map(|&x| self.eval(x)).collect()
+    }
+
+    /// Evaluates each of the given polynomials at a point `x`. The powers of `x` are shared between
+    /// the polynomials, so this is cheaper than calling `eval` on each of them.
+    pub fn eval_batch(polys: &[Self], x: F) -> Vec<F> {
+        let max_len = polys.iter().map(Self::len).max().unwrap_or(0);
+        let x_pow = powers(x, max_len);
+        polys
+            .iter()
+            .map(|p| p.eval_from_power(&x_pow[..p.len()]))
+            .collect()
+    }
+
+    /// Evaluates the polynomial on the subgroup of order `2^rate_bits` times its (padded) length.
+    pub fn lde(&self, rate_bits: usize) -> PolynomialValues<F> {
+        PolynomialValues(lde_onto_coset(self, rate_bits, F::ONE))
+    }
+
+    /// Like `lde`, but evaluates the polynomial on the coset `shift H` of that subgroup.
+    pub fn coset_lde(&self, rate_bits: usize, shift: F) -> PolynomialValues<F> {
+        PolynomialValues(lde_onto_coset(self, rate_bits, shift))
+    }
+
     /// Evaluates the polynomial on subgroup of `F^*` with a given FFT precomputation.
     pub fn eval_domain(&self, fft_precomputation: &FftPrecomputation<F>) -> Vec<F> {
         let domain_size = fft_precomputation.size();
         if self.len() < domain_size {
             // Need to pad the polynomial to have the same length as the domain.
-            fft_with_precomputation(&self.padded(domain_size).coeffs(), fft_precomputation)
+            let mut values = take_buffer(domain_size);
+            values[..self.len()].copy_from_slice(&self.0);
+            values[self.len()..].iter_mut().for_each(|v| *v = F::ZERO);
+            fft_in_place(&mut values, fft_precomputation);
+            values
         } else {
             fft_with_precomputation(&self.coeffs(), fft_precomputation)
         }
@@ -165,12 +200,12 @@ impl<F: Field> Polynomial<F> {
     }
 
     /// Negates the polynomial's coefficients.
-    fn neg(&self) -> Self {
+    pub fn neg(&self) -> Self {
         Self(self.iter().map(|&x| -x).collect())
     }
 
     /// Multiply the polynomial's coefficients by a scalar.
-    fn scalar_mul(&self, c: F) -> Self {
+    pub fn scalar_mul(&self, c: F) -> Self {
         Self(self.iter().map(|&x| c * x).collect())
     }
 
@@ -190,6 +225,36 @@ impl<F: Field> Polynomial<F> {
         Self(a.iter().zip(b.iter()).map(|(&x, &y)| x + y).collect())
     }
 
+    /// Polynomial subtraction.
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    /// Splits the coefficients into polynomials of `chunk_len` coefficients each, so that
+    /// `self(x) = sum_i chunks[i](x) x^(i chunk_len)`.
+    pub fn chunks(&self, chunk_len: usize) -> Vec<Self> {
+        self.0
+            .chunks(chunk_len)
+            .map(|chunk| Self(chunk.to_vec()))
+            .collect()
+    }
+
+    /// The vanishing polynomial of a set of points, i.e. `prod_i (X - roots[i])`.
+    pub fn from_roots(roots: &[F]) -> Self {
+        // TODO: Faster implementation, e.g. with a product tree.
+        roots.iter().fold(Self(vec![F::ONE]), |acc, &r| {
+            let mut product = acc.mul(&Self(vec![-r, F::ONE]));
+            product.trim();
+            product
+        })
+    }
+
+    /// Divides the polynomial by the vanishing polynomial of `roots`. Assumes that it vanishes on
+    /// each of them, otherwise the result is meaningless.
+    pub fn divide_by_vanishing(&self, roots: &[F]) -> Self {
+        self.polynomial_division(&Self::from_roots(roots)).0
+    }
+
     /// Zero-pad the coefficients to have a given length.
     pub fn pad(&mut self, len: usize) {
         self.trim();
@@ -214,15 +279,12 @@ impl<F: Field> Polynomial<F> {
         let a_pad = self.padded(a_deg + b_deg + 1);
         let b_pad = b.padded(a_deg + b_deg + 1);
 
-        let precomputation = fft_precompute(a_deg + b_deg + 1);
+        let precomputation = cached_fft_precomputation(a_deg + b_deg + 1);
         let a_evals = fft_with_precomputation(&a_pad.0, &precomputation);
         let b_evals = fft_with_precomputation(&b_pad.0, &precomputation);
 
-        let mul_evals: Vec<F> = a_evals
-            .iter()
-            .zip(b_evals.iter())
-            .map(|(&pa, &pb)| pa * pb)
-            .collect();
+        let mut mul_evals = a_evals;
+        F::batch_multiply_in_place(&mut mul_evals, &b_evals);
         ifft_with_precomputation_power_of_2(&mul_evals, &precomputation).into()
     }
 
@@ -334,17 +396,10 @@ impl<F: Field> Polynomial<F> {
         let mut a_trim = self.clone();
         a_trim.trim();
         let g = F::MULTIPLICATIVE_SUBGROUP_GENERATOR;
-        let mut g_pow = F::ONE;
-        // Multiply the i-th coefficient of `a` by `g^i`. Then `new_a(w^j) = old_a(g.w^j)`.
-        a_trim.iter_mut().for_each(|x| {
-            *x = (*x) * g_pow;
-            g_pow = g * g_pow;
-        });
         let d = a_trim.degree();
         let root = F::primitive_root_of_unity(log2_ceil(d + 1));
-        let precomputation = fft_precompute(d + 1);
         // Equals to the evaluation of `a` on `{g.w^i}`.
-        let mut a_eval = a_trim.eval_domain(&precomputation);
+        let mut a_eval = a_trim.coset_lde(0, g);
         // Compute the denominators `1/(g^n.w^(n*i) - 1)` using batch inversion.
         let denominator_g = g.exp_usize(n);
         let root_n = root.exp_usize(n);
@@ -360,14 +415,9 @@ impl<F: Field> Polynomial<F> {
         let denominators_inv = F::batch_multiplicative_inverse(&denominators);
         // Divide every element of `a_eval` by the corresponding denominator.
         // Then, `a_eval` is the evaluation of `a/Z_H` on `{g.w^i}`.
-        a_eval
-            .iter_mut()
-            .zip(denominators_inv.iter())
-            .for_each(|(x, &d)| {
-                *x = (*x) * d;
-            });
+        F::batch_multiply_in_place(&mut a_eval.0, &denominators_inv);
         // `p` is the interpolating polynomial of `a_eval` on `{w^i}`.
-        let mut p = Self::from_evaluations(&a_eval, &precomputation);
+        let mut p = a_eval.interpolate();
         // We need to scale it by `g^(-i)` to get the interpolating polynomial of `a_eval` on `{g.w^i}`,
         // a.k.a `a/Z_H`.
         let g_inv = g.multiplicative_inverse_assuming_nonzero();
@@ -380,21 +430,111 @@ impl<F: Field> Polynomial<F> {
     }
 
     /// Commits the polynomial.
-    pub fn commit<C: Curve<ScalarField = F>>(
+    pub fn commit<C: Curve<ScalarField = F>, R: Rng>(
         &self,
         msm_precomputation: &MsmPrecomputation<C>,
         blinding_point: AffinePoint<C>,
         blinding: bool,
+        rng: &mut R,
     ) -> PolynomialCommitment<C> {
         PolynomialCommitment::coeffs_to_commitment(
             &self.coeffs(),
             msm_precomputation,
             blinding_point,
             blinding,
+            rng,
         )
     }
 }
 
+/// A polynomial in point-value form, holding its evaluations over a multiplicative subgroup, in
+/// the order `(g^0, g^1, g^2, ...)` where `g` generates the subgroup.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PolynomialValues<F: Field>(Vec<F>);
+
+impl<F: Field> From<Vec<F>> for PolynomialValues<F> {
+    /// Takes a vector of evaluations, whose length must be a power of two.
+    fn from(values: Vec<F>) -> Self {
+        log2_strict(values.len());
+        Self(values)
+    }
+}
+
+impl<F, I> Index<I> for PolynomialValues<F>
+where
+    F: Field,
+    I: SliceIndex<[F]>,
+{
+    type Output = I::Output;
+
+    /// Indexing on the evaluations.
+    fn index(&self, index: I) -> &Self::Output {
+        &self.0[index]
+    }
+}
+
+impl<F, I> IndexMut<I> for PolynomialValues<F>
+where
+    F: Field,
+    I: SliceIndex<[F]>,
+{
+    fn index_mut(&mut self, index: I) -> &mut <Self as Index<I>>::Output {
+        &mut self.0[index]
+    }
+}
+
+impl<F: Field> PolynomialValues<F> {
+    /// Returns the evaluation vector.
+    pub fn values(&self) -> &[F] {
+        &self.0
+    }
+
+    /// Returns the evaluation vector, consuming `self`.
+    pub fn into_values(self) -> Vec<F> {
+        self.0
+    }
+
+    /// The size of the evaluation domain.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, F> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, F> {
+        self.0.iter_mut()
+    }
+
+    /// Computes the interpolating polynomial of these values, in coefficient form.
+    pub fn interpolate(&self) -> Polynomial<F> {
+        let precomputation = cached_fft_precomputation(self.len());
+        Polynomial(ifft_with_precomputation_power_of_2(&self.0, &precomputation))
+    }
+
+    /// Like `interpolate`, but reuses the evaluation vector for the coefficients.
+    pub fn into_polynomial(mut self) -> Polynomial<F> {
+        let precomputation = cached_fft_precomputation(self.len());
+        ifft_in_place(&mut self.0, &precomputation);
+        Polynomial(self.0)
+    }
+
+    /// Pointwise multiplication, which corresponds to multiplying the underlying polynomials, as
+    /// long as the product's degree is less than the domain size.
+    pub fn mul(&self, other: &Self) -> Self {
+        assert_eq!(self.len(), other.len(), "Evaluation domains differ");
+        let mut product = self.0.clone();
+        F::batch_multiply_in_place(&mut product, &other.0);
+        Self(product)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -528,6 +668,57 @@ mod test {
         println!("Division time: {:?}", now.elapsed());
     }
 
+    #[test]
+    fn test_lde_interpolate() {
+        type F = TweedledeeBase;
+        let a = Polynomial((0..100).map(|_| F::rand()).collect());
+        let b = Polynomial((0..100).map(|_| F::rand()).collect());
+
+        // 100 coefficients are padded to 128, so a rate of 2 leaves room for the product.
+        let product = a.lde(1).mul(&b.lde(1)).interpolate();
+        assert_eq!(product, a.mul(&b));
+        assert_eq!(a.coset_lde(3, F::MULTIPLICATIVE_SUBGROUP_GENERATOR).interpolate().len(), 1024);
+        assert_eq!(a.lde(0).interpolate(), a);
+    }
+
+    #[test]
+    fn test_eval_batch() {
+        type F = TweedledeeBase;
+        let polys = (1..10)
+            .map(|len| Polynomial((0..len).map(|_| F::rand()).collect()))
+            .collect::<Vec<_>>();
+        let x = F::rand();
+        let expected = polys.iter().map(|p| p.eval(x)).collect::<Vec<_>>();
+        assert_eq!(Polynomial::eval_batch(&polys, x), expected);
+        assert_eq!(polys[3].eval_many(&[x]), vec![expected[3]]);
+    }
+
+    #[test]
+    fn test_divide_by_vanishing() {
+        type F = TweedledeeBase;
+        let roots = (0..5).map(|_| F::rand()).collect::<Vec<_>>();
+        let q = Polynomial((0..20).map(|_| F::rand()).collect());
+        let a = q.mul(&Polynomial::from_roots(&roots));
+        for &r in &roots {
+            assert_eq!(a.eval(r), F::ZERO);
+        }
+        assert_eq!(a.divide_by_vanishing(&roots), q);
+    }
+
+    #[test]
+    fn test_chunks() {
+        type F = TweedledeeBase;
+        let a = Polynomial((0..12).map(|_| F::rand()).collect());
+        let x = F::rand();
+        let chunks = a.chunks(4);
+        assert_eq!(chunks.len(), 3);
+        let recombined = chunks
+            .iter()
+            .rev()
+            .fold(F::ZERO, |acc, c| acc * x.exp_usize(4) + c.eval(x));
+        assert_eq!(recombined, a.eval(x));
+    }
+
     #[test]
     fn eq() {
         type F = TweedledeeBase;
@@ -0,0 +1,145 @@
+use crate::permutation_batch::permute_in_lanes;
+use crate::{apply_mds, mds_matrix, Field, Sponge};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The number of full rounds, half of which are applied before the partial rounds, and half
+/// after them.
+pub(crate) const POSEIDON_FULL_ROUNDS: usize = 8;
+/// The number of partial rounds, in which the S-box is only applied to the first element. The
+/// Poseidon paper recommends 56 to 60 partial rounds for 128-bit security with an `x^5` S-box over
+/// ~255-bit fields and small widths; we use the upper end of that range.
+pub(crate) const POSEIDON_PARTIAL_ROUNDS: usize = 60;
+
+pub fn poseidon_hash_n_to_1<F: Field>(inputs: Vec<F>, security_bits: usize) -> F {
+    poseidon_sponge(inputs, 1, security_bits)[0]
+}
+
+/// A sponge with the same rate and capacity as `rescue_sponge`, but using a Poseidon permutation.
+/// Poseidon only needs `x^alpha` S-boxes rather than Rescue's `x^(1/alpha)`, so it is much cheaper
+/// to evaluate natively. In-circuit, it is evaluated with arithmetic gates; see `PoseidonHasher`.
+pub fn poseidon_sponge<F: Field>(
+    inputs: Vec<F>,
+    num_outputs: usize,
+    security_bits: usize,
+) -> Vec<F> {
+    let mut sponge = Sponge::new_poseidon(security_bits);
+    sponge.absorb_all(&inputs);
+    sponge.squeeze_n(num_outputs)
+}
+
+pub fn poseidon_permutation<F: Field>(state: &[F], security_bits: usize) -> Vec<F> {
+    assert!(
+        security_bits <= 128,
+        "Our Poseidon parameters target at most 128 bits of security"
+    );
+    let mut state = state.to_vec();
+    let width = state.len();
+    let constants = generate_poseidon_constants(width);
+
+    for (r, round_constants) in constants.into_iter().enumerate() {
+        state = state
+            .iter()
+            .zip(round_constants)
+            .map(|(&x, c)| x + c)
+            .collect();
+
+        if is_full_poseidon_round(r) {
+            state = state.iter().map(|x| x.exp(F::ALPHA)).collect();
+        } else {
+            state[0] = state[0].exp(F::ALPHA);
+        }
+
+        state = apply_mds(state);
+    }
+
+    state
+}
+
+/// Applies `poseidon_permutation` to each of the given states, which must have the same width. The
+/// round constants are only generated once, and the states are permuted in lockstep, so this is
+/// much faster than permuting them one at a time.
+pub fn poseidon_permutation_batch<F: Field>(
+    states: &[Vec<F>],
+    security_bits: usize,
+) -> Vec<Vec<F>> {
+    assert!(
+        security_bits <= 128,
+        "Our Poseidon parameters target at most 128 bits of security"
+    );
+    if states.is_empty() {
+        return Vec::new();
+    }
+    let width = states[0].len();
+    let constants = generate_poseidon_constants::<F>(width);
+    let mds = mds_matrix::<F>(width).to_rows();
+
+    permute_in_lanes(states, |lanes| {
+        for (r, round_constants) in constants.iter().enumerate() {
+            lanes.add_constants(round_constants);
+            if is_full_poseidon_round(r) {
+                lanes.apply_sbox(|x| x.exp(F::ALPHA));
+            } else {
+                lanes.apply_sbox_to_element(0, |x| x.exp(F::ALPHA));
+            }
+            lanes.apply_mds(&mds);
+        }
+    })
+}
+
+/// Whether the S-box is applied to the whole state in the given round, rather than only to the
+/// first element.
+pub(crate) fn is_full_poseidon_round(r: usize) -> bool {
+    let half_full_rounds = POSEIDON_FULL_ROUNDS / 2;
+    r < half_full_rounds || r >= half_full_rounds + POSEIDON_PARTIAL_ROUNDS
+}
+
+pub(crate) fn generate_poseidon_constants<F: Field>(width: usize) -> Vec<Vec<F>> {
+    // We use a different seed than Rescue, so that the two permutations don't share constants.
+    let mut rng = ChaCha8Rng::seed_from_u64(7331);
+    (0..POSEIDON_FULL_ROUNDS + POSEIDON_PARTIAL_ROUNDS)
+        .map(|_| (0..width).map(|_| F::rand_from_rng(&mut rng)).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{poseidon_permutation, poseidon_sponge, rescue_sponge, Field, TweedledumBase};
+
+    #[test]
+    fn test_poseidon_deterministic() {
+        let inputs = vec![TweedledumBase::ZERO, TweedledumBase::ONE];
+        let outputs1 = poseidon_sponge(inputs.clone(), 2, 128);
+        let outputs2 = poseidon_sponge(inputs, 2, 128);
+        assert_eq!(outputs1, outputs2);
+    }
+
+    #[test]
+    fn test_poseidon_permutation_is_not_trivial() {
+        let state = vec![TweedledumBase::ZERO; 4];
+        let permuted = poseidon_permutation(&state, 128);
+        assert_ne!(permuted, state);
+        assert_ne!(poseidon_permutation(&permuted, 128), permuted);
+    }
+
+    #[test]
+    fn test_poseidon_sponge() {
+        let inputs = (0..5).map(TweedledumBase::from_canonical_usize).collect::<Vec<_>>();
+
+        // Squeezing more outputs extends the shorter output rather than changing it.
+        let short = poseidon_sponge(inputs.clone(), 2, 128);
+        let long = poseidon_sponge(inputs.clone(), 7, 128);
+        assert_eq!(long.len(), 7);
+        assert_eq!(&long[..2], &short[..]);
+
+        // Changing any input changes the output.
+        for i in 0..inputs.len() {
+            let mut modified = inputs.clone();
+            modified[i] = modified[i] + TweedledumBase::ONE;
+            assert_ne!(poseidon_sponge(modified, 2, 128), short);
+        }
+
+        // The Poseidon and Rescue sponges are distinct hashes.
+        assert_ne!(rescue_sponge(inputs, 2, 128), short);
+    }
+}
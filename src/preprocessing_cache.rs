@@ -0,0 +1,214 @@
+//! An on-disk cache of circuits' preprocessed polynomials and their commitments, keyed by a digest
+//! of the circuit, so that rebuilding a circuit which was already built skips preprocessing. See
+//! `CircuitBuilder::set_preprocessing_cache`.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::polynomial::Polynomial;
+use crate::{AffinePoint, HaloCurve, KeyFormat, ToBytes, KEY_FORMAT_VERSION};
+
+/// The preprocessed polynomials of a circuit, in coefficient form, along with commitments to them.
+/// Their degree-8n LDEs aren't cached, since they're eight times larger, and cheap to recompute.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct PreprocessedPolynomials<C: HaloCurve> {
+    pub format: KeyFormat<C>,
+    pub constant_polynomials: Vec<Polynomial<C::ScalarField>>,
+    pub c_constants: Vec<AffinePoint<C>>,
+    pub s_sigma_polynomials: Vec<Polynomial<C::ScalarField>>,
+    pub c_s_sigmas: Vec<AffinePoint<C>>,
+}
+
+/// A digest of everything preprocessing depends on: the curve, the circuit's width, its gates'
+/// constants, and its wire permutation `sigma`. The key format version is included too, so that
+/// entries written by older versions are never read.
+pub(crate) fn circuit_digest<C: HaloCurve>(
+    num_wires: usize,
+    num_routed_wires: usize,
+    gate_constants: &[Vec<C::ScalarField>],
+    sigma: &[usize],
+) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[KEY_FORMAT_VERSION]);
+    hasher.update(C::NAME.as_bytes());
+    for &n in &[num_wires, num_routed_wires, gate_constants.len()] {
+        hasher.update(&(n as u64).to_le_bytes());
+    }
+    let mut buf = Vec::new();
+    for c in gate_constants {
+        hasher.update(&(c.len() as u64).to_le_bytes());
+        for x in c {
+            buf.clear();
+            x.write(&mut buf).expect("Writing to a Vec should not fail");
+            hasher.update(&buf);
+        }
+    }
+    for &s in sigma {
+        hasher.update(&(s as u64).to_le_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// A directory holding one CBOR file of preprocessed polynomials per circuit digest. Entries are
+/// written atomically, so several processes can share a cache directory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PreprocessingCache {
+    dir: PathBuf,
+}
+
+impl PreprocessingCache {
+    /// A cache in the given directory, which is created when the first entry is written.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        PreprocessingCache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Deletes every entry in the cache.
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to clear {}", self.dir.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn path(&self, digest: &[u8; 32]) -> PathBuf {
+        self.dir.join(format!("{}.cbor", hex::encode(digest)))
+    }
+
+    /// Reads the entry with the given digest, if there is one.
+    pub(crate) fn load<C: HaloCurve>(
+        &self,
+        digest: &[u8; 32],
+    ) -> Result<Option<PreprocessedPolynomials<C>>> {
+        let path = self.path(digest);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let preprocessed = serde_cbor::from_slice(&bytes)
+            .with_context(|| format!("Failed to decode {}", path.display()))?;
+        Ok(Some(preprocessed))
+    }
+
+    /// Writes an entry, replacing any existing entry with the same digest.
+    pub(crate) fn store<C: HaloCurve>(
+        &self,
+        digest: &[u8; 32],
+        preprocessed: &PreprocessedPolynomials<C>,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.path(digest);
+        let tmp_path = path.with_extension(format!("tmp-{}", std::process::id()));
+        fs::write(&tmp_path, serde_cbor::to_vec(preprocessed)?)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Returns the entry with the given digest, or computes and stores it if there is none. Since
+    /// the cache is only an optimization, entries which can't be read or written are logged and
+    /// otherwise ignored.
+    pub(crate) fn get_or_compute<C: HaloCurve, F: FnOnce() -> PreprocessedPolynomials<C>>(
+        &self,
+        digest: &[u8; 32],
+        compute: F,
+    ) -> PreprocessedPolynomials<C> {
+        match self.load(digest) {
+            Ok(Some(preprocessed)) => return preprocessed,
+            Ok(None) => {}
+            Err(e) => warn!("Ignoring a preprocessing cache entry: {:#}", e),
+        }
+        let preprocessed = compute();
+        if let Err(e) = self.store(digest, &preprocessed) {
+            warn!("Failed to cache preprocessed polynomials: {:#}", e);
+        }
+        preprocessed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+
+    use crate::preprocessing_cache::circuit_digest;
+    use crate::{verify_proof, BufferGate, Circuit, CircuitBuilder, Curve, Field, PartialWitness, PreprocessingCache, Target, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    fn build_circuit(cache: &PreprocessingCache, constant: F) -> (Circuit<Tweedledee>, Target<F>) {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        builder.set_preprocessing_cache(cache.clone());
+        let pi = builder.add_public_input();
+        let c = builder.constant_wire(constant);
+        let sum = builder.add(pi, c);
+        builder.assert_nonzero(sum);
+        while builder.num_gates() < 20 {
+            builder.add_gate_no_constants(BufferGate::new(builder.num_gates()));
+        }
+        (builder.build(), pi)
+    }
+
+    fn num_entries(cache: &PreprocessingCache) -> usize {
+        fs::read_dir(cache.dir()).map_or(0, |entries| entries.count())
+    }
+
+    #[test]
+    fn test_preprocessing_cache() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("plonky-preprocessing-test-{}", std::process::id()));
+        let cache = PreprocessingCache::new(&dir);
+        cache.clear()?;
+
+        let (computed, pi) = build_circuit(&cache, F::ONE);
+        assert_eq!(num_entries(&cache), 1);
+        let (cached, _) = build_circuit(&cache, F::ONE);
+        assert_eq!(num_entries(&cache), 1);
+        assert!(cached.prover_key == computed.prover_key);
+
+        // A proof generated with the cached polynomials verifies.
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(pi, F::TWO);
+        let witness = cached.generate_witness(partial_witness)?;
+        let proof = cached.generate_proof::<Tweedledum>(&witness, &[])?;
+        let vk = computed.to_vk();
+        verify_proof::<Tweedledee, Tweedledum>(&cached.get_public_inputs(&witness), &proof, &[], &vk, true)?;
+
+        // Another circuit gets another entry.
+        build_circuit(&cache, F::TWO);
+        assert_eq!(num_entries(&cache), 2);
+
+        // A corrupted entry is recomputed and overwritten.
+        for entry in fs::read_dir(&dir)? {
+            fs::write(entry?.path(), b"not cbor")?;
+        }
+        assert!(build_circuit(&cache, F::ONE).0.prover_key == computed.prover_key);
+        assert_eq!(num_entries(&cache), 2);
+
+        cache.clear()?;
+        assert_eq!(num_entries(&cache), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_circuit_digest() {
+        let constants = vec![vec![F::ONE, F::TWO], vec![F::ZERO, F::ONE]];
+        let digest = circuit_digest::<Tweedledee>(9, 6, &constants, &[0, 1, 2]);
+        assert_eq!(digest, circuit_digest::<Tweedledee>(9, 6, &constants, &[0, 1, 2]));
+        assert_ne!(digest, circuit_digest::<Tweedledee>(9, 6, &constants, &[0, 2, 1]));
+        assert_ne!(digest, circuit_digest::<Tweedledee>(10, 6, &constants, &[0, 1, 2]));
+        assert_ne!(digest, circuit_digest::<Tweedledee>(9, 6, &constants[..1], &[0, 1, 2]));
+    }
+}
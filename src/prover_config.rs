@@ -0,0 +1,118 @@
+#[cfg(feature = "parallel")]
+use std::collections::HashMap;
+#[cfg(feature = "parallel")]
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+#[cfg(feature = "parallel")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "parallel")]
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+#[cfg(feature = "parallel")]
+use crate::fft::set_serial_ffts;
+
+/// Thread pools, keyed by their thread count and FFT strategy.
+#[cfg(feature = "parallel")]
+type ThreadPools = HashMap<(usize, FftStrategy), Arc<ThreadPool>>;
+
+/// The thread pools which have been built for each combination of thread count and FFT strategy,
+/// so that proofs with the same configuration share a pool rather than each spawning threads.
+#[cfg(feature = "parallel")]
+static THREAD_POOLS: Lazy<Mutex<ThreadPools>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How the prover parallelizes its FFTs.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum FftStrategy {
+    /// Transform several polynomials at once, and also split each FFT's layers into parallel
+    /// tasks. This keeps all threads busy even when only one polynomial is being transformed.
+    #[default]
+    Parallel,
+    /// Transform several polynomials at once, but run each FFT on a single thread. This avoids the
+    /// scheduling overhead of splitting small FFTs, and suits machines with few cores.
+    Serial,
+}
+
+/// Settings which let operators tune proving to the machine it runs on. None of them affect the
+/// resulting proofs, other than through the prover's randomness. See
+/// `ProverKey::generate_proof_with_config`.
+///
+/// Without the `parallel` feature, proving always runs on the calling thread, so `num_threads` and
+/// `fft_strategy` have no effect.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProverConfig {
+    /// The number of threads to prove with. With `None`, proving uses rayon's global thread pool,
+    /// which has a thread per CPU unless configured otherwise.
+    pub num_threads: Option<usize>,
+    pub fft_strategy: FftStrategy,
+    /// The window size of the MSMs which commit to polynomials. With `None`, the prover uses the
+    /// precomputation in the key. Otherwise, unless the key's precomputation has this window size,
+    /// a precomputation is computed for each proof, so to change the window size of many proofs,
+    /// `ProverKey::set_msm_window` is cheaper.
+    pub msm_window: Option<usize>,
+}
+
+impl ProverConfig {
+    /// Runs `op` in the thread pool for this configuration, so that any parallel work it does,
+    /// such as witness generation or aggregation, also respects the configuration.
+    pub fn install<R: Send, OP: FnOnce() -> R + Send>(&self, op: OP) -> Result<R> {
+        #[cfg(feature = "parallel")]
+        {
+            if self.num_threads.is_none() && self.fft_strategy == FftStrategy::Parallel {
+                return Ok(op());
+            }
+            Ok(self.thread_pool()?.install(op))
+        }
+        #[cfg(not(feature = "parallel"))]
+        Ok(op())
+    }
+
+    #[cfg(feature = "parallel")]
+    fn thread_pool(&self) -> Result<Arc<ThreadPool>> {
+        // Rayon interprets zero threads as its default number of threads.
+        let num_threads = self.num_threads.unwrap_or(0);
+        let key = (num_threads, self.fft_strategy);
+        let mut pools = THREAD_POOLS.lock().unwrap();
+        if let Some(pool) = pools.get(&key) {
+            return Ok(pool.clone());
+        }
+
+        let serial_ffts = self.fft_strategy == FftStrategy::Serial;
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .thread_name(|i| format!("plonky-prover-{}", i))
+            .start_handler(move |_| set_serial_ffts(serial_ffts))
+            .build()?;
+        let pool = Arc::new(pool);
+        pools.insert(key, pool.clone());
+        Ok(pool)
+    }
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{FftStrategy, ProverConfig};
+
+    #[test]
+    fn test_install() {
+        let config = ProverConfig {
+            num_threads: Some(2),
+            fft_strategy: FftStrategy::Serial,
+            msm_window: None,
+        };
+        assert_eq!(config.install(rayon::current_num_threads).unwrap(), 2);
+        assert!(config.install(crate::fft::serial_ffts).unwrap());
+
+        // Pools are shared by configurations with the same threading settings.
+        let other_config = ProverConfig {
+            msm_window: Some(8),
+            ..config.clone()
+        };
+        assert!(Arc::ptr_eq(&config.thread_pool().unwrap(), &other_config.thread_pool().unwrap()));
+
+        // The default configuration uses the global pool, whose FFTs are parallel.
+        assert!(!ProverConfig::default().install(crate::fft::serial_ffts).unwrap());
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// The phases of proving, in the order they run.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum ProverPhase {
+    /// Running the circuit's witness generators. See `Circuit::generate_witness_with_observer`.
+    WitnessGeneration,
+    /// Interpolating the wire polynomials, and committing to them.
+    WireCommitments,
+    /// Computing the permutation product polynomial Z, and committing to it.
+    PermutationProduct,
+    /// Computing the quotient polynomial and the public input quotient, and committing to them.
+    Quotient,
+    /// Opening all polynomials, and generating the batched Halo opening proof.
+    Opening,
+}
+
+impl fmt::Display for ProverPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProverPhase::WitnessGeneration => "witness generation",
+            ProverPhase::WireCommitments => "wire commitments",
+            ProverPhase::PermutationProduct => "permutation product",
+            ProverPhase::Quotient => "quotient",
+            ProverPhase::Opening => "opening",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Callbacks which the prover invokes as it moves through each `ProverPhase`, so that services can
+/// report progress, and profiling can tell which phase got slower. See
+/// `ProverKey::generate_proof_with_observer`.
+///
+/// If proving fails partway through a phase, that phase's `phase_finished` is never called.
+pub trait ProverObserver: Sync {
+    fn phase_started(&self, _phase: ProverPhase) {}
+
+    /// Called once `phase` is done, with the wall-clock time it took. On wasm32, where `Instant`
+    /// is unsupported, the duration is always zero.
+    fn phase_finished(&self, _phase: ProverPhase, _duration: Duration) {}
+}
+
+/// An observer which ignores all callbacks.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoopObserver;
+
+impl ProverObserver for NoopObserver {}
+
+/// An observer which records how long each phase took.
+#[derive(Debug, Default)]
+pub struct PhaseTimings {
+    timings: Mutex<Vec<(ProverPhase, Duration)>>,
+}
+
+impl PhaseTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The phases which have finished so far, in order, along with their durations.
+    pub fn timings(&self) -> Vec<(ProverPhase, Duration)> {
+        self.timings.lock().unwrap().clone()
+    }
+}
+
+impl ProverObserver for PhaseTimings {
+    fn phase_finished(&self, phase: ProverPhase, duration: Duration) {
+        self.timings.lock().unwrap().push((phase, duration));
+    }
+}
+
+/// Times a phase, notifying an observer when it starts and finishes.
+pub(crate) struct PhaseTimer<'a> {
+    observer: &'a dyn ProverObserver,
+    phase: ProverPhase,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl<'a> PhaseTimer<'a> {
+    pub(crate) fn start(observer: &'a dyn ProverObserver, phase: ProverPhase) -> Self {
+        observer.phase_started(phase);
+        PhaseTimer {
+            observer,
+            phase,
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let duration = self.start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        let duration = Duration::default();
+        self.observer.phase_finished(self.phase, duration);
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::{ensure, Result};
+
+use crate::gates::ArithmeticGate;
+use crate::{Circuit, CircuitBuilder, Field, HaloCurve, PartialWitness, Target, Wire};
+
+/// A sparse linear combination of variables, as a list of `(variable index, coefficient)` pairs.
+pub type LinearCombination<F> = Vec<(usize, F)>;
+
+/// A rank-1 constraint system: the constraints `<A_i, z> * <B_i, z> = <C_i, z>`, where
+/// `z = (1, public inputs, private inputs)`. Each matrix is given as a list of sparse rows.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct R1cs<F: Field> {
+    pub num_public_inputs: usize,
+    pub num_private_inputs: usize,
+    pub a: Vec<LinearCombination<F>>,
+    pub b: Vec<LinearCombination<F>>,
+    pub c: Vec<LinearCombination<F>>,
+}
+
+impl<F: Field> R1cs<F> {
+    /// The length of `z`, including the leading one.
+    pub fn num_variables(&self) -> usize {
+        1 + self.num_public_inputs + self.num_private_inputs
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.len()
+    }
+
+    /// Checks that the matrices have the same number of rows, and only refer to valid variables.
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.b.len() == self.a.len() && self.c.len() == self.a.len(),
+            "The matrices have different numbers of rows"
+        );
+        for row in self.a.iter().chain(&self.b).chain(&self.c) {
+            for &(variable, _) in row {
+                ensure!(
+                    variable < self.num_variables(),
+                    "Variable {} is out of range",
+                    variable
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the given public and private inputs satisfy every constraint.
+    pub fn is_satisfied(&self, public_inputs: &[F], private_inputs: &[F]) -> bool {
+        if public_inputs.len() != self.num_public_inputs
+            || private_inputs.len() != self.num_private_inputs
+        {
+            return false;
+        }
+        let mut z = vec![F::ONE];
+        z.extend_from_slice(public_inputs);
+        z.extend_from_slice(private_inputs);
+        let evaluate = |lc: &LinearCombination<F>| {
+            lc.iter().fold(F::ZERO, |sum, &(variable, coefficient)| {
+                sum + coefficient * z[variable]
+            })
+        };
+        (0..self.num_constraints())
+            .all(|i| evaluate(&self.a[i]) * evaluate(&self.b[i]) == evaluate(&self.c[i]))
+    }
+}
+
+/// A Plonk circuit which is satisfied exactly when an R1CS instance is. The public inputs of the
+/// R1CS become the public inputs of the circuit, in order.
+///
+/// Each constraint becomes a single `ArithmeticGate` when `A` and `B` have one term each and `C`
+/// has at most two, which covers most constraints produced by R1CS frontends. Longer linear
+/// combinations cost one extra gate per term.
+pub struct R1csCircuit<C: HaloCurve> {
+    pub circuit: Circuit<C>,
+    /// The target of each variable in `z`.
+    pub variables: Vec<Target<C::ScalarField>>,
+    num_public_inputs: usize,
+}
+
+impl<C: HaloCurve> R1csCircuit<C> {
+    pub fn new(r1cs: &R1cs<C::ScalarField>, security_bits: usize) -> Result<Self> {
+        r1cs.validate()?;
+
+        let mut builder = CircuitBuilder::<C>::new(security_bits);
+        let mut variables = vec![builder.one_wire()];
+        variables.extend(builder.add_public_inputs(r1cs.num_public_inputs));
+        variables.extend(builder.add_virtual_targets(r1cs.num_private_inputs));
+
+        for i in 0..r1cs.num_constraints() {
+            enforce_constraint(&mut builder, &variables, &r1cs.a[i], &r1cs.b[i], &r1cs.c[i]);
+        }
+
+        Ok(R1csCircuit {
+            circuit: builder.build(),
+            variables,
+            num_public_inputs: r1cs.num_public_inputs,
+        })
+    }
+
+    pub fn num_public_inputs(&self) -> usize {
+        self.num_public_inputs
+    }
+
+    /// Returns the inputs of witness generation, given an assignment of the R1CS's inputs.
+    pub fn partial_witness(
+        &self,
+        public_inputs: &[C::ScalarField],
+        private_inputs: &[C::ScalarField],
+    ) -> Result<PartialWitness<C::ScalarField>> {
+        ensure!(
+            public_inputs.len() == self.num_public_inputs
+                && 1 + public_inputs.len() + private_inputs.len() == self.variables.len(),
+            "Wrong number of inputs"
+        );
+        let mut partial_witness = PartialWitness::new();
+        for (&target, &value) in self.variables[1..]
+            .iter()
+            .zip(public_inputs.iter().chain(private_inputs))
+        {
+            partial_witness.try_set_target(target, value)?;
+        }
+        Ok(partial_witness)
+    }
+}
+
+/// Enforces `<a, z> * <b, z> = <c, z>`, where `z` holds the given variables.
+pub(crate) fn enforce_constraint<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    variables: &[Target<C::ScalarField>],
+    a: &LinearCombination<C::ScalarField>,
+    b: &LinearCombination<C::ScalarField>,
+    c: &LinearCombination<C::ScalarField>,
+) {
+    let a = scaled_term(builder, variables, a);
+    let b = scaled_term(builder, variables, b);
+    add_product_constraint(builder, variables, a, b, c);
+}
+
+/// Reduces a linear combination to `coefficient * target`, which avoids any gates when it has a
+/// single term.
+fn scaled_term<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    variables: &[Target<C::ScalarField>],
+    lc: &LinearCombination<C::ScalarField>,
+) -> (C::ScalarField, Target<C::ScalarField>) {
+    match lc.as_slice() {
+        [] => (C::ScalarField::ZERO, builder.zero_wire()),
+        &[(variable, coefficient)] => (coefficient, variables[variable]),
+        _ => (
+            C::ScalarField::ONE,
+            linear_combination(builder, variables, lc),
+        ),
+    }
+}
+
+/// Computes a linear combination with a chain of arithmetic gates. The first gate adds two terms,
+/// and each subsequent gate adds one more.
+fn linear_combination<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    variables: &[Target<C::ScalarField>],
+    lc: &LinearCombination<C::ScalarField>,
+) -> Target<C::ScalarField> {
+    let one = builder.one_wire();
+    let zero = builder.zero_wire();
+    let mut terms = lc
+        .iter()
+        .map(|&(v, coefficient)| (coefficient, variables[v]));
+    let (c_0, t_0) = terms.next().unwrap_or((C::ScalarField::ZERO, zero));
+    let (c_1, t_1) = terms.next().unwrap_or((C::ScalarField::ZERO, zero));
+    let mut sum = arithmetic_gate(builder, c_0, t_0, one, c_1, t_1);
+    for (coefficient, target) in terms {
+        sum = arithmetic_gate(builder, coefficient, target, one, C::ScalarField::ONE, sum);
+    }
+    sum
+}
+
+/// Constrains `a_coeff * a * b_coeff * b = <lc, z>`.
+fn add_product_constraint<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    variables: &[Target<C::ScalarField>],
+    (a_coeff, a): (C::ScalarField, Target<C::ScalarField>),
+    (b_coeff, b): (C::ScalarField, Target<C::ScalarField>),
+    lc: &LinearCombination<C::ScalarField>,
+) {
+    let product_coeff = a_coeff * b_coeff;
+    let zero = builder.zero_wire();
+    match *lc.as_slice() {
+        // If the first term of C has a nonzero coefficient, we can divide by it, so that gate's
+        // output is that term's variable.
+        [(v, c_v)] if c_v.is_nonzero() => {
+            let output = arithmetic_gate(
+                builder,
+                product_coeff / c_v,
+                a,
+                b,
+                C::ScalarField::ZERO,
+                zero,
+            );
+            builder.copy(output, variables[v]);
+        }
+        [(v, c_v), (w, c_w)] if c_v.is_nonzero() => {
+            let output =
+                arithmetic_gate(builder, product_coeff / c_v, a, b, -c_w / c_v, variables[w]);
+            builder.copy(output, variables[v]);
+        }
+        _ => {
+            let product = arithmetic_gate(builder, product_coeff, a, b, C::ScalarField::ZERO, zero);
+            let sum = if lc.is_empty() {
+                zero
+            } else {
+                linear_combination(builder, variables, lc)
+            };
+            builder.copy(product, sum);
+        }
+    }
+}
+
+/// Adds an `ArithmeticGate` computing `const_0 * multiplicand_0 * multiplicand_1 + const_1 * addend`.
+pub(crate) fn arithmetic_gate<C: HaloCurve>(
+    builder: &mut CircuitBuilder<C>,
+    const_0: C::ScalarField,
+    multiplicand_0: Target<C::ScalarField>,
+    multiplicand_1: Target<C::ScalarField>,
+    const_1: C::ScalarField,
+    addend: Target<C::ScalarField>,
+) -> Target<C::ScalarField> {
+    let gate = builder.num_gates();
+    builder.add_gate(ArithmeticGate::new(gate), vec![const_0, const_1]);
+    let wire = |input| Target::Wire(Wire { gate, input });
+    builder.copy(
+        multiplicand_0,
+        wire(ArithmeticGate::<C>::WIRE_MULTIPLICAND_0),
+    );
+    builder.copy(
+        multiplicand_1,
+        wire(ArithmeticGate::<C>::WIRE_MULTIPLICAND_1),
+    );
+    builder.copy(addend, wire(ArithmeticGate::<C>::WIRE_ADDEND));
+    wire(ArithmeticGate::<C>::WIRE_OUTPUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{verify_proof, Curve, Field, LinearCombination, R1cs, R1csCircuit, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// The rows `(A_i, B_i, C_i)` of a single constraint.
+    type Constraint = (LinearCombination<F>, LinearCombination<F>, LinearCombination<F>);
+
+    /// `x^3 + x + 5 = out`, with `out` public. `z = (1, out, x, x^2, x^3)`.
+    fn cubic() -> R1cs<F> {
+        R1cs {
+            num_public_inputs: 1,
+            num_private_inputs: 3,
+            a: vec![
+                vec![(2, F::ONE)],
+                vec![(3, F::ONE)],
+                vec![(4, F::ONE), (2, F::ONE), (0, F::FIVE)],
+            ],
+            b: vec![vec![(2, F::ONE)], vec![(2, F::ONE)], vec![(0, F::ONE)]],
+            c: vec![vec![(3, F::ONE)], vec![(4, F::ONE)], vec![(1, F::ONE)]],
+        }
+    }
+
+    fn cubic_inputs(x: u64) -> (Vec<F>, Vec<F>) {
+        let x = F::from_canonical_u64(x);
+        (vec![x.cube() + x + F::FIVE], vec![x, x.square(), x.cube()])
+    }
+
+    #[test]
+    fn test_r1cs_circuit() -> Result<()> {
+        let r1cs = cubic();
+        let (public_inputs, private_inputs) = cubic_inputs(3);
+        assert!(r1cs.is_satisfied(&public_inputs, &private_inputs));
+
+        let r1cs_circuit = R1csCircuit::<Tweedledee>::new(&r1cs, 128)?;
+        let circuit = &r1cs_circuit.circuit;
+        let inputs = r1cs_circuit.partial_witness(&public_inputs, &private_inputs)?;
+        let witness = circuit.generate_witness(inputs)?;
+        assert_eq!(circuit.get_public_inputs(&witness), public_inputs);
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        verify_proof::<Tweedledee, Tweedledum>(
+            &public_inputs,
+            &proof,
+            &[],
+            &circuit.to_vk(),
+            true,
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_r1cs_circuit_rejects_bad_witness() -> Result<()> {
+        let r1cs = cubic();
+        let r1cs_circuit = R1csCircuit::<Tweedledee>::new(&r1cs, 128)?;
+        let (_, private_inputs) = cubic_inputs(3);
+        let public_inputs = vec![F::from_canonical_u64(36)];
+        assert!(!r1cs.is_satisfied(&public_inputs, &private_inputs));
+        let inputs = r1cs_circuit.partial_witness(&public_inputs, &private_inputs)?;
+        assert!(r1cs_circuit.circuit.generate_witness(inputs).is_err());
+        Ok(())
+    }
+
+    /// Checks each shape of constraint against random R1CS instances, by comparing the circuit's
+    /// verdict with `R1cs::is_satisfied`.
+    #[test]
+    fn test_constraint_shapes() -> Result<()> {
+        // Each constraint is `<A, z> * <B, z> = <C, z>` with `z = (1, p, x, y, w)`, where `w` is
+        // chosen to satisfy it. C always involves `w` with a nonzero coefficient.
+        let shapes: Vec<Constraint> = vec![
+            (vec![(2, F::TWO)], vec![(3, F::THREE)], vec![(4, F::FIVE)]),
+            (
+                vec![(2, F::ONE)],
+                vec![(3, F::ONE)],
+                vec![(4, F::TWO), (1, F::NEG_ONE)],
+            ),
+            (
+                vec![(2, F::ONE), (0, F::ONE)],
+                vec![(3, F::ONE)],
+                vec![(1, F::ONE), (4, F::ONE), (2, F::TWO)],
+            ),
+            (vec![], vec![(3, F::ONE)], vec![(0, F::ZERO), (4, F::ONE)]),
+            (
+                vec![(2, F::ONE), (3, F::ONE), (0, F::TWO)],
+                vec![(0, F::THREE), (2, F::ONE)],
+                vec![(4, F::FOUR)],
+            ),
+        ];
+        for (a, b, c) in shapes {
+            let r1cs = R1cs {
+                num_public_inputs: 1,
+                num_private_inputs: 3,
+                a: vec![a],
+                b: vec![b],
+                c: vec![c.clone()],
+            };
+            let r1cs_circuit = R1csCircuit::<Tweedledee>::new(&r1cs, 128)?;
+
+            let (p, x, y) = (F::rand(), F::rand(), F::rand());
+            let evaluate = |lc: &[(usize, F)], w: F| {
+                let z = [F::ONE, p, x, y, w];
+                lc.iter()
+                    .fold(F::ZERO, |sum, &(v, coeff)| sum + coeff * z[v])
+            };
+            // Solve for w, which appears only in C.
+            let w_coeff = c
+                .iter()
+                .filter(|(v, _)| *v == 4)
+                .map(|(_, coeff)| *coeff)
+                .next()
+                .unwrap();
+            let target = evaluate(&r1cs.a[0], F::ZERO) * evaluate(&r1cs.b[0], F::ZERO);
+            let w = (target - evaluate(&c, F::ZERO)) / w_coeff;
+
+            for &(w, satisfied) in &[(w, true), (w + F::ONE, false)] {
+                assert_eq!(r1cs.is_satisfied(&[p], &[x, y, w]), satisfied);
+                let inputs = r1cs_circuit.partial_witness(&[p], &[x, y, w])?;
+                assert_eq!(
+                    r1cs_circuit.circuit.generate_witness(inputs).is_ok(),
+                    satisfied
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut r1cs = cubic();
+        assert!(r1cs.validate().is_ok());
+        r1cs.c[0][0].0 = 5;
+        assert!(r1cs.validate().is_err());
+        let mut r1cs = cubic();
+        r1cs.b.pop();
+        assert!(R1csCircuit::<Tweedledee>::new(&r1cs, 128).is_err());
+    }
+}
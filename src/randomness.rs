@@ -0,0 +1,66 @@
+use rand::Rng;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::Field;
+
+/// An RNG seeded from OS entropy, for secret randomness such as blinding factors.
+pub fn blinding_rng() -> ChaCha20Rng {
+    ChaCha20Rng::from_entropy()
+}
+
+/// A deterministic RNG, for reproducible proofs and test data. Its outputs must not be used as
+/// secret randomness unless the seed is itself secret.
+pub fn seeded_rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+/// A deterministic RNG seeded with 32 bytes, e.g. the output of a hash.
+pub fn seeded_rng_from_bytes(seed: [u8; 32]) -> ChaCha20Rng {
+    ChaCha20Rng::from_seed(seed)
+}
+
+/// A deterministic RNG seeded with the Blake2b hash of a domain separation label and some data.
+/// Since hashes don't map uniformly to field elements, this is the preferred way of deriving field
+/// elements from arbitrary bytes.
+pub fn hash_seeded_rng(domain: &[u8], data: &[u8]) -> ChaCha20Rng {
+    let mut state = blake2b_simd::Params::new().hash_length(32).to_state();
+    state.update(&(domain.len() as u64).to_le_bytes());
+    state.update(domain);
+    state.update(data);
+    let mut seed = [0u8; 32];
+    seed.copy_from_slice(state.finalize().as_bytes());
+    seeded_rng_from_bytes(seed)
+}
+
+/// Samples `n` uniformly random field elements.
+pub fn rand_field_elements<F: Field, R: Rng>(rng: &mut R, n: usize) -> Vec<F> {
+    (0..n).map(|_| F::rand_from_rng(rng)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{hash_seeded_rng, rand_field_elements, seeded_rng, TweedledumBase};
+
+    type F = TweedledumBase;
+
+    #[test]
+    fn test_seeded_rng() {
+        let a = rand_field_elements::<F, _>(&mut seeded_rng(1), 3);
+        let b = rand_field_elements::<F, _>(&mut seeded_rng(1), 3);
+        let c = rand_field_elements::<F, _>(&mut seeded_rng(2), 3);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_hash_seeded_rng() {
+        let sample = |domain: &[u8], data: &[u8]| {
+            rand_field_elements::<F, _>(&mut hash_seeded_rng(domain, data), 1)[0]
+        };
+        assert_eq!(sample(b"a", b"data"), sample(b"a", b"data"));
+        assert_ne!(sample(b"a", b"data"), sample(b"b", b"data"));
+        // The domain's length is bound, so moving bytes between the domain and data matters.
+        assert_ne!(sample(b"ab", b"c"), sample(b"a", b"bc"));
+    }
+}
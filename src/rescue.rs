@@ -1,5 +1,6 @@
 use crate::util::ceil_div_usize;
-use crate::{apply_mds, Field, PRF};
+use crate::permutation_batch::permute_in_lanes;
+use crate::{apply_mds, mds_matrix, Field, Sponge, PRF};
 use rand_chacha::rand_core::SeedableRng;
 use rand_chacha::ChaCha8Rng;
 
@@ -38,33 +39,9 @@ pub fn rescue_hash_n_to_3<F: Field>(inputs: Vec<F>, security_bits: usize) -> (F,
 }
 
 pub fn rescue_sponge<F: Field>(inputs: Vec<F>, num_outputs: usize, security_bits: usize) -> Vec<F> {
-    // This is mostly arbitrary, but we wouldn't want a huge width as the MDS layer could get
-    // expensive.
-    let rate = 3;
-    let capacity = 1;
-    let width = rate + capacity;
-
-    let mut state = vec![F::ZERO; width];
-
-    // Absorb all input chunks.
-    for input_chunk in inputs.chunks(rate) {
-        for i in 0..input_chunk.len() {
-            state[i] = state[i] + input_chunk[i];
-        }
-        state = rescue_permutation(&state, security_bits);
-    }
-
-    // Squeeze until we have the desired number of outputs.
-    let mut outputs = Vec::new();
-    loop {
-        for i in 0..rate {
-            outputs.push(state[i]);
-            if outputs.len() == num_outputs {
-                return outputs;
-            }
-        }
-        state = rescue_permutation(&state, security_bits);
-    }
+    let mut sponge = Sponge::new_rescue(security_bits);
+    sponge.absorb_all(&inputs);
+    sponge.squeeze_n(num_outputs)
 }
 
 pub fn rescue_permutation<F: Field>(state: &[F], security_bits: usize) -> Vec<F> {
@@ -87,6 +64,30 @@ pub fn rescue_permutation<F: Field>(state: &[F], security_bits: usize) -> Vec<F>
     state
 }
 
+/// Applies `rescue_permutation` to each of the given states, which must have the same width. The
+/// round constants are only generated once, and the states are permuted in lockstep, so this is
+/// much faster than permuting them one at a time.
+pub fn rescue_permutation_batch<F: Field>(states: &[Vec<F>], security_bits: usize) -> Vec<Vec<F>> {
+    if states.is_empty() {
+        return Vec::new();
+    }
+    let width = states[0].len();
+    let constants = generate_rescue_constants::<F>(width, security_bits);
+    let mds = mds_matrix::<F>(width).to_rows();
+
+    permute_in_lanes(states, |lanes| {
+        for (step_a_constants, step_b_constants) in &constants {
+            lanes.apply_sbox(|x| x.kth_root(F::ALPHA));
+            lanes.apply_mds(&mds);
+            lanes.add_constants(step_a_constants);
+
+            lanes.apply_sbox(|x| x.exp(F::ALPHA));
+            lanes.apply_mds(&mds);
+            lanes.add_constants(step_b_constants);
+        }
+    })
+}
+
 fn add_vecs<F: Field>(a: Vec<F>, b: Vec<F>) -> Vec<F> {
     a.iter()
         .zip(b.iter())
@@ -0,0 +1,174 @@
+use std::mem::size_of;
+
+use crate::util::log2_strict;
+use crate::{AffinePoint, Field, HaloCurve, ProjectivePoint, ProverConfig, ProverKey, NUM_CONSTANTS, QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER};
+
+/// The costs of basic operations on the machine proving, which `ProverKey::estimate_resources`
+/// scales by the number of operations proving takes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MachineProfile {
+    /// The time of a single field multiplication on one thread, in nanoseconds.
+    pub field_mul_nanos: f64,
+    /// The time of a single projective curve addition on one thread, in nanoseconds.
+    pub curve_add_nanos: f64,
+}
+
+impl Default for MachineProfile {
+    /// Rough costs for a recent x86_64 server core.
+    fn default() -> Self {
+        MachineProfile {
+            field_mul_nanos: 25.0,
+            curve_add_nanos: 350.0,
+        }
+    }
+}
+
+/// An estimate of the resources needed to generate a proof. See `ProverKey::estimate_resources`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ResourceEstimate {
+    /// The peak memory used while proving, including the prover key itself, in bytes.
+    pub peak_bytes: usize,
+    /// The wall-clock time of proving, excluding witness generation, in seconds.
+    pub est_seconds: f64,
+}
+
+impl<C: HaloCurve> ProverKey<C> {
+    /// Estimates the peak memory and time needed to prove with this key and configuration, using
+    /// the default `MachineProfile`. This lets services schedule proving jobs, and reject circuits
+    /// which won't fit, without proving first.
+    pub fn estimate_resources(&self, config: &ProverConfig) -> ResourceEstimate {
+        self.estimate_resources_with_profile(config, &MachineProfile::default())
+    }
+
+    /// Like `estimate_resources`, but with the operation costs of the given machine.
+    ///
+    /// The estimate counts the prover's large buffers and its field and curve operations, so it
+    /// ignores witness generation, and assumes that work is spread evenly among threads.
+    pub fn estimate_resources_with_profile(
+        &self,
+        config: &ProverConfig,
+        profile: &MachineProfile,
+    ) -> ResourceEstimate {
+        let n = self.degree();
+        let field_bytes = size_of::<C::ScalarField>();
+        let affine_bytes = size_of::<AffinePoint<C>>();
+        let projective_bytes = size_of::<ProjectivePoint<C>>();
+        let num_fixed_polys = NUM_CONSTANTS + self.num_routed_wires;
+        let num_t_chunks = QUOTIENT_POLYNOMIAL_DEGREE_MULTIPLIER;
+        let num_committed_polys = self.num_wires + 1 + num_t_chunks + 1;
+        let scalar_bits = C::ScalarField::BITS;
+
+        let key_window = self.pedersen_g_msm_precomputation.window();
+        let msm_window = config.msm_window.unwrap_or(key_window);
+        let msm_precomputation_bytes = |w: usize| n * scalar_bits.div_ceil(w) * affine_bytes;
+
+        // The key's gate constants, fixed polynomials, subgroups, FFT precomputations, generators
+        // and MSM precomputation.
+        let mut key_elements = n * (NUM_CONSTANTS + num_fixed_polys + 1 + 8 + 2 + 2 * 8);
+        if !self.low_memory {
+            key_elements += 8 * n * num_fixed_polys;
+        }
+        let mut key_bytes = key_elements * field_bytes
+            + n * affine_bytes
+            + msm_precomputation_bytes(key_window);
+        // A custom MSM window means a second precomputation, computed for each proof.
+        if msm_window != key_window {
+            key_bytes += msm_precomputation_bytes(msm_window);
+        }
+
+        // While computing the quotient, the prover holds the witness with and without public
+        // inputs, in both forms, Z, a coset of LDEs, the degree-8n vanishing polynomial in both
+        // forms, and the buffers of dividing it by Z_H.
+        let mut coset_polys = self.num_wires + 1;
+        if self.low_memory {
+            coset_polys += num_fixed_polys;
+        }
+        let quotient_elements = n * (4 * self.num_wires + 2 + coset_polys) + 8 * n * 5;
+        // The opening proof holds copies of all polynomials' coefficients, and the IPA's vectors.
+        let opening_elements = n * (num_fixed_polys + 4 * self.num_wires + num_committed_polys + 2);
+        let proving_bytes = quotient_elements.max(opening_elements) * field_bytes + n * projective_bytes;
+
+        // Each degree-n FFT costs about (n/2) log n multiplications. The wire polynomials are
+        // interpolated twice, each of the 8 cosets takes an LDE of each wire polynomial and Z, and
+        // the vanishing polynomial is interpolated and divided by Z_H at degree 8n.
+        let log_n = log2_strict(n);
+        let mut num_ffts = 2 * self.num_wires + 1 + 8 * coset_polys;
+        if self.low_memory {
+            num_ffts += self.num_routed_wires;
+        }
+        let fft_muls = num_ffts * n / 2 * log_n + 2 * (8 * n / 2) * (log_n + 3);
+        // Evaluating the constraints of all gates takes a few hundred multiplications per point.
+        let constraint_muls = 8 * n * CONSTRAINT_MULS_PER_POINT;
+        let field_muls = fft_muls + constraint_muls;
+
+        // Committing to a polynomial with a window-w precomputation takes about n bits/w additions.
+        // Each IPA round computes two MSMs of half its size from scratch, and folds its generators,
+        // which adds up to a few scalar multiplications per generator.
+        let commitment_adds = num_committed_polys * n * scalar_bits / msm_window;
+        let ipa_adds = n * scalar_bits * 4;
+        let curve_adds = commitment_adds + ipa_adds;
+
+        let single_thread_nanos =
+            field_muls as f64 * profile.field_mul_nanos + curve_adds as f64 * profile.curve_add_nanos;
+        ResourceEstimate {
+            peak_bytes: key_bytes + proving_bytes,
+            est_seconds: single_thread_nanos / num_threads(config) as f64 / 1e9,
+        }
+    }
+}
+
+/// A rough count of the multiplications needed to evaluate all gates' constraints at one point.
+const CONSTRAINT_MULS_PER_POINT: usize = 400;
+
+/// The number of threads proving with `config` uses.
+fn num_threads(config: &ProverConfig) -> usize {
+    #[cfg(feature = "parallel")]
+    let default_threads = rayon::current_num_threads();
+    #[cfg(not(feature = "parallel"))]
+    let default_threads = 1;
+    config.num_threads.unwrap_or(default_threads).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Circuit, CircuitBuilder, ProverConfig, Tweedledee};
+
+    fn circuit_with_gates(num_gates: usize) -> Circuit<Tweedledee> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_virtual_target();
+        let mut acc = x;
+        while builder.num_gates() < num_gates {
+            acc = builder.mul(acc, x);
+        }
+        builder.assert_nonzero(acc);
+        builder.build()
+    }
+
+    #[test]
+    fn test_estimate_resources() {
+        let config = ProverConfig {
+            num_threads: Some(1),
+            ..ProverConfig::default()
+        };
+        let small = circuit_with_gates(10).estimate_resources(&config);
+        let large = circuit_with_gates(200).estimate_resources(&config);
+        assert!(small.peak_bytes < large.peak_bytes);
+        assert!(small.est_seconds < large.est_seconds);
+
+        // Proving with more threads is faster, but uses as much memory.
+        let mut circuit = circuit_with_gates(200);
+        let parallel_config = ProverConfig {
+            num_threads: Some(4),
+            ..ProverConfig::default()
+        };
+        let parallel = circuit.estimate_resources(&parallel_config);
+        assert_eq!(parallel.peak_bytes, large.peak_bytes);
+        assert!(parallel.est_seconds < large.est_seconds);
+
+        // The low-memory mode trades memory for time.
+        circuit.prover_key.set_low_memory(true);
+        let low_memory = circuit.estimate_resources(&config);
+        assert!(low_memory.peak_bytes < large.peak_bytes);
+        assert!(low_memory.est_seconds > large.est_seconds);
+    }
+}
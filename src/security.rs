@@ -0,0 +1,73 @@
+use anyhow::{ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{Field, SECURITY_BITS};
+
+/// The security parameters of a circuit, which its keys and proofs record. See
+/// `CircuitBuilder::set_security_params`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct SecurityParams {
+    /// The targeted security level, in bits. This sets the number of rounds of the algebraic hashes
+    /// used by the Fiat-Shamir transcript, by in-circuit hashing and by `hash_to_curve`.
+    pub bits: usize,
+    /// The number of bits which Fiat-Shamir challenges are truncated to before they're mapped
+    /// through the endomorphism, in the IPA and in the Schnorr proof which ends it. Proofs are only
+    /// as sound as the shorter of `bits` and `challenge_len`.
+    pub challenge_len: usize,
+}
+
+impl SecurityParams {
+    /// Parameters targeting `bits` bits of security, with challenges of the same length.
+    pub fn new(bits: usize) -> Self {
+        SecurityParams {
+            bits,
+            challenge_len: bits,
+        }
+    }
+
+    pub fn with_challenge_len(self, challenge_len: usize) -> Self {
+        SecurityParams {
+            challenge_len,
+            ..self
+        }
+    }
+
+    /// Checks that challenges of this length can be taken from elements of `F`. The endomorphism
+    /// consumes challenge bits in pairs, and we assume the most significant bit of an element is
+    /// unset.
+    pub fn check<F: Field>(&self) -> Result<()> {
+        ensure!(self.bits > 0, "The security level must be positive");
+        ensure!(
+            self.challenge_len > 0 && self.challenge_len.is_multiple_of(2),
+            "The challenge length must be a positive even number of bits, got {}",
+            self.challenge_len
+        );
+        ensure!(
+            self.challenge_len < F::BITS,
+            "Challenges of {} bits don't fit in a {}-bit field",
+            self.challenge_len,
+            F::BITS
+        );
+        Ok(())
+    }
+}
+
+impl Default for SecurityParams {
+    fn default() -> Self {
+        Self::new(SECURITY_BITS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SecurityParams, TweedledeeBase};
+
+    #[test]
+    fn test_check() {
+        assert!(SecurityParams::default().check::<TweedledeeBase>().is_ok());
+        assert!(SecurityParams::new(100).check::<TweedledeeBase>().is_ok());
+        assert!(SecurityParams::new(128).with_challenge_len(127).check::<TweedledeeBase>().is_err());
+        assert!(SecurityParams::new(128).with_challenge_len(256).check::<TweedledeeBase>().is_err());
+        assert!(SecurityParams::new(0).check::<TweedledeeBase>().is_err());
+    }
+}
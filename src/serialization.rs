@@ -1,9 +1,11 @@
-use crate::{AffinePoint, Curve, Field, TweedledumBase, Bls12377Base, Bls12377Scalar, TweedledeeBase, PallasBase, VestaBase};
+use crate::{AffinePoint, Curve, Field, HaloCurve, OpeningSet, Proof, ProofParameters, ProverKey, SchnorrProof, VerificationKey, TweedledumBase, Bls12377Base, Bls12377Scalar, Bn254Base, Bn254Scalar, TweedledeeBase, PallasBase, VestaBase};
 use serde::de::Error as DeError;
 use serde::de::Visitor;
 use serde::ser::Error as SerdeError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::marker::PhantomData;
 use std::io::{Error, ErrorKind, Read, Result, Write};
 
 pub trait ToBytes {
@@ -14,6 +16,18 @@ pub trait FromBytes: Sized {
     fn read<R: Read>(reader: R) -> Result<Self>;
 }
 
+/// Decodes a value which should take up all of the given bytes, rejecting any trailing bytes.
+fn read_all<T: FromBytes>(mut bytes: &[u8]) -> Result<T> {
+    let value = T::read(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{} trailing bytes", bytes.len()),
+        ));
+    }
+    Ok(value)
+}
+
 impl<F: Field> ToBytes for F {
     fn write<W: Write>(&self, mut writer: W) -> Result<()> {
         writer.write_all(&self.to_canonical_u8_vec())
@@ -43,34 +57,89 @@ impl<C: Curve> ToBytes for AffinePoint<C> {
     }
 }
 
+/// Only the canonical encoding of each point is accepted: the mask must not have unknown bits set,
+/// the point at infinity must be encoded with a zero x coordinate and no sign bit, and a point
+/// with `y = 0` must not have its sign bit set. Points outside of the prime order subgroup are
+/// rejected too, which only matters for curves with a cofactor.
 impl<C: Curve> FromBytes for AffinePoint<C> {
     fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let non_canonical = |msg: &str| Error::new(ErrorKind::InvalidData, msg);
+
         let mut mask = vec![0u8];
         reader.read_exact(&mut mask)?;
         let mask = mask[0];
+        if mask & !3 != 0 {
+            return Err(non_canonical("Unknown bits set in point encoding"));
+        }
+        // The x coordinate is always written, even for the point at infinity, so we always read
+        // it to stay aligned with the rest of the input.
+        let mut buf = vec![0u8; C::BaseField::BYTES];
+        reader.read_exact(&mut buf)?;
+
         if mask & 1 == 1 {
+            if mask != 1 || buf.iter().any(|&b| b != 0) {
+                return Err(non_canonical("Non-canonical encoding of the point at infinity"));
+            }
             return Ok(AffinePoint {
                 x: C::BaseField::ZERO,
                 y: C::BaseField::ZERO,
                 zero: true,
             });
         }
-        let mut buf = vec![0u8; C::BaseField::BYTES];
-        reader.read_exact(&mut buf)?;
+
         let x = C::BaseField::from_canonical_u8_vec(buf.to_vec())
-            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
         let square_candidate = x.cube() + C::A * x + C::B;
         let y = square_candidate
             .square_root()
-            .ok_or_else(|| Error::new(ErrorKind::Other, "Invalid x coordinate"))?;
-        if (y.to_canonical_u64_vec()[0] % 2) as u8 == (mask & 2) >> 1 {
-            Ok(AffinePoint::nonzero(x, y))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid x coordinate"))?;
+        let odd = (mask & 2) >> 1;
+        let y = if (y.to_canonical_u64_vec()[0] % 2) as u8 == odd {
+            y
         } else {
-            Ok(AffinePoint::nonzero(x, -y))
+            -y
+        };
+        // If y is zero, negating it doesn't change its parity.
+        if (y.to_canonical_u64_vec()[0] % 2) as u8 != odd {
+            return Err(non_canonical("Sign bit set for a point with y = 0"));
+        }
+        let point = AffinePoint::nonzero(x, y);
+        if !point.is_in_subgroup() {
+            return Err(Error::new(ErrorKind::InvalidData, "Point is not in the prime order subgroup"));
         }
+        Ok(point)
+    }
+}
+
+/// Serializes a `ToBytes` encoding as bytes, or as a hex string for human-readable formats such as
+/// JSON.
+fn serialize_encoding<S: Serializer>(bytes: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+/// Deserializes an encoding written by `serialize_encoding`.
+fn deserialize_encoding<'de, D: Deserializer<'de>, V: Visitor<'de>>(
+    deserializer: D,
+    visitor: V,
+) -> std::result::Result<V::Value, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(visitor)
+    } else {
+        deserializer.deserialize_bytes(visitor)
     }
 }
 
+fn decode_hex<E: DeError>(hex: &str) -> std::result::Result<Vec<u8>, E> {
+    hex::decode(hex).map_err(|e| E::custom(format!("Invalid hex: {}", e)))
+}
+
+/// Points and field elements are serialized as their `ToBytes` encodings, which human-readable
+/// formats such as JSON represent as lowercase hex strings. Deserialization only accepts canonical
+/// encodings, with no trailing bytes.
 impl<C: Curve> Serialize for AffinePoint<C> {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
         where
@@ -79,7 +148,7 @@ impl<C: Curve> Serialize for AffinePoint<C> {
         let mut buf = vec![];
         self.write(&mut buf)
             .map_err(|e| S::Error::custom(format!("{}", e)))?;
-        serializer.serialize_bytes(&buf)
+        serialize_encoding(&buf, serializer)
     }
 }
 
@@ -100,12 +169,19 @@ impl<'de, C: Curve> Deserialize<'de> for AffinePoint<C> {
             }
 
             fn visit_bytes<E: DeError>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
-                AffinePoint::<C>::read(v).map_err(|e| DeError::custom(format!("{}", e)))
+                read_all::<AffinePoint<C>>(v).map_err(|e| DeError::custom(format!("{}", e)))
+            }
+
+            fn visit_str<E: DeError>(self, v: &str) -> std::result::Result<Self::Value, E> {
+                self.visit_bytes(&decode_hex(v)?)
             }
         }
-        deserializer.deserialize_bytes(AffinePointVisitor {
-            phantom: std::marker::PhantomData,
-        })
+        deserialize_encoding(
+            deserializer,
+            AffinePointVisitor {
+                phantom: std::marker::PhantomData,
+            },
+        )
     }
 }
 
@@ -119,7 +195,7 @@ macro_rules! impl_serde_field {
                 let mut buf = vec![];
                 self.write(&mut buf)
                     .map_err(|e| S::Error::custom(format!("{}", e)))?;
-                serializer.serialize_bytes(&buf)
+                serialize_encoding(&buf, serializer)
             }
         }
 
@@ -138,10 +214,14 @@ macro_rules! impl_serde_field {
                     }
 
                     fn visit_bytes<E: DeError>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
-                        <$field>::read(v).map_err(|e| DeError::custom(format!("{}", e)))
+                        read_all::<$field>(v).map_err(|e| DeError::custom(format!("{}", e)))
+                    }
+
+                    fn visit_str<E: DeError>(self, v: &str) -> std::result::Result<Self::Value, E> {
+                        self.visit_bytes(&decode_hex(v)?)
                     }
                 }
-                deserializer.deserialize_bytes(FieldVisitor {})
+                deserialize_encoding(deserializer, FieldVisitor {})
             }
         }
     };
@@ -151,17 +231,439 @@ impl_serde_field!(TweedledumBase);
 impl_serde_field!(TweedledeeBase);
 impl_serde_field!(Bls12377Base);
 impl_serde_field!(Bls12377Scalar);
+impl_serde_field!(Bn254Base);
+impl_serde_field!(Bn254Scalar);
 impl_serde_field!(PallasBase);
 impl_serde_field!(VestaBase);
 
+/// The version of the binary proof encoding. It is the first byte of every encoded proof, and
+/// should be bumped whenever the encoding changes, so that old proofs are rejected rather than
+/// misread.
+pub const PROOF_FORMAT_VERSION: u8 = 3;
+
+/// The version of the serialized `ProverKey` and `VerificationKey` formats. It should be bumped
+/// whenever either key changes, so that old keys are rejected rather than misread.
+pub const KEY_FORMAT_VERSION: u8 = 3;
+
+/// Records the format version and curve of a serialized key. Deserializing a key with another
+/// format version, or for another curve, fails with a descriptive error.
+pub struct KeyFormat<C: Curve> {
+    _curve: PhantomData<C>,
+}
+
+impl<C: Curve> KeyFormat<C> {
+    pub fn new() -> Self {
+        KeyFormat {
+            _curve: PhantomData,
+        }
+    }
+}
+
+impl<C: Curve> Default for KeyFormat<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Curve> Clone for KeyFormat<C> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Curve> fmt::Debug for KeyFormat<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyFormat {{ version: {}, curve: {} }}", KEY_FORMAT_VERSION, C::NAME)
+    }
+}
+
+impl<C: Curve> PartialEq for KeyFormat<C> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<C: Curve> Eq for KeyFormat<C> {}
+
+#[derive(Serialize, Deserialize)]
+struct RawKeyFormat {
+    version: u8,
+    curve: String,
+}
+
+impl<C: Curve> Serialize for KeyFormat<C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        RawKeyFormat {
+            version: KEY_FORMAT_VERSION,
+            curve: C::NAME.to_string(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, C: Curve> Deserialize<'de> for KeyFormat<C> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let raw = RawKeyFormat::deserialize(deserializer)?;
+        if raw.version != KEY_FORMAT_VERSION {
+            return Err(D::Error::custom(format!(
+                "Unsupported key format version {}, expected {}",
+                raw.version, KEY_FORMAT_VERSION
+            )));
+        }
+        if raw.curve != C::NAME {
+            return Err(D::Error::custom(format!(
+                "Expected a key over {}, got one over {}",
+                C::NAME,
+                raw.curve
+            )));
+        }
+        Ok(Self::new())
+    }
+}
+
+/// Writes a slice, prefixed by its length as a little-endian `u32`.
+fn write_vec<T: ToBytes, W: Write>(items: &[T], mut writer: W) -> Result<()> {
+    writer.write_all(&(items.len() as u32).to_le_bytes())?;
+    for item in items {
+        item.write(&mut writer)?;
+    }
+    Ok(())
+}
+
+/// Reads a vector written by `write_vec`.
+fn read_vec<T: FromBytes, R: Read>(mut reader: R) -> Result<Vec<T>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    (0..u32::from_le_bytes(len))
+        .map(|_| T::read(&mut reader))
+        .collect()
+}
+
+impl<C: HaloCurve> ToBytes for SchnorrProof<C> {
+    fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        self.r.write(&mut writer)?;
+        self.z1.write(&mut writer)?;
+        self.z2.write(&mut writer)
+    }
+}
+
+impl<C: HaloCurve> FromBytes for SchnorrProof<C> {
+    fn read<R: Read>(mut reader: R) -> Result<Self> {
+        Ok(SchnorrProof {
+            r: AffinePoint::read(&mut reader)?,
+            z1: C::ScalarField::read(&mut reader)?,
+            z2: C::ScalarField::read(&mut reader)?,
+        })
+    }
+}
+
+impl<F: Field> ToBytes for OpeningSet<F> {
+    fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_vec(&self.o_constants, &mut writer)?;
+        write_vec(&self.o_plonk_sigmas, &mut writer)?;
+        write_vec(&self.o_wires, &mut writer)?;
+        self.o_plonk_z.write(&mut writer)?;
+        write_vec(&self.o_plonk_t, &mut writer)?;
+        write_vec(&self.o_old_proofs, &mut writer)?;
+        self.o_pi_quotient.write(&mut writer)
+    }
+}
+
+impl<F: Field> FromBytes for OpeningSet<F> {
+    fn read<R: Read>(mut reader: R) -> Result<Self> {
+        Ok(OpeningSet {
+            o_constants: read_vec(&mut reader)?,
+            o_plonk_sigmas: read_vec(&mut reader)?,
+            o_wires: read_vec(&mut reader)?,
+            o_plonk_z: F::read(&mut reader)?,
+            o_plonk_t: read_vec(&mut reader)?,
+            o_old_proofs: read_vec(&mut reader)?,
+            o_pi_quotient: F::read(&mut reader)?,
+        })
+    }
+}
+
+/// Writes a string, prefixed by its length as a little-endian `u32`.
+fn write_str<W: Write>(s: &str, mut writer: W) -> Result<()> {
+    writer.write_all(&(s.len() as u32).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+/// Reads a string written by `write_str`.
+fn read_str<R: Read>(mut reader: R) -> Result<String> {
+    let len = read_u32(&mut reader)?;
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len as usize {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "Truncated string"));
+    }
+    String::from_utf8(buf).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn read_u32<R: Read>(mut reader: R) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+impl ToBytes for ProofParameters {
+    fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_str(&self.curve, &mut writer)?;
+        write_str(&self.transcript_hash, &mut writer)?;
+        writer.write_all(&(self.num_wires as u32).to_le_bytes())?;
+        writer.write_all(&(self.security_bits as u32).to_le_bytes())?;
+        writer.write_all(&(self.challenge_len as u32).to_le_bytes())
+    }
+}
+
+impl FromBytes for ProofParameters {
+    fn read<R: Read>(mut reader: R) -> Result<Self> {
+        Ok(ProofParameters {
+            curve: read_str(&mut reader)?,
+            transcript_hash: read_str(&mut reader)?,
+            num_wires: read_u32(&mut reader)? as usize,
+            security_bits: read_u32(&mut reader)? as usize,
+            challenge_len: read_u32(&mut reader)? as usize,
+        })
+    }
+}
+
+/// Proofs are encoded as a `PROOF_FORMAT_VERSION` byte, followed by each field in declaration
+/// order. Curve points are compressed, and field elements are in canonical form.
+impl<C: HaloCurve> ToBytes for Proof<C> {
+    fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&[PROOF_FORMAT_VERSION])?;
+        self.parameters.write(&mut writer)?;
+        write_vec(&self.c_wires, &mut writer)?;
+        self.c_plonk_z.write(&mut writer)?;
+        write_vec(&self.c_plonk_t, &mut writer)?;
+        self.c_pis_quotient.write(&mut writer)?;
+        self.o_local.write(&mut writer)?;
+        self.o_right.write(&mut writer)?;
+        self.o_below.write(&mut writer)?;
+        write_vec(&self.halo_l, &mut writer)?;
+        write_vec(&self.halo_r, &mut writer)?;
+        self.halo_g.write(&mut writer)?;
+        self.schnorr_proof.write(&mut writer)
+    }
+}
+
+impl<C: HaloCurve> FromBytes for Proof<C> {
+    fn read<R: Read>(mut reader: R) -> Result<Self> {
+        let mut version = [0u8];
+        reader.read_exact(&mut version)?;
+        if version[0] != PROOF_FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported proof format version {}, expected {}",
+                    version[0], PROOF_FORMAT_VERSION
+                ),
+            ));
+        }
+        let parameters = ProofParameters::read(&mut reader)?;
+        if parameters.curve != C::NAME {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Expected a proof over {}, got one over {}", C::NAME, parameters.curve),
+            ));
+        }
+        Ok(Proof {
+            parameters,
+            c_wires: read_vec(&mut reader)?,
+            c_plonk_z: AffinePoint::read(&mut reader)?,
+            c_plonk_t: read_vec(&mut reader)?,
+            c_pis_quotient: AffinePoint::read(&mut reader)?,
+            o_local: OpeningSet::read(&mut reader)?,
+            o_right: OpeningSet::read(&mut reader)?,
+            o_below: OpeningSet::read(&mut reader)?,
+            halo_l: read_vec(&mut reader)?,
+            halo_r: read_vec(&mut reader)?,
+            halo_g: AffinePoint::read(&mut reader)?,
+            schnorr_proof: SchnorrProof::read(&mut reader)?,
+        })
+    }
+}
+
+impl<C: HaloCurve> Proof<C> {
+    /// Encodes the proof in our compact binary format. See `PROOF_FORMAT_VERSION`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut buf).expect("Writing to a Vec should not fail");
+        buf
+    }
+
+    /// Decodes a proof encoded with `to_bytes`, rejecting any trailing bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        read_all(bytes)
+    }
+}
+
+/// JSON encodings, meant for debugging, test vectors and services not written in Rust. Points and
+/// field elements are hex strings of their binary encodings, i.e. compressed points and
+/// little-endian field elements.
+impl<C: HaloCurve> Proof<C>
+where
+    Self: Serialize + DeserializeOwned,
+{
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serializing a proof should not fail")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl<C: HaloCurve> VerificationKey<C>
+where
+    Self: Serialize + DeserializeOwned,
+{
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Serializing a verification key should not fail")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Keys are encoded as CBOR, the same as when they're stored with `serde_cbor`.
+impl<C: HaloCurve> ToBytes for VerificationKey<C>
+where
+    Self: Serialize,
+{
+    fn write<W: Write>(&self, writer: W) -> Result<()> {
+        serde_cbor::to_writer(writer, self).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl<C: HaloCurve> FromBytes for VerificationKey<C>
+where
+    Self: DeserializeOwned,
+{
+    fn read<R: Read>(reader: R) -> Result<Self> {
+        read_cbor(reader)
+    }
+}
+
+impl<C: HaloCurve> ToBytes for ProverKey<C>
+where
+    Self: Serialize,
+{
+    fn write<W: Write>(&self, writer: W) -> Result<()> {
+        serde_cbor::to_writer(writer, self).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+impl<C: HaloCurve> FromBytes for ProverKey<C>
+where
+    Self: DeserializeOwned,
+{
+    fn read<R: Read>(reader: R) -> Result<Self> {
+        read_cbor(reader)
+    }
+}
+
+/// Reads a single CBOR value, leaving anything after it unread.
+fn read_cbor<T: DeserializeOwned, R: Read>(reader: R) -> Result<T> {
+    let mut deserializer = serde_cbor::Deserializer::from_reader(reader);
+    T::deserialize(&mut deserializer).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Text encodings of the binary encodings of proofs, keys, commitments and field elements, for
+/// embedding them in JSON-RPC payloads and URLs. Base64 strings use the URL-safe alphabet without
+/// padding (RFC 4648, section 5).
+pub trait TextEncoding: ToBytes + FromBytes {
+    fn to_hex(&self) -> String {
+        hex::encode(to_vec(self))
+    }
+
+    fn from_hex(hex: &str) -> Result<Self> {
+        let bytes = hex::decode(hex).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        read_all(&bytes)
+    }
+
+    fn to_base64(&self) -> String {
+        base64_encode(&to_vec(self))
+    }
+
+    fn from_base64(base64: &str) -> Result<Self> {
+        read_all(&base64_decode(base64)?)
+    }
+}
+
+impl<T: ToBytes + FromBytes> TextEncoding for T {}
+
+fn to_vec<T: ToBytes + ?Sized>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.write(&mut buf).expect("Writing to a Vec should not fail");
+    buf
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let n = (block[0] as u32) << 16 | (block[1] as u32) << 8 | block[2] as u32;
+        for i in 0..=chunk.len() {
+            encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Decodes unpadded base64url, rejecting padding, other characters and non-canonical encodings,
+/// i.e. those with nonzero unused bits in the last character.
+fn base64_decode(encoded: &str) -> Result<Vec<u8>> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg);
+    if encoded.len() % 4 == 1 {
+        return Err(invalid("Invalid base64 length"));
+    }
+
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.as_bytes().chunks(4) {
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let digit = BASE64_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or_else(|| invalid("Invalid base64 character"))?;
+            n |= (digit as u32) << (18 - 6 * i);
+        }
+        let num_bytes = chunk.len() - 1;
+        if n & (0xFFFFFF >> (8 * num_bytes)) != 0 {
+            return Err(invalid("Non-canonical base64"));
+        }
+        bytes.extend_from_slice(&n.to_be_bytes()[1..=num_bytes]);
+    }
+    Ok(bytes)
+}
+
+/// Encodes public inputs as a JSON array of hex strings, as in `Proof::to_json`.
+pub fn public_inputs_to_json<F: Field>(public_inputs: &[F]) -> String {
+    serde_json::to_string_pretty(public_inputs).expect("Serializing field elements should not fail")
+}
+
+/// Decodes public inputs encoded with `public_inputs_to_json`.
+pub fn public_inputs_from_json<F: Field>(json: &str) -> Result<Vec<F>> {
+    Ok(serde_json::from_str(json)?)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{blake_hash_base_field_to_curve, CircuitBuilder, HaloCurve, PartialWitness, Proof, VerificationKey};
+    use crate::{blake_hash_base_field_to_curve, field_to_biguint, verify, verify_proof, Circuit, CircuitBuilder, HaloCurve, PartialWitness, Proof, ProverKey, VerificationError, VerificationKey, Witness};
     use crate::{Bls12377, Bls12377Base, Bls12377Scalar};
+    use crate::{Bn254, Bn254Base, Bn254Scalar};
     use crate::{Tweedledee, TweedledeeBase, Tweedledum, TweedledumBase};
     use crate::{Pallas, PallasBase, Vesta, VestaBase};
     use anyhow::Result;
+    use num::BigUint;
 
     macro_rules! test_field_serialization {
         ($field:ty, $test_name:ident) => {
@@ -224,6 +726,8 @@ mod test {
     test_field_serialization!(TweedledumBase, test_tweedledum_base_serialization);
     test_field_serialization!(Bls12377Base, test_bls_base_serialization);
     test_field_serialization!(Bls12377Scalar, test_bls_scalar_serialization);
+    test_field_serialization!(Bn254Base, test_bn254_base_serialization);
+    test_field_serialization!(Bn254Scalar, test_bn254_scalar_serialization);
     test_curve_serialization!(
         Pallas,
         <Pallas as Curve>::BaseField,
@@ -249,9 +753,174 @@ mod test {
         <Bls12377 as Curve>::BaseField,
         test_bls_curve_serialization
     );
+    test_curve_serialization!(
+        Bn254,
+        <Bn254 as Curve>::BaseField,
+        test_bn254_curve_serialization
+    );
 
-    // Generate a proof and verification key for the factorial circuit.
-    fn get_circuit_vk<C: HaloCurve, InnerC: HaloCurve<BaseField=C::ScalarField>>() -> (Proof<C>, VerificationKey<C>) {
+    #[test]
+    fn test_non_canonical_encodings_rejected() -> Result<()> {
+        type F = TweedledumBase;
+        let from_cbor_bytes = |bytes: Vec<u8>| serde_cbor::to_vec(&serde_cbor::Value::Bytes(bytes));
+
+        // A field element must be in range, and must not have trailing bytes.
+        assert!(serde_cbor::from_slice::<F>(&from_cbor_bytes(vec![0xFF; F::BYTES])?).is_err());
+        let mut buf = F::ONE.to_canonical_u8_vec();
+        buf.push(0);
+        assert!(serde_cbor::from_slice::<F>(&from_cbor_bytes(buf)?).is_err());
+
+        let p = blake_hash_base_field_to_curve::<Tweedledum>(F::rand());
+        let mut encoded = vec![];
+        p.write(&mut encoded)?;
+        let with_mask = |mask: u8| {
+            let mut e = encoded.clone();
+            e[0] = mask;
+            e
+        };
+        assert!(AffinePoint::<Tweedledum>::read(&with_mask(encoded[0] | 4)[..]).is_err());
+        // The point at infinity must have a zero x coordinate and no sign bit.
+        assert!(AffinePoint::<Tweedledum>::read(&with_mask(1)[..]).is_err());
+        let mut zero = [0u8; F::BYTES + 1];
+        zero[0] = 3;
+        assert!(AffinePoint::<Tweedledum>::read(&zero[..]).is_err());
+        zero[0] = 1;
+        assert!(AffinePoint::<Tweedledum>::read(&zero[..])?.zero);
+
+        Ok(())
+    }
+
+    /// Encodes `x` in `num_bytes` little-endian bytes, like a field element, but without reducing it.
+    fn encode_unreduced(x: &BigUint, num_bytes: usize) -> Vec<u8> {
+        let mut bytes = x.to_bytes_le();
+        bytes.resize(num_bytes, 0);
+        bytes
+    }
+
+    fn assert_field_canonicality<F: Field + Serialize + DeserializeOwned>() -> Result<()> {
+        let modulus = field_to_biguint(F::NEG_ONE) + 1u32;
+        for value in &[modulus.clone(), modulus.clone() + 1u32, modulus.clone() * 2u32 - 1u32] {
+            let bytes = encode_unreduced(value, F::BYTES);
+            assert!(F::read(&bytes[..]).is_err(), "{} was accepted", value);
+            assert!(F::from_hex(&hex::encode(&bytes)).is_err());
+            assert!(serde_json::from_str::<F>(&format!("\"{}\"", hex::encode(&bytes))).is_err());
+            let cbor = serde_cbor::to_vec(&serde_cbor::Value::Bytes(bytes))?;
+            assert!(serde_cbor::from_slice::<F>(&cbor).is_err());
+        }
+        Ok(())
+    }
+
+    fn assert_curve_canonicality<C: Curve>() {
+        let modulus = field_to_biguint(C::BaseField::NEG_ONE) + 1u32;
+        let mut x_out_of_range = vec![0u8];
+        x_out_of_range.extend(encode_unreduced(&modulus, C::BaseField::BYTES));
+        assert!(AffinePoint::<C>::read(&x_out_of_range[..]).is_err());
+
+        // Find an x for which x^3 + a x + b is not a square, so that no point has that x.
+        let x_off_curve = (0..)
+            .map(|_| C::BaseField::rand())
+            .find(|&x| (x.cube() + C::A * x + C::B).square_root().is_none())
+            .expect("Half of all x coordinates are off the curve");
+        let mut off_curve = vec![0u8];
+        off_curve.extend(x_off_curve.to_canonical_u8_vec());
+        assert!(AffinePoint::<C>::read(&off_curve[..]).is_err());
+    }
+
+    #[test]
+    fn test_canonicality() -> Result<()> {
+        assert_field_canonicality::<TweedledeeBase>()?;
+        assert_field_canonicality::<TweedledumBase>()?;
+        assert_field_canonicality::<PallasBase>()?;
+        assert_field_canonicality::<VestaBase>()?;
+        assert_field_canonicality::<Bn254Base>()?;
+        assert_field_canonicality::<Bn254Scalar>()?;
+        assert_field_canonicality::<Bls12377Base>()?;
+        assert_field_canonicality::<Bls12377Scalar>()?;
+
+        assert_curve_canonicality::<Tweedledee>();
+        assert_curve_canonicality::<Tweedledum>();
+        assert_curve_canonicality::<Pallas>();
+        assert_curve_canonicality::<Vesta>();
+        assert_curve_canonicality::<Bn254>();
+        assert_curve_canonicality::<Bls12377>();
+        Ok(())
+    }
+
+    #[test]
+    fn test_points_outside_subgroup_rejected() -> Result<()> {
+        type BF = Bls12377Base;
+        // BLS12-377's G1 has a large cofactor, so a random point is almost surely not in the
+        // subgroup of order |Bls12377Scalar|.
+        let (x, y) = (0..)
+            .map(|_| BF::rand())
+            .find_map(|x| (x.cube() + BF::ONE).square_root().map(|y| (x, y)))
+            .expect("Half of all x coordinates are on the curve");
+        let p = AffinePoint::<Bls12377>::nonzero(x, y);
+        assert!(!p.is_in_subgroup());
+        let mut encoded = vec![];
+        p.write(&mut encoded)?;
+        assert!(AffinePoint::<Bls12377>::read(&encoded[..]).is_err());
+
+        let g = Bls12377::GENERATOR_AFFINE;
+        assert!(g.is_in_subgroup());
+        assert_eq!(read_all::<AffinePoint<Bls12377>>(&to_vec(&g))?, g);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_canonical_proof_key_and_witness_rejected() -> Result<()> {
+        type F = <Tweedledee as Curve>::ScalarField;
+        let modulus = field_to_biguint(F::NEG_ONE) + 1u32;
+        let modulus_hex = hex::encode(encode_unreduced(&modulus, F::BYTES));
+
+        // The proof ends with the Schnorr proof's z2, a scalar.
+        let (proof, vk) = get_circuit_vk::<Tweedledee, Tweedledum>();
+        let mut bytes = proof.to_bytes();
+        let len = bytes.len();
+        bytes[len - F::BYTES..].copy_from_slice(&encode_unreduced(&modulus, F::BYTES));
+        assert!(Proof::<Tweedledee>::from_bytes(&bytes).is_err());
+        let json = proof.to_json();
+        let z2_hex = proof.schnorr_proof.z2.to_hex();
+        assert!(Proof::<Tweedledee>::from_json(&json.replace(&z2_hex, &modulus_hex)).is_err());
+
+        // Replace a commitment of the key with a point which is off the curve.
+        let x_off_curve = (0..)
+            .map(|_| TweedledeeBase::rand())
+            .find(|&x| (x.cube() + Tweedledee::B).square_root().is_none())
+            .expect("Half of all x coordinates are off the curve");
+        let off_curve_hex = format!("00{}", x_off_curve.to_hex());
+        let vk_json = vk.to_json().replace(&vk.c_constants[0].to_hex(), &off_curve_hex);
+        assert!(VerificationKey::<Tweedledee>::from_json(&vk_json).is_err());
+
+        let (_circuit, witness) = get_circuit_witness::<Tweedledee>();
+        let witness_json = serde_json::to_string(&witness)?;
+        let value_hex = witness.get_indices(0, 0).to_hex();
+        let tampered = witness_json.replace(&value_hex, &modulus_hex);
+        assert!(serde_json::from_str::<Witness<F>>(&tampered).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_point_at_infinity_in_vec() -> Result<()> {
+        let p = blake_hash_base_field_to_curve::<Tweedledum>(TweedledumBase::rand());
+        let points = vec![AffinePoint::<Tweedledum>::ZERO, p];
+        let mut buf = vec![];
+        write_vec(&points, &mut buf)?;
+        assert_eq!(read_all::<PointVec>(&buf)?.0, points);
+        Ok(())
+    }
+
+    struct PointVec(Vec<AffinePoint<Tweedledum>>);
+
+    impl FromBytes for PointVec {
+        fn read<R: Read>(reader: R) -> std::io::Result<Self> {
+            Ok(PointVec(read_vec(reader)?))
+        }
+    }
+
+    // Build the factorial circuit, along with the inputs for its witness.
+    fn get_circuit_inputs<C: HaloCurve>() -> (Circuit<C>, PartialWitness<C::ScalarField>) {
         let mut builder = CircuitBuilder::<C>::new(128);
         let n = 10;
         let factorial_usize = (1..=n).product();
@@ -267,15 +936,184 @@ mod test {
             partial_witness
                 .set_target(factors_pis[i], C::ScalarField::from_canonical_usize(i + 1));
         });
-        let circuit = builder.build();
-        let witness = circuit.generate_witness(partial_witness);
+        (builder.build(), partial_witness)
+    }
+
+    // Build the factorial circuit, along with a witness for it.
+    fn get_circuit_witness<C: HaloCurve>() -> (Circuit<C>, Witness<C::ScalarField>) {
+        let (circuit, partial_witness) = get_circuit_inputs::<C>();
+        let witness = circuit.generate_witness(partial_witness).unwrap();
+        (circuit, witness)
+    }
+
+    // Generate a proof and verification key for the factorial circuit.
+    fn get_circuit_vk<C: HaloCurve, InnerC: HaloCurve<BaseField=C::ScalarField>>() -> (Proof<C>, VerificationKey<C>) {
+        let (circuit, witness) = get_circuit_witness::<C>();
         let proof = circuit
-            .generate_proof::<InnerC>(&witness, &[], true)
+            .generate_proof::<InnerC>(&witness, &[])
             .unwrap();
         let vk = circuit.to_vk();
         (proof, vk)
     }
 
+    #[test]
+    fn test_prover_key_serialization() -> Result<()> {
+        let (circuit, witness) = get_circuit_witness::<Tweedledee>();
+        let ser_pk = serde_cbor::to_vec(&circuit.prover_key)?;
+        let der_pk: ProverKey<Tweedledee> = serde_cbor::from_slice(&ser_pk)?;
+        assert!(der_pk == circuit.prover_key);
+        // The FFT precomputations are shared with the cache, rather than copied into the key.
+        assert!(std::sync::Arc::ptr_eq(&der_pk.fft_precomputation_8n, &circuit.fft_precomputation_8n));
+
+        // A proof generated from the deserialized key should verify against the original circuit.
+        let proof = der_pk.generate_proof::<Tweedledum>(&witness, &[])?;
+        let public_inputs = circuit.get_public_inputs(&witness);
+        verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_witness_serialization() -> Result<()> {
+        let (circuit, inputs) = get_circuit_inputs::<Tweedledee>();
+        let ser_inputs = serde_cbor::to_vec(&inputs)?;
+        let der_inputs: PartialWitness<<Tweedledee as Curve>::ScalarField> =
+            serde_cbor::from_slice(&ser_inputs)?;
+        assert_eq!(der_inputs, inputs);
+
+        // Replaying witness generation from the deserialized inputs gives the same witness, up to
+        // the random values of blinding gates.
+        let witness = circuit.generate_witness(inputs)?;
+        let replayed_witness = circuit.generate_witness(der_inputs)?;
+        assert_eq!(
+            circuit.get_public_inputs(&replayed_witness),
+            circuit.get_public_inputs(&witness)
+        );
+
+        let ser_witness = serde_cbor::to_vec(&witness)?;
+        let der_witness: Witness<<Tweedledee as Curve>::ScalarField> =
+            serde_cbor::from_slice(&ser_witness)?;
+        assert_eq!(der_witness, witness);
+
+        let proof = circuit.generate_proof::<Tweedledum>(&der_witness, &[])?;
+        let public_inputs = circuit.get_public_inputs(&witness);
+        verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_bytes_rejects_malformed() {
+        let (proof, _vk) = get_circuit_vk::<Tweedledee, Tweedledum>();
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes[0], PROOF_FORMAT_VERSION);
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] += 1;
+        assert!(Proof::<Tweedledee>::from_bytes(&wrong_version).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(Proof::<Tweedledee>::from_bytes(&trailing).is_err());
+        assert!(Proof::<Tweedledee>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_json() -> Result<()> {
+        let (circuit, witness) = get_circuit_witness::<Tweedledee>();
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        let vk = circuit.to_vk();
+        let public_inputs = circuit.get_public_inputs(&witness);
+
+        let der_proof = Proof::<Tweedledee>::from_json(&proof.to_json())?;
+        let der_vk = VerificationKey::<Tweedledee>::from_json(&vk.to_json())?;
+        let der_public_inputs = public_inputs_from_json(&public_inputs_to_json(&public_inputs))?;
+        assert_eq!(der_proof, proof);
+        assert_eq!(der_vk, vk);
+        assert_eq!(der_public_inputs, public_inputs);
+        verify_proof::<Tweedledee, Tweedledum>(&der_public_inputs, &der_proof, &[], &der_vk, true)?;
+
+        // Field elements are hex strings of their little-endian encodings.
+        type F = <Tweedledee as Curve>::ScalarField;
+        let expected = format!("\"02{}\"", "00".repeat(F::BYTES - 1));
+        assert_eq!(serde_json::to_string(&F::TWO)?, expected);
+        assert!(public_inputs_from_json::<F>("[\"0g\"]").is_err());
+        assert!(public_inputs_from_json::<F>("[\"020\"]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_encodings() -> Result<()> {
+        let (circuit, witness) = get_circuit_witness::<Tweedledee>();
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        let vk = circuit.to_vk();
+
+        assert_eq!(proof.to_hex(), hex::encode(proof.to_bytes()));
+        assert_eq!(Proof::<Tweedledee>::from_hex(&proof.to_hex())?, proof);
+        assert_eq!(Proof::<Tweedledee>::from_base64(&proof.to_base64())?, proof);
+        assert_eq!(VerificationKey::<Tweedledee>::from_hex(&vk.to_hex())?, vk);
+        assert_eq!(VerificationKey::<Tweedledee>::from_base64(&vk.to_base64())?, vk);
+        let pk = &circuit.prover_key;
+        assert_eq!(&ProverKey::<Tweedledee>::from_base64(&pk.to_base64())?, pk);
+        let commitment = proof.c_wires[0];
+        assert_eq!(AffinePoint::<Tweedledee>::from_base64(&commitment.to_base64())?, commitment);
+
+        // Trailing bytes are rejected, as with `from_bytes`.
+        assert!(Proof::<Tweedledee>::from_hex(&format!("{}00", proof.to_hex())).is_err());
+        assert!(VerificationKey::<Tweedledee>::from_hex(&format!("{}00", vk.to_hex())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_base64() {
+        // Test vectors from RFC 4648, without padding.
+        let vectors = [("", ""), ("f", "Zg"), ("fo", "Zm8"), ("foo", "Zm9v"), ("foob", "Zm9vYg"), ("fooba", "Zm9vYmE"), ("foobar", "Zm9vYmFy")];
+        for &(bytes, encoded) in vectors.iter() {
+            assert_eq!(base64_encode(bytes.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), bytes.as_bytes());
+        }
+        assert_eq!(base64_encode(&[0xFB, 0xFF]), "-_8");
+        assert_eq!(base64_decode("-_8").unwrap(), vec![0xFB, 0xFF]);
+
+        assert!(base64_decode("Zm9vY").is_err());
+        assert!(base64_decode("Zg==").is_err());
+        assert!(base64_decode("+/8").is_err());
+        // "Zh" has nonzero unused bits, so it's a non-canonical encoding of "f".
+        assert!(base64_decode("Zh").is_err());
+    }
+
+    #[test]
+    fn test_format_mismatches_rejected() -> Result<()> {
+        let (proof, vk) = get_circuit_vk::<Tweedledee, Tweedledum>();
+        assert_eq!(proof.parameters.curve, "Tweedledee");
+        assert_eq!(proof.parameters.transcript_hash, "Rescue");
+
+        // A proof or key over another curve is rejected when deserialized.
+        let err = Proof::<Tweedledum>::from_bytes(&proof.to_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "Expected a proof over Tweedledum, got one over Tweedledee");
+        let err = serde_cbor::from_slice::<VerificationKey<Tweedledum>>(&serde_cbor::to_vec(&vk)?)
+            .unwrap_err();
+        assert!(err.to_string().contains("Expected a key over Tweedledum, got one over Tweedledee"));
+
+        // So is a key with another format version.
+        let old_format = serde_cbor::to_vec(&RawKeyFormat {
+            version: KEY_FORMAT_VERSION - 1,
+            curve: "Tweedledee".to_string(),
+        })?;
+        let err = serde_cbor::from_slice::<KeyFormat<Tweedledee>>(&old_format).unwrap_err();
+        assert!(err.to_string().contains("Unsupported key format version"));
+
+        // A proof for another security level fails verification before any other check.
+        let mut proof = proof;
+        proof.parameters.security_bits = 100;
+        let public_inputs = (1..=10).map(<Tweedledee as Curve>::ScalarField::from_canonical_usize).collect::<Vec<_>>();
+        assert_eq!(
+            verify::<Tweedledee, Tweedledum>(&vk, &public_inputs, &proof),
+            Err(VerificationError::IncompatibleProof(
+                "Expected a proof with security level 128, got 100".to_string()
+            ))
+        );
+        Ok(())
+    }
 
     macro_rules! test_proof_vk_serialization {
         ($curve:ty, $inner_curve:ty, $test_name:ident) => {
@@ -306,6 +1144,14 @@ mod test {
                 println!("Vk size without any precomputation: {} bytes", ser_vk_none.len());
 
                 let der_proof = serde_cbor::from_slice(&ser_proof)?;
+                let bytes_proof = proof.to_bytes();
+                assert!(
+                    bytes_proof.len() <= ser_proof.len(),
+                    "The compact encoding ({} bytes) should be no larger than CBOR ({} bytes)",
+                    bytes_proof.len(),
+                    ser_proof.len()
+                );
+                assert_eq!(proof, Proof::<$curve>::from_bytes(&bytes_proof)?);
                 let der_vk: VerificationKey<$curve> = serde_cbor::from_slice(&ser_vk)?;
                 let der_vk_no_fft: VerificationKey<$curve> = serde_cbor::from_slice(&ser_vk_no_fft)?;
                 let der_vk_no_msm: VerificationKey<$curve> = serde_cbor::from_slice(&ser_vk_no_msm)?;
@@ -0,0 +1,215 @@
+//! Helpers for crates which embed plonky to check that their serialization paths round-trip, and
+//! stay compatible with the encodings of earlier versions. Golden vectors are the encodings of a
+//! fixed proof and verification key, which are generated deterministically, so they only change
+//! when the proof system or the encodings do:
+//!
+//! ```ignore
+//! let golden = golden_vectors::<Tweedledee, Tweedledum>()?;
+//! assert_matches_golden(Path::new("tests/fixtures/tweedledee"), &golden);
+//! ```
+//!
+//! Fixture files which don't exist yet are written, and should then be committed. To accept
+//! intentional changes, rerun with the `PLONKY_BLESS` environment variable set.
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{public_inputs_to_json, CircuitBuilder, Field, HaloCurve, PartialWitness, Proof, TextEncoding, VerificationKey};
+
+/// The environment variable which makes `assert_matches_golden` overwrite fixtures which differ.
+pub const BLESS_ENV_VAR: &str = "PLONKY_BLESS";
+
+/// The seed of the prover's randomness in `golden_vectors`.
+const GOLDEN_SEED: u64 = 0x706c6f6e6b79;
+
+/// Asserts that `value` survives a round trip through each serde format we use, i.e. JSON and
+/// CBOR, and that re-encoding the decoded value gives identical output.
+pub fn assert_roundtrip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+    let json = serde_json::to_string(value).expect("JSON serialization failed");
+    let from_json: T = serde_json::from_str(&json).expect("JSON deserialization failed");
+    assert_eq!(&from_json, value, "JSON round trip changed the value");
+    assert_eq!(
+        serde_json::to_string(&from_json).expect("JSON serialization failed"),
+        json,
+        "JSON encoding is not stable"
+    );
+
+    let cbor = serde_cbor::to_vec(value).expect("CBOR serialization failed");
+    let from_cbor: T = serde_cbor::from_slice(&cbor).expect("CBOR deserialization failed");
+    assert_eq!(&from_cbor, value, "CBOR round trip changed the value");
+    assert_eq!(
+        serde_cbor::to_vec(&from_cbor).expect("CBOR serialization failed"),
+        cbor,
+        "CBOR encoding is not stable"
+    );
+}
+
+/// Asserts that `value` survives a round trip through our binary encoding, in hex and base64 too,
+/// and that truncated encodings are rejected rather than decoded.
+pub fn assert_bytes_roundtrip<T: TextEncoding + PartialEq + Debug>(value: &T) {
+    let hex = value.to_hex();
+    assert_eq!(&T::from_hex(&hex).expect("Hex decoding failed"), value, "Hex round trip changed the value");
+    let base64 = value.to_base64();
+    assert_eq!(
+        &T::from_base64(&base64).expect("Base64 decoding failed"),
+        value,
+        "Base64 round trip changed the value"
+    );
+    if hex.len() >= 2 {
+        assert!(
+            T::from_hex(&hex[..hex.len() - 2]).is_err(),
+            "A truncated encoding was accepted"
+        );
+    }
+}
+
+/// The encodings of a fixed statement, see `golden_vectors`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GoldenVectors {
+    /// `Proof::to_hex`.
+    pub proof_hex: String,
+    /// `Proof::to_json`.
+    pub proof_json: String,
+    /// `VerificationKey::to_hex`, without precomputations.
+    pub vk_hex: String,
+    /// `VerificationKey::to_json`, without precomputations.
+    pub vk_json: String,
+    /// `public_inputs_to_json`.
+    pub public_inputs_json: String,
+}
+
+impl GoldenVectors {
+    /// The fixture file names, along with their contents.
+    pub fn files(&self) -> [(&'static str, &str); 5] {
+        [
+            ("proof.hex", &self.proof_hex),
+            ("proof.json", &self.proof_json),
+            ("vk.hex", &self.vk_hex),
+            ("vk.json", &self.vk_json),
+            ("public_inputs.json", &self.public_inputs_json),
+        ]
+    }
+}
+
+/// Encodes a proof that 5 * 7 = 35, with 35 as the public input, along with its verification key.
+/// The circuit isn't zero-knowledge and the prover's randomness is seeded, so the result only
+/// depends on the curves and the version of plonky.
+pub fn golden_vectors<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>() -> Result<GoldenVectors>
+where
+    Proof<C>: Serialize + DeserializeOwned,
+    VerificationKey<C>: Serialize + DeserializeOwned,
+{
+    let mut builder = CircuitBuilder::<C>::new(128);
+    builder.set_zero_knowledge(false);
+    let pi = builder.add_public_input();
+    let x = builder.add_virtual_target();
+    let y = builder.add_virtual_target();
+    let product = builder.mul(x, y);
+    builder.copy(product, pi);
+    let circuit = builder.build();
+
+    let mut inputs = PartialWitness::new();
+    inputs.set_target(x, C::ScalarField::from_canonical_usize(5));
+    inputs.set_target(y, C::ScalarField::from_canonical_usize(7));
+    let witness = circuit.generate_witness(inputs)?;
+    let proof = circuit.generate_proof_deterministic::<InnerC>(&witness, &[], GOLDEN_SEED)?;
+    let vk = VerificationKey {
+        fft_precomputation: None,
+        pedersen_g_msm_precomputation: None,
+        ..circuit.to_vk()
+    };
+
+    Ok(GoldenVectors {
+        proof_hex: proof.to_hex(),
+        proof_json: proof.to_json(),
+        vk_hex: vk.to_hex(),
+        vk_json: vk.to_json(),
+        public_inputs_json: public_inputs_to_json(&circuit.get_public_inputs(&witness)),
+    })
+}
+
+/// Asserts that `golden` matches the fixture files in `dir`. Missing fixtures are written, as are
+/// differing ones if the `PLONKY_BLESS` environment variable is set.
+pub fn assert_matches_golden(dir: &Path, golden: &GoldenVectors) {
+    let bless = std::env::var_os(BLESS_ENV_VAR).is_some();
+    fs::create_dir_all(dir).expect("Failed to create the fixture directory");
+    for &(name, contents) in golden.files().iter() {
+        let path = dir.join(name);
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected.trim_end() == contents.trim_end() => {}
+            Ok(_) if !bless => panic!(
+                "{} no longer matches; if the change is intentional, rerun with {} set",
+                path.display(),
+                BLESS_ENV_VAR
+            ),
+            _ => fs::write(&path, contents).expect("Failed to write a fixture"),
+        }
+    }
+}
+
+/// Asserts that the encodings in `golden` decode, and that the binary and JSON encodings agree.
+/// Unlike `assert_matches_golden`, this also applies to fixtures recorded with older versions.
+pub fn assert_golden_decodes<C: HaloCurve>(golden: &GoldenVectors)
+where
+    Proof<C>: Serialize + DeserializeOwned,
+    VerificationKey<C>: Serialize + DeserializeOwned,
+{
+    // Compare re-encodings, since `Proof<C>: PartialEq` would need `C: PartialEq`.
+    let proof = Proof::<C>::from_hex(&golden.proof_hex).expect("Failed to decode the proof");
+    let proof_from_json = Proof::<C>::from_json(&golden.proof_json).expect("Failed to decode the proof");
+    assert_eq!(proof.to_hex(), proof_from_json.to_hex());
+
+    let vk = VerificationKey::<C>::from_hex(&golden.vk_hex).expect("Failed to decode the key");
+    let vk_from_json = VerificationKey::<C>::from_json(&golden.vk_json).expect("Failed to decode the key");
+    assert_eq!(vk.to_hex(), vk_from_json.to_hex());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use anyhow::Result;
+
+    use crate::serialization_testing::{assert_bytes_roundtrip, assert_golden_decodes, assert_matches_golden, assert_roundtrip, golden_vectors};
+    use crate::{public_inputs_from_json, verify_proof, AffinePoint, Curve, Field, Pallas, Proof, Tweedledee, Tweedledum, VerificationKey, Vesta};
+
+    #[test]
+    fn test_roundtrip_helpers() {
+        type F = <Tweedledee as Curve>::ScalarField;
+        let x = F::rand();
+        assert_roundtrip(&x);
+        assert_bytes_roundtrip(&x);
+        assert_roundtrip(&vec![F::ZERO, F::NEG_ONE]);
+        assert_roundtrip(&Tweedledee::GENERATOR_AFFINE);
+        assert_bytes_roundtrip(&Tweedledee::GENERATOR_AFFINE);
+        assert_bytes_roundtrip(&AffinePoint::<Tweedledee>::ZERO);
+    }
+
+    #[test]
+    fn test_golden_vectors_verify() -> Result<()> {
+        let golden = golden_vectors::<Tweedledee, Tweedledum>()?;
+        assert_eq!(golden, golden_vectors::<Tweedledee, Tweedledum>()?, "Golden vectors are not deterministic");
+        assert_golden_decodes::<Tweedledee>(&golden);
+
+        let proof = Proof::<Tweedledee>::from_json(&golden.proof_json)?;
+        let vk = VerificationKey::<Tweedledee>::from_json(&golden.vk_json)?;
+        let public_inputs = public_inputs_from_json(&golden.public_inputs_json)?;
+        assert_roundtrip(&proof);
+        assert_roundtrip(&vk);
+        verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &vk, true)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_golden_fixtures() -> Result<()> {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        assert_matches_golden(&fixtures.join("tweedledee"), &golden_vectors::<Tweedledee, Tweedledum>()?);
+        assert_matches_golden(&fixtures.join("pallas"), &golden_vectors::<Pallas, Vesta>()?);
+        Ok(())
+    }
+}
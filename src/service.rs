@@ -0,0 +1,745 @@
+//! A proving service, which queues proving jobs for a set of circuits and runs them on a pool of
+//! worker threads, along with a small HTTP interface to it.
+//!
+//! Submitting a job returns its ID immediately. Clients then poll the job's status, which reports
+//! its position in the queue while it waits, how long it has been running and which proving phase
+//! it's in, and finally its proof or the reason it failed.
+//!
+//! The HTTP interface serves JSON over HTTP/1.1, with one request per connection:
+//!
+//! - `POST /circuits/<name>/jobs`, with a JSON `Witness`, submits a job and returns `{"job": id}`.
+//! - `GET /jobs/<id>` returns the status of a job, and `DELETE /jobs/<id>` forgets a job.
+//! - `GET /circuits/<name>/verification_key` returns the circuit's verification key.
+//! - `POST /circuits/<name>/verify`, with `{"public_inputs": [...], "proof": {...}}`, returns
+//!   `{"valid": bool}`, along with an `error` if the proof is invalid.
+//!
+//! The service deliberately runs on std threads rather than an async runtime, so that it adds no
+//! dependencies. Each connection is handled on its own thread, so `ServiceLimits` bounds the
+//! number of open connections, the size of requests and how long a client may take to send one,
+//! as well as the number of queued jobs and how long finished jobs are kept. Requests beyond the
+//! connection or queue limits are rejected with `503 Service Unavailable`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::marker::PhantomData;
+use std::net::{TcpListener, TcpStream};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{verify, HaloCurve, Proof, ProverConfig, ProverKey, ProverObserver, ProverPhase, VerificationKey, Witness};
+
+/// The longest request line or header line the HTTP interface accepts, including its line break.
+const MAX_HEADER_LINE_LEN: usize = 8 << 10;
+
+/// The most headers the HTTP interface accepts in a request.
+const MAX_HEADERS: usize = 64;
+
+pub type JobId = u64;
+
+/// Bounds on the resources a proving service and its HTTP interface use, so that clients can't
+/// exhaust the server's memory or threads.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServiceLimits {
+    /// The most jobs which may wait for a worker. Submitting a job beyond this fails with
+    /// `SubmitError::QueueFull`.
+    pub max_queued_jobs: usize,
+    /// The most finished jobs to keep. Beyond this, the oldest finished jobs are forgotten.
+    pub max_finished_jobs: usize,
+    /// How long a finished job is kept before it is forgotten, unless it is removed earlier.
+    pub finished_job_ttl: Duration,
+    /// The most connections the HTTP interface handles at once. Further connections are rejected.
+    pub max_connections: usize,
+    /// The largest request body the HTTP interface accepts, which must fit the JSON witnesses of
+    /// the service's circuits.
+    pub max_request_body_len: usize,
+    /// How long the HTTP interface waits for each read from, or write to, a client.
+    pub io_timeout: Duration,
+}
+
+impl Default for ServiceLimits {
+    fn default() -> Self {
+        ServiceLimits {
+            max_queued_jobs: 1024,
+            max_finished_jobs: 1024,
+            finished_job_ttl: Duration::from_secs(60 * 60),
+            max_connections: 64,
+            max_request_body_len: 8 << 20,
+            io_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The reason a job could not be submitted.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubmitError {
+    UnknownCircuit(String),
+    /// `ServiceLimits::max_queued_jobs` jobs are already waiting for a worker.
+    QueueFull,
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::UnknownCircuit(circuit) => write!(f, "Unknown circuit {}", circuit),
+            SubmitError::QueueFull => write!(f, "The job queue is full"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum JobStatus<C: HaloCurve> {
+    /// The job is waiting for a worker, behind `position` other jobs.
+    Queued { position: usize },
+    /// The job is being proven. `phase` is the proving phase it's in, if it has started one.
+    Running { elapsed: Duration, phase: Option<ProverPhase> },
+    Succeeded { proof: Box<Proof<C>>, proving_time: Duration },
+    Failed { error: String },
+}
+
+impl<C: HaloCurve> JobStatus<C> {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Succeeded { .. } | JobStatus::Failed { .. })
+    }
+}
+
+enum Job<C: HaloCurve> {
+    Queued { circuit: String, witness: Witness<C::ScalarField> },
+    Running { start: Instant, phase: Option<ProverPhase> },
+    Succeeded { proof: Box<Proof<C>>, proving_time: Duration },
+    Failed { error: String },
+}
+
+struct Jobs<C: HaloCurve> {
+    jobs: HashMap<JobId, Job<C>>,
+    queue: VecDeque<JobId>,
+    /// The finished jobs which haven't been removed, in the order they finished.
+    finished: VecDeque<(JobId, Instant)>,
+    next_id: JobId,
+    shutting_down: bool,
+}
+
+impl<C: HaloCurve> Jobs<C> {
+    /// Forgets the finished jobs which are beyond the limits on their number or age.
+    fn evict_finished(&mut self, limits: &ServiceLimits) {
+        while let Some(&(id, finished)) = self.finished.front() {
+            if self.finished.len() <= limits.max_finished_jobs && finished.elapsed() < limits.finished_job_ttl {
+                break;
+            }
+            self.finished.pop_front();
+            self.jobs.remove(&id);
+        }
+    }
+}
+
+struct Shared<C: HaloCurve> {
+    circuits: HashMap<String, (ProverKey<C>, VerificationKey<C>)>,
+    limits: ServiceLimits,
+    jobs: Mutex<Jobs<C>>,
+    /// Notified whenever a job is queued or finishes.
+    changed: Condvar,
+}
+
+impl<C: HaloCurve> Shared<C> {
+    fn lock_jobs(&self) -> MutexGuard<'_, Jobs<C>> {
+        self.jobs.lock().expect("A worker panicked while holding the lock")
+    }
+}
+
+/// Proves with a fixed set of named circuits, on a pool of worker threads. Proofs are generated
+/// with `ProverKey::generate_proof`, without old proofs. Dropping the service waits for running
+/// jobs to finish, and abandons queued ones.
+///
+/// Finished jobs are kept until they are removed, or until they exceed the service's limits.
+pub struct ProvingService<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> {
+    shared: Arc<Shared<C>>,
+    workers: Vec<JoinHandle<()>>,
+    _inner_curve: PhantomData<fn() -> InnerC>,
+}
+
+impl<C: HaloCurve + Send, InnerC: HaloCurve<BaseField = C::ScalarField>> ProvingService<C, InnerC> {
+    pub fn new(circuits: HashMap<String, ProverKey<C>>, num_workers: usize) -> Self {
+        Self::with_limits(circuits, num_workers, ServiceLimits::default())
+    }
+
+    pub fn with_limits(circuits: HashMap<String, ProverKey<C>>, num_workers: usize, limits: ServiceLimits) -> Self {
+        assert!(num_workers > 0, "A proving service needs at least one worker");
+        let circuits = circuits
+            .into_iter()
+            .map(|(name, prover_key)| {
+                let vk = prover_key.to_vk();
+                (name, (prover_key, vk))
+            })
+            .collect();
+        let shared = Arc::new(Shared {
+            circuits,
+            limits,
+            jobs: Mutex::new(Jobs {
+                jobs: HashMap::new(),
+                queue: VecDeque::new(),
+                finished: VecDeque::new(),
+                next_id: 0,
+                shutting_down: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let workers = (0..num_workers)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || run_worker::<C, InnerC>(&shared))
+            })
+            .collect();
+        ProvingService {
+            shared,
+            workers,
+            _inner_curve: PhantomData,
+        }
+    }
+
+    pub fn limits(&self) -> &ServiceLimits {
+        &self.shared.limits
+    }
+
+    pub fn verification_key(&self, circuit: &str) -> Option<&VerificationKey<C>> {
+        self.shared.circuits.get(circuit).map(|(_, vk)| vk)
+    }
+
+    /// Queues a job to prove the given circuit with the given witness.
+    pub fn submit(&self, circuit: &str, witness: Witness<C::ScalarField>) -> Result<JobId, SubmitError> {
+        if !self.shared.circuits.contains_key(circuit) {
+            return Err(SubmitError::UnknownCircuit(circuit.to_string()));
+        }
+        let mut jobs = self.shared.lock_jobs();
+        jobs.evict_finished(&self.shared.limits);
+        if jobs.queue.len() >= self.shared.limits.max_queued_jobs {
+            return Err(SubmitError::QueueFull);
+        }
+        let id = jobs.next_id;
+        jobs.next_id += 1;
+        let circuit = circuit.to_string();
+        jobs.jobs.insert(id, Job::Queued { circuit, witness });
+        jobs.queue.push_back(id);
+        self.shared.changed.notify_all();
+        Ok(id)
+    }
+
+    /// The status of a job, or `None` if there is no such job.
+    pub fn status(&self, id: JobId) -> Option<JobStatus<C>> {
+        let mut jobs = self.shared.lock_jobs();
+        jobs.evict_finished(&self.shared.limits);
+        job_status(&jobs, id)
+    }
+
+    /// Blocks until a job finishes, then returns its status.
+    pub fn wait(&self, id: JobId) -> Option<JobStatus<C>> {
+        let mut jobs = self.shared.lock_jobs();
+        loop {
+            match job_status(&jobs, id) {
+                Some(status) if !status.is_finished() => {
+                    jobs = self.shared.changed.wait(jobs).expect("A worker panicked while holding the lock");
+                }
+                status => return status,
+            }
+        }
+    }
+
+    /// Forgets a job, along with its proof. A queued job is cancelled, whereas a running job
+    /// continues, but its result is discarded. Returns whether there was such a job.
+    pub fn remove(&self, id: JobId) -> bool {
+        let mut jobs = self.shared.lock_jobs();
+        jobs.queue.retain(|&queued_id| queued_id != id);
+        jobs.finished.retain(|&(finished_id, _)| finished_id != id);
+        jobs.jobs.remove(&id).is_some()
+    }
+
+    pub fn verify(&self, circuit: &str, public_inputs: &[C::ScalarField], proof: &Proof<C>) -> Result<()> {
+        let vk = self
+            .verification_key(circuit)
+            .ok_or_else(|| anyhow!("Unknown circuit {}", circuit))?;
+        verify::<C, InnerC>(vk, public_inputs, proof).map_err(|e| anyhow!("{}", e))
+    }
+}
+
+impl<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>> Drop for ProvingService<C, InnerC> {
+    fn drop(&mut self) {
+        self.shared.lock_jobs().shutting_down = true;
+        self.shared.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            // A worker only panics if the lock was poisoned, and we're shutting down regardless.
+            let _ = worker.join();
+        }
+    }
+}
+
+fn job_status<C: HaloCurve>(jobs: &Jobs<C>, id: JobId) -> Option<JobStatus<C>> {
+    Some(match jobs.jobs.get(&id)? {
+        Job::Queued { .. } => JobStatus::Queued {
+            position: jobs.queue.iter().position(|&queued_id| queued_id == id).expect("Queued jobs are in the queue"),
+        },
+        Job::Running { start, phase } => JobStatus::Running {
+            elapsed: start.elapsed(),
+            phase: *phase,
+        },
+        Job::Succeeded { proof, proving_time } => JobStatus::Succeeded {
+            proof: proof.clone(),
+            proving_time: *proving_time,
+        },
+        Job::Failed { error } => JobStatus::Failed { error: error.clone() },
+    })
+}
+
+fn run_worker<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(shared: &Shared<C>) {
+    loop {
+        let (id, circuit, witness, start) = {
+            let mut jobs = shared.lock_jobs();
+            let id = loop {
+                if jobs.shutting_down {
+                    return;
+                }
+                match jobs.queue.pop_front() {
+                    Some(id) => break id,
+                    None => jobs = shared.changed.wait(jobs).expect("A worker panicked while holding the lock"),
+                }
+            };
+            let start = Instant::now();
+            match jobs.jobs.insert(id, Job::Running { start, phase: None }) {
+                Some(Job::Queued { circuit, witness }) => (id, circuit, witness, start),
+                _ => unreachable!("Only queued jobs are in the queue"),
+            }
+        };
+
+        let (prover_key, _) = &shared.circuits[&circuit];
+        let observer = JobObserver { shared, id };
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            prover_key.generate_proof_with_observer::<InnerC>(&witness, &[], &ProverConfig::default(), &observer)
+        }));
+        let job = match result {
+            Ok(Ok(proof)) => Job::Succeeded {
+                proof: Box::new(proof),
+                proving_time: start.elapsed(),
+            },
+            Ok(Err(e)) => Job::Failed { error: e.to_string() },
+            Err(_) => Job::Failed {
+                error: "The prover panicked".to_string(),
+            },
+        };
+
+        let mut jobs = shared.lock_jobs();
+        // The job may have been removed while it was running.
+        if let Some(entry) = jobs.jobs.get_mut(&id) {
+            *entry = job;
+            jobs.finished.push_back((id, Instant::now()));
+            jobs.evict_finished(&shared.limits);
+        }
+        shared.changed.notify_all();
+    }
+}
+
+/// Records the phase of a running job in its status.
+struct JobObserver<'a, C: HaloCurve> {
+    shared: &'a Shared<C>,
+    id: JobId,
+}
+
+impl<'a, C: HaloCurve> ProverObserver for JobObserver<'a, C> {
+    fn phase_started(&self, phase: ProverPhase) {
+        if let Some(Job::Running { phase: job_phase, .. }) = self.shared.lock_jobs().jobs.get_mut(&self.id) {
+            *job_phase = Some(phase);
+        }
+    }
+}
+
+/// Serves the HTTP interface to a proving service, handling each connection on a new thread, up to
+/// the service's `max_connections`. Returns only if accepting a connection fails.
+pub fn serve<C, InnerC>(service: Arc<ProvingService<C, InnerC>>, listener: TcpListener) -> Result<()>
+where
+    C: HaloCurve + Send,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    Proof<C>: Serialize + DeserializeOwned,
+    VerificationKey<C>: Serialize,
+    Witness<C::ScalarField>: DeserializeOwned,
+    C::ScalarField: DeserializeOwned,
+{
+    let num_connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let limits = service.limits();
+        stream.set_read_timeout(Some(limits.io_timeout))?;
+        stream.set_write_timeout(Some(limits.io_timeout))?;
+        if num_connections.fetch_add(1, Ordering::SeqCst) >= limits.max_connections {
+            num_connections.fetch_sub(1, Ordering::SeqCst);
+            let body = json!({ "error": "Too many connections" });
+            if let Err(e) = write_response(&mut stream, 503, &body) {
+                warn!("Failed to reject a proving service connection: {}", e);
+            }
+            continue;
+        }
+
+        let service = service.clone();
+        let connection = Connection(num_connections.clone());
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(&service, stream) {
+                warn!("Failed to handle a proving service request: {}", e);
+            }
+            drop(connection);
+        });
+    }
+    Ok(())
+}
+
+/// Counts an open connection, until it is dropped.
+struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+fn handle_connection<C, InnerC>(service: &ProvingService<C, InnerC>, mut stream: TcpStream) -> Result<()>
+where
+    C: HaloCurve + Send,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    Proof<C>: Serialize + DeserializeOwned,
+    VerificationKey<C>: Serialize,
+    Witness<C::ScalarField>: DeserializeOwned,
+    C::ScalarField: DeserializeOwned,
+{
+    let (status, body) = match read_request(&mut stream, service.limits().max_request_body_len) {
+        Ok(request) => route(service, &request),
+        Err(e) => (400, json!({ "error": e.to_string() })),
+    };
+    write_response(&mut stream, status, &body)
+}
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        503 => "Service Unavailable",
+        _ => "Not Found",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+fn read_request(stream: &mut TcpStream, max_body_len: usize) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let request_line = read_header_line(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => bail!("Malformed request line"),
+    };
+
+    let mut content_length = 0;
+    let mut num_headers = 0;
+    loop {
+        let header = read_header_line(&mut reader)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        num_headers += 1;
+        ensure!(num_headers <= MAX_HEADERS, "Too many headers");
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+        }
+    }
+    ensure!(content_length <= max_body_len, "The request body is too large");
+    // The body is read as it arrives, rather than into a buffer of the claimed length.
+    let mut body = Vec::new();
+    reader.take(content_length as u64).read_to_end(&mut body)?;
+    ensure!(body.len() == content_length, "The request body is shorter than its Content-Length");
+    Ok(Request { method, path, body })
+}
+
+/// Reads a line of the request head, which must end with a line break within
+/// `MAX_HEADER_LINE_LEN` bytes.
+fn read_header_line<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_HEADER_LINE_LEN as u64).read_line(&mut line)?;
+    ensure!(line.ends_with('\n'), "A header line is too long, or the request ended early");
+    Ok(line)
+}
+
+fn route<C, InnerC>(service: &ProvingService<C, InnerC>, request: &Request) -> (u16, Value)
+where
+    C: HaloCurve + Send,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    Proof<C>: Serialize + DeserializeOwned,
+    VerificationKey<C>: Serialize,
+    Witness<C::ScalarField>: DeserializeOwned,
+    C::ScalarField: DeserializeOwned,
+{
+    let not_found = (404, json!({ "error": "Not found" }));
+    let bad_request = |e: anyhow::Error| (400, json!({ "error": e.to_string() }));
+    let segments = request.path.trim_matches('/').split('/').collect::<Vec<_>>();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["circuits", circuit, "jobs"]) => {
+            match serde_json::from_slice(&request.body) {
+                Ok(witness) => match service.submit(circuit, witness) {
+                    Ok(id) => (202, json!({ "job": id })),
+                    Err(e @ SubmitError::QueueFull) => (503, json!({ "error": e.to_string() })),
+                    Err(e) => bad_request(e.into()),
+                },
+                Err(e) => bad_request(e.into()),
+            }
+        }
+        ("GET", ["circuits", circuit, "verification_key"]) => match service.verification_key(circuit) {
+            Some(vk) => (200, json!(vk)),
+            None => not_found,
+        },
+        ("POST", ["circuits", circuit, "verify"]) => {
+            let parsed = serde_json::from_slice::<Value>(&request.body).and_then(|mut body| {
+                let public_inputs: Vec<C::ScalarField> = serde_json::from_value(body["public_inputs"].take())?;
+                let proof: Proof<C> = serde_json::from_value(body["proof"].take())?;
+                Ok((public_inputs, proof))
+            });
+            match parsed {
+                Ok((public_inputs, proof)) => match service.verify(circuit, &public_inputs, &proof) {
+                    Ok(()) => (200, json!({ "valid": true })),
+                    Err(e) => (200, json!({ "valid": false, "error": e.to_string() })),
+                },
+                Err(e) => bad_request(e.into()),
+            }
+        }
+        ("GET", ["jobs", id]) => match id.parse().ok().and_then(|id| service.status(id)) {
+            Some(status) => (200, status_to_json(status)),
+            None => not_found,
+        },
+        ("DELETE", ["jobs", id]) => match id.parse() {
+            Ok(id) if service.remove(id) => (200, json!({})),
+            _ => not_found,
+        },
+        _ => not_found,
+    }
+}
+
+fn status_to_json<C: HaloCurve>(status: JobStatus<C>) -> Value
+where
+    Proof<C>: Serialize,
+{
+    match status {
+        JobStatus::Queued { position } => json!({ "status": "queued", "position": position }),
+        JobStatus::Running { elapsed, phase } => json!({
+            "status": "running",
+            "elapsed_secs": elapsed.as_secs_f64(),
+            "phase": phase.map(|phase| phase.to_string()),
+        }),
+        JobStatus::Succeeded { proof, proving_time } => json!({
+            "status": "succeeded",
+            "proving_secs": proving_time.as_secs_f64(),
+            "proof": *proof,
+        }),
+        JobStatus::Failed { error } => json!({ "status": "failed", "error": error }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use serde_json::{json, Value};
+
+    use crate::service::{serve, JobStatus, ProvingService, ServiceLimits, SubmitError};
+    use crate::{public_inputs_to_json, Circuit, CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum, Witness};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// A circuit which proves knowledge of a square root of its public input.
+    fn square_circuit() -> (Circuit<Tweedledee>, Witness<F>) {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let root = builder.add_virtual_target();
+        let square = builder.square(root);
+        let public_input = builder.add_public_input();
+        builder.copy(square, public_input);
+        let circuit = builder.build();
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(root, F::FIVE);
+        let witness = circuit.generate_witness(partial_witness).unwrap();
+        (circuit, witness)
+    }
+
+    fn new_service(circuit: &Circuit<Tweedledee>) -> ProvingService<Tweedledee, Tweedledum> {
+        new_service_with_limits(circuit, ServiceLimits::default())
+    }
+
+    fn new_service_with_limits(
+        circuit: &Circuit<Tweedledee>,
+        limits: ServiceLimits,
+    ) -> ProvingService<Tweedledee, Tweedledum> {
+        let mut circuits = HashMap::new();
+        circuits.insert("square".to_string(), circuit.prover_key.clone());
+        ProvingService::with_limits(circuits, 1, limits)
+    }
+
+    #[test]
+    fn test_proving_service() -> Result<()> {
+        let (circuit, witness) = square_circuit();
+        let service = new_service(&circuit);
+        assert!(service.submit("cube", witness.clone()).is_err());
+
+        let id = service.submit("square", witness.clone())?;
+        let proof = match service.wait(id) {
+            Some(JobStatus::Succeeded { proof, .. }) => proof,
+            status => panic!("Unexpected status {:?}", status),
+        };
+        service.verify("square", &[F::from_canonical_u64(25)], &proof)?;
+        assert!(service.verify("square", &[F::from_canonical_u64(26)], &proof).is_err());
+
+        // A malformed witness fails its job, but not the worker.
+        let failing_id = service.submit("square", Witness::new(vec![]))?;
+        assert!(matches!(service.wait(failing_id), Some(JobStatus::Failed { .. })));
+        let id = service.submit("square", witness)?;
+        assert!(matches!(service.wait(id), Some(JobStatus::Succeeded { .. })));
+
+        assert!(service.remove(id));
+        assert_eq!(service.status(id), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_proving_service_limits() -> Result<()> {
+        let (circuit, witness) = square_circuit();
+        let limits = ServiceLimits {
+            max_queued_jobs: 1,
+            max_finished_jobs: 1,
+            ..ServiceLimits::default()
+        };
+        let service = new_service_with_limits(&circuit, limits);
+
+        // The worker takes at most one job before the submissions are done, so at least two wait
+        // in a queue which only fits one.
+        let submitted = (0..3).map(|_| service.submit("square", witness.clone())).collect::<Vec<_>>();
+        assert!(submitted.contains(&Err(SubmitError::QueueFull)));
+
+        // Only the most recently finished job is kept.
+        let ids = submitted.into_iter().filter_map(|result| result.ok()).collect::<Vec<_>>();
+        for &id in &ids {
+            assert!(matches!(service.wait(id), Some(JobStatus::Succeeded { .. }) | None));
+        }
+        let (last_id, earlier_ids) = ids.split_last().unwrap();
+        assert!(matches!(service.status(*last_id), Some(JobStatus::Succeeded { .. })));
+        for &id in earlier_ids {
+            assert_eq!(service.status(id), None);
+        }
+        Ok(())
+    }
+
+    fn request(address: &str, method: &str, path: &str, body: &str) -> Result<(u16, Value)> {
+        let mut stream = TcpStream::connect(address)?;
+        write!(stream, "{} {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", method, path, body.len(), body)?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        let status = response[9..12].parse()?;
+        let body_start = response.find("\r\n\r\n").unwrap() + 4;
+        Ok((status, serde_json::from_str(&response[body_start..])?))
+    }
+
+    #[test]
+    fn test_http() -> Result<()> {
+        let (circuit, witness) = square_circuit();
+        let service = Arc::new(new_service(&circuit));
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?.to_string();
+        std::thread::spawn(move || serve(service, listener));
+
+        let (status, response) = request(&address, "POST", "/circuits/square/jobs", &serde_json::to_string(&witness)?)?;
+        assert_eq!(status, 202);
+        let job_path = format!("/jobs/{}", response["job"]);
+        let proof = loop {
+            let (status, response) = request(&address, "GET", &job_path, "")?;
+            assert_eq!(status, 200);
+            match response["status"].as_str() {
+                Some("queued") | Some("running") => std::thread::sleep(std::time::Duration::from_millis(50)),
+                Some("succeeded") => break response["proof"].clone(),
+                _ => panic!("Unexpected status {}", response),
+            }
+        };
+
+        let public_inputs: Value = serde_json::from_str(&public_inputs_to_json(&[F::from_canonical_u64(25)]))?;
+        let body = json!({ "public_inputs": public_inputs, "proof": proof }).to_string();
+        assert_eq!(request(&address, "POST", "/circuits/square/verify", &body)?, (200, json!({ "valid": true })));
+
+        assert_eq!(request(&address, "GET", "/circuits/square/verification_key", "")?.0, 200);
+        assert_eq!(request(&address, "GET", "/circuits/cube/verification_key", "")?.0, 404);
+        assert_eq!(request(&address, "POST", "/circuits/square/jobs", "{")?.0, 400);
+        assert_eq!(request(&address, "DELETE", &job_path, "")?.0, 200);
+        assert_eq!(request(&address, "GET", &job_path, "")?.0, 404);
+        Ok(())
+    }
+
+    /// Serves a proving service for the square circuit on a new thread, returning its address.
+    fn spawn_server(circuit: &Circuit<Tweedledee>, limits: ServiceLimits) -> Result<String> {
+        let service = Arc::new(new_service_with_limits(circuit, limits));
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?.to_string();
+        std::thread::spawn(move || serve(service, listener));
+        Ok(address)
+    }
+
+    #[test]
+    fn test_http_limits() -> Result<()> {
+        let (circuit, _) = square_circuit();
+
+        // A body over the limit is rejected before it is read.
+        let limits = ServiceLimits {
+            max_request_body_len: 16,
+            ..ServiceLimits::default()
+        };
+        let address = spawn_server(&circuit, limits)?;
+        let mut stream = TcpStream::connect(&address)?;
+        write!(stream, "POST /circuits/square/jobs HTTP/1.1\r\nContent-Length: 1073741824\r\n\r\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+
+        // While a connection is open, another one is rejected.
+        let limits = ServiceLimits {
+            max_connections: 1,
+            io_timeout: Duration::from_secs(5),
+            ..ServiceLimits::default()
+        };
+        let address = spawn_server(&circuit, limits)?;
+        let idle_stream = TcpStream::connect(&address)?;
+        let mut stream = TcpStream::connect(&address)?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        assert!(response.starts_with("HTTP/1.1 503"), "{}", response);
+        drop(idle_stream);
+        Ok(())
+    }
+}
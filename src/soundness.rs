@@ -0,0 +1,225 @@
+//! Test utilities which guard against accidentally unsound constraints. Starting from a valid
+//! witness, they perturb the wires of a gate or gadget one at a time, and check that each perturbed
+//! witness is caught, first by `check_circuit`, then by the prover or verifier.
+
+use crate::gates::gate_name;
+use crate::util::transpose;
+use crate::{check_circuit, verify_proof, Circuit, Field, HaloCurve, PartialWitness, Target, Wire, Witness};
+
+/// The maximum number of wires `assert_perturbations_detected` perturbs, so that large gadgets
+/// remain quick to check. Wires are sampled evenly among the candidates.
+const MAX_PERTURBED_WIRES: usize = 64;
+
+/// Returns a copy of `witness` with `delta` added to the value of `wire`.
+pub(crate) fn perturb<F: Field>(witness: &Witness<F>, wire: Wire, delta: F) -> Witness<F> {
+    let mut rows = transpose(&witness.transpose());
+    rows[wire.gate][wire.input] = rows[wire.gate][wire.input] + delta;
+    Witness::new(rows)
+}
+
+/// Generates a witness from `inputs`, then perturbs each wire populated by witness generation in a
+/// gate of one of the given types, and asserts that `check_circuit` rejects every perturbed
+/// witness. Finally, asserts that the first perturbed witness can't be turned into a valid proof.
+///
+/// Every populated wire of a gate should be constrained, either by the gate itself or by a copy
+/// constraint, so a perturbation which goes unnoticed points to a soundness bug.
+pub(crate) fn assert_perturbations_detected<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    circuit: &Circuit<C>,
+    inputs: PartialWitness<C::ScalarField>,
+    gate_types: &[&str],
+) {
+    let partial_witness = circuit
+        .generate_partial_witness(inputs)
+        .expect("Witness generation should succeed");
+    let witness = Witness::from_partial(&partial_witness, circuit.degree(), circuit.num_wires);
+    assert_eq!(
+        check_circuit::<C, InnerC>(circuit, &witness),
+        Ok(()),
+        "The unperturbed witness should be valid"
+    );
+
+    let mut wires = partial_witness
+        .all_populated_targets()
+        .into_iter()
+        .filter_map(|target| match target {
+            Target::Wire(wire) => Some(wire),
+            _ => None,
+        })
+        .filter(|wire| {
+            gate_name::<C, InnerC>(&circuit.gate_constants[wire.gate])
+                .is_some_and(|name| gate_types.contains(&name))
+        })
+        .collect::<Vec<_>>();
+    wires.sort_by_key(|wire| (wire.gate, wire.input));
+    assert!(!wires.is_empty(), "No wires of types {:?} were populated", gate_types);
+    let stride = wires.len().div_ceil(MAX_PERTURBED_WIRES);
+
+    for &wire in wires.iter().step_by(stride) {
+        // Try both directions, since e.g. a bit can only be perturbed upwards or downwards while
+        // remaining binary.
+        for &delta in &[C::ScalarField::ONE, C::ScalarField::NEG_ONE] {
+            assert!(
+                check_circuit::<C, InnerC>(circuit, &perturb(&witness, wire, delta)).is_err(),
+                "Adding {:?} to {:?} ({:?}) went undetected",
+                delta,
+                wire,
+                gate_name::<C, InnerC>(&circuit.gate_constants[wire.gate])
+            );
+        }
+    }
+
+    assert_proof_rejected::<C, InnerC>(circuit, &perturb(&witness, wires[0], C::ScalarField::ONE));
+}
+
+/// Asserts that proving with the given invalid witness either fails, or yields a proof which the
+/// verifier rejects.
+pub(crate) fn assert_proof_rejected<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    circuit: &Circuit<C>,
+    witness: &Witness<C::ScalarField>,
+) {
+    if let Ok(proof) = circuit.generate_proof::<InnerC>(witness, &[]) {
+        let public_inputs = circuit.get_public_inputs(witness);
+        assert!(
+            verify_proof::<C, InnerC>(&public_inputs, &proof, &[], &circuit.to_vk(), true).is_err(),
+            "A proof from an invalid witness was accepted"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::soundness::assert_perturbations_detected;
+    use crate::{blake_hash_base_field_to_curve, ArithmeticGate, Base4SumGate, CircuitBuilder, ConstantGate, Curve, CurveAddGate, CurveDblGate, CurveEndoGate, CurveMulOp, Field, Gate, PartialWitness, RescueStepAGate, RescueStepBGate, Target, Tweedledee, Tweedledum, Wire};
+
+    type C = Tweedledee;
+    type InnerC = Tweedledum;
+    type SF = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_arithmetic_soundness() {
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let x_y = builder.mul(x, y);
+        let sum = builder.add(x_y, x);
+        builder.assert_nonzero(sum);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, SF::rand());
+        inputs.set_target(y, SF::rand());
+        assert_perturbations_detected::<C, InnerC>(&circuit, inputs, &[ArithmeticGate::<C>::NAME]);
+    }
+
+    #[test]
+    fn test_constant_soundness() {
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let x = builder.add_virtual_target();
+        let c = builder.constant_wire(SF::rand());
+        let product = builder.mul(x, c);
+        builder.assert_nonzero(product);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, SF::rand());
+        assert_perturbations_detected::<C, InnerC>(&circuit, inputs, &[ConstantGate::<C>::NAME]);
+    }
+
+    #[test]
+    fn test_split_binary_soundness() {
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let x = builder.add_virtual_target();
+        builder.split_binary_checked(x, 16);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, SF::from_canonical_usize(0xB00F));
+        assert_perturbations_detected::<C, InnerC>(&circuit, inputs, &[ArithmeticGate::<C>::NAME]);
+    }
+
+    #[test]
+    fn test_base_4_sum_soundness() {
+        type B4 = Base4SumGate<C>;
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let limbs = (0..B4::NUM_LIMBS)
+            .map(|i| SF::from_canonical_usize(i % 4))
+            .collect::<Vec<_>>();
+        let sum = limbs.iter().fold(SF::ZERO, |acc, &l| acc.quadruple() + l);
+
+        let index = builder.num_gates();
+        builder.add_gate_no_constants(B4::new(index));
+        let acc_old = builder.zero_wire();
+        let acc_new = builder.constant_wire(sum);
+        builder.copy(acc_old, Target::Wire(Wire { gate: index, input: B4::WIRE_ACC_OLD }));
+        builder.copy(acc_new, Target::Wire(Wire { gate: index, input: B4::WIRE_ACC_NEW }));
+        let limb_targets = (0..B4::NUM_LIMBS)
+            .map(|i| Target::Wire(Wire { gate: index, input: B4::wire_limb(i) }))
+            .collect::<Vec<_>>();
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_targets(&limb_targets, &limbs);
+        assert_perturbations_detected::<C, InnerC>(&circuit, inputs, &[B4::NAME]);
+    }
+
+    #[test]
+    fn test_curve_add_and_double_soundness() {
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let p = builder.add_virtual_point_target();
+        let q = builder.add_virtual_point_target();
+        let sum = builder.curve_add::<InnerC>(p, q);
+        let double = builder.curve_double::<InnerC>(sum);
+        builder.assert_nonzero(double.x);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_point_target(p, blake_hash_base_field_to_curve::<InnerC>(SF::rand()));
+        inputs.set_point_target(q, blake_hash_base_field_to_curve::<InnerC>(SF::rand()));
+        assert_perturbations_detected::<C, InnerC>(
+            &circuit,
+            inputs,
+            &[CurveAddGate::<C, InnerC>::NAME, CurveDblGate::<C, InnerC>::NAME],
+        );
+    }
+
+    #[test]
+    fn test_curve_endo_soundness() {
+        // The inner curve is Tweedledee here, so that the scalar is a Tweedledum scalar.
+        type BF = <Tweedledee as Curve>::BaseField;
+        let mut builder = CircuitBuilder::<Tweedledum>::new(128);
+        let scalar = builder.add_virtual_target();
+        let point = builder.add_virtual_point_target();
+        let result = builder.curve_msm_endo::<Tweedledee>(&[CurveMulOp { scalar, point }]);
+        builder.assert_nonzero(result.msm_result.x);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(scalar, BF::rand());
+        inputs.set_point_target(point, blake_hash_base_field_to_curve::<Tweedledee>(BF::rand()));
+        assert_perturbations_detected::<Tweedledum, Tweedledee>(
+            &circuit,
+            inputs,
+            &[CurveEndoGate::<Tweedledum, Tweedledee>::NAME],
+        );
+    }
+
+    #[test]
+    fn test_rescue_soundness() {
+        let mut builder = CircuitBuilder::<C>::new(128);
+        let x = builder.add_virtual_target();
+        let hash = builder.rescue_hash_n_to_1(&[x]);
+        builder.assert_nonzero(hash);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, SF::rand());
+        assert_perturbations_detected::<C, InnerC>(
+            &circuit,
+            inputs,
+            &[RescueStepAGate::<C>::NAME, RescueStepBGate::<C>::NAME],
+        );
+    }
+}
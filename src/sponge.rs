@@ -0,0 +1,309 @@
+use crate::util::ceil_div_usize;
+use crate::{AlgebraicHasher, Field, GmimcHasher, PoseidonHasher, RescueHasher};
+
+/// The domain separation tag of the sponges used to hash to curves.
+pub(crate) const HASH_TO_CURVE_DOMAIN_TAG: u64 = 1;
+/// The domain separation tag of the sponges used by `hash_fields`.
+pub(crate) const VARIABLE_LENGTH_HASH_DOMAIN_TAG: u64 = 2;
+/// The domain separation tag of the sponges used by `hash_bytes_to_curve`.
+pub(crate) const HASH_BYTES_TO_CURVE_DOMAIN_TAG: u64 = 3;
+
+/// A sponge over an algebraic permutation.
+///
+/// Inputs are added to the first `rate` elements of the state, and the permutation is applied
+/// after each `rate` inputs. When switching from absorbing to squeezing, any partially absorbed
+/// block is permuted, then outputs are read from the first `rate` elements of the state, applying
+/// the permutation whenever they have all been read. Absorbing after squeezing discards any
+/// unread outputs. This makes it a duplex sponge whose inputs are sometimes zero (when we perform
+/// multiple squeezes) and whose outputs are sometimes ignored (when we perform multiple
+/// absorptions), so the security properties of a duplex sponge still apply.
+#[derive(Clone)]
+pub struct Sponge<F: Field> {
+    state: Vec<F>,
+    rate: usize,
+    security_bits: usize,
+    permutation: fn(&[F], usize) -> Vec<F>,
+    mode: SpongeMode,
+    /// The position within the rate portion of the state at which the next input will be added,
+    /// or from which the next output will be read, depending on the mode.
+    position: usize,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum SpongeMode {
+    Absorbing,
+    Squeezing,
+}
+
+impl<F: Field> Sponge<F> {
+    pub fn new(
+        rate: usize,
+        capacity: usize,
+        security_bits: usize,
+        permutation: fn(&[F], usize) -> Vec<F>,
+    ) -> Self {
+        assert!(rate > 0, "The rate must be positive");
+        assert!(capacity > 0, "The capacity must be positive");
+        Sponge {
+            state: vec![F::ZERO; rate + capacity],
+            rate,
+            security_bits,
+            permutation,
+            mode: SpongeMode::Absorbing,
+            position: 0,
+        }
+    }
+
+    /// A sponge with the permutation and parameters of the given hasher.
+    pub fn new_with_hasher<H: AlgebraicHasher<F>>(security_bits: usize) -> Self {
+        Self::new(H::RATE, H::WIDTH - H::RATE, security_bits, H::permute)
+    }
+
+    pub fn new_rescue(security_bits: usize) -> Self {
+        Self::new_with_hasher::<RescueHasher>(security_bits)
+    }
+
+    pub fn new_poseidon(security_bits: usize) -> Self {
+        Self::new_with_hasher::<PoseidonHasher>(security_bits)
+    }
+
+    pub fn new_gmimc(security_bits: usize) -> Self {
+        Self::new_with_hasher::<GmimcHasher>(security_bits)
+    }
+
+    /// Sets a domain separation tag, which initializes the first capacity element of the state.
+    /// Sponges with different tags compute unrelated functions of their inputs. Must be called
+    /// before anything is absorbed or squeezed.
+    pub fn with_domain_tag(mut self, tag: u64) -> Self {
+        assert!(
+            self.mode == SpongeMode::Absorbing
+                && self.position == 0
+                && self.state.iter().all(|x| x.is_zero()),
+            "The domain tag must be set before using the sponge"
+        );
+        self.state[self.rate] = F::from_canonical_u64(tag);
+        self
+    }
+
+    pub fn rate(&self) -> usize {
+        self.rate
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.state.len() - self.rate
+    }
+
+    pub fn absorb(&mut self, input: F) {
+        if self.mode == SpongeMode::Squeezing {
+            // Any unread outputs are discarded, and we start overwriting the rate portion.
+            self.mode = SpongeMode::Absorbing;
+            self.position = 0;
+        }
+
+        self.state[self.position] = self.state[self.position] + input;
+        self.position += 1;
+        if self.position == self.rate {
+            self.permute();
+            self.position = 0;
+        }
+    }
+
+    pub fn absorb_all(&mut self, inputs: &[F]) {
+        for &input in inputs {
+            self.absorb(input);
+        }
+    }
+
+    pub fn squeeze(&mut self) -> F {
+        if self.mode == SpongeMode::Absorbing {
+            // Permute any partially absorbed block. A full block has already been permuted.
+            if self.position > 0 {
+                self.permute();
+            }
+            self.mode = SpongeMode::Squeezing;
+            self.position = 0;
+        }
+
+        // The permutation is applied lazily, so that absorbing right after reading a full block
+        // of outputs doesn't cost an extra permutation.
+        if self.position == self.rate {
+            self.permute();
+            self.position = 0;
+        }
+
+        let output = self.state[self.position];
+        self.position += 1;
+        output
+    }
+
+    pub fn squeeze_n(&mut self, n: usize) -> Vec<F> {
+        (0..n).map(|_| self.squeeze()).collect()
+    }
+
+    fn permute(&mut self) {
+        self.state = (self.permutation)(&self.state, self.security_bits);
+    }
+}
+
+/// Pads a variable-length input to a multiple of `rate` elements, by appending a one followed by as
+/// many zeros as needed. Since the padding always starts with a one, distinct inputs (including
+/// inputs of different lengths) give distinct padded inputs.
+pub fn pad_variable_length<T: Copy>(inputs: &[T], one: T, zero: T, rate: usize) -> Vec<T> {
+    let mut padded = inputs.to_vec();
+    padded.push(one);
+    padded.resize(ceil_div_usize(padded.len(), rate) * rate, zero);
+    padded
+}
+
+/// Hashes a variable number of field elements to a single one. The inputs are padded with
+/// `pad_variable_length` and absorbed into a Rescue sponge with its own domain tag. This matches
+/// `CircuitBuilder::hash_fields`.
+pub fn hash_fields<F: Field>(inputs: &[F], security_bits: usize) -> F {
+    let mut sponge =
+        Sponge::new_rescue(security_bits).with_domain_tag(VARIABLE_LENGTH_HASH_DOMAIN_TAG);
+    let padded = pad_variable_length(inputs, F::ONE, F::ZERO, sponge.rate());
+    sponge.absorb_all(&padded);
+    sponge.squeeze()
+}
+
+/// Hashes arbitrary bytes to a single field element, by hashing `bytes_to_fields(bytes)` with
+/// `hash_fields`.
+pub fn hash_bytes<F: Field>(bytes: &[u8], security_bits: usize) -> F {
+    hash_fields(&bytes_to_fields::<F>(bytes), security_bits)
+}
+
+/// Encodes bytes as field elements: the number of bytes, followed by the bytes packed in
+/// little-endian chunks of `(F::BITS - 1) / 8` bytes, so that each chunk fits in a field element.
+/// A circuit which hashes byte data with `CircuitBuilder::hash_fields` should take these elements
+/// as inputs.
+pub fn bytes_to_fields<F: Field>(bytes: &[u8]) -> Vec<F> {
+    let bytes_per_element = (F::BITS - 1) / 8;
+    // `from_canonical_u8_vec` expects whole u64 limbs.
+    let padded_len = ceil_div_usize(F::BITS, 64) * 8;
+
+    let mut elements = vec![F::from_canonical_usize(bytes.len())];
+    for chunk in bytes.chunks(bytes_per_element) {
+        let mut chunk = chunk.to_vec();
+        chunk.resize(padded_len, 0);
+        elements.push(F::from_canonical_u8_vec(chunk).expect("Chunk should fit in a field element"));
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bytes_to_fields, hash_bytes, hash_fields, pad_variable_length, rescue_permutation, Field,
+        Sponge, TweedledumBase,
+    };
+
+    type F = TweedledumBase;
+
+    #[test]
+    fn test_sponge_absorb_squeeze() {
+        let inputs = (0..4).map(F::from_canonical_usize).collect::<Vec<_>>();
+        let mut sponge = Sponge::new_rescue(128);
+        sponge.absorb_all(&inputs);
+        let outputs = sponge.squeeze_n(4);
+
+        // Compute the same thing with explicit permutations.
+        let mut state = vec![F::ZERO; 4];
+        for (i, &input) in inputs[..3].iter().enumerate() {
+            state[i] = state[i] + input;
+        }
+        state = rescue_permutation(&state, 128);
+        state[0] = state[0] + inputs[3];
+        state = rescue_permutation(&state, 128);
+        let mut expected = state[..3].to_vec();
+        state = rescue_permutation(&state, 128);
+        expected.push(state[0]);
+
+        assert_eq!(outputs, expected);
+    }
+
+    #[test]
+    fn test_sponge_duplex() {
+        let mut sponge = Sponge::new_poseidon(128);
+        sponge.absorb(F::ONE);
+        let first = sponge.squeeze();
+        sponge.absorb(F::ONE);
+        let second = sponge.squeeze();
+        assert_ne!(first, second);
+
+        // Without the second absorption, we would instead have read the next output of the block.
+        let mut other = Sponge::new_poseidon(128);
+        other.absorb(F::ONE);
+        let outputs = other.squeeze_n(2);
+        assert_eq!(outputs[0], first);
+        assert_ne!(outputs[1], second);
+    }
+
+    #[test]
+    fn test_sponge_domain_tag() {
+        let mut untagged = Sponge::new_rescue(128);
+        let mut tagged = Sponge::new_rescue(128).with_domain_tag(1);
+        let mut other_tag = Sponge::new_rescue(128).with_domain_tag(2);
+        for sponge in [&mut untagged, &mut tagged, &mut other_tag].iter_mut() {
+            sponge.absorb_all(&[F::ONE, F::TWO]);
+        }
+        let outputs = [untagged.squeeze(), tagged.squeeze(), other_tag.squeeze()];
+        assert_ne!(outputs[0], outputs[1]);
+        assert_ne!(outputs[0], outputs[2]);
+        assert_ne!(outputs[1], outputs[2]);
+    }
+
+    #[test]
+    fn test_sponge_custom_rate() {
+        let mut sponge = Sponge::new(5, 2, 128, rescue_permutation);
+        assert_eq!(sponge.rate(), 5);
+        assert_eq!(sponge.capacity(), 2);
+        sponge.absorb_all(&[F::ONE; 7]);
+        let outputs = sponge.squeeze_n(6);
+        assert_eq!(outputs.len(), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "The domain tag must be set before using the sponge")]
+    fn test_late_domain_tag() {
+        let mut sponge = Sponge::<F>::new_rescue(128);
+        sponge.absorb(F::ONE);
+        let _ = sponge.with_domain_tag(1);
+    }
+
+    #[test]
+    fn test_pad_variable_length() {
+        assert_eq!(pad_variable_length(&[], 1, 0, 3), vec![1, 0, 0]);
+        assert_eq!(pad_variable_length(&[5, 6], 1, 0, 3), vec![5, 6, 1]);
+        assert_eq!(pad_variable_length(&[5, 6, 7], 1, 0, 3), vec![5, 6, 7, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_hash_fields_unambiguous() {
+        // Inputs which differ only by trailing zeros must not collide.
+        let hashes = [
+            hash_fields::<F>(&[], 128),
+            hash_fields(&[F::ZERO], 128),
+            hash_fields(&[F::ZERO, F::ZERO], 128),
+            hash_fields(&[F::ONE], 128),
+            hash_fields(&[F::ONE, F::ZERO], 128),
+        ];
+        for i in 0..hashes.len() {
+            for j in 0..i {
+                assert_ne!(hashes[i], hashes[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        let bytes = (0..100u8).collect::<Vec<_>>();
+        let elements = bytes_to_fields::<F>(&bytes);
+        assert_eq!(elements.len(), 1 + 4);
+        assert_eq!(elements[0], F::from_canonical_usize(100));
+        assert_eq!(elements[1].to_canonical_u8_vec()[..31], bytes[..31]);
+
+        assert_eq!(hash_bytes::<F>(&bytes, 128), hash_fields(&elements, 128));
+        assert_ne!(hash_bytes::<F>(&[1], 128), hash_bytes::<F>(&[1, 0], 128));
+        assert_ne!(hash_bytes::<F>(&[], 128), hash_bytes::<F>(&[0], 128));
+    }
+}
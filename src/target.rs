@@ -1,18 +1,19 @@
 use crate::{Field, NUM_ROUTED_WIRES, NUM_WIRES};
 use num::BigUint;
+use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::marker::PhantomData;
 
 /// A sort of proxy wire, in the context of routing and witness generation. It is not an actual
 /// witness element (i.e. wire) itself, but it can be copy-constrained to wires, listed as a
 /// dependency in generators, etc.
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 pub struct VirtualTarget {
     pub index: usize,
 }
 
 /// Represents a wire in the circuit.
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
 pub struct Wire {
     /// The index of the associated gate.
     pub gate: usize,
@@ -27,12 +28,14 @@ impl Wire {
 }
 
 /// A routing target over a field `F`.
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub enum Target<F: Field> {
     PublicInput(PublicInput<F>),
     VirtualTarget(VirtualTarget),
     Wire(Wire),
     // Trick taken from https://github.com/rust-lang/rust/issues/32739#issuecomment-627765543.
+    #[serde(skip)]
     _Field(Infallible, PhantomData<F>),
 }
 
@@ -68,7 +71,8 @@ pub struct BoundedTarget<F: Field> {
     pub max: BigUint,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct PublicInput<F: Field> {
     pub index: usize,
     _field: PhantomData<F>,
@@ -0,0 +1,222 @@
+//! JSON test vectors for the sponges, transcripts, hashes to curves and proofs, so that verifiers
+//! written in other languages can be checked against plonky. To write them to a directory:
+//!
+//! ```sh
+//! cargo run --release --bin test_vectors -- test_vectors/
+//! ```
+//!
+//! Field elements and points are hex strings of their binary encodings, as in `Proof::to_json`.
+//! All vectors are over Tweedledee's base field, i.e. Tweedledum's scalar field, at 128 bits of
+//! security, except for the proofs which also cover Pallas.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::plonk_challenger::{Blake2bChallenger, Challenger, Halo2Blake2bChallenger, Transcript};
+use crate::serialization_testing::{golden_vectors, GoldenVectors};
+use crate::{blake_hash_usize_to_curve, hash_bytes_to_curve, hash_fields, hash_usize_to_curve, poseidon_hash_usize_to_curve, AffinePoint, AlgebraicHasher, Curve, Field, GmimcHasher, Pallas, PoseidonHasher, RescueHasher, Sponge, Tweedledee, Tweedledum, Vesta};
+
+/// The field which the sponge and transcript vectors are over.
+type F = <Tweedledee as Curve>::BaseField;
+
+const SECURITY_BITS: usize = 128;
+
+/// The number of outputs squeezed from each sponge.
+const SQUEEZE_COUNT: usize = 3;
+
+/// The domain tag of the tagged sponge vectors.
+const SPONGE_DOMAIN_TAG: u64 = 7;
+
+/// The input lengths of the sponge vectors, chosen to cover empty inputs and inputs on either
+/// side of a multiple of the rate.
+const SPONGE_INPUT_LENGTHS: [usize; 6] = [0, 1, 2, 3, 4, 9];
+
+/// Every set of test vectors, along with its name, which the binary uses as a file name.
+pub fn all_test_vectors() -> Result<Vec<(&'static str, Value)>> {
+    Ok(vec![
+        ("sponge", sponge_vectors()),
+        ("transcript", transcript_vectors()),
+        ("hash_to_curve", hash_to_curve_vectors()),
+        ("proofs", proof_vectors()?),
+    ])
+}
+
+/// Inputs are 0, 1, 2, ..., so that they're easy to reproduce.
+fn sponge_inputs(len: usize) -> Vec<F> {
+    (0..len).map(F::from_canonical_usize).collect()
+}
+
+/// Outputs of the Rescue, Poseidon and GMiMC sponges, with and without a domain tag, along with
+/// `hash_fields`, which pads its input before absorbing it.
+pub fn sponge_vectors() -> Value {
+    let mut cases = sponge_cases::<RescueHasher>();
+    cases.extend(sponge_cases::<PoseidonHasher>());
+    cases.extend(sponge_cases::<GmimcHasher>());
+
+    let hash_fields_cases = SPONGE_INPUT_LENGTHS
+        .iter()
+        .map(|&len| {
+            let inputs = sponge_inputs(len);
+            json!({
+                "inputs": inputs,
+                "output": hash_fields(&inputs, SECURITY_BITS),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "security_bits": SECURITY_BITS,
+        "sponge": cases,
+        "hash_fields": hash_fields_cases,
+    })
+}
+
+fn sponge_cases<H: AlgebraicHasher<F>>() -> Vec<Value> {
+    let mut cases = Vec::new();
+    for &domain_tag in [None, Some(SPONGE_DOMAIN_TAG)].iter() {
+        for &len in SPONGE_INPUT_LENGTHS.iter() {
+            let mut sponge = Sponge::<F>::new_with_hasher::<H>(SECURITY_BITS);
+            if let Some(tag) = domain_tag {
+                sponge = sponge.with_domain_tag(tag);
+            }
+            let inputs = sponge_inputs(len);
+            sponge.absorb_all(&inputs);
+            cases.push(json!({
+                "hasher": H::NAME,
+                "width": H::WIDTH,
+                "rate": H::RATE,
+                "domain_tag": domain_tag,
+                "inputs": inputs,
+                "outputs": sponge.squeeze_n(SQUEEZE_COUNT),
+            }));
+        }
+    }
+    cases
+}
+
+/// Runs a fixed script of observations and challenges on each transcript, recording every step
+/// along with the challenges it produced.
+pub fn transcript_vectors() -> Value {
+    json!({
+        "security_bits": SECURITY_BITS,
+        "transcripts": [
+            transcript_case(Challenger::<F>::new(SECURITY_BITS)),
+            transcript_case(Challenger::<F>::new_poseidon(SECURITY_BITS)),
+            transcript_case(Blake2bChallenger::<F>::new()),
+            transcript_case(Halo2Blake2bChallenger::<F>::new()),
+        ],
+    })
+}
+
+fn transcript_case<T: Transcript<F>>(mut transcript: T) -> Value {
+    let point = Tweedledee::GENERATOR_AFFINE;
+    let elements = sponge_inputs(5);
+
+    let mut steps = Vec::new();
+    transcript.observe_label(b"plonky test vectors");
+    steps.push(json!({ "observe_label": hex::encode(b"plonky test vectors") }));
+    steps.push(json!({ "challenges": transcript.get_n_challenges(2) }));
+    transcript.observe_elements(&elements);
+    steps.push(json!({ "observe_elements": elements }));
+    steps.push(json!({ "challenges": [transcript.get_challenge()] }));
+    transcript.observe_affine_point(point);
+    steps.push(json!({ "observe_affine_point": point }));
+    transcript.observe_element(F::NEG_ONE);
+    steps.push(json!({ "observe_elements": [F::NEG_ONE] }));
+    steps.push(json!({ "challenges": transcript.get_n_challenges(3) }));
+
+    json!({
+        "hash": transcript.hash_name(),
+        "steps": steps,
+    })
+}
+
+fn point_vector<C: Curve>(point: AffinePoint<C>) -> Value {
+    json!({
+        "point": point,
+        "x": point.x,
+        "y": point.y,
+    })
+}
+
+/// Points from each of our hashes to curves, on Tweedledee.
+pub fn hash_to_curve_vectors() -> Value {
+    let seeds = [0usize, 1, 2, 1000];
+    let usize_cases = seeds
+        .iter()
+        .map(|&seed| {
+            json!({
+                "seed": seed,
+                "blake": point_vector(blake_hash_usize_to_curve::<Tweedledee>(seed)),
+                "rescue": point_vector(hash_usize_to_curve::<Tweedledee>(seed, SECURITY_BITS)),
+                "poseidon": point_vector(poseidon_hash_usize_to_curve::<Tweedledee>(seed, SECURITY_BITS)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let messages: [(&[u8], &[u8]); 3] = [(b"", b""), (b"plonky", b""), (b"plonky", b"test vector message")];
+    let bytes_cases = messages
+        .iter()
+        .map(|&(domain_tag, msg)| {
+            json!({
+                "domain_tag": hex::encode(domain_tag),
+                "msg": hex::encode(msg),
+                "rescue": point_vector(hash_bytes_to_curve::<Tweedledee>(domain_tag, msg, SECURITY_BITS)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "curve": "Tweedledee",
+        "security_bits": SECURITY_BITS,
+        "hash_usize_to_curve": usize_cases,
+        "hash_bytes_to_curve": bytes_cases,
+    })
+}
+
+fn proof_case(curve: &str, golden: GoldenVectors) -> Result<Value> {
+    Ok(json!({
+        "curve": curve,
+        "statement": "5 * 7 = 35, with 35 as the public input",
+        "proof": serde_json::from_str::<Value>(&golden.proof_json)?,
+        "proof_hex": golden.proof_hex,
+        "vk": serde_json::from_str::<Value>(&golden.vk_json)?,
+        "vk_hex": golden.vk_hex,
+        "public_inputs": serde_json::from_str::<Value>(&golden.public_inputs_json)?,
+    }))
+}
+
+/// The small proofs of `serialization_testing::golden_vectors`, which verifiers should accept.
+pub fn proof_vectors() -> Result<Value> {
+    Ok(json!([
+        proof_case("Tweedledee", golden_vectors::<Tweedledee, Tweedledum>()?)?,
+        proof_case("Pallas", golden_vectors::<Pallas, Vesta>()?)?,
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::test_vectors::{all_test_vectors, transcript_vectors};
+
+    #[test]
+    fn test_vectors_are_deterministic() -> Result<()> {
+        let vectors = all_test_vectors()?;
+        assert_eq!(vectors, all_test_vectors()?);
+        let names = vectors.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["sponge", "transcript", "hash_to_curve", "proofs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transcripts_differ() {
+        let vectors = transcript_vectors();
+        let transcripts = vectors["transcripts"].as_array().unwrap();
+        for (i, a) in transcripts.iter().enumerate() {
+            for b in &transcripts[i + 1..] {
+                assert_ne!(a["steps"], b["steps"], "Two transcripts gave the same challenges");
+            }
+        }
+    }
+}
@@ -1,31 +1,53 @@
-use anyhow::{bail, ensure, Result};
+use std::fmt;
+
+use anyhow::{ensure, Result};
+use rand::thread_rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::partition::get_subgroup_shift;
 
 use crate::gates::evaluate_all_constraints;
-use crate::halo::verify_ipa;
-use crate::plonk_proof::OldProof;
+use crate::halo::ipa_msm_terms;
+use crate::plonk_challenger::{Challenger, Transcript};
+use crate::plonk_proof::{OldProof, OpeningSet, ProofChallenge, ProofParameters};
 use crate::plonk_util::{halo_g, halo_n, halo_n_mul, halo_s, pedersen_hash, powers, reduce_with_powers};
 use crate::util::{ceil_div_usize, log2_strict};
-use crate::{blake_hash_usize_to_curve, fft_precompute, msm_execute_parallel, msm_precompute, AffinePoint, Circuit, FftPrecomputation, Field, HaloCurve, MsmPrecomputation, Polynomial, Proof, GRID_WIDTH, NUM_ROUTED_WIRES, NUM_WIRES};
+use crate::{blake_hash_usize_to_curve, msm_execute_parallel, msm_parallel, msm_precompute, AffinePoint, Circuit, FftPrecomputation, Field, HaloCurve, KeyFormat, MsmPrecomputation, PlonkyError, ProjectivePoint, Proof, SecurityParams, ToBytes, GRID_WIDTH, NUM_ROUTED_WIRES, NUM_WIRES};
 
 pub const SECURITY_BITS: usize = 128;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct VerificationKey<C: HaloCurve> {
+    pub format: KeyFormat<C>,
     pub c_constants: Vec<AffinePoint<C>>,
     pub c_s_sigmas: Vec<AffinePoint<C>>,
     pub degree: usize,
+    #[serde(default = "default_num_wires")]
+    pub num_wires: usize,
+    #[serde(default = "default_num_routed_wires")]
+    pub num_routed_wires: usize,
     pub num_public_inputs: usize,
     pub num_gates_without_pis: usize,
     pub security_bits: usize,
+    /// The length of Fiat-Shamir challenges in the IPA. See `SecurityParams::challenge_len`.
+    pub challenge_len: usize,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pedersen_g_msm_precomputation: Option<MsmPrecomputation<C>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub fft_precomputation: Option<FftPrecomputation<C::ScalarField>>,
 }
 
+// Keys serialized before the circuit width was configurable use the default width.
+fn default_num_wires() -> usize {
+    NUM_WIRES
+}
+
+fn default_num_routed_wires() -> usize {
+    NUM_ROUTED_WIRES
+}
+
 impl<C: HaloCurve> From<Circuit<C>> for VerificationKey<C> {
     fn from(circuit: Circuit<C>) -> Self {
         circuit.to_vk()
@@ -43,6 +65,346 @@ impl<C: HaloCurve> VerificationKey<C> {
         self.clear_fft_precomputation();
         self.clear_msm_precomputation();
     }
+
+    pub fn security_params(&self) -> SecurityParams {
+        SecurityParams {
+            bits: self.security_bits,
+            challenge_len: self.challenge_len,
+        }
+    }
+
+    /// Returns a Blake3 hash of the key's commitments and sizes, ignoring any precomputations.
+    /// Since circuit building is deterministic, this can be used to pin a verification key.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        for &n in &[
+            self.degree,
+            self.num_wires,
+            self.num_routed_wires,
+            self.num_public_inputs,
+            self.num_gates_without_pis,
+            self.security_bits,
+            self.challenge_len,
+        ] {
+            hasher.update(&(n as u64).to_le_bytes());
+        }
+        for c in self.c_constants.iter().chain(self.c_s_sigmas.iter()) {
+            let mut buf = Vec::new();
+            c.write(&mut buf).expect("Writing to a Vec should not fail");
+            hasher.update(&buf);
+        }
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Checks that the key's public input gates fit in the circuit. Public inputs are stored
+    /// `NUM_WIRES` per `PublicInputGate`, and each of those gates is followed by a buffer gate, all
+    /// after the first `num_gates_without_pis` gates.
+    pub fn check_public_input_layout(&self) -> std::result::Result<(), VerificationError> {
+        let num_public_input_gates = ceil_div_usize(self.num_public_inputs, NUM_WIRES);
+        if !self.degree.is_power_of_two() {
+            return Err(VerificationError::InvalidPublicInputLayout(format!(
+                "The circuit's degree {} is not a power of two",
+                self.degree
+            )));
+        }
+        if self.num_gates_without_pis + 2 * num_public_input_gates > self.degree {
+            return Err(VerificationError::InvalidPublicInputLayout(format!(
+                "{} public inputs need {} gates after gate {}, but the circuit only has {} gates",
+                self.num_public_inputs,
+                2 * num_public_input_gates,
+                self.num_gates_without_pis,
+                self.degree
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The reason a proof was rejected.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VerificationError {
+    /// The proof is not well-formed, e.g. a point is not on the curve, or its size doesn't match
+    /// the verification key.
+    MalformedProof(String),
+    /// The proof was generated for different parameters, e.g. another curve or transcript hash.
+    IncompatibleProof(String),
+    /// The number of public inputs doesn't match the verification key.
+    WrongNumberOfPublicInputs { expected: usize, actual: usize },
+    /// The verification key's public input gates don't fit in its circuit.
+    InvalidPublicInputLayout(String),
+    /// The verification key's security parameters are invalid, e.g. its challenges don't fit in
+    /// the scalar field.
+    InvalidSecurityParams(String),
+    /// The public inputs differ from those the proof was generated for, at the given indices. Only
+    /// `verify_debug` can tell.
+    PublicInputMismatch { indices: Vec<usize> },
+    /// The Fiat-Shamir challenges could not be derived from the transcript.
+    InvalidTranscript(String),
+    /// An opening of an old proof's `G` polynomial is incorrect.
+    InvalidOldProofOpening(String),
+    /// The opening of the quotient polynomial `t` doesn't match the constraints.
+    InvalidQuotientOpening,
+    /// The opening of the public input quotient polynomial doesn't match the public inputs.
+    InvalidPublicInputQuotientOpening,
+    /// The batched opening proof of all polynomial commitments is invalid.
+    InvalidOpeningProof,
+    /// The `G` point of the opening proof is not `<s, G>`.
+    InvalidHaloG,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::MalformedProof(msg) => write!(f, "Malformed proof: {}", msg),
+            VerificationError::IncompatibleProof(msg) => write!(f, "Incompatible proof: {}", msg),
+            VerificationError::WrongNumberOfPublicInputs { expected, actual } => write!(
+                f,
+                "Expected {} public inputs, got {}.",
+                expected, actual
+            ),
+            VerificationError::InvalidPublicInputLayout(msg) => {
+                write!(f, "Invalid public input layout: {}", msg)
+            }
+            VerificationError::InvalidSecurityParams(msg) => {
+                write!(f, "Invalid security parameters: {}", msg)
+            }
+            VerificationError::PublicInputMismatch { indices } => write!(
+                f,
+                "The public inputs at indices {:?} differ from those the proof was generated for.",
+                indices
+            ),
+            VerificationError::InvalidTranscript(msg) => write!(f, "Invalid transcript: {}", msg),
+            VerificationError::InvalidOldProofOpening(msg) => write!(f, "{}", msg),
+            VerificationError::InvalidQuotientOpening => {
+                write!(f, "Incorrect opening of the t polynomial.")
+            }
+            VerificationError::InvalidPublicInputQuotientOpening => {
+                write!(f, "Incorrect opening of the public inputs quotient polynomial.")
+            }
+            VerificationError::InvalidOpeningProof => write!(f, "Invalid IPA proof."),
+            VerificationError::InvalidHaloG => write!(f, "Invalid G point."),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Fully verifies a proof, given only the verification key and the public inputs. Unlike
+/// `verify_proof`, this doesn't support old proofs, so it is meant for verifiers which only check
+/// final proofs, such as servers and light clients.
+pub fn verify<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    vk: &VerificationKey<C>,
+    public_inputs: &[C::ScalarField],
+    proof: &Proof<C>,
+) -> std::result::Result<(), VerificationError> {
+    verify_with_transcript::<C, InnerC, _>(vk, public_inputs, proof, Challenger::new(vk.security_bits))
+}
+
+/// Like `verify`, but for proofs generated with a custom Fiat-Shamir transcript, e.g. via
+/// `ProverKey::generate_proof_with_transcript`. `challenger` must be fresh, and of the same kind
+/// the prover used.
+pub fn verify_with_transcript<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    T: Transcript<C::BaseField>,
+>(
+    vk: &VerificationKey<C>,
+    public_inputs: &[C::ScalarField],
+    proof: &Proof<C>,
+    challenger: T,
+) -> std::result::Result<(), VerificationError> {
+    verify_proof_inner::<C, InnerC, _, _>(public_inputs.iter().copied(), proof, &[], vk, true, challenger)
+        .map(|_| ())
+}
+
+/// Like `verify`, but takes the public inputs as an iterator, which is traversed twice rather than
+/// collected. This lets verifiers check statements with very many public inputs, e.g. read from
+/// disk, without holding them all in memory.
+pub fn verify_streaming<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    I: Iterator<Item = C::ScalarField> + Clone,
+>(
+    vk: &VerificationKey<C>,
+    public_inputs: I,
+    proof: &Proof<C>,
+) -> std::result::Result<(), VerificationError> {
+    verify_proof_inner::<C, InnerC, _, _>(
+        public_inputs,
+        proof,
+        &[],
+        vk,
+        true,
+        Challenger::new(vk.security_bits),
+    )
+    .map(|_| ())
+}
+
+/// Like `verify`, but meant for debugging a rejected proof, given the public inputs which the prover
+/// used, e.g. from `Circuit::get_public_inputs`. If the proof is rejected with `public_inputs` but
+/// accepted with `prover_public_inputs`, the returned error lists the indices at which they differ,
+/// rather than just reporting the check which failed. Since the public inputs are part of the
+/// transcript, that may be any check, not only the public input quotient opening.
+pub fn verify_debug<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    vk: &VerificationKey<C>,
+    public_inputs: &[C::ScalarField],
+    proof: &Proof<C>,
+    prover_public_inputs: &[C::ScalarField],
+) -> std::result::Result<(), VerificationError> {
+    match verify::<C, InnerC>(vk, public_inputs, proof) {
+        Err(_)
+            if public_inputs.len() == prover_public_inputs.len()
+                && verify::<C, InnerC>(vk, prover_public_inputs, proof).is_ok() =>
+        {
+            let indices = public_inputs
+                .iter()
+                .zip(prover_public_inputs)
+                .enumerate()
+                .filter(|(_, (x, y))| x != y)
+                .map(|(i, _)| i)
+                .collect();
+            Err(VerificationError::PublicInputMismatch { indices })
+        }
+        result => result,
+    }
+}
+
+/// Fully verifies many proofs for the same verification key, `public_inputs[i]` being the public
+/// inputs of `proofs[i]`. This is much faster than verifying each proof separately, as the opening
+/// proofs of all proofs are checked together, with a single large MSM.
+///
+/// If the combined check fails, we can't tell which proof is invalid; callers who need to know can
+/// fall back to verifying proofs one by one.
+pub fn verify_batch<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    proofs: &[Proof<C>],
+    public_inputs: &[Vec<C::ScalarField>],
+    vk: &VerificationKey<C>,
+) -> std::result::Result<(), VerificationError> {
+    if proofs.len() != public_inputs.len() {
+        return Err(VerificationError::MalformedProof(format!(
+            "Got {} proofs but {} sets of public inputs",
+            proofs.len(),
+            public_inputs.len()
+        )));
+    }
+
+    let verify_openings = |(proof, pis): (&Proof<C>, &Vec<C::ScalarField>)| {
+        verify_proof_openings::<C, InnerC, _, _>(
+            pis.iter().copied(),
+            proof,
+            &[],
+            vk,
+            Challenger::new(vk.security_bits),
+        )
+    };
+    #[cfg(feature = "parallel")]
+    let challs = proofs
+        .par_iter()
+        .zip(public_inputs)
+        .map(verify_openings)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    #[cfg(not(feature = "parallel"))]
+    let challs = proofs
+        .iter()
+        .zip(public_inputs)
+        .map(verify_openings)
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    // Each proof's IPA is valid if and only if some MSM is zero. We check a random combination of
+    // these MSMs, along with the `G` points of all proofs.
+    let mut rng = thread_rng();
+    let mut scalars = Vec::new();
+    let mut points = Vec::new();
+    let mut accumulators = Vec::new();
+    for (proof, proof_challs) in proofs.iter().zip(challs) {
+        let weight = C::ScalarField::rand_from_rng(&mut rng);
+        let (ipa_scalars, ipa_points) = ipa_msm_terms_for_proof(vk, proof, &[], &proof_challs);
+        scalars.extend(ipa_scalars.into_iter().map(|x| weight * x));
+        points.extend(ipa_points);
+        accumulators.push(OldProof {
+            halo_g: proof.halo_g,
+            halo_us: proof_challs.halo_us,
+        });
+    }
+
+    if msm_with_accumulators_is_zero(vk, scalars, points, &accumulators) {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidOpeningProof)
+    }
+}
+
+/// Verifies a proof, except for the linear time check of its `G` point, which is deferred by
+/// returning it as an accumulator. Accumulators can be checked later, many at a time, with
+/// `verify_accumulators`, or passed as old proofs to a recursive proof which checks them.
+pub fn verify_lazily<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    vk: &VerificationKey<C>,
+    public_inputs: &[C::ScalarField],
+    proof: &Proof<C>,
+) -> std::result::Result<OldProof<C>, VerificationError> {
+    let accumulator = verify_proof_inner::<C, InnerC, _, _>(
+        public_inputs.iter().copied(),
+        proof,
+        &[],
+        vk,
+        false,
+        Challenger::new(vk.security_bits),
+    )?;
+    Ok(accumulator.expect("An accumulator should be returned when verify_g is false"))
+}
+
+/// Checks that the `G` point of each accumulator is a commitment to the polynomial determined by
+/// its challenges, with a single MSM for all of them. The accumulators must come from proofs with
+/// the same degree as `vk`.
+pub fn verify_accumulators<C: HaloCurve>(
+    accumulators: &[OldProof<C>],
+    vk: &VerificationKey<C>,
+) -> std::result::Result<(), VerificationError> {
+    if accumulators.iter().any(|acc| 1 << acc.halo_us.len() != vk.degree) {
+        return Err(VerificationError::MalformedProof(
+            "Accumulator doesn't match the verification key's degree".to_string(),
+        ));
+    }
+    if msm_with_accumulators_is_zero(vk, Vec::new(), Vec::new(), accumulators) {
+        Ok(())
+    } else {
+        Err(VerificationError::InvalidHaloG)
+    }
+}
+
+/// Returns whether the given MSM is zero, after adding to it a random combination of the
+/// equations `halo_g = <s, G>` of each accumulator. Note that the given MSM should already be
+/// randomized, if it combines several equations.
+fn msm_with_accumulators_is_zero<C: HaloCurve>(
+    vk: &VerificationKey<C>,
+    mut scalars: Vec<C::ScalarField>,
+    mut points: Vec<ProjectivePoint<C>>,
+    accumulators: &[OldProof<C>],
+) -> bool {
+    let mut rng = thread_rng();
+    let mut combined_s = vec![C::ScalarField::ZERO; vk.degree];
+    for accumulator in accumulators {
+        let weight = C::ScalarField::rand_from_rng(&mut rng);
+        scalars.push(weight);
+        points.push(accumulator.halo_g.to_projective());
+        for (acc, s) in combined_s.iter_mut().zip(halo_s(&accumulator.halo_us)) {
+            *acc = *acc - weight * s;
+        }
+    }
+
+    let computed_precomputation;
+    let pedersen_g_msm_precomputation = match &vk.pedersen_g_msm_precomputation {
+        Some(precomputation) => precomputation,
+        None => {
+            let pedersen_g: Vec<_> = (0..vk.degree).map(blake_hash_usize_to_curve::<C>).collect();
+            computed_precomputation =
+                msm_precompute(&AffinePoint::batch_to_projective(&pedersen_g), 8);
+            &computed_precomputation
+        }
+    };
+    let total = msm_parallel(&scalars, &points, 8)
+        + pedersen_hash(&combined_s, pedersen_g_msm_precomputation);
+    total.to_affine().zero
 }
 
 /// Verifies a proof `proof` and some old proofs G points for a given verification key.
@@ -56,49 +418,216 @@ pub fn verify_proof<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>
     vk: &VerificationKey<C>,
     verify_g: bool,
 ) -> Result<Option<OldProof<C>>> {
+    Ok(verify_proof_inner::<C, InnerC, _, _>(
+        public_inputs.iter().copied(),
+        proof,
+        old_proofs,
+        vk,
+        verify_g,
+        Challenger::new(vk.security_bits),
+    )
+    .map_err(PlonkyError::from)?)
+}
+
+fn verify_proof_inner<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    T: Transcript<C::BaseField>,
+    I: Iterator<Item = C::ScalarField> + Clone,
+>(
+    public_inputs: I,
+    proof: &Proof<C>,
+    old_proofs: &[OldProof<C>],
+    vk: &VerificationKey<C>,
+    verify_g: bool,
+    challenger: T,
+) -> std::result::Result<Option<OldProof<C>>, VerificationError> {
+    let challs =
+        verify_proof_openings::<C, InnerC, T, I>(public_inputs, proof, old_proofs, vk, challenger)?;
+
+    // Verify polynomial commitment openings.
+    let (scalars, points) = ipa_msm_terms_for_proof(vk, proof, old_proofs, &challs);
+    if !msm_parallel(&scalars, &points, 8).to_affine().zero {
+        return Err(VerificationError::InvalidOpeningProof);
+    }
+
+    if verify_g {
+        let pedersen_g: Vec<_> = (0..vk.degree).map(blake_hash_usize_to_curve::<C>).collect();
+        let w = 8; // TODO: Should really be set dynamically based on MSM size.
+        let pedersen_g_msm_precomputation =
+            msm_precompute(&AffinePoint::batch_to_projective(&pedersen_g), w);
+
+        // Verify that `self.halo_g = <s, G>`.
+        if proof.halo_g
+            == pedersen_hash(&halo_s(&challs.halo_us), &pedersen_g_msm_precomputation).to_affine()
+        {
+            Ok(None)
+        } else {
+            Err(VerificationError::InvalidHaloG)
+        }
+    } else {
+        Ok(Some(OldProof {
+            halo_g: proof.halo_g,
+            halo_us: challs.halo_us,
+        }))
+    }
+}
+
+/// Performs all checks of a proof except for its opening proof, i.e. its IPA and `G` point, which
+/// are the expensive ones. Returns the proof's challenges.
+fn verify_proof_openings<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+    T: Transcript<C::BaseField>,
+    I: Iterator<Item = C::ScalarField> + Clone,
+>(
+    public_inputs: I,
+    proof: &Proof<C>,
+    old_proofs: &[OldProof<C>],
+    vk: &VerificationKey<C>,
+    challenger: T,
+) -> std::result::Result<ProofChallenge<C>, VerificationError> {
+    // Deserialized keys may carry any challenge length, and longer challenges than the field has
+    // bits would panic when they're sliced out of challenges.
+    vk.security_params()
+        .check::<C::ScalarField>()
+        .map_err(|e| VerificationError::InvalidSecurityParams(e.to_string()))?;
+
+    let expected_parameters =
+        ProofParameters::new::<C>(challenger.hash_name(), vk.num_wires, vk.security_params());
+    proof
+        .parameters
+        .check_compatible(&expected_parameters)
+        .map_err(|e| VerificationError::IncompatibleProof(e.to_string()))?;
+
     // Verify that the proof parameters are valid.
-    check_proof_parameters(proof)?;
+    check_proof_parameters(proof)
+        .and_then(|()| check_proof_width(proof, vk))
+        .map_err(|e| VerificationError::MalformedProof(e.to_string()))?;
+
+    // Check the number of public inputs up front if the iterator knows it, e.g. for slices, so
+    // that we don't hash a transcript only to reject it.
+    vk.check_public_input_layout()?;
+    if let (lower, Some(upper)) = public_inputs.size_hint() {
+        if lower == upper && lower != vk.num_public_inputs {
+            return Err(VerificationError::WrongNumberOfPublicInputs {
+                expected: vk.num_public_inputs,
+                actual: lower,
+            });
+        }
+    }
 
-    // Observe the transcript and generate the associated challenge points using Fiat-Shamir.
-    let challs = proof.get_challenges(public_inputs, old_proofs)?;
+    // Observe the transcript and generate the associated challenge points using Fiat-Shamir. The
+    // public inputs are counted in the same pass, so that they're only traversed twice.
+    let mut num_public_inputs = 0;
+    let challs = proof.get_challenges_streaming(
+        vk,
+        public_inputs.clone().inspect(|_| num_public_inputs += 1),
+        old_proofs,
+        challenger,
+    );
+    if num_public_inputs != vk.num_public_inputs {
+        return Err(VerificationError::WrongNumberOfPublicInputs {
+            expected: vk.num_public_inputs,
+            actual: num_public_inputs,
+        });
+    }
+    let challs = challs.map_err(|e| VerificationError::InvalidTranscript(e.to_string()))?;
 
     // Check the old proofs' openings.
-    verify_old_proof_evaluation(old_proofs, &proof, challs.zeta)?;
+    verify_old_proof_evaluation(old_proofs, proof, challs.zeta)
+        .map_err(|e| VerificationError::InvalidOldProofOpening(e.to_string()))?;
+
+    if !quotient_opening_is_valid::<C, InnerC>(
+        vk.num_routed_wires,
+        vk.degree,
+        challs.beta,
+        challs.gamma,
+        challs.alpha,
+        challs.zeta,
+        &proof.o_local,
+        &proof.o_right,
+        &proof.o_below,
+    ) {
+        return Err(VerificationError::InvalidQuotientOpening);
+    }
 
-    let degree = vk.degree;
+    let subgroup_generator_n = C::ScalarField::primitive_root_of_unity(log2_strict(vk.degree));
 
+    // Verify that the purported opening of the public input quotient polynomial is valid.
+    let num_public_input_gates = ceil_div_usize(vk.num_public_inputs, NUM_WIRES);
+    // Compute the denominator `prod_{pi \in PI} (X - pi)`.
+    let pis_quotient_denominator = (0..num_public_input_gates)
+        .fold(C::ScalarField::ONE, |acc, i| {
+            acc * (challs.zeta - subgroup_generator_n.exp_usize(vk.num_gates_without_pis + 2 * i))
+        });
+    let pis_quotient_numerator =
+        C::ScalarField::inner_product(&proof.o_local.o_wires, &powers(challs.alpha, vk.num_wires))
+            - eval_public_inputs_polynomial(
+                public_inputs,
+                challs.alpha,
+                challs.zeta,
+                vk.degree,
+                vk.num_gates_without_pis,
+            );
+    let computed_pis_quotient_opening = pis_quotient_numerator / pis_quotient_denominator;
+
+    if computed_pis_quotient_opening != proof.o_local.o_pi_quotient {
+        return Err(VerificationError::InvalidPublicInputQuotientOpening);
+    }
+
+    Ok(challs)
+}
+
+/// Checks that the opening of the quotient polynomial `t` matches the openings of the other
+/// polynomials, i.e. that the vanishing polynomial is divisible by `Z_H` at `zeta`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn quotient_opening_is_valid<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    num_routed_wires: usize,
+    degree: usize,
+    beta: C::ScalarField,
+    gamma: C::ScalarField,
+    alpha: C::ScalarField,
+    zeta: C::ScalarField,
+    o_local: &OpeningSet<C::ScalarField>,
+    o_right: &OpeningSet<C::ScalarField>,
+    o_below: &OpeningSet<C::ScalarField>,
+) -> bool {
     let constraint_terms = evaluate_all_constraints::<C, InnerC>(
-        &proof.o_local.o_constants,
-        &proof.o_local.o_wires,
-        &proof.o_right.o_wires,
-        &proof.o_below.o_wires,
+        &o_local.o_constants,
+        &o_local.o_wires,
+        &o_right.o_wires,
+        &o_below.o_wires,
     );
 
     // Evaluate zeta^degree.
-    let zeta_power_d = challs.zeta.exp_usize(degree);
+    let zeta_power_d = zeta.exp_usize(degree);
     // Evaluate Z_H(zeta).
     let one = <C::ScalarField as Field>::ONE;
     let zero_of_zeta = zeta_power_d - one;
 
     // Evaluate L_1(zeta) = (zeta^degree - 1) / (degree * (zeta - 1)).
     let lagrange_1_eval =
-        zero_of_zeta / (C::ScalarField::from_canonical_usize(degree) * (challs.zeta - one));
+        zero_of_zeta / (C::ScalarField::from_canonical_usize(degree) * (zeta - one));
 
     // Get z(zeta), z(g.zeta) from the proof openings.
-    let (z_x, z_gx) = (proof.o_local.o_plonk_z, proof.o_right.o_plonk_z);
+    let (z_x, z_gx) = (o_local.o_plonk_z, o_right.o_plonk_z);
     // Evaluate the L_1(x) (Z(x) - 1) vanishing term.
     let vanishing_z_1_term = lagrange_1_eval * (z_x - one);
 
     // Compute Z(zeta) f'(zeta) - Z(g * zeta) g'(zeta), which should vanish on H.
     let mut f_prime = one;
     let mut g_prime = one;
-    for i in 0..NUM_ROUTED_WIRES {
+    for i in 0..num_routed_wires {
         let k_i = get_subgroup_shift::<C::ScalarField>(i);
-        let s_id = k_i * challs.zeta;
-        let beta_s_id = challs.beta * s_id;
-        let beta_s_sigma = challs.beta * proof.o_local.o_plonk_sigmas[i];
-        let f_prime_part = proof.o_local.o_wires[i] + beta_s_id + challs.gamma;
-        let g_prime_part = proof.o_local.o_wires[i] + beta_s_sigma + challs.gamma;
+        let s_id = k_i * zeta;
+        let beta_s_id = beta * s_id;
+        let beta_s_sigma = beta * o_local.o_plonk_sigmas[i];
+        let f_prime_part = o_local.o_wires[i] + beta_s_id + gamma;
+        let g_prime_part = o_local.o_wires[i] + beta_s_sigma + gamma;
         f_prime = f_prime * f_prime_part;
         g_prime = g_prime * g_prime_part;
     }
@@ -112,89 +641,47 @@ pub fn verify_proof<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>
     .concat();
 
     // Compute t(zeta).
-    let computed_t_opening = reduce_with_powers(&vanishing_terms, challs.alpha) / zero_of_zeta;
+    let computed_t_opening = reduce_with_powers(&vanishing_terms, alpha) / zero_of_zeta;
 
     // Compute the purported opening of t(zeta).
-    let purported_t_opening = reduce_with_powers(&proof.o_local.o_plonk_t, zeta_power_d);
+    let purported_t_opening = reduce_with_powers(&o_local.o_plonk_t, zeta_power_d);
 
     // If the two values differ, the proof is invalid.
-    if computed_t_opening != purported_t_opening {
-        bail!("Incorrect opening of the t polynomial.");
-    }
+    computed_t_opening == purported_t_opening
+}
 
+/// Returns the terms of an MSM which is zero if and only if the opening proof of `proof` is valid.
+fn ipa_msm_terms_for_proof<C: HaloCurve>(
+    vk: &VerificationKey<C>,
+    proof: &Proof<C>,
+    old_proofs: &[OldProof<C>],
+    challs: &ProofChallenge<C>,
+) -> (Vec<C::ScalarField>, Vec<ProjectivePoint<C>>) {
     let subgroup_generator_n = C::ScalarField::primitive_root_of_unity(log2_strict(vk.degree));
-
-    // Verify that the purported opening of the public input quotient polynomial is valid.
-    let num_public_input_gates = ceil_div_usize(vk.num_public_inputs, NUM_WIRES);
-    // Compute the denominator `prod_{pi \in PI} (X - pi)`.
-    let pis_quotient_denominator = (0..num_public_input_gates)
-        .fold(C::ScalarField::ONE, |acc, i| {
-            acc * (challs.zeta - subgroup_generator_n.exp_usize(vk.num_gates_without_pis + 2 * i))
-        });
-    let pis_quotient_numerator =
-        C::ScalarField::inner_product(&proof.o_local.o_wires, &powers(challs.alpha, NUM_WIRES))
-            - public_inputs_to_polynomial(
-                public_inputs,
-                challs.alpha,
-                vk.degree,
-                vk.num_gates_without_pis,
-                vk.fft_precomputation.as_ref(),
-            )
-            .eval(challs.zeta);
-    let computed_pis_quotient_opening = pis_quotient_numerator / pis_quotient_denominator;
-
-    if computed_pis_quotient_opening != proof.o_local.o_pi_quotient {
-        bail!("Incorrect opening of the public inputs quotient polynomial.");
-    }
-
-    // Verify polynomial commitment openings.
     let pedersen_h = blake_hash_usize_to_curve(vk.degree);
     let u_curve = blake_hash_usize_to_curve(vk.degree + 1);
-    ensure!(
-        verify_all_ipas::<C>(
-            &vk.c_constants,
-            &vk.c_s_sigmas,
-            subgroup_generator_n,
-            u_curve,
-            pedersen_h,
-            &proof,
-            old_proofs,
-            challs.u,
-            challs.v,
-            challs.u_scaling,
-            challs.zeta,
-            &challs.halo_us,
-            challs.schnorr_challenge,
-            vk.security_bits,
-        ),
-        "Invalid IPA proof."
-    );
-
-    if verify_g {
-        let pedersen_g: Vec<_> = (0..vk.degree).map(blake_hash_usize_to_curve::<C>).collect();
-        let w = 8; // TODO: Should really be set dynamically based on MSM size.
-        let pedersen_g_msm_precomputation =
-            msm_precompute(&AffinePoint::batch_to_projective(&pedersen_g), w);
-
-        // Verify that `self.halo_g = <s, G>`.
-        if proof.halo_g
-            == pedersen_hash(&halo_s(&challs.halo_us), &pedersen_g_msm_precomputation).to_affine()
-        {
-            Ok(None)
-        } else {
-            bail!("Invalid G point.");
-        }
-    } else {
-        Ok(Some(OldProof {
-            halo_g: proof.halo_g,
-            halo_us: challs.halo_us,
-        }))
-    }
+    all_ipas_msm_terms::<C>(
+        &vk.c_constants,
+        &vk.c_s_sigmas,
+        subgroup_generator_n,
+        u_curve,
+        pedersen_h,
+        proof,
+        old_proofs,
+        challs.u,
+        challs.v,
+        challs.u_scaling,
+        challs.zeta,
+        &challs.halo_us,
+        challs.schnorr_challenge,
+        vk.challenge_len,
+    )
 }
 
-/// Verify all IPAs in the given proof using a reduction to a single polynomial.
+/// Reduces all IPAs in the given proof to a single one, and returns the terms of an MSM which is
+/// zero if and only if that IPA is valid.
 #[allow(clippy::too_many_arguments)]
-fn verify_all_ipas<C: HaloCurve>(
+fn all_ipas_msm_terms<C: HaloCurve>(
     c_constants: &[AffinePoint<C>],
     c_s_sigmas: &[AffinePoint<C>],
     subgroup_generator_n: C::ScalarField,
@@ -208,8 +695,8 @@ fn verify_all_ipas<C: HaloCurve>(
     zeta: C::ScalarField,
     halo_us: &[C::ScalarField],
     schnorr_challenge: C::ScalarField,
-    security_bits: usize,
-) -> bool {
+    challenge_len: usize,
+) -> (Vec<C::ScalarField>, Vec<ProjectivePoint<C>>) {
     // Reduce all polynomial commitments to a single one, i.e. a random combination of them.
     let c_all: Vec<AffinePoint<C>> = [
         c_constants,
@@ -224,7 +711,7 @@ fn verify_all_ipas<C: HaloCurve>(
     let powers_of_u = powers(u, c_all.len());
     let actual_scalars = powers_of_u
         .iter()
-        .map(|u_pow| halo_n::<C>(&u_pow.to_canonical_bool_vec()[..security_bits]))
+        .map(|u_pow| halo_n::<C>(&u_pow.to_canonical_bool_vec()[..challenge_len]))
         .collect::<Vec<_>>();
     let precomputation = msm_precompute(&AffinePoint::batch_to_projective(&c_all), 8);
     let c_reduction = msm_execute_parallel(&precomputation, &actual_scalars);
@@ -240,7 +727,7 @@ fn verify_all_ipas<C: HaloCurve>(
     let reduced_opening = reduce_with_powers(&opening_set_reductions, v);
 
     let u_prime =
-        halo_n_mul(&u_scaling.to_canonical_bool_vec()[..security_bits], u_curve).to_projective();
+        halo_n_mul(&u_scaling.to_canonical_bool_vec()[..challenge_len], u_curve).to_projective();
 
     let points = vec![
         zeta,
@@ -252,7 +739,7 @@ fn verify_all_ipas<C: HaloCurve>(
         .map(|&p| halo_g(p, &halo_us))
         .collect::<Vec<_>>();
     let halo_b = reduce_with_powers(&halo_bs, v);
-    verify_ipa::<C>(
+    ipa_msm_terms::<C>(
         &proof.halo_l,
         &proof.halo_r,
         proof.halo_g,
@@ -264,6 +751,7 @@ fn verify_all_ipas<C: HaloCurve>(
         pedersen_h,
         schnorr_challenge,
         proof.schnorr_proof,
+        challenge_len,
     )
 }
 
@@ -273,14 +761,17 @@ fn verify_old_proof_evaluation<C: HaloCurve>(
     proof: &Proof<C>,
     zeta: C::ScalarField,
 ) -> Result<()> {
-    if old_proofs.len() != proof.o_local.o_old_proofs.len() {
-        bail!("Incorrect number of old proofs opening.")
-    }
+    ensure!(
+        old_proofs.len() == proof.o_local.o_old_proofs.len(),
+        "Incorrect number of old proofs opening."
+    );
     for (i, p) in old_proofs.iter().enumerate() {
         // If the value `v` doesn't match the corresponding wire in the `PublicInputGate`, return false.
-        if halo_g(zeta, &p.halo_us) != proof.o_local.o_old_proofs[i] {
-            bail!("{}-th old proof opening is incorrect", i);
-        }
+        ensure!(
+            halo_g(zeta, &p.halo_us) == proof.o_local.o_old_proofs[i],
+            "{}-th old proof opening is incorrect",
+            i
+        );
     }
     Ok(())
 }
@@ -354,45 +845,70 @@ fn check_proof_parameters<C: HaloCurve>(proof: &Proof<C>) -> Result<()> {
     Ok(())
 }
 
-/// Reshapes a vector of field elements to a zero-padded matrix of height `NUM_WIRES`.
-fn public_inputs_to_wires_vec<F: Field>(public_inputs: &[F]) -> Vec<Vec<F>> {
-    let n = public_inputs.len();
-    let mut wires = (0..NUM_WIRES)
-        .map(|i| {
-            (i..n)
-                .step_by(NUM_WIRES)
-                .flat_map(|j| vec![public_inputs[j], F::ZERO])
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    if n % NUM_WIRES > 0 {
-        for i in n % NUM_WIRES..NUM_WIRES {
-            wires[i].extend(&[F::ZERO; 2]);
-        }
+/// Check that the proof has one wire commitment and opening per wire of the circuit, and one
+/// permutation polynomial opening per routed wire.
+fn check_proof_width<C: HaloCurve>(proof: &Proof<C>, vk: &VerificationKey<C>) -> Result<()> {
+    ensure!(
+        proof.c_wires.len() == vk.num_wires,
+        "Expected {} wire commitments, got {}.",
+        vk.num_wires,
+        proof.c_wires.len()
+    );
+    for opening_set in proof.all_opening_sets() {
+        ensure!(
+            opening_set.o_wires.len() == vk.num_wires,
+            "Expected {} wire openings, got {}.",
+            vk.num_wires,
+            opening_set.o_wires.len()
+        );
+        ensure!(
+            opening_set.o_plonk_sigmas.len() == vk.num_routed_wires,
+            "Expected {} permutation polynomial openings, got {}.",
+            vk.num_routed_wires,
+            opening_set.o_plonk_sigmas.len()
+        );
     }
-    wires
+    Ok(())
 }
 
-/// Returns the polynomial interpolating the (scaled) public input values at the `PublicInputGate`s.
-fn public_inputs_to_polynomial<F: Field>(
-    public_inputs: &[F],
+/// Evaluates at `zeta` the polynomial which is zero on the subgroup, except at the
+/// `PublicInputGate`s, where it interpolates the gates' public inputs combined with powers of
+/// `alpha`. Rather than interpolating it with an FFT, we sum the Lagrange basis polynomials of the
+/// public input gates, which takes constant memory.
+fn eval_public_inputs_polynomial<F: Field>(
+    public_inputs: impl Iterator<Item = F>,
     alpha: F,
+    zeta: F,
     degree: usize,
     num_gates_without_pis: usize,
-    fft_precomputation: Option<&FftPrecomputation<F>>,
-) -> Polynomial<F> {
-    let pis_wires_vec = public_inputs_to_wires_vec(public_inputs);
-    let mut scaled_wires_vec = vec![F::ZERO; num_gates_without_pis];
-    scaled_wires_vec.extend((0..pis_wires_vec[0].len())
-        .map(|i| {
-            (0..pis_wires_vec.len())
-                .map(|j| pis_wires_vec[j][i] * alpha.exp_usize(j))
-                .fold(F::ZERO, |acc, x| acc + x)
-        }));
-    scaled_wires_vec.resize(degree, F::ZERO);
-
-    fft_precomputation.map_or_else(
-        || Polynomial::from_evaluations(&scaled_wires_vec, &fft_precompute(degree)),
-        |precomp| Polynomial::from_evaluations(&scaled_wires_vec, precomp),
-    )
+) -> F {
+    let generator = F::primitive_root_of_unity(log2_strict(degree));
+    let generator_squared = generator.square();
+    let alpha_powers = powers(alpha, NUM_WIRES);
+
+    // The basis polynomial of the gate at `g^r` is `L_r(X) = g^r (X^n - 1) / (n (X - g^r))`. We
+    // accumulate the sum of `s_r g^r / (zeta - g^r)` as a single fraction, so that we only invert
+    // once.
+    let mut numerator = F::ZERO;
+    let mut denominator = F::ONE;
+    let mut point = generator.exp_usize(num_gates_without_pis);
+    let mut public_inputs = public_inputs.peekable();
+    while public_inputs.peek().is_some() {
+        let s = public_inputs
+            .by_ref()
+            .take(NUM_WIRES)
+            .zip(&alpha_powers)
+            .fold(F::ZERO, |acc, (pi, &a)| acc + pi * a);
+        let diff = zeta - point;
+        if diff.is_zero() {
+            // All other basis polynomials vanish at this gate's point.
+            return s;
+        }
+        numerator = numerator * diff + s * point * denominator;
+        denominator = denominator * diff;
+        // Public input gates are interleaved with `BufferGate`s.
+        point = point * generator_squared;
+    }
+
+    (zeta.exp_usize(degree) - F::ONE) * numerator / (F::from_canonical_usize(degree) * denominator)
 }
@@ -1,9 +1,14 @@
 use crate::util::transpose;
-use crate::{biguint_to_field, biguint_to_limbs, field_to_biguint, AffinePoint, AffinePointTarget, BigIntTarget, Curve, Field, ForeignFieldTarget, OrderingTarget, Target, Wire, LIMB_BITS, NUM_WIRES, NUM_ADVICE_WIRES, NUM_ROUTED_WIRES};
+use crate::{biguint_to_field, biguint_to_limbs, field_to_biguint, AffinePoint, AffinePointTarget, BigIntTarget, Curve, Field, ForeignFieldTarget, OrderingTarget, PlonkyError, Target, Wire, LIMB_BITS, NUM_ADVICE_WIRES, NUM_ROUTED_WIRES};
+use anyhow::Result;
 use num::{BigUint, Zero};
+use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, collections::HashMap};
 
-#[derive(Debug)]
+/// A partial assignment of values to targets. Partial witnesses can be serialized, so that the
+/// inputs which led to a witness generation failure can be saved and replayed later.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct PartialWitness<F: Field> {
     wire_values: HashMap<Target<F>, F>,
 }
@@ -45,6 +50,11 @@ impl<F: Field> PartialWitness<F> {
         self.wire_values[&target]
     }
 
+    /// Returns the value of the given target, or `None` if it has not been set.
+    pub fn try_get_target(&self, target: Target<F>) -> Option<F> {
+        self.wire_values.get(&target).copied()
+    }
+
     pub fn get_targets(&self, targets: &[Target<F>]) -> Vec<F> {
         targets.iter().map(|&t| self.get_target(t)).collect()
     }
@@ -125,14 +135,32 @@ impl<F: Field> PartialWitness<F> {
         self.get_target(Target::Wire(wire))
     }
 
+    /// Returns the value of the given wire, or `None` if it has not been set.
+    pub fn try_get_wire(&self, wire: Wire) -> Option<F> {
+        self.try_get_target(Target::Wire(wire))
+    }
+
+    /// Sets the value of the given target. Setting a target again with the same value is a no-op,
+    /// but setting it to a different value is an error, and the existing value is kept.
+    pub fn try_set_target(&mut self, target: Target<F>, value: F) -> Result<()> {
+        if let Some(&old_value) = self.wire_values.get(&target) {
+            if old_value != value {
+                return Err(PlonkyError::ConflictingValues(format!(
+                    "Target {:?} was set twice with different values: {:?} and {:?}",
+                    target, old_value, value
+                ))
+                .into());
+            }
+            return Ok(());
+        }
+        self.wire_values.insert(target, value);
+        Ok(())
+    }
+
+    /// Like `try_set_target`, but panics if the target was already set to a different value.
     pub fn set_target(&mut self, target: Target<F>, value: F) {
-        let opt_old_value = self.wire_values.insert(target, value);
-        if let Some(old_value) = opt_old_value {
-            debug_assert_eq!(
-                old_value, value,
-                "Target {:?} was set twice with different values",
-                target
-            );
+        if let Err(e) = self.try_set_target(target, value) {
+            panic!("{}", e);
         }
     }
 
@@ -165,13 +193,26 @@ impl<F: Field> PartialWitness<F> {
             .for_each(|(&point_target, &point)| self.set_point_target(point_target, point))
     }
 
+    pub fn try_set_wire(&mut self, wire: Wire, value: F) -> Result<()> {
+        self.try_set_target(Target::Wire(wire), value)
+    }
+
     pub fn set_wire(&mut self, wire: Wire, value: F) {
         self.set_target(Target::Wire(wire), value);
     }
 
-    pub fn extend(&mut self, other: PartialWitness<F>) {
+    /// Adds all values from `other`, failing on the first target which `other` sets to a value
+    /// different from the one already present.
+    pub fn try_extend(&mut self, other: PartialWitness<F>) -> Result<()> {
         for (target, value) in other.wire_values {
-            self.set_target(target, value);
+            self.try_set_target(target, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn extend(&mut self, other: PartialWitness<F>) {
+        if let Err(e) = self.try_extend(other) {
+            panic!("{}", e);
         }
     }
 
@@ -206,7 +247,10 @@ impl<F: Field> PartialWitness<F> {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A full assignment of values to every wire of the circuit. A witness can be serialized so that it
+/// can be generated on one machine and proven on another.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Witness<F: Field> {
     wire_values: Vec<Vec<F>>,
 }
@@ -230,11 +274,11 @@ impl<F: Field> Witness<F> {
 
     /// Converts a `PartialWitness` to a a `Witness`.
     /// The partial witness should be sufficiently preprocessed, e.g., it should contain copy constraints.
-    pub fn from_partial(pw: &PartialWitness<F>, degree: usize) -> Self {
+    pub fn from_partial(pw: &PartialWitness<F>, degree: usize, num_wires: usize) -> Self {
         let mut wire_values: Vec<Vec<F>> = Vec::new();
         for i in 0..degree {
             let mut gate_i_wires = Vec::new();
-            for j in 0..NUM_WIRES {
+            for j in 0..num_wires {
                 let wire = Wire { gate: i, input: j };
                 let value = if pw.contains_wire(wire) {
                     pw.get_wire(wire)
@@ -253,6 +297,47 @@ impl<F: Field> Witness<F> {
 pub trait WitnessGenerator<F: Field>: 'static + Sync {
     fn dependencies(&self) -> Vec<Target<F>>;
 
+    /// A name for this generator, used in error messages.
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
     /// Given a partial witness, return any newly generated values. The caller will merge them in.
     fn generate(&self, constants: &[Vec<F>], witness: &PartialWitness<F>) -> PartialWitness<F>;
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{Curve, Field, PartialWitness, PlonkyError, Tweedledee, Wire};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    #[test]
+    fn test_set_wire_conflict() {
+        let wire = Wire { gate: 3, input: 1 };
+        let mut witness = PartialWitness::new();
+        assert!(!witness.contains_wire(wire));
+        assert_eq!(witness.try_get_wire(wire), None);
+
+        witness.set_wire(wire, F::ONE);
+        assert_eq!(witness.try_get_wire(wire), Some(F::ONE));
+
+        // Setting the same value again is fine, but a different value is rejected.
+        assert!(witness.try_set_wire(wire, F::ONE).is_ok());
+        let err = witness.try_set_wire(wire, F::TWO).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PlonkyError>(),
+            Some(PlonkyError::ConflictingValues(_))
+        ));
+        assert_eq!(witness.get_wire(wire), F::ONE);
+    }
+
+    #[test]
+    #[should_panic(expected = "set twice with different values")]
+    fn test_set_wire_conflict_panics() {
+        let wire = Wire { gate: 0, input: 0 };
+        let mut witness = PartialWitness::<F>::new();
+        witness.set_wire(wire, F::ONE);
+        witness.set_wire(wire, F::TWO);
+    }
+}
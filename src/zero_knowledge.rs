@@ -0,0 +1,196 @@
+//! Test utilities which guard against regressions in blinding. They prove one statement many
+//! times, with different witnesses and fresh randomness, and apply simple statistical tests to the
+//! proofs, checking that the elements which depend on the witness look like fresh random values.
+//!
+//! These tests can't show that proofs are zero-knowledge, but a missing or reused blinding factor,
+//! or an opening which reveals a wire value, makes them fail with overwhelming probability.
+
+use crate::{Circuit, Field, HaloCurve, PartialWitness, Proof};
+
+/// The number of standard deviations from its expected value at which a count is rejected. A
+/// sound implementation fails with probability below 10^-8 per test.
+const MAX_DEVIATION: f64 = 6.0;
+
+/// The number of low bits of each element which the bit tests use. The bits of an element below
+/// the modulus are close to uniform, except for the highest ones.
+const TESTED_BITS: usize = 64;
+
+/// The elements of a proof which should look random when the circuit is zero-knowledge, each
+/// reduced to its lowest 64 bits. Points are represented by their x coordinates.
+///
+/// Openings of the constant and sigma polynomials, and of the public input quotient, depend only
+/// on the circuit, the public inputs and the challenges, so they're omitted. So are openings of the
+/// quotient chunks which are zero at every opening point: those chunks lie beyond the quotient's
+/// degree, so they're the zero polynomial, whose openings reveal nothing. A nonzero chunk vanishes
+/// at all the random opening points only with negligible probability.
+pub(crate) fn witness_dependent_elements<C: HaloCurve>(proof: &Proof<C>) -> Vec<u64> {
+    let mut points = proof.c_wires.clone();
+    points.push(proof.c_plonk_z);
+    points.extend(&proof.c_plonk_t);
+    points.extend(&proof.halo_l);
+    points.extend(&proof.halo_r);
+    points.push(proof.halo_g);
+    points.push(proof.schnorr_proof.r);
+
+    let opening_sets = proof.all_opening_sets();
+    let is_zero_chunk = |i: usize| opening_sets.iter().all(|o| o.o_plonk_t[i].is_zero());
+    let mut scalars = Vec::new();
+    for opening_set in &opening_sets {
+        scalars.extend(&opening_set.o_wires);
+        scalars.push(opening_set.o_plonk_z);
+        for (i, &o_plonk_t) in opening_set.o_plonk_t.iter().enumerate() {
+            if !is_zero_chunk(i) {
+                scalars.push(o_plonk_t);
+            }
+        }
+    }
+    scalars.push(proof.schnorr_proof.z1);
+    scalars.push(proof.schnorr_proof.z2);
+
+    points
+        .iter()
+        .map(|p| p.x.to_canonical_u64_vec()[0])
+        .chain(scalars.iter().map(|x| x.to_canonical_u64_vec()[0]))
+        .collect()
+}
+
+/// Generates a witness from each of the given inputs, which should all satisfy the same
+/// statement, and proves it, returning the witness-dependent elements of each proof.
+pub(crate) fn sample_proofs<C: HaloCurve, InnerC: HaloCurve<BaseField = C::ScalarField>>(
+    circuit: &Circuit<C>,
+    inputs: &[PartialWitness<C::ScalarField>],
+) -> Vec<Vec<u64>> {
+    inputs
+        .iter()
+        .map(|inputs| {
+            let witness = circuit
+                .generate_witness(inputs.clone())
+                .expect("Witness generation should succeed");
+            let proof = circuit
+                .generate_proof::<InnerC>(&witness, &[])
+                .expect("Proving should succeed");
+            witness_dependent_elements(&proof)
+        })
+        .collect()
+}
+
+/// Asserts that `count` successes out of `trials` are consistent with a success probability of
+/// one half.
+fn assert_balanced(count: usize, trials: usize, what: &str) {
+    let expected = trials as f64 / 2.0;
+    let std_dev = (trials as f64 / 4.0).sqrt();
+    assert!(
+        (count as f64 - expected).abs() <= MAX_DEVIATION * std_dev,
+        "{}: {} ones out of {} bits",
+        what,
+        count,
+        trials
+    );
+}
+
+/// Asserts that the elements at each position of the samples never repeat, so no element is
+/// fixed, or determined by a witness which repeats among the samples.
+pub(crate) fn assert_no_repeats(samples: &[Vec<u64>]) {
+    let num_elements = samples[0].len();
+    assert!(samples.iter().all(|s| s.len() == num_elements), "Proofs have different shapes");
+    for i in 0..num_elements {
+        let mut values = samples.iter().map(|s| s[i]).collect::<Vec<_>>();
+        values.sort_unstable();
+        values.dedup();
+        assert_eq!(values.len(), samples.len(), "Element {} repeats across proofs", i);
+    }
+}
+
+/// Asserts that the bits of the elements are balanced, both overall and at each bit position.
+pub(crate) fn assert_bits_balanced(samples: &[Vec<u64>]) {
+    let elements = samples.iter().flatten().copied().collect::<Vec<_>>();
+    let ones = elements.iter().map(|x| x.count_ones() as usize).sum();
+    assert_balanced(ones, elements.len() * TESTED_BITS, "All bits");
+    for bit in 0..TESTED_BITS {
+        let ones = elements.iter().filter(|&&x| (x >> bit) & 1 == 1).count();
+        assert_balanced(ones, elements.len(), &format!("Bit {}", bit));
+    }
+}
+
+/// Asserts that proofs of two different witnesses are indistinguishable, by comparing the number
+/// of ones among the bits at each position of the proofs. With `n` proofs of each witness, the
+/// difference of the counts has mean zero and variance `2n * 64 / 4`.
+pub(crate) fn assert_indistinguishable(a: &[Vec<u64>], b: &[Vec<u64>]) {
+    assert_eq!(a.len(), b.len(), "The samples should have the same size");
+    let std_dev = ((a.len() + b.len()) as f64 * TESTED_BITS as f64 / 4.0).sqrt();
+    let count_ones = |samples: &[Vec<u64>], i: usize| -> i64 {
+        samples.iter().map(|s| s[i].count_ones() as i64).sum()
+    };
+    for i in 0..a[0].len() {
+        let difference = count_ones(a, i) - count_ones(b, i);
+        assert!(
+            (difference as f64).abs() <= MAX_DEVIATION * std_dev,
+            "Element {} distinguishes the witnesses: its bits have {} more ones",
+            i,
+            difference
+        );
+    }
+}
+
+/// Proves one statement with each of the two witnesses `num_proofs` times, and asserts that the
+/// proofs pass each of the statistical tests.
+pub(crate) fn assert_proofs_look_random<
+    C: HaloCurve,
+    InnerC: HaloCurve<BaseField = C::ScalarField>,
+>(
+    circuit: &Circuit<C>,
+    inputs_a: PartialWitness<C::ScalarField>,
+    inputs_b: PartialWitness<C::ScalarField>,
+    num_proofs: usize,
+) {
+    let a = sample_proofs::<C, InnerC>(circuit, &vec![inputs_a; num_proofs]);
+    let b = sample_proofs::<C, InnerC>(circuit, &vec![inputs_b; num_proofs]);
+    let all = [a.clone(), b.clone()].concat();
+    assert_no_repeats(&all);
+    assert_bits_balanced(&all);
+    assert_indistinguishable(&a, &b);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::zero_knowledge::{assert_no_repeats, assert_proofs_look_random, sample_proofs};
+    use crate::{Circuit, CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// A circuit proving knowledge of factors of its public input, along with two factorizations
+    /// of 35.
+    fn factoring_circuit(zero_knowledge: bool) -> (Circuit<Tweedledee>, PartialWitness<F>, PartialWitness<F>) {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        builder.set_zero_knowledge(zero_knowledge);
+        let pi = builder.add_public_input();
+        let x = builder.add_virtual_target();
+        let y = builder.add_virtual_target();
+        let product = builder.mul(x, y);
+        builder.copy(product, pi);
+        let circuit = builder.build();
+
+        let factors = |a: usize, b: usize| {
+            let mut inputs = PartialWitness::new();
+            inputs.set_targets(&[x, y], &[F::from_canonical_usize(a), F::from_canonical_usize(b)]);
+            inputs
+        };
+        (circuit, factors(5, 7), factors(1, 35))
+    }
+
+    #[test]
+    fn test_proofs_look_random() {
+        let (circuit, inputs_a, inputs_b) = factoring_circuit(true);
+        assert_proofs_look_random::<Tweedledee, Tweedledum>(&circuit, inputs_a, inputs_b, 16);
+    }
+
+    /// Without blinding, the wire commitments are determined by the witness, so proofs of the same
+    /// witness share them.
+    #[test]
+    fn test_unblinded_proofs_detected() {
+        let (circuit, inputs, _) = factoring_circuit(false);
+        let samples = sample_proofs::<Tweedledee, Tweedledum>(&circuit, &vec![inputs; 4]);
+        let result = std::panic::catch_unwind(|| assert_no_repeats(&samples));
+        assert!(result.is_err(), "Proofs without blinding passed the tests");
+    }
+}
@@ -0,0 +1,505 @@
+//! Import and export of circuits in the zkInterface format, so that existing frontends can drive
+//! this backend. The message types mirror the owned messages of the `zkinterface` crate field for
+//! field, so they have the same serde encodings, and can be converted to its FlatBuffers messages.
+//!
+//! zkInterface describes rank-1 constraint systems, in which each constraint has the form
+//! `<A, x> * <B, x> = <C, x>`. Variable 0 is the constant one, the instance variables listed in the
+//! circuit header are public inputs, and all other variables are private. Imported systems are
+//! synthesized with `R1csCircuit`.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::gates::{ArithmeticGate, BufferGate, ConstantGate, Gate, PublicInputGate};
+use crate::util::ceil_div_usize;
+use crate::{Circuit, Field, HaloCurve, LinearCombination, PartialWitness, PublicInput, R1cs, R1csCircuit, Target, Wire, NUM_ADVICE_WIRES, NUM_ROUTED_WIRES};
+
+/// A list of variables, along with a value for each of them. Values are encoded as little-endian
+/// integers of equal length, one after the other.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Variables {
+    pub variable_ids: Vec<u64>,
+    pub values: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CircuitHeader {
+    /// The public inputs, and optionally their values.
+    pub instance_variables: Variables,
+    /// The smallest variable ID which is not used by the circuit.
+    pub free_variable_id: u64,
+    /// The largest element of the field, i.e. its order minus one.
+    pub field_maximum: Option<Vec<u8>>,
+}
+
+/// A constraint `<A, x> * <B, x> = <C, x>`. The values of each linear combination are its
+/// coefficients.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BilinearConstraint {
+    pub linear_combination_a: Variables,
+    pub linear_combination_b: Variables,
+    pub linear_combination_c: Variables,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ConstraintSystem {
+    pub constraints: Vec<BilinearConstraint>,
+}
+
+/// The values of the private variables.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Witness {
+    pub assigned_variables: Variables,
+}
+
+impl Variables {
+    /// Encodes the given variables and values.
+    pub fn new<F: Field>(variable_ids: Vec<u64>, values: &[F]) -> Self {
+        debug_assert_eq!(variable_ids.len(), values.len());
+        Variables {
+            variable_ids,
+            values: Some(values.iter().flat_map(|x| x.to_canonical_u8_vec()).collect()),
+        }
+    }
+
+    /// Decodes the values of the variables, which must be present.
+    pub fn get_values<F: Field>(&self) -> Result<Vec<F>> {
+        let values = match &self.values {
+            Some(values) => values,
+            None => bail!("Values are missing"),
+        };
+        if self.variable_ids.is_empty() {
+            ensure!(values.is_empty(), "Values given for no variables");
+            return Ok(Vec::new());
+        }
+        ensure!(
+            values.len() % self.variable_ids.len() == 0,
+            "{} bytes of values for {} variables",
+            values.len(),
+            self.variable_ids.len()
+        );
+        let value_len = values.len() / self.variable_ids.len();
+        values.chunks(value_len).map(field_from_le_bytes).collect()
+    }
+
+    /// Decodes the variables along with their values. A missing list of values is treated as all
+    /// ones, which is a common shorthand in linear combinations.
+    fn get_terms<F: Field>(&self) -> Result<Vec<(u64, F)>> {
+        let values = match self.values {
+            Some(_) => self.get_values()?,
+            None => vec![F::ONE; self.variable_ids.len()],
+        };
+        Ok(self.variable_ids.iter().copied().zip(values).collect())
+    }
+}
+
+/// Decodes a little-endian integer of any length, which must be less than the field's order.
+pub(crate) fn field_from_le_bytes<F: Field>(bytes: &[u8]) -> Result<F> {
+    let num_bytes = ceil_div_usize(F::BITS, 64) * 8;
+    ensure!(
+        bytes.iter().skip(num_bytes).all(|&b| b == 0),
+        "Value is out of range"
+    );
+    let mut bytes = bytes[..bytes.len().min(num_bytes)].to_vec();
+    bytes.resize(num_bytes, 0);
+    F::from_canonical_u8_vec(bytes)
+}
+
+/// A circuit imported from a zkInterface constraint system.
+pub struct ZkifCircuit<C: HaloCurve> {
+    pub circuit: Circuit<C>,
+    /// The target of each variable which is an instance variable or appears in a constraint.
+    pub variables: HashMap<u64, Target<C::ScalarField>>,
+}
+
+impl<C: HaloCurve> ZkifCircuit<C> {
+    /// Builds a circuit which enforces the given constraints. The instance variables become the
+    /// circuit's public inputs, in the order in which the header lists them.
+    pub fn import(
+        header: &CircuitHeader,
+        constraint_system: &ConstraintSystem,
+        security_bits: usize,
+    ) -> Result<Self> {
+        if let Some(field_maximum) = &header.field_maximum {
+            ensure!(
+                field_from_le_bytes::<C::ScalarField>(field_maximum).ok()
+                    == Some(C::ScalarField::NEG_ONE),
+                "The circuit is over a different field"
+            );
+        }
+
+        // Number the variables as R1CS expects: the constant one, then the instance variables,
+        // then the remaining variables in order of appearance.
+        let mut indices = HashMap::new();
+        indices.insert(0, 0);
+        let instance_ids = &header.instance_variables.variable_ids;
+        for &id in instance_ids {
+            ensure!(id != 0, "Variable 0 is the constant one, not an instance variable");
+            let index = indices.len();
+            ensure!(
+                indices.insert(id, index).is_none(),
+                "Instance variable {} is listed twice",
+                id
+            );
+        }
+
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        let mut c = Vec::new();
+        for constraint in &constraint_system.constraints {
+            a.push(import_linear_combination(&mut indices, &constraint.linear_combination_a)?);
+            b.push(import_linear_combination(&mut indices, &constraint.linear_combination_b)?);
+            c.push(import_linear_combination(&mut indices, &constraint.linear_combination_c)?);
+        }
+
+        for &id in indices.keys() {
+            ensure!(
+                id < header.free_variable_id,
+                "Variable {} is not below the free variable ID",
+                id
+            );
+        }
+
+        let r1cs = R1cs {
+            num_public_inputs: instance_ids.len(),
+            num_private_inputs: indices.len() - 1 - instance_ids.len(),
+            a,
+            b,
+            c,
+        };
+        let r1cs_circuit = R1csCircuit::new(&r1cs, security_bits)?;
+        let variables = indices
+            .into_iter()
+            .map(|(id, index)| (id, r1cs_circuit.variables[index]))
+            .collect();
+        Ok(ZkifCircuit {
+            circuit: r1cs_circuit.circuit,
+            variables,
+        })
+    }
+
+    /// Returns the inputs of witness generation, given the values of the instance variables in the
+    /// header and the values of the private variables. Values of variables which don't appear in
+    /// the circuit are ignored.
+    pub fn partial_witness(
+        &self,
+        header: &CircuitHeader,
+        witness: &Witness,
+    ) -> Result<PartialWitness<C::ScalarField>> {
+        let mut partial_witness = PartialWitness::new();
+        for variables in &[&header.instance_variables, &witness.assigned_variables] {
+            let values = variables.get_values::<C::ScalarField>()?;
+            for (id, value) in variables.variable_ids.iter().zip(values) {
+                if let Some(&target) = self.variables.get(id) {
+                    ensure!(*id != 0, "Variable 0 is the constant one, and can't be assigned");
+                    partial_witness.try_set_target(target, value)?;
+                }
+            }
+        }
+        Ok(partial_witness)
+    }
+}
+
+/// Decodes a linear combination, numbering any new variables after the existing ones.
+fn import_linear_combination<F: Field>(
+    indices: &mut HashMap<u64, usize>,
+    linear_combination: &Variables,
+) -> Result<LinearCombination<F>> {
+    let terms = linear_combination.get_terms::<F>()?;
+    Ok(terms
+        .into_iter()
+        .map(|(id, coefficient)| {
+            let next_index = indices.len();
+            (*indices.entry(id).or_insert(next_index), coefficient)
+        })
+        .collect())
+}
+
+/// Exports a circuit as a zkInterface constraint system. Each class of copy-constrained wires
+/// becomes a variable, and the public inputs become the instance variables `1..=n`. If a witness is
+/// given, the header and the returned `Witness` include the values of the variables.
+///
+/// Only circuits made of arithmetic, constant, buffer and public input gates can be exported,
+/// since other gates have constraints of higher degree.
+pub fn export_zkinterface<C: HaloCurve>(
+    circuit: &Circuit<C>,
+    witness: Option<&crate::Witness<C::ScalarField>>,
+) -> Result<(CircuitHeader, ConstraintSystem, Option<Witness>)> {
+    let prover_key = &circuit.prover_key;
+    let num_gates = prover_key.gate_constants.len();
+    let partitions = &circuit.routing_target_partitions;
+
+    // Assign an ID to each class of wires, starting with the public inputs.
+    let mut class_ids: HashMap<Wire, u64> = HashMap::new();
+    let mut constraints = Vec::new();
+    let mut next_id = 1;
+    let public_input_wires = (0..prover_key.num_public_inputs)
+        .map(|i| {
+            PublicInput::<C::ScalarField>::new(i).original_wire(prover_key.num_gates_without_pis)
+        })
+        .collect::<Vec<_>>();
+    for &wire in &public_input_wires {
+        let id = next_id;
+        next_id += 1;
+        // A public input may have been copied to another one, in which case the two variables must
+        // be equal.
+        if let Some(&other_id) = class_ids.get(&wire) {
+            constraints.push(equality_constraint::<C::ScalarField>(id, other_id));
+        } else {
+            for member in partitions.get_partition(Target::Wire(wire)) {
+                if let Target::Wire(w) = member {
+                    class_ids.insert(*w, id);
+                }
+            }
+        }
+    }
+    let mut variable = |wire: Wire| -> u64 {
+        if let Some(&id) = class_ids.get(&wire) {
+            return id;
+        }
+        let id = next_id;
+        next_id += 1;
+        for member in partitions.get_partition(Target::Wire(wire)) {
+            if let Target::Wire(w) = member {
+                class_ids.insert(*w, id);
+            }
+        }
+        id
+    };
+
+    for (gate, constants) in prover_key.gate_constants.iter().enumerate() {
+        let wire = |input| Wire { gate, input };
+        if has_prefix::<C, ArithmeticGate<C>>(constants) {
+            let prefix_len = ArithmeticGate::<C>::PREFIX.len();
+            let (const_0, const_1) = (constants[prefix_len], constants[prefix_len + 1]);
+            let multiplicand_0 = variable(wire(ArithmeticGate::<C>::WIRE_MULTIPLICAND_0));
+            let multiplicand_1 = variable(wire(ArithmeticGate::<C>::WIRE_MULTIPLICAND_1));
+            let addend = variable(wire(ArithmeticGate::<C>::WIRE_ADDEND));
+            let output = variable(wire(ArithmeticGate::<C>::WIRE_OUTPUT));
+            // const_0 * multiplicand_0 * multiplicand_1 = output - const_1 * addend
+            constraints.push(BilinearConstraint {
+                linear_combination_a: Variables::new(vec![multiplicand_0], &[const_0]),
+                linear_combination_b: Variables::new(vec![multiplicand_1], &[C::ScalarField::ONE]),
+                linear_combination_c: Variables::new(
+                    vec![output, addend],
+                    &[C::ScalarField::ONE, -const_1],
+                ),
+            });
+        } else if has_prefix::<C, ConstantGate<C>>(constants) {
+            let c = constants[ConstantGate::<C>::PREFIX.len()];
+            let output = variable(wire(ConstantGate::<C>::WIRE_OUTPUT));
+            constraints.push(BilinearConstraint {
+                linear_combination_a: Variables::new(vec![0], &[C::ScalarField::ONE]),
+                linear_combination_b: Variables::new(vec![0], &[c]),
+                linear_combination_c: Variables::new(vec![output], &[C::ScalarField::ONE]),
+            });
+        } else if has_prefix::<C, PublicInputGate<C>>(constants) {
+            ensure!(gate + 1 < num_gates, "A public input gate must be followed by a buffer gate");
+            for i in 0..NUM_ADVICE_WIRES {
+                let advice = variable(wire(NUM_ROUTED_WIRES + i));
+                let copy = variable(Wire { gate: gate + 1, input: i });
+                constraints.push(equality_constraint::<C::ScalarField>(advice, copy));
+            }
+        } else if !has_prefix::<C, BufferGate<C>>(constants) {
+            bail!(
+                "Gate {} cannot be expressed as rank-1 constraints; only circuits made of \
+                 arithmetic, constant, buffer and public input gates can be exported",
+                gate
+            );
+        }
+    }
+
+    let field_maximum = Some(C::ScalarField::NEG_ONE.to_canonical_u8_vec());
+    let instance_ids = (1..=prover_key.num_public_inputs as u64).collect::<Vec<_>>();
+    let mut header = CircuitHeader {
+        instance_variables: Variables {
+            variable_ids: instance_ids.clone(),
+            values: None,
+        },
+        free_variable_id: next_id,
+        field_maximum,
+    };
+
+    let witness = witness.map(|witness| {
+        let public_inputs = public_input_wires
+            .iter()
+            .map(|&w| witness.get(w))
+            .collect::<Vec<_>>();
+        header.instance_variables = Variables::new(instance_ids, &public_inputs);
+
+        let mut private_values = HashMap::new();
+        for (&wire, &id) in &class_ids {
+            if id > prover_key.num_public_inputs as u64 {
+                private_values.insert(id, witness.get(wire));
+            }
+        }
+        let mut private_ids = private_values.keys().copied().collect::<Vec<_>>();
+        private_ids.sort_unstable();
+        let values = private_ids
+            .iter()
+            .map(|id| private_values[id])
+            .collect::<Vec<_>>();
+        Witness {
+            assigned_variables: Variables::new(private_ids, &values),
+        }
+    });
+
+    Ok((header, ConstraintSystem { constraints }, witness))
+}
+
+/// Returns whether a gate's constants start with the prefix of the gate type `G`.
+fn has_prefix<C: HaloCurve, G: Gate<C>>(constants: &[C::ScalarField]) -> bool {
+    G::PREFIX
+        .iter()
+        .zip(constants)
+        .all(|(&bit, &c)| c == C::ScalarField::from_canonical_bool(bit))
+}
+
+/// The constraint `1 * x = y`.
+fn equality_constraint<F: Field>(x: u64, y: u64) -> BilinearConstraint {
+    BilinearConstraint {
+        linear_combination_a: Variables::new(vec![0], &[F::ONE]),
+        linear_combination_b: Variables::new(vec![x], &[F::ONE]),
+        linear_combination_c: Variables::new(vec![y], &[F::ONE]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::zkinterface::{export_zkinterface, BilinearConstraint, CircuitHeader, ConstraintSystem, Variables, Witness, ZkifCircuit};
+    use crate::{verify_proof, CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum};
+
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    /// The constraint system for `x^3 + x + 5 = out`, with `out` public. Variables: 1 is `out`, 2 is
+    /// `x`, 3 is `x^2` and 4 is `x^3`.
+    fn cubic() -> (CircuitHeader, ConstraintSystem) {
+        let lc = |ids: Vec<u64>, coeffs: &[F]| Variables::new(ids, coeffs);
+        let constraints = vec![
+            BilinearConstraint {
+                linear_combination_a: lc(vec![2], &[F::ONE]),
+                linear_combination_b: lc(vec![2], &[F::ONE]),
+                linear_combination_c: lc(vec![3], &[F::ONE]),
+            },
+            BilinearConstraint {
+                linear_combination_a: lc(vec![3], &[F::ONE]),
+                linear_combination_b: lc(vec![2], &[F::ONE]),
+                linear_combination_c: lc(vec![4], &[F::ONE]),
+            },
+            BilinearConstraint {
+                linear_combination_a: lc(vec![4, 2, 0], &[F::ONE, F::ONE, F::FIVE]),
+                linear_combination_b: lc(vec![0], &[F::ONE]),
+                linear_combination_c: lc(vec![1], &[F::ONE]),
+            },
+        ];
+        let header = CircuitHeader {
+            instance_variables: Variables {
+                variable_ids: vec![1],
+                values: None,
+            },
+            free_variable_id: 5,
+            field_maximum: Some(F::NEG_ONE.to_canonical_u8_vec()),
+        };
+        (header, ConstraintSystem { constraints })
+    }
+
+    fn cubic_values(x: u64) -> (CircuitHeader, Witness) {
+        let (mut header, _) = cubic();
+        let x = F::from_canonical_u64(x);
+        header.instance_variables = Variables::new(vec![1], &[x.cube() + x + F::FIVE]);
+        let witness = Witness {
+            assigned_variables: Variables::new(vec![2, 3, 4], &[x, x.square(), x.cube()]),
+        };
+        (header, witness)
+    }
+
+    #[test]
+    fn test_import() -> Result<()> {
+        let (header, constraint_system) = cubic();
+        let zkif_circuit = ZkifCircuit::<Tweedledee>::import(&header, &constraint_system, 128)?;
+        let circuit = &zkif_circuit.circuit;
+
+        let (header_values, witness) = cubic_values(3);
+        let inputs = zkif_circuit.partial_witness(&header_values, &witness)?;
+        let witness = circuit.generate_witness(inputs)?;
+        let public_inputs = circuit.get_public_inputs(&witness);
+        assert_eq!(public_inputs, vec![F::from_canonical_u64(35)]);
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+
+        // A wrong public input is inconsistent with the private values.
+        let (mut wrong_header, witness) = cubic_values(3);
+        wrong_header.instance_variables = Variables::new(vec![1], &[F::from_canonical_u64(36)]);
+        let inputs = zkif_circuit.partial_witness(&wrong_header, &witness)?;
+        assert!(circuit.generate_witness(inputs).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_rejects_malformed() {
+        let (mut header, constraint_system) = cubic();
+        header.field_maximum = Some(vec![1]);
+        assert!(ZkifCircuit::<Tweedledee>::import(&header, &constraint_system, 128).is_err());
+
+        let (mut header, constraint_system) = cubic();
+        header.free_variable_id = 4;
+        assert!(ZkifCircuit::<Tweedledee>::import(&header, &constraint_system, 128).is_err());
+
+        let (header, mut constraint_system) = cubic();
+        constraint_system.constraints[0].linear_combination_a.values = Some(vec![0xFF; 32]);
+        assert!(ZkifCircuit::<Tweedledee>::import(&header, &constraint_system, 128).is_err());
+    }
+
+    /// Exports a circuit, then checks that the exported witness satisfies the exported constraints.
+    #[test]
+    fn test_export() -> Result<()> {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_public_input();
+        let y = builder.add_virtual_target();
+        let x_squared = builder.square(x);
+        let sum = builder.add(x_squared, y);
+        let three = builder.constant_wire(F::THREE);
+        let out = builder.mul(sum, three);
+        let out_pi = builder.add_public_input();
+        builder.copy(out, out_pi);
+        let circuit = builder.build();
+
+        let mut inputs = PartialWitness::new();
+        inputs.set_target(x, F::FIVE);
+        inputs.set_target(y, F::TWO);
+        let witness = circuit.generate_witness(inputs)?;
+
+        let (header, constraint_system, zkif_witness) =
+            export_zkinterface(&circuit, Some(&witness))?;
+        let zkif_witness = zkif_witness.unwrap();
+        assert_eq!(header.instance_variables.variable_ids, vec![1, 2]);
+        assert_eq!(
+            header.instance_variables.get_values::<F>()?,
+            vec![F::FIVE, F::from_canonical_u64(81)]
+        );
+
+        // Re-importing the exported system gives a circuit which accepts the exported witness.
+        let zkif_circuit = ZkifCircuit::<Tweedledum>::import(&header, &constraint_system, 128);
+        assert!(zkif_circuit.is_err(), "The exported system is over Tweedledee's scalar field");
+        let zkif_circuit = ZkifCircuit::<Tweedledee>::import(&header, &constraint_system, 128)?;
+        let inputs = zkif_circuit.partial_witness(&header, &zkif_witness)?;
+        let reimported_witness = zkif_circuit.circuit.generate_witness(inputs)?;
+        assert_eq!(
+            zkif_circuit.circuit.get_public_inputs(&reimported_witness),
+            circuit.get_public_inputs(&witness)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_rejects_other_gates() {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let x = builder.add_public_input();
+        builder.rescue_hash_n_to_1(&[x]);
+        assert!(export_zkinterface(&builder.build(), None).is_err());
+    }
+}
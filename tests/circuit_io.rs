@@ -0,0 +1,60 @@
+#![cfg(feature = "macros")]
+
+use anyhow::Result;
+use plonky::{verify_proof, CircuitBuilder, CircuitIo, Curve, Field, PartialWitness, Tweedledee, Tweedledum};
+
+type F = <Tweedledee as Curve>::ScalarField;
+
+#[derive(CircuitIo, Clone, Debug, PartialEq)]
+struct Point<F: Field> {
+    x: F,
+    y: F,
+}
+
+#[derive(CircuitIo, Clone, Debug, PartialEq)]
+struct Statement<F: Field> {
+    start: Point<F>,
+    steps: [F; 3],
+    end: Point<F>,
+}
+
+#[derive(CircuitIo, Clone, Debug, PartialEq)]
+struct Pair<F: Field>(F, F);
+
+/// Proves that `end` is `start` translated by the sum of `steps` in both coordinates.
+#[test]
+fn test_derive_circuit_io() -> Result<()> {
+    assert_eq!(Statement::<F>::num_elements(), 7);
+
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let statement: StatementTargets<F> = builder.add_public_input_io::<Statement<F>>();
+    let secret: PairTargets<F> = builder.add_virtual_io::<Pair<F>>();
+    let total = builder.add_many(&statement.steps);
+    let x = builder.add(statement.start.x, total);
+    let y = builder.add(statement.start.y, total);
+    builder.copy(x, statement.end.x);
+    builder.copy(y, statement.end.y);
+    let product = builder.mul(secret.0, secret.1);
+    builder.assert_nonzero(product);
+    let circuit = builder.build();
+
+    let value = Statement {
+        start: Point { x: F::ONE, y: F::TWO },
+        steps: [F::ONE, F::ONE, F::TWO],
+        end: Point {
+            x: F::from_canonical_u32(5),
+            y: F::from_canonical_u32(6),
+        },
+    };
+    let mut inputs = PartialWitness::new();
+    inputs.set_io(&statement, &value);
+    inputs.set_io(&secret, &Pair(F::TWO, F::TWO));
+    assert_eq!(inputs.get_io::<Pair<F>>(&secret), Pair(F::TWO, F::TWO));
+    let witness = circuit.generate_witness(inputs)?;
+
+    let public_inputs = circuit.get_public_inputs(&witness);
+    assert_eq!(Statement::<F>::from_elements(&public_inputs)?, value);
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "macros")]
+
+use anyhow::Result;
+use plonky::{circuit, verify_proof, CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum};
+
+type F = <Tweedledee as Curve>::ScalarField;
+
+#[test]
+fn test_circuit_macro() -> Result<()> {
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    circuit! {
+        &mut builder;
+        public product;
+        input x, y, b;
+        let sum = (x + y) * 2 + 1;
+        assert x * y == product;
+        assert x != y;
+        assert_binary b;
+        assert_nonzero x - 1;
+    }
+    assert_eq!(builder.target_name(x), Some("x"));
+    assert_eq!(builder.target_name(sum), Some("sum"));
+    assert_eq!(builder.target_name(product), Some("product"));
+    let circuit = builder.build();
+
+    let mut inputs = PartialWitness::new();
+    inputs.set_targets(&[x, y, b], &[F::from_canonical_u32(5), F::from_canonical_u32(7), F::ONE]);
+    let partial_witness = circuit.generate_partial_witness(inputs.clone())?;
+    assert_eq!(partial_witness.get_target(sum), F::from_canonical_u32(25));
+
+    // Public inputs are stored in their gates' wires, so we read `product` from the witness.
+    let witness = circuit.generate_witness(inputs)?;
+    let public_inputs = circuit.get_public_inputs(&witness);
+    assert_eq!(public_inputs, vec![F::from_canonical_u32(35)]);
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+    Ok(())
+}
+
+#[test]
+fn test_circuit_macro_unsatisfied() {
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    circuit! {
+        &mut builder;
+        input x, y;
+        assert x == y + 1;
+    }
+    let circuit = builder.build();
+
+    let mut inputs = PartialWitness::new();
+    inputs.set_targets(&[x, y], &[F::from_canonical_u32(3), F::from_canonical_u32(3)]);
+    assert!(circuit.generate_witness(inputs).is_err());
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "cli")]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use anyhow::Result;
+use plonky::zkinterface::{BilinearConstraint, Variables};
+use plonky::{Curve, Field, Tweedledee};
+use serde_json::json;
+
+type F = <Tweedledee as Curve>::ScalarField;
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_plonky"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("Failed to run plonky")
+}
+
+fn temp_dir() -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("plonky-cli-test-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Compiles the circuit `x^3 + x + 5 = out`, with `out` public, then proves and verifies.
+#[test]
+fn test_compile_prove_verify() -> Result<()> {
+    let lc = |ids: Vec<u64>, coeffs: &[F]| Variables::new(ids, coeffs);
+    let constraints = vec![
+        BilinearConstraint {
+            linear_combination_a: lc(vec![2], &[F::ONE]),
+            linear_combination_b: lc(vec![2], &[F::ONE]),
+            linear_combination_c: lc(vec![3], &[F::ONE]),
+        },
+        BilinearConstraint {
+            linear_combination_a: lc(vec![3], &[F::ONE]),
+            linear_combination_b: lc(vec![2], &[F::ONE]),
+            linear_combination_c: lc(vec![4], &[F::ONE]),
+        },
+        BilinearConstraint {
+            linear_combination_a: lc(vec![4, 2, 0], &[F::ONE, F::ONE, F::FIVE]),
+            linear_combination_b: lc(vec![0], &[F::ONE]),
+            linear_combination_c: lc(vec![1], &[F::ONE]),
+        },
+    ];
+    let circuit = json!({
+        "header": {
+            "instance_variables": { "variable_ids": [1], "values": null },
+            "free_variable_id": 5,
+            "field_maximum": null,
+        },
+        "constraint_system": { "constraints": constraints },
+    });
+    let x = F::THREE;
+    let witness = json!({
+        "instance_variables": lc(vec![1], &[x.cube() + x + F::FIVE]),
+        "assigned_variables": lc(vec![2, 3, 4], &[x, x.square(), x.cube()]),
+    });
+
+    let dir = temp_dir()?;
+    fs::write(dir.join("circuit.json"), circuit.to_string())?;
+    fs::write(dir.join("witness.json"), witness.to_string())?;
+
+    let output = run(&dir, &["compile", "circuit.json", "--prover-key", "pk.cbor", "--verification-key", "vk.json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run(&dir, &["prove", "circuit.json", "witness.json", "--proof", "proof.json", "--public-inputs", "pis.json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let public_inputs: Vec<F> = plonky::public_inputs_from_json(&fs::read_to_string(dir.join("pis.json"))?)?;
+    assert_eq!(public_inputs, vec![F::from_canonical_u64(35)]);
+
+    let verify_args = ["verify", "--verification-key", "vk.json", "--public-inputs", "pis.json", "--proof", "proof.json"];
+    assert!(run(&dir, &verify_args).status.success());
+
+    // A proof doesn't verify against different public inputs.
+    fs::write(dir.join("pis.json"), plonky::public_inputs_to_json(&[F::from_canonical_u64(36)]))?;
+    let output = run(&dir, &verify_args);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("The proof is invalid"));
+
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
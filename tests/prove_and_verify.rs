@@ -1,5 +1,7 @@
 use anyhow::Result;
-use plonky::{blake_hash_base_field_to_curve, msm_parallel, rescue_hash_1_to_1, verify_proof, AffinePoint, Base4SumGate, Circuit, CircuitBuilder, Curve, CurveMulOp, Field, HaloCurve, PartialWitness, Target, Tweedledee, Tweedledum, Wire, Witness};
+use plonky::plonk_challenger::{Blake2bChallenger, Challenger, Halo2Blake2bChallenger};
+use plonky::plonk_util::halo_n_mul;
+use plonky::{blake_hash_base_field_to_curve, finalize_recursion, msm_parallel, rescue_hash_1_to_1, verify, verify_accumulators, verify_batch, verify_debug, verify_lazily, verify_proof, verify_streaming, verify_with_transcript, AffinePoint, Base4SumGate, Circuit, CircuitBuilder, Curve, CurveMulOp, FftStrategy, Field, HaloCurve, MerkleTree, PartialWitness, PhaseTimings, ProjectivePoint, ProverConfig, ProverPhase, RecursionLayout, SecurityParams, Target, Tweedledee, Tweedledum, VerificationError, Wire, Witness};
 use rand::{thread_rng, Rng};
 use std::time::Instant;
 
@@ -10,7 +12,7 @@ fn get_trivial_circuit<C: HaloCurve>(x: C::ScalarField) -> (Circuit<C>, Witness<
     let mut partial_witness = PartialWitness::new();
     partial_witness.set_target(t, x);
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness).unwrap();
     (circuit, witness)
 }
 
@@ -18,13 +20,154 @@ fn get_trivial_circuit<C: HaloCurve>(x: C::ScalarField) -> (Circuit<C>, Witness<
 fn test_proof_trivial() -> Result<()> {
     let (circuit, witness) = get_trivial_circuit(<Tweedledee as Curve>::ScalarField::ZERO);
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &circuit.into(), true)?;
 
     Ok(())
 }
 
+#[test]
+fn test_proof_without_zero_knowledge() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    builder.set_zero_knowledge(false);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_cubed = builder.mul_many(&[t, t, t]);
+    builder.copy(t_cubed, pi);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(t, F::from_canonical_usize(3));
+    let circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    let vk = circuit.to_vk();
+    verify_proof::<Tweedledee, Tweedledum>(&[F::from_canonical_usize(27)], &proof, &[], &vk, true)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_deterministic_proof() -> Result<()> {
+    let (circuit, witness) = get_trivial_circuit(<Tweedledee as Curve>::ScalarField::ZERO);
+    let proof_1 = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?;
+    let proof_2 = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?;
+    let proof_3 = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 43)?;
+    assert_eq!(proof_1, proof_2);
+    assert_ne!(proof_1, proof_3);
+    verify_proof::<Tweedledee, Tweedledum>(&[], &proof_1, &[], &circuit.to_vk(), true)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_low_memory_proof() -> Result<()> {
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let one = builder.one_wire();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    let quad = builder.add_many(&[one, t, t_sq]);
+    let seven = builder.constant_wire(<Tweedledee as Curve>::ScalarField::from_canonical_usize(7));
+    let res = builder.sub(quad, seven);
+    builder.assert_zero(res);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(t, <Tweedledee as Curve>::ScalarField::TWO);
+    let mut circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
+
+    let proof = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?;
+    circuit.prover_key.set_low_memory(true);
+    let low_memory_proof = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?;
+    assert_eq!(proof, low_memory_proof);
+    verify_proof::<Tweedledee, Tweedledum>(&[], &low_memory_proof, &[], &circuit.to_vk(), true)?;
+
+    // Switching back should restore the LDEs used by the default prover.
+    circuit.prover_key.set_low_memory(false);
+    let proof_again = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?;
+    assert_eq!(proof, proof_again);
+
+    Ok(())
+}
+
+#[test]
+fn test_prover_config() -> Result<()> {
+    let (mut circuit, witness) = get_trivial_circuit::<Tweedledee>(<Tweedledee as Curve>::ScalarField::ZERO);
+    let vk = circuit.to_vk();
+    let config = ProverConfig {
+        num_threads: Some(2),
+        fft_strategy: FftStrategy::Serial,
+        msm_window: Some(9),
+    };
+    let proof = circuit.generate_proof_with_config::<Tweedledum>(&witness, &[], &config)?;
+    verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &vk, true)?;
+
+    // The MSM window doesn't affect the proof.
+    let proof = circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?;
+    circuit.prover_key.set_msm_window(9);
+    assert_eq!(circuit.generate_proof_deterministic::<Tweedledum>(&witness, &[], 42)?, proof);
+    Ok(())
+}
+
+#[test]
+fn test_prove_batch() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let square = builder.mul(pi, pi);
+    builder.assert_nonzero(square);
+    let circuit = builder.build();
+    let vk = circuit.to_vk();
+
+    let witnesses = (1..=5)
+        .map(|i| {
+            let mut partial_witness = PartialWitness::new();
+            partial_witness.set_target(pi, F::from_canonical_usize(i));
+            circuit.generate_witness(partial_witness)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let config = ProverConfig {
+        num_threads: Some(2),
+        msm_window: Some(9),
+        ..ProverConfig::default()
+    };
+    let proofs = circuit.prove_batch_with_config::<Tweedledum>(&witnesses, &config)?;
+    assert_eq!(proofs.len(), witnesses.len());
+    for (witness, proof) in witnesses.iter().zip(&proofs) {
+        verify_proof::<Tweedledee, Tweedledum>(&circuit.get_public_inputs(witness), proof, &[], &vk, true)?;
+    }
+
+    assert!(circuit.prove_batch::<Tweedledum>(&[])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_prover_observer() -> Result<()> {
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let t = builder.add_virtual_target();
+    builder.assert_zero(t);
+    let circuit = builder.build();
+
+    let observer = PhaseTimings::new();
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(t, <Tweedledee as Curve>::ScalarField::ZERO);
+    let witness = circuit.generate_witness_with_observer(partial_witness, &observer)?;
+    let proof = circuit.generate_proof_with_observer::<Tweedledum>(&witness, &[], &ProverConfig::default(), &observer)?;
+    verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &circuit.to_vk(), true)?;
+
+    let phases = observer.timings().into_iter().map(|(phase, _)| phase).collect::<Vec<_>>();
+    assert_eq!(
+        phases,
+        vec![
+            ProverPhase::WitnessGeneration,
+            ProverPhase::WireCommitments,
+            ProverPhase::PermutationProduct,
+            ProverPhase::Quotient,
+            ProverPhase::Opening,
+        ]
+    );
+    Ok(())
+}
+
 #[test]
 #[allow(clippy::same_item_push)]
 fn test_proof_trivial_circuit_many_proofs() -> Result<()> {
@@ -32,7 +175,7 @@ fn test_proof_trivial_circuit_many_proofs() -> Result<()> {
     for _ in 0..10 {
         let (circuit, witness) = get_trivial_circuit(<Tweedledee as Curve>::ScalarField::ZERO);
         let proof = circuit
-            .generate_proof::<Tweedledum>(&witness, &[], true)
+            .generate_proof::<Tweedledum>(&witness, &[])
             .unwrap();
         let vk = circuit.to_vk();
         let old_proof = verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &vk, false)
@@ -42,7 +185,7 @@ fn test_proof_trivial_circuit_many_proofs() -> Result<()> {
     }
     let (circuit, witness) = get_trivial_circuit(<Tweedledee as Curve>::ScalarField::ZERO);
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &old_proofs, true)
+        .generate_proof::<Tweedledum>(&witness, &old_proofs)
         .unwrap();
     let vk = circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &old_proofs, &vk, true)?;
@@ -61,9 +204,9 @@ fn test_proof_sum() -> Result<()> {
     partial_witness.set_target(t1, <Tweedledee as Curve>::ScalarField::ZERO);
     partial_witness.set_target(t2, <Tweedledee as Curve>::ScalarField::ZERO);
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let vk = circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &vk, true)?;
@@ -91,10 +234,10 @@ fn test_proof_sum_big() -> Result<()> {
     dbg!(now.elapsed());
     let circuit = builder.build();
     dbg!(now.elapsed());
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     dbg!(now.elapsed());
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     dbg!(now.elapsed());
     let vk = circuit.to_vk();
@@ -118,9 +261,9 @@ fn test_proof_quadratic() -> Result<()> {
     let mut partial_witness = PartialWitness::new();
     partial_witness.set_target(t, <Tweedledee as Curve>::ScalarField::TWO);
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let vk = circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &vk, true)?;
@@ -128,6 +271,327 @@ fn test_proof_quadratic() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_proof_wide_circuit() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new_with_width(128, 12, 7);
+    let six_pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    let t_sq_plus_t = builder.add(t_sq, t);
+    builder.copy(t_sq_plus_t, six_pi);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(six_pi, F::from_canonical_usize(6));
+    partial_witness.set_target(t, F::TWO);
+    let circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    assert_eq!(proof.c_wires.len(), 12);
+    let vk = circuit.to_vk();
+    verify_proof::<Tweedledee, Tweedledum>(&[F::from_canonical_usize(6)], &proof, &[], &vk, true)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_proof_wide_circuit_packs_additions() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let sum_gates = |builder: &mut CircuitBuilder<Tweedledee>| {
+        let terms = builder.add_virtual_targets(4);
+        builder.zero_wire();
+        builder.one_wire();
+        let num_gates = builder.num_gates();
+        let mut sum = terms[0];
+        for &term in &terms[1..] {
+            sum = builder.add(sum, term);
+        }
+        (terms, sum, builder.num_gates() - num_gates)
+    };
+
+    // Each addition needs its own gate at the default width.
+    let (_, _, num_default_gates) = sum_gates(&mut CircuitBuilder::<Tweedledee>::new(128));
+    assert_eq!(num_default_gates, 3);
+
+    // With twelve wires, seven of them routed, two additions share each gate.
+    let mut builder = CircuitBuilder::<Tweedledee>::new_with_width(128, 12, 7);
+    let sum_pi = builder.add_public_input();
+    let (terms, sum, num_wide_gates) = sum_gates(&mut builder);
+    assert_eq!(num_wide_gates, 2);
+    builder.copy(sum, sum_pi);
+    let circuit = builder.build();
+
+    let mut partial_witness = PartialWitness::new();
+    let values = [1u32, 2, 3, 4].iter().map(|&x| F::from_canonical_u32(x)).collect::<Vec<_>>();
+    partial_witness.set_targets(&terms, &values);
+    let witness = circuit.generate_witness(partial_witness)?;
+    let public_inputs = circuit.get_public_inputs(&witness);
+    assert_eq!(public_inputs, vec![F::from_canonical_u32(10)]);
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    verify_proof::<Tweedledee, Tweedledum>(&public_inputs, &proof, &[], &circuit.to_vk(), true)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_security_params() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let security = SecurityParams::new(100).with_challenge_len(160);
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    builder.set_security_params(security);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    builder.copy(t_sq, pi);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(t, F::from_canonical_usize(5));
+    let circuit = builder.build();
+    assert_eq!(circuit.security_params(), security);
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    assert_eq!(proof.parameters.challenge_len, 160);
+    let mut vk = circuit.to_vk();
+    verify::<Tweedledee, Tweedledum>(&vk, &[F::from_canonical_usize(25)], &proof)?;
+
+    // A verifier expecting challenges of another length rejects the proof up front.
+    vk.challenge_len = 128;
+    assert!(matches!(
+        verify::<Tweedledee, Tweedledum>(&vk, &[F::from_canonical_usize(25)], &proof),
+        Err(VerificationError::IncompatibleProof(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_standalone_verify() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    builder.copy(t_sq, pi);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(t, F::from_canonical_usize(5));
+    let circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+
+    // A light client only has the verification key, without any precomputations.
+    let mut vk = circuit.to_vk();
+    vk.clear_all();
+    drop(circuit);
+
+    verify::<Tweedledee, Tweedledum>(&vk, &[F::from_canonical_usize(25)], &proof)?;
+    assert!(verify::<Tweedledee, Tweedledum>(&vk, &[F::from_canonical_usize(26)], &proof).is_err());
+    assert_eq!(
+        verify::<Tweedledee, Tweedledum>(&vk, &[], &proof),
+        Err(VerificationError::WrongNumberOfPublicInputs {
+            expected: 1,
+            actual: 0
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_public_input_diagnostics() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pis = builder.add_public_inputs(8);
+    let product = builder.mul_many(&pis);
+    builder.assert_nonzero(product);
+    let circuit = builder.build();
+
+    let mut partial_witness = PartialWitness::new();
+    for (i, &pi) in pis.iter().enumerate() {
+        partial_witness.set_target(pi, F::from_canonical_usize(i + 1));
+    }
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    let vk = circuit.to_vk();
+    let prover_public_inputs = circuit.get_public_inputs(&witness);
+
+    verify_debug::<Tweedledee, Tweedledum>(&vk, &prover_public_inputs, &proof, &prover_public_inputs)?;
+    let mut public_inputs = prover_public_inputs.clone();
+    public_inputs[2] = F::ZERO;
+    public_inputs[7] = F::ONE;
+    // The public inputs are part of the transcript, so which check fails first depends on the
+    // challenges: the transcript may be invalid before the public input quotient is checked.
+    assert!(matches!(
+        verify::<Tweedledee, Tweedledum>(&vk, &public_inputs, &proof),
+        Err(VerificationError::InvalidPublicInputQuotientOpening)
+            | Err(VerificationError::InvalidTranscript(_))
+    ));
+    assert_eq!(
+        verify_debug::<Tweedledee, Tweedledum>(&vk, &public_inputs, &proof, &prover_public_inputs),
+        Err(VerificationError::PublicInputMismatch { indices: vec![2, 7] })
+    );
+
+    // A key whose public input gates don't fit in the circuit is rejected up front.
+    let mut bad_vk = vk.clone();
+    bad_vk.num_gates_without_pis = bad_vk.degree - 1;
+    assert!(matches!(
+        verify::<Tweedledee, Tweedledum>(&bad_vk, &prover_public_inputs, &proof),
+        Err(VerificationError::InvalidPublicInputLayout(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_security_params() -> Result<()> {
+    let (circuit, witness) = get_trivial_circuit::<Tweedledee>(<Tweedledee as Curve>::ScalarField::ZERO);
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    let vk = circuit.to_vk();
+    verify::<Tweedledee, Tweedledum>(&vk, &[], &proof)?;
+
+    // A key whose challenges are longer than the scalar field is rejected up front, both by the
+    // verifier and when finalizing a recursive proof of it.
+    let mut bad_vk = vk.clone();
+    bad_vk.challenge_len = 1000;
+    assert!(matches!(
+        verify::<Tweedledee, Tweedledum>(&bad_vk, &[], &proof),
+        Err(VerificationError::InvalidSecurityParams(_))
+    ));
+
+    let layout = RecursionLayout {
+        degree_pow: circuit.degree_pow(),
+        num_old_proofs: 0,
+        old_proof_degree_pow: 0,
+        num_carried_accumulators: 0,
+        carried_degree_pow: 0,
+        vk_is_input: false,
+    };
+    // With a valid key, finalizing gets as far as the public inputs.
+    let err = finalize_recursion::<Tweedledee, Tweedledum>(&[], layout, &vk).unwrap_err();
+    assert!(err.to_string().contains("recursion layout"), "{}", err);
+    let err = finalize_recursion::<Tweedledee, Tweedledum>(&[], layout, &bad_vk).unwrap_err();
+    assert!(err.to_string().contains("don't fit"), "{}", err);
+    Ok(())
+}
+
+#[test]
+fn test_batch_verify() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    builder.copy(t_sq, pi);
+    let circuit = builder.build();
+    let vk = circuit.to_vk();
+
+    let mut proofs = Vec::new();
+    let mut pis = Vec::new();
+    for i in 0..4 {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(t, F::from_canonical_usize(i));
+        let witness = circuit.generate_witness(partial_witness)?;
+        proofs.push(circuit.generate_proof::<Tweedledum>(&witness, &[])?);
+        pis.push(vec![F::from_canonical_usize(i * i)]);
+    }
+    verify_batch::<Tweedledee, Tweedledum>(&proofs, &pis, &vk)?;
+
+    // A single invalid proof makes the batch fail.
+    let mut bad_proofs = proofs.clone();
+    bad_proofs[2].halo_g = bad_proofs[1].halo_g;
+    assert!(verify_batch::<Tweedledee, Tweedledum>(&bad_proofs, &pis, &vk).is_err());
+    let mut bad_pis = pis.clone();
+    bad_pis.swap(0, 3);
+    assert!(verify_batch::<Tweedledee, Tweedledum>(&proofs, &bad_pis, &vk).is_err());
+    assert!(verify_batch::<Tweedledee, Tweedledum>(&proofs, &pis[1..], &vk).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_lazy_verification() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    builder.copy(t_sq, pi);
+    let circuit = builder.build();
+    let vk = circuit.to_vk();
+
+    let mut accumulators = Vec::new();
+    for i in 0..3 {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(t, F::from_canonical_usize(i));
+        let witness = circuit.generate_witness(partial_witness)?;
+        let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        let pis = [F::from_canonical_usize(i * i)];
+        accumulators.push(verify_lazily::<Tweedledee, Tweedledum>(&vk, &pis, &proof)?);
+    }
+    verify_accumulators(&accumulators, &vk)?;
+
+    // An accumulator with a wrong `G` point is rejected.
+    accumulators[1].halo_g = accumulators[0].halo_g;
+    assert_eq!(
+        verify_accumulators(&accumulators, &vk),
+        Err(VerificationError::InvalidHaloG)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_custom_transcripts() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    type BF = <Tweedledee as Curve>::BaseField;
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_sq = builder.square(t);
+    builder.copy(t_sq, pi);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(t, F::from_canonical_usize(5));
+    let circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
+    let vk = circuit.to_vk();
+    let pis = [F::from_canonical_usize(25)];
+
+    let proof = circuit.generate_proof_with_transcript::<Tweedledum, _>(
+        &witness,
+        &[],
+        Blake2bChallenger::<BF>::new(),
+    )?;
+    verify_with_transcript::<Tweedledee, Tweedledum, _>(&vk, &pis, &proof, Blake2bChallenger::new())?;
+    // The verifier must use the same kind of transcript as the prover.
+    assert_eq!(
+        verify::<Tweedledee, Tweedledum>(&vk, &pis, &proof),
+        Err(VerificationError::IncompatibleProof(
+            "Expected a proof with transcript hash Rescue, got Blake2b".to_string()
+        ))
+    );
+
+    let proof = circuit.generate_proof_with_transcript::<Tweedledum, _>(
+        &witness,
+        &[],
+        Challenger::<BF>::new_poseidon(128),
+    )?;
+    verify_with_transcript::<Tweedledee, Tweedledum, _>(&vk, &pis, &proof, Challenger::new_poseidon(128))?;
+    assert!(
+        verify_with_transcript::<Tweedledee, Tweedledum, _>(&vk, &pis, &proof, Blake2bChallenger::new())
+            .is_err()
+    );
+
+    let proof = circuit.generate_proof_with_transcript::<Tweedledum, _>(
+        &witness,
+        &[],
+        Halo2Blake2bChallenger::<BF>::new(),
+    )?;
+    verify_with_transcript::<Tweedledee, Tweedledum, _>(&vk, &pis, &proof, Halo2Blake2bChallenger::new())?;
+    assert!(
+        verify_with_transcript::<Tweedledee, Tweedledum, _>(&vk, &pis, &proof, Blake2bChallenger::new())
+            .is_err()
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_proof_quadratic_public_input() -> Result<()> {
     type F = <Tweedledee as Curve>::ScalarField;
@@ -143,9 +607,9 @@ fn test_proof_quadratic_public_input() -> Result<()> {
     partial_witness.set_target(seven_pi, F::from_canonical_usize(7));
     partial_witness.set_target(t, F::TWO);
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let vk = circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&[F::from_canonical_usize(7)], &proof, &[], &vk, false)?;
@@ -171,10 +635,10 @@ fn test_proof_sum_public_input() -> Result<()> {
         partial_witness.set_target(triangular_pis[i], F::from_canonical_usize(i + 1));
     });
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let now = Instant::now();
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     dbg!(now.elapsed());
     let vk = circuit.to_vk();
@@ -208,9 +672,9 @@ fn test_proof_factorial_public_input() -> Result<()> {
         partial_witness.set_target(factors_pis[i], F::from_canonical_usize(i + 1));
     });
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let vk = circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(
@@ -238,9 +702,9 @@ fn test_proof_public_input() -> Result<()> {
     let mut partial_witness = PartialWitness::new();
     (0..n).for_each(|i| partial_witness.set_target(pis[i], values[i]));
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let pis = circuit.get_public_inputs(&witness);
     // Check that the public inputs are set correctly in the proof.
@@ -269,9 +733,9 @@ fn test_proof_public_input_copied() -> Result<()> {
         builder.copy(virtuals[i], pis[i]);
     });
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let pis = circuit.get_public_inputs(&witness);
     // Check that the public inputs are set correctly in the proof.
@@ -282,6 +746,68 @@ fn test_proof_public_input_copied() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_verify_streaming() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    // Many public inputs, whose last gate isn't full.
+    let n = 1001;
+    let values = (0..n).map(|_| F::rand()).collect::<Vec<_>>();
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pis = builder.add_public_inputs(n);
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_targets(&pis, &values);
+    let circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    let vk = circuit.to_vk();
+
+    // The public inputs are yielded lazily, as if read from a stream.
+    let public_inputs = (0..n).map(|i| values[i]);
+    verify_streaming::<Tweedledee, Tweedledum, _>(&vk, public_inputs.clone(), &proof)?;
+    verify::<Tweedledee, Tweedledum>(&vk, &values, &proof)?;
+
+    let mut wrong_values = values.clone();
+    wrong_values[n - 1] = wrong_values[n - 1] + F::ONE;
+    assert!(verify_streaming::<Tweedledee, Tweedledum, _>(&vk, wrong_values.into_iter(), &proof).is_err());
+    assert_eq!(
+        verify_streaming::<Tweedledee, Tweedledum, _>(&vk, public_inputs.take(n - 1), &proof),
+        Err(VerificationError::WrongNumberOfPublicInputs {
+            expected: n,
+            actual: n - 1
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_merkle_proof() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    let tree = MerkleTree::new((0..8).map(|_| F::rand()).collect(), 128);
+    let index = 6;
+    let proof = tree.open(index);
+
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let leaf = builder.add_virtual_target();
+    let index_bits = builder.add_virtual_targets(tree.height());
+    let siblings = builder.add_virtual_targets(tree.height());
+    let root = builder.constant_wire(tree.root());
+    builder.verify_merkle_proof(leaf, &index_bits, &siblings, root);
+    let circuit = builder.build();
+
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_target(leaf, tree.leaves()[index]);
+    for (i, &bit) in index_bits.iter().enumerate() {
+        partial_witness.set_target(bit, F::from_canonical_usize((index >> i) & 1));
+    }
+    partial_witness.set_targets(&siblings, &proof.siblings);
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+    verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &circuit.to_vk(), true)?;
+
+    Ok(())
+}
+
 #[test]
 fn test_rescue_hash() -> Result<()> {
     type F = <Tweedledee as Curve>::ScalarField;
@@ -296,9 +822,9 @@ fn test_rescue_hash() -> Result<()> {
     let mut partial_witness = PartialWitness::new();
     partial_witness.set_target(t, x);
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let vk = circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&[], &proof, &[], &vk, true)?;
@@ -327,10 +853,10 @@ fn test_curve_add() -> Result<()> {
     partial_witness.set_point_target(tb, b);
 
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
 
     let proof = circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
 
     let vk = circuit.to_vk();
@@ -372,9 +898,57 @@ fn test_curve_msm() -> Result<()> {
     );
     partial_witness.set_point_targets(&tps, &ps);
     let circuit = builder.build();
-    let witness = circuit.generate_witness(partial_witness);
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit
+        .generate_proof::<Tweedledee>(&witness, &[])
+        .unwrap();
+
+    let vk = circuit.to_vk();
+    verify_proof::<Tweedledum, Tweedledee>(&[], &proof, &[], &vk, true)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_curve_msm_endo() -> Result<()> {
+    type SF = <Tweedledee as Curve>::ScalarField;
+    type BF = <Tweedledee as Curve>::BaseField;
+    let n = 3;
+    let xs = (0..n).map(|_| SF::rand()).collect::<Vec<_>>();
+    let ps = (0..n)
+        .map(|_| blake_hash_base_field_to_curve::<Tweedledee>(BF::rand()))
+        .collect::<Vec<_>>();
+    let res = xs
+        .iter()
+        .zip(&ps)
+        .map(|(x, &p)| halo_n_mul(&x.to_canonical_bool_vec()[..128], p).to_projective())
+        .fold(ProjectivePoint::ZERO, |acc, p| acc + p);
+    let mut builder = CircuitBuilder::<Tweedledum>::new(128);
+    let txs = builder.add_virtual_targets(n);
+    let tps = builder.add_virtual_point_targets(n);
+    let tres_purported = builder.curve_msm_endo::<Tweedledee>(
+        &(0..n)
+            .map(|i| CurveMulOp {
+                scalar: txs[i],
+                point: tps[i],
+            })
+            .collect::<Vec<_>>(),
+    );
+    let tres_true = builder.constant_affine_point(res.to_affine());
+    builder.copy_curve(tres_purported.msm_result, tres_true);
+
+    let mut partial_witness = PartialWitness::new();
+    partial_witness.set_targets(
+        &txs,
+        &xs.into_iter()
+            .map(|x| x.try_convert().unwrap())
+            .collect::<Vec<_>>(),
+    );
+    partial_witness.set_point_targets(&tps, &ps);
+    let circuit = builder.build();
+    let witness = circuit.generate_witness(partial_witness)?;
     let proof = circuit
-        .generate_proof::<Tweedledee>(&witness, &[], true)
+        .generate_proof::<Tweedledee>(&witness, &[])
         .unwrap();
 
     let vk = circuit.to_vk();
@@ -438,8 +1012,8 @@ fn test_base_4_sum() -> Result<()> {
     let circuit = builder.build();
     let mut partial_witness = PartialWitness::new();
     partial_witness.set_targets(&t_limbs, &limbs);
-    let witness = circuit.generate_witness(partial_witness);
-    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[], true)?;
+    let witness = circuit.generate_witness(partial_witness)?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
     verify_proof::<C, InnerC>(&[], &proof, &[], &circuit.into(), true)?;
 
     Ok(())
@@ -462,8 +1036,8 @@ fn test_curve_double_gate() -> Result<()> {
     builder.copy_curve(t_double_p_purported, t_double_p_true);
 
     let circuit = builder.build();
-    let witness = circuit.generate_witness(PartialWitness::new());
-    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[], true)?;
+    let witness = circuit.generate_witness(PartialWitness::new())?;
+    let proof = circuit.generate_proof::<Tweedledum>(&witness, &[])?;
     verify_proof::<C, InnerC>(&[], &proof, &[], &circuit.into(), true)?;
 
     Ok(())
@@ -1,42 +1,297 @@
 use anyhow::Result;
-use plonky::{recursive_verification_circuit, verify_proof, CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum};
+use plonky::plonk_challenger::Challenger;
+use plonky::util::log2_strict;
+use plonky::{aggregate, aggregate_with_arity, carried_accumulators, finalize_recursion, pcd_circuit, recursion_public_inputs, verify_pcd, AggregateProof, PcdProof, RecursionContext, RecursionLayout, AGGREGATION_ARITY, recursive_circuit_size, recursive_circuit_size_with_vk_input, recursive_verification_circuit, recursive_verification_circuit_with_vk_input, verify_accumulators, verify_proof, CircuitBuilder, Curve, Field, PartialWitness, Tweedledee, Tweedledum};
 
 #[test]
-// TODO: Fails for the moment.
-#[ignore]
 fn test_proof_trivial_recursive() -> Result<()> {
     let mut builder = CircuitBuilder::<Tweedledee>::new(128);
     let t = builder.constant_wire(<Tweedledee as Curve>::ScalarField::ZERO);
     let mut partial_witness = PartialWitness::new();
     partial_witness.set_target(t, <Tweedledee as Curve>::ScalarField::ZERO);
     let inner_circuit = builder.build();
-    let witness = inner_circuit.generate_witness(partial_witness);
+    let witness = inner_circuit.generate_witness(partial_witness)?;
     let inner_proof = inner_circuit
-        .generate_proof::<Tweedledum>(&witness, &[], true)
+        .generate_proof::<Tweedledum>(&witness, &[])
         .unwrap();
     let inner_vk = inner_circuit.to_vk();
     verify_proof::<Tweedledee, Tweedledum>(&[], &inner_proof, &[], &inner_vk, true)?;
 
-    let recursion_circuit = recursive_verification_circuit::<Tweedledum, Tweedledee>(
-        inner_circuit.degree_pow(),
-        128,
-        0,
-        0,
-    );
+    let recursion_circuit =
+        recursive_verification_circuit::<Tweedledum, Tweedledee>(&inner_vk, 0, None);
+    let size = recursive_circuit_size::<Tweedledum, Tweedledee>(&inner_vk, 0, None);
+    assert_eq!(size.degree_pow, recursion_circuit.circuit.degree_pow());
+    assert_eq!(size.num_public_inputs, recursion_circuit.circuit.num_public_inputs);
     let mut recursion_inputs = PartialWitness::new();
-    if let Err(e) = recursion_circuit
-        .proof
-        .populate_witness(&mut recursion_inputs, inner_proof)
-    {
-        panic!("Failed to populate inputs: {:?}", e);
-    }
-    let recursion_witness = recursion_circuit.circuit.generate_witness(recursion_inputs);
+    recursion_circuit.populate_witness(&mut recursion_inputs, &inner_proof, &[], &[])?;
+    let recursion_witness = recursion_circuit.circuit.generate_witness(recursion_inputs)?;
+
+    // The circuit should export the same values as the native verifier computes.
+    let pis = recursion_circuit
+        .circuit
+        .get_public_inputs(&recursion_witness);
+    let challs = inner_proof.get_challenges(&inner_vk, &[], &[], Challenger::new(128))?;
+    assert_eq!(
+        pis,
+        recursion_public_inputs::<Tweedledee, Tweedledum>(&inner_proof, &[], None, &[], &challs, 128, None)?
+    );
+
     let proof = recursion_circuit
         .circuit
-        .generate_proof::<Tweedledee>(&recursion_witness, &[], true)
+        .generate_proof::<Tweedledee>(&recursion_witness, &[])
         .unwrap();
     let vk = recursion_circuit.circuit.to_vk();
-    verify_proof::<Tweedledum, Tweedledee>(&[], &proof, &[], &vk, true)?;
+    verify_proof::<Tweedledum, Tweedledee>(&pis, &proof, &[], &vk, true)?;
+
+    // At the end of the chain, check the deferred assumptions and accumulator natively.
+    finalize_recursion::<Tweedledee, Tweedledum>(&pis, recursion_circuit.layout, &inner_vk)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_proof_recursive_vk_input() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    // Two different circuits, showing knowledge of a cube root and a square root respectively.
+    let root_circuit = |power: usize| {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+        let pi = builder.add_public_input();
+        let t = builder.add_virtual_target();
+        let t_power = builder.mul_many(&vec![t; power]);
+        builder.copy(t_power, pi);
+        (builder.build(), t)
+    };
+    let (cube_circuit, cube_root) = root_circuit(3);
+    let (square_circuit, square_root) = root_circuit(2);
+    let cube_vk = cube_circuit.to_vk();
+    let square_vk = square_circuit.to_vk();
+    assert_eq!(cube_vk.degree, square_vk.degree);
+
+    // A single recursive circuit can verify proofs of either one.
+    let recursion_circuit =
+        recursive_verification_circuit_with_vk_input::<Tweedledum, Tweedledee>(&cube_vk, 0, None);
+    let size = recursive_circuit_size_with_vk_input::<Tweedledum, Tweedledee>(&cube_vk, 0, None);
+    assert_eq!(size.degree_pow, recursion_circuit.circuit.degree_pow());
+    let recursion_vk = recursion_circuit.circuit.to_vk();
+    for (inner_circuit, root, inner_vk, other_vk) in &[
+        (&cube_circuit, cube_root, &cube_vk, &square_vk),
+        (&square_circuit, square_root, &square_vk, &cube_vk),
+    ] {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(*root, F::from_canonical_usize(3));
+        let witness = inner_circuit.generate_witness(partial_witness)?;
+        let inner_pis = inner_circuit.get_public_inputs(&witness);
+        let inner_proof = inner_circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        verify_proof::<Tweedledee, Tweedledum>(&inner_pis, &inner_proof, &[], inner_vk, true)?;
+
+        let mut recursion_inputs = PartialWitness::new();
+        recursion_circuit.populate_inner_vk(&mut recursion_inputs, inner_vk)?;
+        recursion_circuit.populate_witness(&mut recursion_inputs, &inner_proof, &inner_pis, &[])?;
+        let recursion_witness = recursion_circuit.circuit.generate_witness(recursion_inputs)?;
+        let pis = recursion_circuit
+            .circuit
+            .get_public_inputs(&recursion_witness);
+        let challs = inner_proof.get_challenges(inner_vk, &inner_pis, &[], Challenger::new(128))?;
+        assert_eq!(
+            pis,
+            recursion_public_inputs::<Tweedledee, Tweedledum>(
+                &inner_proof,
+                &inner_pis,
+                None,
+                &[],
+                &challs,
+                128,
+                Some(inner_vk),
+            )?
+        );
+
+        let proof = recursion_circuit
+            .circuit
+            .generate_proof::<Tweedledee>(&recursion_witness, &[])?;
+        verify_proof::<Tweedledum, Tweedledee>(&pis, &proof, &[], &recursion_vk, true)?;
+        finalize_recursion::<Tweedledee, Tweedledum>(&pis, recursion_circuit.layout, inner_vk)?;
+
+        // The exported key identifies which circuit was verified.
+        assert!(
+            finalize_recursion::<Tweedledee, Tweedledum>(&pis, recursion_circuit.layout, other_vk)
+                .is_err()
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_aggregate() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    type BF = <Tweedledee as Curve>::BaseField;
+
+    // Each proof shows knowledge of a cube root of its public input.
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_cubed = builder.mul_many(&[t, t, t]);
+    builder.copy(t_cubed, pi);
+    let circuit = builder.build();
+    let vk = circuit.to_vk();
+
+    let mut proofs = Vec::new();
+    let mut public_inputs = Vec::new();
+    for &root in &[2, 3] {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(t, F::from_canonical_usize(root));
+        let witness = circuit.generate_witness(partial_witness)?;
+        proofs.push(circuit.generate_proof::<Tweedledum>(&witness, &[])?);
+        public_inputs.push(circuit.get_public_inputs(&witness));
+    }
+
+    let aggregate_proof = aggregate::<Tweedledum, Tweedledee>(&vk, &proofs, &public_inputs)?;
+    aggregate_proof.verify()?;
+
+    // Two proofs are aggregated by a single node. Tampering with the values it exported should
+    // be caught.
+    match aggregate_proof {
+        AggregateProof::Odd(mut aggregation) => {
+            aggregation.pcd_proof.public_inputs[0] =
+                aggregation.pcd_proof.public_inputs[0] + BF::ONE;
+            assert!(aggregation.verify().is_err());
+        }
+        AggregateProof::Even(_) => panic!("Expected a single level"),
+    }
+
+    // A context builds the node circuit once, and reuses it for later batches.
+    let mut context = RecursionContext::<Tweedledum, Tweedledee>::new();
+    context.aggregate(&vk, &proofs, &public_inputs, AGGREGATION_ARITY)?.verify()?;
+    proofs.reverse();
+    public_inputs.reverse();
+    context.aggregate(&vk, &proofs, &public_inputs, AGGREGATION_ARITY)?.verify()?;
+    assert_eq!(context.num_circuits(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_aggregate_batch() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_squared = builder.mul(t, t);
+    builder.copy(t_squared, pi);
+    let circuit = builder.build();
+    let vk = circuit.to_vk();
+
+    let mut proofs = Vec::new();
+    let mut public_inputs = Vec::new();
+    for root in 2..5 {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(t, F::from_canonical_usize(root));
+        let witness = circuit.generate_witness(partial_witness)?;
+        proofs.push(circuit.generate_proof::<Tweedledum>(&witness, &[])?);
+        public_inputs.push(circuit.get_public_inputs(&witness));
+    }
+
+    // With a large enough arity, a single node verifies all three proofs.
+    let aggregate_proof =
+        aggregate_with_arity::<Tweedledum, Tweedledee>(&vk, &proofs, &public_inputs, 4)?;
+    match &aggregate_proof {
+        AggregateProof::Odd(aggregation) => assert_eq!(aggregation.layout.arity, 3),
+        AggregateProof::Even(_) => panic!("Expected a single level"),
+    }
+    aggregate_proof.verify()
+}
+
+#[test]
+fn test_pcd_merge() -> Result<()> {
+    type F = <Tweedledee as Curve>::ScalarField;
+    type BF = <Tweedledee as Curve>::BaseField;
+
+    // Each source's message is the square of a secret.
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let message = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_squared = builder.mul(t, t);
+    builder.copy(t_squared, message);
+    let source_circuit = builder.build();
+    let source_vk = source_circuit.to_vk();
+
+    let mut sources = Vec::new();
+    for &root in &[2, 3] {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(t, F::from_canonical_usize(root));
+        let witness = source_circuit.generate_witness(partial_witness)?;
+        sources.push(PcdProof {
+            proof: source_circuit.generate_proof::<Tweedledum>(&witness, &[])?,
+            public_inputs: source_circuit.get_public_inputs(&witness),
+            old_proofs: Vec::new(),
+        });
+    }
+
+    // The merge node's message is the sum of its predecessors' messages.
+    let merge_circuit =
+        pcd_circuit::<Tweedledum, Tweedledee, _>(&source_vk, None, 2, |builder, messages| {
+            vec![builder.add(messages[0].take(0), messages[1].take(0))]
+        });
+    let merged = merge_circuit.prove(PartialWitness::new(), &[&sources[0], &sources[1]])?;
+    assert_eq!(merged.public_inputs[0], BF::from_canonical_usize(13));
+
+    let merge_vk = merge_circuit.circuit.to_vk();
+    verify_pcd(&merged, &merge_vk, merge_circuit.layout, &source_vk)?;
+
+    let mut tampered = merged;
+    tampered.public_inputs[0] = BF::from_canonical_usize(14);
+    assert!(verify_pcd(&tampered, &merge_vk, merge_circuit.layout, &source_vk).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_dummy_base_case() -> Result<()> {
+    type BF = <Tweedledee as Curve>::BaseField;
+
+    // The first step of the chain would verify proofs of this circuit, but has none to verify.
+    let mut builder = CircuitBuilder::<Tweedledee>::new(128);
+    let pi = builder.add_public_input();
+    let t = builder.add_virtual_target();
+    let t_cubed = builder.mul_many(&[t, t, t]);
+    builder.copy(t_cubed, pi);
+    let inner_vk = builder.build().to_vk();
+
+    let circuit = recursive_verification_circuit::<Tweedledum, Tweedledee>(&inner_vk, 0, None);
+    let mut inputs = PartialWitness::new();
+    circuit.populate_dummy_witness(&mut inputs, &inner_vk)?;
+    let witness = circuit.circuit.generate_witness(inputs)?;
+    let pis = circuit.circuit.get_public_inputs(&witness);
+    assert_eq!(pis[0], BF::ONE);
+    let proof = circuit.circuit.generate_proof::<Tweedledee>(&witness, &[])?;
+    let vk = circuit.circuit.to_vk();
+    verify_proof::<Tweedledum, Tweedledee>(&pis, &proof, &[], &vk, true)?;
+    finalize_recursion::<Tweedledee, Tweedledum>(&pis, circuit.layout, &inner_vk)?;
+
+    // The next circuit in the cycle would verify this proof as usual, but carry an accumulator
+    // which is valid by construction in place of the dummy proof's.
+    let challs = proof.get_challenges(&vk, &pis, &[], Challenger::new(128))?;
+    let next_pis = recursion_public_inputs::<Tweedledum, Tweedledee>(
+        &proof,
+        &pis,
+        Some(circuit.layout),
+        &[],
+        &challs,
+        128,
+        None,
+    )?;
+    let next_layout = RecursionLayout {
+        degree_pow: log2_strict(vk.degree),
+        num_old_proofs: 0,
+        old_proof_degree_pow: circuit.layout.carried_degree_pow,
+        num_carried_accumulators: 1,
+        carried_degree_pow: circuit.layout.degree_pow,
+        vk_is_input: false,
+    };
+    let carried = carried_accumulators::<Tweedledum, Tweedledee>(&next_pis, next_layout)?;
+    verify_accumulators(&carried, &inner_vk)?;
 
     Ok(())
 }
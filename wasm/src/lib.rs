@@ -0,0 +1,122 @@
+//! WebAssembly bindings for plonky, for proving and verifying in the browser.
+//!
+//! Circuits can't be passed across the JS boundary, so this exposes a fixed demo circuit, which
+//! proves knowledge of a preimage of a public Rescue hash. Field elements are passed as 32-byte
+//! little-endian canonical encodings, and proofs in the format of `Proof::to_bytes`.
+//!
+//! Build with `wasm-pack build wasm --target web`. By default the prover runs on the calling
+//! thread; see the `threads` feature for multithreaded proving.
+
+use plonky::{rescue_hash_n_to_1, verify, Circuit, CircuitBuilder, Curve, Field, PartialWitness, Proof, Target, Tweedledee, Tweedledum, VerificationKey};
+use wasm_bindgen::prelude::*;
+
+type F = <Tweedledee as Curve>::ScalarField;
+
+const SECURITY_BITS: usize = 128;
+
+#[wasm_bindgen]
+pub struct PreimageCircuit {
+    circuit: Circuit<Tweedledee>,
+    vk: VerificationKey<Tweedledee>,
+    preimage: Target<F>,
+    hash: Target<F>,
+}
+
+#[wasm_bindgen]
+impl PreimageCircuit {
+    /// Builds the circuit and its keys. This is slow, so callers should reuse the instance.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PreimageCircuit {
+        let mut builder = CircuitBuilder::<Tweedledee>::new(SECURITY_BITS);
+        let hash = builder.add_public_input();
+        let preimage = builder.add_virtual_target();
+        let computed_hash = builder.rescue_hash_n_to_1(&[preimage]);
+        let difference = builder.sub(computed_hash, hash);
+        builder.assert_zero(difference);
+        let circuit = builder.build();
+        let vk = circuit.to_vk();
+        PreimageCircuit {
+            circuit,
+            vk,
+            preimage,
+            hash,
+        }
+    }
+
+    /// Computes the Rescue hash of `preimage`, i.e. the public input of a proof for `preimage`.
+    pub fn hash(&self, preimage: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let preimage = decode_field(preimage)?;
+        Ok(native_hash(preimage).to_canonical_u8_vec())
+    }
+
+    /// Proves knowledge of `preimage`, returning the encoded proof.
+    pub fn prove(&self, preimage: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let preimage = decode_field(preimage)?;
+        self.prove_native(preimage).map_err(to_js_error)
+    }
+
+    /// Verifies a proof that the prover knows a preimage of `hash`.
+    pub fn verify(&self, hash: &[u8], proof: &[u8]) -> bool {
+        match (decode_field(hash), Proof::from_bytes(proof)) {
+            (Ok(hash), Ok(proof)) => verify::<Tweedledee, Tweedledum>(&self.vk, &[hash], &proof).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+impl PreimageCircuit {
+    fn prove_native(&self, preimage: F) -> anyhow::Result<Vec<u8>> {
+        let mut partial_witness = PartialWitness::new();
+        partial_witness.set_target(self.preimage, preimage);
+        partial_witness.set_target(self.hash, native_hash(preimage));
+        let witness = self.circuit.generate_witness(partial_witness)?;
+        let proof = self.circuit.generate_proof::<Tweedledum>(&witness, &[])?;
+        Ok(proof.to_bytes())
+    }
+}
+
+impl Default for PreimageCircuit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn native_hash(preimage: F) -> F {
+    rescue_hash_n_to_1(vec![preimage], SECURITY_BITS)
+}
+
+fn decode_field(bytes: &[u8]) -> Result<F, JsValue> {
+    if bytes.len() != F::BYTES {
+        return Err(JsValue::from_str(&format!(
+            "Expected a {}-byte field element, got {} bytes",
+            F::BYTES,
+            bytes.len()
+        )));
+    }
+    F::from_canonical_u8_vec(bytes.to_vec()).map_err(to_js_error)
+}
+
+fn to_js_error<E: std::fmt::Display>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky::Field;
+
+    use crate::{native_hash, PreimageCircuit, F};
+
+    #[test]
+    fn test_prove_and_verify() {
+        let circuit = PreimageCircuit::new();
+        let preimage = F::from_canonical_usize(42);
+        let hash = native_hash(preimage).to_canonical_u8_vec();
+
+        let proof = circuit.prove_native(preimage).unwrap();
+        assert!(circuit.verify(&hash, &proof));
+
+        let wrong_hash = native_hash(F::from_canonical_usize(43)).to_canonical_u8_vec();
+        assert!(!circuit.verify(&wrong_hash, &proof));
+        assert!(!circuit.verify(&hash, &proof[1..]));
+    }
+}